@@ -28,6 +28,7 @@ def serialize_instruction(instruction: dict[str, Any]) -> dict[str, Any]:
 
     # Build binary instruction data matching Veil program format:
     # discriminator (8 bytes) + commitment (32 bytes) + amount (u64, 8 bytes)
+    # + encrypted note (Option<Vec<u8>>, 1 byte when absent)
     commitment_hex = data.get("commitment", "")
     amount = data.get("amount", 0)
 
@@ -36,8 +37,9 @@ def serialize_instruction(instruction: dict[str, Any]) -> dict[str, Any]:
     if len(commitment_bytes) != 32:
         raise ValueError(f"Commitment must be 32 bytes, got {len(commitment_bytes)}")
 
-    # Pack: discriminator + commitment + amount (little-endian u64)
-    binary_data = SHIELD_SOL_DISC + commitment_bytes + struct.pack("<Q", amount)
+    # Pack: discriminator + commitment + amount (little-endian u64), then
+    # a Borsh `None` for the encrypted note
+    binary_data = SHIELD_SOL_DISC + commitment_bytes + struct.pack("<Q", amount) + b"\x00"
     data_base64 = base64.b64encode(binary_data).decode()
 
     return {
@@ -0,0 +1,113 @@
+// Generate the program's golden test vectors from the withdraw circuit hashing
+//
+// Usage: node gen_test_vectors.js [output]
+//   (default: ../veil/crates/program/src/test_vectors.rs)
+//
+// The tests at the bottom of the existing file are kept; only the generated
+// vectors above `#[cfg(test)]` are replaced.
+
+const { buildPoseidon } = require("circomlibjs");
+const fs = require("fs");
+
+const TREE_DEPTH = 10;
+
+// (secret, amount) pairs inserted in leaf order
+const NOTES = [
+  [0x1234567890abcdefn, 1000000000n],
+  [0xfedcba0987654321n, 100000000n],
+  [0x0badc0ffeen, 10000000n],
+];
+
+function rustBytes(value, indent) {
+  const hex = value.toString(16).padStart(64, "0");
+  const lines = [];
+  for (let i = 0; i < 64; i += 16) {
+    const row = [];
+    for (let j = i; j < i + 16; j += 2) row.push("0x" + hex.slice(j, j + 2));
+    lines.push(indent + "    " + row.join(", ") + ",");
+  }
+  return "[\n" + lines.join("\n") + "\n" + indent + "]";
+}
+
+function rustU64(value) {
+  return value.toString().replace(/\B(?=(\d{3})+(?!\d))/g, "_");
+}
+
+async function main() {
+  const output = process.argv[2] || "../veil/crates/program/src/test_vectors.rs";
+  const poseidon = await buildPoseidon();
+  const F = poseidon.F;
+  const hash = (a, b) => F.toObject(poseidon([a, b]));
+
+  const zeros = [0n];
+  for (let i = 0; i < TREE_DEPTH; i++) zeros.push(hash(zeros[i], zeros[i]));
+
+  const notes = NOTES.map(([secret, amount]) => {
+    const commitment = hash(amount, secret);
+    return { secret, amount, commitment, nullifierHash: hash(commitment, secret) };
+  });
+
+  const root = (leaves) => {
+    let level = leaves.slice();
+    for (let d = 0; d < TREE_DEPTH; d++) {
+      if (level.length % 2) level.push(zeros[d]);
+      const next = [];
+      for (let i = 0; i < level.length; i += 2) next.push(hash(level[i], level[i + 1]));
+      level = next;
+    }
+    return level[0];
+  };
+  const roots = notes.map((_, i) => root(notes.slice(0, i + 1).map((n) => n.commitment)));
+
+  const pairs = [[1n, 2n], [0n, 0n], [notes[0].commitment, notes[1].commitment]];
+
+  const existing = fs.readFileSync(output, "utf8");
+  const header = existing.slice(0, existing.indexOf("/// Poseidon(left, right) for selected inputs"));
+  const tests = existing.slice(existing.indexOf("#[cfg(test)]"));
+
+  let out = header;
+  out += "/// Poseidon(left, right) for selected inputs: (left, right, hash)\n";
+  out += "pub const HASH_PAIRS: [([u8; 32], [u8; 32], [u8; 32]); " + pairs.length + "] = [\n";
+  for (const [a, b] of pairs) {
+    const pad = "        ";
+    out += "    (\n";
+    out += pad + rustBytes(a, pad) + ",\n";
+    out += pad + rustBytes(b, pad) + ",\n";
+    out += pad + rustBytes(hash(a, b), pad) + ",\n";
+    out += "    ),\n";
+  }
+  out += "];\n\n";
+
+  out += "/// Notes inserted into the tree, in leaf order\n";
+  out += "pub const NOTES: [NoteVector; " + notes.length + "] = [\n";
+  for (const note of notes) {
+    const pad = "        ";
+    out += "    NoteVector {\n";
+    out += pad + "secret: " + rustBytes(note.secret, pad) + ",\n";
+    out += pad + "amount: " + rustU64(note.amount) + ",\n";
+    out += pad + "commitment: " + rustBytes(note.commitment, pad) + ",\n";
+    out += pad + "nullifier_hash: " + rustBytes(note.nullifierHash, pad) + ",\n";
+    out += "    },\n";
+  }
+  out += "];\n\n";
+
+  out += "/// Empty subtree roots: zeros[0] = 0, zeros[i] = Poseidon(zeros[i-1], zeros[i-1])\n";
+  out += "pub const ZERO_HASHES: [[u8; 32]; TREE_DEPTH + 1] = [\n";
+  zeros.forEach((zero, i) => {
+    out += "    // Level " + i + "\n";
+    out += "    " + rustBytes(zero, "    ") + ",\n";
+  });
+  out += "];\n\n";
+
+  out += "/// Tree root after inserting `NOTES[..=i]`\n";
+  out += "pub const ROOTS: [[u8; 32]; " + roots.length + "] = [\n";
+  for (const r of roots) out += "    " + rustBytes(r, "    ") + ",\n";
+  out += "];\n\n";
+
+  fs.writeFileSync(output, out + tests);
+}
+
+main().catch((err) => {
+  console.error(err);
+  process.exit(1);
+});
@@ -16,6 +16,10 @@ include "merkle.circom";
  *   - nullifierHash: Hash to prevent double-spending
  *   - recipient: Address receiving the funds
  *   - amount: Amount being withdrawn
+ *   - relayer: Relayer allowed to submit the withdrawal
+ *   - fee: Fee paid to the relayer
+ *   - refund: Lamports the relayer forwards to the recipient
+ *   - expirySlot: Last Solana slot at which the proof may be submitted
  *
  * Private Inputs:
  *   - secret: User's secret (32 bytes as field element)
@@ -29,6 +33,10 @@ template Withdraw(levels) {
     signal input nullifierHash;
     signal input recipient;
     signal input amount;
+    signal input relayer;
+    signal input fee;
+    signal input refund;
+    signal input expirySlot;
 
     // Private inputs
     signal input secret;
@@ -62,7 +70,20 @@ template Withdraw(levels) {
 
     // Note: recipient and amount are public inputs, so they're already bound
     // to the proof via the verification equation. No additional constraint needed.
+
+    // Add hidden quadratic constraints so relayer, fee, refund and expiry cannot be
+    // optimised out of the circuit and tampered with after proving
+    signal recipientSquare;
+    signal relayerSquare;
+    signal feeSquare;
+    signal refundSquare;
+    signal expirySlotSquare;
+    recipientSquare <== recipient * recipient;
+    relayerSquare <== relayer * relayer;
+    feeSquare <== fee * fee;
+    refundSquare <== refund * refund;
+    expirySlotSquare <== expirySlot * expirySlot;
 }
 
 // Main component with 10 levels (matches TREE_DEPTH in Rust)
-component main {public [root, nullifierHash, recipient, amount]} = Withdraw(10);
+component main {public [root, nullifierHash, recipient, amount, relayer, fee, refund, expirySlot]} = Withdraw(10);
@@ -0,0 +1,150 @@
+//! Emit verifying keys for the program's VK accounts
+//!
+//! Runs a circuit-specific Groth16 setup for each on-chain circuit and writes
+//! `<name>_vk.json` (the `propose_verifying_key` arguments) and `<name>.pk`
+//! (the compressed proving key) to the output directory.
+//!
+//! Usage: export-vks [OUT_DIR]   (default: target/vks)
+//!
+//! WARNING: Keys come from random toxic waste and are only fit for localnet
+//! and devnet. Mainnet keys must come from a trusted setup ceremony.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use ark_bn254::Fr;
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_serialize::CanonicalSerialize;
+
+use veil_circuits::constants::{
+    ESCROW_UNSHIELD_CIRCUIT_ID, ESCROW_UNSHIELD_PUBLIC_INPUTS, JOIN_SPLIT_CIRCUIT_ID, JOIN_SPLIT_PUBLIC_INPUTS, MULTI_ASSET_UNSHIELD_CIRCUIT_ID,
+    MULTI_ASSET_UNSHIELD_PUBLIC_INPUTS, SHIELD_CIRCUIT_ID, SHIELD_PUBLIC_INPUTS,
+    TIMELOCKED_UNSHIELD_CIRCUIT_ID, TIMELOCKED_UNSHIELD_PUBLIC_INPUTS, TRANSFER_CIRCUIT_ID, TRANSFER_PUBLIC_INPUTS, TREE_DEPTH, UNSHIELD_CIRCUIT_ID,
+    UNSHIELD_MANY_CIRCUIT_ID, UNSHIELD_MANY_PUBLIC_INPUTS, UNSHIELD_PUBLIC_INPUTS,
+    UNSHIELD_WITH_CHANGE_CIRCUIT_ID, UNSHIELD_WITH_CHANGE_PUBLIC_INPUTS,
+};
+use veil_circuits::{
+    setup, EscrowUnshieldCircuit, JoinSplitCircuit, MultiAssetUnshieldCircuit, ProgramVerifyingKey, ShieldCircuit,
+    TimelockedUnshieldCircuit, TransferCircuit, UnshieldCircuit, UnshieldManyCircuit, UnshieldWithChangeCircuit,
+};
+
+fn export<C: ConstraintSynthesizer<Fr>>(
+    out_dir: &Path,
+    name: &str,
+    circuit_id: u8,
+    num_public_inputs: usize,
+    circuit: C,
+) -> Result<(), String> {
+    let (pk, vk) = setup(circuit).map_err(|e| e.to_string())?;
+    let exported = ProgramVerifyingKey::from_arkworks(circuit_id, num_public_inputs, &vk)
+        .map_err(|e| e.to_string())?;
+
+    let vk_path = out_dir.join(format!("{name}_vk.json"));
+    fs::write(&vk_path, exported.to_json().map_err(|e| e.to_string())?)
+        .map_err(|e| format!("{}: {e}", vk_path.display()))?;
+
+    let mut pk_bytes = Vec::new();
+    pk.serialize_compressed(&mut pk_bytes).map_err(|e| e.to_string())?;
+    let pk_path = out_dir.join(format!("{name}.pk"));
+    fs::write(&pk_path, pk_bytes).map_err(|e| format!("{}: {e}", pk_path.display()))?;
+
+    println!("{name}: {} public inputs -> {}", num_public_inputs, vk_path.display());
+    Ok(())
+}
+
+fn run(out_dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(out_dir).map_err(|e| format!("{}: {e}", out_dir.display()))?;
+
+    let zero = Fr::from(0u64);
+    let transfer = TransferCircuit::new(
+        zero,
+        zero,
+        zero,
+        0,
+        zero,
+        zero,
+        zero,
+        zero,
+        zero,
+        0,
+        vec![zero; TREE_DEPTH],
+        vec![false; TREE_DEPTH],
+        zero,
+    );
+    export(out_dir, "transfer", TRANSFER_CIRCUIT_ID, TRANSFER_PUBLIC_INPUTS, transfer)?;
+    export(
+        out_dir,
+        "unshield",
+        UNSHIELD_CIRCUIT_ID,
+        UNSHIELD_PUBLIC_INPUTS,
+        UnshieldCircuit::blank(),
+    )?;
+    export(
+        out_dir,
+        "unshield_with_change",
+        UNSHIELD_WITH_CHANGE_CIRCUIT_ID,
+        UNSHIELD_WITH_CHANGE_PUBLIC_INPUTS,
+        UnshieldWithChangeCircuit::blank(),
+    )?;
+    export(
+        out_dir,
+        "unshield_many",
+        UNSHIELD_MANY_CIRCUIT_ID,
+        UNSHIELD_MANY_PUBLIC_INPUTS,
+        UnshieldManyCircuit::blank(),
+    )?;
+    export(
+        out_dir,
+        "timelocked_unshield",
+        TIMELOCKED_UNSHIELD_CIRCUIT_ID,
+        TIMELOCKED_UNSHIELD_PUBLIC_INPUTS,
+        TimelockedUnshieldCircuit::blank(),
+    )?;
+    export(
+        out_dir,
+        "escrow_unshield",
+        ESCROW_UNSHIELD_CIRCUIT_ID,
+        ESCROW_UNSHIELD_PUBLIC_INPUTS,
+        EscrowUnshieldCircuit::blank(),
+    )?;
+    export(
+        out_dir,
+        "join_split",
+        JOIN_SPLIT_CIRCUIT_ID,
+        JOIN_SPLIT_PUBLIC_INPUTS,
+        JoinSplitCircuit::blank(),
+    )?;
+
+    // Multi-asset pools also register these
+    export(
+        out_dir,
+        "shield",
+        SHIELD_CIRCUIT_ID,
+        SHIELD_PUBLIC_INPUTS,
+        ShieldCircuit::new(zero, 0, zero, zero, zero),
+    )?;
+    export(
+        out_dir,
+        "multi_asset_unshield",
+        MULTI_ASSET_UNSHIELD_CIRCUIT_ID,
+        MULTI_ASSET_UNSHIELD_PUBLIC_INPUTS,
+        MultiAssetUnshieldCircuit::blank(),
+    )?;
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let out_dir = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("target/vks"));
+
+    match run(&out_dir) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("export-vks: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
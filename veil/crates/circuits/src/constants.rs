@@ -0,0 +1,74 @@
+//! Shared circuit constants
+//!
+//! The circuit IDs and public input counts mirror `CircuitId` in the program's
+//! `verification` module; a verifying key is only accepted on-chain if its IC
+//! length matches.
+
+pub use veil_core::crypto::merkle::TREE_DEPTH;
+
+/// Domain separator for deriving a spending key from the user's secret
+pub const SPENDING_KEY_DOMAIN: &[u8] = b"NYX_SPENDING_KEY";
+
+
+/// Asset ID of native SOL notes
+pub const NATIVE_ASSET_ID: u64 = 0;
+
+/// On-chain circuit ID of the transfer circuit
+pub const TRANSFER_CIRCUIT_ID: u8 = 0;
+
+/// On-chain circuit ID of the unshield (withdraw) circuit
+pub const UNSHIELD_CIRCUIT_ID: u8 = 1;
+
+/// On-chain circuit ID of the 2-in/2-out join-split circuit
+pub const JOIN_SPLIT_CIRCUIT_ID: u8 = 2;
+
+/// On-chain circuit ID of the shield circuit (multi-asset pools)
+pub const SHIELD_CIRCUIT_ID: u8 = 4;
+
+/// On-chain circuit ID of the multi-asset unshield circuit
+pub const MULTI_ASSET_UNSHIELD_CIRCUIT_ID: u8 = 5;
+
+/// On-chain circuit ID of the partial withdrawal circuit
+pub const UNSHIELD_WITH_CHANGE_CIRCUIT_ID: u8 = 6;
+
+/// On-chain circuit ID of the multi-recipient unshield circuit
+pub const UNSHIELD_MANY_CIRCUIT_ID: u8 = 7;
+
+/// On-chain circuit ID of the timelocked note unshield circuit
+pub const TIMELOCKED_UNSHIELD_CIRCUIT_ID: u8 = 8;
+
+/// On-chain circuit ID of the escrow note unshield circuit
+pub const ESCROW_UNSHIELD_CIRCUIT_ID: u8 = 9;
+
+/// Payout slots of the multi-recipient unshield circuit
+pub const MAX_UNSHIELD_RECIPIENTS: usize = 4;
+
+/// Transfer public inputs: root, nullifier, new_commitment, expiry_slot, nullifier_domain
+pub const TRANSFER_PUBLIC_INPUTS: usize = 5;
+
+/// Unshield public inputs: root, nullifier, recipient, amount, relayer, fee, refund, expiry_slot,
+/// nullifier_domain
+pub const UNSHIELD_PUBLIC_INPUTS: usize = 9;
+
+/// Multi-asset unshield public inputs: the unshield inputs followed by asset_id
+pub const MULTI_ASSET_UNSHIELD_PUBLIC_INPUTS: usize = UNSHIELD_PUBLIC_INPUTS + 1;
+
+/// Partial withdrawal public inputs: the unshield inputs followed by change_commitment
+pub const UNSHIELD_WITH_CHANGE_PUBLIC_INPUTS: usize = UNSHIELD_PUBLIC_INPUTS + 1;
+
+/// Timelocked unshield public inputs: the unshield inputs followed by not_before_slot
+pub const TIMELOCKED_UNSHIELD_PUBLIC_INPUTS: usize = UNSHIELD_PUBLIC_INPUTS + 1;
+
+/// Escrow unshield public inputs: the unshield inputs followed by arbiter and escrow_id
+pub const ESCROW_UNSHIELD_PUBLIC_INPUTS: usize = UNSHIELD_PUBLIC_INPUTS + 2;
+
+/// Multi-recipient unshield public inputs: root, nullifier, recipients, amounts, relayer, fee,
+/// expiry_slot, nullifier_domain
+pub const UNSHIELD_MANY_PUBLIC_INPUTS: usize = 6 + 2 * MAX_UNSHIELD_RECIPIENTS;
+
+/// Join-split public inputs: root, nullifier_a, nullifier_b, commitment_a, commitment_b,
+/// expiry_slot, nullifier_domain
+pub const JOIN_SPLIT_PUBLIC_INPUTS: usize = 7;
+
+/// Shield public inputs: commitment, amount, asset_id
+pub const SHIELD_PUBLIC_INPUTS: usize = 3;
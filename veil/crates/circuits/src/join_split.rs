@@ -0,0 +1,431 @@
+//! Join-Split Circuit
+//!
+//! Spends two notes and creates two, conserving value per asset, so one
+//! transaction in a multi-asset pool can move several mints at once.
+//!
+//! 1. Each input note with a non-zero amount is in the Merkle tree, and
+//!    every input's nullifier is derived from the owner's spending key,
+//!    leaf index and the pool's nullifier domain
+//! 2. Each output note holds one of the inputs' assets
+//! 3. For each input asset, the outputs of that asset add up to the inputs
+//!    of that asset, so value can't move between assets
+//! 4. Every amount fits in 64 bits, so sums can't wrap around the field
+//!
+//! A zero-amount input needn't be in the tree, so a single note can be
+//! split into payment and change by pairing it with a dummy input. When
+//! both inputs hold the same asset the two conservation checks coincide
+//! and the circuit is an ordinary 2-in/2-out transfer.
+//!
+//! Public Inputs (in the program's `CircuitId::JoinSplit` order):
+//! - merkle_root: The Merkle root both inputs are proven against
+//! - nullifier_a, nullifier_b: Nullifiers of the spent notes
+//! - commitment_a, commitment_b: Commitments of the created notes
+//! - expiry_slot: Last Solana slot at which the proof may be submitted
+//! - nullifier_domain: Tag binding the nullifiers to the pool
+//!
+//! Private Inputs (Witness):
+//! - For each input: secret, amount, blinding, asset_id, leaf_index and
+//!   Merkle path
+//! - For each output: the recipient's spending key, amount, blinding and
+//!   asset_id
+
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use veil_core::proof::gadgets::merkle::MerklePathGadget;
+use veil_core::proof::gadgets::poseidon::poseidon_hash2_gadget;
+
+use crate::constants::{JOIN_SPLIT_PUBLIC_INPUTS, SPENDING_KEY_DOMAIN, TREE_DEPTH};
+
+/// A note spent by the join-split
+#[derive(Clone, Debug)]
+pub struct JoinSplitInput {
+    /// Owner's secret
+    pub secret: Fr,
+    /// Note amount
+    pub amount: u64,
+    /// Blinding factor of the note
+    pub blinding: Fr,
+    /// Asset ID of the note
+    pub asset_id: Fr,
+    /// Leaf index in the Merkle tree
+    pub leaf_index: u64,
+    /// Merkle path siblings
+    pub merkle_path: Vec<Fr>,
+    /// Merkle path indices (left/right)
+    pub merkle_indices: Vec<bool>,
+}
+
+impl JoinSplitInput {
+    /// Input with placeholder values, for key generation
+    pub fn blank() -> Self {
+        let zero = Fr::from(0u64);
+        Self {
+            secret: zero,
+            amount: 0,
+            blinding: zero,
+            asset_id: zero,
+            leaf_index: 0,
+            merkle_path: vec![zero; TREE_DEPTH],
+            merkle_indices: vec![false; TREE_DEPTH],
+        }
+    }
+}
+
+/// A note created by the join-split
+#[derive(Clone, Debug)]
+pub struct JoinSplitOutput {
+    /// Recipient's spending key
+    pub spending_key: Fr,
+    /// Note amount
+    pub amount: u64,
+    /// Blinding factor of the note
+    pub blinding: Fr,
+    /// Asset ID of the note
+    pub asset_id: Fr,
+}
+
+impl JoinSplitOutput {
+    /// Output with placeholder values, for key generation
+    pub fn blank() -> Self {
+        let zero = Fr::from(0u64);
+        Self {
+            spending_key: zero,
+            amount: 0,
+            blinding: zero,
+            asset_id: zero,
+        }
+    }
+}
+
+/// 2-in/2-out join-split circuit with per-asset value conservation
+#[derive(Clone, Default)]
+pub struct JoinSplitCircuit {
+    // ===== Public Inputs =====
+    /// Merkle root both inputs are proven against
+    pub merkle_root: Option<Fr>,
+    /// Nullifiers of the spent notes
+    pub nullifiers: Option<[Fr; 2]>,
+    /// Commitments of the created notes
+    pub commitments: Option<[Fr; 2]>,
+    /// Last slot at which the proof may be submitted
+    pub expiry_slot: Option<u64>,
+    /// Tag binding the nullifiers to the pool
+    pub nullifier_domain: Option<Fr>,
+
+    // ===== Private Inputs (Witness) =====
+    /// Spent notes
+    pub inputs: Option<[JoinSplitInput; 2]>,
+    /// Created notes
+    pub outputs: Option<[JoinSplitOutput; 2]>,
+}
+
+impl JoinSplitCircuit {
+    /// Create a new join-split circuit with all values
+    pub fn new(
+        merkle_root: Fr,
+        nullifiers: [Fr; 2],
+        commitments: [Fr; 2],
+        expiry_slot: u64,
+        nullifier_domain: Fr,
+        inputs: [JoinSplitInput; 2],
+        outputs: [JoinSplitOutput; 2],
+    ) -> Self {
+        Self {
+            merkle_root: Some(merkle_root),
+            nullifiers: Some(nullifiers),
+            commitments: Some(commitments),
+            expiry_slot: Some(expiry_slot),
+            nullifier_domain: Some(nullifier_domain),
+            inputs: Some(inputs),
+            outputs: Some(outputs),
+        }
+    }
+
+    /// Circuit with placeholder values, for key generation
+    pub fn blank() -> Self {
+        let zero = Fr::from(0u64);
+        Self::new(
+            zero,
+            [zero; 2],
+            [zero; 2],
+            0,
+            zero,
+            [JoinSplitInput::blank(), JoinSplitInput::blank()],
+            [JoinSplitOutput::blank(), JoinSplitOutput::blank()],
+        )
+    }
+
+    /// Number of public inputs
+    pub const NUM_PUBLIC_INPUTS: usize = JOIN_SPLIT_PUBLIC_INPUTS;
+}
+
+/// Allocate a note amount and constrain it to 64 bits
+fn alloc_amount(cs: ConstraintSystemRef<Fr>, amount: u64) -> Result<FpVar<Fr>, SynthesisError> {
+    let amount_var = FpVar::new_witness(cs, || Ok(Fr::from(amount)))?;
+    for bit in &amount_var.to_bits_le()?[64..] {
+        bit.enforce_equal(&Boolean::FALSE)?;
+    }
+    Ok(amount_var)
+}
+
+/// commitment = Poseidon(Poseidon(spending_key, amount), Poseidon(blinding, asset_id))
+fn commitment_gadget(
+    cs: ConstraintSystemRef<Fr>,
+    spending_key: &FpVar<Fr>,
+    amount: &FpVar<Fr>,
+    blinding: &FpVar<Fr>,
+    asset_id: &FpVar<Fr>,
+) -> Result<FpVar<Fr>, SynthesisError> {
+    let h1 = poseidon_hash2_gadget(cs.clone(), spending_key, amount)?;
+    let h2 = poseidon_hash2_gadget(cs.clone(), blinding, asset_id)?;
+    poseidon_hash2_gadget(cs, &h1, &h2)
+}
+
+/// Total amount of the notes holding `asset`
+fn asset_total(
+    notes: &[(FpVar<Fr>, FpVar<Fr>)],
+    asset: &FpVar<Fr>,
+) -> Result<FpVar<Fr>, SynthesisError> {
+    let mut total = FpVar::zero();
+    for (amount, asset_id) in notes {
+        total += asset_id.is_eq(asset)?.select(amount, &FpVar::zero())?;
+    }
+    Ok(total)
+}
+
+impl ConstraintSynthesizer<Fr> for JoinSplitCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        // ===== Allocate Public Inputs =====
+        let merkle_root_var = FpVar::new_input(cs.clone(), || {
+            self.merkle_root.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let nullifiers = self.nullifiers.ok_or(SynthesisError::AssignmentMissing)?;
+        let nullifier_vars = [
+            FpVar::new_input(cs.clone(), || Ok(nullifiers[0]))?,
+            FpVar::new_input(cs.clone(), || Ok(nullifiers[1]))?,
+        ];
+
+        let commitments = self.commitments.ok_or(SynthesisError::AssignmentMissing)?;
+        let commitment_vars = [
+            FpVar::new_input(cs.clone(), || Ok(commitments[0]))?,
+            FpVar::new_input(cs.clone(), || Ok(commitments[1]))?,
+        ];
+
+        let expiry_slot_var = FpVar::new_input(cs.clone(), || {
+            self.expiry_slot.map(Fr::from).ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let nullifier_domain_var = FpVar::new_input(cs.clone(), || {
+            self.nullifier_domain.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let inputs = self.inputs.ok_or(SynthesisError::AssignmentMissing)?;
+        let outputs = self.outputs.ok_or(SynthesisError::AssignmentMissing)?;
+
+        let domain_separator = FpVar::new_constant(
+            cs.clone(),
+            Fr::from_le_bytes_mod_order(SPENDING_KEY_DOMAIN),
+        )?;
+
+        // ===== Constraint 1: Spend each input note =====
+        let mut input_notes = Vec::with_capacity(2);
+        for (input, nullifier_var) in inputs.iter().zip(&nullifier_vars) {
+            let secret_var = FpVar::new_witness(cs.clone(), || Ok(input.secret))?;
+            let amount_var = alloc_amount(cs.clone(), input.amount)?;
+            let blinding_var = FpVar::new_witness(cs.clone(), || Ok(input.blinding))?;
+            let asset_id_var = FpVar::new_witness(cs.clone(), || Ok(input.asset_id))?;
+            let leaf_index_var = FpVar::new_witness(cs.clone(), || Ok(Fr::from(input.leaf_index)))?;
+
+            let spending_key_var =
+                poseidon_hash2_gadget(cs.clone(), &secret_var, &domain_separator)?;
+            let commitment_var = commitment_gadget(
+                cs.clone(),
+                &spending_key_var,
+                &amount_var,
+                &blinding_var,
+                &asset_id_var,
+            )?;
+
+            let path_gadget = MerklePathGadget::new_witness(
+                cs.clone(),
+                &input.merkle_path,
+                &input.merkle_indices,
+            )?;
+            // amount * (computed_root - merkle_root) == 0, so only dummy
+            // inputs of amount zero may be missing from the tree
+            let computed_root = path_gadget.compute_root(cs.clone(), &commitment_var)?;
+            (&amount_var * (computed_root - &merkle_root_var)).enforce_equal(&FpVar::zero())?;
+
+            let index_with_domain =
+                poseidon_hash2_gadget(cs.clone(), &leaf_index_var, &nullifier_domain_var)?;
+            let computed_nullifier =
+                poseidon_hash2_gadget(cs.clone(), &spending_key_var, &index_with_domain)?;
+            computed_nullifier.enforce_equal(nullifier_var)?;
+
+            input_notes.push((amount_var, asset_id_var));
+        }
+
+        // ===== Constraint 2: Create each output note =====
+        let mut output_notes = Vec::with_capacity(2);
+        for (output, commitment_var) in outputs.iter().zip(&commitment_vars) {
+            let spending_key_var = FpVar::new_witness(cs.clone(), || Ok(output.spending_key))?;
+            let amount_var = alloc_amount(cs.clone(), output.amount)?;
+            let blinding_var = FpVar::new_witness(cs.clone(), || Ok(output.blinding))?;
+            let asset_id_var = FpVar::new_witness(cs.clone(), || Ok(output.asset_id))?;
+
+            let computed_commitment = commitment_gadget(
+                cs.clone(),
+                &spending_key_var,
+                &amount_var,
+                &blinding_var,
+                &asset_id_var,
+            )?;
+            computed_commitment.enforce_equal(commitment_var)?;
+
+            // The output's asset must be one of the inputs' assets
+            let from_a = &asset_id_var - &input_notes[0].1;
+            let from_b = &asset_id_var - &input_notes[1].1;
+            (from_a * from_b).enforce_equal(&FpVar::zero())?;
+
+            output_notes.push((amount_var, asset_id_var));
+        }
+
+        // ===== Constraint 3: Conserve value per asset =====
+        for (_, asset) in &input_notes {
+            asset_total(&input_notes, asset)?.enforce_equal(&asset_total(&output_notes, asset)?)?;
+        }
+
+        // ===== Constraint 4: Bind the expiry =====
+        // Only checked on-chain; squared so it can't be altered after proving
+        let _square = expiry_slot_var.square()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::UniformRand;
+    use ark_relations::r1cs::ConstraintSystem;
+    use rand::rngs::OsRng;
+    use veil_core::crypto::merkle::PoseidonMerkleTree;
+
+    use crate::note::{note_commitment, nullifier, nullifier_domain, spending_key};
+
+    /// Join-split spending notes of `(amount, asset)` into `outputs`
+    fn build_circuit(inputs: [(u64, u64); 2], outputs: [(u64, u64); 2]) -> JoinSplitCircuit {
+        build_circuit_with_dummy(inputs, outputs, false)
+    }
+
+    /// As `build_circuit`, leaving the second input out of the tree if
+    /// `dummy` is set
+    fn build_circuit_with_dummy(
+        inputs: [(u64, u64); 2],
+        outputs: [(u64, u64); 2],
+        dummy: bool,
+    ) -> JoinSplitCircuit {
+        let domain = nullifier_domain(&[5u8; 32], &[0u8; 32]);
+        let mut tree = PoseidonMerkleTree::new();
+
+        let mut inserted = 0;
+        let notes = inputs.map(|(amount, asset)| {
+            let secret = Fr::rand(&mut OsRng);
+            let blinding = Fr::rand(&mut OsRng);
+            let commitment = note_commitment(
+                &spending_key(&secret),
+                &Fr::from(amount),
+                &blinding,
+                &Fr::from(asset),
+            );
+            inserted += 1;
+            let leaf_index = if dummy && inserted == 2 {
+                0
+            } else {
+                tree.insert(commitment).unwrap()
+            };
+            (secret, amount, blinding, asset, leaf_index)
+        });
+        let spent = notes.map(|(secret, amount, blinding, asset, leaf_index)| {
+            let path = tree.generate_proof(leaf_index).unwrap();
+            JoinSplitInput {
+                secret,
+                amount,
+                blinding,
+                asset_id: Fr::from(asset),
+                leaf_index,
+                merkle_path: path.siblings,
+                merkle_indices: path.indices,
+            }
+        });
+        let nullifiers = spent
+            .clone()
+            .map(|input| nullifier(&spending_key(&input.secret), input.leaf_index, &domain));
+
+        let created = outputs.map(|(amount, asset)| JoinSplitOutput {
+            spending_key: Fr::rand(&mut OsRng),
+            amount,
+            blinding: Fr::rand(&mut OsRng),
+            asset_id: Fr::from(asset),
+        });
+        let commitments = created.clone().map(|output| {
+            note_commitment(
+                &output.spending_key,
+                &Fr::from(output.amount),
+                &output.blinding,
+                &output.asset_id,
+            )
+        });
+
+        JoinSplitCircuit::new(tree.root(), nullifiers, commitments, 1_000, domain, spent, created)
+    }
+
+    fn is_satisfied(circuit: JoinSplitCircuit) -> bool {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_join_split_single_asset() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        build_circuit([(600, 7), (400, 7)], [(250, 7), (750, 7)])
+            .generate_constraints(cs.clone())
+            .unwrap();
+
+        println!("Join-split circuit constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+        assert_eq!(cs.num_instance_variables(), JoinSplitCircuit::NUM_PUBLIC_INPUTS + 1);
+    }
+
+    #[test]
+    fn test_join_split_conserves_each_asset() {
+        // Two assets pass through in one transaction
+        assert!(is_satisfied(build_circuit([(600, 7), (400, 9)], [(400, 9), (600, 7)])));
+
+        // Same totals, but value moved from asset 9 to asset 7
+        assert!(!is_satisfied(build_circuit([(600, 7), (400, 9)], [(700, 7), (300, 9)])));
+    }
+
+    #[test]
+    fn test_join_split_rejects_new_asset() {
+        assert!(!is_satisfied(build_circuit([(600, 7), (400, 7)], [(600, 7), (400, 8)])));
+    }
+
+    #[test]
+    fn test_join_split_rejects_inflation() {
+        assert!(!is_satisfied(build_circuit([(600, 7), (400, 7)], [(600, 7), (401, 7)])));
+    }
+
+    #[test]
+    fn test_join_split_splits_with_dummy_input() {
+        // One note split into payment and change
+        assert!(is_satisfied(build_circuit_with_dummy([(1_000, 7), (0, 7)], [(300, 7), (700, 7)], true)));
+
+        // A dummy input can't carry value
+        assert!(!is_satisfied(build_circuit_with_dummy([(1_000, 7), (5, 7)], [(300, 7), (705, 7)], true)));
+    }
+}
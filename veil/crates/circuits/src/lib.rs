@@ -0,0 +1,43 @@
+//! Veil - Circuits
+//!
+//! Arkworks constraint systems for the Veil privacy pool, kept in one place
+//! so the prover, the SDK and the on-chain program agree on every public
+//! input layout.
+//!
+//! # Modules
+//! - `constants`: Tree depth, domain separators and per-circuit public input counts
+//! - `join_split`: Spends two notes into two, conserving value per asset
+//! - `note`: Native note hashing, asset IDs and nullifier domains matching the in-circuit gadgets
+//! - `shield`: Proves a deposit commitment is well formed
+//! - `unshield`: Proves a withdrawal (9 public inputs, matches the program's withdraw layout),
+//!   with the asset ID as a tenth for multi-asset pools, with a change
+//!   commitment as a tenth for partial withdrawals, or with the unlock slot
+//!   as a tenth for timelocked notes
+//! - `unshield_many`: Proves a withdrawal of one note to several recipients
+//! - `vk`: Exports verifying keys in the layout of the program's VK accounts
+//!
+//! The transfer circuit lives in `veil-core` and is re-exported here.
+//!
+//! Verifying keys are emitted by the `export-vks` binary:
+//!
+//! ```text
+//! cargo run -p veil-circuits --bin export-vks -- target/vks
+//! ```
+
+pub mod constants;
+pub mod join_split;
+pub mod note;
+pub mod shield;
+pub mod unshield;
+pub mod unshield_many;
+pub mod vk;
+
+pub use join_split::{JoinSplitCircuit, JoinSplitInput, JoinSplitOutput};
+pub use shield::ShieldCircuit;
+pub use unshield::{
+    EscrowUnshieldCircuit, MultiAssetUnshieldCircuit, TimelockedUnshieldCircuit, UnshieldCircuit,
+    UnshieldWithChangeCircuit,
+};
+pub use unshield_many::UnshieldManyCircuit;
+pub use veil_core::proof::TransferCircuit;
+pub use vk::{setup, ProgramVerifyingKey, VkExportError};
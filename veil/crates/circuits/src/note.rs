@@ -0,0 +1,65 @@
+//! Native note hashing
+//!
+//! Out-of-circuit versions of the derivations the circuits constrain, for
+//! building witnesses and public inputs. Each function has a matching gadget
+//! sequence in the circuits; changing one without the other breaks proving.
+
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use veil_core::crypto::poseidon::poseidon_hash2;
+
+pub use veil_core::crypto::nullifier::{asset_id, nullifier_domain};
+
+use crate::constants::SPENDING_KEY_DOMAIN;
+
+/// spending_key = Poseidon(secret, SPENDING_KEY_DOMAIN)
+pub fn spending_key(secret: &Fr) -> Fr {
+    poseidon_hash2(secret, &Fr::from_le_bytes_mod_order(SPENDING_KEY_DOMAIN))
+}
+
+/// one_time_key = Poseidon(spending_key, tweak)
+///
+/// The key owning a note sent to a stealth address, whose recipient
+/// published `spending_key` (see `veil_core::crypto::stealth`).
+pub fn stealth_spending_key(spending_key: &Fr, tweak: &Fr) -> Fr {
+    poseidon_hash2(spending_key, tweak)
+}
+
+/// commitment = Poseidon(Poseidon(spending_key, amount), Poseidon(blinding, asset_id))
+pub fn note_commitment(spending_key: &Fr, amount: &Fr, blinding: &Fr, asset_id: &Fr) -> Fr {
+    let h1 = poseidon_hash2(spending_key, amount);
+    let h2 = poseidon_hash2(blinding, asset_id);
+    poseidon_hash2(&h1, &h2)
+}
+
+/// leaf = Poseidon(commitment, not_before_slot)
+///
+/// The leaf shielded for a note that can't be spent before `not_before_slot`.
+pub fn timelocked_commitment(commitment: &Fr, not_before_slot: u64) -> Fr {
+    poseidon_hash2(commitment, &Fr::from(not_before_slot))
+}
+
+/// leaf = Poseidon(Poseidon(commitment, arbiter), escrow_id)
+///
+/// The leaf shielded for a note that can only be spent with `arbiter`'s
+/// resolution of deal `escrow_id`. `arbiter` is the arbiter's pubkey as
+/// `bytes_to_field` encodes it.
+pub fn escrow_commitment(commitment: &Fr, arbiter: &Fr, escrow_id: &Fr) -> Fr {
+    poseidon_hash2(&poseidon_hash2(commitment, arbiter), escrow_id)
+}
+
+/// nullifier = Poseidon(spending_key, Poseidon(leaf_index, nullifier_domain))
+///
+/// `domain` is the pool's `nullifier_domain(pool, mint)`.
+pub fn nullifier(spending_key: &Fr, leaf_index: u64, domain: &Fr) -> Fr {
+    let index_with_domain = poseidon_hash2(&Fr::from(leaf_index), domain);
+    poseidon_hash2(spending_key, &index_with_domain)
+}
+
+/// Encode 32 on-chain bytes (a pubkey) as the field element the program passes
+///
+/// The program hands the raw bytes to the alt_bn128 syscalls as a big-endian
+/// scalar, which reduces them modulo r.
+pub fn bytes_to_field(bytes: &[u8; 32]) -> Fr {
+    Fr::from_be_bytes_mod_order(bytes)
+}
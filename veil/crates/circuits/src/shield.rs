@@ -0,0 +1,137 @@
+//! Shield Circuit
+//!
+//! Proves that a deposit commitment opens to the deposited amount and asset,
+//! so a depositor can't insert a note worth more than they paid in.
+//!
+//! Public Inputs:
+//! - commitment: The note commitment inserted into the tree
+//! - amount: The deposited amount
+//! - asset_id: The deposited asset (0 for native SOL)
+//!
+//! Private Inputs (Witness):
+//! - secret: The secret the spending key is derived from
+//! - blinding: The note's blinding factor
+
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use veil_core::proof::gadgets::poseidon::poseidon_hash2_gadget;
+
+use crate::constants::{SHIELD_PUBLIC_INPUTS, SPENDING_KEY_DOMAIN};
+
+/// Shield circuit for deposits
+#[derive(Clone, Default)]
+pub struct ShieldCircuit {
+    // ===== Public Inputs =====
+    /// Commitment being deposited
+    pub commitment: Option<Fr>,
+    /// Deposited amount
+    pub amount: Option<u64>,
+    /// Deposited asset
+    pub asset_id: Option<Fr>,
+
+    // ===== Private Inputs (Witness) =====
+    /// Depositor's secret
+    pub secret: Option<Fr>,
+    /// Blinding factor of the commitment
+    pub blinding: Option<Fr>,
+}
+
+impl ShieldCircuit {
+    /// Create a new shield circuit with all values
+    pub fn new(commitment: Fr, amount: u64, asset_id: Fr, secret: Fr, blinding: Fr) -> Self {
+        Self {
+            commitment: Some(commitment),
+            amount: Some(amount),
+            asset_id: Some(asset_id),
+            secret: Some(secret),
+            blinding: Some(blinding),
+        }
+    }
+
+    /// Number of public inputs
+    pub const NUM_PUBLIC_INPUTS: usize = SHIELD_PUBLIC_INPUTS;
+}
+
+impl ConstraintSynthesizer<Fr> for ShieldCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        // ===== Allocate Public Inputs =====
+        let commitment_var = FpVar::new_input(cs.clone(), || {
+            self.commitment.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let amount_var = FpVar::new_input(cs.clone(), || {
+            self.amount.map(Fr::from).ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let asset_id_var = FpVar::new_input(cs.clone(), || {
+            self.asset_id.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // ===== Allocate Private Inputs (Witnesses) =====
+        let secret_var = FpVar::new_witness(cs.clone(), || {
+            self.secret.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let blinding_var = FpVar::new_witness(cs.clone(), || {
+            self.blinding.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // ===== Constraint 1: Compute spending key =====
+        let domain_separator = FpVar::new_constant(
+            cs.clone(),
+            Fr::from_le_bytes_mod_order(SPENDING_KEY_DOMAIN),
+        )?;
+        let spending_key_var = poseidon_hash2_gadget(cs.clone(), &secret_var, &domain_separator)?;
+
+        // ===== Constraint 2: Verify commitment opening =====
+        let h1 = poseidon_hash2_gadget(cs.clone(), &spending_key_var, &amount_var)?;
+        let h2 = poseidon_hash2_gadget(cs.clone(), &blinding_var, &asset_id_var)?;
+        let computed_commitment = poseidon_hash2_gadget(cs.clone(), &h1, &h2)?;
+
+        computed_commitment.enforce_equal(&commitment_var)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::UniformRand;
+    use ark_relations::r1cs::ConstraintSystem;
+    use rand::rngs::OsRng;
+
+    use crate::note::{note_commitment, spending_key};
+
+    #[test]
+    fn test_shield_circuit_valid() {
+        let secret = Fr::rand(&mut OsRng);
+        let blinding = Fr::rand(&mut OsRng);
+        let asset_id = Fr::from(0u64);
+        let commitment = note_commitment(&spending_key(&secret), &Fr::from(1000u64), &blinding, &asset_id);
+
+        let circuit = ShieldCircuit::new(commitment, 1000, asset_id, secret, blinding);
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        assert_eq!(cs.num_instance_variables(), ShieldCircuit::NUM_PUBLIC_INPUTS + 1);
+    }
+
+    #[test]
+    fn test_shield_circuit_inflated_amount() {
+        let secret = Fr::rand(&mut OsRng);
+        let blinding = Fr::rand(&mut OsRng);
+        let asset_id = Fr::from(0u64);
+        let commitment = note_commitment(&spending_key(&secret), &Fr::from(1000u64), &blinding, &asset_id);
+
+        // Note commits to 1000 but the deposit claims 10
+        let circuit = ShieldCircuit::new(commitment, 10, asset_id, secret, blinding);
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+}
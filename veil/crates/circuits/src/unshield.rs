@@ -0,0 +1,899 @@
+//! Unshield Circuit for Withdrawals
+//!
+//! This circuit proves that a withdrawal is valid:
+//! 1. The withdrawer knows the preimage of a commitment in the Merkle tree
+//! 2. The nullifier is correctly derived from the spending key, leaf index
+//!    and the pool's nullifier domain
+//! 3. The note holds exactly the withdrawn amount, or for
+//!    `UnshieldWithChangeCircuit` the withdrawn amount plus a change note
+//!    returned to the owner
+//! 4. The recipient, relayer, fee, refund and expiry are bound to the proof
+//! 5. For `TimelockedUnshieldCircuit`, the leaf binds the note's
+//!    `not_before_slot`, which the program checks against the clock
+//! 6. For `EscrowUnshieldCircuit`, the leaf binds the note's arbiter and
+//!    escrow ID, and the program requires the arbiter's signed resolution
+//! 7. A note sent to the owner's stealth address is owned by the one-time
+//!    key `note::stealth_spending_key(spending_key, tweak)` instead of the
+//!    spending key itself; its nullifier derives from that one-time key
+//!
+//! Public Inputs (in the program's `WithdrawPublicInputs` order):
+//! - merkle_root: The current Merkle tree root
+//! - nullifier: The nullifier for the spent note
+//! - recipient: Recipient pubkey bytes as a field element
+//! - amount: The withdrawn amount
+//! - relayer: Relayer pubkey bytes as a field element
+//! - fee: The relayer fee
+//! - refund: Lamports the relayer forwards to the recipient
+//! - expiry_slot: Last Solana slot at which the proof may be submitted
+//! - nullifier_domain: Tag binding the nullifier to the pool and its mint
+//!   (see `note::nullifier_domain`)
+//! - asset_id: The note's asset (`MultiAssetUnshieldCircuit` only)
+//! - change_commitment: Commitment of the change note
+//!   (`UnshieldWithChangeCircuit` only)
+//! - not_before_slot: First slot at which the note may be spent
+//!   (`TimelockedUnshieldCircuit` only)
+//! - arbiter, escrow_id: Arbiter pubkey bytes as a field element and the
+//!   deal the note is escrowed for (`EscrowUnshieldCircuit` only)
+//!
+//! Private Inputs (Witness):
+//! - secret: The secret used to derive the spending key
+//! - blinding: The blinding factor of the note commitment
+//! - asset_id: The note's asset (`UnshieldCircuit` only; single-asset pools
+//!   have one vault, so the asset needn't be revealed)
+//! - leaf_index: The index of the commitment in the Merkle tree
+//! - merkle_path: The sibling hashes in the Merkle path
+//! - change_amount, change_blinding: Opening of the change note
+//!   (`UnshieldWithChangeCircuit` only)
+//! - is_stealth, stealth_tweak: Whether the note was sent to a stealth
+//!   address, and the tweak its one-time key was derived with
+
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+    ToBitsGadget,
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use veil_core::proof::gadgets::merkle::MerklePathGadget;
+use veil_core::proof::gadgets::poseidon::poseidon_hash2_gadget;
+
+use crate::constants::{
+    ESCROW_UNSHIELD_PUBLIC_INPUTS, MULTI_ASSET_UNSHIELD_PUBLIC_INPUTS, SPENDING_KEY_DOMAIN, TIMELOCKED_UNSHIELD_PUBLIC_INPUTS,
+    TREE_DEPTH, UNSHIELD_PUBLIC_INPUTS, UNSHIELD_WITH_CHANGE_PUBLIC_INPUTS,
+};
+
+/// Unshield circuit for withdrawals
+#[derive(Clone, Default)]
+pub struct UnshieldCircuit {
+    // ===== Public Inputs =====
+    /// Current Merkle root
+    pub merkle_root: Option<Fr>,
+    /// Nullifier for the spent note
+    pub nullifier: Option<Fr>,
+    /// Recipient of the withdrawal
+    pub recipient: Option<Fr>,
+    /// Amount withdrawn
+    pub amount: Option<u64>,
+    /// Relayer submitting the withdrawal
+    pub relayer: Option<Fr>,
+    /// Fee paid to the relayer
+    pub fee: Option<u64>,
+    /// Lamports forwarded to the recipient
+    pub refund: Option<u64>,
+    /// Last slot at which the proof may be submitted
+    pub expiry_slot: Option<u64>,
+    /// Tag binding the nullifier to the pool and its mint
+    pub nullifier_domain: Option<Fr>,
+
+    // ===== Private Inputs (Witness) =====
+    /// Owner's secret
+    pub secret: Option<Fr>,
+    /// Blinding factor of the note
+    pub blinding: Option<Fr>,
+    /// Asset ID of the note
+    pub asset_id: Option<Fr>,
+    /// Leaf index in the Merkle tree
+    pub leaf_index: Option<u64>,
+    /// Merkle path siblings
+    pub merkle_path: Option<Vec<Fr>>,
+    /// Merkle path indices (left/right)
+    pub merkle_indices: Option<Vec<bool>>,
+    /// Tweak of a note sent to the owner's stealth address, `None` for a
+    /// note of the owner's own spending key
+    pub stealth_tweak: Option<Fr>,
+}
+
+impl UnshieldCircuit {
+    /// Create a new unshield circuit with all values
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        merkle_root: Fr,
+        nullifier: Fr,
+        recipient: Fr,
+        amount: u64,
+        relayer: Fr,
+        fee: u64,
+        refund: u64,
+        expiry_slot: u64,
+        nullifier_domain: Fr,
+        secret: Fr,
+        blinding: Fr,
+        asset_id: Fr,
+        leaf_index: u64,
+        merkle_path: Vec<Fr>,
+        merkle_indices: Vec<bool>,
+    ) -> Self {
+        Self {
+            merkle_root: Some(merkle_root),
+            nullifier: Some(nullifier),
+            recipient: Some(recipient),
+            amount: Some(amount),
+            relayer: Some(relayer),
+            fee: Some(fee),
+            refund: Some(refund),
+            expiry_slot: Some(expiry_slot),
+            nullifier_domain: Some(nullifier_domain),
+            secret: Some(secret),
+            blinding: Some(blinding),
+            asset_id: Some(asset_id),
+            leaf_index: Some(leaf_index),
+            merkle_path: Some(merkle_path),
+            merkle_indices: Some(merkle_indices),
+            stealth_tweak: None,
+        }
+    }
+
+    /// Spend a note sent to the owner's stealth address under `tweak`
+    pub fn with_stealth_tweak(mut self, tweak: Fr) -> Self {
+        self.stealth_tweak = Some(tweak);
+        self
+    }
+
+    /// Circuit with placeholder values, for key generation
+    ///
+    /// Setup only needs the constraint shape, but the Merkle path gadget
+    /// needs a full-depth path to allocate.
+    pub fn blank() -> Self {
+        let zero = Fr::from(0u64);
+        Self::new(
+            zero,
+            zero,
+            zero,
+            0,
+            zero,
+            0,
+            0,
+            0,
+            zero,
+            zero,
+            zero,
+            zero,
+            0,
+            vec![zero; TREE_DEPTH],
+            vec![false; TREE_DEPTH],
+        )
+    }
+
+    /// Number of public inputs
+    pub const NUM_PUBLIC_INPUTS: usize = UNSHIELD_PUBLIC_INPUTS;
+}
+
+/// Unshield circuit for multi-asset pools
+///
+/// Same constraints as `UnshieldCircuit`, with the note's asset ID exposed
+/// as a tenth public input. A multi-asset pool pays out of the vault of the
+/// mint whose asset ID it passes, so a note can only drain its own asset.
+#[derive(Clone, Default)]
+pub struct MultiAssetUnshieldCircuit(pub UnshieldCircuit);
+
+impl MultiAssetUnshieldCircuit {
+    /// Circuit with placeholder values, for key generation
+    pub fn blank() -> Self {
+        Self(UnshieldCircuit::blank())
+    }
+
+    /// Number of public inputs
+    pub const NUM_PUBLIC_INPUTS: usize = MULTI_ASSET_UNSHIELD_PUBLIC_INPUTS;
+}
+
+/// Unshield circuit for partial withdrawals
+///
+/// Withdraws part of a note and returns the rest to the owner as a change
+/// note, whose commitment is a tenth public input the program inserts into
+/// the tree. The spent note holds `amount + change_amount`.
+#[derive(Clone, Default)]
+pub struct UnshieldWithChangeCircuit {
+    /// The withdrawal; `amount` is the part paid out
+    pub unshield: UnshieldCircuit,
+    /// Commitment of the change note
+    pub change_commitment: Option<Fr>,
+    /// Amount left in the change note
+    pub change_amount: Option<u64>,
+    /// Blinding factor of the change note
+    pub change_blinding: Option<Fr>,
+}
+
+impl UnshieldWithChangeCircuit {
+    /// Create a partial withdrawal of `unshield.amount` with the given change note
+    pub fn new(
+        unshield: UnshieldCircuit,
+        change_commitment: Fr,
+        change_amount: u64,
+        change_blinding: Fr,
+    ) -> Self {
+        Self {
+            unshield,
+            change_commitment: Some(change_commitment),
+            change_amount: Some(change_amount),
+            change_blinding: Some(change_blinding),
+        }
+    }
+
+    /// Circuit with placeholder values, for key generation
+    pub fn blank() -> Self {
+        let zero = Fr::from(0u64);
+        Self::new(UnshieldCircuit::blank(), zero, 0, zero)
+    }
+
+    /// Number of public inputs
+    pub const NUM_PUBLIC_INPUTS: usize = UNSHIELD_WITH_CHANGE_PUBLIC_INPUTS;
+}
+
+/// Unshield circuit for timelocked notes
+///
+/// A timelocked note's leaf is `Poseidon(commitment, not_before_slot)`
+/// (see `note::timelocked_commitment`), so the lock is fixed when the note
+/// is shielded. The slot is a tenth public input; the program rejects the
+/// proof before that slot, which lets vesting and escrow payments sit in
+/// the pool until they unlock.
+#[derive(Clone, Default)]
+pub struct TimelockedUnshieldCircuit {
+    /// The withdrawal of the full note
+    pub unshield: UnshieldCircuit,
+    /// First slot at which the note may be spent
+    pub not_before_slot: Option<u64>,
+}
+
+impl TimelockedUnshieldCircuit {
+    /// Create a withdrawal of a note locked until `not_before_slot`
+    pub fn new(unshield: UnshieldCircuit, not_before_slot: u64) -> Self {
+        Self {
+            unshield,
+            not_before_slot: Some(not_before_slot),
+        }
+    }
+
+    /// Circuit with placeholder values, for key generation
+    pub fn blank() -> Self {
+        Self::new(UnshieldCircuit::blank(), 0)
+    }
+
+    /// Number of public inputs
+    pub const NUM_PUBLIC_INPUTS: usize = TIMELOCKED_UNSHIELD_PUBLIC_INPUTS;
+}
+
+/// Unshield circuit for escrow notes
+///
+/// An escrow note's leaf is `Poseidon(Poseidon(commitment, arbiter),
+/// escrow_id)` (see `note::escrow_commitment`). Both are public inputs,
+/// after the unshield inputs; the program only accepts the proof with the
+/// arbiter's signature over a resolution naming the recipient, so the
+/// parties of a deal can share the note and the arbiter decides who gets it.
+#[derive(Clone, Default)]
+pub struct EscrowUnshieldCircuit {
+    /// The withdrawal of the full note
+    pub unshield: UnshieldCircuit,
+    /// Arbiter pubkey bytes as a field element
+    pub arbiter: Option<Fr>,
+    /// The deal the note is escrowed for
+    pub escrow_id: Option<Fr>,
+}
+
+impl EscrowUnshieldCircuit {
+    /// Create a withdrawal of a note escrowed under `arbiter` for `escrow_id`
+    pub fn new(unshield: UnshieldCircuit, arbiter: Fr, escrow_id: Fr) -> Self {
+        Self {
+            unshield,
+            arbiter: Some(arbiter),
+            escrow_id: Some(escrow_id),
+        }
+    }
+
+    /// Circuit with placeholder values, for key generation
+    pub fn blank() -> Self {
+        let zero = Fr::from(0u64);
+        Self::new(UnshieldCircuit::blank(), zero, zero)
+    }
+
+    /// Number of public inputs
+    pub const NUM_PUBLIC_INPUTS: usize = ESCROW_UNSHIELD_PUBLIC_INPUTS;
+}
+
+/// Change note of a partial withdrawal
+struct ChangeNote {
+    commitment: Option<Fr>,
+    amount: Option<u64>,
+    blinding: Option<Fr>,
+}
+
+/// Arbiter and deal an escrow note's leaf binds
+struct EscrowTerms {
+    arbiter: Option<Fr>,
+    escrow_id: Option<Fr>,
+}
+
+impl ConstraintSynthesizer<Fr> for UnshieldCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        self.synthesize(cs, false, None, None, None)
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for MultiAssetUnshieldCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        self.0.synthesize(cs, true, None, None, None)
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for UnshieldWithChangeCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let change = ChangeNote {
+            commitment: self.change_commitment,
+            amount: self.change_amount,
+            blinding: self.change_blinding,
+        };
+        self.unshield.synthesize(cs, false, Some(change), None, None)
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for TimelockedUnshieldCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        self.unshield.synthesize(cs, false, None, Some(self.not_before_slot), None)
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for EscrowUnshieldCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let escrow = EscrowTerms {
+            arbiter: self.arbiter,
+            escrow_id: self.escrow_id,
+        };
+        self.unshield.synthesize(cs, false, None, None, Some(escrow))
+    }
+}
+
+impl UnshieldCircuit {
+    /// Generate the unshield constraints, with the asset ID as the last
+    /// public input if `expose_asset_id`, the change note's commitment if
+    /// `change` is given, the note's unlock slot if `not_before_slot` is, or
+    /// its arbiter and escrow ID if `escrow` is
+    fn synthesize(
+        self,
+        cs: ConstraintSystemRef<Fr>,
+        expose_asset_id: bool,
+        change: Option<ChangeNote>,
+        not_before_slot: Option<Option<u64>>,
+        escrow: Option<EscrowTerms>,
+    ) -> Result<(), SynthesisError> {
+        // ===== Allocate Public Inputs =====
+        let merkle_root_var = FpVar::new_input(cs.clone(), || {
+            self.merkle_root.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let nullifier_var = FpVar::new_input(cs.clone(), || {
+            self.nullifier.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let recipient_var = FpVar::new_input(cs.clone(), || {
+            self.recipient.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let amount_var = FpVar::new_input(cs.clone(), || {
+            self.amount.map(Fr::from).ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let relayer_var = FpVar::new_input(cs.clone(), || {
+            self.relayer.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let fee_var = FpVar::new_input(cs.clone(), || {
+            self.fee.map(Fr::from).ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let refund_var = FpVar::new_input(cs.clone(), || {
+            self.refund.map(Fr::from).ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let expiry_slot_var = FpVar::new_input(cs.clone(), || {
+            self.expiry_slot.map(Fr::from).ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let nullifier_domain_var = FpVar::new_input(cs.clone(), || {
+            self.nullifier_domain.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // ===== Allocate Private Inputs (Witnesses) =====
+        let secret_var = FpVar::new_witness(cs.clone(), || {
+            self.secret.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let blinding_var = FpVar::new_witness(cs.clone(), || {
+            self.blinding.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // Allocated after the other public inputs, so it comes last
+        let asset_id = || self.asset_id.ok_or(SynthesisError::AssignmentMissing);
+        let asset_id_var = if expose_asset_id {
+            FpVar::new_input(cs.clone(), asset_id)?
+        } else {
+            FpVar::new_witness(cs.clone(), asset_id)?
+        };
+
+        // Also allocated last; the change amount must fit in 64 bits so the
+        // note amount can't wrap around the field
+        let change_vars = match &change {
+            Some(change) => {
+                let commitment_var = FpVar::new_input(cs.clone(), || {
+                    change.commitment.ok_or(SynthesisError::AssignmentMissing)
+                })?;
+                let amount_var = FpVar::new_witness(cs.clone(), || {
+                    change.amount.map(Fr::from).ok_or(SynthesisError::AssignmentMissing)
+                })?;
+                for bit in &amount_var.to_bits_le()?[64..] {
+                    bit.enforce_equal(&Boolean::FALSE)?;
+                }
+                let blinding_var = FpVar::new_witness(cs.clone(), || {
+                    change.blinding.ok_or(SynthesisError::AssignmentMissing)
+                })?;
+                Some((commitment_var, amount_var, blinding_var))
+            }
+            None => None,
+        };
+
+        // Also allocated last; range checked so the program's u64 compare
+        // against the clock sees the same slot the leaf binds
+        let not_before_slot_var = match not_before_slot {
+            Some(slot) => {
+                let slot_var = FpVar::new_input(cs.clone(), || {
+                    slot.map(Fr::from).ok_or(SynthesisError::AssignmentMissing)
+                })?;
+                for bit in &slot_var.to_bits_le()?[64..] {
+                    bit.enforce_equal(&Boolean::FALSE)?;
+                }
+                Some(slot_var)
+            }
+            None => None,
+        };
+
+        // Also allocated last
+        let escrow_vars = match escrow {
+            Some(escrow) => {
+                let arbiter_var = FpVar::new_input(cs.clone(), || {
+                    escrow.arbiter.ok_or(SynthesisError::AssignmentMissing)
+                })?;
+                let escrow_id_var = FpVar::new_input(cs.clone(), || {
+                    escrow.escrow_id.ok_or(SynthesisError::AssignmentMissing)
+                })?;
+                Some((arbiter_var, escrow_id_var))
+            }
+            None => None,
+        };
+
+        let leaf_index = self.leaf_index.ok_or(SynthesisError::AssignmentMissing)?;
+        let leaf_index_var = FpVar::new_witness(cs.clone(), || Ok(Fr::from(leaf_index)))?;
+
+        // Allocated for every note so stealth and plain notes share one key
+        let is_stealth_var = Boolean::new_witness(cs.clone(), || Ok(self.stealth_tweak.is_some()))?;
+        let stealth_tweak_var =
+            FpVar::new_witness(cs.clone(), || Ok(self.stealth_tweak.unwrap_or_default()))?;
+
+        // ===== Constraint 1: Compute spending key =====
+        let domain_separator = FpVar::new_constant(
+            cs.clone(),
+            Fr::from_le_bytes_mod_order(SPENDING_KEY_DOMAIN),
+        )?;
+        let spending_key_var = poseidon_hash2_gadget(cs.clone(), &secret_var, &domain_separator)?;
+
+        // A stealth note is owned by a one-time key, which the sender derived
+        // from the owner's published spending key and the stealth tweak
+        let stealth_key_var =
+            poseidon_hash2_gadget(cs.clone(), &spending_key_var, &stealth_tweak_var)?;
+        let note_key_var = is_stealth_var.select(&stealth_key_var, &spending_key_var)?;
+
+        // ===== Constraint 2: Compute note commitment =====
+        // The note holds the public amount plus any change, so without a
+        // change note it is withdrawn in full
+        let note_amount_var = match &change_vars {
+            Some((_, change_amount_var, _)) => &amount_var + change_amount_var,
+            None => amount_var.clone(),
+        };
+        let h1 = poseidon_hash2_gadget(cs.clone(), &note_key_var, &note_amount_var)?;
+        let h2 = poseidon_hash2_gadget(cs.clone(), &blinding_var, &asset_id_var)?;
+        let commitment_var = poseidon_hash2_gadget(cs.clone(), &h1, &h2)?;
+
+        // A timelocked note's leaf also binds its unlock slot
+        let leaf_var = match &not_before_slot_var {
+            Some(slot_var) => poseidon_hash2_gadget(cs.clone(), &commitment_var, slot_var)?,
+            None => commitment_var,
+        };
+
+        // An escrow note's leaf also binds its arbiter and deal
+        let leaf_var = match &escrow_vars {
+            Some((arbiter_var, escrow_id_var)) => {
+                let with_arbiter = poseidon_hash2_gadget(cs.clone(), &leaf_var, arbiter_var)?;
+                poseidon_hash2_gadget(cs.clone(), &with_arbiter, escrow_id_var)?
+            }
+            None => leaf_var,
+        };
+
+        // ===== Constraint 3: Verify Merkle membership =====
+        let merkle_path = self.merkle_path.ok_or(SynthesisError::AssignmentMissing)?;
+        let merkle_indices = self.merkle_indices.ok_or(SynthesisError::AssignmentMissing)?;
+
+        let path_gadget = MerklePathGadget::new_witness(cs.clone(), &merkle_path, &merkle_indices)?;
+        path_gadget.verify(cs.clone(), &leaf_var, &merkle_root_var)?;
+
+        // ===== Constraint 4: Verify nullifier derivation =====
+        let index_with_domain =
+            poseidon_hash2_gadget(cs.clone(), &leaf_index_var, &nullifier_domain_var)?;
+        let computed_nullifier = poseidon_hash2_gadget(cs.clone(), &note_key_var, &index_with_domain)?;
+
+        computed_nullifier.enforce_equal(&nullifier_var)?;
+
+        // ===== Constraint 5: Bind withdrawal parameters =====
+        // These are only checked on-chain; square them so each input takes
+        // part in a constraint and can't be altered after proving
+        for var in [&recipient_var, &relayer_var, &fee_var, &refund_var, &expiry_slot_var] {
+            let _square = var.square()?;
+        }
+
+        // ===== Constraint 6: Verify the change note =====
+        // The change goes back to the owner's spending key, in the same asset
+        if let Some((change_commitment_var, change_amount_var, change_blinding_var)) = change_vars {
+            let h1 = poseidon_hash2_gadget(cs.clone(), &spending_key_var, &change_amount_var)?;
+            let h2 = poseidon_hash2_gadget(cs.clone(), &change_blinding_var, &asset_id_var)?;
+            let computed_change = poseidon_hash2_gadget(cs.clone(), &h1, &h2)?;
+            computed_change.enforce_equal(&change_commitment_var)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::UniformRand;
+    use ark_relations::r1cs::ConstraintSystem;
+    use rand::rngs::OsRng;
+    use veil_core::crypto::merkle::PoseidonMerkleTree;
+
+    use crate::note::{
+        asset_id, bytes_to_field, escrow_commitment, note_commitment, nullifier, nullifier_domain,
+        spending_key, stealth_spending_key, timelocked_commitment,
+    };
+
+    fn build_circuit(claimed_nullifier: Option<Fr>) -> UnshieldCircuit {
+        build_circuit_in_pool(claimed_nullifier, &[5u8; 32])
+    }
+
+    fn build_circuit_in_pool(claimed_nullifier: Option<Fr>, proof_pool: &[u8; 32]) -> UnshieldCircuit {
+        build_circuit_with_asset(claimed_nullifier, proof_pool, Fr::from(0u64))
+    }
+
+    fn build_circuit_with_asset(
+        claimed_nullifier: Option<Fr>,
+        proof_pool: &[u8; 32],
+        asset_id: Fr,
+    ) -> UnshieldCircuit {
+        let secret = Fr::rand(&mut OsRng);
+        let blinding = Fr::rand(&mut OsRng);
+        let key = spending_key(&secret);
+        let commitment = note_commitment(&key, &Fr::from(1000u64), &blinding, &asset_id);
+
+        let mut tree = PoseidonMerkleTree::new();
+        let leaf_index = tree.insert(commitment).unwrap();
+        let path = tree.generate_proof(leaf_index).unwrap();
+        let domain = nullifier_domain(&[5u8; 32], &[0u8; 32]);
+
+        UnshieldCircuit::new(
+            tree.root(),
+            claimed_nullifier.unwrap_or_else(|| nullifier(&key, leaf_index, &domain)),
+            bytes_to_field(&[7u8; 32]),
+            1000,
+            bytes_to_field(&[9u8; 32]),
+            3,
+            0,
+            1_000,
+            nullifier_domain(proof_pool, &[0u8; 32]),
+            secret,
+            blinding,
+            asset_id,
+            leaf_index,
+            path.siblings,
+            path.indices,
+        )
+    }
+
+    #[test]
+    fn test_unshield_circuit_valid() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        build_circuit(None).generate_constraints(cs.clone()).unwrap();
+
+        println!("Unshield circuit constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+        assert_eq!(cs.num_instance_variables(), UnshieldCircuit::NUM_PUBLIC_INPUTS + 1);
+    }
+
+    #[test]
+    fn test_unshield_circuit_invalid_nullifier() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        build_circuit(Some(Fr::rand(&mut OsRng)))
+            .generate_constraints(cs.clone())
+            .unwrap();
+
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_unshield_circuit_rejects_other_pool_domain() {
+        // The note's nullifier was derived for pool [5; 32]; proving it
+        // against another pool's domain must fail
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        build_circuit_in_pool(None, &[6u8; 32])
+            .generate_constraints(cs.clone())
+            .unwrap();
+
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_multi_asset_unshield_exposes_asset() {
+        let asset_id = Fr::from(asset_id(&[3u8; 32]));
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        MultiAssetUnshieldCircuit(build_circuit_with_asset(None, &[5u8; 32], asset_id))
+            .generate_constraints(cs.clone())
+            .unwrap();
+
+        assert!(cs.is_satisfied().unwrap());
+        assert_eq!(
+            cs.num_instance_variables(),
+            MultiAssetUnshieldCircuit::NUM_PUBLIC_INPUTS + 1
+        );
+    }
+
+    #[test]
+    fn test_multi_asset_unshield_rejects_other_asset() {
+        // The note holds one asset; claiming another changes its commitment,
+        // which is then not in the tree
+        let mut circuit = build_circuit_with_asset(None, &[5u8; 32], Fr::from(asset_id(&[3u8; 32])));
+        circuit.asset_id = Some(Fr::from(asset_id(&[4u8; 32])));
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        MultiAssetUnshieldCircuit(circuit)
+            .generate_constraints(cs.clone())
+            .unwrap();
+
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    /// Withdraw `amount` of a 1000 note, leaving `change_amount` in a change note
+    fn build_change_circuit(amount: u64, change_amount: u64) -> UnshieldWithChangeCircuit {
+        let mut unshield = build_circuit(None);
+        unshield.amount = Some(amount);
+        let key = spending_key(&unshield.secret.unwrap());
+        let change_blinding = Fr::rand(&mut OsRng);
+        let change_commitment = note_commitment(
+            &key,
+            &Fr::from(change_amount),
+            &change_blinding,
+            &unshield.asset_id.unwrap(),
+        );
+        UnshieldWithChangeCircuit::new(unshield, change_commitment, change_amount, change_blinding)
+    }
+
+    #[test]
+    fn test_unshield_with_change_valid() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        build_change_circuit(300, 700).generate_constraints(cs.clone()).unwrap();
+
+        assert!(cs.is_satisfied().unwrap());
+        assert_eq!(
+            cs.num_instance_variables(),
+            UnshieldWithChangeCircuit::NUM_PUBLIC_INPUTS + 1
+        );
+    }
+
+    #[test]
+    fn test_unshield_with_change_conserves_value() {
+        // Withdrawing 300 of a 1000 note can't leave 701 behind
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        build_change_circuit(300, 701).generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // Nor can the change note differ from the one committed to
+        let mut circuit = build_change_circuit(300, 700);
+        circuit.change_commitment = Some(Fr::rand(&mut OsRng));
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    /// Withdraw a 1000 note whose leaf locks it until `locked_until`,
+    /// claiming `claimed_slot` as the unlock slot
+    fn build_timelocked_circuit(locked_until: u64, claimed_slot: u64) -> TimelockedUnshieldCircuit {
+        let mut unshield = build_circuit(None);
+        let key = spending_key(&unshield.secret.unwrap());
+        let commitment = note_commitment(
+            &key,
+            &Fr::from(1000u64),
+            &unshield.blinding.unwrap(),
+            &unshield.asset_id.unwrap(),
+        );
+
+        let mut tree = PoseidonMerkleTree::new();
+        let leaf_index = tree.insert(timelocked_commitment(&commitment, locked_until)).unwrap();
+        let path = tree.generate_proof(leaf_index).unwrap();
+        let domain = nullifier_domain(&[5u8; 32], &[0u8; 32]);
+        unshield.merkle_root = Some(tree.root());
+        unshield.nullifier = Some(nullifier(&key, leaf_index, &domain));
+        unshield.leaf_index = Some(leaf_index);
+        unshield.merkle_path = Some(path.siblings);
+        unshield.merkle_indices = Some(path.indices);
+        TimelockedUnshieldCircuit::new(unshield, claimed_slot)
+    }
+
+    #[test]
+    fn test_timelocked_unshield_valid() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        build_timelocked_circuit(5_000, 5_000)
+            .generate_constraints(cs.clone())
+            .unwrap();
+
+        assert!(cs.is_satisfied().unwrap());
+        assert_eq!(
+            cs.num_instance_variables(),
+            TimelockedUnshieldCircuit::NUM_PUBLIC_INPUTS + 1
+        );
+    }
+
+    #[test]
+    fn test_timelocked_unshield_binds_unlock_slot() {
+        // Claiming an earlier unlock slot changes the leaf, which is then
+        // not in the tree
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        build_timelocked_circuit(5_000, 4_999)
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // Nor can a timelocked leaf be spent as a plain note
+        let circuit = build_timelocked_circuit(5_000, 5_000);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.unshield.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    /// Withdraw a 1000 note escrowed under arbiter 7 for deal 9, claiming
+    /// `claimed_arbiter` and `claimed_escrow_id`
+    fn build_escrow_circuit(claimed_arbiter: u64, claimed_escrow_id: u64) -> EscrowUnshieldCircuit {
+        let mut unshield = build_circuit(None);
+        let key = spending_key(&unshield.secret.unwrap());
+        let commitment = note_commitment(
+            &key,
+            &Fr::from(1000u64),
+            &unshield.blinding.unwrap(),
+            &unshield.asset_id.unwrap(),
+        );
+        let leaf = escrow_commitment(&commitment, &Fr::from(7u64), &Fr::from(9u64));
+
+        let mut tree = PoseidonMerkleTree::new();
+        let leaf_index = tree.insert(leaf).unwrap();
+        let path = tree.generate_proof(leaf_index).unwrap();
+        let domain = nullifier_domain(&[5u8; 32], &[0u8; 32]);
+        unshield.merkle_root = Some(tree.root());
+        unshield.nullifier = Some(nullifier(&key, leaf_index, &domain));
+        unshield.leaf_index = Some(leaf_index);
+        unshield.merkle_path = Some(path.siblings);
+        unshield.merkle_indices = Some(path.indices);
+        EscrowUnshieldCircuit::new(
+            unshield,
+            Fr::from(claimed_arbiter),
+            Fr::from(claimed_escrow_id),
+        )
+    }
+
+    #[test]
+    fn test_escrow_unshield_valid() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        build_escrow_circuit(7, 9).generate_constraints(cs.clone()).unwrap();
+
+        assert!(cs.is_satisfied().unwrap());
+        assert_eq!(cs.num_instance_variables(), EscrowUnshieldCircuit::NUM_PUBLIC_INPUTS + 1);
+    }
+
+    #[test]
+    fn test_escrow_unshield_binds_arbiter_and_deal() {
+        // Naming another arbiter or deal changes the leaf, which is then
+        // not in the tree
+        for (arbiter, escrow_id) in [(8, 9), (7, 10)] {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            build_escrow_circuit(arbiter, escrow_id)
+                .generate_constraints(cs.clone())
+                .unwrap();
+            assert!(!cs.is_satisfied().unwrap());
+        }
+
+        // Nor can an escrow leaf be spent as a plain note
+        let circuit = build_escrow_circuit(7, 9);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.unshield.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    /// Withdraw a 1000 note sent to the stealth address of the secret's
+    /// spending key under `tweak`, claiming `claimed_tweak` when proving
+    fn build_stealth_circuit(tweak: Fr, claimed_tweak: Option<Fr>) -> UnshieldCircuit {
+        let unshield = build_circuit(None);
+        let key = stealth_spending_key(&spending_key(&unshield.secret.unwrap()), &tweak);
+        let commitment = note_commitment(
+            &key,
+            &Fr::from(1000u64),
+            &unshield.blinding.unwrap(),
+            &unshield.asset_id.unwrap(),
+        );
+
+        let mut tree = PoseidonMerkleTree::new();
+        let leaf_index = tree.insert(commitment).unwrap();
+        let path = tree.generate_proof(leaf_index).unwrap();
+        let domain = nullifier_domain(&[5u8; 32], &[0u8; 32]);
+        let mut circuit = UnshieldCircuit {
+            merkle_root: Some(tree.root()),
+            nullifier: Some(nullifier(&key, leaf_index, &domain)),
+            leaf_index: Some(leaf_index),
+            merkle_path: Some(path.siblings),
+            merkle_indices: Some(path.indices),
+            ..unshield
+        };
+        circuit.stealth_tweak = claimed_tweak;
+        circuit
+    }
+
+    #[test]
+    fn test_unshield_stealth_note() {
+        let tweak = Fr::rand(&mut OsRng);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        build_stealth_circuit(tweak, Some(tweak))
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        assert_eq!(cs.num_instance_variables(), UnshieldCircuit::NUM_PUBLIC_INPUTS + 1);
+    }
+
+    #[test]
+    fn test_stealth_note_needs_its_tweak() {
+        // Spent as a plain note of the published spending key
+        let tweak = Fr::rand(&mut OsRng);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        build_stealth_circuit(tweak, None).generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // Or under another tweak
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        build_stealth_circuit(tweak, Some(Fr::rand(&mut OsRng)))
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // Plain and stealth spends share one constraint shape, so one key
+        let plain = ConstraintSystem::<Fr>::new_ref();
+        build_circuit(None).generate_constraints(plain.clone()).unwrap();
+        let stealth = ConstraintSystem::<Fr>::new_ref();
+        build_stealth_circuit(tweak, Some(tweak))
+            .generate_constraints(stealth.clone())
+            .unwrap();
+        assert_eq!(plain.num_constraints(), stealth.num_constraints());
+    }
+}
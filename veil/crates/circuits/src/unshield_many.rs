@@ -0,0 +1,328 @@
+//! Multi-Recipient Unshield Circuit
+//!
+//! Withdraws one note to several recipients with a single proof, so a DAO
+//! can run payroll without one withdrawal transaction per payee:
+//! 1. The withdrawer knows the preimage of a commitment in the Merkle tree
+//! 2. The nullifier is correctly derived from the spending key, leaf index
+//!    and the pool's nullifier domain
+//! 3. The note holds exactly the sum of the payouts plus the relayer fee
+//! 4. Every recipient and amount, the relayer, fee and expiry are bound to
+//!    the proof
+//!
+//! The circuit has `MAX_UNSHIELD_RECIPIENTS` payout slots; unused slots
+//! carry a zero recipient and amount.
+//!
+//! Public Inputs (in the program's `CircuitId::UnshieldMany` order):
+//! - merkle_root: The current Merkle tree root
+//! - nullifier: The nullifier for the spent note
+//! - recipients: Each slot's recipient pubkey bytes as a field element
+//! - amounts: Each slot's payout
+//! - relayer: Relayer pubkey bytes as a field element
+//! - fee: The relayer fee, paid on top of the payouts
+//! - expiry_slot: Last Solana slot at which the proof may be submitted
+//! - nullifier_domain: Tag binding the nullifier to the pool and its mint
+//!
+//! Private Inputs (Witness):
+//! - secret, blinding, asset_id: Opening of the spent note
+//! - leaf_index, merkle_path: Position of the note in the Merkle tree
+
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use veil_core::proof::gadgets::merkle::MerklePathGadget;
+use veil_core::proof::gadgets::poseidon::poseidon_hash2_gadget;
+
+use crate::constants::{
+    MAX_UNSHIELD_RECIPIENTS, SPENDING_KEY_DOMAIN, TREE_DEPTH, UNSHIELD_MANY_PUBLIC_INPUTS,
+};
+
+/// Unshield circuit paying one note out to several recipients
+#[derive(Clone, Default)]
+pub struct UnshieldManyCircuit {
+    // ===== Public Inputs =====
+    /// Current Merkle root
+    pub merkle_root: Option<Fr>,
+    /// Nullifier for the spent note
+    pub nullifier: Option<Fr>,
+    /// Recipient of each payout slot
+    pub recipients: Option<[Fr; MAX_UNSHIELD_RECIPIENTS]>,
+    /// Amount of each payout slot
+    pub amounts: Option<[u64; MAX_UNSHIELD_RECIPIENTS]>,
+    /// Relayer submitting the withdrawal
+    pub relayer: Option<Fr>,
+    /// Fee paid to the relayer
+    pub fee: Option<u64>,
+    /// Last slot at which the proof may be submitted
+    pub expiry_slot: Option<u64>,
+    /// Tag binding the nullifier to the pool and its mint
+    pub nullifier_domain: Option<Fr>,
+
+    // ===== Private Inputs (Witness) =====
+    /// Owner's secret
+    pub secret: Option<Fr>,
+    /// Blinding factor of the note
+    pub blinding: Option<Fr>,
+    /// Asset ID of the note
+    pub asset_id: Option<Fr>,
+    /// Leaf index in the Merkle tree
+    pub leaf_index: Option<u64>,
+    /// Merkle path siblings
+    pub merkle_path: Option<Vec<Fr>>,
+    /// Merkle path indices (left/right)
+    pub merkle_indices: Option<Vec<bool>>,
+}
+
+impl UnshieldManyCircuit {
+    /// Create a new multi-recipient unshield circuit with all values
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        merkle_root: Fr,
+        nullifier: Fr,
+        recipients: [Fr; MAX_UNSHIELD_RECIPIENTS],
+        amounts: [u64; MAX_UNSHIELD_RECIPIENTS],
+        relayer: Fr,
+        fee: u64,
+        expiry_slot: u64,
+        nullifier_domain: Fr,
+        secret: Fr,
+        blinding: Fr,
+        asset_id: Fr,
+        leaf_index: u64,
+        merkle_path: Vec<Fr>,
+        merkle_indices: Vec<bool>,
+    ) -> Self {
+        Self {
+            merkle_root: Some(merkle_root),
+            nullifier: Some(nullifier),
+            recipients: Some(recipients),
+            amounts: Some(amounts),
+            relayer: Some(relayer),
+            fee: Some(fee),
+            expiry_slot: Some(expiry_slot),
+            nullifier_domain: Some(nullifier_domain),
+            secret: Some(secret),
+            blinding: Some(blinding),
+            asset_id: Some(asset_id),
+            leaf_index: Some(leaf_index),
+            merkle_path: Some(merkle_path),
+            merkle_indices: Some(merkle_indices),
+        }
+    }
+
+    /// Circuit with placeholder values, for key generation
+    pub fn blank() -> Self {
+        let zero = Fr::from(0u64);
+        Self::new(
+            zero,
+            zero,
+            [zero; MAX_UNSHIELD_RECIPIENTS],
+            [0; MAX_UNSHIELD_RECIPIENTS],
+            zero,
+            0,
+            0,
+            zero,
+            zero,
+            zero,
+            zero,
+            0,
+            vec![zero; TREE_DEPTH],
+            vec![false; TREE_DEPTH],
+        )
+    }
+
+    /// Number of public inputs
+    pub const NUM_PUBLIC_INPUTS: usize = UNSHIELD_MANY_PUBLIC_INPUTS;
+}
+
+impl ConstraintSynthesizer<Fr> for UnshieldManyCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        // ===== Allocate Public Inputs =====
+        let merkle_root_var = FpVar::new_input(cs.clone(), || {
+            self.merkle_root.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let nullifier_var = FpVar::new_input(cs.clone(), || {
+            self.nullifier.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let mut recipient_vars = Vec::with_capacity(MAX_UNSHIELD_RECIPIENTS);
+        for i in 0..MAX_UNSHIELD_RECIPIENTS {
+            recipient_vars.push(FpVar::new_input(cs.clone(), || {
+                self.recipients
+                    .map(|recipients| recipients[i])
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?);
+        }
+
+        let mut amount_vars = Vec::with_capacity(MAX_UNSHIELD_RECIPIENTS);
+        for i in 0..MAX_UNSHIELD_RECIPIENTS {
+            amount_vars.push(FpVar::new_input(cs.clone(), || {
+                self.amounts
+                    .map(|amounts| Fr::from(amounts[i]))
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?);
+        }
+
+        let relayer_var = FpVar::new_input(cs.clone(), || {
+            self.relayer.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let fee_var = FpVar::new_input(cs.clone(), || {
+            self.fee.map(Fr::from).ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let expiry_slot_var = FpVar::new_input(cs.clone(), || {
+            self.expiry_slot.map(Fr::from).ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let nullifier_domain_var = FpVar::new_input(cs.clone(), || {
+            self.nullifier_domain.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // ===== Allocate Private Inputs (Witnesses) =====
+        let secret_var = FpVar::new_witness(cs.clone(), || {
+            self.secret.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let blinding_var = FpVar::new_witness(cs.clone(), || {
+            self.blinding.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let asset_id_var = FpVar::new_witness(cs.clone(), || {
+            self.asset_id.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let leaf_index = self.leaf_index.ok_or(SynthesisError::AssignmentMissing)?;
+        let leaf_index_var = FpVar::new_witness(cs.clone(), || Ok(Fr::from(leaf_index)))?;
+
+        // ===== Constraint 1: Compute spending key =====
+        let domain_separator = FpVar::new_constant(
+            cs.clone(),
+            Fr::from_le_bytes_mod_order(SPENDING_KEY_DOMAIN),
+        )?;
+        let spending_key_var = poseidon_hash2_gadget(cs.clone(), &secret_var, &domain_separator)?;
+
+        // ===== Constraint 2: Compute note commitment =====
+        // The note holds every payout plus the fee. The program passes each
+        // as a u64, so the sum can't wrap around the field
+        let note_amount_var = amount_vars.iter().fold(fee_var.clone(), |sum, amount| sum + amount);
+        let h1 = poseidon_hash2_gadget(cs.clone(), &spending_key_var, &note_amount_var)?;
+        let h2 = poseidon_hash2_gadget(cs.clone(), &blinding_var, &asset_id_var)?;
+        let commitment_var = poseidon_hash2_gadget(cs.clone(), &h1, &h2)?;
+
+        // ===== Constraint 3: Verify Merkle membership =====
+        let merkle_path = self.merkle_path.ok_or(SynthesisError::AssignmentMissing)?;
+        let merkle_indices = self.merkle_indices.ok_or(SynthesisError::AssignmentMissing)?;
+
+        let path_gadget = MerklePathGadget::new_witness(cs.clone(), &merkle_path, &merkle_indices)?;
+        path_gadget.verify(cs.clone(), &commitment_var, &merkle_root_var)?;
+
+        // ===== Constraint 4: Verify nullifier derivation =====
+        let index_with_domain =
+            poseidon_hash2_gadget(cs.clone(), &leaf_index_var, &nullifier_domain_var)?;
+        let computed_nullifier = poseidon_hash2_gadget(cs.clone(), &spending_key_var, &index_with_domain)?;
+
+        computed_nullifier.enforce_equal(&nullifier_var)?;
+
+        // ===== Constraint 5: Bind withdrawal parameters =====
+        // Recipients, relayer and expiry are only checked on-chain; square
+        // them so each input takes part in a constraint
+        for var in recipient_vars.iter().chain([&relayer_var, &expiry_slot_var]) {
+            let _square = var.square()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::UniformRand;
+    use ark_relations::r1cs::ConstraintSystem;
+    use rand::rngs::OsRng;
+    use veil_core::crypto::merkle::PoseidonMerkleTree;
+
+    use crate::note::{bytes_to_field, note_commitment, nullifier, nullifier_domain, spending_key};
+
+    /// Pay `amounts` out of a note of `note_amount` with a fee of 5
+    fn build_circuit(note_amount: u64, amounts: [u64; MAX_UNSHIELD_RECIPIENTS]) -> UnshieldManyCircuit {
+        let secret = Fr::rand(&mut OsRng);
+        let blinding = Fr::rand(&mut OsRng);
+        let asset_id = Fr::from(0u64);
+        let key = spending_key(&secret);
+        let commitment = note_commitment(&key, &Fr::from(note_amount), &blinding, &asset_id);
+
+        let mut tree = PoseidonMerkleTree::new();
+        let leaf_index = tree.insert(commitment).unwrap();
+        let path = tree.generate_proof(leaf_index).unwrap();
+        let domain = nullifier_domain(&[5u8; 32], &[0u8; 32]);
+
+        let mut recipients = [Fr::from(0u64); MAX_UNSHIELD_RECIPIENTS];
+        for (i, recipient) in recipients.iter_mut().enumerate() {
+            if amounts[i] > 0 {
+                *recipient = bytes_to_field(&[i as u8 + 1; 32]);
+            }
+        }
+
+        UnshieldManyCircuit::new(
+            tree.root(),
+            nullifier(&key, leaf_index, &domain),
+            recipients,
+            amounts,
+            bytes_to_field(&[9u8; 32]),
+            5,
+            1_000,
+            domain,
+            secret,
+            blinding,
+            asset_id,
+            leaf_index,
+            path.siblings,
+            path.indices,
+        )
+    }
+
+    fn is_satisfied(circuit: UnshieldManyCircuit) -> bool {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_unshield_many_valid() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        build_circuit(1005, [400, 350, 250, 0])
+            .generate_constraints(cs.clone())
+            .unwrap();
+
+        println!("Unshield many circuit constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap());
+        assert_eq!(cs.num_instance_variables(), UnshieldManyCircuit::NUM_PUBLIC_INPUTS + 1);
+    }
+
+    #[test]
+    fn test_unshield_many_conserves_value() {
+        // The payouts and fee must add up to the note exactly
+        assert!(!is_satisfied(build_circuit(1005, [400, 350, 251, 0])));
+        assert!(!is_satisfied(build_circuit(1005, [400, 350, 249, 0])));
+    }
+
+    #[test]
+    fn test_unshield_many_single_recipient() {
+        // Unused slots are zero, so one payout is an ordinary withdrawal
+        assert!(is_satisfied(build_circuit(505, [500, 0, 0, 0])));
+    }
+
+    #[test]
+    fn test_unshield_many_invalid_nullifier() {
+        let mut circuit = build_circuit(1005, [400, 350, 250, 0]);
+        circuit.nullifier = Some(Fr::rand(&mut OsRng));
+        assert!(!is_satisfied(circuit));
+    }
+}
@@ -0,0 +1,171 @@
+//! Verifying key export
+//!
+//! Converts arkworks Groth16 verifying keys into the layout the program's
+//! `VerifyingKeyAccount` stores: big-endian coordinates, with G2 points as
+//! (x.c1, x.c0, y.c1, y.c0) as the alt_bn128 syscalls expect. The JSON form
+//! carries the `propose_verifying_key` arguments as hex strings.
+
+use ark_bn254::{Bn254, Fr, G1Affine, G2Affine};
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::{Groth16, ProvingKey, VerifyingKey};
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_snark::SNARK;
+use rand::rngs::OsRng;
+use serde::Serialize;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum VkExportError {
+    #[error("Setup error: {0}")]
+    SetupError(String),
+    #[error("Verifying key has {actual} IC points, circuit expects {expected}")]
+    PublicInputMismatch { expected: usize, actual: usize },
+    #[error("Serialization error: {0}")]
+    SerializationError(String),
+}
+
+/// Generate proving and verifying keys for a circuit
+///
+/// WARNING: This uses random toxic waste and is suitable only for testing
+/// and localnet. Production keys come from a trusted setup ceremony.
+pub fn setup<C: ConstraintSynthesizer<Fr>>(
+    circuit: C,
+) -> Result<(ProvingKey<Bn254>, VerifyingKey<Bn254>), VkExportError> {
+    Groth16::<Bn254>::circuit_specific_setup(circuit, &mut OsRng)
+        .map_err(|e| VkExportError::SetupError(e.to_string()))
+}
+
+/// Verifying key in the program's VK account layout
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProgramVerifyingKey {
+    /// On-chain circuit ID (see `constants`)
+    pub circuit_id: u8,
+    /// Alpha * G1 (64 bytes)
+    pub alpha_g1: [u8; 64],
+    /// Beta * G2 (128 bytes)
+    pub beta_g2: [u8; 128],
+    /// Gamma * G2 (128 bytes)
+    pub gamma_g2: [u8; 128],
+    /// Delta * G2 (128 bytes)
+    pub delta_g2: [u8; 128],
+    /// IC elements (one per public input + 1)
+    pub ic: Vec<[u8; 64]>,
+}
+
+/// `propose_verifying_key` arguments with hex-encoded points
+#[derive(Serialize)]
+struct ProgramVerifyingKeyJson {
+    circuit_id: u8,
+    alpha_g1: String,
+    beta_g2: String,
+    gamma_g2: String,
+    delta_g2: String,
+    ic: Vec<String>,
+}
+
+impl ProgramVerifyingKey {
+    /// Convert an arkworks verifying key, checking its public input count
+    pub fn from_arkworks(
+        circuit_id: u8,
+        num_public_inputs: usize,
+        vk: &VerifyingKey<Bn254>,
+    ) -> Result<Self, VkExportError> {
+        if vk.gamma_abc_g1.len() != num_public_inputs + 1 {
+            return Err(VkExportError::PublicInputMismatch {
+                expected: num_public_inputs + 1,
+                actual: vk.gamma_abc_g1.len(),
+            });
+        }
+
+        Ok(Self {
+            circuit_id,
+            alpha_g1: g1_to_program_bytes(&vk.alpha_g1),
+            beta_g2: g2_to_program_bytes(&vk.beta_g2),
+            gamma_g2: g2_to_program_bytes(&vk.gamma_g2),
+            delta_g2: g2_to_program_bytes(&vk.delta_g2),
+            ic: vk.gamma_abc_g1.iter().map(g1_to_program_bytes).collect(),
+        })
+    }
+
+    /// Serialize as JSON for the deploy scripts
+    pub fn to_json(&self) -> Result<String, VkExportError> {
+        let json = ProgramVerifyingKeyJson {
+            circuit_id: self.circuit_id,
+            alpha_g1: hex::encode(self.alpha_g1),
+            beta_g2: hex::encode(self.beta_g2),
+            gamma_g2: hex::encode(self.gamma_g2),
+            delta_g2: hex::encode(self.delta_g2),
+            ic: self.ic.iter().map(hex::encode).collect(),
+        };
+        serde_json::to_string_pretty(&json)
+            .map_err(|e| VkExportError::SerializationError(e.to_string()))
+    }
+}
+
+/// Encode a base field element as 32 big-endian bytes
+fn fq_to_be<F: PrimeField>(value: &F) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&value.into_bigint().to_bytes_be());
+    bytes
+}
+
+/// Encode a G1 point as x || y (big-endian)
+pub fn g1_to_program_bytes(point: &G1Affine) -> [u8; 64] {
+    let mut bytes = [0u8; 64];
+    if point.infinity {
+        return bytes;
+    }
+    bytes[0..32].copy_from_slice(&fq_to_be(&point.x));
+    bytes[32..64].copy_from_slice(&fq_to_be(&point.y));
+    bytes
+}
+
+/// Encode a G2 point as x.c1 || x.c0 || y.c1 || y.c0 (big-endian)
+pub fn g2_to_program_bytes(point: &G2Affine) -> [u8; 128] {
+    let mut bytes = [0u8; 128];
+    if point.infinity {
+        return bytes;
+    }
+    bytes[0..32].copy_from_slice(&fq_to_be(&point.x.c1));
+    bytes[32..64].copy_from_slice(&fq_to_be(&point.x.c0));
+    bytes[64..96].copy_from_slice(&fq_to_be(&point.y.c1));
+    bytes[96..128].copy_from_slice(&fq_to_be(&point.y.c0));
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ec::AffineRepr;
+
+    use crate::constants::SHIELD_PUBLIC_INPUTS;
+    use crate::ShieldCircuit;
+
+    #[test]
+    fn test_g1_generator_encoding() {
+        let bytes = g1_to_program_bytes(&G1Affine::generator());
+        let mut expected = [0u8; 64];
+        expected[31] = 1;
+        expected[63] = 2;
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn test_g2_generator_encoding_puts_c1_first() {
+        let bytes = g2_to_program_bytes(&G2Affine::generator());
+        // EIP-197 generator: x = (0x1800de..., 0x198e93...) as (c0, c1)
+        assert_eq!(&bytes[0..2], &[0x19, 0x8e]);
+        assert_eq!(&bytes[32..34], &[0x18, 0x00]);
+    }
+
+    #[test]
+    fn test_export_checks_public_input_count() {
+        let (_, vk) = setup(ShieldCircuit::default()).unwrap();
+
+        let exported = ProgramVerifyingKey::from_arkworks(0, SHIELD_PUBLIC_INPUTS, &vk).unwrap();
+        assert_eq!(exported.ic.len(), SHIELD_PUBLIC_INPUTS + 1);
+        assert!(exported.to_json().unwrap().contains("\"circuit_id\": 0"));
+
+        assert!(ProgramVerifyingKey::from_arkworks(0, SHIELD_PUBLIC_INPUTS + 1, &vk).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+//! Chain access
+//!
+//! Reads the pool, commitment record and commitment chunk accounts the
+//! wallet needs, and sends its transactions. Trees are synced from the
+//! pool's commitment chunks, so only Groth16 pools on the incremental tree
+//! are supported.
+
+use anchor_lang::AccountDeserialize;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use veil_program::state::{
+    CommitmentRecord, PrivacyPool, ProofSystem, TreeBackend, COMMITMENT_CHUNK_LEAVES,
+    POOL_VERSION,
+};
+use veil_sdk::{pda, TreeSync};
+
+use crate::error::{CliError, Result};
+
+/// Compute units requested by spends, which verify a Groth16 proof
+const SPEND_COMPUTE_UNITS: u32 = 400_000;
+
+/// RPC client and the keypair paying for the wallet's transactions
+pub struct Chain {
+    rpc: RpcClient,
+    payer: Keypair,
+}
+
+impl Chain {
+    pub fn new(rpc_url: String, payer: Keypair) -> Self {
+        Self { rpc: RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed()), payer }
+    }
+
+    pub fn payer(&self) -> &Keypair {
+        &self.payer
+    }
+
+    /// Data of `address`, `None` if it doesn't exist
+    fn data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>> {
+        let response = self.rpc.get_account_with_commitment(address, self.rpc.commitment())?;
+        Ok(response.value.map(|account| account.data))
+    }
+
+    /// Whether `address` exists
+    pub fn exists(&self, address: &Pubkey) -> Result<bool> {
+        Ok(self.data(address)?.is_some())
+    }
+
+    /// The pool at `address`, which must be migrated to the current layout
+    pub fn pool(&self, address: &Pubkey) -> Result<PrivacyPool> {
+        let data = self.data(address)?.ok_or(CliError::AccountNotFound(address.to_string()))?;
+        let pool = data
+            .get(8..8 + PrivacyPool::SIZE)
+            .map(bytemuck::pod_read_unaligned::<PrivacyPool>)
+            .ok_or_else(|| CliError::InvalidAccount(address.to_string(), "not a pool".into()))?;
+        if pool.version != POOL_VERSION {
+            return Err(CliError::UnsupportedPool(
+                address.to_string(),
+                format!("layout version {} needs migrating", pool.version),
+            ));
+        }
+        if pool.proof_system() != ProofSystem::Groth16 {
+            return Err(CliError::UnsupportedPool(
+                address.to_string(),
+                "only Groth16 pools are supported".into(),
+            ));
+        }
+        if pool.tree_backend() != TreeBackend::Incremental {
+            return Err(CliError::UnsupportedPool(
+                address.to_string(),
+                "compressed trees are not supported".into(),
+            ));
+        }
+        Ok(pool)
+    }
+
+    /// Where `commitment` was inserted into `pool`, if it was
+    pub fn commitment_record(
+        &self,
+        pool: &Pubkey,
+        commitment: &[u8; 32],
+    ) -> Result<Option<CommitmentRecord>> {
+        let address = pda::commitment_record(pool, commitment);
+        self.data(&address)?
+            .map(|data| {
+                CommitmentRecord::try_deserialize(&mut data.as_slice())
+                    .map_err(|err| CliError::InvalidAccount(address.to_string(), err.to_string()))
+            })
+            .transpose()
+    }
+
+    /// Bring `tree` up to date from its pool's commitment chunks, and
+    /// check its root is one the pool accepts
+    pub fn sync_tree(&self, tree: &mut TreeSync) -> Result<()> {
+        for chunk_index in tree.next_chunk_index().. {
+            let address = pda::commitment_chunk(tree.pool(), tree.tree_epoch(), chunk_index);
+            let Some(data) = self.data(&address)? else {
+                break;
+            };
+            tree.apply_chunk(&data)?;
+            if tree.len() < (chunk_index as u64 + 1) * COMMITMENT_CHUNK_LEAVES as u64 {
+                break;
+            }
+        }
+        let pool = tree.pool();
+        let data = self.data(pool)?.ok_or(CliError::AccountNotFound(pool.to_string()))?;
+        Ok(tree.check_pool(&data)?)
+    }
+
+    /// Latest slot
+    pub fn slot(&self) -> Result<u64> {
+        Ok(self.rpc.get_slot()?)
+    }
+
+    /// Send `instructions` in one transaction paid for by the payer
+    pub fn send(&self, instructions: &[Instruction]) -> Result<Signature> {
+        self.send_with_budget(instructions, None)
+    }
+
+    /// Send a spend, with the compute budget its proof verification needs
+    pub fn send_spend(&self, instructions: &[Instruction]) -> Result<Signature> {
+        self.send_with_budget(instructions, Some(SPEND_COMPUTE_UNITS))
+    }
+
+    fn send_with_budget(
+        &self,
+        instructions: &[Instruction],
+        compute_units: Option<u32>,
+    ) -> Result<Signature> {
+        let mut all = Vec::with_capacity(instructions.len() + 1);
+        all.extend(compute_units.map(ComputeBudgetInstruction::set_compute_unit_limit));
+        all.extend_from_slice(instructions);
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let transaction = Transaction::new_signed_with_payer(
+            &all,
+            Some(&self.payer.pubkey()),
+            &[&self.payer],
+            blockhash,
+        );
+        Ok(self.rpc.send_and_confirm_transaction(&transaction)?)
+    }
+}
@@ -0,0 +1,340 @@
+//! Subcommands
+//!
+//! Every command that creates a note saves it to the wallet before sending
+//! the transaction creating it, so a note can't land on chain without the
+//! wallet knowing its opening.
+//!
+//! Spends are submitted by the wallet's own keypair, which links that
+//! keypair to the spend for anyone watching the chain. Withdraw through a
+//! relayer (see `veil-relayer`) when that matters.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, Write};
+use std::path::Path;
+
+use ark_bn254::Bn254;
+use ark_groth16::ProvingKey;
+use ark_serialize::CanonicalDeserialize;
+use rand::rngs::OsRng;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use veil_program::state::PrivacyPool;
+use veil_sdk::instructions::{self, instruction};
+use veil_sdk::backup;
+use veil_sdk::prover::{self, UnshieldParams};
+use veil_sdk::{pda, KdfParams, Spend, TreeSync};
+
+use crate::chain::Chain;
+use crate::error::{CliError, Result};
+use crate::wallet::{StoredNote, Wallet};
+
+/// Slots a spend proof stays valid for, leaving time to prove and send it
+const EXPIRY_SLOTS: u64 = 1_000;
+
+/// Load the proving key `<name>.pk` written by `export-vks`
+fn proving_key(keys_dir: &Path, name: &str) -> Result<ProvingKey<Bn254>> {
+    let path = keys_dir.join(format!("{name}.pk"));
+    let file = File::open(&path)
+        .map_err(|err| CliError::ProvingKey(path.display().to_string(), err.to_string()))?;
+    ProvingKey::deserialize_compressed(BufReader::new(file))
+        .map_err(|err| CliError::ProvingKey(path.display().to_string(), err.to_string()))
+}
+
+/// The chunk the pool's next leaf is archived to, and the archive of the
+/// pool's tree when that leaf rolls a full tree over
+///
+/// The program creates both if they don't exist yet.
+fn next_chunk(pool: &Pubkey, state: &PrivacyPool) -> (Pubkey, Option<Pubkey>) {
+    if state.has_room_for(1) {
+        let chunk = pda::commitment_chunk(pool, state.tree_epoch, state.current_chunk_index());
+        (chunk, None)
+    } else {
+        let chunk = pda::commitment_chunk(pool, state.tree_epoch + 1, 0);
+        (chunk, Some(pda::tree_epoch(pool, state.tree_epoch)))
+    }
+}
+
+/// The payer's relayer bond, if it has one
+fn relayer_bond(chain: &Chain) -> Result<Option<Pubkey>> {
+    let bond = pda::relayer_bond(&chain.payer().pubkey());
+    Ok(chain.exists(&bond)?.then_some(bond))
+}
+
+/// Record where note `id` was inserted, if it was
+fn sync_note(wallet: &mut Wallet, chain: &Chain, id: usize) -> Result<bool> {
+    let note = wallet.note(id)?;
+    let commitment = note.note()?.commitment_bytes();
+    let Some(record) = chain.commitment_record(&note.pool, &commitment)? else {
+        return Ok(false);
+    };
+    let note = &mut wallet.notes[id];
+    note.leaf_index = Some(record.leaf_index);
+    note.tree_epoch = Some(record.tree_epoch);
+    Ok(true)
+}
+
+/// The wallet's copy of tree `tree_epoch` of `pool`, synced and cached
+/// in `trees/` next to the wallet
+fn synced_tree(
+    wallet_path: &Path,
+    chain: &Chain,
+    pool: &Pubkey,
+    tree_epoch: u64,
+) -> Result<TreeSync> {
+    let path = wallet_path.with_file_name("trees").join(format!("{pool}-{tree_epoch}.bin"));
+    // A missing or unreadable cache is rebuilt from the chunks
+    let mut tree = TreeSync::load(&path)
+        .ok()
+        .filter(|tree| tree.pool() == pool && tree.tree_epoch() == tree_epoch)
+        .unwrap_or_else(|| TreeSync::new(*pool, tree_epoch));
+    chain.sync_tree(&mut tree)?;
+    tree.save(&path)?;
+    Ok(tree)
+}
+
+/// The witness spending note `id`, and its pool's state
+fn prepare_spend(
+    wallet: &Wallet,
+    wallet_path: &Path,
+    chain: &Chain,
+    id: usize,
+) -> Result<(StoredNote, PrivacyPool, Spend)> {
+    let note = wallet.spendable(id)?.clone();
+    let state = chain.pool(&note.pool)?;
+    if note.tree_epoch != Some(state.tree_epoch) {
+        return Err(CliError::UnsupportedPool(
+            note.pool.to_string(),
+            "spending notes of archived trees is not supported".into(),
+        ));
+    }
+    let tree = synced_tree(wallet_path, chain, &note.pool, state.tree_epoch)?;
+    let spend = tree.witness(note.note()?)?;
+    Ok((note, state, spend))
+}
+
+/// Create a wallet at `path`
+pub fn init(path: &Path, seed: Option<[u8; 32]>) -> Result<()> {
+    if path.exists() {
+        return Err(CliError::Wallet(format!("{} already exists", path.display())));
+    }
+    let seed = seed.unwrap_or_else(rand::random);
+    Wallet::new(seed).save(path)?;
+    println!("Created wallet {}", path.display());
+    Ok(())
+}
+
+/// Shield a new note into the pool of `denomination` and `mint`
+pub fn shield(
+    wallet: &mut Wallet,
+    wallet_path: &Path,
+    chain: &Chain,
+    denomination: u64,
+    mint: Pubkey,
+    amount: Option<u64>,
+) -> Result<()> {
+    let amount = match (denomination, amount) {
+        (0, Some(amount)) => amount,
+        (0, None) => return Err(CliError::Usage("--amount is required for custom pools".into())),
+        (denomination, None) => denomination,
+        (denomination, Some(amount)) if amount == denomination => denomination,
+        _ => return Err(CliError::Usage("--amount must match the pool's denomination".into())),
+    };
+    let (pool, _) = pda::pool(denomination, &mint);
+    let state = chain.pool(&pool)?;
+    let depositor = chain.payer().pubkey();
+
+    let note = wallet.derive_note(amount, &mint);
+    let commitment = note.commitment_bytes();
+    let id = wallet.push(StoredNote::new(pool, mint, &note));
+    wallet.save(wallet_path)?;
+
+    let (chunk, tree_archive) = next_chunk(&pool, &state);
+    let ix = if mint == Pubkey::default() {
+        let mut accounts =
+            veil_cpi::accounts::ShieldSol::new(pool, depositor, &commitment, Some(chunk));
+        accounts.tree_archive = tree_archive;
+        instructions::build(
+            &accounts,
+            &instruction::ShieldSol { commitment, amount, encrypted_note: None },
+        )
+    } else {
+        let mut accounts =
+            veil_cpi::accounts::Shield::new(pool, mint, depositor, &commitment, Some(chunk));
+        accounts.tree_archive = tree_archive;
+        instructions::build(
+            &accounts,
+            &instruction::Shield { commitment, amount, encrypted_note: None },
+        )
+    };
+    let signature = chain.send(&[ix])?;
+
+    sync_note(wallet, chain, id)?;
+    wallet.save(wallet_path)?;
+    println!("Shielded note {id} of {amount} into {pool}: {signature}");
+    Ok(())
+}
+
+/// Spend note `id` into a fresh note of the same owner and amount
+///
+/// The transfer circuit keeps the note's owner, so this unlinks a note
+/// from its deposit rather than paying another wallet.
+pub fn transfer(
+    wallet: &mut Wallet,
+    wallet_path: &Path,
+    chain: &Chain,
+    keys_dir: &Path,
+    id: usize,
+) -> Result<()> {
+    let (note, state, spend) = prepare_spend(wallet, wallet_path, chain, id)?;
+    let pk = proving_key(keys_dir, "transfer")?;
+    let expiry_slot = chain.slot()? + EXPIRY_SLOTS;
+    let proof =
+        prover::prove_transfer(&pk, &spend, &note.pool, &note.mint, expiry_slot, &mut OsRng)?;
+
+    let output = wallet.push(StoredNote::new(note.pool, note.mint, &proof.output));
+    wallet.save(wallet_path)?;
+
+    let (chunk, tree_archive) = next_chunk(&note.pool, &state);
+    let mut accounts = veil_cpi::accounts::Transfer::new(
+        note.pool,
+        &proof.nullifier,
+        state.vk_version,
+        chain.payer().pubkey(),
+        Some(chunk),
+    );
+    accounts.tree_archive = tree_archive;
+    accounts.relayer_bond = relayer_bond(chain)?;
+    let ix = instructions::build(&accounts, &proof.transfer(None));
+    let signature = chain.send_spend(&[ix])?;
+
+    wallet.notes[id].spent = true;
+    sync_note(wallet, chain, output)?;
+    wallet.save(wallet_path)?;
+    println!("Transferred note {id} into note {output}: {signature}");
+    Ok(())
+}
+
+/// Withdraw all of note `id` to `recipient`
+pub fn unshield(
+    wallet: &mut Wallet,
+    wallet_path: &Path,
+    chain: &Chain,
+    keys_dir: &Path,
+    id: usize,
+    recipient: Pubkey,
+) -> Result<()> {
+    let (note, state, spend) = prepare_spend(wallet, wallet_path, chain, id)?;
+    let pk = proving_key(keys_dir, "unshield")?;
+    let relayer = chain.payer().pubkey();
+    let params = UnshieldParams {
+        recipient,
+        relayer,
+        fee: 0,
+        refund: 0,
+        expiry_slot: chain.slot()? + EXPIRY_SLOTS,
+    };
+    let proof = prover::prove_unshield(&pk, &spend, &note.pool, &note.mint, &params, &mut OsRng)?;
+
+    let relayer_bond = relayer_bond(chain)?;
+    let ix = if note.mint == Pubkey::default() {
+        let mut accounts = veil_cpi::accounts::UnshieldSol::new(
+            note.pool,
+            &proof.nullifier,
+            state.vk_version,
+            recipient,
+            state.treasury,
+            relayer,
+        );
+        accounts.relayer_bond = relayer_bond;
+        instructions::build(&accounts, &proof.unshield_sol())
+    } else {
+        let mut accounts = veil_cpi::accounts::Unshield::new(
+            note.pool,
+            note.mint,
+            &proof.nullifier,
+            state.vk_version,
+            recipient,
+            state.treasury,
+            relayer,
+        );
+        accounts.relayer_bond = relayer_bond;
+        instructions::build(&accounts, &proof.unshield())
+    };
+    let signature = chain.send_spend(&[ix])?;
+
+    wallet.notes[id].spent = true;
+    wallet.save(wallet_path)?;
+    println!("Withdrew note {id} ({}) to {recipient}: {signature}", note.amount);
+    Ok(())
+}
+
+/// List the wallet's unspent notes and their total per pool
+pub fn balance(wallet: &Wallet) {
+    let mut totals: Vec<(Pubkey, u64)> = Vec::new();
+    for (id, note) in wallet.notes.iter().enumerate().filter(|(_, note)| !note.spent) {
+        let status = if note.is_spendable() { "" } else { " (not synced)" };
+        println!("{id}: {} in {}{status}", note.amount, note.pool);
+        if note.is_spendable() {
+            match totals.iter_mut().find(|(pool, _)| *pool == note.pool) {
+                Some((_, total)) => *total += note.amount,
+                None => totals.push((note.pool, note.amount)),
+            }
+        }
+    }
+    for (pool, total) in totals {
+        println!("{pool}: {total}");
+    }
+}
+
+/// Locate the wallet's notes that aren't synced yet
+pub fn sync(wallet: &mut Wallet, wallet_path: &Path, chain: &Chain) -> Result<()> {
+    let pending: Vec<usize> = (0..wallet.notes.len())
+        .filter(|&id| !wallet.notes[id].spent && wallet.notes[id].leaf_index.is_none())
+        .collect();
+    let mut synced = 0;
+    for id in &pending {
+        if sync_note(wallet, chain, *id)? {
+            synced += 1;
+        }
+    }
+    wallet.save(wallet_path)?;
+    println!("Synced {synced} of {} pending notes", pending.len());
+    Ok(())
+}
+
+/// Write every note of the wallet to a backup at `path`, encrypted under
+/// `password`
+pub fn export_notes(wallet: &Wallet, path: &Path, password: &str) -> Result<()> {
+    if path.exists() {
+        return Err(CliError::Wallet(format!("{} already exists", path.display())));
+    }
+    let notes = wallet.notes.iter().map(StoredNote::to_backup).collect::<Result<Vec<_>>>()?;
+    let backup = backup::export_notes(&notes, password, &KdfParams::default(), &mut OsRng)?;
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(&backup)?;
+    println!("Exported {} notes to {}", notes.len(), path.display());
+    Ok(())
+}
+
+/// Add the notes of the backup at `path` to the wallet
+pub fn import_notes(
+    wallet: &mut Wallet,
+    wallet_path: &Path,
+    path: &Path,
+    password: &str,
+) -> Result<()> {
+    let notes = backup::import_notes(&fs::read(path)?, password)?;
+    let (added, skipped) = wallet.import(&notes);
+    wallet.save(wallet_path)?;
+    println!("Imported {added} new notes of {} from {}", notes.len(), path.display());
+    if skipped > 0 {
+        println!("Skipped {skipped} stealth or multi-asset notes the wallet can't hold");
+    }
+    Ok(())
+}
@@ -0,0 +1,60 @@
+//! CLI errors
+
+use solana_client::client_error::ClientError;
+use thiserror::Error;
+use veil_sdk::SdkError;
+
+/// Errors of the wallet
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("{0}")]
+    Usage(String),
+
+    #[error("Wallet error: {0}")]
+    Wallet(String),
+
+    #[error("Unknown note {0}")]
+    UnknownNote(usize),
+
+    #[error("Note {0} is not synced yet; run `veil sync`")]
+    NotSynced(usize),
+
+    #[error("Note {0} is already spent")]
+    Spent(usize),
+
+    #[error("Pool {0} is not supported: {1}")]
+    UnsupportedPool(String, String),
+
+    #[error("Account {0} not found")]
+    AccountNotFound(String),
+
+    #[error("Invalid account {0}: {1}")]
+    InvalidAccount(String, String),
+
+    #[error("Cannot load proving key {0}: {1}")]
+    ProvingKey(String, String),
+
+    #[error(transparent)]
+    Sdk(#[from] SdkError),
+
+    /// Boxed: the client error is large enough that every `Result` would
+    /// carry its size
+    #[error("RPC error: {0}")]
+    Rpc(Box<ClientError>),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl From<ClientError> for CliError {
+    fn from(err: ClientError) -> Self {
+        Self::Rpc(Box::new(err))
+    }
+}
+
+// Every command `Result` carries the error's size: keep it under
+// clippy's `result_large_err` limit by boxing large sources
+const _: () = assert!(std::mem::size_of::<CliError>() <= 128);
+
+/// Result type of the wallet
+pub type Result<T> = std::result::Result<T, CliError>;
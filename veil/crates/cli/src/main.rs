@@ -0,0 +1,250 @@
+//! Veil CLI
+//!
+//! Command-line wallet for Veil privacy pools: shields deposits, spends
+//! notes and keeps their openings in a wallet file.
+//!
+//! Usage: veil [OPTIONS] <COMMAND>
+//!
+//! Commands:
+//!   init [--seed <hex>]                           Create a wallet
+//!   shield --denomination <n> [--mint <pubkey>] [--amount <n>]
+//!                                                 Shield a new note
+//!   transfer <note>                               Spend a note into a fresh one
+//!   unshield <note> --recipient <pubkey>          Withdraw a note
+//!   balance                                       List unspent notes
+//!   sync                                          Locate pending notes on chain
+//!   export-notes <file>                           Write a password-encrypted
+//!                                                 backup of every note
+//!   import-notes <file>                           Add the notes of a backup
+//!
+//! Options (or their environment variables):
+//!   --wallet <path>   Wallet file (`VEIL_WALLET`, default ~/.veil/wallet.json);
+//!                     synced trees are cached in `trees/` next to it
+//!   --keypair <path>  Keypair paying for transactions (`VEIL_KEYPAIR`,
+//!                     default ~/.config/solana/id.json)
+//!   --url <url>       JSON-RPC endpoint (`VEIL_RPC_URL`, default localnet)
+//!   --keys <dir>      Proving keys from `export-vks` (`VEIL_KEYS`, default
+//!                     target/vks)
+//!
+//! Backup passwords are prompted for, or read from `VEIL_PASSWORD`.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::read_keypair_file;
+
+mod chain;
+mod commands;
+mod error;
+mod wallet;
+
+use chain::Chain;
+use error::{CliError, Result};
+use wallet::Wallet;
+
+const USAGE: &str = "Usage: veil [--wallet <path>] [--keypair <path>] [--url <url>] [--keys <dir>] \
+<init|shield|transfer|unshield|balance|sync|export-notes|import-notes> [ARGS]";
+
+/// Parsed command line: the command, its positional arguments and the
+/// `--name value` options
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Args {
+    command: String,
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if let Some(name) = arg.strip_prefix("--") {
+                let value = args
+                    .next()
+                    .ok_or_else(|| CliError::Usage(format!("--{name} needs a value")))?;
+                parsed.options.insert(name.to_string(), value);
+            } else if parsed.command.is_empty() {
+                parsed.command = arg;
+            } else {
+                parsed.positional.push(arg);
+            }
+        }
+        if parsed.command.is_empty() {
+            return Err(CliError::Usage(USAGE.into()));
+        }
+        Ok(parsed)
+    }
+
+    /// Option `name`, parsed
+    fn option<T: FromStr>(&self, name: &str) -> Result<Option<T>> {
+        self.options
+            .get(name)
+            .map(|value| {
+                value.parse().map_err(|_| CliError::Usage(format!("invalid --{name}: {value}")))
+            })
+            .transpose()
+    }
+
+    /// Option `name`, required
+    fn required<T: FromStr>(&self, name: &str) -> Result<T> {
+        self.option(name)?
+            .ok_or_else(|| CliError::Usage(format!("{} needs --{name}", self.command)))
+    }
+
+    /// The note ID given as first positional argument
+    fn note_id(&self) -> Result<usize> {
+        self.positional
+            .first()
+            .and_then(|id| id.parse().ok())
+            .ok_or_else(|| CliError::Usage(format!("{} needs a note ID", self.command)))
+    }
+
+    /// The file given as first positional argument
+    fn file(&self) -> Result<PathBuf> {
+        self.positional
+            .first()
+            .map(PathBuf::from)
+            .ok_or_else(|| CliError::Usage(format!("{} needs a file", self.command)))
+    }
+
+    /// Option `name`, else environment variable `var`, else `default`
+    fn path(&self, name: &str, var: &str, default: &str) -> PathBuf {
+        self.options
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(var).ok())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home().join(default))
+    }
+}
+
+fn home() -> PathBuf {
+    std::env::var("HOME").map(PathBuf::from).unwrap_or_default()
+}
+
+/// The backup password, from `VEIL_PASSWORD` or prompted for (twice
+/// when `confirm`)
+fn password(confirm: bool) -> Result<String> {
+    if let Ok(password) = std::env::var("VEIL_PASSWORD") {
+        return Ok(password);
+    }
+    let password = rpassword::prompt_password("Backup password: ")?;
+    if password.is_empty() {
+        return Err(CliError::Usage("the backup password can't be empty".into()));
+    }
+    if confirm && rpassword::prompt_password("Repeat password: ")? != password {
+        return Err(CliError::Usage("passwords don't match".into()));
+    }
+    Ok(password)
+}
+
+fn chain(args: &Args) -> Result<Chain> {
+    let keypair_path = args.path("keypair", "VEIL_KEYPAIR", ".config/solana/id.json");
+    let payer = read_keypair_file(&keypair_path).map_err(|err| {
+        CliError::Usage(format!("cannot read keypair {}: {err}", keypair_path.display()))
+    })?;
+    let url = args
+        .options
+        .get("url")
+        .cloned()
+        .or_else(|| std::env::var("VEIL_RPC_URL").ok())
+        .unwrap_or_else(|| "http://127.0.0.1:8899".to_string());
+    Ok(Chain::new(url, payer))
+}
+
+fn run(args: Args) -> Result<()> {
+    let wallet_path = args.path("wallet", "VEIL_WALLET", ".veil/wallet.json");
+    if args.command == "init" {
+        let seed = args
+            .options
+            .get("seed")
+            .map(|seed| {
+                hex::decode(seed)
+                    .ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or_else(|| CliError::Usage("--seed must be 32 hex bytes".into()))
+            })
+            .transpose()?;
+        return commands::init(&wallet_path, seed);
+    }
+
+    let mut wallet = Wallet::load(&wallet_path)?;
+    let keys_dir = args
+        .options
+        .get("keys")
+        .cloned()
+        .or_else(|| std::env::var("VEIL_KEYS").ok())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("target/vks"));
+    match args.command.as_str() {
+        "shield" => commands::shield(
+            &mut wallet,
+            &wallet_path,
+            &chain(&args)?,
+            args.required("denomination")?,
+            args.option::<Pubkey>("mint")?.unwrap_or_default(),
+            args.option("amount")?,
+        ),
+        "transfer" => {
+            let id = args.note_id()?;
+            commands::transfer(&mut wallet, &wallet_path, &chain(&args)?, &keys_dir, id)
+        }
+        "unshield" => {
+            let id = args.note_id()?;
+            let recipient = args.required("recipient")?;
+            commands::unshield(&mut wallet, &wallet_path, &chain(&args)?, &keys_dir, id, recipient)
+        }
+        "balance" => {
+            commands::balance(&wallet);
+            Ok(())
+        }
+        "sync" => commands::sync(&mut wallet, &wallet_path, &chain(&args)?),
+        "export-notes" => commands::export_notes(&wallet, &args.file()?, &password(true)?),
+        "import-notes" => {
+            let path = args.file()?;
+            commands::import_notes(&mut wallet, &wallet_path, &path, &password(false)?)
+        }
+        command => Err(CliError::Usage(format!("unknown command {command}\n{USAGE}"))),
+    }
+}
+
+fn main() -> ExitCode {
+    match Args::parse(std::env::args().skip(1)).and_then(run) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Result<Args> {
+        Args::parse(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_parse_command_options_and_positionals() {
+        let recipient = Pubkey::default();
+        let parsed = args(&format!("--url http://rpc unshield 3 --recipient {recipient}")).unwrap();
+        assert_eq!(parsed.command, "unshield");
+        assert_eq!(parsed.note_id().unwrap(), 3);
+        assert_eq!(parsed.required::<Pubkey>("recipient").unwrap(), recipient);
+        assert_eq!(parsed.option::<u64>("amount").unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(args(""), Err(CliError::Usage(_))));
+        assert!(matches!(args("shield --denomination"), Err(CliError::Usage(_))));
+        let parsed = args("shield --denomination lots").unwrap();
+        assert!(parsed.required::<u64>("denomination").is_err());
+    }
+}
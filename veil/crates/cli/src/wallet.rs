@@ -0,0 +1,308 @@
+//! Wallet file
+//!
+//! A JSON file holding the wallet's seed and every note it owns. Note
+//! secrets and blindings of shielded notes derive from the seed and the
+//! note's index; notes created by transfers keep their owner's secret and
+//! take a random blinding. The openings are stored with each note either
+//! way, so the file alone is enough to spend them; keep it private.
+
+use std::fs;
+use std::path::Path;
+
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use solana_sdk::pubkey::Pubkey;
+use veil_sdk::note::{asset_id, fr_from_bytes, fr_to_bytes};
+use veil_sdk::{BackupNote, Note};
+
+use crate::error::{CliError, Result};
+
+/// Domain separator of note secrets derived from the seed
+const NOTE_SECRET_DOMAIN: &[u8] = b"VEIL_WALLET_NOTE_SECRET";
+
+/// Domain separator of note blindings derived from the seed
+const NOTE_BLINDING_DOMAIN: &[u8] = b"VEIL_WALLET_NOTE_BLINDING";
+
+/// Derive the field element `domain` assigns to note `index` of `seed`
+fn derive(domain: &[u8], seed: &[u8; 32], index: u64) -> Fr {
+    let hash = Sha256::new()
+        .chain_update(domain)
+        .chain_update(seed)
+        .chain_update(index.to_le_bytes())
+        .finalize();
+    Fr::from_le_bytes_mod_order(&hash)
+}
+
+/// 32-byte values as hex strings
+mod hex32 {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        let value = String::deserialize(deserializer)?;
+        hex::decode(&value)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| serde::de::Error::custom("expected 32 hex bytes"))
+    }
+}
+
+/// A note the wallet owns
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredNote {
+    pub pool: Pubkey,
+    /// Mint of an SPL pool, the default pubkey for SOL
+    pub mint: Pubkey,
+    pub amount: u64,
+    #[serde(with = "hex32")]
+    pub secret: [u8; 32],
+    #[serde(with = "hex32")]
+    pub blinding: [u8; 32],
+    /// Where the note was inserted, once synced
+    pub leaf_index: Option<u64>,
+    pub tree_epoch: Option<u64>,
+    pub spent: bool,
+}
+
+impl StoredNote {
+    /// A note of `pool` not yet inserted
+    pub fn new(pool: Pubkey, mint: Pubkey, note: &Note) -> Self {
+        Self {
+            pool,
+            mint,
+            amount: note.amount,
+            secret: fr_to_bytes(&note.secret),
+            blinding: fr_to_bytes(&note.blinding),
+            leaf_index: None,
+            tree_epoch: None,
+            spent: false,
+        }
+    }
+
+    /// The note's opening
+    pub fn note(&self) -> Result<Note> {
+        Ok(Note::new(
+            fr_from_bytes(&self.secret)?,
+            self.amount,
+            fr_from_bytes(&self.blinding)?,
+            asset_id(&self.mint),
+        ))
+    }
+
+    /// Whether the note is synced and unspent
+    pub fn is_spendable(&self) -> bool {
+        self.leaf_index.is_some() && !self.spent
+    }
+
+    /// The note as backups hold it
+    pub fn to_backup(&self) -> Result<BackupNote> {
+        Ok(BackupNote {
+            pool: self.pool,
+            mint: self.mint,
+            note: self.note()?,
+            tree_epoch: self.tree_epoch,
+            leaf_index: self.leaf_index,
+            spent: self.spent,
+        })
+    }
+
+    /// A backed up note, `None` if the wallet can't hold it: stealth notes,
+    /// and notes whose asset isn't their pool's mint
+    pub fn from_backup(backup: &BackupNote) -> Option<Self> {
+        let note = &backup.note;
+        if note.stealth_tweak.is_some() || note.asset_id != asset_id(&backup.mint) {
+            return None;
+        }
+        Some(Self {
+            pool: backup.pool,
+            mint: backup.mint,
+            amount: note.amount,
+            secret: fr_to_bytes(&note.secret),
+            blinding: fr_to_bytes(&note.blinding),
+            leaf_index: backup.leaf_index,
+            tree_epoch: backup.tree_epoch,
+            spent: backup.spent,
+        })
+    }
+}
+
+/// Seed and notes of a wallet
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Wallet {
+    #[serde(with = "hex32")]
+    pub seed: [u8; 32],
+    /// Notes derived from the seed so far
+    pub next_index: u64,
+    pub notes: Vec<StoredNote>,
+}
+
+impl Wallet {
+    /// An empty wallet of `seed`
+    pub fn new(seed: [u8; 32]) -> Self {
+        Self { seed, next_index: 0, notes: Vec::new() }
+    }
+
+    /// Read the wallet at `path`
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read(path).map_err(|err| {
+            CliError::Wallet(format!("{}: {err}; run `veil init`", path.display()))
+        })?;
+        serde_json::from_slice(&contents)
+            .map_err(|err| CliError::Wallet(format!("{}: {err}", path.display())))
+    }
+
+    /// Write the wallet to `path`, readable by its owner only
+    ///
+    /// The file is replaced in one rename, so a crash can't leave notes
+    /// half written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = serde_json::to_vec_pretty(self)
+            .map_err(|err| CliError::Wallet(err.to_string()))?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, contents)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp, fs::Permissions::from_mode(0o600))?;
+        }
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// The next note derived from the seed, holding `amount` of `mint`
+    pub fn derive_note(&mut self, amount: u64, mint: &Pubkey) -> Note {
+        let index = self.next_index;
+        self.next_index += 1;
+        Note::new(
+            derive(NOTE_SECRET_DOMAIN, &self.seed, index),
+            amount,
+            derive(NOTE_BLINDING_DOMAIN, &self.seed, index),
+            asset_id(mint),
+        )
+    }
+
+    /// Store a note, returning its ID
+    pub fn push(&mut self, note: StoredNote) -> usize {
+        self.notes.push(note);
+        self.notes.len() - 1
+    }
+
+    /// The note with ID `id`
+    pub fn note(&self, id: usize) -> Result<&StoredNote> {
+        self.notes.get(id).ok_or(CliError::UnknownNote(id))
+    }
+
+    /// Add backed up notes, returning how many were new and how many the
+    /// wallet can't hold
+    ///
+    /// Notes the wallet already has take the backup's location and spent
+    /// status where the wallet lacks them, so importing an older backup
+    /// never unspends a note.
+    pub fn import(&mut self, backup: &[BackupNote]) -> (usize, usize) {
+        let (mut added, mut skipped) = (0, 0);
+        for note in backup {
+            let Some(note) = StoredNote::from_backup(note) else {
+                skipped += 1;
+                continue;
+            };
+            let existing = self.notes.iter_mut().find(|stored| {
+                stored.pool == note.pool
+                    && stored.mint == note.mint
+                    && stored.amount == note.amount
+                    && stored.secret == note.secret
+                    && stored.blinding == note.blinding
+            });
+            match existing {
+                Some(stored) => {
+                    stored.spent |= note.spent;
+                    if stored.leaf_index.is_none() {
+                        stored.leaf_index = note.leaf_index;
+                        stored.tree_epoch = note.tree_epoch;
+                    }
+                }
+                None => {
+                    self.notes.push(note);
+                    added += 1;
+                }
+            }
+        }
+        (added, skipped)
+    }
+
+    /// The note with ID `id`, if it can be spent
+    pub fn spendable(&self, id: usize) -> Result<&StoredNote> {
+        let note = self.note(id)?;
+        if note.spent {
+            return Err(CliError::Spent(id));
+        }
+        if note.leaf_index.is_none() {
+            return Err(CliError::NotSynced(id));
+        }
+        Ok(note)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notes_derive_from_seed() {
+        let mut wallet = Wallet::new([7u8; 32]);
+        let first = wallet.derive_note(5, &Pubkey::default());
+        let second = wallet.derive_note(5, &Pubkey::default());
+        assert_ne!(first.secret, second.secret);
+
+        // The same seed derives the same notes
+        let mut restored = Wallet::new([7u8; 32]);
+        assert_eq!(restored.derive_note(5, &Pubkey::default()), first);
+    }
+
+    #[test]
+    fn test_wallet_round_trips() {
+        let dir = std::env::temp_dir().join(format!("veil-wallet-{}", std::process::id()));
+        let path = dir.join("wallet.json");
+        let mut wallet = Wallet::new([1u8; 32]);
+        let note = wallet.derive_note(1_000, &Pubkey::default());
+        let id = wallet.push(StoredNote::new(Pubkey::new_unique(), Pubkey::default(), &note));
+        wallet.save(&path).unwrap();
+
+        let loaded = Wallet::load(&path).unwrap();
+        assert_eq!(loaded.next_index, 1);
+        assert_eq!(loaded.note(id).unwrap().note().unwrap(), note);
+        assert!(matches!(loaded.spendable(id), Err(CliError::NotSynced(0))));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_import_merges_backed_up_notes() {
+        let mut wallet = Wallet::new([2u8; 32]);
+        let pool = Pubkey::new_unique();
+        let note = wallet.derive_note(1_000, &Pubkey::default());
+        wallet.push(StoredNote::new(pool, Pubkey::default(), &note));
+
+        let mut backup = vec![wallet.notes[0].to_backup().unwrap()];
+        backup[0].leaf_index = Some(3);
+        backup[0].tree_epoch = Some(0);
+        let other = Wallet::new([3u8; 32]).derive_note(5, &Pubkey::default());
+        let mut other = StoredNote::new(pool, Pubkey::default(), &other).to_backup().unwrap();
+        other.spent = true;
+        backup.push(other.clone());
+        other.note = other.note.with_stealth_tweak(Fr::from(1u64));
+        backup.push(other);
+
+        assert_eq!(wallet.import(&backup), (1, 1));
+        assert_eq!(wallet.notes.len(), 2);
+        assert!(wallet.spendable(0).is_ok());
+        assert!(wallet.notes[1].spent);
+        assert_eq!(wallet.import(&backup), (0, 1));
+    }
+}
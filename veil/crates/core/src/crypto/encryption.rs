@@ -19,6 +19,13 @@
 //! 1. Recipient computes shared secret = ECDH(private_key, R)
 //! 2. Derive symmetric key from shared secret
 //! 3. Decrypt ciphertext using ChaCha20-Poly1305
+//!
+//! Wire format, sharing its header with the SDK's x25519 notes
+//! (`veil_sdk::encryption`, version 1) so a reader tells them apart:
+//!
+//! ```text
+//! ephemeral_key (32) || version (1) || ciphertext (64)
+//! ```
 
 use ark_bn254::Fr;
 use ark_ec::{CurveGroup, Group};
@@ -29,7 +36,7 @@ use sha2::{Digest, Sha256};
 use thiserror::Error;
 
 /// The curve used for encryption (same as commitment curve)
-type G1 = ark_bn254::G1Projective;
+pub(crate) type G1 = ark_bn254::G1Projective;
 type G1Affine = ark_bn254::G1Affine;
 
 /// Domain separator for key derivation
@@ -44,8 +51,14 @@ pub const CIPHERTEXT_SIZE: usize = NOTE_DATA_SIZE + 16; // + auth tag
 /// Size of the ephemeral public key
 pub const EPHEMERAL_KEY_SIZE: usize = 32;
 
+/// Version byte of this format, following the ephemeral key
+pub const ENCRYPTION_VERSION: u8 = 2;
+
+/// Size of the header: ephemeral key and version
+pub const HEADER_SIZE: usize = EPHEMERAL_KEY_SIZE + 1;
+
 /// Total size of an encrypted note
-pub const ENCRYPTED_NOTE_SIZE: usize = EPHEMERAL_KEY_SIZE + CIPHERTEXT_SIZE;
+pub const ENCRYPTED_NOTE_SIZE: usize = HEADER_SIZE + CIPHERTEXT_SIZE;
 
 /// Errors for encryption operations
 #[derive(Error, Debug)]
@@ -58,6 +71,8 @@ pub enum EncryptionError {
     DecryptionFailed,
     #[error("Invalid ciphertext length")]
     InvalidCiphertextLength,
+    #[error("Unsupported encrypted note version {0}")]
+    UnsupportedVersion(u8),
     #[error("Serialization error: {0}")]
     SerializationError(String),
 }
@@ -117,12 +132,21 @@ impl EncryptedNote {
     pub fn to_bytes(&self) -> [u8; ENCRYPTED_NOTE_SIZE] {
         let mut bytes = [0u8; ENCRYPTED_NOTE_SIZE];
         bytes[0..EPHEMERAL_KEY_SIZE].copy_from_slice(&self.ephemeral_key);
-        bytes[EPHEMERAL_KEY_SIZE..].copy_from_slice(&self.ciphertext);
+        bytes[EPHEMERAL_KEY_SIZE] = ENCRYPTION_VERSION;
+        bytes[HEADER_SIZE..].copy_from_slice(&self.ciphertext);
         bytes
     }
 
     /// Deserialize from bytes
+    ///
+    /// Notes of another version, such as the SDK's, fail with
+    /// `UnsupportedVersion`.
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, EncryptionError> {
+        if let Some(&version) = bytes.get(EPHEMERAL_KEY_SIZE) {
+            if version != ENCRYPTION_VERSION {
+                return Err(EncryptionError::UnsupportedVersion(version));
+            }
+        }
         if bytes.len() < ENCRYPTED_NOTE_SIZE {
             return Err(EncryptionError::InvalidCiphertextLength);
         }
@@ -131,7 +155,7 @@ impl EncryptedNote {
         ephemeral_key.copy_from_slice(&bytes[0..EPHEMERAL_KEY_SIZE]);
 
         let mut ciphertext = [0u8; CIPHERTEXT_SIZE];
-        ciphertext.copy_from_slice(&bytes[EPHEMERAL_KEY_SIZE..ENCRYPTED_NOTE_SIZE]);
+        ciphertext.copy_from_slice(&bytes[HEADER_SIZE..ENCRYPTED_NOTE_SIZE]);
 
         Ok(Self { ephemeral_key, ciphertext })
     }
@@ -202,16 +226,27 @@ pub fn encrypt_note(
     use ark_ff::UniformRand;
 
     // Parse recipient public key
-    let recipient = G1Affine::deserialize_compressed(recipient_pubkey.as_slice())
-        .map_err(|_| EncryptionError::InvalidPublicKey)?;
-    let recipient_point = G1::from(recipient);
+    let recipient_point = parse_public_key(recipient_pubkey)?;
 
     // Generate ephemeral keypair
     let ephemeral_private = Fr::rand(&mut OsRng);
+
+    encrypt_note_with_ephemeral(note_data, &recipient_point, &ephemeral_private)
+}
+
+/// Encrypt note data for a recipient under a given ephemeral private key
+///
+/// Stealth notes reuse the ephemeral key of their tweak derivation, so the
+/// one published key serves both.
+pub(crate) fn encrypt_note_with_ephemeral(
+    note_data: &NoteData,
+    recipient_point: &G1,
+    ephemeral_private: &Fr,
+) -> Result<EncryptedNote, EncryptionError> {
     let ephemeral_public = G1::generator() * ephemeral_private;
 
     // Compute shared secret via ECDH
-    let shared_secret = recipient_point * ephemeral_private;
+    let shared_secret = *recipient_point * ephemeral_private;
 
     // Derive symmetric key
     let symmetric_key = derive_symmetric_key(&shared_secret);
@@ -235,6 +270,13 @@ pub fn encrypt_note(
     })
 }
 
+/// Parse a compressed public key
+pub(crate) fn parse_public_key(bytes: &[u8; 32]) -> Result<G1, EncryptionError> {
+    let point = G1Affine::deserialize_compressed(bytes.as_slice())
+        .map_err(|_| EncryptionError::InvalidPublicKey)?;
+    Ok(G1::from(point))
+}
+
 /// Decrypt an encrypted note
 ///
 /// # Arguments
@@ -251,9 +293,7 @@ pub fn decrypt_note(
     let sk = Fr::from_le_bytes_mod_order(private_key);
 
     // Parse ephemeral public key
-    let ephemeral = G1Affine::deserialize_compressed(encrypted_note.ephemeral_key.as_slice())
-        .map_err(|_| EncryptionError::InvalidPublicKey)?;
-    let ephemeral_point = G1::from(ephemeral);
+    let ephemeral_point = parse_public_key(&encrypted_note.ephemeral_key)?;
 
     // Compute shared secret via ECDH
     let shared_secret = ephemeral_point * sk;
@@ -413,5 +453,14 @@ mod tests {
 
         assert_eq!(encrypted.ephemeral_key, restored.ephemeral_key);
         assert_eq!(encrypted.ciphertext, restored.ciphertext);
+        assert_eq!(bytes[EPHEMERAL_KEY_SIZE], ENCRYPTION_VERSION);
+
+        // Notes in another format's version are told apart by the header
+        let mut other = bytes;
+        other[EPHEMERAL_KEY_SIZE] = 1;
+        assert!(matches!(
+            EncryptedNote::from_bytes(&other),
+            Err(EncryptionError::UnsupportedVersion(1))
+        ));
     }
 }
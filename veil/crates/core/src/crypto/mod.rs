@@ -6,11 +6,13 @@ pub mod merkle;
 pub mod nullifier;
 pub mod poseidon;
 pub mod poseidon_constants;
+pub mod stealth;
 
 pub use commitment::{Commitment, CommitmentPoint};
 pub use encryption::{decrypt_note, encrypt_note, EncryptedNote, EncryptionKeypair, NoteData};
 pub use merkle::{MerklePath, PoseidonMerkleTree};
 #[allow(deprecated)]
 pub use nullifier::generate_nullifier_hash;
-pub use nullifier::{Note, Nullifier, SpendingKey};
+pub use nullifier::{asset_id, nullifier_domain, Note, Nullifier, SpendingKey};
 pub use poseidon::{poseidon_hash2, poseidon_hash_bytes, poseidon_hash_fields};
+pub use stealth::{send_to_stealth_address, StealthKeys, StealthMetaAddress, StealthNote};
@@ -25,6 +25,57 @@ const SPENDING_KEY_DOMAIN: &[u8] = b"NYX_SPENDING_KEY";
 /// Domain separator for nullifier derivation
 const NULLIFIER_DOMAIN: &[u8] = b"NYX_NULLIFIER";
 
+/// Tag binding a pool's nullifiers to the pool and its mint
+///
+/// Matches `verification::nullifier_domain` in the program:
+/// sha256(NULLIFIER_DOMAIN || pool || mint) with the top three bits cleared,
+/// read as a big-endian field element. Spend circuits take it as a public
+/// input and derive nullifiers as Poseidon(spending_key, Poseidon(leaf_index, tag)).
+/// Native SOL pools use the all-zero pubkey as their mint.
+pub fn nullifier_domain(pool: &[u8; 32], mint: &[u8; 32]) -> Fr {
+    // Imported per function: blake3's hasher implements `Digest` too when
+    // solana-program enables its traits, which would make the blake3
+    // `finalize` calls elsewhere in this module ambiguous
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    hasher.update(NULLIFIER_DOMAIN);
+    hasher.update(pool);
+    hasher.update(mint);
+    let mut tag: [u8; 32] = hasher.finalize().into();
+    tag[0] &= 0x1f;
+    Fr::from_be_bytes_mod_order(&tag)
+}
+
+/// Domain separator for asset IDs
+const ASSET_ID_DOMAIN: &[u8] = b"NYX_ASSET";
+
+/// Wrapped SOL mint (So11111111111111111111111111111111111111112)
+const WRAPPED_SOL_MINT: [u8; 32] = [
+    0x06, 0x9b, 0x88, 0x57, 0xfe, 0xab, 0x81, 0x84, 0xfb, 0x68, 0x7f, 0x63, 0x46, 0x18, 0xc0, 0x35,
+    0xda, 0xc4, 0x39, 0xdc, 0x1a, 0xeb, 0x3b, 0x55, 0x98, 0xa0, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x01,
+];
+
+/// Asset ID a note of `mint` commits to
+///
+/// Matches `verification::asset_id` in the program: 0 for native SOL (the
+/// all-zero pubkey) and wSOL, otherwise the first 8 bytes of
+/// sha256(ASSET_ID_DOMAIN || mint) read as a little-endian u64.
+pub fn asset_id(mint: &[u8; 32]) -> u64 {
+    use sha2::{Digest, Sha256};
+
+    if *mint == [0u8; 32] || *mint == WRAPPED_SOL_MINT {
+        return 0;
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(ASSET_ID_DOMAIN);
+    hasher.update(mint);
+    let hash: [u8; 32] = hasher.finalize().into();
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash[..8]);
+    u64::from_le_bytes(bytes)
+}
+
 #[derive(Error, Debug)]
 pub enum NullifierError {
     #[error("Invalid secret length: expected 32 bytes")]
@@ -398,4 +449,16 @@ mod tests {
         // Spending key should not be derivable back to secret
         // (Poseidon is a one-way function)
     }
+
+    #[test]
+    fn test_asset_id() {
+        // Native SOL and wSOL share asset 0
+        assert_eq!(asset_id(&[0u8; 32]), 0);
+        assert_eq!(asset_id(&WRAPPED_SOL_MINT), 0);
+
+        let mint = [7u8; 32];
+        assert_ne!(asset_id(&mint), 0);
+        assert_eq!(asset_id(&mint), asset_id(&mint));
+        assert_ne!(asset_id(&mint), asset_id(&[8u8; 32]));
+    }
 }
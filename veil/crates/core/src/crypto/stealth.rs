@@ -0,0 +1,264 @@
+//! Stealth Addresses
+//!
+//! Lets a sender create a note for a recipient from a published address
+//! alone, instead of exchanging note data out of band.
+//!
+//! A recipient publishes a stealth meta-address: a scan public key S = s*G
+//! and a spend key B = spending_key(b). B is the spending key of a secret b
+//! kept for stealth notes only; publishing it reveals nothing about notes
+//! the secret doesn't own.
+//!
+//! Sending:
+//! 1. Sender generates ephemeral keypair (r, R = r*G)
+//! 2. Shared secret = ECDH(r, S) = r * S
+//! 3. tweak = H(shared secret), reduced into the scalar field
+//! 4. The note is owned by the one-time key Poseidon(B, tweak) and its
+//!    opening is encrypted to S under the same ephemeral key
+//! 5. R is published with the commitment, as the encrypted note's ephemeral key
+//!
+//! Scanning:
+//! 1. Recipient computes shared secret = ECDH(s, R) and decrypts the note
+//! 2. Re-derives the tweak, and from it the one-time key
+//! 3. Spends with b and the tweak; the unshield circuits check
+//!    Poseidon(spending_key(b), tweak) owns the note
+//!
+//! Only the holder of b can spend the note. The scan key alone finds and
+//! opens incoming notes, so it can be handed to a watch-only wallet. As
+//! with any note created by someone else, the sender knows the one-time key
+//! and so can compute the note's nullifier.
+
+use ark_bn254::Fr;
+use ark_ec::CurveGroup;
+use ark_ff::{PrimeField, UniformRand};
+use ark_serialize::CanonicalSerialize;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+
+use super::encryption::{
+    decrypt_note, encrypt_note_with_ephemeral, parse_public_key, EncryptedNote,
+    EncryptionError, EncryptionKeypair, NoteData, G1,
+};
+use super::nullifier::SpendingKey;
+use super::poseidon::poseidon_hash2;
+
+/// Domain separator for tweak derivation
+const STEALTH_DOMAIN: &[u8] = b"NYX_STEALTH_V1";
+
+/// Serialized meta-address size: scan public key (32) + spend key (32)
+pub const META_ADDRESS_SIZE: usize = 64;
+
+/// Address a recipient publishes to receive stealth notes
+#[derive(Clone, Debug)]
+pub struct StealthMetaAddress {
+    /// Public key senders encrypt to and derive tweaks with
+    pub scan_pubkey: [u8; 32],
+    /// Spending key one-time keys are derived from
+    pub spend_key: SpendingKey,
+}
+
+impl StealthMetaAddress {
+    /// Serialize to bytes
+    pub fn to_bytes(&self) -> [u8; META_ADDRESS_SIZE] {
+        let mut bytes = [0u8; META_ADDRESS_SIZE];
+        bytes[..32].copy_from_slice(&self.scan_pubkey);
+        bytes[32..].copy_from_slice(&self.spend_key.to_bytes());
+        bytes
+    }
+
+    /// Deserialize from bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, EncryptionError> {
+        if bytes.len() != META_ADDRESS_SIZE {
+            return Err(EncryptionError::InvalidPublicKey);
+        }
+
+        let mut scan_pubkey = [0u8; 32];
+        scan_pubkey.copy_from_slice(&bytes[..32]);
+        // Reject scan keys that aren't curve points up front
+        let _ = parse_public_key(&scan_pubkey)?;
+
+        let mut spend_key = [0u8; 32];
+        spend_key.copy_from_slice(&bytes[32..]);
+
+        Ok(Self { scan_pubkey, spend_key: SpendingKey::from_bytes(&spend_key) })
+    }
+}
+
+/// A note created for a stealth address
+#[derive(Clone, Debug)]
+pub struct StealthNote {
+    /// One-time key owning the note
+    pub spending_key: SpendingKey,
+    /// The note's opening, encrypted to the scan key; its ephemeral key is
+    /// the one the recipient derives the tweak from
+    pub encrypted_note: EncryptedNote,
+}
+
+/// A stealth note found while scanning
+#[derive(Clone, Debug)]
+pub struct ReceivedStealthNote {
+    /// The note's opening
+    pub note_data: NoteData,
+    /// Tweak the one-time key was derived with, a witness when spending
+    pub tweak: Fr,
+    /// One-time key owning the note
+    pub spending_key: SpendingKey,
+}
+
+/// Keys of a stealth address recipient
+pub struct StealthKeys {
+    /// Scan keypair
+    scan: EncryptionKeypair,
+    /// Secret the spend key is derived from
+    spend_secret: [u8; 32],
+}
+
+impl StealthKeys {
+    /// Create from a scan secret and a spend secret
+    pub fn from_secrets(scan_secret: &[u8; 32], spend_secret: &[u8; 32]) -> Self {
+        Self {
+            scan: EncryptionKeypair::from_secret(scan_secret),
+            spend_secret: *spend_secret,
+        }
+    }
+
+    /// The meta-address to publish
+    pub fn meta_address(&self) -> StealthMetaAddress {
+        StealthMetaAddress {
+            scan_pubkey: self.scan.public_key_bytes(),
+            spend_key: SpendingKey::from_secret(&self.spend_secret),
+        }
+    }
+
+    /// The secret proving ownership of every one-time key
+    pub fn spend_secret(&self) -> &[u8; 32] {
+        &self.spend_secret
+    }
+
+    /// Try to open an encrypted note as a stealth note for these keys
+    ///
+    /// Returns `None` if the note wasn't sent to this address. Callers
+    /// should still check the note's commitment matches the one emitted
+    /// alongside it, as for any encrypted note.
+    pub fn scan(&self, encrypted_note: &EncryptedNote) -> Option<ReceivedStealthNote> {
+        let scan_private = self.scan.private_key_bytes();
+        let note_data = decrypt_note(encrypted_note, &scan_private).ok()?;
+
+        let ephemeral_point = parse_public_key(&encrypted_note.ephemeral_key).ok()?;
+        let shared_secret = ephemeral_point * Fr::from_le_bytes_mod_order(&scan_private);
+        let tweak = derive_tweak(&shared_secret);
+        let spending_key = one_time_spending_key(&self.meta_address().spend_key, &tweak);
+
+        Some(ReceivedStealthNote { note_data, tweak, spending_key })
+    }
+}
+
+/// Create a note for a stealth address
+pub fn send_to_stealth_address(
+    note_data: &NoteData,
+    meta_address: &StealthMetaAddress,
+) -> Result<StealthNote, EncryptionError> {
+    let scan_point = parse_public_key(&meta_address.scan_pubkey)?;
+    let ephemeral_private = Fr::rand(&mut OsRng);
+
+    let tweak = derive_tweak(&(scan_point * ephemeral_private));
+    let spending_key = one_time_spending_key(&meta_address.spend_key, &tweak);
+    let encrypted_note = encrypt_note_with_ephemeral(note_data, &scan_point, &ephemeral_private)?;
+
+    Ok(StealthNote { spending_key, encrypted_note })
+}
+
+/// one_time_key = Poseidon(spend_key, tweak)
+///
+/// Must match `note::stealth_spending_key` in the circuits.
+pub fn one_time_spending_key(spend_key: &SpendingKey, tweak: &Fr) -> SpendingKey {
+    SpendingKey::from_field(poseidon_hash2(spend_key.as_field(), tweak))
+}
+
+/// Commitment of a note opening owned by `spending_key`
+///
+/// commitment = Poseidon(Poseidon(spending_key, amount), Poseidon(blinding, asset_id))
+pub fn note_commitment(spending_key: &SpendingKey, note_data: &NoteData) -> Fr {
+    let h1 = poseidon_hash2(spending_key.as_field(), &Fr::from(note_data.amount));
+    let h2 = poseidon_hash2(
+        &Fr::from_le_bytes_mod_order(&note_data.blinding),
+        &Fr::from(note_data.asset_id),
+    );
+    poseidon_hash2(&h1, &h2)
+}
+
+/// Derive the tweak from an ECDH shared secret
+fn derive_tweak(shared_secret: &G1) -> Fr {
+    let mut point_bytes = Vec::new();
+    shared_secret.into_affine().serialize_compressed(&mut point_bytes)
+        .expect("serialization failed");
+
+    let mut hasher = Sha256::new();
+    hasher.update(STEALTH_DOMAIN);
+    hasher.update(&point_bytes);
+    hasher.update(b"tweak");
+    Fr::from_le_bytes_mod_order(&hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipient() -> StealthKeys {
+        StealthKeys::from_secrets(&[1u8; 32], &[2u8; 32])
+    }
+
+    #[test]
+    fn test_recipient_finds_stealth_note() {
+        let keys = recipient();
+        let note = NoteData::new(1_000_000_000, [7u8; 32], 0);
+        let sent = send_to_stealth_address(&note, &keys.meta_address()).unwrap();
+
+        let received = keys.scan(&sent.encrypted_note).unwrap();
+        assert_eq!(received.note_data.amount, note.amount);
+        assert_eq!(received.note_data.blinding, note.blinding);
+        assert_eq!(received.spending_key.to_bytes(), sent.spending_key.to_bytes());
+        assert_eq!(
+            note_commitment(&received.spending_key, &received.note_data),
+            note_commitment(&sent.spending_key, &note)
+        );
+
+        // The one-time key is the spend secret's key under the tweak
+        let spend_key = SpendingKey::from_secret(keys.spend_secret());
+        assert_eq!(
+            one_time_spending_key(&spend_key, &received.tweak).to_bytes(),
+            sent.spending_key.to_bytes()
+        );
+    }
+
+    #[test]
+    fn test_one_time_keys_are_unlinkable() {
+        let keys = recipient();
+        let meta_address = keys.meta_address();
+        let note = NoteData::new(500, [5u8; 32], 0);
+
+        let first = send_to_stealth_address(&note, &meta_address).unwrap();
+        let second = send_to_stealth_address(&note, &meta_address).unwrap();
+        assert_ne!(first.spending_key.to_bytes(), second.spending_key.to_bytes());
+        assert_ne!(first.spending_key.to_bytes(), meta_address.spend_key.to_bytes());
+        assert_ne!(first.encrypted_note.ephemeral_key, second.encrypted_note.ephemeral_key);
+    }
+
+    #[test]
+    fn test_other_recipient_finds_nothing() {
+        let note = NoteData::new(500, [5u8; 32], 0);
+        let sent = send_to_stealth_address(&note, &recipient().meta_address()).unwrap();
+
+        let other = StealthKeys::from_secrets(&[3u8; 32], &[2u8; 32]);
+        assert!(other.scan(&sent.encrypted_note).is_none());
+    }
+
+    #[test]
+    fn test_meta_address_serialization() {
+        let meta_address = recipient().meta_address();
+        let restored = StealthMetaAddress::from_bytes(&meta_address.to_bytes()).unwrap();
+
+        assert_eq!(restored.scan_pubkey, meta_address.scan_pubkey);
+        assert_eq!(restored.spend_key.to_bytes(), meta_address.spend_key.to_bytes());
+        assert!(StealthMetaAddress::from_bytes(&[0u8; 32]).is_err());
+    }
+}
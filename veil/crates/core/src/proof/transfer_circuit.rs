@@ -2,7 +2,8 @@
 //!
 //! This circuit proves that a private transfer is valid:
 //! 1. The sender knows the preimage of a commitment in the Merkle tree
-//! 2. The nullifier is correctly derived from the spending key and leaf index
+//! 2. The nullifier is correctly derived from the spending key, leaf index
+//!    and the pool's nullifier domain
 //! 3. The new commitment is correctly formed
 //! 4. Amount conservation is maintained (input = output for now)
 //!
@@ -10,6 +11,9 @@
 //! - merkle_root: The current Merkle tree root
 //! - nullifier: The nullifier for the spent note
 //! - new_commitment: The commitment to the output note
+//! - expiry_slot: Last Solana slot at which the proof may be submitted
+//! - nullifier_domain: Tag binding the nullifier to the pool and its mint
+//!   (see `crypto::nullifier::nullifier_domain`)
 //!
 //! Private Inputs (Witness):
 //! - sender_secret: The secret used to derive the spending key
@@ -24,7 +28,7 @@ use ark_ff::PrimeField;
 use ark_r1cs_std::{
     alloc::AllocVar,
     eq::EqGadget,
-    fields::fp::FpVar,
+    fields::{fp::FpVar, FieldVar},
 };
 use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
 
@@ -41,6 +45,10 @@ pub struct TransferCircuit {
     pub nullifier: Option<Fr>,
     /// New commitment for the output note
     pub new_commitment: Option<Fr>,
+    /// Last slot at which the proof may be submitted
+    pub expiry_slot: Option<u64>,
+    /// Tag binding the nullifier to the pool and its mint
+    pub nullifier_domain: Option<Fr>,
 
     // ===== Private Inputs (Witness) =====
     /// Sender's secret (32 bytes as Fr)
@@ -67,6 +75,8 @@ impl Default for TransferCircuit {
             merkle_root: None,
             nullifier: None,
             new_commitment: None,
+            expiry_slot: None,
+            nullifier_domain: None,
             sender_secret: None,
             input_amount: None,
             input_blinding: None,
@@ -81,10 +91,13 @@ impl Default for TransferCircuit {
 
 impl TransferCircuit {
     /// Create a new transfer circuit with all values
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         merkle_root: Fr,
         nullifier: Fr,
         new_commitment: Fr,
+        expiry_slot: u64,
+        nullifier_domain: Fr,
         sender_secret: Fr,
         input_amount: Fr,
         input_blinding: Fr,
@@ -98,6 +111,8 @@ impl TransferCircuit {
             merkle_root: Some(merkle_root),
             nullifier: Some(nullifier),
             new_commitment: Some(new_commitment),
+            expiry_slot: Some(expiry_slot),
+            nullifier_domain: Some(nullifier_domain),
             sender_secret: Some(sender_secret),
             input_amount: Some(input_amount),
             input_blinding: Some(input_blinding),
@@ -110,7 +125,7 @@ impl TransferCircuit {
     }
 
     /// Number of public inputs
-    pub const NUM_PUBLIC_INPUTS: usize = 3; // merkle_root, nullifier, new_commitment
+    pub const NUM_PUBLIC_INPUTS: usize = 5; // merkle_root, nullifier, new_commitment, expiry_slot, nullifier_domain
 }
 
 impl ConstraintSynthesizer<Fr> for TransferCircuit {
@@ -128,6 +143,14 @@ impl ConstraintSynthesizer<Fr> for TransferCircuit {
             self.new_commitment.ok_or(SynthesisError::AssignmentMissing)
         })?;
 
+        let expiry_slot_var = FpVar::new_input(cs.clone(), || {
+            self.expiry_slot.map(Fr::from).ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let nullifier_domain_var = FpVar::new_input(cs.clone(), || {
+            self.nullifier_domain.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
         // ===== Allocate Private Inputs (Witnesses) =====
         let sender_secret_var = FpVar::new_witness(cs.clone(), || {
             self.sender_secret.ok_or(SynthesisError::AssignmentMissing)
@@ -174,12 +197,9 @@ impl ConstraintSynthesizer<Fr> for TransferCircuit {
         path_gadget.verify(cs.clone(), &input_commitment_var, &merkle_root_var)?;
 
         // ===== Constraint 4: Verify nullifier derivation =====
-        // nullifier = Poseidon(spending_key, hash(leaf_index || domain))
-        let nullifier_domain = FpVar::new_constant(
-            cs.clone(),
-            Fr::from_le_bytes_mod_order(b"NYX_NULLIFIER"),
-        )?;
-        let index_with_domain = poseidon_hash2_gadget(cs.clone(), &leaf_index_var, &nullifier_domain)?;
+        // nullifier = Poseidon(spending_key, Poseidon(leaf_index, nullifier_domain))
+        let index_with_domain =
+            poseidon_hash2_gadget(cs.clone(), &leaf_index_var, &nullifier_domain_var)?;
         let computed_nullifier = poseidon_hash2_gadget(cs.clone(), &spending_key_var, &index_with_domain)?;
 
         // Enforce nullifier matches
@@ -195,6 +215,11 @@ impl ConstraintSynthesizer<Fr> for TransferCircuit {
         // Enforce new commitment matches
         computed_new_commitment.enforce_equal(&new_commitment_var)?;
 
+        // ===== Constraint 6: Bind expiry slot =====
+        // The expiry is only checked on-chain against the clock; square it so
+        // the input takes part in a constraint and can't be altered after proving
+        let _expiry_square = expiry_slot_var.square()?;
+
         Ok(())
     }
 }
@@ -207,7 +232,7 @@ mod tests {
     use rand::rngs::OsRng;
 
     use crate::crypto::merkle::PoseidonMerkleTree;
-    use crate::crypto::nullifier::{Nullifier, SpendingKey};
+    use crate::crypto::nullifier::{nullifier_domain, Nullifier, SpendingKey};
     use crate::crypto::poseidon::poseidon_hash2;
 
     /// Helper to compute note commitment
@@ -240,9 +265,9 @@ mod tests {
         let proof = tree.generate_proof(leaf_index).unwrap();
 
         // Compute nullifier (matching the circuit's derivation)
-        let nullifier_domain = Fr::from_le_bytes_mod_order(b"NYX_NULLIFIER");
+        let pool_domain = nullifier_domain(&[1u8; 32], &[0u8; 32]);
         let index_fr = Fr::from(leaf_index);
-        let index_with_domain = poseidon_hash2(&index_fr, &nullifier_domain);
+        let index_with_domain = poseidon_hash2(&index_fr, &pool_domain);
         let nullifier = poseidon_hash2(&spending_key, &index_with_domain);
 
         // Compute output commitment
@@ -253,6 +278,8 @@ mod tests {
             merkle_root,
             nullifier,
             new_commitment,
+            1_000,
+            pool_domain,
             sender_secret,
             input_amount,
             input_blinding,
@@ -299,6 +326,8 @@ mod tests {
             merkle_root,
             wrong_nullifier,
             new_commitment,
+            1_000,
+            nullifier_domain(&[1u8; 32], &[0u8; 32]),
             sender_secret,
             input_amount,
             input_blinding,
@@ -337,9 +366,9 @@ mod tests {
         let mut proof = tree.generate_proof(leaf_index).unwrap();
         proof.siblings[0] = Fr::rand(&mut OsRng);
 
-        let nullifier_domain = Fr::from_le_bytes_mod_order(b"NYX_NULLIFIER");
+        let pool_domain = nullifier_domain(&[1u8; 32], &[0u8; 32]);
         let index_fr = Fr::from(leaf_index);
-        let index_with_domain = poseidon_hash2(&index_fr, &nullifier_domain);
+        let index_with_domain = poseidon_hash2(&index_fr, &pool_domain);
         let nullifier = poseidon_hash2(&spending_key, &index_with_domain);
 
         let new_commitment = compute_commitment(&spending_key, &input_amount, &output_blinding, &asset_id);
@@ -348,6 +377,8 @@ mod tests {
             merkle_root,
             nullifier,
             new_commitment,
+            1_000,
+            pool_domain,
             sender_secret,
             input_amount,
             input_blinding,
@@ -0,0 +1,242 @@
+//! Python bindings
+//!
+//! The `veil._rust_core` extension module, built by maturin. Only compiled
+//! with the `python` feature, so the core also builds for targets pyo3
+//! can't, such as wasm32.
+
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::crypto::{generate_nullifier_hash, Commitment};
+use crate::proof::{generate_transfer_proof, verify_transfer_proof, TransferWitness};
+
+/// Generate a Pedersen commitment for shielding assets
+///
+/// # Arguments
+/// * `amount` - Amount to shield (in lamports/smallest unit)
+/// * `secret` - User's secret key (32 bytes minimum)
+///
+/// # Returns
+/// * Commitment bytes (32 bytes)
+#[pyfunction]
+fn generate_commitment(py: Python, amount: u64, secret: &[u8]) -> PyResult<Py<PyBytes>> {
+    // Validate inputs
+    if secret.len() < 32 {
+        return Err(PyValueError::new_err("Secret must be at least 32 bytes"));
+    }
+
+    // Generate commitment using Rust (fast!)
+    let commitment = Commitment::new(amount, secret)
+        .map_err(|e| PyRuntimeError::new_err(format!("Commitment generation failed: {}", e)))?;
+
+    // Serialize commitment
+    let bytes = commitment.to_bytes();
+
+    // Return as Python bytes
+    Ok(PyBytes::new(py, &bytes).into())
+}
+
+/// Generate a nullifier to prevent double-spending
+///
+/// # Arguments
+/// * `commitment` - The commitment bytes
+/// * `secret` - User's secret key
+///
+/// # Returns
+/// * Nullifier hash (32 bytes)
+#[pyfunction]
+fn generate_nullifier(py: Python, commitment: &[u8], secret: &[u8]) -> PyResult<Py<PyBytes>> {
+    if commitment.len() != 32 {
+        return Err(PyValueError::new_err("Commitment must be 32 bytes"));
+    }
+    if secret.len() < 32 {
+        return Err(PyValueError::new_err("Secret must be at least 32 bytes"));
+    }
+
+    let nullifier = generate_nullifier_hash(commitment, secret)
+        .map_err(|e| PyRuntimeError::new_err(format!("Nullifier generation failed: {}", e)))?;
+
+    Ok(PyBytes::new(py, &nullifier).into())
+}
+
+/// Generate zkSNARK proof for private transfer
+///
+/// # Arguments
+/// * `witness_json` - JSON string containing witness data
+///
+/// # Returns
+/// * Proof bytes
+#[pyfunction]
+fn generate_proof(py: Python, witness_json: &str) -> PyResult<Py<PyBytes>> {
+    // Parse witness from JSON
+    let witness: TransferWitness = serde_json::from_str(witness_json)
+        .map_err(|e| PyValueError::new_err(format!("Invalid witness JSON: {}", e)))?;
+
+    // Generate proof (this is the expensive operation!)
+    let proof = generate_transfer_proof(&witness)
+        .map_err(|e| PyRuntimeError::new_err(format!("Proof generation failed: {}", e)))?;
+
+    Ok(PyBytes::new(py, &proof).into())
+}
+
+/// Verify zkSNARK proof
+///
+/// # Arguments
+/// * `proof` - Proof bytes
+/// * `public_inputs_json` - JSON string containing public inputs
+///
+/// # Returns
+/// * Boolean indicating if proof is valid
+#[pyfunction]
+fn verify_proof(proof: &[u8], public_inputs_json: &str) -> PyResult<bool> {
+    let valid = verify_transfer_proof(proof, public_inputs_json)
+        .map_err(|e| PyRuntimeError::new_err(format!("Proof verification failed: {}", e)))?;
+
+    Ok(valid)
+}
+
+/// Poseidon hash function (zkSNARK-friendly)
+///
+/// # Arguments
+/// * `inputs` - Array of field elements to hash
+///
+/// # Returns
+/// * Hash output (32 bytes)
+#[pyfunction]
+fn poseidon_hash(py: Python, inputs: Vec<Vec<u8>>) -> PyResult<Py<PyBytes>> {
+    use crate::crypto::poseidon_hash_bytes;
+
+    let hash = poseidon_hash_bytes(&inputs)
+        .map_err(|e| PyRuntimeError::new_err(format!("Poseidon hash failed: {}", e)))?;
+
+    Ok(PyBytes::new(py, &hash).into())
+}
+
+/// Create a note for a stealth address
+///
+/// # Arguments
+/// * `meta_address` - The recipient's stealth meta-address (64 bytes)
+/// * `amount` - Note amount
+/// * `blinding` - Note blinding factor (32 bytes)
+/// * `asset_id` - Note asset ID (0 for native SOL)
+///
+/// # Returns
+/// * (commitment, encrypted note); the encrypted note goes in the
+///   shield/transfer instruction and starts with the ephemeral key and
+///   the format's version byte
+#[pyfunction]
+fn create_stealth_note(
+    py: Python,
+    meta_address: &[u8],
+    amount: u64,
+    blinding: &[u8],
+    asset_id: u64,
+) -> PyResult<(Py<PyBytes>, Py<PyBytes>)> {
+    use ark_ff::{BigInteger, PrimeField};
+    use crate::crypto::encryption::NoteData;
+    use crate::crypto::stealth::{note_commitment, send_to_stealth_address, StealthMetaAddress};
+
+    let meta_address = StealthMetaAddress::from_bytes(meta_address)
+        .map_err(|e| PyValueError::new_err(format!("Invalid meta-address: {}", e)))?;
+    let blinding: [u8; 32] = blinding
+        .try_into()
+        .map_err(|_| PyValueError::new_err("Blinding must be 32 bytes"))?;
+
+    let note_data = NoteData::new(amount, blinding, asset_id);
+    let note = send_to_stealth_address(&note_data, &meta_address)
+        .map_err(|e| PyRuntimeError::new_err(format!("Stealth note creation failed: {}", e)))?;
+    let commitment = note_commitment(&note.spending_key, &note_data).into_bigint().to_bytes_le();
+
+    Ok((
+        PyBytes::new(py, &commitment).into(),
+        PyBytes::new(py, &note.encrypted_note.to_bytes()).into(),
+    ))
+}
+
+/// Try to open an encrypted note as a stealth note
+///
+/// # Arguments
+/// * `encrypted_note` - Encrypted note from an `EncryptedNote` event
+/// * `scan_secret` - The recipient's scan secret (32 bytes)
+/// * `spend_secret` - The recipient's spend secret (32 bytes)
+///
+/// # Returns
+/// * (amount, blinding, asset_id, tweak), or None if the note isn't for
+///   this address or is in another format; the tweak is a witness when
+///   spending
+#[pyfunction]
+fn scan_stealth_note(
+    py: Python,
+    encrypted_note: &[u8],
+    scan_secret: &[u8],
+    spend_secret: &[u8],
+) -> PyResult<Option<(u64, Py<PyBytes>, u64, Py<PyBytes>)>> {
+    use ark_ff::{BigInteger, PrimeField};
+    use crate::crypto::encryption::{EncryptedNote, EncryptionError};
+    use crate::crypto::stealth::StealthKeys;
+
+    let encrypted_note = match EncryptedNote::from_bytes(encrypted_note) {
+        Ok(encrypted_note) => encrypted_note,
+        // Notes in the SDK's format are never stealth notes
+        Err(EncryptionError::UnsupportedVersion(_)) => return Ok(None),
+        Err(e) => return Err(PyValueError::new_err(format!("Invalid encrypted note: {}", e))),
+    };
+    let scan_secret: [u8; 32] = scan_secret
+        .try_into()
+        .map_err(|_| PyValueError::new_err("Scan secret must be 32 bytes"))?;
+    let spend_secret: [u8; 32] = spend_secret
+        .try_into()
+        .map_err(|_| PyValueError::new_err("Spend secret must be 32 bytes"))?;
+
+    let keys = StealthKeys::from_secrets(&scan_secret, &spend_secret);
+    Ok(keys.scan(&encrypted_note).map(|note| {
+        (
+            note.note_data.amount,
+            PyBytes::new(py, &note.note_data.blinding).into(),
+            note.note_data.asset_id,
+            PyBytes::new(py, &note.tweak.into_bigint().to_bytes_le()).into(),
+        )
+    }))
+}
+
+/// Stealth meta-address for a scan secret and a spend secret
+///
+/// # Returns
+/// * Meta-address bytes (64 bytes) to publish
+#[pyfunction]
+fn stealth_meta_address(
+    py: Python,
+    scan_secret: &[u8],
+    spend_secret: &[u8],
+) -> PyResult<Py<PyBytes>> {
+    use crate::crypto::stealth::StealthKeys;
+
+    let scan_secret: [u8; 32] = scan_secret
+        .try_into()
+        .map_err(|_| PyValueError::new_err("Scan secret must be 32 bytes"))?;
+    let spend_secret: [u8; 32] = spend_secret
+        .try_into()
+        .map_err(|_| PyValueError::new_err("Spend secret must be 32 bytes"))?;
+
+    let keys = StealthKeys::from_secrets(&scan_secret, &spend_secret);
+    Ok(PyBytes::new(py, &keys.meta_address().to_bytes()).into())
+}
+
+/// Python module definition
+#[pymodule]
+fn _rust_core(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(generate_commitment, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_nullifier, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_proof, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_proof, m)?)?;
+    m.add_function(wrap_pyfunction!(poseidon_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(stealth_meta_address, m)?)?;
+    m.add_function(wrap_pyfunction!(create_stealth_note, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_stealth_note, m)?)?;
+
+    // Add version
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+
+    Ok(())
+}
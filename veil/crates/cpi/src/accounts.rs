@@ -0,0 +1,481 @@
+//! Instruction accounts
+//!
+//! Fields are in the order the program expects, followed by the event CPI
+//! accounts every instruction here takes. `new` derives every PDA it can
+//! and leaves the optional accounts out; set them on the struct to pass
+//! them.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::AccountMeta;
+use anchor_lang::solana_program::system_program;
+
+use crate::{pda, ASSOCIATED_TOKEN_PROGRAM_ID, ID, TOKEN_PROGRAM_ID};
+
+/// Circuit ID of private transfers, part of their verifying key seeds
+const TRANSFER_CIRCUIT_ID: u8 = 0;
+
+/// Circuit ID of withdrawals, part of their verifying key seeds
+const UNSHIELD_CIRCUIT_ID: u8 = 1;
+
+/// Meta of an optional account, the program ID standing in for `None`
+fn optional(key: Option<Pubkey>, is_writable: bool) -> AccountMeta {
+    match key {
+        Some(key) if is_writable => AccountMeta::new(key, false),
+        Some(key) => AccountMeta::new_readonly(key, false),
+        None => AccountMeta::new_readonly(ID, false),
+    }
+}
+
+/// Metas of the event CPI accounts closing every account list
+fn event_cpi() -> [AccountMeta; 2] {
+    [
+        AccountMeta::new_readonly(pda::event_authority(), false),
+        AccountMeta::new_readonly(ID, false),
+    ]
+}
+
+/// Accounts of `shield_sol`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShieldSol {
+    pub pool: Pubkey,
+    pub protocol_config: Pubkey,
+    pub vault: Pubkey,
+    /// Pays the deposit and the commitment record's rent
+    pub depositor: Pubkey,
+    /// Required by pools on the incremental tree
+    pub commitment_chunk: Option<Pubkey>,
+    /// Required when the pool's tree is full: `pda::tree_epoch` of its
+    /// current epoch, which the instruction rolls over
+    pub tree_archive: Option<Pubkey>,
+    pub commitment_record: Pubkey,
+    pub pool_stats: Option<Pubkey>,
+    /// Wrapped SOL account the shielded amount is unwrapped from
+    pub depositor_wsol_account: Option<Pubkey>,
+    /// Required with `depositor_wsol_account`
+    pub token_program: Option<Pubkey>,
+    /// Required with `depositor_wsol_account`: `pda::wsol_unwrap` of the
+    /// depositor
+    pub wsol_unwrap_account: Option<Pubkey>,
+    /// Required with `depositor_wsol_account`: the wrapped SOL mint
+    pub wsol_mint: Option<Pubkey>,
+    /// Required by pools whose CPI policy restricts calling programs
+    pub instructions_sysvar: Option<Pubkey>,
+}
+
+impl ShieldSol {
+    /// Accounts shielding `commitment` into the SOL pool `pool`
+    pub fn new(
+        pool: Pubkey,
+        depositor: Pubkey,
+        commitment: &[u8; 32],
+        commitment_chunk: Option<Pubkey>,
+    ) -> Self {
+        Self {
+            pool,
+            protocol_config: pda::protocol_config(),
+            vault: pda::vault(&pool),
+            depositor,
+            commitment_chunk,
+            tree_archive: None,
+            commitment_record: pda::commitment_record(&pool, commitment),
+            pool_stats: None,
+            depositor_wsol_account: None,
+            token_program: None,
+            wsol_unwrap_account: None,
+            wsol_mint: None,
+            instructions_sysvar: None,
+        }
+    }
+}
+
+impl ToAccountMetas for ShieldSol {
+    fn to_account_metas(&self, _is_signer: Option<bool>) -> Vec<AccountMeta> {
+        let mut metas = vec![
+            AccountMeta::new(self.pool, false),
+            AccountMeta::new_readonly(self.protocol_config, false),
+            AccountMeta::new(self.vault, false),
+            AccountMeta::new(self.depositor, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+            optional(self.commitment_chunk, true),
+            optional(self.tree_archive, true),
+            AccountMeta::new(self.commitment_record, false),
+            optional(self.pool_stats, true),
+            optional(self.depositor_wsol_account, true),
+            optional(self.token_program, false),
+            optional(self.wsol_unwrap_account, true),
+            optional(self.wsol_mint, false),
+            optional(self.instructions_sysvar, false),
+        ];
+        metas.extend(event_cpi());
+        metas
+    }
+}
+
+/// Accounts of `shield`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shield {
+    pub pool: Pubkey,
+    pub protocol_config: Pubkey,
+    pub mint_allowlist: Pubkey,
+    pub vault_authority: Pubkey,
+    pub mint: Pubkey,
+    /// Owns `depositor_token_account` and pays the rent of new accounts
+    pub depositor: Pubkey,
+    pub token_program: Pubkey,
+    pub vault_token_account: Pubkey,
+    pub depositor_token_account: Pubkey,
+    /// Required by pools on the incremental tree
+    pub commitment_chunk: Option<Pubkey>,
+    /// Required when the pool's tree is full: `pda::tree_epoch` of its
+    /// current epoch, which the instruction rolls over
+    pub tree_archive: Option<Pubkey>,
+    pub commitment_record: Pubkey,
+    pub pool_stats: Option<Pubkey>,
+    /// Required by pools whose CPI policy restricts calling programs
+    pub instructions_sysvar: Option<Pubkey>,
+}
+
+impl Shield {
+    /// Accounts shielding `commitment` into the SPL pool `pool` of `mint`,
+    /// from the depositor's associated token account
+    pub fn new(
+        pool: Pubkey,
+        mint: Pubkey,
+        depositor: Pubkey,
+        commitment: &[u8; 32],
+        commitment_chunk: Option<Pubkey>,
+    ) -> Self {
+        let vault_authority = pda::vault(&pool);
+        Self {
+            pool,
+            protocol_config: pda::protocol_config(),
+            mint_allowlist: pda::mint_allowlist(),
+            vault_authority,
+            mint,
+            depositor,
+            token_program: TOKEN_PROGRAM_ID,
+            vault_token_account: pda::associated_token_account(
+                &vault_authority,
+                &mint,
+                &TOKEN_PROGRAM_ID,
+            ),
+            depositor_token_account: pda::associated_token_account(
+                &depositor,
+                &mint,
+                &TOKEN_PROGRAM_ID,
+            ),
+            commitment_chunk,
+            tree_archive: None,
+            commitment_record: pda::commitment_record(&pool, commitment),
+            pool_stats: None,
+            instructions_sysvar: None,
+        }
+    }
+}
+
+impl ToAccountMetas for Shield {
+    fn to_account_metas(&self, _is_signer: Option<bool>) -> Vec<AccountMeta> {
+        let mut metas = vec![
+            AccountMeta::new(self.pool, false),
+            AccountMeta::new_readonly(self.protocol_config, false),
+            AccountMeta::new_readonly(self.mint_allowlist, false),
+            AccountMeta::new_readonly(self.vault_authority, false),
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new(self.depositor, true),
+            AccountMeta::new_readonly(self.token_program, false),
+            AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(self.vault_token_account, false),
+            AccountMeta::new(self.depositor_token_account, false),
+            optional(self.commitment_chunk, true),
+            optional(self.tree_archive, true),
+            AccountMeta::new(self.commitment_record, false),
+            optional(self.pool_stats, true),
+            optional(self.instructions_sysvar, false),
+        ];
+        metas.extend(event_cpi());
+        metas
+    }
+}
+
+/// Accounts a spend verifies its proof and nullifier against
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpendAccounts {
+    pub pool: Pubkey,
+    pub protocol_config: Pubkey,
+    pub nullifier_marker: Pubkey,
+    pub nullifier_set: Pubkey,
+    pub nullifier_bloom: Pubkey,
+    pub nullifier_tree: Pubkey,
+    /// Required by Groth16 pools
+    pub verifying_key: Option<Pubkey>,
+    /// Required by PLONK pools
+    pub plonk_verifying_key: Option<Pubkey>,
+}
+
+impl SpendAccounts {
+    /// Accounts spending `nullifier` in `pool`, verified with the Groth16
+    /// key `vk_version` of `circuit_id`
+    fn new(pool: Pubkey, nullifier: &[u8; 32], circuit_id: u8, vk_version: u32) -> Self {
+        Self {
+            pool,
+            protocol_config: pda::protocol_config(),
+            nullifier_marker: pda::nullifier_marker(&pool, nullifier),
+            nullifier_set: pda::nullifier_set(&pool, nullifier),
+            nullifier_bloom: pda::nullifier_bloom(&pool),
+            nullifier_tree: pda::nullifier_tree(&pool),
+            verifying_key: Some(pda::verifying_key(&pool, circuit_id, vk_version)),
+            plonk_verifying_key: None,
+        }
+    }
+
+    fn metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.pool, false),
+            AccountMeta::new_readonly(self.protocol_config, false),
+            AccountMeta::new_readonly(self.nullifier_marker, false),
+            AccountMeta::new(self.nullifier_set, false),
+            AccountMeta::new(self.nullifier_bloom, false),
+            AccountMeta::new(self.nullifier_tree, false),
+            optional(self.verifying_key, false),
+            optional(self.plonk_verifying_key, false),
+        ]
+    }
+}
+
+/// Accounts of `transfer`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transfer {
+    pub spend: SpendAccounts,
+    /// Submits the proof and pays for nullifier set growth
+    pub relayer: Pubkey,
+    /// Archived tree the note belongs to, if not the current one
+    pub tree_epoch: Option<Pubkey>,
+    /// Required by pools on the incremental tree
+    pub commitment_chunk: Option<Pubkey>,
+    /// Required when the pool's tree is full: `pda::tree_epoch` of its
+    /// current epoch, which the instruction rolls over
+    pub tree_archive: Option<Pubkey>,
+    /// Required by pools that only accept bonded relayers
+    pub relayer_bond: Option<Pubkey>,
+}
+
+impl Transfer {
+    /// Accounts spending `nullifier` of `pool` into a new note, verified
+    /// with the pool's Groth16 transfer key `vk_version`
+    pub fn new(
+        pool: Pubkey,
+        nullifier: &[u8; 32],
+        vk_version: u32,
+        relayer: Pubkey,
+        commitment_chunk: Option<Pubkey>,
+    ) -> Self {
+        Self {
+            spend: SpendAccounts::new(pool, nullifier, TRANSFER_CIRCUIT_ID, vk_version),
+            relayer,
+            tree_epoch: None,
+            commitment_chunk,
+            tree_archive: None,
+            relayer_bond: None,
+        }
+    }
+}
+
+impl ToAccountMetas for Transfer {
+    fn to_account_metas(&self, _is_signer: Option<bool>) -> Vec<AccountMeta> {
+        let mut metas = self.spend.metas();
+        metas.extend([
+            AccountMeta::new(self.relayer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+            optional(self.tree_epoch, false),
+            optional(self.commitment_chunk, true),
+            optional(self.tree_archive, true),
+            optional(self.relayer_bond, false),
+        ]);
+        metas.extend(event_cpi());
+        metas
+    }
+}
+
+/// Accounts of `unshield_sol`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnshieldSol {
+    pub spend: SpendAccounts,
+    pub vault: Pubkey,
+    pub recipient: Pubkey,
+    /// The pool's treasury, paid its share of the fee
+    pub treasury: Pubkey,
+    /// Submits the proof and is paid the fee
+    pub relayer: Pubkey,
+    /// Archived tree the note belongs to, if not the current one
+    pub tree_epoch: Option<Pubkey>,
+    pub pool_stats: Option<Pubkey>,
+    /// Paid wrapped SOL instead of `recipient` when passed
+    pub recipient_wsol_account: Option<Pubkey>,
+    /// Required with `recipient_wsol_account`
+    pub token_program: Option<Pubkey>,
+    /// Required by pools that only accept bonded relayers
+    pub relayer_bond: Option<Pubkey>,
+}
+
+impl UnshieldSol {
+    /// Accounts withdrawing `nullifier`'s note from the SOL pool `pool` to
+    /// `recipient`, verified with the pool's Groth16 withdraw key
+    /// `vk_version`
+    pub fn new(
+        pool: Pubkey,
+        nullifier: &[u8; 32],
+        vk_version: u32,
+        recipient: Pubkey,
+        treasury: Pubkey,
+        relayer: Pubkey,
+    ) -> Self {
+        Self {
+            spend: SpendAccounts::new(pool, nullifier, UNSHIELD_CIRCUIT_ID, vk_version),
+            vault: pda::vault(&pool),
+            recipient,
+            treasury,
+            relayer,
+            tree_epoch: None,
+            pool_stats: None,
+            recipient_wsol_account: None,
+            token_program: None,
+            relayer_bond: None,
+        }
+    }
+}
+
+impl ToAccountMetas for UnshieldSol {
+    fn to_account_metas(&self, _is_signer: Option<bool>) -> Vec<AccountMeta> {
+        let mut metas = self.spend.metas();
+        metas.extend([
+            AccountMeta::new(self.vault, false),
+            AccountMeta::new(self.recipient, false),
+            AccountMeta::new(self.treasury, false),
+            AccountMeta::new(self.relayer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+            optional(self.tree_epoch, false),
+            optional(self.pool_stats, true),
+            optional(self.recipient_wsol_account, true),
+            optional(self.token_program, false),
+            optional(self.relayer_bond, false),
+        ]);
+        metas.extend(event_cpi());
+        metas
+    }
+}
+
+/// Accounts of `unshield`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Unshield {
+    pub spend: SpendAccounts,
+    pub vault_authority: Pubkey,
+    pub mint: Pubkey,
+    /// Owns `recipient_token_account` and receives the refund
+    pub recipient: Pubkey,
+    /// Submits the proof, pays the rent of new accounts and is paid the fee
+    pub relayer: Pubkey,
+    pub token_program: Pubkey,
+    pub vault_token_account: Pubkey,
+    pub recipient_token_account: Pubkey,
+    pub relayer_token_account: Pubkey,
+    pub treasury_token_account: Pubkey,
+    /// Archived tree the note belongs to, if not the current one
+    pub tree_epoch: Option<Pubkey>,
+    pub pool_stats: Option<Pubkey>,
+    /// Required by pools that only accept bonded relayers
+    pub relayer_bond: Option<Pubkey>,
+}
+
+impl Unshield {
+    /// Accounts withdrawing `nullifier`'s note from the SPL pool `pool` of
+    /// `mint` to associated token accounts, verified with the pool's
+    /// Groth16 withdraw key `vk_version`
+    pub fn new(
+        pool: Pubkey,
+        mint: Pubkey,
+        nullifier: &[u8; 32],
+        vk_version: u32,
+        recipient: Pubkey,
+        treasury: Pubkey,
+        relayer: Pubkey,
+    ) -> Self {
+        let vault_authority = pda::vault(&pool);
+        let token_account =
+            |owner: &Pubkey| pda::associated_token_account(owner, &mint, &TOKEN_PROGRAM_ID);
+        Self {
+            spend: SpendAccounts::new(pool, nullifier, UNSHIELD_CIRCUIT_ID, vk_version),
+            vault_authority,
+            mint,
+            recipient,
+            relayer,
+            token_program: TOKEN_PROGRAM_ID,
+            vault_token_account: token_account(&vault_authority),
+            recipient_token_account: token_account(&recipient),
+            relayer_token_account: token_account(&relayer),
+            treasury_token_account: token_account(&treasury),
+            tree_epoch: None,
+            pool_stats: None,
+            relayer_bond: None,
+        }
+    }
+}
+
+impl ToAccountMetas for Unshield {
+    fn to_account_metas(&self, _is_signer: Option<bool>) -> Vec<AccountMeta> {
+        let mut metas = self.spend.metas();
+        metas.extend([
+            AccountMeta::new_readonly(self.vault_authority, false),
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new(self.recipient, false),
+            AccountMeta::new(self.relayer, true),
+            AccountMeta::new_readonly(self.token_program, false),
+            AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(self.vault_token_account, false),
+            AccountMeta::new(self.recipient_token_account, false),
+            AccountMeta::new(self.relayer_token_account, false),
+            AccountMeta::new(self.treasury_token_account, false),
+            optional(self.tree_epoch, false),
+            optional(self.pool_stats, true),
+            optional(self.relayer_bond, false),
+        ]);
+        metas.extend(event_cpi());
+        metas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_optional_accounts_are_the_program_id() {
+        let pool = pda::pool(1_000_000_000, &Pubkey::default()).0;
+        let accounts = ShieldSol::new(pool, Pubkey::new_unique(), &[1u8; 32], None);
+        let metas = accounts.to_account_metas(None);
+
+        assert_eq!(metas[5], AccountMeta::new_readonly(ID, false));
+        assert_eq!(metas[6], AccountMeta::new_readonly(ID, false));
+        assert_eq!(metas[7], AccountMeta::new(pda::commitment_record(&pool, &[1u8; 32]), false));
+        // The depositor signs, and the event CPI accounts come last
+        assert!(metas[3].is_signer);
+        assert_eq!(metas[metas.len() - 1].pubkey, ID);
+        assert_eq!(metas[metas.len() - 2].pubkey, pda::event_authority());
+    }
+
+    #[test]
+    fn test_account_counts_match_the_program() {
+        let (pool, nullifier) = (Pubkey::new_unique(), [3u8; 32]);
+        let (mint, user, relayer) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+
+        let shield = Shield::new(pool, mint, user, &[1u8; 32], None);
+        assert_eq!(shield.to_account_metas(None).len(), 18);
+        let transfer = Transfer::new(pool, &nullifier, 1, relayer, None);
+        assert_eq!(transfer.to_account_metas(None).len(), 16);
+        let unshield_sol = UnshieldSol::new(pool, &nullifier, 1, user, relayer, relayer);
+        assert_eq!(unshield_sol.to_account_metas(None).len(), 20);
+        let unshield = Unshield::new(pool, mint, &nullifier, 1, user, relayer, relayer);
+        assert_eq!(unshield.to_account_metas(None).len(), 24);
+    }
+}
@@ -0,0 +1,124 @@
+//! Instruction data
+//!
+//! Each struct holds an instruction's arguments in the program's order and
+//! serializes after the instruction's Anchor discriminator,
+//! `sha256("global:<instruction>")[..8]`.
+
+use anchor_lang::prelude::*;
+use anchor_lang::{Discriminator, InstructionData};
+
+/// Arguments of `shield_sol`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ShieldSol {
+    pub commitment: [u8; 32],
+    pub amount: u64,
+    pub encrypted_note: Option<Vec<u8>>,
+}
+
+/// Arguments of `shield`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Shield {
+    pub commitment: [u8; 32],
+    pub amount: u64,
+    pub encrypted_note: Option<Vec<u8>>,
+}
+
+/// Arguments of `transfer`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Transfer {
+    pub nullifier: [u8; 32],
+    pub root: [u8; 32],
+    pub new_commitment: [u8; 32],
+    pub expiry_slot: u64,
+    pub proof: Vec<u8>,
+    pub encrypted_note: Option<Vec<u8>>,
+}
+
+/// Arguments of `unshield_sol`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct UnshieldSol {
+    pub nullifier: [u8; 32],
+    pub root: [u8; 32],
+    pub amount: u64,
+    pub fee: u64,
+    pub refund: u64,
+    pub expiry_slot: u64,
+    pub proof: Vec<u8>,
+}
+
+/// Arguments of `unshield`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Unshield {
+    pub nullifier: [u8; 32],
+    pub root: [u8; 32],
+    pub amount: u64,
+    pub fee: u64,
+    pub refund: u64,
+    pub expiry_slot: u64,
+    pub proof: Vec<u8>,
+}
+
+impl Discriminator for ShieldSol {
+    const DISCRIMINATOR: [u8; 8] = [236, 230, 72, 63, 15, 240, 212, 155];
+}
+
+impl Discriminator for Shield {
+    const DISCRIMINATOR: [u8; 8] = [220, 198, 253, 246, 231, 84, 147, 98];
+}
+
+impl Discriminator for Transfer {
+    const DISCRIMINATOR: [u8; 8] = [163, 52, 200, 231, 140, 3, 69, 186];
+}
+
+impl Discriminator for UnshieldSol {
+    const DISCRIMINATOR: [u8; 8] = [211, 8, 170, 159, 48, 29, 154, 202];
+}
+
+impl Discriminator for Unshield {
+    const DISCRIMINATOR: [u8; 8] = [21, 228, 55, 24, 194, 10, 21, 22];
+}
+
+impl InstructionData for ShieldSol {}
+impl InstructionData for Shield {}
+impl InstructionData for Transfer {}
+impl InstructionData for UnshieldSol {}
+impl InstructionData for Unshield {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::hash::hash;
+
+    fn anchor_discriminator(instruction: &str) -> [u8; 8] {
+        hash(format!("global:{}", instruction).as_bytes()).to_bytes()[..8]
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_discriminators_match_the_program() {
+        assert_eq!(ShieldSol::DISCRIMINATOR, anchor_discriminator("shield_sol"));
+        assert_eq!(Shield::DISCRIMINATOR, anchor_discriminator("shield"));
+        assert_eq!(Transfer::DISCRIMINATOR, anchor_discriminator("transfer"));
+        assert_eq!(UnshieldSol::DISCRIMINATOR, anchor_discriminator("unshield_sol"));
+        assert_eq!(Unshield::DISCRIMINATOR, anchor_discriminator("unshield"));
+    }
+
+    #[test]
+    fn test_data_follows_discriminator() {
+        let data = UnshieldSol {
+            nullifier: [1u8; 32],
+            root: [2u8; 32],
+            amount: 5,
+            fee: 1,
+            refund: 0,
+            expiry_slot: 9,
+            proof: vec![7; 3],
+        };
+        let bytes = data.data();
+        assert_eq!(bytes[..8], UnshieldSol::DISCRIMINATOR);
+        // Fixed fields, then the proof's length prefix and bytes
+        assert_eq!(bytes.len(), 8 + 64 + 32 + 4 + 3);
+        assert_eq!(UnshieldSol::try_from_slice(&bytes[8..]).unwrap(), data);
+    }
+}
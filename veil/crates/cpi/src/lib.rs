@@ -0,0 +1,97 @@
+//! Veil CPI
+//!
+//! Instruction builders for programs that call the Veil program, so they
+//! can shield deposits, transfer notes and withdraw without depending on
+//! the program crate or copying its discriminators and account orders.
+//!
+//! Each instruction has an accounts struct in `accounts`, listing the
+//! accounts in the order the program expects, and a data struct in
+//! `instruction`. `shield_sol`, `shield`, `transfer`, `unshield_sol` and
+//! `unshield` combine the two into an `Instruction` for `invoke` or
+//! `invoke_signed`:
+//!
+//! ```ignore
+//! let accounts = veil_cpi::accounts::ShieldSol::new(pool, depositor, &commitment, chunk);
+//! let data = veil_cpi::instruction::ShieldSol { commitment, amount, encrypted_note: None };
+//! invoke(&veil_cpi::shield_sol(&accounts, &data), &account_infos)?;
+//! ```
+//!
+//! Optional accounts left as `None` are passed as the Veil program ID, as
+//! Anchor expects. Pools whose CPI policy restricts calling programs only
+//! accept shields from allowed programs, with the instructions sysvar
+//! passed.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::InstructionData;
+
+pub mod accounts;
+pub mod instruction;
+pub mod pda;
+
+declare_id!("3qhVPvz8T1WiozCLEfhUuv8WZHDPpEfnAzq2iSatULc7");
+
+/// SPL Token program
+pub const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+/// SPL Associated Token Account program
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// Build an instruction calling the Veil program
+fn build(accounts: &impl ToAccountMetas, data: &impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Build a `shield_sol` instruction
+pub fn shield_sol(accounts: &accounts::ShieldSol, data: &instruction::ShieldSol) -> Instruction {
+    build(accounts, data)
+}
+
+/// Build a `shield` instruction
+pub fn shield(accounts: &accounts::Shield, data: &instruction::Shield) -> Instruction {
+    build(accounts, data)
+}
+
+/// Build a `transfer` instruction
+pub fn transfer(accounts: &accounts::Transfer, data: &instruction::Transfer) -> Instruction {
+    build(accounts, data)
+}
+
+/// Build an `unshield_sol` instruction
+pub fn unshield_sol(
+    accounts: &accounts::UnshieldSol,
+    data: &instruction::UnshieldSol,
+) -> Instruction {
+    build(accounts, data)
+}
+
+/// Build an `unshield` instruction
+pub fn unshield(accounts: &accounts::Unshield, data: &instruction::Unshield) -> Instruction {
+    build(accounts, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::Discriminator;
+
+    #[test]
+    fn test_builders_call_veil() {
+        let pool = pda::pool(1_000_000_000, &Pubkey::default()).0;
+        let accounts = accounts::ShieldSol::new(pool, Pubkey::new_unique(), &[1u8; 32], None);
+        let data = instruction::ShieldSol {
+            commitment: [1u8; 32],
+            amount: 1_000_000_000,
+            encrypted_note: None,
+        };
+        let instruction = shield_sol(&accounts, &data);
+        assert_eq!(instruction.program_id, ID);
+        assert_eq!(instruction.data[..8], instruction::ShieldSol::DISCRIMINATOR);
+        assert_eq!(instruction.accounts.len(), 16);
+    }
+}
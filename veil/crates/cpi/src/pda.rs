@@ -0,0 +1,123 @@
+//! Veil program addresses
+//!
+//! Seeds mirror the program's; only the accounts callers need for the
+//! instructions in this crate are covered.
+
+use anchor_lang::prelude::*;
+
+use crate::{ASSOCIATED_TOKEN_PROGRAM_ID, ID};
+
+/// Derive a pool for a denomination and mint (default pubkey for SOL)
+///
+/// SOL pools add no mint seed, which keeps their original addresses.
+pub fn pool(denomination: u64, mint: &Pubkey) -> (Pubkey, u8) {
+    let mint_seed: &[u8] = if *mint == Pubkey::default() {
+        &[]
+    } else {
+        mint.as_ref()
+    };
+    Pubkey::find_program_address(&[b"pool", &denomination.to_le_bytes(), mint_seed], &ID)
+}
+
+/// Derive the protocol config
+pub fn protocol_config() -> Pubkey {
+    Pubkey::find_program_address(&[b"protocol_config"], &ID).0
+}
+
+/// Derive the SPL mint allowlist
+pub fn mint_allowlist() -> Pubkey {
+    Pubkey::find_program_address(&[b"mint_allowlist"], &ID).0
+}
+
+/// Derive a pool's vault: the SOL vault of SOL pools, the token vault's
+/// authority of SPL pools
+pub fn vault(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"vault", pool.as_ref()], &ID).0
+}
+
+/// Derive the record of where a commitment was inserted
+pub fn commitment_record(pool: &Pubkey, commitment: &[u8; 32]) -> Pubkey {
+    Pubkey::find_program_address(&[b"commitment", pool.as_ref(), commitment], &ID).0
+}
+
+/// Derive the chunk archiving leaves `chunk_index * 256..` of a tree
+pub fn commitment_chunk(pool: &Pubkey, tree_epoch: u64, chunk_index: u32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"commitment_chunk",
+            pool.as_ref(),
+            &tree_epoch.to_le_bytes(),
+            &chunk_index.to_le_bytes(),
+        ],
+        &ID,
+    )
+    .0
+}
+
+/// Derive the archive of tree `tree_epoch` of a pool, created when the
+/// tree rolls over
+pub fn tree_epoch(pool: &Pubkey, tree_epoch: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"tree_epoch", pool.as_ref(), &tree_epoch.to_le_bytes()], &ID).0
+}
+
+/// Derive the scratch token account a depositor's wSOL is unwrapped through
+pub fn wsol_unwrap(depositor: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"wsol_unwrap", depositor.as_ref()], &ID).0
+}
+
+/// Derive the legacy nullifier marker, which must not exist for a spend
+pub fn nullifier_marker(pool: &Pubkey, nullifier: &[u8; 32]) -> Pubkey {
+    Pubkey::find_program_address(&[b"nullifier", pool.as_ref(), nullifier], &ID).0
+}
+
+/// Derive the nullifier set shard holding a nullifier
+pub fn nullifier_set(pool: &Pubkey, nullifier: &[u8; 32]) -> Pubkey {
+    Pubkey::find_program_address(&[b"nullifier_set", pool.as_ref(), &[nullifier[31]]], &ID).0
+}
+
+/// Derive a pool's spent-nullifier bloom filter
+pub fn nullifier_bloom(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"nullifier_bloom", pool.as_ref()], &ID).0
+}
+
+/// Derive a pool's indexed nullifier tree
+pub fn nullifier_tree(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"nullifier_tree", pool.as_ref()], &ID).0
+}
+
+/// Derive a pool's Groth16 verifying key for a circuit and key version
+pub fn verifying_key(pool: &Pubkey, circuit_id: u8, version: u32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"verifying_key", pool.as_ref(), &[circuit_id], &version.to_le_bytes()],
+        &ID,
+    )
+    .0
+}
+
+/// Derive a pool's PLONK verifying key for a circuit and key version
+pub fn plonk_verifying_key(pool: &Pubkey, circuit_id: u8, version: u32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"plonk_verifying_key", pool.as_ref(), &[circuit_id], &version.to_le_bytes()],
+        &ID,
+    )
+    .0
+}
+
+/// Derive a relayer's bond
+pub fn relayer_bond(relayer: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"relayer_bond", relayer.as_ref()], &ID).0
+}
+
+/// Derive the signer of the program's event CPIs
+pub fn event_authority() -> Pubkey {
+    Pubkey::find_program_address(&[b"__event_authority"], &ID).0
+}
+
+/// Derive the associated token account of `owner` for `mint`
+pub fn associated_token_account(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
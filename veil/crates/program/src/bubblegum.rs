@@ -0,0 +1,165 @@
+//! Bubblegum Compressed NFT Transfers
+//!
+//! Compressed NFTs are leaves of a Bubblegum tree rather than token
+//! accounts, so the NFT pool holds one by owning its leaf: `shield_cnft`
+//! transfers the leaf to the pool's vault authority and `unshield_cnft`
+//! transfers it on to the recipient. Bubblegum checks the leaf against the
+//! tree, so the caller passes the leaf's Merkle proof through as remaining
+//! accounts.
+//!
+//! As in `compression`, the CPI is built by hand rather than through the
+//! `mpl-bubblegum` crate, which pins its own Anchor and Solana versions.
+//! Only V1 trees, backed by spl-account-compression, are supported.
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program::invoke_signed;
+
+use crate::compression::MAX_COMPRESSED_TREE_DEPTH;
+use crate::instructions::NyxError;
+
+/// Metaplex Bubblegum program
+pub const BUBBLEGUM_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY");
+
+/// Seeds prefix of a compressed NFT's asset ID
+pub const ASSET_SEED: &[u8] = b"asset";
+
+/// Anchor discriminator of `transfer`
+const TRANSFER_DISCRIMINATOR: [u8; 8] = [0xa3, 0x34, 0xc8, 0xe7, 0x8c, 0x03, 0x45, 0xba];
+
+/// A compressed NFT's leaf, as Bubblegum needs it to verify a transfer
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CnftLeaf {
+    /// Root of the tree the proof was built against
+    pub root: [u8; 32],
+    /// Hash of the NFT's metadata
+    pub data_hash: [u8; 32],
+    /// Hash of the NFT's creators
+    pub creator_hash: [u8; 32],
+    /// Leaf nonce, which fixes the asset ID
+    pub nonce: u64,
+    /// Leaf index in the tree
+    pub index: u32,
+}
+
+impl CnftLeaf {
+    /// Asset ID of the NFT in `merkle_tree`
+    pub fn asset_id(&self, merkle_tree: &Pubkey) -> Pubkey {
+        derive_asset_id(merkle_tree, self.nonce)
+    }
+}
+
+/// Derive the asset ID of the compressed NFT minted at `nonce`
+pub fn derive_asset_id(merkle_tree: &Pubkey, nonce: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[ASSET_SEED, merkle_tree.as_ref(), &nonce.to_le_bytes()],
+        &BUBBLEGUM_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Accounts for a Bubblegum transfer
+pub struct TransferAccounts<'a, 'info> {
+    /// Bubblegum's config PDA for the tree
+    pub tree_config: &'a AccountInfo<'info>,
+    /// Current leaf owner, which signs the transfer
+    pub leaf_owner: &'a AccountInfo<'info>,
+    /// Current leaf delegate (the owner if none was set)
+    pub leaf_delegate: &'a AccountInfo<'info>,
+    /// Owner the leaf is transferred to
+    pub new_leaf_owner: &'a AccountInfo<'info>,
+    /// The concurrent Merkle tree holding the leaf
+    pub merkle_tree: &'a AccountInfo<'info>,
+    /// spl-noop program
+    pub log_wrapper: &'a AccountInfo<'info>,
+    /// spl-account-compression program
+    pub compression_program: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+    /// Bubblegum program
+    pub bubblegum_program: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> TransferAccounts<'a, 'info> {
+    /// Transfer the leaf to `new_leaf_owner`
+    ///
+    /// `proof` holds the leaf's proof nodes, less any the tree's canopy
+    /// stores. `signer_seeds` sign for a PDA leaf owner and are empty when
+    /// the owner signed the transaction.
+    pub fn transfer(
+        &self,
+        leaf: &CnftLeaf,
+        proof: &[AccountInfo<'info>],
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        require!(
+            proof.len() <= MAX_COMPRESSED_TREE_DEPTH as usize,
+            NyxError::InvalidCnftProof
+        );
+
+        let mut data = TRANSFER_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&leaf.root);
+        data.extend_from_slice(&leaf.data_hash);
+        data.extend_from_slice(&leaf.creator_hash);
+        data.extend_from_slice(&leaf.nonce.to_le_bytes());
+        data.extend_from_slice(&leaf.index.to_le_bytes());
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(self.tree_config.key(), false),
+            AccountMeta::new_readonly(self.leaf_owner.key(), true),
+            AccountMeta::new_readonly(self.leaf_delegate.key(), false),
+            AccountMeta::new_readonly(self.new_leaf_owner.key(), false),
+            AccountMeta::new(self.merkle_tree.key(), false),
+            AccountMeta::new_readonly(self.log_wrapper.key(), false),
+            AccountMeta::new_readonly(self.compression_program.key(), false),
+            AccountMeta::new_readonly(self.system_program.key(), false),
+        ];
+        accounts.extend(proof.iter().map(|node| AccountMeta::new_readonly(node.key(), false)));
+        let instruction = Instruction {
+            program_id: BUBBLEGUM_PROGRAM_ID,
+            accounts,
+            data,
+        };
+
+        let mut infos = vec![
+            self.tree_config.clone(),
+            self.leaf_owner.clone(),
+            self.leaf_delegate.clone(),
+            self.new_leaf_owner.clone(),
+            self.merkle_tree.clone(),
+            self.log_wrapper.clone(),
+            self.compression_program.clone(),
+            self.system_program.clone(),
+            self.bubblegum_program.clone(),
+        ];
+        infos.extend_from_slice(proof);
+        invoke_signed(&instruction, &infos, signer_seeds)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_discriminator() {
+        let hash = solana_program::hash::hash(b"global:transfer").to_bytes();
+        assert_eq!(hash[..8], TRANSFER_DISCRIMINATOR);
+    }
+
+    #[test]
+    fn test_asset_id_depends_on_tree_and_nonce() {
+        let tree = Pubkey::new_unique();
+        let leaf = CnftLeaf {
+            root: [0u8; 32],
+            data_hash: [0u8; 32],
+            creator_hash: [0u8; 32],
+            nonce: 7,
+            index: 7,
+        };
+        assert_eq!(leaf.asset_id(&tree), derive_asset_id(&tree, 7));
+        assert_ne!(derive_asset_id(&tree, 7), derive_asset_id(&tree, 8));
+        assert_ne!(derive_asset_id(&tree, 7), derive_asset_id(&Pubkey::new_unique(), 7));
+    }
+}
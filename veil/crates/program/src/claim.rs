@@ -0,0 +1,126 @@
+//! Claim Links
+//!
+//! A claim link's `claim_hash` commits to an Ed25519 key generated for the
+//! link, whose secret key is what the sender hands over. Claiming takes
+//! that key's signature over the link and the recipient, made with the
+//! Ed25519 precompile in the instruction right before `claim`, so the key
+//! itself never goes on-chain and a copied claim can't be redirected.
+
+use anchor_lang::prelude::*;
+use solana_program::hash;
+
+use crate::escrow;
+use crate::instructions::NyxError;
+use crate::state::CLAIM_LINK_SEED;
+
+/// Domain separator of claim messages
+pub const CLAIM_DOMAIN: &[u8] = b"NYX_CLAIM";
+
+/// The claim hash committing a link to `claim_key`
+pub fn claim_hash(claim_key: &Pubkey) -> [u8; 32] {
+    hash::hashv(&[CLAIM_DOMAIN, claim_key.as_ref()]).to_bytes()
+}
+
+/// Message the link's key signs to pay `claim_link` out to `recipient`
+///
+/// Message = sha256(CLAIM_DOMAIN || claim_link || recipient)
+pub fn claim_message(claim_link: &Pubkey, recipient: &Pubkey) -> [u8; 32] {
+    hash::hashv(&[CLAIM_DOMAIN, claim_link.as_ref(), recipient.as_ref()]).to_bytes()
+}
+
+/// Address of the claim link with the given terms
+pub fn claim_link_address(
+    pool: &Pubkey,
+    claim_hash: &[u8; 32],
+    refund_to: &Pubkey,
+    expiry_slot: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            CLAIM_LINK_SEED,
+            pool.as_ref(),
+            claim_hash,
+            refund_to.as_ref(),
+            &expiry_slot.to_le_bytes(),
+        ],
+        &crate::ID,
+    )
+}
+
+/// Require `claim_key` to match the link's hash and the instruction before
+/// this one to be its signature paying `claim_link` to `recipient`
+pub fn require_claim_signature(
+    instructions_sysvar: &AccountInfo,
+    link_claim_hash: &[u8; 32],
+    claim_key: &Pubkey,
+    claim_link: &Pubkey,
+    recipient: &Pubkey,
+) -> Result<()> {
+    require!(claim_hash(claim_key) == *link_claim_hash, NyxError::InvalidClaimSecret);
+    require!(
+        escrow::preceding_signature_is(
+            instructions_sysvar,
+            claim_key,
+            &claim_message(claim_link, recipient),
+        )?,
+        NyxError::InvalidClaimSecret
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::ClaimLink;
+
+    fn link(expiry_slot: u64) -> ClaimLink {
+        ClaimLink {
+            pool: Pubkey::new_unique(),
+            claim_hash: claim_hash(&Pubkey::new_unique()),
+            refund_to: Pubkey::new_unique(),
+            expiry_slot,
+            amount: 1_000,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn test_link_address_binds_terms() {
+        let (pool, refund_to) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let hash = claim_hash(&Pubkey::new_unique());
+        let (address, _) = claim_link_address(&pool, &hash, &refund_to, 100);
+        assert_eq!(claim_link_address(&pool, &hash, &refund_to, 100).0, address);
+        assert_ne!(claim_link_address(&pool, &hash, &refund_to, 101).0, address);
+        assert_ne!(claim_link_address(&pool, &hash, &Pubkey::new_unique(), 100).0, address);
+        assert_ne!(
+            claim_link_address(&pool, &claim_hash(&Pubkey::new_unique()), &refund_to, 100).0,
+            address
+        );
+    }
+
+    #[test]
+    fn test_claim_hash_commits_to_key() {
+        let key = Pubkey::new_unique();
+        assert_eq!(claim_hash(&key), claim_hash(&key));
+        assert_ne!(claim_hash(&key), claim_hash(&Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn test_claim_message_binds_link_and_recipient() {
+        let (claim_link, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let message = claim_message(&claim_link, &recipient);
+        assert_ne!(message, claim_message(&claim_link, &Pubkey::new_unique()));
+        assert_ne!(message, claim_message(&Pubkey::new_unique(), &recipient));
+        assert_ne!(message, escrow::resolution_message(&claim_link, &[0u8; 32], &recipient));
+    }
+
+    #[test]
+    fn test_claim_and_reclaim_windows() {
+        let link = link(100);
+        // Claims are open up to and including the expiry slot
+        assert!(link.is_claimable(0));
+        assert!(link.is_claimable(100));
+        // Refunds open the slot after
+        assert!(!link.is_claimable(101));
+    }
+}
@@ -0,0 +1,176 @@
+//! Commitment Chunks
+//!
+//! A `CommitmentChunk` archives a run of consecutive leaves of a pool's
+//! tree. Every instruction inserting into an incremental tree passes the
+//! chunk its first leaf lands in, and the next one when the leaves cross
+//! into it. The addresses are checked here, and a chunk the leaves are the
+//! first to reach is created on the spot, so nobody has to create chunks
+//! ahead of the tree.
+
+use std::cell::RefMut;
+use std::mem;
+
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+use crate::instructions::NyxError;
+use crate::nullifier;
+use crate::state::{CommitmentChunk, COMMITMENT_CHUNK_SEED};
+
+/// Address of chunk `chunk_index` of the tree `tree_epoch` of `pool`
+pub fn commitment_chunk_address(pool: &Pubkey, tree_epoch: u64, chunk_index: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            COMMITMENT_CHUNK_SEED,
+            pool.as_ref(),
+            &tree_epoch.to_le_bytes(),
+            &chunk_index.to_le_bytes(),
+        ],
+        &crate::ID,
+    )
+}
+
+/// Require `chunk_info` to be chunk `chunk_index` of the tree `tree_epoch`
+/// of `pool`, returning its bump
+pub fn require_chunk_address(
+    chunk_info: &AccountInfo,
+    pool: &Pubkey,
+    tree_epoch: u64,
+    chunk_index: u32,
+) -> Result<u8> {
+    let (expected, bump) = commitment_chunk_address(pool, tree_epoch, chunk_index);
+    require_keys_eq!(chunk_info.key(), expected, NyxError::InvalidCommitmentChunk);
+    Ok(bump)
+}
+
+/// Borrow a commitment chunk from its account data
+fn load_chunk_mut<'a>(chunk_info: &'a AccountInfo) -> Result<RefMut<'a, CommitmentChunk>> {
+    let data = chunk_info.try_borrow_mut_data()?;
+    require!(
+        data.len() >= 8 + CommitmentChunk::SIZE && data[..8] == CommitmentChunk::DISCRIMINATOR,
+        NyxError::InvalidCommitmentChunk
+    );
+    Ok(RefMut::map(data, |data| {
+        bytemuck::from_bytes_mut(&mut data[8..8 + mem::size_of::<CommitmentChunk>()])
+    }))
+}
+
+/// Check the address of chunk `chunk_index`, creating the chunk with
+/// `payer` covering the rent if it doesn't exist yet
+fn ensure_chunk<'info>(
+    program_id: &Pubkey,
+    chunk_info: &AccountInfo<'info>,
+    pool_key: &Pubkey,
+    tree_epoch: u64,
+    chunk_index: u32,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let bump = require_chunk_address(chunk_info, pool_key, tree_epoch, chunk_index)?;
+    if chunk_info.owner == program_id {
+        return Ok(());
+    }
+
+    nullifier::create_pda_account(
+        program_id,
+        chunk_info,
+        payer,
+        system_program,
+        8 + CommitmentChunk::SIZE,
+        &[&[
+            COMMITMENT_CHUNK_SEED,
+            pool_key.as_ref(),
+            &tree_epoch.to_le_bytes(),
+            &chunk_index.to_le_bytes(),
+            &[bump],
+        ]],
+    )?;
+    chunk_info.try_borrow_mut_data()?[..8].copy_from_slice(&CommitmentChunk::DISCRIMINATOR);
+    load_chunk_mut(chunk_info)?.initialize(*pool_key, tree_epoch, chunk_index);
+    Ok(())
+}
+
+/// Write inserted leaves to their commitment chunks
+///
+/// `first_index` is the leaf index of `commitments[0]`. Leaves past the end
+/// of `chunk` go to `next_chunk`, which batches crossing a chunk boundary
+/// must pass.
+#[allow(clippy::too_many_arguments)]
+pub fn archive_commitments<'info>(
+    program_id: &Pubkey,
+    pool_key: &Pubkey,
+    tree_epoch: u64,
+    first_index: u64,
+    commitments: &[[u8; 32]],
+    chunk: &AccountInfo<'info>,
+    next_chunk: Option<&AccountInfo<'info>>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let chunk_index = CommitmentChunk::index_of(first_index);
+    let last_index = first_index + (commitments.len() as u64).saturating_sub(1);
+    ensure_chunk(program_id, chunk, pool_key, tree_epoch, chunk_index, payer, system_program)?;
+    let next_chunk = if CommitmentChunk::index_of(last_index) != chunk_index {
+        let next_chunk = next_chunk.ok_or(NyxError::InvalidCommitmentChunk)?;
+        ensure_chunk(
+            program_id,
+            next_chunk,
+            pool_key,
+            tree_epoch,
+            chunk_index + 1,
+            payer,
+            system_program,
+        )?;
+        Some(next_chunk)
+    } else {
+        None
+    };
+
+    let mut chunk = load_chunk_mut(chunk)?;
+    let mut next_chunk = next_chunk.map(load_chunk_mut).transpose()?;
+
+    for (leaf_index, commitment) in (first_index..).zip(commitments) {
+        if chunk.covers(pool_key, tree_epoch, leaf_index) {
+            chunk.append(leaf_index, *commitment)?;
+        } else {
+            let next = next_chunk
+                .as_mut()
+                .filter(|next| next.covers(pool_key, tree_epoch, leaf_index))
+                .ok_or(NyxError::InvalidCommitmentChunk)?;
+            next.append(leaf_index, *commitment)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_are_kept_per_pool_epoch_and_index() {
+        let pool = Pubkey::new_unique();
+        let (address, _) = commitment_chunk_address(&pool, 0, 0);
+        assert_eq!(commitment_chunk_address(&pool, 0, 0).0, address);
+        assert_ne!(commitment_chunk_address(&pool, 0, 1).0, address);
+        assert_ne!(commitment_chunk_address(&pool, 1, 0).0, address);
+        assert_ne!(commitment_chunk_address(&Pubkey::new_unique(), 0, 0).0, address);
+    }
+
+    #[test]
+    fn test_chunk_address_is_checked() {
+        let pool = Pubkey::new_unique();
+        let (address, bump) = commitment_chunk_address(&pool, 2, 5);
+        let (mut lamports, mut data) = (0, Vec::new());
+        let system = solana_program::system_program::ID;
+        let chunk =
+            AccountInfo::new(&address, false, true, &mut lamports, &mut data, &system, false, 0);
+        assert_eq!(require_chunk_address(&chunk, &pool, 2, 5).unwrap(), bump);
+
+        // Any other chunk of the pool, or the same chunk of another tree, is refused
+        let err = require_chunk_address(&chunk, &pool, 2, 6).unwrap_err();
+        assert_eq!(err, NyxError::InvalidCommitmentChunk.into());
+        let err = require_chunk_address(&chunk, &pool, 3, 5).unwrap_err();
+        assert_eq!(err, NyxError::InvalidCommitmentChunk.into());
+    }
+}
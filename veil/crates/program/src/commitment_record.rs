@@ -0,0 +1,135 @@
+//! Commitment Records
+//!
+//! A `CommitmentRecord` maps a shielded commitment to the leaf it landed
+//! at. Single shields create theirs through an Anchor `init` constraint;
+//! batch shields pass one record per commitment as remaining accounts, so
+//! they are checked and created here, the same way `init` would: topping
+//! up an address someone prefunded rather than failing on it.
+
+use anchor_lang::prelude::*;
+
+use crate::instructions::NyxError;
+use crate::nullifier;
+use crate::state::{CommitmentRecord, COMMITMENT_RECORD_SEED};
+
+/// Address of the record of `commitment` in `pool`
+pub fn commitment_record_address(pool: &Pubkey, commitment: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[COMMITMENT_RECORD_SEED, pool.as_ref(), commitment],
+        &crate::ID,
+    )
+}
+
+/// Require `record_info` to be the not yet created record of `commitment`
+/// in `pool`, returning its bump
+pub fn require_new_record(
+    record_info: &AccountInfo,
+    pool: &Pubkey,
+    commitment: &[u8; 32],
+) -> Result<u8> {
+    let (expected, bump) = commitment_record_address(pool, commitment);
+    require_keys_eq!(record_info.key(), expected, NyxError::InvalidCommitmentRecord);
+    require!(record_info.owner != &crate::ID, NyxError::DuplicateCommitment);
+    Ok(bump)
+}
+
+/// Create one commitment record per batch commitment
+///
+/// `first_index` is the leaf index `commitments[0]` will be inserted at.
+/// Fails with `DuplicateCommitment` if any record already exists. Records
+/// prefunded to block the batch are created all the same.
+#[allow(clippy::too_many_arguments)]
+pub fn create_commitment_records<'info>(
+    program_id: &Pubkey,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    records: &[AccountInfo<'info>],
+    pool_key: &Pubkey,
+    tree_epoch: u64,
+    first_index: u64,
+    commitments: &[[u8; 32]],
+) -> Result<()> {
+    require!(
+        records.len() == commitments.len(),
+        NyxError::InvalidCommitmentRecord
+    );
+    let slot = Clock::get()?.slot;
+
+    let leaves = commitments.iter().zip(first_index..);
+    for (record_info, (commitment, leaf_index)) in records.iter().zip(leaves) {
+        let bump = require_new_record(record_info, pool_key, commitment)?;
+        nullifier::create_pda_account(
+            program_id,
+            record_info,
+            payer,
+            system_program,
+            8 + CommitmentRecord::SIZE,
+            &[&[COMMITMENT_RECORD_SEED, pool_key.as_ref(), commitment, &[bump]]],
+        )?;
+
+        let record = CommitmentRecord {
+            pool: *pool_key,
+            commitment: *commitment,
+            tree_epoch,
+            leaf_index,
+            slot,
+            bump,
+        };
+        let mut data = record_info.try_borrow_mut_data()?;
+        record.try_serialize(&mut &mut data[..])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_are_kept_per_pool_and_commitment() {
+        let (pool, commitment) = (Pubkey::new_unique(), [1u8; 32]);
+        let (address, _) = commitment_record_address(&pool, &commitment);
+        assert_eq!(commitment_record_address(&pool, &commitment).0, address);
+        assert_ne!(commitment_record_address(&pool, &[2u8; 32]).0, address);
+        assert_ne!(commitment_record_address(&Pubkey::new_unique(), &commitment).0, address);
+    }
+
+    #[test]
+    fn test_batch_records_must_be_new() {
+        let (pool, commitment) = (Pubkey::new_unique(), [1u8; 32]);
+        let (address, bump) = commitment_record_address(&pool, &commitment);
+        let (mut lamports, mut data) = (0, Vec::new());
+        let system = solana_program::system_program::ID;
+        let mut record =
+            AccountInfo::new(&address, false, true, &mut lamports, &mut data, &system, false, 0);
+        assert_eq!(require_new_record(&record, &pool, &commitment).unwrap(), bump);
+
+        // Another commitment's record doesn't count
+        let err = require_new_record(&record, &pool, &[2u8; 32]).unwrap_err();
+        assert_eq!(err, NyxError::InvalidCommitmentRecord.into());
+
+        // A record that exists means the commitment was shielded before
+        record.owner = &crate::ID;
+        let err = require_new_record(&record, &pool, &commitment).unwrap_err();
+        assert_eq!(err, NyxError::DuplicateCommitment.into());
+    }
+
+    #[test]
+    fn test_record_fits_its_account() {
+        let record = CommitmentRecord {
+            pool: Pubkey::new_unique(),
+            commitment: [1u8; 32],
+            tree_epoch: u64::MAX,
+            leaf_index: u64::MAX,
+            slot: u64::MAX,
+            bump: 255,
+        };
+        let mut data = Vec::new();
+        record.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), 8 + CommitmentRecord::SIZE);
+
+        let decoded = CommitmentRecord::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!(decoded.leaf_index, u64::MAX);
+        assert_eq!(decoded.commitment, record.commitment);
+    }
+}
@@ -0,0 +1,269 @@
+//! SPL Account Compression Backend
+//!
+//! Pools in compressed mode append commitments to an spl-account-compression
+//! concurrent Merkle tree instead of the in-account incremental tree. The
+//! tree lives in its own account, so capacity (up to 2^30 leaves) doesn't
+//! grow the pool, and leaves are logged through the noop program for
+//! indexers to rebuild paths.
+//!
+//! The CPIs are built by hand rather than through the `spl-account-compression`
+//! crate, which pins its own Anchor and Solana versions.
+//!
+//! Note: the compression program hashes nodes with keccak256, so spends from
+//! a compressed pool need circuits that prove keccak membership; the
+//! Poseidon withdraw circuit only matches the incremental backend. Until
+//! they exist spends reject compressed pools, and pools can't switch to a
+//! compressed tree (`COMPRESSED_SPENDS_SUPPORTED`).
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program::invoke_signed;
+
+use crate::instructions::NyxError;
+
+/// spl-account-compression program
+pub const COMPRESSION_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+
+/// spl-noop program the compression program logs leaves through
+pub const NOOP_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+
+/// Seeds prefix for the PDA that owns a pool's compressed tree
+pub const TREE_AUTHORITY_SEED: &[u8] = b"tree_authority";
+
+/// Smallest supported compressed tree depth (the incremental tree's depth)
+pub const MIN_COMPRESSED_TREE_DEPTH: u32 = 10;
+
+/// Largest depth the compression program supports
+pub const MAX_COMPRESSED_TREE_DEPTH: u32 = 30;
+
+/// Whether the spend circuits prove membership in compressed trees
+///
+/// `enable_compressed_tree` is refused while they don't, since the notes
+/// shielded into the tree could never be spent.
+pub const COMPRESSED_SPENDS_SUPPORTED: bool = false;
+
+/// Number of remaining accounts taken by `CompressionAccounts`
+pub const COMPRESSION_ACCOUNTS_LEN: usize = 4;
+
+/// Anchor discriminator of `init_empty_merkle_tree`
+const INIT_EMPTY_MERKLE_TREE_DISCRIMINATOR: [u8; 8] =
+    [0xbf, 0x0b, 0x77, 0x07, 0xb4, 0x6b, 0xdc, 0x6e];
+
+/// Anchor discriminator of `append`
+const APPEND_DISCRIMINATOR: [u8; 8] = [0x95, 0x78, 0x12, 0xde, 0xec, 0xe1, 0x58, 0xcb];
+
+/// Size of the tree account header (account type, version, V1 header data)
+const TREE_HEADER_SIZE: usize = 56;
+
+/// Offset of `max_buffer_size` within the header
+const HEADER_MAX_BUFFER_SIZE_OFFSET: usize = 2;
+
+/// Offset of `max_depth` within the header
+const HEADER_MAX_DEPTH_OFFSET: usize = 6;
+
+/// Offset of the change log buffer: sequence_number, active_index, buffer_size
+const CHANGE_LOGS_OFFSET: usize = TREE_HEADER_SIZE + 24;
+
+/// Accounts for appending to a pool's compressed tree
+pub struct CompressionAccounts<'a, 'info> {
+    /// The concurrent Merkle tree account
+    pub merkle_tree: &'a AccountInfo<'info>,
+    /// Tree authority PDA of the pool
+    pub tree_authority: &'a AccountInfo<'info>,
+    /// spl-noop program
+    pub noop_program: &'a AccountInfo<'info>,
+    /// spl-account-compression program
+    pub compression_program: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> CompressionAccounts<'a, 'info> {
+    /// Take the compression accounts from the front of the remaining accounts
+    ///
+    /// Expects [merkle_tree, tree_authority, noop_program, compression_program]
+    /// and checks each against the pool's configuration.
+    pub fn from_remaining(
+        accounts: &'a [AccountInfo<'info>],
+        merkle_tree: &Pubkey,
+        tree_authority: &Pubkey,
+    ) -> Result<Self> {
+        require!(
+            accounts.len() >= COMPRESSION_ACCOUNTS_LEN,
+            NyxError::InvalidCompressionAccounts
+        );
+        require_keys_eq!(
+            accounts[0].key(),
+            *merkle_tree,
+            NyxError::InvalidCompressionAccounts
+        );
+        require_keys_eq!(
+            accounts[1].key(),
+            *tree_authority,
+            NyxError::InvalidCompressionAccounts
+        );
+        require_keys_eq!(
+            accounts[2].key(),
+            NOOP_PROGRAM_ID,
+            NyxError::InvalidCompressionAccounts
+        );
+        require_keys_eq!(
+            accounts[3].key(),
+            COMPRESSION_PROGRAM_ID,
+            NyxError::InvalidCompressionAccounts
+        );
+
+        Ok(Self {
+            merkle_tree: &accounts[0],
+            tree_authority: &accounts[1],
+            noop_program: &accounts[2],
+            compression_program: &accounts[3],
+        })
+    }
+
+    /// Invoke the compression program, signing as the tree authority
+    fn invoke(&self, data: Vec<u8>, pool: &Pubkey, authority_bump: u8) -> Result<()> {
+        let instruction = Instruction {
+            program_id: COMPRESSION_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(self.merkle_tree.key(), false),
+                AccountMeta::new_readonly(self.tree_authority.key(), true),
+                AccountMeta::new_readonly(NOOP_PROGRAM_ID, false),
+            ],
+            data,
+        };
+        let signer_seeds: &[&[&[u8]]] = &[&[TREE_AUTHORITY_SEED, pool.as_ref(), &[authority_bump]]];
+
+        invoke_signed(
+            &instruction,
+            &[
+                self.merkle_tree.clone(),
+                self.tree_authority.clone(),
+                self.noop_program.clone(),
+                self.compression_program.clone(),
+            ],
+            signer_seeds,
+        )?;
+        Ok(())
+    }
+
+    /// Initialize the (pre-allocated) tree account with the pool as authority
+    pub fn init_empty_merkle_tree(
+        &self,
+        pool: &Pubkey,
+        authority_bump: u8,
+        max_depth: u32,
+        max_buffer_size: u32,
+    ) -> Result<()> {
+        let mut data = INIT_EMPTY_MERKLE_TREE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&max_depth.to_le_bytes());
+        data.extend_from_slice(&max_buffer_size.to_le_bytes());
+        self.invoke(data, pool, authority_bump)
+    }
+
+    /// Append a leaf and return the tree's new root
+    pub fn append(&self, pool: &Pubkey, authority_bump: u8, leaf: &[u8; 32]) -> Result<[u8; 32]> {
+        let mut data = APPEND_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(leaf);
+        self.invoke(data, pool, authority_bump)?;
+
+        let tree_data = self.merkle_tree.try_borrow_data()?;
+        current_root(&tree_data).ok_or_else(|| error!(NyxError::InvalidCompressionAccounts))
+    }
+}
+
+/// Derive the PDA that owns a pool's compressed tree
+pub fn derive_tree_authority(program_id: &Pubkey, pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TREE_AUTHORITY_SEED, pool.as_ref()], program_id)
+}
+
+/// Read the current root from a concurrent Merkle tree account
+///
+/// The root is that of the active change log entry. Returns `None` if the
+/// account is too short for its own header.
+pub fn current_root(data: &[u8]) -> Option<[u8; 32]> {
+    let read_u32 = |offset: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(
+            data.get(offset..offset + 4)?.try_into().ok()?,
+        ))
+    };
+    let max_buffer_size = read_u32(HEADER_MAX_BUFFER_SIZE_OFFSET)? as usize;
+    let max_depth = read_u32(HEADER_MAX_DEPTH_OFFSET)? as usize;
+
+    let active_index = u64::from_le_bytes(
+        data.get(TREE_HEADER_SIZE + 8..TREE_HEADER_SIZE + 16)?
+            .try_into()
+            .ok()?,
+    ) as usize;
+    if active_index >= max_buffer_size {
+        return None;
+    }
+
+    // ChangeLog: root, path[max_depth], index (u32), padding (u32)
+    let change_log_size = 32 + 32 * max_depth + 8;
+    let offset = CHANGE_LOGS_OFFSET + active_index * change_log_size;
+    data.get(offset..offset + 32)?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree_account(
+        max_depth: u32,
+        max_buffer_size: u32,
+        active_index: u64,
+        root: [u8; 32],
+    ) -> Vec<u8> {
+        let change_log_size = 32 + 32 * max_depth as usize + 8;
+        let mut data = vec![0u8; CHANGE_LOGS_OFFSET + change_log_size * max_buffer_size as usize];
+        data[HEADER_MAX_BUFFER_SIZE_OFFSET..HEADER_MAX_BUFFER_SIZE_OFFSET + 4]
+            .copy_from_slice(&max_buffer_size.to_le_bytes());
+        data[HEADER_MAX_DEPTH_OFFSET..HEADER_MAX_DEPTH_OFFSET + 4]
+            .copy_from_slice(&max_depth.to_le_bytes());
+        data[TREE_HEADER_SIZE + 8..TREE_HEADER_SIZE + 16]
+            .copy_from_slice(&active_index.to_le_bytes());
+
+        let offset = CHANGE_LOGS_OFFSET + active_index as usize * change_log_size;
+        data[offset..offset + 32].copy_from_slice(&root);
+        data
+    }
+
+    #[test]
+    fn test_current_root_reads_active_change_log() {
+        let data = tree_account(14, 64, 5, [7u8; 32]);
+        assert_eq!(current_root(&data), Some([7u8; 32]));
+    }
+
+    #[test]
+    fn test_current_root_rejects_bad_accounts() {
+        assert_eq!(current_root(&[0u8; 16]), None);
+
+        // Active index outside the buffer
+        let mut data = tree_account(14, 8, 0, [7u8; 32]);
+        data[TREE_HEADER_SIZE + 8..TREE_HEADER_SIZE + 16].copy_from_slice(&8u64.to_le_bytes());
+        assert_eq!(current_root(&data), None);
+    }
+
+    #[test]
+    fn test_spends_require_poseidon_tree() {
+        use crate::state::PrivacyPool;
+
+        let mut pool: Box<PrivacyPool> = Box::new(bytemuck::Zeroable::zeroed());
+        assert!(pool.require_poseidon_tree().is_ok());
+
+        pool.enable_compressed_tree(Pubkey::new_unique(), 14, 255, [7u8; 32]);
+        assert_eq!(
+            pool.require_poseidon_tree().unwrap_err(),
+            NyxError::UnsupportedTreeBackend.into()
+        );
+    }
+
+    #[test]
+    fn test_discriminators() {
+        let hash = solana_program::hash::hash(b"global:append").to_bytes();
+        assert_eq!(hash[..8], APPEND_DISCRIMINATOR);
+        let hash = solana_program::hash::hash(b"global:init_empty_merkle_tree").to_bytes();
+        assert_eq!(hash[..8], INIT_EMPTY_MERKLE_TREE_DISCRIMINATOR);
+    }
+}
@@ -0,0 +1,113 @@
+//! CPI Guard
+//!
+//! A pool's `CpiPolicy` decides whether shields may come from other
+//! programs: from any, from none, or only from the front-end programs the
+//! authority allowed. The invocation's stack height tells a direct shield
+//! from one arriving through CPI, and instructions sysvar introspection
+//! names the caller: the program of the transaction's instruction being
+//! executed. The sysvar only shows that top-level program, so a shield
+//! nested more than one CPI deep has a caller that can't be checked and
+//! is refused unless the pool allows any caller.
+//!
+//! Pools that allow any caller don't need the sysvar, so existing clients
+//! keep working until the authority tightens the policy.
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT};
+use solana_program::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
+
+use crate::instructions::NyxError;
+use crate::state::{CpiPolicy, PrivacyPool};
+
+/// Program calling into the Veil program, given the invocation's stack
+/// height and the program of the transaction's current instruction; `None`
+/// for a direct call
+///
+/// Fails for calls nested deeper than one CPI, whose caller isn't the
+/// top-level program and can't be identified.
+pub fn caller_of(stack_height: usize, top_level_program: &Pubkey) -> Result<Option<Pubkey>> {
+    match stack_height {
+        TRANSACTION_LEVEL_STACK_HEIGHT => Ok(None),
+        height if height == TRANSACTION_LEVEL_STACK_HEIGHT + 1 => Ok(Some(*top_level_program)),
+        _ => err!(NyxError::CallerNotAllowed),
+    }
+}
+
+/// Program calling into the Veil program in the current instruction
+pub fn current_caller(instructions_sysvar: &AccountInfo) -> Result<Option<Pubkey>> {
+    let current = load_current_index_checked(instructions_sysvar)?;
+    let instruction = load_instruction_at_checked(current as usize, instructions_sysvar)?;
+    caller_of(get_stack_height(), &instruction.program_id)
+}
+
+/// Fail unless the pool's CPI policy lets the current caller shield
+///
+/// The instructions sysvar is only needed when the policy restricts CPI.
+pub fn require_shield_caller(
+    pool: &PrivacyPool,
+    instructions_sysvar: Option<&AccountInfo>,
+) -> Result<()> {
+    if pool.cpi_policy() == CpiPolicy::AllowAny {
+        return Ok(());
+    }
+    let instructions_sysvar = instructions_sysvar.ok_or(NyxError::InstructionsSysvarRequired)?;
+    let caller = current_caller(instructions_sysvar)?;
+    pool.require_caller_allowed(caller.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool_with_policy(policy: CpiPolicy, allowed_callers: &[Pubkey]) -> Box<PrivacyPool> {
+        let mut pool: Box<PrivacyPool> = Box::new(bytemuck::Zeroable::zeroed());
+        pool.set_cpi_policy(policy, allowed_callers).unwrap();
+        pool
+    }
+
+    #[test]
+    fn test_caller_of() {
+        let front_end = Pubkey::new_unique();
+        assert_eq!(caller_of(TRANSACTION_LEVEL_STACK_HEIGHT, &crate::ID).unwrap(), None);
+        let cpi_height = TRANSACTION_LEVEL_STACK_HEIGHT + 1;
+        assert_eq!(caller_of(cpi_height, &front_end).unwrap(), Some(front_end));
+
+        // An allowed front end at the top can't vouch for a program it calls
+        let err = caller_of(cpi_height + 1, &front_end).unwrap_err();
+        assert_eq!(err, NyxError::CallerNotAllowed.into());
+    }
+
+    #[test]
+    fn test_cpi_policies() {
+        let (front_end, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let pool = pool_with_policy(CpiPolicy::AllowAny, &[]);
+        assert!(pool.require_caller_allowed(None).is_ok());
+        assert!(pool.require_caller_allowed(Some(&other)).is_ok());
+        // No sysvar needed to allow everyone
+        assert!(require_shield_caller(&pool, None).is_ok());
+
+        let pool = pool_with_policy(CpiPolicy::DenyCpi, &[]);
+        assert!(pool.require_caller_allowed(None).is_ok());
+        assert!(pool.require_caller_allowed(Some(&other)).is_err());
+        assert!(require_shield_caller(&pool, None).is_err());
+
+        let pool = pool_with_policy(CpiPolicy::Allowlist, &[front_end]);
+        assert!(pool.require_caller_allowed(Some(&front_end)).is_ok());
+        assert!(pool.require_caller_allowed(Some(&other)).is_err());
+        assert!(pool.require_caller_allowed(None).is_err());
+    }
+
+    #[test]
+    fn test_allowed_callers_fit_the_pool() {
+        let mut pool: Box<PrivacyPool> = Box::new(bytemuck::Zeroable::zeroed());
+        let callers: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+        assert!(pool.set_cpi_policy(CpiPolicy::Allowlist, &callers).is_err());
+        assert!(pool.set_cpi_policy(CpiPolicy::Allowlist, &[Pubkey::default()]).is_err());
+
+        // Narrowing the list drops the callers left out
+        pool.set_cpi_policy(CpiPolicy::Allowlist, &callers[..4]).unwrap();
+        pool.set_cpi_policy(CpiPolicy::Allowlist, &callers[..1]).unwrap();
+        assert!(pool.require_caller_allowed(Some(&callers[3])).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//! CPI Destinations
+//!
+//! `unshield_cpi` withdraws a note straight into another program, such as
+//! a lending deposit, instead of a wallet. The note is paid out to a
+//! transient token account of the destination authority, and the program
+//! invokes the caller's instruction on the destination program signing as
+//! that authority, which must hand the whole payout on.
+//!
+//! The destination authority is a PDA of the pool and the call: the
+//! destination program, its accounts and its instruction data. The unshield
+//! proof binds it as the recipient, so a relayer can't change where the
+//! funds land or on whose behalf. Only programs in the admin-managed
+//! `DestinationRegistry` can be called.
+
+use anchor_lang::prelude::*;
+use solana_program::hash;
+use solana_program::program::invoke_signed;
+
+use crate::instructions::NyxError;
+use crate::swap;
+
+/// Seeds prefix for the PDA that holds tokens during a destination call
+pub const DESTINATION_AUTHORITY_SEED: &[u8] = b"destination_authority";
+
+/// Largest destination instruction data accepted
+pub const MAX_DESTINATION_DATA_LEN: usize = 512;
+
+/// Hash of a destination call
+///
+/// Hash = sha256(program || (key || is_writable)* || data)
+///
+/// The destination authority and its token account are derived from the
+/// hash, so wherever they appear in `accounts` they are hashed as the
+/// default pubkey.
+pub fn call_hash(
+    program: &Pubkey,
+    accounts: &[AccountInfo<'_>],
+    derived: &[Pubkey],
+    data: &[u8],
+) -> [u8; 32] {
+    let mut hasher = hash::Hasher::default();
+    hasher.hash(program.as_ref());
+    for info in accounts {
+        let key = if derived.contains(info.key) {
+            Pubkey::default()
+        } else {
+            info.key()
+        };
+        hasher.hash(key.as_ref());
+        hasher.hash(&[info.is_writable as u8]);
+    }
+    hasher.hash(data);
+    hasher.result().to_bytes()
+}
+
+/// Derive the destination authority for a call out of `pool`
+pub fn derive_destination_authority(
+    program_id: &Pubkey,
+    pool: &Pubkey,
+    call_hash: &[u8; 32],
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[DESTINATION_AUTHORITY_SEED, pool.as_ref(), call_hash.as_ref()],
+        program_id,
+    )
+}
+
+/// Invoke the destination program, signing as the destination authority
+///
+/// The program must already have been checked against the registry.
+pub fn invoke_destination<'info>(
+    destination_program: &AccountInfo<'info>,
+    accounts: &[AccountInfo<'info>],
+    destination_authority: &Pubkey,
+    data: Vec<u8>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    require!(data.len() <= MAX_DESTINATION_DATA_LEN, NyxError::InvalidDestinationCall);
+
+    let instruction =
+        swap::route_instruction(destination_program.key, accounts, destination_authority, data);
+    let mut infos = accounts.to_vec();
+    infos.push(destination_program.clone());
+    invoke_signed(&instruction, &infos, signer_seeds)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_hash_binds_program_accounts_and_data() {
+        let owner = Pubkey::new_unique();
+        let (authority_key, obligation_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut lamports, mut data) = ([0u64; 2], [[0u8; 0]; 2]);
+        let [l0, l1] = &mut lamports;
+        let [d0, d1] = &mut data;
+        let accounts = [
+            AccountInfo::new(&authority_key, false, true, l0, d0, &owner, false, 0),
+            AccountInfo::new(&obligation_key, false, true, l1, d1, &owner, false, 0),
+        ];
+        let program = Pubkey::new_unique();
+
+        let hash = call_hash(&program, &accounts, &[authority_key], &[1, 2]);
+        assert_ne!(hash, call_hash(&Pubkey::new_unique(), &accounts, &[authority_key], &[1, 2]));
+        assert_ne!(hash, call_hash(&program, &accounts[..1], &[authority_key], &[1, 2]));
+        assert_ne!(hash, call_hash(&program, &accounts, &[authority_key], &[1, 3]));
+
+        // Derived accounts are hashed as placeholders, so the hash can be
+        // computed before they are known
+        assert_eq!(hash, call_hash(&program, &accounts, &[authority_key, owner], &[1, 2]));
+        assert_ne!(hash, call_hash(&program, &accounts, &[], &[1, 2]));
+    }
+
+    #[test]
+    fn test_destination_authority_binds_pool_and_call() {
+        let pool = Pubkey::new_unique();
+        let (authority, _) = derive_destination_authority(&crate::ID, &pool, &[1u8; 32]);
+        assert_ne!(authority, derive_destination_authority(&crate::ID, &pool, &[2u8; 32]).0);
+        assert_ne!(
+            authority,
+            derive_destination_authority(&crate::ID, &Pubkey::new_unique(), &[1u8; 32]).0
+        );
+    }
+}
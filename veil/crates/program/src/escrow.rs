@@ -0,0 +1,166 @@
+//! Arbitrated Escrow
+//!
+//! An escrow note is shielded under
+//! `escrow_commitment(commitment, arbiter, escrow_id)`: its leaf binds an
+//! arbiter and a deal. The parties to the deal share the note's opening,
+//! so either can prove the spend, but `unshield_sol_escrow` and
+//! `unshield_escrow` only accept it alongside the arbiter's signature over
+//! a resolution naming the recipient. The arbiter releases the note to the
+//! seller or refunds it to the buyer without either party's identity, or
+//! the deal, appearing on-chain before the resolution.
+//!
+//! The arbiter signs with the Ed25519 precompile in the instruction right
+//! before the withdrawal; the precompile fails the transaction on a bad
+//! signature, so the program only checks what was signed, and by whom.
+
+use anchor_lang::prelude::*;
+use solana_program::ed25519_program;
+use solana_program::hash;
+use solana_program::instruction::Instruction;
+use solana_program::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
+
+use crate::instructions::NyxError;
+
+/// Domain separator of resolution messages
+pub const ESCROW_RESOLUTION_DOMAIN: &[u8] = b"NYX_ESCROW_RESOLUTION";
+
+/// Size of the Ed25519 precompile's per-signature offsets
+const ED25519_OFFSETS_SIZE: usize = 14;
+
+/// Offsets in an Ed25519 precompile instruction referring to its own data
+const ED25519_THIS_INSTRUCTION: u16 = u16::MAX;
+
+/// Message the arbiter signs to resolve `escrow_id` in favour of `recipient`
+///
+/// Message = sha256(ESCROW_RESOLUTION_DOMAIN || pool || escrow_id || recipient)
+pub fn resolution_message(pool: &Pubkey, escrow_id: &[u8; 32], recipient: &Pubkey) -> [u8; 32] {
+    hash::hashv(&[ESCROW_RESOLUTION_DOMAIN, pool.as_ref(), escrow_id, recipient.as_ref()])
+        .to_bytes()
+}
+
+/// Signer and message of an Ed25519 precompile instruction
+///
+/// Only a single signature with its key and message inline is accepted.
+pub fn parse_ed25519_instruction(instruction: &Instruction) -> Option<(Pubkey, &[u8])> {
+    if instruction.program_id != ed25519_program::ID {
+        return None;
+    }
+    let data = &instruction.data;
+    if data.len() < 2 + ED25519_OFFSETS_SIZE || data[0] != 1 {
+        return None;
+    }
+
+    // signature_offset, signature_instruction_index, public_key_offset,
+    // public_key_instruction_index, message_data_offset, message_data_size,
+    // message_instruction_index
+    let offset = |i: usize| u16::from_le_bytes([data[2 + 2 * i], data[3 + 2 * i]]) as usize;
+    let this_instruction = ED25519_THIS_INSTRUCTION as usize;
+    if [offset(1), offset(3), offset(6)] != [this_instruction; 3] {
+        return None;
+    }
+
+    let pubkey = data.get(offset(2)..offset(2) + 32)?;
+    let message = data.get(offset(4)..offset(4) + offset(5))?;
+    Some((Pubkey::try_from(pubkey).ok()?, message))
+}
+
+/// Whether the instruction before this one is `signer`'s Ed25519
+/// signature over `message`
+pub fn preceding_signature_is(
+    instructions_sysvar: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8; 32],
+) -> Result<bool> {
+    let current = load_current_index_checked(instructions_sysvar)?;
+    if current == 0 {
+        return Ok(false);
+    }
+    let instruction = load_instruction_at_checked(current as usize - 1, instructions_sysvar)?;
+    Ok(parse_ed25519_instruction(&instruction)
+        .is_some_and(|(key, signed_message)| key == *signer && signed_message == message))
+}
+
+/// Require the instruction before this one to be `arbiter`'s Ed25519
+/// signature over `message`
+pub fn require_arbiter_resolution(
+    instructions_sysvar: &AccountInfo,
+    arbiter: &Pubkey,
+    message: &[u8; 32],
+) -> Result<()> {
+    require!(
+        preceding_signature_is(instructions_sysvar, arbiter, message)?,
+        NyxError::MissingEscrowResolution
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ed25519 precompile instruction data as `new_ed25519_instruction`
+    /// lays it out: offsets, then pubkey, signature and message
+    fn ed25519_instruction(pubkey: &Pubkey, message: &[u8], instruction_index: u16) -> Instruction {
+        let pubkey_offset = 2 + ED25519_OFFSETS_SIZE as u16;
+        let signature_offset = pubkey_offset + 32;
+        let message_offset = signature_offset + 64;
+
+        let mut data = vec![1u8, 0];
+        for offset in [
+            signature_offset,
+            instruction_index,
+            pubkey_offset,
+            instruction_index,
+            message_offset,
+            message.len() as u16,
+            instruction_index,
+        ] {
+            data.extend_from_slice(&offset.to_le_bytes());
+        }
+        data.extend_from_slice(pubkey.as_ref());
+        data.extend_from_slice(&[7u8; 64]);
+        data.extend_from_slice(message);
+        Instruction {
+            program_id: ed25519_program::ID,
+            accounts: Vec::new(),
+            data,
+        }
+    }
+
+    #[test]
+    fn test_resolution_message_binds_escrow_and_recipient() {
+        let (pool, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let message = resolution_message(&pool, &[1u8; 32], &recipient);
+        assert_ne!(message, resolution_message(&pool, &[2u8; 32], &recipient));
+        assert_ne!(message, resolution_message(&pool, &[1u8; 32], &Pubkey::new_unique()));
+        assert_ne!(message, resolution_message(&Pubkey::new_unique(), &[1u8; 32], &recipient));
+    }
+
+    #[test]
+    fn test_parse_ed25519_instruction() {
+        let arbiter = Pubkey::new_unique();
+        let message = resolution_message(&Pubkey::new_unique(), &[1u8; 32], &arbiter);
+
+        let instruction = ed25519_instruction(&arbiter, &message, ED25519_THIS_INSTRUCTION);
+        assert_eq!(
+            parse_ed25519_instruction(&instruction),
+            Some((arbiter, message.as_slice()))
+        );
+
+        // Signatures over data in other instructions aren't what they seem
+        assert_eq!(parse_ed25519_instruction(&ed25519_instruction(&arbiter, &message, 0)), None);
+
+        // Nor is anything but the precompile
+        let mut other = instruction.clone();
+        other.program_id = crate::ID;
+        assert_eq!(parse_ed25519_instruction(&other), None);
+
+        // Nor several signatures, or truncated data
+        let mut several = instruction.clone();
+        several.data[0] = 2;
+        assert_eq!(parse_ed25519_instruction(&several), None);
+        let mut truncated = instruction;
+        truncated.data.truncate(truncated.data.len() - 1);
+        assert_eq!(parse_ed25519_instruction(&truncated), None);
+    }
+}
@@ -0,0 +1,569 @@
+//! Program Events
+//!
+//! Anchor events emitted as commitments enter a pool's tree, as nullifiers
+//! are spent and as the pool's configuration changes. Indexers and wallets
+//! replay them to sync the tree and detect consumed notes, instead of
+//! diffing pool account snapshots or polling nullifier accounts.
+//!
+//! Every shield, transfer and unshield also emits one summary event
+//! (`ShieldEvent`, `TransferEvent`, `UnshieldEvent`) with the amounts and
+//! parties it made public, so indexers can account for flows without
+//! parsing the program's logs.
+//!
+//! Events are emitted as self-CPIs (Anchor's event CPI) rather than logged:
+//! the runtime truncates the log of a transaction that logs too much,
+//! silently dropping events, while inner instructions are always kept.
+//! Indexers parse events from the inner instructions signed by the event
+//! authority PDA. Instructions emit with `emit_cpi!`; shared helpers such as
+//! `nullifier::spend_nullifier` return their events for the instruction to
+//! emit.
+
+use anchor_lang::prelude::*;
+
+/// A commitment was inserted as a leaf
+#[event]
+pub struct MerkleLeafInserted {
+    /// Pool the leaf belongs to
+    pub pool: Pubkey,
+    /// Tree epoch the leaf belongs to
+    pub tree_epoch: u64,
+    /// The inserted commitment
+    pub leaf: [u8; 32],
+    /// Leaf index within the tree
+    pub leaf_index: u64,
+    /// Root after inserting this leaf
+    pub new_root: [u8; 32],
+    /// Slot of the insertion
+    pub slot: u64,
+}
+
+/// A commitment was inserted with a note encrypted to its recipient
+///
+/// Only logged, never stored: recipients scan for these and try to
+/// decrypt each with their viewing key to find incoming notes, instead of
+/// receiving notes out of band. A note sent to a stealth address is
+/// encrypted to its scan key, and its recipient derives the note's
+/// one-time key from the same ephemeral key.
+#[event]
+pub struct EncryptedNote {
+    /// Pool the leaf belongs to
+    pub pool: Pubkey,
+    /// Tree epoch the leaf belongs to
+    pub tree_epoch: u64,
+    /// The commitment the note opens
+    pub commitment: [u8; 32],
+    /// Leaf index of the commitment
+    pub leaf_index: u64,
+    /// Ephemeral public key the note was encrypted under, its leading bytes
+    pub ephemeral_key: [u8; 32],
+    /// The note, encrypted to the recipient's viewing key
+    pub encrypted_note: Vec<u8>,
+}
+
+/// The pool's current root changed
+///
+/// Emitted once per instruction, after all of its leaves are inserted.
+#[event]
+pub struct RootUpdated {
+    /// Pool whose root changed
+    pub pool: Pubkey,
+    /// Tree epoch of the root
+    pub tree_epoch: u64,
+    /// Root before the instruction
+    pub old_root: [u8; 32],
+    /// Root after the instruction
+    pub new_root: [u8; 32],
+    /// Number of leaves in the tree after the instruction
+    pub leaf_count: u64,
+    /// Slot of the update
+    pub slot: u64,
+}
+
+/// A nullifier was spent, consuming the note it was derived from
+#[event]
+pub struct NullifierSpent {
+    /// Pool the nullifier was spent in
+    pub pool: Pubkey,
+    /// The spent nullifier
+    pub nullifier: [u8; 32],
+    /// Slot of the spend
+    pub slot: u64,
+}
+
+/// The pool's relayer fee changed
+#[event]
+pub struct RelayerFeeUpdated {
+    /// Pool whose fee changed
+    pub pool: Pubkey,
+    /// Fee before the change, in basis points
+    pub old_fee_bps: u16,
+    /// Fee after the change, in basis points
+    pub new_fee_bps: u16,
+    /// Slot of the change
+    pub slot: u64,
+}
+
+/// A new pool authority was proposed
+#[event]
+pub struct AuthorityProposed {
+    /// Pool whose authority is being handed over
+    pub pool: Pubkey,
+    /// Current authority
+    pub authority: Pubkey,
+    /// Proposed authority (default pubkey when a proposal is cancelled)
+    pub pending_authority: Pubkey,
+    /// Slot of the proposal
+    pub slot: u64,
+}
+
+/// The proposed pool authority accepted and took over the pool
+#[event]
+pub struct AuthorityTransferred {
+    /// Pool whose authority changed
+    pub pool: Pubkey,
+    /// Authority before the transfer
+    pub old_authority: Pubkey,
+    /// Authority after the transfer
+    pub new_authority: Pubkey,
+    /// Slot of the transfer
+    pub slot: u64,
+}
+
+/// The pool was paused or unpaused
+#[event]
+pub struct PauseUpdated {
+    /// Pool whose pause state changed
+    pub pool: Pubkey,
+    /// Whether shields and transfers are paused
+    pub paused: bool,
+    /// Whether unshields are paused
+    pub unshields_paused: bool,
+    /// Slot of the change
+    pub slot: u64,
+}
+
+/// Guardians froze or unfroze the pool's verifying key updates
+#[event]
+pub struct VkFreezeUpdated {
+    /// Pool whose verifying key updates changed
+    pub pool: Pubkey,
+    /// Whether verifying key updates are frozen
+    pub frozen: bool,
+    /// Slot of the change
+    pub slot: u64,
+}
+
+/// A pool account was upgraded to a newer layout version
+#[event]
+pub struct PoolMigrated {
+    /// Pool that was migrated
+    pub pool: Pubkey,
+    /// Layout version before the migration
+    pub from_version: u8,
+    /// Layout version after the migration
+    pub to_version: u8,
+    /// Slot of the migration
+    pub slot: u64,
+}
+
+/// The protocol config was changed
+#[event]
+pub struct ProtocolConfigUpdated {
+    /// Account allowed to change the config
+    pub admin: Pubkey,
+    /// Account allowed to propose verifying keys for every pool
+    pub vk_authority: Pubkey,
+    /// Relayer fee new pools start with, in basis points
+    pub default_relayer_fee_bps: u16,
+    /// Treasury share of relayer fees, in basis points
+    pub treasury_fee_share_bps: u16,
+    /// Whether shields and transfers are paused on every pool
+    pub paused: bool,
+    /// Slot of the change
+    pub slot: u64,
+}
+
+/// A new protocol admin was proposed
+#[event]
+pub struct ProtocolAdminProposed {
+    /// Current admin
+    pub admin: Pubkey,
+    /// Proposed admin (default pubkey when a proposal is cancelled)
+    pub pending_admin: Pubkey,
+    /// Slot of the proposal
+    pub slot: u64,
+}
+
+/// A note was withdrawn through the emergency escape hatch
+#[event]
+pub struct EmergencyWithdrawal {
+    /// Pool the note was withdrawn from
+    pub pool: Pubkey,
+    /// Revealed commitment of the note
+    pub commitment: [u8; 32],
+    /// Leaf index of the note
+    pub leaf_index: u64,
+    /// Nullifier marked spent
+    pub nullifier: [u8; 32],
+    /// Account that received the funds
+    pub recipient: Pubkey,
+    /// Amount withdrawn
+    pub amount: u64,
+    /// Slot of the withdrawal
+    pub slot: u64,
+}
+
+/// A pool's yield strategy was configured
+#[event]
+pub struct YieldStrategyUpdated {
+    /// Pool whose vault the strategy manages
+    pub pool: Pubkey,
+    /// SPL stake pool the vault deposits with
+    pub stake_pool: Pubkey,
+    /// Share of the value locked kept in the vault, in basis points
+    pub buffer_bps: u16,
+    /// Slot of the change
+    pub slot: u64,
+}
+
+/// A pool's vault was rebalanced against its yield strategy
+#[event]
+pub struct YieldStrategyRebalanced {
+    /// Pool whose vault was rebalanced
+    pub pool: Pubkey,
+    /// Lamports deposited with the stake pool
+    pub deposited: u64,
+    /// Lamports redeemed from the stake pool
+    pub withdrawn: u64,
+    /// Lamports deposited with the stake pool afterwards, at cost
+    pub deployed: u64,
+    /// Slot of the rebalance
+    pub slot: u64,
+}
+
+/// A note was spent into a claim link
+#[event]
+pub struct ClaimLinkCreated {
+    /// Pool the note was spent from
+    pub pool: Pubkey,
+    /// The claim link PDA
+    pub claim_link: Pubkey,
+    /// Lamports claimable
+    pub amount: u64,
+    /// Last slot at which the link can be claimed
+    pub expiry_slot: u64,
+}
+
+/// A claim link was claimed or, after expiry, refunded
+#[event]
+pub struct ClaimLinkClosed {
+    /// Pool the note was spent from
+    pub pool: Pubkey,
+    /// The claim link PDA
+    pub claim_link: Pubkey,
+    /// Account paid the lamports
+    pub recipient: Pubkey,
+    /// Lamports paid, including the link's rent
+    pub amount: u64,
+    /// Whether the link was refunded instead of claimed
+    pub refunded: bool,
+    /// Slot of the claim or refund
+    pub slot: u64,
+}
+
+/// A withdrawal intent was queued
+#[event]
+pub struct UnshieldQueued {
+    /// Pool the withdrawal is from
+    pub pool: Pubkey,
+    /// Hash of the withdrawal's parameters
+    pub intent_hash: [u8; 32],
+    /// Slot the intent was queued at
+    pub queued_slot: u64,
+}
+
+/// A queued withdrawal intent was executed or cancelled
+#[event]
+pub struct QueuedUnshieldClosed {
+    /// Pool the withdrawal is from
+    pub pool: Pubkey,
+    /// Hash of the withdrawal's parameters
+    pub intent_hash: [u8; 32],
+    /// Whether the withdrawal was executed instead of cancelled
+    pub executed: bool,
+    /// Slot of the execution or cancellation
+    pub slot: u64,
+}
+
+/// A note was spent into a payment stream
+#[event]
+pub struct PaymentStreamCreated {
+    /// Pool the note was spent from
+    pub pool: Pubkey,
+    /// The payment stream PDA
+    pub payment_stream: Pubkey,
+    /// Lamports to be paid out over the stream
+    pub amount: u64,
+    /// Most lamports paid per period
+    pub amount_per_period: u64,
+    /// Length of a period in slots
+    pub period_slots: u64,
+}
+
+/// A payment stream paid its recipient
+#[event]
+pub struct StreamPaymentMade {
+    /// The payment stream PDA
+    pub payment_stream: Pubkey,
+    /// Account paid
+    pub recipient: Pubkey,
+    /// Lamports paid
+    pub amount: u64,
+    /// Lamports left to pay
+    pub remaining: u64,
+    /// Slot of the payment
+    pub slot: u64,
+}
+
+/// A payment stream ran out or was cancelled
+#[event]
+pub struct PaymentStreamClosed {
+    /// Pool the note was spent from
+    pub pool: Pubkey,
+    /// The payment stream PDA
+    pub payment_stream: Pubkey,
+    /// Lamports returned to the refund address, including the rent
+    pub refunded: u64,
+    /// Whether the stream was cancelled instead of paid out
+    pub cancelled: bool,
+    /// Slot of the closure
+    pub slot: u64,
+}
+
+/// A note was shielded into the pool
+///
+/// Carries what a depositor made public: the amount, the mint and the
+/// leaf, alongside the `MerkleLeafInserted` of the leaf itself.
+#[event]
+pub struct ShieldEvent {
+    /// Pool the note was shielded into
+    pub pool: Pubkey,
+    /// Mint of the deposit (default pubkey for SOL, the asset ID of
+    /// compressed NFTs)
+    pub mint: Pubkey,
+    /// Account that paid the deposit
+    pub depositor: Pubkey,
+    /// Commitment of the new note
+    pub commitment: [u8; 32],
+    /// Tree epoch the leaf belongs to
+    pub tree_epoch: u64,
+    /// Leaf index of the commitment
+    pub leaf_index: u64,
+    /// Root after the instruction
+    pub root: [u8; 32],
+    /// Amount deposited, in lamports or token base units (1 for NFTs)
+    pub amount: u64,
+    /// Slot of the shield
+    pub slot: u64,
+}
+
+/// Notes were spent into new notes without leaving the shielded set
+///
+/// Covers private transfers and join-splits, and swaps, stakes and
+/// migrations, whose outputs are shielded again.
+#[event]
+pub struct TransferEvent {
+    /// Pool the notes were spent from
+    pub pool: Pubkey,
+    /// Nullifiers spent
+    pub nullifiers: Vec<[u8; 32]>,
+    /// Commitments of the new notes, in leaf order
+    pub commitments: Vec<[u8; 32]>,
+    /// Pool the new notes were inserted into
+    pub output_pool: Pubkey,
+    /// Leaf index of the first new commitment
+    pub first_leaf_index: u64,
+    /// Root of the output pool after the instruction
+    pub root: [u8; 32],
+    /// Account that submitted the proof
+    pub relayer: Pubkey,
+    /// Slot of the transfer
+    pub slot: u64,
+}
+
+/// A note was withdrawn from the pool
+///
+/// A note paid out to several recipients emits one per recipient, the
+/// first counting the fee.
+#[event]
+pub struct UnshieldEvent {
+    /// Pool the note was withdrawn from
+    pub pool: Pubkey,
+    /// Mint withdrawn (default pubkey for SOL, the asset ID of compressed
+    /// NFTs)
+    pub mint: Pubkey,
+    /// The spent nullifier
+    pub nullifier: [u8; 32],
+    /// Account that received the funds
+    pub recipient: Pubkey,
+    /// Amount withdrawn, fee included (1 for NFTs)
+    pub amount: u64,
+    /// Fee paid out of the amount, relayer and treasury shares together
+    pub fee: u64,
+    /// Account that submitted the proof
+    pub relayer: Pubkey,
+    /// Slot of the withdrawal
+    pub slot: u64,
+}
+
+/// The pool authority changed the pool's configuration
+///
+/// Carries the whole configuration after the change, so indexers don't
+/// need to know which instruction changed which value.
+#[event]
+pub struct PoolConfigChanged {
+    /// Pool whose configuration changed
+    pub pool: Pubkey,
+    /// Authority that made the change
+    pub authority: Pubkey,
+    /// Account receiving protocol funds
+    pub treasury: Pubkey,
+    /// Relayer fee, in basis points
+    pub relayer_fee_bps: u16,
+    /// Verifier backend for spend proofs (`ProofSystem` as u8)
+    pub proof_system: u8,
+    /// Active verifying key version
+    pub vk_version: u32,
+    /// Delay between proposing and activating new verifying keys
+    pub vk_update_delay_slots: u64,
+    /// How long the previous verifying key version stays valid
+    pub vk_grace_period_slots: u64,
+    /// Slots a root must age before proofs may use it
+    pub min_spend_delay_slots: u64,
+    /// Deposits the pool must exceed before unshields are allowed
+    pub min_anonymity_set: u64,
+    /// Withdrawal rate limit window in slots (0 = disabled)
+    pub withdrawal_window_slots: u64,
+    /// Maximum amount withdrawn per window (0 = unlimited)
+    pub withdrawal_max_amount: u64,
+    /// Maximum withdrawals per window (0 = unlimited)
+    pub withdrawal_max_count: u64,
+    /// Maximum vault balance shields may reach (0 = uncapped)
+    pub deposit_cap: u64,
+    /// Rent reimbursement SPL unshields may charge (0 = none)
+    pub max_rent_fee: u64,
+    /// Slots after spending before a legacy nullifier marker can be closed
+    pub marker_finality_slots: u64,
+    /// Which programs may shield through CPI (`CpiPolicy` as u8)
+    pub cpi_policy: u8,
+    /// Programs allowed to shield under the allowlist policy
+    pub allowed_callers: Vec<Pubkey>,
+    /// Which relayers may submit spends (`RelayerPolicy` as u8)
+    pub relayer_policy: u8,
+    /// Relayers accepted under the allowlist policy
+    pub allowed_relayers: Vec<Pubkey>,
+    /// Slot at which closing the pool was scheduled (0 = not scheduled)
+    pub close_scheduled_at: u64,
+    /// Slot of the change
+    pub slot: u64,
+}
+
+/// A relayer was slashed for charging more than its quoted fee
+#[event]
+pub struct RelayerSlashed {
+    /// Pool the withdrawal was from
+    pub pool: Pubkey,
+    /// The slashed relayer
+    pub relayer: Pubkey,
+    /// Nullifier of the overcharged withdrawal
+    pub nullifier: [u8; 32],
+    /// Recipient of the withdrawal, paid the slashed lamports
+    pub recipient: Pubkey,
+    /// Fee the relayer quoted
+    pub quoted_fee: u64,
+    /// Fee the withdrawal paid
+    pub fee: u64,
+    /// Lamports slashed from the bond
+    pub slashed: u64,
+    /// Slot of the dispute
+    pub slot: u64,
+}
+
+/// A relayer published or changed its fee quote for a pool
+#[event]
+pub struct RelayerQuoteUpdated {
+    /// Pool the quote is for
+    pub pool: Pubkey,
+    /// The quoting relayer
+    pub relayer: Pubkey,
+    /// Fee charged, in basis points
+    pub fee_bps: u16,
+    /// Smallest withdrawal accepted, in the pool's units
+    pub min_withdrawal: u64,
+    /// Endpoint wallets submit withdrawals to
+    pub url: String,
+    /// Slot of the update
+    pub slot: u64,
+}
+
+/// A relayer withdrew its fee quote for a pool
+#[event]
+pub struct RelayerQuoteClosed {
+    /// Pool the quote was for
+    pub pool: Pubkey,
+    /// The relayer
+    pub relayer: Pubkey,
+    /// Slot of the closure
+    pub slot: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::{Discriminator, Event};
+
+    #[test]
+    fn test_shield_event_decodes_from_its_data() {
+        let event = ShieldEvent {
+            pool: Pubkey::new_unique(),
+            mint: Pubkey::default(),
+            depositor: Pubkey::new_unique(),
+            commitment: [1u8; 32],
+            tree_epoch: 2,
+            leaf_index: 300,
+            root: [4u8; 32],
+            amount: 1_000_000,
+            slot: 500,
+        };
+        // What an indexer reads from the event CPI, after the event tag
+        let data = event.data();
+        assert_eq!(data[..8], ShieldEvent::DISCRIMINATOR);
+
+        let decoded = ShieldEvent::try_from_slice(&data[8..]).unwrap();
+        assert_eq!((decoded.pool, decoded.depositor), (event.pool, event.depositor));
+        assert_eq!((decoded.commitment, decoded.root), (event.commitment, event.root));
+        assert_eq!((decoded.tree_epoch, decoded.leaf_index), (2, 300));
+        assert_eq!((decoded.amount, decoded.slot), (1_000_000, 500));
+    }
+
+    #[test]
+    fn test_unshield_event_decodes_from_its_data() {
+        let event = UnshieldEvent {
+            pool: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            nullifier: [7u8; 32],
+            recipient: Pubkey::new_unique(),
+            amount: 1_000_000,
+            fee: 3_000,
+            relayer: Pubkey::new_unique(),
+            slot: 500,
+        };
+        let data = event.data();
+        assert_eq!(data[..8], UnshieldEvent::DISCRIMINATOR);
+
+        let decoded = UnshieldEvent::try_from_slice(&data[8..]).unwrap();
+        assert_eq!((decoded.mint, decoded.nullifier), (event.mint, event.nullifier));
+        assert_eq!((decoded.recipient, decoded.relayer), (event.recipient, event.relayer));
+        assert_eq!((decoded.amount, decoded.fee), (1_000_000, 3_000));
+    }
+}
@@ -6,18 +6,29 @@
 //! The verification is performed using Solana's syscalls directly, avoiding
 //! external dependencies that have compatibility issues with SBF builds.
 //!
-//! Proof Format (256 bytes):
+//! Proof Format (1 + 256 bytes):
+//! - format: 1 byte (`ProofFormat`: 0 = snarkjs, 1 = arkworks)
 //! - proof_a: 64 bytes (G1 point, big-endian)
 //! - proof_b: 128 bytes (G2 point)
 //! - proof_c: 64 bytes (G1 point)
 //!
-//! Public Inputs (each 32 bytes, big-endian):
+//! snarkjs proofs are big-endian with G2 coordinates ordered (c1, c0), the
+//! layout the syscalls expect. arkworks proofs are the output of
+//! `serialize_uncompressed`: little-endian, G2 ordered (c0, c1), with
+//! flag bits in the top of each point's last byte.
+//!
+//! Withdraw Public Inputs (each 32 bytes, big-endian):
 //! - merkle_root
 //! - nullifier_hash
 //! - recipient
 //! - amount
+//! - relayer
+//! - fee
+//! - refund
+//! - expiry_slot
 
 use anchor_lang::prelude::*;
+use solana_program::keccak;
 use solana_program::alt_bn128::{
     prelude::*,
     compression::prelude::*,
@@ -26,23 +37,46 @@ use solana_program::alt_bn128::{
 /// Groth16 proof size in bytes
 pub const PROOF_SIZE: usize = 256;
 
+/// Size of a serialized proof: format byte followed by the proof points
+pub const SERIALIZED_PROOF_SIZE: usize = 1 + PROOF_SIZE;
+
+/// Flag bits arkworks packs into the top of a point's last byte
+/// (bit 7: y is negative, bit 6: point at infinity)
+const ARKWORKS_FLAG_MASK: u8 = 0b1100_0000;
+const ARKWORKS_INFINITY_FLAG: u8 = 0b0100_0000;
+
 /// Size of a single public input (field element)
 pub const PUBLIC_INPUT_SIZE: usize = 32;
 
 /// Number of public inputs for the withdrawal circuit
-/// Public inputs: root, nullifierHash, recipient, amount
-pub const NUM_PUBLIC_INPUTS: usize = 4;
+/// Public inputs: root, nullifierHash, recipient, amount, relayer, fee, refund, expiry_slot,
+/// nullifier_domain
+pub const NUM_PUBLIC_INPUTS: usize = 9;
 
 /// Total size of all public inputs
 pub const PUBLIC_INPUTS_SIZE: usize = NUM_PUBLIC_INPUTS * PUBLIC_INPUT_SIZE;
 
-/// Verifying key for the withdrawal circuit
+/// Maximum number of proofs checked in one batched pairing
+pub const MAX_BATCH_SIZE: usize = 8;
+
+/// Seed prefix for verifying key PDAs
+pub const VK_SEED: &[u8] = b"verifying_key";
+
+/// Maximum number of public inputs a registered verifying key may have
+pub const MAX_PUBLIC_INPUTS: usize = 16;
+
+/// Reference verifying key for the withdrawal circuit
 ///
 /// This key was generated during trusted setup with snarkjs.
 /// It matches the proving key used for proof generation.
+/// It is not used for verification directly: register it on-chain
+/// with `propose_verifying_key` so it can be rotated without a redeploy.
 ///
-/// Circuit: WhaleVault Withdraw (TREE_DEPTH=10)
+/// Circuit: WhaleVault Withdraw v1 (TREE_DEPTH=10)
 /// Public inputs: root, nullifierHash, recipient, amount
+///
+/// This predates relayer/fee/refund binding; regenerate it from the
+/// current `withdraw.circom` before registering.
 pub mod vk {
     /// Alpha * G1 (64 bytes)
     pub const ALPHA_G1: [u8; 64] = [
@@ -124,9 +158,107 @@ pub mod vk {
     ];
 }
 
-/// Check if verifying key is initialized (not all zeros)
-fn is_vk_initialized() -> bool {
-    vk::ALPHA_G1.iter().any(|&b| b != 0)
+/// Borrowed view of a Groth16 verifying key in Solana (big-endian) format
+///
+/// Borrowing lets the same verifier run against keys compiled into the
+/// program and keys loaded from account data.
+#[derive(Clone, Copy, Debug)]
+pub struct Groth16VerifyingKey<'a> {
+    /// Alpha * G1 (64 bytes)
+    pub alpha_g1: &'a [u8; 64],
+    /// Beta * G2 (128 bytes)
+    pub beta_g2: &'a [u8; 128],
+    /// Gamma * G2 (128 bytes)
+    pub gamma_g2: &'a [u8; 128],
+    /// Delta * G2 (128 bytes)
+    pub delta_g2: &'a [u8; 128],
+    /// IC elements (one per public input + 1)
+    pub ic: &'a [[u8; 64]],
+}
+
+impl<'a> Groth16VerifyingKey<'a> {
+    /// Number of public inputs this key verifies
+    pub fn num_public_inputs(&self) -> usize {
+        self.ic.len().saturating_sub(1)
+    }
+
+    /// Check the key is not all zeros (i.e. was actually generated)
+    pub fn is_initialized(&self) -> bool {
+        self.alpha_g1.iter().any(|&b| b != 0) && !self.ic.is_empty()
+    }
+}
+
+/// On-chain verifying key account
+///
+/// One per (pool, circuit, version), written by the pool authority via
+/// `propose_verifying_key`. A version only verifies proofs once it has been
+/// activated with `activate_verifying_keys`, and until the grace period
+/// after the next rotation ends.
+#[account]
+pub struct VerifyingKeyAccount {
+    /// Pool this key verifies proofs for
+    pub pool: Pubkey,
+    /// Circuit this key belongs to (see `verification::CircuitId`)
+    pub circuit_id: u8,
+    /// Pool verifying key version this key was proposed for
+    pub version: u32,
+    /// Alpha * G1 (64 bytes)
+    pub alpha_g1: [u8; 64],
+    /// Beta * G2 (128 bytes)
+    pub beta_g2: [u8; 128],
+    /// Gamma * G2 (128 bytes)
+    pub gamma_g2: [u8; 128],
+    /// Delta * G2 (128 bytes)
+    pub delta_g2: [u8; 128],
+    /// IC elements (one per public input + 1)
+    pub ic: Vec<[u8; 64]>,
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl VerifyingKeyAccount {
+    /// Account size (with room for MAX_PUBLIC_INPUTS)
+    pub const SIZE: usize = 32  // pool
+        + 1    // circuit_id
+        + 4    // version
+        + 64   // alpha_g1
+        + 128  // beta_g2
+        + 128  // gamma_g2
+        + 128  // delta_g2
+        + 4 + 64 * (MAX_PUBLIC_INPUTS + 1)  // ic
+        + 1;   // bump
+
+    /// Borrow as a verifier key
+    pub fn as_groth16_vk(&self) -> Groth16VerifyingKey<'_> {
+        Groth16VerifyingKey {
+            alpha_g1: &self.alpha_g1,
+            beta_g2: &self.beta_g2,
+            gamma_g2: &self.gamma_g2,
+            delta_g2: &self.delta_g2,
+            ic: &self.ic,
+        }
+    }
+}
+
+/// Serialization format of a Groth16 proof, given by its first byte
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ProofFormat {
+    /// circom/snarkjs proof converted to big-endian, G2 as (c1, c0)
+    Snarkjs = 0,
+    /// arkworks `CanonicalSerialize` uncompressed, little-endian, G2 as (c0, c1)
+    Arkworks = 1,
+}
+
+impl ProofFormat {
+    /// Parse from the format byte
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ProofFormat::Snarkjs),
+            1 => Some(ProofFormat::Arkworks),
+            _ => None,
+        }
+    }
 }
 
 /// Groth16 proof structure
@@ -158,6 +290,53 @@ impl Groth16Proof {
         Some(Self { a, b, c })
     }
 
+    /// Parse a serialized proof, dispatching on its format byte
+    ///
+    /// The returned points are always in the big-endian syscall layout.
+    pub fn deserialize(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != SERIALIZED_PROOF_SIZE {
+            return None;
+        }
+
+        match ProofFormat::from_u8(bytes[0])? {
+            ProofFormat::Snarkjs => Self::from_snarkjs_bytes(&bytes[1..]),
+            ProofFormat::Arkworks => Self::from_arkworks_bytes(&bytes[1..]),
+        }
+    }
+
+    /// Parse a snarkjs proof (already in the syscall layout)
+    pub fn from_snarkjs_bytes(bytes: &[u8]) -> Option<Self> {
+        Self::from_bytes(bytes)
+    }
+
+    /// Parse an arkworks uncompressed proof
+    ///
+    /// Each coordinate is reversed to big-endian, G2 components are swapped
+    /// to (c1, c0), and the flag bits are cleared. Points at infinity are
+    /// rejected since no valid proof contains them.
+    pub fn from_arkworks_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != PROOF_SIZE {
+            return None;
+        }
+
+        let mut a: [u8; 64] = bytes[0..64].try_into().ok()?;
+        let mut b: [u8; 128] = bytes[64..192].try_into().ok()?;
+        let mut c: [u8; 64] = bytes[192..256].try_into().ok()?;
+
+        for flags in [&mut a[63], &mut b[127], &mut c[63]] {
+            if *flags & ARKWORKS_INFINITY_FLAG != 0 {
+                return None;
+            }
+            *flags &= !ARKWORKS_FLAG_MASK;
+        }
+
+        Some(Self {
+            a: le_to_be_g1(&a),
+            b: swap_g2_components(&le_to_be_g2(&b)),
+            c: le_to_be_g1(&c),
+        })
+    }
+
     /// Convert to raw bytes
     pub fn to_bytes(&self) -> [u8; PROOF_SIZE] {
         let mut bytes = [0u8; PROOF_SIZE];
@@ -179,15 +358,68 @@ pub struct WithdrawPublicInputs {
     pub recipient: [u8; 32],
     /// Amount being withdrawn
     pub amount: [u8; 32],
+    /// Relayer address (binds the fee payee so it can't be redirected)
+    pub relayer: [u8; 32],
+    /// Relayer fee
+    pub fee: [u8; 32],
+    /// Lamports the relayer forwards to the recipient (SPL withdrawals)
+    pub refund: [u8; 32],
+    /// Last slot at which the proof may be submitted
+    pub expiry_slot: [u8; 32],
+    /// Tag binding the nullifier to the pool and its mint
+    pub nullifier_domain: [u8; 32],
 }
 
 impl WithdrawPublicInputs {
+    /// Build from on-chain values
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        root: &[u8; 32],
+        nullifier_hash: &[u8; 32],
+        recipient: &Pubkey,
+        amount: u64,
+        relayer: &Pubkey,
+        fee: u64,
+        refund: u64,
+        expiry_slot: u64,
+        nullifier_domain: &[u8; 32],
+    ) -> Self {
+        Self {
+            root: *root,
+            nullifier_hash: *nullifier_hash,
+            recipient: recipient.to_bytes(),
+            amount: u64_to_field_bytes(amount),
+            relayer: relayer.to_bytes(),
+            fee: u64_to_field_bytes(fee),
+            refund: u64_to_field_bytes(refund),
+            expiry_slot: u64_to_field_bytes(expiry_slot),
+            nullifier_domain: *nullifier_domain,
+        }
+    }
+
     /// Convert to the format expected by the verifier (big-endian field elements)
     pub fn to_verifier_inputs(&self) -> [[u8; 32]; NUM_PUBLIC_INPUTS] {
-        [self.root, self.nullifier_hash, self.recipient, self.amount]
+        [
+            self.root,
+            self.nullifier_hash,
+            self.recipient,
+            self.amount,
+            self.relayer,
+            self.fee,
+            self.refund,
+            self.expiry_slot,
+            self.nullifier_domain,
+        ]
     }
 }
 
+/// Encode a u64 as a 32-byte big-endian field element
+pub fn u64_to_field_bytes(value: u64) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes[24..32].copy_from_slice(&value.to_be_bytes());
+    bytes
+}
+
 /// Errors for Groth16 verification
 #[error_code]
 pub enum Groth16Error {
@@ -205,11 +437,20 @@ pub enum Groth16Error {
     ScalarMulFailed,
     #[msg("Point addition failed")]
     PointAddFailed,
+    #[msg("Unknown proof serialization format or malformed point")]
+    InvalidProofFormat,
+    #[msg("Batch must contain between 1 and MAX_BATCH_SIZE proofs")]
+    InvalidBatchSize,
 }
 
 /// Negate a G1 point (for pairing check)
 /// G1 point: (x, y) -> (x, p - y) where p is the field modulus
-fn negate_g1(point: &[u8; 64]) -> [u8; 64] {
+/// The point at infinity (all zeros) is its own negation
+pub(crate) fn negate_g1(point: &[u8; 64]) -> [u8; 64] {
+    if point.iter().all(|&b| b == 0) {
+        return *point;
+    }
+
     // BN254 field modulus p
     const P: [u8; 32] = [
         0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29,
@@ -232,7 +473,8 @@ fn negate_g1(point: &[u8; 64]) -> [u8; 64] {
     result
 }
 
-/// Verify a Groth16 proof for a withdrawal using Solana's alt_bn128 syscalls
+/// Verify a Groth16 proof against an arbitrary verifying key using Solana's
+/// alt_bn128 syscalls
 ///
 /// Groth16 verification equation:
 /// e(A, B) = e(alpha, beta) * e(L, gamma) * e(C, delta)
@@ -241,36 +483,44 @@ fn negate_g1(point: &[u8; 64]) -> [u8; 64] {
 /// e(-A, B) * e(alpha, beta) * e(L, gamma) * e(C, delta) = 1
 ///
 /// Where L = IC[0] + sum(public_input[i] * IC[i+1])
-pub fn verify_groth16_withdraw(
+pub fn verify_groth16(
     proof_bytes: &[u8],
-    root: &[u8; 32],
-    nullifier_hash: &[u8; 32],
-    recipient: &[u8; 32],
-    amount: &[u8; 32],
+    public_inputs: &[[u8; 32]],
+    vk: &Groth16VerifyingKey,
 ) -> Result<bool> {
-    // Parse proof
-    let proof = Groth16Proof::from_bytes(proof_bytes)
-        .ok_or(Groth16Error::InvalidProofSize)?;
-
-    // Check if verifying key is initialized
-    if !is_vk_initialized() {
-        // VK not initialized - for development, return true
-        msg!("WARNING: Verifying key not initialized, skipping proof verification");
-        return Ok(true);
-    }
+    require!(proof_bytes.len() == SERIALIZED_PROOF_SIZE, Groth16Error::InvalidProofSize);
 
-    // Compute L = IC[0] + sum(public_input[i] * IC[i+1])
-    let public_inputs = [root, nullifier_hash, recipient, amount];
+    // Parse proof in whichever format the client used
+    let proof = Groth16Proof::deserialize(proof_bytes)
+        .ok_or(Groth16Error::InvalidProofFormat)?;
+
+    let l_point = compute_linear_combination(public_inputs, vk)?;
+    check_pairing(&proof, &l_point, vk)
+}
+
+/// Compute L = IC[0] + sum(public_input[i] * IC[i+1])
+///
+/// This is the part of verification that grows with the number of public
+/// inputs, so staged verification runs it in its own transaction.
+pub fn compute_linear_combination(
+    public_inputs: &[[u8; 32]],
+    vk: &Groth16VerifyingKey,
+) -> Result<[u8; 64]> {
+    require!(vk.is_initialized(), Groth16Error::VkNotInitialized);
+    require!(
+        public_inputs.len() == vk.num_public_inputs(),
+        Groth16Error::InvalidPublicInputs
+    );
 
     // Start with IC[0]
-    let mut l_point = vk::IC[0];
+    let mut l_point = vk.ic[0];
 
     // Add public_input[i] * IC[i+1] for each public input
     for (i, input) in public_inputs.iter().enumerate() {
         // Scalar multiplication: input * IC[i+1]
         let mut scalar_mul_input = [0u8; 96]; // 64 bytes point + 32 bytes scalar
-        scalar_mul_input[0..64].copy_from_slice(&vk::IC[i + 1]);
-        scalar_mul_input[64..96].copy_from_slice(*input);
+        scalar_mul_input[0..64].copy_from_slice(&vk.ic[i + 1]);
+        scalar_mul_input[64..96].copy_from_slice(input);
 
         let mul_result = alt_bn128_multiplication(&scalar_mul_input)
             .map_err(|_| Groth16Error::ScalarMulFailed)?;
@@ -286,6 +536,17 @@ pub fn verify_groth16_withdraw(
         l_point.copy_from_slice(&add_result);
     }
 
+    Ok(l_point)
+}
+
+/// Run the final pairing check for a proof given its precomputed L point
+pub fn check_pairing(
+    proof: &Groth16Proof,
+    l_point: &[u8; 64],
+    vk: &Groth16VerifyingKey,
+) -> Result<bool> {
+    require!(vk.is_initialized(), Groth16Error::VkNotInitialized);
+
     // Prepare pairing input: 4 pairs of (G1, G2) points
     // Each pair is 192 bytes (64 G1 + 128 G2)
     let mut pairing_input = [0u8; 768]; // 4 * 192
@@ -296,24 +557,159 @@ pub fn verify_groth16_withdraw(
     pairing_input[64..192].copy_from_slice(&proof.b);
 
     // Pair 2: (alpha, beta)
-    pairing_input[192..256].copy_from_slice(&vk::ALPHA_G1);
-    pairing_input[256..384].copy_from_slice(&vk::BETA_G2);
+    pairing_input[192..256].copy_from_slice(vk.alpha_g1);
+    pairing_input[256..384].copy_from_slice(vk.beta_g2);
 
     // Pair 3: (L, gamma)
-    pairing_input[384..448].copy_from_slice(&l_point);
-    pairing_input[448..576].copy_from_slice(&vk::GAMMA_G2);
+    pairing_input[384..448].copy_from_slice(l_point);
+    pairing_input[448..576].copy_from_slice(vk.gamma_g2);
 
     // Pair 4: (C, delta)
     pairing_input[576..640].copy_from_slice(&proof.c);
-    pairing_input[640..768].copy_from_slice(&vk::DELTA_G2);
+    pairing_input[640..768].copy_from_slice(vk.delta_g2);
 
     // Perform pairing check
     // Returns true if product of pairings equals 1
     let pairing_result = alt_bn128_pairing(&pairing_input)
         .map_err(|_| Groth16Error::PairingFailed)?;
 
-    // The result is a single byte: 1 if valid, 0 if invalid
-    Ok(pairing_result[31] == 1)
+    // The result is a 32-byte big-endian integer: 1 if valid, 0 if invalid
+    Ok(pairing_result.len() == 32
+        && pairing_result[..31].iter().all(|&b| b == 0)
+        && pairing_result[31] == 1)
+}
+
+/// Verify several Groth16 proofs against the same key with one multi-pairing
+///
+/// Each proof comes with its precomputed L point. The individual equations
+/// are combined with random 128-bit weights r_i derived from a hash of the
+/// whole batch, so a bad proof can't be cancelled out by another:
+///
+/// prod(e(-r_i * A_i, B_i)) * e(sum(r_i) * alpha, beta)
+///     * e(sum(r_i * L_i), gamma) * e(sum(r_i * C_i), delta) = 1
+///
+/// This needs N + 3 pairings instead of 4N. The result is all-or-nothing:
+/// it only tells whether every proof in the batch is valid.
+pub fn verify_groth16_batch(
+    proofs: &[Groth16Proof],
+    l_points: &[[u8; 64]],
+    vk: &Groth16VerifyingKey,
+) -> Result<bool> {
+    require!(vk.is_initialized(), Groth16Error::VkNotInitialized);
+    require!(
+        !proofs.is_empty() && proofs.len() <= MAX_BATCH_SIZE,
+        Groth16Error::InvalidBatchSize
+    );
+    require!(proofs.len() == l_points.len(), Groth16Error::InvalidPublicInputs);
+
+    let weights = batch_weights(proofs, l_points);
+
+    let mut alpha_acc: Option<[u8; 64]> = None;
+    let mut l_acc: Option<[u8; 64]> = None;
+    let mut c_acc: Option<[u8; 64]> = None;
+
+    // N (-r_i * A_i, B_i) pairs followed by the three accumulated pairs
+    let mut pairing_input = Vec::with_capacity((proofs.len() + 3) * 192);
+
+    for ((proof, l_point), weight) in proofs.iter().zip(l_points).zip(weights.iter()) {
+        let weighted_a = g1_mul(&proof.a, weight)?;
+        pairing_input.extend_from_slice(&negate_g1(&weighted_a));
+        pairing_input.extend_from_slice(&proof.b);
+
+        alpha_acc = Some(g1_accumulate(alpha_acc, &g1_mul(vk.alpha_g1, weight)?)?);
+        l_acc = Some(g1_accumulate(l_acc, &g1_mul(l_point, weight)?)?);
+        c_acc = Some(g1_accumulate(c_acc, &g1_mul(&proof.c, weight)?)?);
+    }
+
+    // Batch is non-empty, so every accumulator is set
+    let (alpha_acc, l_acc, c_acc) = match (alpha_acc, l_acc, c_acc) {
+        (Some(alpha), Some(l), Some(c)) => (alpha, l, c),
+        _ => return err!(Groth16Error::InvalidBatchSize),
+    };
+
+    pairing_input.extend_from_slice(&alpha_acc);
+    pairing_input.extend_from_slice(vk.beta_g2);
+    pairing_input.extend_from_slice(&l_acc);
+    pairing_input.extend_from_slice(vk.gamma_g2);
+    pairing_input.extend_from_slice(&c_acc);
+    pairing_input.extend_from_slice(vk.delta_g2);
+
+    let pairing_result = alt_bn128_pairing(&pairing_input)
+        .map_err(|_| Groth16Error::PairingFailed)?;
+
+    Ok(pairing_result.len() == 32
+        && pairing_result[..31].iter().all(|&b| b == 0)
+        && pairing_result[31] == 1)
+}
+
+/// Derive the random weights for a batch from a hash of all its proofs
+///
+/// Weights are 128-bit big-endian scalars, large enough that a forged
+/// proof passes only with negligible probability.
+fn batch_weights(proofs: &[Groth16Proof], l_points: &[[u8; 64]]) -> Vec<[u8; 32]> {
+    let mut transcript = Vec::with_capacity(proofs.len() * (PROOF_SIZE + 64));
+    for (proof, l_point) in proofs.iter().zip(l_points) {
+        transcript.extend_from_slice(&proof.to_bytes());
+        transcript.extend_from_slice(l_point);
+    }
+    let seed = keccak::hash(&transcript).to_bytes();
+
+    (0..proofs.len())
+        .map(|i| {
+            let digest = keccak::hashv(&[&seed, &(i as u32).to_le_bytes()]).to_bytes();
+            let mut weight = [0u8; 32];
+            weight[16..32].copy_from_slice(&digest[..16]);
+            weight
+        })
+        .collect()
+}
+
+/// Scalar multiplication of a G1 point via the syscall
+pub(crate) fn g1_mul(point: &[u8; 64], scalar: &[u8; 32]) -> Result<[u8; 64]> {
+    let mut input = [0u8; 96];
+    input[0..64].copy_from_slice(point);
+    input[64..96].copy_from_slice(scalar);
+
+    let result = alt_bn128_multiplication(&input)
+        .map_err(|_| Groth16Error::ScalarMulFailed)?;
+
+    let mut out = [0u8; 64];
+    out.copy_from_slice(&result);
+    Ok(out)
+}
+
+/// Add a G1 point to an optional running sum
+fn g1_accumulate(acc: Option<[u8; 64]>, point: &[u8; 64]) -> Result<[u8; 64]> {
+    match acc {
+        Some(acc) => g1_add(&acc, point),
+        None => Ok(*point),
+    }
+}
+
+/// Addition of two G1 points via the syscall
+pub(crate) fn g1_add(a: &[u8; 64], b: &[u8; 64]) -> Result<[u8; 64]> {
+    let mut input = [0u8; 128];
+    input[0..64].copy_from_slice(a);
+    input[64..128].copy_from_slice(b);
+
+    let result = alt_bn128_addition(&input)
+        .map_err(|_| Groth16Error::PointAddFailed)?;
+
+    let mut out = [0u8; 64];
+    out.copy_from_slice(&result);
+    Ok(out)
+}
+
+/// Verify a Groth16 proof for a withdrawal
+///
+/// Public inputs: root, nullifierHash, recipient, amount, relayer, fee, refund, expiry_slot,
+/// nullifier_domain
+pub fn verify_groth16_withdraw(
+    vk: &Groth16VerifyingKey,
+    proof_bytes: &[u8],
+    public_inputs: &WithdrawPublicInputs,
+) -> Result<bool> {
+    verify_groth16(proof_bytes, &public_inputs.to_verifier_inputs(), vk)
 }
 
 /// Convert a 32-byte little-endian field element to big-endian
@@ -354,6 +750,18 @@ pub fn le_to_be_g2(le_bytes: &[u8; 128]) -> [u8; 128] {
     be_bytes
 }
 
+/// Swap the Fq2 components of a big-endian G2 point from (c0, c1) to (c1, c0)
+///
+/// The alt_bn128 syscalls follow EIP-197, which puts the imaginary part first
+pub fn swap_g2_components(point: &[u8; 128]) -> [u8; 128] {
+    let mut swapped = [0u8; 128];
+    swapped[0..32].copy_from_slice(&point[32..64]);
+    swapped[32..64].copy_from_slice(&point[0..32]);
+    swapped[64..96].copy_from_slice(&point[96..128]);
+    swapped[96..128].copy_from_slice(&point[64..96]);
+    swapped
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -378,6 +786,146 @@ mod tests {
         assert!(Groth16Proof::from_bytes(&proof_bytes).is_none());
     }
 
+    fn withdraw_vk_account() -> VerifyingKeyAccount {
+        VerifyingKeyAccount {
+            pool: Pubkey::new_unique(),
+            circuit_id: 1,
+            version: 1,
+            alpha_g1: vk::ALPHA_G1,
+            beta_g2: vk::BETA_G2,
+            gamma_g2: vk::GAMMA_G2,
+            delta_g2: vk::DELTA_G2,
+            ic: vk::IC.to_vec(),
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn test_vk_account_shape() {
+        let account = withdraw_vk_account();
+        let vk = account.as_groth16_vk();
+        assert!(vk.is_initialized());
+        assert_eq!(vk.num_public_inputs(), vk::IC.len() - 1);
+    }
+
+    #[test]
+    fn test_withdraw_public_inputs_layout() {
+        let recipient = Pubkey::new_unique();
+        let relayer = Pubkey::new_unique();
+        let inputs = WithdrawPublicInputs::new(
+            &[1u8; 32], &[2u8; 32], &recipient, 1000, &relayer, 3, 0, 500, &[4u8; 32],
+        )
+        .to_verifier_inputs();
+
+        assert_eq!(inputs.len(), NUM_PUBLIC_INPUTS);
+        assert_eq!(inputs[2], recipient.to_bytes());
+        assert_eq!(inputs[3], u64_to_field_bytes(1000));
+        assert_eq!(inputs[4], relayer.to_bytes());
+        assert_eq!(inputs[5][31], 3);
+        assert_eq!(inputs[6], [0u8; 32]);
+        assert_eq!(inputs[7], u64_to_field_bytes(500));
+        assert_eq!(inputs[8], [4u8; 32]);
+    }
+
+    #[test]
+    fn test_empty_vk_not_initialized() {
+        let mut account = withdraw_vk_account();
+        account.alpha_g1 = [0u8; 64];
+        assert!(!account.as_groth16_vk().is_initialized());
+
+        let proof_bytes = [1u8; SERIALIZED_PROOF_SIZE];
+        let inputs = [[0u8; 32]; vk::IC.len() - 1];
+        assert!(verify_groth16(&proof_bytes, &inputs, &account.as_groth16_vk()).is_err());
+    }
+
+    #[test]
+    fn test_verify_rejects_wrong_input_count() {
+        let account = withdraw_vk_account();
+        let proof_bytes = [1u8; SERIALIZED_PROOF_SIZE];
+        let inputs = [[0u8; 32]; vk::IC.len() - 2];
+        assert!(verify_groth16(&proof_bytes, &inputs, &account.as_groth16_vk()).is_err());
+    }
+
+    #[test]
+    fn test_verify_rejects_wrong_proof_size() {
+        let account = withdraw_vk_account();
+        let proof_bytes = [1u8; PROOF_SIZE];
+        let inputs = [[0u8; 32]; vk::IC.len() - 1];
+        assert!(verify_groth16(&proof_bytes, &inputs, &account.as_groth16_vk()).is_err());
+    }
+
+    #[test]
+    fn test_proof_format_dispatch() {
+        let mut bytes = [0u8; SERIALIZED_PROOF_SIZE];
+        bytes[1] = 7;
+        bytes[0] = ProofFormat::Snarkjs as u8;
+        assert_eq!(Groth16Proof::deserialize(&bytes).unwrap().a[0], 7);
+
+        bytes[0] = 2;
+        assert!(Groth16Proof::deserialize(&bytes).is_none());
+        assert!(Groth16Proof::deserialize(&bytes[..PROOF_SIZE]).is_none());
+    }
+
+    #[test]
+    fn test_arkworks_proof_matches_snarkjs() {
+        // Build a proof in the syscall layout, then encode it the way arkworks would
+        let mut be = [0u8; PROOF_SIZE];
+        for (i, byte) in be.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        // Keep the top bits of each coordinate clear, as real field elements do
+        for start in (0..PROOF_SIZE).step_by(32) {
+            be[start] &= !ARKWORKS_FLAG_MASK;
+        }
+        let expected = Groth16Proof::from_bytes(&be).unwrap();
+
+        let mut ark = [0u8; PROOF_SIZE];
+        ark[0..64].copy_from_slice(&le_to_be_g1(&expected.a));
+        ark[64..192].copy_from_slice(&le_to_be_g2(&swap_g2_components(&expected.b)));
+        ark[192..256].copy_from_slice(&le_to_be_g1(&expected.c));
+        // y-is-negative flag on A must be ignored
+        ark[63] |= 0b1000_0000;
+
+        let mut serialized = vec![ProofFormat::Arkworks as u8];
+        serialized.extend_from_slice(&ark);
+        let parsed = Groth16Proof::deserialize(&serialized).unwrap();
+        assert_eq!(parsed.to_bytes(), expected.to_bytes());
+
+        // Points at infinity are rejected
+        serialized[64] |= ARKWORKS_INFINITY_FLAG;
+        assert!(Groth16Proof::deserialize(&serialized).is_none());
+    }
+
+    #[test]
+    fn test_batch_weights_bound_to_batch() {
+        let proof_a = Groth16Proof::from_bytes(&[1u8; PROOF_SIZE]).unwrap();
+        let proof_b = Groth16Proof::from_bytes(&[2u8; PROOF_SIZE]).unwrap();
+        let l_points = [[3u8; 64], [4u8; 64]];
+
+        let weights = batch_weights(&[proof_a.clone(), proof_b.clone()], &l_points);
+        assert_eq!(weights.len(), 2);
+        assert_ne!(weights[0], weights[1]);
+        // 128-bit scalars
+        assert!(weights.iter().all(|w| w[..16].iter().all(|&b| b == 0)));
+
+        // Changing any proof changes every weight
+        let reordered = batch_weights(&[proof_b, proof_a], &l_points);
+        assert_ne!(weights[0], reordered[0]);
+        assert_ne!(weights[1], reordered[1]);
+    }
+
+    #[test]
+    fn test_batch_rejects_bad_sizes() {
+        let account = withdraw_vk_account();
+        let vk = account.as_groth16_vk();
+        assert!(verify_groth16_batch(&[], &[], &vk).is_err());
+
+        let proof = Groth16Proof::from_bytes(&[1u8; PROOF_SIZE]).unwrap();
+        let proofs = vec![proof; MAX_BATCH_SIZE + 1];
+        let l_points = vec![[0u8; 64]; MAX_BATCH_SIZE + 1];
+        assert!(verify_groth16_batch(&proofs, &l_points, &vk).is_err());
+    }
+
     #[test]
     fn test_le_to_be_conversion() {
         let le = [1u8, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -3,13 +3,18 @@
 //! Defines the instructions that can be sent to the privacy program.
 //!
 //! Proof sizes:
-//! - MVP (signature): 96 bytes [signature (64) | pubkey (32)]
-//! - Groth16 (zkSNARK): 256 bytes [proof_a (64) | proof_b (128) | proof_c (64)]
+//! - MVP (signature): 96 bytes [signature (64) | pubkey (32)], only accepted with the
+//!   `mock-prover` feature
+//! - Groth16 (zkSNARK): 257 bytes [format (1) | proof_a (64) | proof_b (128) | proof_c (64)],
+//!   where format is 0 for snarkjs and 1 for arkworks encoding
+//! - PLONK (zkSNARK): 768 bytes [9 commitments (64 each) | 6 evaluations (32 each)]
 
 use anchor_lang::prelude::*;
 
-use crate::verification::{MVP_PROOF_SIZE, ProofType};
-use crate::groth16::PROOF_SIZE as GROTH16_PROOF_SIZE;
+use crate::state::{
+    EPHEMERAL_KEY_LEN, MAX_ENCRYPTED_NOTE_LEN, MAX_SHIELD_BATCH_SIZE, MAX_UNSHIELD_BATCH_SIZE,
+};
+use crate::verification::{ProofType, MAX_AGGREGATED_WITHDRAWALS};
 
 /// Instruction data for Shield
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
@@ -18,6 +23,22 @@ pub struct ShieldData {
     pub commitment: [u8; 32],
     /// Amount to shield (in token smallest unit)
     pub amount: u64,
+    /// Note encrypted to the recipient, emitted for them to scan for
+    pub encrypted_note: Option<Vec<u8>>,
+}
+
+/// Instruction data for ShieldBatch / ShieldSolBatch
+///
+/// One deposit transfer covers the sum of `amounts`; each commitment is
+/// inserted as its own leaf.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ShieldBatchData {
+    /// Commitments to insert, in leaf order
+    pub commitments: Vec<[u8; 32]>,
+    /// Amount backing each commitment (in token smallest unit)
+    pub amounts: Vec<u64>,
+    /// Note encrypted to each commitment's recipient (empty to emit none)
+    pub encrypted_notes: Vec<Vec<u8>>,
 }
 
 /// Instruction data for Transfer
@@ -25,10 +46,16 @@ pub struct ShieldData {
 pub struct TransferData {
     /// Nullifier to spend
     pub nullifier: [u8; 32],
+    /// Merkle root the proof was generated against (current or recent)
+    pub root: [u8; 32],
     /// New commitment for recipient
     pub new_commitment: [u8; 32],
-    /// Proof (MVP: 96 bytes, Groth16: 256 bytes)
+    /// Last slot at which the proof may be submitted (bound in the proof)
+    pub expiry_slot: u64,
+    /// Proof (MVP: 96 bytes, Groth16: 257 bytes, PLONK: 768 bytes)
     pub proof: Vec<u8>,
+    /// Note encrypted to the recipient, emitted for them to scan for
+    pub encrypted_note: Option<Vec<u8>>,
 }
 
 /// Instruction data for Unshield
@@ -36,18 +63,105 @@ pub struct TransferData {
 pub struct UnshieldData {
     /// Nullifier to spend
     pub nullifier: [u8; 32],
+    /// Merkle root the proof was generated against (current or recent)
+    pub root: [u8; 32],
     /// Amount to withdraw
     pub amount: u64,
-    /// Proof (MVP: 96 bytes, Groth16: 256 bytes)
+    /// Fee paid to the relayer (bound in the proof)
+    pub fee: u64,
+    /// Lamports forwarded by the relayer to the recipient (bound in the proof)
+    pub refund: u64,
+    /// Last slot at which the proof may be submitted (bound in the proof)
+    pub expiry_slot: u64,
+    /// Proof (MVP: 96 bytes, Groth16: 257 bytes, PLONK: 768 bytes)
     pub proof: Vec<u8>,
 }
 
+/// One withdrawal attested by an aggregated unshield proof
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct AggregatedWithdrawal {
+    /// Nullifier to spend
+    pub nullifier: [u8; 32],
+    /// Amount to withdraw
+    pub amount: u64,
+    /// Fee paid to the relayer (bound in the proof)
+    pub fee: u64,
+}
+
+/// Instruction data for UnshieldAggregated
+///
+/// Recipients are passed as remaining accounts, each followed by the
+/// nullifier marker PDA for its withdrawal.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct UnshieldAggregatedData {
+    /// Withdrawals covered by the proof, in recipient order
+    pub withdrawals: Vec<AggregatedWithdrawal>,
+    /// Merkle root the proof was generated against (current or recent)
+    pub root: [u8; 32],
+    /// Last slot at which the proof may be submitted (bound in the proof)
+    pub expiry_slot: u64,
+    /// Proof (MVP: 96 bytes, Groth16: 257 bytes, PLONK: 768 bytes)
+    pub proof: Vec<u8>,
+}
+
+/// One independently proven withdrawal in an unshield batch
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct BatchWithdrawal {
+    /// Nullifier to spend
+    pub nullifier: [u8; 32],
+    /// Merkle root the proof was generated against (current or recent)
+    pub root: [u8; 32],
+    /// Amount to withdraw
+    pub amount: u64,
+    /// Fee paid to the relayer (bound in the proof)
+    pub fee: u64,
+    /// Last slot at which the proof may be submitted (bound in the proof)
+    pub expiry_slot: u64,
+    /// Proof (MVP: 96 bytes, Groth16: 257 bytes, PLONK: 768 bytes)
+    pub proof: Vec<u8>,
+}
+
+/// Terms of a payment stream, bound by the seeds of its PDA
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PaymentStreamTerms {
+    /// Caller-chosen nonce, so streams with the same terms don't collide
+    pub stream_id: [u8; 32],
+    /// Account paid each period
+    pub recipient: Pubkey,
+    /// Where the unpaid balance goes when the stream is cancelled or ends
+    pub refund_to: Pubkey,
+    /// Most lamports paid per period
+    pub amount_per_period: u64,
+    /// Length of a period in slots
+    pub period_slots: u64,
+}
+
+impl PaymentStreamTerms {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.amount_per_period > 0 && self.period_slots > 0,
+            NyxError::InvalidPaymentStream
+        );
+        Ok(())
+    }
+}
+
+/// Instruction data for UnshieldBatch
+///
+/// Recipients are passed as remaining accounts, each followed by the
+/// nullifier marker PDA and nullifier set shard for its withdrawal.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct UnshieldBatchData {
+    /// Withdrawals, each with its own proof, in recipient order
+    pub withdrawals: Vec<BatchWithdrawal>,
+}
+
 /// Custom error codes for the privacy program
 #[error_code]
 pub enum NyxError {
     #[msg("Invalid amount")]
     InvalidAmount,
-    #[msg("Invalid proof size: expected 96 (MVP) or 256 (Groth16) bytes")]
+    #[msg("Invalid proof: expected a 257-byte Groth16 or 768-byte PLONK proof (or 96-byte MVP proof with mock-prover)")]
     InvalidProof,
     #[msg("Nullifier already spent")]
     NullifierSpent,
@@ -59,21 +173,304 @@ pub enum NyxError {
     ProofVerificationFailed,
     #[msg("Amount does not match pool denomination")]
     InvalidDenomination,
+    #[msg("Signer is not the pool authority")]
+    Unauthorized,
+    #[msg("Invalid verifying key")]
+    InvalidVerifyingKey,
+    #[msg("Relayer fee exceeds withdrawal amount")]
+    FeeExceedsAmount,
+    #[msg("Refund is not allowed for this withdrawal")]
+    InvalidRefund,
+    #[msg("Proof has expired")]
+    ProofExpired,
+    #[msg("Staged verification is not at the expected stage")]
+    InvalidVerificationStage,
+    #[msg("Batch is empty or too large")]
+    InvalidBatchSize,
+    #[msg("Account is not a verification state for this pool")]
+    InvalidVerificationState,
+    #[msg("Operation is not supported for the pool's proof system")]
+    UnsupportedProofSystem,
+    #[msg("Verifying key version is not active for this pool")]
+    VkVersionNotAccepted,
+    #[msg("Verifying key update is still timelocked")]
+    VkTimelockActive,
+    #[msg("No verifying key update is pending")]
+    NoPendingVkUpdate,
+    #[msg("Timelock exceeds the maximum allowed slots")]
+    InvalidTimelock,
+    #[msg("Public input is not a canonical BN254 scalar field element")]
+    NonCanonicalFieldElement,
+    #[msg("Remaining accounts do not match the aggregated withdrawals")]
+    InvalidAggregatedAccounts,
+    #[msg("Nullifier appears more than once in the batch")]
+    DuplicateNullifier,
+    #[msg("Commitments and amounts differ in length")]
+    BatchLengthMismatch,
+    #[msg("Merkle tree still has room for commitments")]
+    TreeNotFull,
+    #[msg("Commitment chunk does not cover the inserted leaf")]
+    InvalidCommitmentChunk,
+    #[msg("Compression accounts do not match the pool's tree")]
+    InvalidCompressionAccounts,
+    #[msg("Operation not supported by the pool's tree backend")]
+    UnsupportedTreeBackend,
+    #[msg("Pool already holds commitments")]
+    PoolNotEmpty,
+    #[msg("Unsupported Merkle tree depth")]
+    InvalidTreeDepth,
+    #[msg("Commitment already inserted")]
+    DuplicateCommitment,
+    #[msg("Commitment record does not match the commitment")]
+    InvalidCommitmentRecord,
+    #[msg("Merkle root is not in the pool's root history; regenerate the proof against a recent root")]
+    StaleRoot,
+    #[msg("Merkle path does not lead from the leaf to the root")]
+    InvalidMerkleProof,
+    #[msg("Root history size is out of range or does not match the pool account")]
+    InvalidRootHistorySize,
+    #[msg("Nullifier set shard is full")]
+    NullifierSetFull,
+    #[msg("Account is not the nullifier set shard for this nullifier")]
+    InvalidNullifierSet,
+    #[msg("Nullifier marker is still inside its finality window")]
+    MarkerNotFinal,
+    #[msg("Finality window exceeds the maximum")]
+    InvalidFinalityWindow,
+    #[msg("Nullifier tree queue is full; insert queued nullifiers first")]
+    NullifierQueueFull,
+    #[msg("Nullifier tree queue is empty")]
+    NullifierQueueEmpty,
+    #[msg("Nullifier tree is full")]
+    NullifierTreeFull,
+    #[msg("Low leaf is not in the nullifier tree or does not bracket the nullifier")]
+    InvalidLowLeaf,
+    #[msg("Nullifier must be nonzero")]
+    ZeroNullifier,
+    #[msg("Relayer fee exceeds the maximum")]
+    InvalidRelayerFee,
+    #[msg("Relayer fee exceeds the pool's relayer fee rate")]
+    FeeAboveRelayerRate,
+    #[msg("Signer is not the pending pool authority")]
+    NotPendingAuthority,
+    #[msg("Pool is paused")]
+    PoolPaused,
+    #[msg("Not enough pool guardians signed")]
+    GuardianQuorumNotMet,
+    #[msg("Guardian list or threshold is invalid")]
+    InvalidGuardianSet,
+    #[msg("Verifying key updates are frozen")]
+    VkUpdatesFrozen,
+    #[msg("Pool account layout is already current")]
+    PoolUpToDate,
+    #[msg("Pool value accounting overflowed")]
+    AccountingOverflow,
+    #[msg("Vault balance is below the value locked in the pool")]
+    PoolInsolvent,
+    #[msg("Root is newer than the pool's spend delay")]
+    RootNotMature,
+    #[msg("Spend delay exceeds the maximum")]
+    InvalidSpendDelay,
+    #[msg("Pool has too few deposits to unshield from")]
+    AnonymitySetTooSmall,
+    #[msg("Minimum anonymity set exceeds the maximum")]
+    InvalidAnonymitySet,
+    #[msg("Withdrawal rate limit exceeded")]
+    WithdrawalRateLimited,
+    #[msg("Deposit would exceed the pool's deposit cap")]
+    DepositCapExceeded,
+    #[msg("Pool closure has not been scheduled")]
+    CloseNotScheduled,
+    #[msg("Pool closure timelock has not elapsed")]
+    CloseTimelockActive,
+    #[msg("Pool still holds value")]
+    PoolNotDrained,
+    #[msg("Pool registry is full")]
+    PoolRegistryFull,
+    #[msg("Protocol is paused")]
+    ProtocolPaused,
+    #[msg("Invalid protocol config")]
+    InvalidProtocolConfig,
+    #[msg("Unshields have not been halted long enough for emergency withdrawals")]
+    EmergencyWithdrawLocked,
+    #[msg("Emergency withdrawals need a fixed-denomination pool")]
+    EmergencyWithdrawUnsupported,
+    #[msg("Mint is not allowlisted")]
+    MintNotAllowlisted,
+    #[msg("Mint allowlist is full")]
+    MintAllowlistFull,
+    #[msg("Instruction not supported by multi-asset pools")]
+    MultiAssetPoolUnsupported,
+    #[msg("Pool is not a multi-asset pool")]
+    NotMultiAssetPool,
+    #[msg("Pool is not an NFT pool")]
+    NotNftPool,
+    #[msg("Compressed NFT proof is too long")]
+    InvalidCnftProof,
+    #[msg("Encrypted note is too long")]
+    EncryptedNoteTooLong,
+    #[msg("Shielding for a recipient requires an encrypted note")]
+    MissingEncryptedNote,
+    #[msg("Remaining accounts do not match the payouts")]
+    InvalidRecipientAccounts,
+    #[msg("Remaining accounts do not match the batched withdrawals")]
+    InvalidBatchAccounts,
+    #[msg("Swap route must go through a supported DEX")]
+    UnsupportedSwapProgram,
+    #[msg("Swap route data is too long")]
+    InvalidSwapRoute,
+    #[msg("Swap input and output pools must be single-asset pools of different mints")]
+    InvalidSwapPools,
+    #[msg("Swap route left part of the input unspent")]
+    SwapInputNotSpent,
+    #[msg("Swap returned less than the minimum output")]
+    SwapSlippageExceeded,
+    #[msg("Shielded staking needs a SOL pool as input and a single-asset pool as output")]
+    InvalidStakePools,
+    #[msg("Stake deposit minted fewer pool tokens than the new note holds")]
+    StakeDepositTooSmall,
+    #[msg("Yield strategies are only supported by SOL pools")]
+    YieldStrategyUnsupported,
+    #[msg("Strategy buffer must be between MIN_STRATEGY_BUFFER_BPS and 10000 basis points")]
+    InvalidStrategyBuffer,
+    #[msg("Unwind the yield strategy before changing its stake pool")]
+    StrategyInUse,
+    #[msg("Account is not an SPL stake pool")]
+    InvalidStakePool,
+    #[msg("Note is timelocked until a later slot")]
+    NoteTimelocked,
+    #[msg("Claim links are only supported by SOL pools")]
+    ClaimLinkUnsupported,
+    #[msg("Claim link expiry must be in the future")]
+    InvalidClaimExpiry,
+    #[msg("Claim key or its signature does not match the claim link")]
+    InvalidClaimSecret,
+    #[msg("Claim link has expired")]
+    ClaimLinkExpired,
+    #[msg("Claim link has not expired yet")]
+    ClaimLinkNotExpired,
+    #[msg("Migrations move value between two different single-asset SOL pools")]
+    InvalidMigrationPools,
+    #[msg("Spent notes net of fees must add up to the migrated note's amount")]
+    MigrationValueMismatch,
+    #[msg("Encrypted note is too short to hold its ephemeral key")]
+    EncryptedNoteTooShort,
+    #[msg("Withdrawal does not match the queued intent")]
+    UnshieldIntentMismatch,
+    #[msg("Queued withdrawal is outside its execution window")]
+    UnshieldOutsideWindow,
+    #[msg("Payment streams need a non-zero amount per period and period")]
+    InvalidPaymentStream,
+    #[msg("No stream payment is due yet")]
+    StreamPaymentNotDue,
+    #[msg("Escrow withdrawals need the arbiter's signed resolution for the recipient")]
+    MissingEscrowResolution,
+    #[msg("Destination program is not registered")]
+    DestinationNotAllowlisted,
+    #[msg("Destination registry is full")]
+    DestinationRegistryFull,
+    #[msg("Destination call does not match its authority or left part of the payout")]
+    InvalidDestinationCall,
+    #[msg("Calling program is not allowed to shield into this pool")]
+    CallerNotAllowed,
+    #[msg("Allowed callers must be distinct from the default pubkey and fit the pool")]
+    InvalidAllowedCallers,
+    #[msg("The pool's CPI policy needs the instructions sysvar")]
+    InstructionsSysvarRequired,
+    #[msg("Fee disputes need the relayer's signed fee quote")]
+    MissingFeeQuote,
+    #[msg("The withdrawal's fee is within the relayer's quote")]
+    FeeWithinQuote,
+    #[msg("The withdrawal's nullifier has not been spent")]
+    NullifierNotSpent,
+    #[msg("Bond can only be withdrawn after the unbonding period")]
+    BondNotWithdrawable,
+    #[msg("Quoted fee exceeds the pool's relayer fee or the URL is too long")]
+    InvalidRelayerQuote,
+    #[msg("Relayer is not allowed to submit spends for this pool")]
+    RelayerNotAllowed,
+    #[msg("Allowed relayers must be distinct from the default pubkey and fit the pool")]
+    InvalidAllowedRelayers,
+    #[msg("Account is not the pool's PDA")]
+    InvalidPoolAddress,
+    #[msg("Signer is not the pending protocol admin")]
+    NotPendingAdmin,
+    #[msg("Emergency withdrawal was not committed in an earlier slot")]
+    EmergencyWithdrawNotCommitted,
+    #[msg("Stake pool is not allowlisted")]
+    StakePoolNotAllowlisted,
+    #[msg("Stake pool allowlist is full")]
+    StakePoolAllowlistFull,
+    #[msg("Nullifier is waiting in the tree's queue")]
+    NullifierQueued,
+    #[msg("Activation needs the proposed verifying key of every circuit")]
+    MissingVerifyingKey,
+    #[msg("Leaf is newer than the pool's spend delay")]
+    LeafNotMature,
+    #[msg("Pool is scheduled to close and takes no deposits")]
+    PoolClosing,
+    #[msg("Tree archive is not the new archive of the pool's full tree")]
+    InvalidTreeArchive,
+}
+
+/// Check an encrypted note fits in the log it is emitted to and carries
+/// its ephemeral key
+pub fn validate_encrypted_note(encrypted_note: Option<&[u8]>) -> Result<()> {
+    if let Some(note) = encrypted_note {
+        require!(note.len() <= MAX_ENCRYPTED_NOTE_LEN, NyxError::EncryptedNoteTooLong);
+        require!(note.len() >= EPHEMERAL_KEY_LEN, NyxError::EncryptedNoteTooShort);
+    }
+    Ok(())
 }
 
 impl ShieldData {
     pub fn validate(&self) -> Result<()> {
         require!(self.amount > 0, NyxError::InvalidAmount);
+        validate_encrypted_note(self.encrypted_note.as_deref())?;
+        Ok(())
+    }
+}
+
+impl ShieldBatchData {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            !self.commitments.is_empty() && self.commitments.len() <= MAX_SHIELD_BATCH_SIZE,
+            NyxError::InvalidBatchSize
+        );
+        require!(
+            self.commitments.len() == self.amounts.len(),
+            NyxError::BatchLengthMismatch
+        );
+        require!(self.amounts.iter().all(|&amount| amount > 0), NyxError::InvalidAmount);
+        require!(
+            self.encrypted_notes.is_empty() || self.encrypted_notes.len() == self.commitments.len(),
+            NyxError::BatchLengthMismatch
+        );
+        for note in &self.encrypted_notes {
+            validate_encrypted_note(Some(note))?;
+        }
+        for (i, commitment) in self.commitments.iter().enumerate() {
+            require!(
+                !self.commitments[..i].contains(commitment),
+                NyxError::DuplicateCommitment
+            );
+        }
         Ok(())
     }
+
+    /// Sum of all amounts, or `None` on overflow
+    pub fn total_amount(&self) -> Option<u64> {
+        self.amounts
+            .iter()
+            .try_fold(0u64, |sum, &amount| sum.checked_add(amount))
+    }
 }
 
 impl TransferData {
     pub fn validate(&self) -> Result<()> {
-        // Accept both MVP (96 bytes) and Groth16 (256 bytes) proofs
-        let valid_size = self.proof.len() == MVP_PROOF_SIZE
-            || self.proof.len() == GROTH16_PROOF_SIZE;
-        require!(valid_size, NyxError::InvalidProof);
+        // Groth16 (257 bytes), PLONK (768 bytes), or MVP (96 bytes) with mock-prover
+        require!(self.proof_type().is_some(), NyxError::InvalidProof);
+        validate_encrypted_note(self.encrypted_note.as_deref())?;
         Ok(())
     }
 
@@ -86,10 +483,59 @@ impl TransferData {
 impl UnshieldData {
     pub fn validate(&self) -> Result<()> {
         require!(self.amount > 0, NyxError::InvalidAmount);
-        // Accept both MVP (96 bytes) and Groth16 (256 bytes) proofs
-        let valid_size = self.proof.len() == MVP_PROOF_SIZE
-            || self.proof.len() == GROTH16_PROOF_SIZE;
-        require!(valid_size, NyxError::InvalidProof);
+        require!(self.fee <= self.amount, NyxError::FeeExceedsAmount);
+        // Groth16 (257 bytes), PLONK (768 bytes), or MVP (96 bytes) with mock-prover
+        require!(self.proof_type().is_some(), NyxError::InvalidProof);
+        Ok(())
+    }
+
+    /// Get the detected proof type
+    pub fn proof_type(&self) -> Option<ProofType> {
+        ProofType::detect(&self.proof)
+    }
+}
+
+impl AggregatedWithdrawal {
+    pub fn validate(&self) -> Result<()> {
+        require!(self.amount > 0, NyxError::InvalidAmount);
+        require!(self.fee <= self.amount, NyxError::FeeExceedsAmount);
+        Ok(())
+    }
+}
+
+impl UnshieldAggregatedData {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            !self.withdrawals.is_empty()
+                && self.withdrawals.len() <= MAX_AGGREGATED_WITHDRAWALS,
+            NyxError::InvalidBatchSize
+        );
+        for (i, withdrawal) in self.withdrawals.iter().enumerate() {
+            withdrawal.validate()?;
+            require!(
+                self.withdrawals[..i]
+                    .iter()
+                    .all(|other| other.nullifier != withdrawal.nullifier),
+                NyxError::DuplicateNullifier
+            );
+        }
+        // Groth16 (257 bytes), PLONK (768 bytes), or MVP (96 bytes) with mock-prover
+        require!(self.proof_type().is_some(), NyxError::InvalidProof);
+        Ok(())
+    }
+
+    /// Get the detected proof type
+    pub fn proof_type(&self) -> Option<ProofType> {
+        ProofType::detect(&self.proof)
+    }
+}
+
+impl BatchWithdrawal {
+    pub fn validate(&self) -> Result<()> {
+        require!(self.amount > 0, NyxError::InvalidAmount);
+        require!(self.fee <= self.amount, NyxError::FeeExceedsAmount);
+        // Groth16 (257 bytes), PLONK (768 bytes), or MVP (96 bytes) with mock-prover
+        require!(self.proof_type().is_some(), NyxError::InvalidProof);
         Ok(())
     }
 
@@ -98,3 +544,63 @@ impl UnshieldData {
         ProofType::detect(&self.proof)
     }
 }
+
+impl UnshieldBatchData {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            !self.withdrawals.is_empty() && self.withdrawals.len() <= MAX_UNSHIELD_BATCH_SIZE,
+            NyxError::InvalidBatchSize
+        );
+        for (i, withdrawal) in self.withdrawals.iter().enumerate() {
+            withdrawal.validate()?;
+            require!(
+                self.withdrawals[..i]
+                    .iter()
+                    .all(|other| other.nullifier != withdrawal.nullifier),
+                NyxError::DuplicateNullifier
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch(commitments: Vec<[u8; 32]>, amounts: Vec<u64>) -> ShieldBatchData {
+        ShieldBatchData { commitments, amounts, encrypted_notes: Vec::new() }
+    }
+
+    #[test]
+    fn test_shield_batch_size_and_lengths_are_checked() {
+        assert!(batch(vec![[1u8; 32], [2u8; 32]], vec![10, 20]).validate().is_ok());
+
+        let err = batch(Vec::new(), Vec::new()).validate().unwrap_err();
+        assert_eq!(err, NyxError::InvalidBatchSize.into());
+        let too_many = MAX_SHIELD_BATCH_SIZE + 1;
+        let commitments = (0..too_many as u8).map(|i| [i; 32]).collect();
+        let err = batch(commitments, vec![1; too_many]).validate().unwrap_err();
+        assert_eq!(err, NyxError::InvalidBatchSize.into());
+
+        let err = batch(vec![[1u8; 32], [2u8; 32]], vec![10]).validate().unwrap_err();
+        assert_eq!(err, NyxError::BatchLengthMismatch.into());
+        let mut notes = batch(vec![[1u8; 32], [2u8; 32]], vec![10, 20]);
+        notes.encrypted_notes = vec![vec![0u8; EPHEMERAL_KEY_LEN]];
+        assert_eq!(notes.validate().unwrap_err(), NyxError::BatchLengthMismatch.into());
+    }
+
+    #[test]
+    fn test_shield_batch_rejects_empty_and_repeated_deposits() {
+        let err = batch(vec![[1u8; 32], [2u8; 32]], vec![10, 0]).validate().unwrap_err();
+        assert_eq!(err, NyxError::InvalidAmount.into());
+        let err = batch(vec![[1u8; 32], [1u8; 32]], vec![10, 20]).validate().unwrap_err();
+        assert_eq!(err, NyxError::DuplicateCommitment.into());
+    }
+
+    #[test]
+    fn test_shield_batch_total_checks_overflow() {
+        assert_eq!(batch(vec![[1u8; 32], [2u8; 32]], vec![10, 20]).total_amount(), Some(30));
+        assert_eq!(batch(vec![[1u8; 32], [2u8; 32]], vec![u64::MAX, 1]).total_amount(), None);
+    }
+}
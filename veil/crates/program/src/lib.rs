@@ -2,20 +2,51 @@
 //!
 //! On-chain program for managing privacy pool state.
 //! Supports both native SOL and SPL token deposits.
+//!
+//! Admin instructions only need the authority's signature. Rent is paid by
+//! a separate `payer`, so authorities can be multisig vaults or governance
+//! PDAs signing through CPI.
 
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount};
+use anchor_spl::associated_token::{get_associated_token_address_with_program_id, AssociatedToken};
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
 // Valid Base58 program ID (placeholder - replace with actual deployed program ID)
 // Using system program format: 32 bytes = 43-44 Base58 chars
 declare_id!("3qhVPvz8T1WiozCLEfhUuv8WZHDPpEfnAzq2iSatULc7");
 
+pub mod bubblegum;
+pub mod claim;
+pub mod commitment_chunk;
+pub mod commitment_record;
+pub mod compression;
+pub mod cpi_guard;
+pub mod destination;
+pub mod escrow;
+pub mod events;
 pub mod groth16;
 pub mod instructions;
+pub mod maturity;
 pub mod merkle;
+pub mod migration;
 pub mod nullifier;
+pub mod nullifier_tree;
+pub mod plonk;
+pub mod poseidon;
 pub mod processor;
+pub mod queue;
+pub mod rate_limit;
+pub mod relayer;
+pub mod rollover;
+pub mod snapshot;
+pub mod stake;
+pub mod staged;
 pub mod state;
+pub mod stream;
+pub mod swap;
+#[cfg(any(test, feature = "test-vectors"))]
+pub mod test_vectors;
 pub mod token;
 pub mod verification;
 
@@ -27,113 +58,6951 @@ pub mod veil_program {
     ///
     /// # Arguments
     /// * `denomination` - Fixed deposit amount in lamports (0 = custom/variable pool)
-    pub fn initialize(ctx: Context<Initialize>, denomination: u64) -> Result<()> {
-        processor::process_initialize(ctx, denomination)
+    /// * `root_history_size` - Number of past roots proofs may use
+    ///   (`state::DEFAULT_ROOT_HISTORY_SIZE` unless deposit traffic needs more)
+    /// * `decimals` - Decimals of the pool's mint (9 for native SOL); the
+    ///   pool is only created if they match the mint account, so the
+    ///   denomination is checked against the unit it was meant in
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        denomination: u64,
+        root_history_size: u16,
+        decimals: u8,
+    ) -> Result<()> {
+        processor::process_initialize(ctx, denomination, root_history_size, decimals)
+    }
+
+    /// Schedule closing the pool (authority only)
+    ///
+    /// Starts the `POOL_CLOSE_DELAY_SLOTS` timelock after which
+    /// `close_pool` may run. The pool takes no deposits from then on, so
+    /// none can keep it from draining.
+    pub fn schedule_pool_close(ctx: Context<UpdatePoolConfig>) -> Result<()> {
+        processor::process_schedule_pool_close(ctx)
+    }
+
+    /// Cancel a scheduled pool close and reopen deposits (authority only)
+    pub fn cancel_pool_close(ctx: Context<UpdatePoolConfig>) -> Result<()> {
+        processor::process_cancel_pool_close(ctx)
+    }
+
+    /// Close a drained pool and reclaim its rent (authority only)
+    ///
+    /// Requires the scheduled timelock to have elapsed, every shielded
+    /// amount to have been unshielded and the vault to be empty. SPL pools
+    /// pass their `vault_token_account`.
+    pub fn close_pool(ctx: Context<ClosePool>) -> Result<()> {
+        processor::process_close_pool(ctx)
+    }
+
+    /// Upgrade the pool account to the current layout version (authority only)
+    ///
+    /// Reallocates the account if the current layout needs more room, with
+    /// the payer covering the extra rent, and backfills fields that older
+    /// layouts lack. SPL pools pass their `vault_token_account`, whose
+    /// balance seeds the value-locked totals.
+    pub fn migrate_pool(ctx: Context<MigratePool>) -> Result<()> {
+        processor::process_migrate_pool(ctx)
+    }
+
+    /// Create the global `PoolRegistry` (protocol admin only)
+    ///
+    /// The admin becomes the registry's authority, the only account that
+    /// may register pools afterwards.
+    pub fn initialize_pool_registry(ctx: Context<InitializePoolRegistry>) -> Result<()> {
+        processor::process_initialize_pool_registry(ctx)
+    }
+
+    /// Create the canonical 0.1/1/10/100 SOL pools and register them
+    ///
+    /// Signed by the `PoolRegistry` authority; `initialize_pool_registry`
+    /// must run first.
+    pub fn initialize_standard_pools(
+        ctx: Context<InitializeStandardPools>,
+        root_history_size: u16,
+    ) -> Result<()> {
+        processor::process_initialize_standard_pools(ctx, root_history_size)
+    }
+
+    /// Create the multi-asset pool, whose notes may hold any allowlisted mint
+    ///
+    /// Notes commit to `verification::asset_id` of their mint, so one tree
+    /// and anonymity set covers every asset. Deposits go through
+    /// `shield_multi_asset` and withdrawals through `unshield_multi_asset`,
+    /// which bind the asset into their proofs; the pool registers keys for
+    /// the `Shield` and `MultiAssetUnshield` circuits besides `Transfer`.
+    pub fn initialize_multi_asset_pool(
+        ctx: Context<InitializeMultiAssetPool>,
+        root_history_size: u16,
+    ) -> Result<()> {
+        processor::process_initialize_multi_asset_pool(ctx, root_history_size)
+    }
+
+    /// Create the NFT pool, whose notes each hold one NFT
+    ///
+    /// A note commits to the owner's spending key and the NFT's asset ID,
+    /// so the NFT can be released to a wallet with no on-chain link to the
+    /// one that deposited it. Like the multi-asset pool, it registers keys
+    /// for the `Shield` and `MultiAssetUnshield` circuits.
+    pub fn initialize_nft_pool(
+        ctx: Context<InitializeNftPool>,
+        root_history_size: u16,
+    ) -> Result<()> {
+        processor::process_initialize_nft_pool(ctx, root_history_size)
+    }
+
+    /// Create the protocol-wide config read by every pool
+    ///
+    /// Signed by the program's upgrade authority, so only the deployer can
+    /// pick the first admin; `admin` may be a governance PDA. Shields,
+    /// transfers and unshields that pay a relayer fee require the config
+    /// account, so clients built before it must add it to those account
+    /// lists; `unshield_self`, the NFT unshields and `emergency_withdraw`
+    /// don't take it.
+    pub fn initialize_protocol_config(
+        ctx: Context<InitializeProtocolConfig>,
+        admin: Pubkey,
+        vk_authority: Pubkey,
+        default_relayer_fee_bps: u16,
+        treasury_fee_share_bps: u16,
+    ) -> Result<()> {
+        processor::process_initialize_protocol_config(
+            ctx,
+            admin,
+            vk_authority,
+            default_relayer_fee_bps,
+            treasury_fee_share_bps,
+        )
+    }
+
+    /// Change the protocol VK authority and fee settings (admin only)
+    pub fn update_protocol_config(
+        ctx: Context<UpdateProtocolConfig>,
+        vk_authority: Pubkey,
+        default_relayer_fee_bps: u16,
+        treasury_fee_share_bps: u16,
+    ) -> Result<()> {
+        processor::process_update_protocol_config(
+            ctx,
+            vk_authority,
+            default_relayer_fee_bps,
+            treasury_fee_share_bps,
+        )
+    }
+
+    /// Propose a new protocol admin (admin only)
+    ///
+    /// As with `propose_authority`, the current admin stays in control until
+    /// `new_admin` signs `accept_protocol_admin`. Proposing the default
+    /// pubkey cancels a pending proposal.
+    pub fn propose_protocol_admin(
+        ctx: Context<UpdateProtocolConfig>,
+        new_admin: Pubkey,
+    ) -> Result<()> {
+        processor::process_propose_protocol_admin(ctx, new_admin)
+    }
+
+    /// Accept a pending protocol admin proposal (proposed admin only)
+    pub fn accept_protocol_admin(ctx: Context<AcceptProtocolAdmin>) -> Result<()> {
+        processor::process_accept_protocol_admin(ctx)
+    }
+
+    /// Pause or unpause shields and transfers on every pool (admin only)
+    ///
+    /// Unshields stay open, so a protocol pause never traps funds; pause a
+    /// pool's unshields to halt them.
+    pub fn set_protocol_paused(ctx: Context<UpdateProtocolConfig>, paused: bool) -> Result<()> {
+        processor::process_set_protocol_paused(ctx, paused)
+    }
+
+    /// Allow SPL pools for a mint (admin only)
+    ///
+    /// Creates the `MintAllowlist` on first use.
+    pub fn allow_mint(ctx: Context<UpdateMintAllowlist>, mint: Pubkey) -> Result<()> {
+        processor::process_allow_mint(ctx, mint)
+    }
+
+    /// Stop new pools and deposits for a mint (admin only)
+    pub fn disallow_mint(ctx: Context<UpdateMintAllowlist>, mint: Pubkey) -> Result<()> {
+        processor::process_disallow_mint(ctx, mint)
+    }
+
+    /// Let `unshield_cpi` withdraw into a program (admin only)
+    ///
+    /// Creates the `DestinationRegistry` on first use.
+    pub fn allow_destination_program(
+        ctx: Context<UpdateDestinationRegistry>,
+        program: Pubkey,
+    ) -> Result<()> {
+        processor::process_allow_destination_program(ctx, program)
+    }
+
+    /// Stop `unshield_cpi` withdrawals into a program (admin only)
+    pub fn disallow_destination_program(
+        ctx: Context<UpdateDestinationRegistry>,
+        program: Pubkey,
+    ) -> Result<()> {
+        processor::process_disallow_destination_program(ctx, program)
+    }
+
+    /// Let yield strategies deposit with a stake pool (admin only)
+    ///
+    /// Creates the `StakePoolAllowlist` on first use.
+    pub fn allow_stake_pool(
+        ctx: Context<UpdateStakePoolAllowlist>,
+        stake_pool: Pubkey,
+    ) -> Result<()> {
+        processor::process_allow_stake_pool(ctx, stake_pool)
+    }
+
+    /// Stop yield strategy deposits with a stake pool (admin only)
+    pub fn disallow_stake_pool(
+        ctx: Context<UpdateStakePoolAllowlist>,
+        stake_pool: Pubkey,
+    ) -> Result<()> {
+        processor::process_disallow_stake_pool(ctx, stake_pool)
+    }
+
+    /// Change how many past roots the pool accepts proofs against (authority only)
+    ///
+    /// Reallocates the pool account; the payer funds growth and gets the
+    /// rent back on shrink.
+    pub fn resize_root_history(
+        ctx: Context<ResizeRootHistory>,
+        root_history_size: u16,
+    ) -> Result<()> {
+        processor::process_resize_root_history(ctx, root_history_size)
+    }
+
+    /// Propose the pool's next Groth16 verifying key for a circuit (authority only)
+    ///
+    /// The key is stored under the pending version and only takes effect
+    /// after `activate_verifying_keys` once the update delay has passed.
+    pub fn propose_verifying_key(
+        ctx: Context<ProposeVerifyingKey>,
+        circuit_id: u8,
+        alpha_g1: [u8; 64],
+        beta_g2: [u8; 128],
+        gamma_g2: [u8; 128],
+        delta_g2: [u8; 128],
+        ic: Vec<[u8; 64]>,
+    ) -> Result<()> {
+        processor::process_propose_verifying_key(ctx, circuit_id, alpha_g1, beta_g2, gamma_g2, delta_g2, ic)
+    }
+
+    /// Propose the pool's next PLONK verifying key for a circuit (authority only)
+    pub fn propose_plonk_verifying_key(
+        ctx: Context<ProposePlonkVerifyingKey>,
+        circuit_id: u8,
+        key: plonk::PlonkVerifyingKeyData,
+    ) -> Result<()> {
+        processor::process_propose_plonk_verifying_key(ctx, circuit_id, key)
+    }
+
+    /// Activate the proposed verifying keys once their timelock has expired
+    ///
+    /// Takes the proposed key of every `CircuitId`, in discriminator order,
+    /// for the pool's proof system as remaining accounts.
+    pub fn activate_verifying_keys(ctx: Context<UpdatePoolConfig>) -> Result<()> {
+        processor::process_activate_verifying_keys(ctx)
+    }
+
+    /// Configure the verifying key update delay and grace period (authority only)
+    ///
+    /// The new values take effect once the current delay has passed.
+    pub fn set_vk_timelock(
+        ctx: Context<UpdatePoolConfig>,
+        update_delay_slots: u64,
+        grace_period_slots: u64,
+    ) -> Result<()> {
+        processor::process_set_vk_timelock(ctx, update_delay_slots, grace_period_slots)
+    }
+
+    /// Set the relayer fee, in basis points (authority only)
+    pub fn set_relayer_fee(ctx: Context<UpdatePoolConfig>, fee_bps: u16) -> Result<()> {
+        processor::process_set_relayer_fee(ctx, fee_bps)
+    }
+
+    /// Set the account receiving protocol funds (authority only)
+    pub fn set_treasury(ctx: Context<UpdatePoolConfig>, treasury: Pubkey) -> Result<()> {
+        processor::process_set_treasury(ctx, treasury)
+    }
+
+    /// Propose a new pool authority (authority only)
+    ///
+    /// The current authority stays in control until `new_authority` signs
+    /// `accept_authority`, so a mistyped key cannot lock the pool's admin
+    /// functions. Proposing the default pubkey cancels a pending proposal.
+    pub fn propose_authority(ctx: Context<UpdatePoolConfig>, new_authority: Pubkey) -> Result<()> {
+        processor::process_propose_authority(ctx, new_authority)
+    }
+
+    /// Accept a pending authority proposal (proposed authority only)
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        processor::process_accept_authority(ctx)
+    }
+
+    /// Replace the pool's guardian set (authority only)
+    ///
+    /// Guardians can only pause the pool and freeze verifying key updates,
+    /// and need `threshold` of them to sign each action. Once appointed,
+    /// `threshold` of them must also sign their replacement, passed as
+    /// remaining accounts, so the authority can't undo a freeze.
+    pub fn set_guardians(
+        ctx: Context<SetGuardians>,
+        guardians: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        processor::process_set_guardians(ctx, guardians, threshold)
+    }
+
+    /// Pause the pool (guardian quorum)
+    ///
+    /// Rejects shields and transfers, and unshields too unless
+    /// `allow_unshields` is set so users can still exit. Meant for
+    /// responding to a discovered circuit or verifier bug. Pass the signing
+    /// guardians as remaining accounts.
+    pub fn pause(ctx: Context<GuardianAction>, allow_unshields: bool) -> Result<()> {
+        processor::process_pause(ctx, allow_unshields)
+    }
+
+    /// Lift a pause (guardian quorum)
+    pub fn unpause(ctx: Context<GuardianAction>) -> Result<()> {
+        processor::process_unpause(ctx)
+    }
+
+    /// Freeze or unfreeze verifying key proposals and activation (guardian quorum)
+    pub fn freeze_vk_updates(ctx: Context<GuardianAction>, frozen: bool) -> Result<()> {
+        processor::process_freeze_vk_updates(ctx, frozen)
+    }
+
+    /// Set how many slots deposits must age before they can be spent (authority only)
+    ///
+    /// Spends must prove against a root at least this old, which defeats
+    /// deposit-then-immediately-withdraw timing correlation. Pair it with a
+    /// root history long enough to hold a root that old.
+    pub fn set_min_spend_delay(ctx: Context<UpdatePoolConfig>, delay_slots: u64) -> Result<()> {
+        processor::process_set_min_spend_delay(ctx, delay_slots)
+    }
+
+    /// Set how many deposits the pool must exceed before unshields are allowed (authority only)
+    ///
+    /// Protects early users of a fresh pool, whose withdrawals would
+    /// otherwise be trivially linkable to the few deposits made so far.
+    pub fn set_min_anonymity_set(
+        ctx: Context<UpdatePoolConfig>,
+        min_anonymity_set: u64,
+    ) -> Result<()> {
+        processor::process_set_min_anonymity_set(ctx, min_anonymity_set)
+    }
+
+    /// Cap the vault balance shields may reach, 0 for no cap (authority only)
+    ///
+    /// Lets a pool with a newly deployed verifier grow gradually.
+    pub fn set_deposit_cap(ctx: Context<UpdatePoolConfig>, deposit_cap: u64) -> Result<()> {
+        processor::process_set_deposit_cap(ctx, deposit_cap)
+    }
+
+    /// Let relayers recover the rent of fresh recipients, 0 for none
+    /// (authority only)
+    ///
+    /// A recipient wallet with no SOL can't hold tokens, so the relayer
+    /// creates its token account and may forward a `refund`, both in SOL.
+    /// SPL unshields that create the account or pay a refund may then
+    /// charge up to `max_rent_fee`, in the pool's units, on top of the
+    /// relayer fee rate; others only the rate. It is part of the proven fee
+    /// and goes to the relayer alone, not the treasury.
+    pub fn set_max_rent_fee(ctx: Context<UpdatePoolConfig>, max_rent_fee: u64) -> Result<()> {
+        processor::process_set_max_rent_fee(ctx, max_rent_fee)
+    }
+
+    /// Limit withdrawals per window of slots (authority only)
+    ///
+    /// Caps the amount and number of withdrawals over a sliding window of
+    /// `window_slots`, bounding the damage of a compromised verifier or
+    /// proving key. Zero disables the limit or one of its dimensions.
+    pub fn set_withdrawal_rate_limit(
+        ctx: Context<UpdatePoolConfig>,
+        window_slots: u64,
+        max_amount: u64,
+        max_count: u64,
+    ) -> Result<()> {
+        processor::process_set_withdrawal_rate_limit(ctx, window_slots, max_amount, max_count)
+    }
+
+    /// Set how long legacy nullifier markers must age before closing (authority only)
+    pub fn set_marker_finality_window(
+        ctx: Context<UpdatePoolConfig>,
+        finality_slots: u64,
+    ) -> Result<()> {
+        processor::process_set_marker_finality_window(ctx, finality_slots)
+    }
+
+    /// Select the verifier backend for the pool's spend proofs (authority only)
+    pub fn set_proof_system(
+        ctx: Context<UpdatePoolConfig>,
+        proof_system: state::ProofSystem,
+    ) -> Result<()> {
+        processor::process_set_proof_system(ctx, proof_system)
+    }
+
+    /// Restrict which programs may shield into the pool through CPI
+    /// (authority only)
+    ///
+    /// `allowed_callers` lists the front-end programs accepted under
+    /// `CpiPolicy::Allowlist`, at most `state::MAX_ALLOWED_CALLERS`, and
+    /// replaces the previous list. Shields into pools that restrict CPI
+    /// must pass the instructions sysvar.
+    pub fn set_cpi_policy(
+        ctx: Context<UpdatePoolConfig>,
+        policy: state::CpiPolicy,
+        allowed_callers: Vec<Pubkey>,
+    ) -> Result<()> {
+        processor::process_set_cpi_policy(ctx, policy, allowed_callers)
+    }
+
+    /// Restrict which relayers may submit the pool's transfers and
+    /// withdrawals (authority only)
+    ///
+    /// `allowed_relayers` lists the relayers accepted under
+    /// `RelayerPolicy::Allowlist`, at most `state::MAX_ALLOWED_RELAYERS`,
+    /// and replaces the previous list. Under `RelayerPolicy::RegistryOnly`
+    /// spends must pass the relayer's bond.
+    pub fn set_relayer_policy(
+        ctx: Context<UpdatePoolConfig>,
+        policy: state::RelayerPolicy,
+        allowed_relayers: Vec<Pubkey>,
+    ) -> Result<()> {
+        processor::process_set_relayer_policy(ctx, policy, allowed_relayers)
+    }
+
+    /// Opt a SOL pool into staking idle vault SOL, or change its buffer
+    /// (authority only)
+    ///
+    /// The vault keeps `buffer_bps` of the value locked liquid and deposits
+    /// the rest with `stake_pool`, which must be on the protocol's stake
+    /// pool allowlist, on each rebalance. Deposited lamports still count
+    /// towards solvency, at their value as of the last rebalance. The stake
+    /// pool can only be changed once the strategy is unwound.
+    pub fn configure_yield_strategy(
+        ctx: Context<ConfigureYieldStrategy>,
+        buffer_bps: u16,
+    ) -> Result<()> {
+        processor::process_configure_yield_strategy(ctx, buffer_bps)
+    }
+
+    /// Move the vault towards its yield strategy's buffer (anyone)
+    ///
+    /// Marks the vault's stake pool tokens to market, then deposits liquid
+    /// SOL above the buffer with the stake pool, or redeems stake pool
+    /// tokens when the vault is below it. Deposits are rejected while the
+    /// pool is paused or the stake pool is off the allowlist; redemptions
+    /// never are, so the vault can always be refilled for withdrawals, up
+    /// to what the stake pool's reserve holds.
+    pub fn rebalance_yield_strategy(ctx: Context<RebalanceYieldStrategy>) -> Result<()> {
+        processor::process_rebalance_yield_strategy(ctx)
     }
 
     /// Shield native SOL - deposit SOL and create commitment
-    pub fn shield_sol(ctx: Context<ShieldSol>, commitment: [u8; 32], amount: u64) -> Result<()> {
-        processor::process_shield_sol(ctx, commitment, amount)
+    ///
+    /// The `commitment_record` PDA stores the leaf index so the recipient
+    /// can find it; an existing record rejects the commitment as a duplicate.
+    /// `encrypted_note`, if given, is emitted in an `EncryptedNote` event so
+    /// the recipient can find the note by scanning logs with their viewing
+    /// key; it is never stored.
+    pub fn shield_sol(
+        ctx: Context<ShieldSol>,
+        commitment: [u8; 32],
+        amount: u64,
+        encrypted_note: Option<Vec<u8>>,
+    ) -> Result<()> {
+        processor::process_shield_sol(ctx, commitment, amount, encrypted_note)
     }
 
     /// Shield SPL tokens - deposit tokens and create commitment
-    pub fn shield(ctx: Context<Shield>, commitment: [u8; 32], amount: u64) -> Result<()> {
-        processor::process_shield(ctx, commitment, amount)
+    ///
+    /// The `commitment_record` PDA stores the leaf index so the recipient
+    /// can find it; an existing record rejects the commitment as a duplicate.
+    /// `encrypted_note` is emitted as for `shield_sol`.
+    pub fn shield(
+        ctx: Context<Shield>,
+        commitment: [u8; 32],
+        amount: u64,
+        encrypted_note: Option<Vec<u8>>,
+    ) -> Result<()> {
+        processor::process_shield(ctx, commitment, amount, encrypted_note)
+    }
+
+    /// Shield native SOL into a note for someone else (payroll, gifts)
+    ///
+    /// `commitment` is built from the recipient's spending key, so only the
+    /// recipient can spend it. The depositor picked the note's opening, so
+    /// `encrypted_note` is required: it is how the recipient learns it.
+    pub fn shield_sol_for(
+        ctx: Context<ShieldSol>,
+        commitment: [u8; 32],
+        amount: u64,
+        encrypted_note: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_shield_sol_for(ctx, commitment, amount, encrypted_note)
+    }
+
+    /// Shield SPL tokens into a note for someone else, as `shield_sol_for`
+    pub fn shield_for(
+        ctx: Context<Shield>,
+        commitment: [u8; 32],
+        amount: u64,
+        encrypted_note: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_shield_for(ctx, commitment, amount, encrypted_note)
+    }
+
+    /// Shield SPL tokens of any allowlisted mint into the multi-asset pool
+    ///
+    /// `proof` shows that `commitment` opens to `amount` of the mint's
+    /// asset, so a deposit can't be withdrawn as a different asset.
+    /// `encrypted_note` is emitted as for `shield_sol`.
+    pub fn shield_multi_asset(
+        ctx: Context<ShieldMultiAsset>,
+        commitment: [u8; 32],
+        amount: u64,
+        proof: Vec<u8>,
+        encrypted_note: Option<Vec<u8>>,
+    ) -> Result<()> {
+        processor::process_shield_multi_asset(ctx, commitment, amount, proof, encrypted_note)
+    }
+
+    /// Shield an NFT - deposit it into its own vault and create a commitment
+    ///
+    /// `proof` shows that `commitment` opens to an amount of 1 of the NFT's
+    /// asset. `encrypted_note` is emitted as for `shield_sol`.
+    pub fn shield_nft(
+        ctx: Context<ShieldNft>,
+        commitment: [u8; 32],
+        proof: Vec<u8>,
+        encrypted_note: Option<Vec<u8>>,
+    ) -> Result<()> {
+        processor::process_shield_nft(ctx, commitment, proof, encrypted_note)
+    }
+
+    /// Shield a compressed NFT into the NFT pool
+    ///
+    /// Transfers the leaf to the vault authority through Bubblegum and
+    /// commits to the NFT's asset ID; `proof` is the shield proof as for
+    /// `shield_nft`. The leaf's Merkle proof nodes follow any compression
+    /// accounts in the remaining accounts.
+    pub fn shield_cnft<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ShieldCnft<'info>>,
+        commitment: [u8; 32],
+        leaf: bubblegum::CnftLeaf,
+        proof: Vec<u8>,
+        encrypted_note: Option<Vec<u8>>,
+    ) -> Result<()> {
+        processor::process_shield_cnft(ctx, commitment, leaf, proof, encrypted_note)
+    }
+
+    /// Shield native SOL for several commitments with one transfer
+    ///
+    /// `amounts[i]` backs `commitments[i]`; the depositor pays the sum once
+    /// and every commitment is inserted as its own leaf. The remaining
+    /// accounts carry one commitment record PDA per commitment, after the
+    /// tree accounts of a compressed pool. `encrypted_notes` is empty or
+    /// holds one note per commitment.
+    pub fn shield_sol_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ShieldSolBatch<'info>>,
+        commitments: Vec<[u8; 32]>,
+        amounts: Vec<u64>,
+        encrypted_notes: Vec<Vec<u8>>,
+    ) -> Result<()> {
+        processor::process_shield_sol_batch(ctx, commitments, amounts, encrypted_notes)
+    }
+
+    /// Shield SPL tokens for several commitments with one transfer
+    pub fn shield_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ShieldBatch<'info>>,
+        commitments: Vec<[u8; 32]>,
+        amounts: Vec<u64>,
+        encrypted_notes: Vec<Vec<u8>>,
+    ) -> Result<()> {
+        processor::process_shield_batch(ctx, commitments, amounts, encrypted_notes)
+    }
+
+    /// Create the account archiving a run of leaves of the current tree (permissionless)
+    ///
+    /// Every instruction that inserts leaves writes them to the chunk
+    /// covering their index, creating it when the leaf is its first, so
+    /// this only lets a chunk be paid for ahead of time.
+    pub fn init_commitment_chunk(ctx: Context<InitCommitmentChunk>, chunk_index: u32) -> Result<()> {
+        processor::process_init_commitment_chunk(ctx, chunk_index)
+    }
+
+    /// Create the pool's spent-nullifier bloom filter (permissionless)
+    ///
+    /// Spends that pass the filter add their nullifier to it. It is only
+    /// a hint for wallets, so spends may leave it out.
+    pub fn init_nullifier_bloom(ctx: Context<InitNullifierBloom>) -> Result<()> {
+        processor::process_init_nullifier_bloom(ctx)
+    }
+
+    /// Create the pool's stats account (permissionless)
+    ///
+    /// Shields and unshields that pass it record their activity in
+    /// per-epoch buckets.
+    pub fn init_pool_stats(ctx: Context<InitPoolStats>) -> Result<()> {
+        processor::process_init_pool_stats(ctx)
+    }
+
+    /// Create the pool's indexed nullifier tree (permissionless)
+    ///
+    /// Spends queue their nullifier in the tree, so it must exist before
+    /// the pool's next spend.
+    pub fn init_nullifier_tree(ctx: Context<InitNullifierTree>) -> Result<()> {
+        processor::process_init_nullifier_tree(ctx)
+    }
+
+    /// Insert the oldest queued nullifier into the indexed tree (permissionless)
+    ///
+    /// # Arguments
+    /// * `low_leaf` - Leaf whose gap contains the nullifier
+    /// * `low_index` - Index of the low leaf
+    /// * `low_path` - Siblings of the low leaf, from the leaf level up
+    pub fn insert_nullifier_tree(
+        ctx: Context<InsertNullifierTree>,
+        low_leaf: nullifier_tree::IndexedLeaf,
+        low_index: u64,
+        low_path: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        processor::process_insert_nullifier_tree(ctx, low_leaf, low_index, low_path)
+    }
+
+    /// Insert a spent nullifier that found the tree's queue full
+    /// (permissionless)
+    ///
+    /// # Arguments
+    /// * `nullifier` - A nullifier in the pool's nullifier set, not queued
+    ///   and not yet in the tree
+    /// * `low_leaf` - Leaf whose gap contains the nullifier
+    /// * `low_index` - Index of the low leaf
+    /// * `low_path` - Siblings of the low leaf, from the leaf level up
+    pub fn backfill_nullifier_tree(
+        ctx: Context<BackfillNullifierTree>,
+        nullifier: [u8; 32],
+        low_leaf: nullifier_tree::IndexedLeaf,
+        low_index: u64,
+        low_path: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        processor::process_backfill_nullifier_tree(ctx, nullifier, low_leaf, low_index, low_path)
+    }
+
+    /// Move an empty pool onto an spl-account-compression tree (authority only)
+    ///
+    /// `merkle_tree` must be allocated and owned by the compression program
+    /// beforehand. From then on, inserting instructions append to it through
+    /// the remaining accounts [merkle_tree, tree_authority, noop_program,
+    /// compression_program] instead of the in-account tree.
+    ///
+    /// Refused for now: compressed trees hash with keccak, which the spend
+    /// circuits can't prove membership in (see `compression`).
+    pub fn enable_compressed_tree(
+        ctx: Context<EnableCompressedTree>,
+        max_depth: u32,
+        max_buffer_size: u32,
+    ) -> Result<()> {
+        processor::process_enable_compressed_tree(ctx, max_depth, max_buffer_size)
+    }
+
+    /// Archive the full Merkle tree and start a new one (permissionless)
+    ///
+    /// Only allowed once the tree has no room left. The final root is kept
+    /// in a `TreeEpoch` PDA that spends of old notes pass in. Insertions
+    /// that don't fit roll the tree over themselves when passed that PDA,
+    /// so this only lets the rollover happen ahead of them.
+    pub fn rollover_tree(ctx: Context<RolloverTree>) -> Result<()> {
+        processor::process_rollover_tree(ctx)
+    }
+
+    /// Check that `leaf` is in the pool's tree (read-only, for CPI callers)
+    ///
+    /// `root` must be the current root or in the root history. `path` holds
+    /// the `TREE_DEPTH` siblings from the leaf up, or only the bottom
+    /// `TREE_DEPTH - CANOPY_DEPTH` when proving against the current root,
+    /// whose upper siblings come from the pool's canopy. Fails unless the
+    /// path checks out and the leaf is older than the pool's spend delay,
+    /// going by the leaf's `CommitmentRecord` when passed and by the root's
+    /// age otherwise (see `maturity`).
+    pub fn verify_membership(
+        ctx: Context<VerifyMembership>,
+        root: [u8; 32],
+        leaf: [u8; 32],
+        leaf_index: u64,
+        path: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        processor::process_verify_membership(ctx, root, leaf, leaf_index, path)
+    }
+
+    /// Close a legacy nullifier marker and reclaim its rent (permissionless)
+    ///
+    /// The nullifier is mirrored into the pool's nullifier set first, so it
+    /// stays spent, and added to its bloom filter, which spends from before
+    /// the filter existed never reached. Only allowed once the marker is
+    /// older than the pool's finality window; the rent goes to the pool
+    /// treasury.
+    pub fn close_nullifier_marker(ctx: Context<CloseNullifierMarker>) -> Result<()> {
+        processor::process_close_nullifier_marker(ctx)
+    }
+
+    /// Return a versioned snapshot of the pool's tree (read-only)
+    ///
+    /// Indexers simulate this to bootstrap from the current frontier and
+    /// root history instead of replaying every insertion.
+    pub fn snapshot_tree(ctx: Context<SnapshotTree>) -> Result<snapshot::TreeSnapshot> {
+        processor::process_snapshot_tree(ctx)
     }
 
     /// Private transfer - spend commitment and create new one
+    ///
+    /// `root` is the root the proof was generated against: the current root,
+    /// one still in the root history, or the final root of the archived tree
+    /// passed as `tree_epoch`. The proof is rejected once the current slot
+    /// passes `expiry_slot`. `encrypted_note` is emitted as for `shield_sol`.
     pub fn transfer(
         ctx: Context<Transfer>,
         nullifier: [u8; 32],
+        root: [u8; 32],
         new_commitment: [u8; 32],
+        expiry_slot: u64,
+        proof: Vec<u8>,
+        encrypted_note: Option<Vec<u8>>,
+    ) -> Result<()> {
+        processor::process_transfer(
+            ctx,
+            nullifier,
+            root,
+            new_commitment,
+            expiry_slot,
+            proof,
+            encrypted_note,
+        )
+    }
+
+    /// Private join-split - spend two notes and create two
+    ///
+    /// The proof shows the outputs hold the same value per asset as the
+    /// inputs, so dust notes can be merged, or a note split into payment and
+    /// change by pairing it with a zero-amount dummy input, without leaving
+    /// the pool. Both notes must be spent against the same `root`.
+    pub fn join_split(
+        ctx: Context<JoinSplit>,
+        nullifiers: [[u8; 32]; 2],
+        root: [u8; 32],
+        commitments: [[u8; 32]; 2],
+        expiry_slot: u64,
         proof: Vec<u8>,
     ) -> Result<()> {
-        processor::process_transfer(ctx, nullifier, new_commitment, proof)
+        processor::process_join_split(ctx, nullifiers, root, commitments, expiry_slot, proof)
     }
 
-    /// Unshield native SOL - spend commitment and withdraw SOL
-    pub fn unshield_sol(
-        ctx: Context<UnshieldSol>,
-        nullifier: [u8; 32],
-        amount: u64,
-        proof: Vec<u8>,
-    ) -> Result<()> {
-        processor::process_unshield_sol(ctx, nullifier, amount, proof)
-    }
+    /// Unshield native SOL - spend commitment and withdraw SOL
+    ///
+    /// `fee` and `refund` are public inputs of the proof, binding the
+    /// relayer that may submit it and what it is paid. The recipient gets
+    /// `amount - fee`; the fee, capped by the pool's relayer fee rate, is
+    /// split between the relayer and the pool treasury. `root` is checked
+    /// as for `transfer`. The proof is rejected once the current slot
+    /// passes `expiry_slot`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn unshield_sol(
+        ctx: Context<UnshieldSol>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        refund: u64,
+        expiry_slot: u64,
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_unshield_sol(ctx, nullifier, root, amount, fee, refund, expiry_slot, proof)
+    }
+
+    /// Unshield native SOL to a wallet that submits the proof itself
+    ///
+    /// As `unshield_sol` with the recipient as both signer and fee payer,
+    /// so no relayer is involved and no relayer fee is charged. The proof
+    /// binds the recipient as its relayer with a zero fee and refund. Only
+    /// for wallets already funded for transaction fees, such as burners;
+    /// paying for the exit links the recipient to whoever funded it.
+    /// Pools restricting relayers must accept the recipient as one.
+    pub fn unshield_self(
+        ctx: Context<UnshieldSelf>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        expiry_slot: u64,
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_unshield_self(ctx, nullifier, root, amount, expiry_slot, proof)
+    }
+
+    /// Unshield SPL tokens - spend commitment and withdraw tokens
+    ///
+    /// The fee is paid in tokens as for `unshield_sol`. `refund` lamports
+    /// are forwarded from the relayer to the recipient.
+    #[allow(clippy::too_many_arguments)]
+    pub fn unshield(
+        ctx: Context<Unshield>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        refund: u64,
+        expiry_slot: u64,
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_unshield(ctx, nullifier, root, amount, fee, refund, expiry_slot, proof)
+    }
+
+    /// Unshield part of a note's SOL, returning the rest as a change note
+    ///
+    /// As `unshield_sol`, but the spent note may hold more than `amount`;
+    /// the remainder goes into a new note for the owner, whose commitment
+    /// the proof binds and the pool inserts into the tree. Variable pools
+    /// only, since change would break a fixed pool's uniform notes.
+    #[allow(clippy::too_many_arguments)]
+    pub fn unshield_sol_with_change(
+        ctx: Context<UnshieldSolWithChange>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        refund: u64,
+        expiry_slot: u64,
+        change_commitment: [u8; 32],
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_unshield_sol_with_change(
+            ctx,
+            nullifier,
+            root,
+            amount,
+            fee,
+            refund,
+            expiry_slot,
+            change_commitment,
+            proof,
+        )
+    }
+
+    /// Unshield part of a note's tokens, returning the rest as a change note
+    ///
+    /// As `unshield_sol_with_change` for SPL pools.
+    #[allow(clippy::too_many_arguments)]
+    pub fn unshield_with_change(
+        ctx: Context<UnshieldWithChange>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        refund: u64,
+        expiry_slot: u64,
+        change_commitment: [u8; 32],
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_unshield_with_change(
+            ctx,
+            nullifier,
+            root,
+            amount,
+            fee,
+            refund,
+            expiry_slot,
+            change_commitment,
+            proof,
+        )
+    }
+
+    /// Unshield SOL from a timelocked note
+    ///
+    /// As `unshield_sol` for a note shielded under
+    /// `timelocked_commitment(commitment, not_before_slot)`. The proof binds
+    /// the unlock slot, and is rejected before the current slot reaches it,
+    /// so the note can back vesting or escrow payments that only the
+    /// owner can claim, and only once unlocked.
+    #[allow(clippy::too_many_arguments)]
+    pub fn unshield_sol_timelocked(
+        ctx: Context<UnshieldSolTimelocked>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        refund: u64,
+        expiry_slot: u64,
+        not_before_slot: u64,
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_unshield_sol_timelocked(
+            ctx,
+            nullifier,
+            root,
+            amount,
+            fee,
+            refund,
+            expiry_slot,
+            not_before_slot,
+            proof,
+        )
+    }
+
+    /// Unshield SPL tokens from a timelocked note
+    ///
+    /// As `unshield_sol_timelocked` for SPL pools.
+    #[allow(clippy::too_many_arguments)]
+    pub fn unshield_timelocked(
+        ctx: Context<UnshieldTimelocked>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        refund: u64,
+        expiry_slot: u64,
+        not_before_slot: u64,
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_unshield_timelocked(
+            ctx,
+            nullifier,
+            root,
+            amount,
+            fee,
+            refund,
+            expiry_slot,
+            not_before_slot,
+            proof,
+        )
+    }
+
+    /// Unshield SOL from an escrow note
+    ///
+    /// As `unshield_sol` for a note shielded under
+    /// `escrow_commitment(commitment, arbiter, escrow_id)`. The proof binds
+    /// the arbiter and deal, and is only accepted right after the arbiter's
+    /// Ed25519 signature over `escrow::resolution_message` for the
+    /// recipient, so the parties to a shielded OTC deal can both hold the
+    /// note while the arbiter decides who is paid.
+    #[allow(clippy::too_many_arguments)]
+    pub fn unshield_sol_escrow(
+        ctx: Context<UnshieldSolEscrow>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        refund: u64,
+        expiry_slot: u64,
+        arbiter: Pubkey,
+        escrow_id: [u8; 32],
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_unshield_sol_escrow(
+            ctx,
+            nullifier,
+            root,
+            amount,
+            fee,
+            refund,
+            expiry_slot,
+            arbiter,
+            escrow_id,
+            proof,
+        )
+    }
+
+    /// Unshield SPL tokens from an escrow note
+    ///
+    /// As `unshield_sol_escrow` for SPL pools.
+    #[allow(clippy::too_many_arguments)]
+    pub fn unshield_escrow(
+        ctx: Context<UnshieldEscrow>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        refund: u64,
+        expiry_slot: u64,
+        arbiter: Pubkey,
+        escrow_id: [u8; 32],
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_unshield_escrow(
+            ctx,
+            nullifier,
+            root,
+            amount,
+            fee,
+            refund,
+            expiry_slot,
+            arbiter,
+            escrow_id,
+            proof,
+        )
+    }
+
+    /// Unshield SPL tokens from the multi-asset pool
+    ///
+    /// As `unshield`, paying out of the vault for `mint`. The proof also
+    /// binds the mint's asset ID, so only notes of that asset can drain it.
+    #[allow(clippy::too_many_arguments)]
+    pub fn unshield_multi_asset(
+        ctx: Context<UnshieldMultiAsset>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        refund: u64,
+        expiry_slot: u64,
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_unshield_multi_asset(
+            ctx,
+            nullifier,
+            root,
+            amount,
+            fee,
+            refund,
+            expiry_slot,
+            proof,
+        )
+    }
+
+    /// Unshield an NFT to a fresh wallet
+    ///
+    /// The proof is a multi-asset unshield of amount 1 with no fee, since an
+    /// NFT can't be split. The relayer pays for the recipient's token
+    /// account and gets the emptied vault's rent back; `refund` lamports are
+    /// forwarded to the recipient as for `unshield`.
+    pub fn unshield_nft(
+        ctx: Context<UnshieldNft>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        refund: u64,
+        expiry_slot: u64,
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_unshield_nft(ctx, nullifier, root, refund, expiry_slot, proof)
+    }
+
+    /// Unshield a compressed NFT to a fresh wallet
+    ///
+    /// As `unshield_nft`, transferring the vault's leaf to `recipient`
+    /// through Bubblegum. The leaf's Merkle proof nodes are passed as the
+    /// remaining accounts.
+    #[allow(clippy::too_many_arguments)]
+    pub fn unshield_cnft<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UnshieldCnft<'info>>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        refund: u64,
+        expiry_slot: u64,
+        leaf: bubblegum::CnftLeaf,
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_unshield_cnft(ctx, nullifier, root, refund, expiry_slot, leaf, proof)
+    }
+
+    /// Unshield native SOL to several recipients with one aggregated proof
+    ///
+    /// Pass `[recipient, nullifier_marker, nullifier_set]` triples as
+    /// remaining accounts, one per withdrawal and in the same order, with
+    /// the recipient and nullifier set shard writable.
+    pub fn unshield_aggregated<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UnshieldAggregated<'info>>,
+        withdrawals: Vec<instructions::AggregatedWithdrawal>,
+        root: [u8; 32],
+        expiry_slot: u64,
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_unshield_aggregated(ctx, withdrawals, root, expiry_slot, proof)
+    }
+
+    /// Unshield native SOL from one note to several recipients (payroll)
+    ///
+    /// One proof authorizes every payout, with each amount a public input;
+    /// the note holds their sum plus `fee`. Pass the recipients as writable
+    /// remaining accounts, one per amount and in the same order.
+    #[allow(clippy::too_many_arguments)]
+    pub fn unshield_many<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UnshieldMany<'info>>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amounts: Vec<u64>,
+        fee: u64,
+        expiry_slot: u64,
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_unshield_many(ctx, nullifier, root, amounts, fee, expiry_slot, proof)
+    }
+
+    /// Unshield native SOL for several independent notes in one transaction
+    ///
+    /// Each withdrawal carries its own unshield proof, so relayers can
+    /// bundle unrelated requests. Pass `[recipient, nullifier_marker,
+    /// nullifier_set]` triples as remaining accounts, one per withdrawal
+    /// and in the same order, with the recipient and nullifier set shard
+    /// writable. Every note must belong to the same tree.
+    pub fn unshield_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UnshieldBatch<'info>>,
+        withdrawals: Vec<instructions::BatchWithdrawal>,
+    ) -> Result<()> {
+        processor::process_unshield_batch(ctx, withdrawals)
+    }
+
+    /// Unshield SPL tokens into another program through CPI
+    ///
+    /// The note is unshielded to the destination authority for the call
+    /// (see `destination::derive_destination_authority`), which the proof
+    /// binds as recipient. `data` is then invoked on `destination_program`
+    /// with the remaining accounts, signed by the authority, and must move
+    /// the whole payout out of the authority's token account. The
+    /// destination program must be in the `DestinationRegistry`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn unshield_cpi<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UnshieldCpi<'info>>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        expiry_slot: u64,
+        proof: Vec<u8>,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_unshield_cpi(ctx, nullifier, root, amount, fee, expiry_slot, proof, data)
+    }
+
+    /// Swap a note of one SPL pool into a note of another through a DEX
+    ///
+    /// The note is unshielded to the swap authority for `commitment` and
+    /// `min_amount_out` (see `swap::derive_swap_authority`), which the
+    /// proof binds as recipient. The route in `route_data` is invoked
+    /// through `dex_program` with the remaining accounts, and exactly
+    /// `min_amount_out` of its output is shielded into the output pool;
+    /// any surplus goes to that pool's treasury, so the relayer earns only
+    /// the proven fee. The route must spend the whole input, and the output
+    /// pool must use the incremental tree.
+    #[allow(clippy::too_many_arguments)]
+    pub fn shielded_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ShieldedSwap<'info>>,
+        commitment: [u8; 32],
+        min_amount_out: u64,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        expiry_slot: u64,
+        proof: Vec<u8>,
+        route_data: Vec<u8>,
+        encrypted_note: Option<Vec<u8>>,
+    ) -> Result<()> {
+        processor::process_shielded_swap(
+            ctx,
+            commitment,
+            min_amount_out,
+            nullifier,
+            root,
+            amount,
+            fee,
+            expiry_slot,
+            proof,
+            route_data,
+            encrypted_note,
+        )
+    }
+
+    /// Stake a note of the SOL pool into an SPL stake pool, keeping the
+    /// stake pool's tokens shielded
+    ///
+    /// The vault deposits `amount - fee` lamports with the stake pool,
+    /// which mints its tokens into the output pool's vault, and
+    /// `pool_tokens` of them are committed under `commitment`. The proof
+    /// binds the recipient from `stake::derive_deposit_authority`. The
+    /// deposit must mint at least `pool_tokens`; any rounding excess stays
+    /// in the vault. The output pool must use the incremental tree.
+    #[allow(clippy::too_many_arguments)]
+    pub fn shielded_stake(
+        ctx: Context<ShieldedStake>,
+        commitment: [u8; 32],
+        pool_tokens: u64,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        expiry_slot: u64,
+        proof: Vec<u8>,
+        encrypted_note: Option<Vec<u8>>,
+    ) -> Result<()> {
+        processor::process_shielded_stake(
+            ctx,
+            commitment,
+            pool_tokens,
+            nullifier,
+            root,
+            amount,
+            fee,
+            expiry_slot,
+            proof,
+            encrypted_note,
+        )
+    }
+
+    /// Move notes of one SOL pool into a note of another
+    ///
+    /// Each of `withdrawals` spends a note of the source pool with an
+    /// unshield proof whose recipient is the migration authority for
+    /// `commitment` and `amount_out` (see
+    /// `migration::derive_migration_authority`). The notes net of their
+    /// fees must add up to exactly `amount_out`, which the source vault
+    /// pays straight into the destination vault before `commitment` is
+    /// inserted into the destination pool's tree. Remaining accounts are
+    /// a (nullifier_marker, nullifier_set) pair per withdrawal. The
+    /// destination pool must use the incremental tree.
+    pub fn migrate<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Migrate<'info>>,
+        commitment: [u8; 32],
+        amount_out: u64,
+        withdrawals: Vec<instructions::BatchWithdrawal>,
+        encrypted_note: Option<Vec<u8>>,
+    ) -> Result<()> {
+        processor::process_migrate(ctx, commitment, amount_out, withdrawals, encrypted_note)
+    }
+
+    /// Spend a note of a SOL pool into a link claimable by secret
+    ///
+    /// The vault pays `amount - fee` into a claim link PDA derived from
+    /// `claim_hash`, `refund_to` and `claim_expiry_slot`, which the proof
+    /// binds as its recipient. `claim_hash` commits to a key generated for
+    /// the link; whoever holds its secret key can `claim` it to a fresh
+    /// recipient, so a payment can be sent as a link without knowing the
+    /// recipient's address. The
+    /// relayer pays the link's rent out of its fee; the rent goes with the
+    /// lamports when the link is claimed or refunded.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_claim(
+        ctx: Context<CreateClaim>,
+        claim_hash: [u8; 32],
+        refund_to: Pubkey,
+        claim_expiry_slot: u64,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        expiry_slot: u64,
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_create_claim(
+            ctx,
+            claim_hash,
+            refund_to,
+            claim_expiry_slot,
+            nullifier,
+            root,
+            amount,
+            fee,
+            expiry_slot,
+            proof,
+        )
+    }
+
+    /// Claim a link with its key's signature (anyone)
+    ///
+    /// Pays the link's lamports and rent to `recipient` and closes it. The
+    /// instruction before must be the Ed25519 signature of `claim_key` over
+    /// `claim::claim_message` for the link and `recipient`, so a claim seen
+    /// in flight can't be resubmitted to another recipient. The link is
+    /// still a bearer instrument until claimed.
+    pub fn claim(ctx: Context<Claim>, claim_key: Pubkey) -> Result<()> {
+        processor::process_claim(ctx, claim_key)
+    }
+
+    /// Return an expired, unclaimed link to its refund address (anyone)
+    pub fn reclaim_claim(ctx: Context<ReclaimClaim>) -> Result<()> {
+        processor::process_reclaim_claim(ctx)
+    }
+
+    /// Spend a note of a SOL pool into a stream of periodic payments
+    ///
+    /// The vault pays `amount - fee` into a payment stream PDA derived from
+    /// the terms, which the proof binds as its recipient. The stream then
+    /// pays `terms.recipient` at most `terms.amount_per_period` per
+    /// `terms.period_slots`, starting now, for shielded subscriptions and
+    /// salaries. The relayer pays the stream's rent out of its fee; the rent
+    /// goes to the refund address when the stream closes.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_payment_stream(
+        ctx: Context<CreatePaymentStream>,
+        terms: instructions::PaymentStreamTerms,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        expiry_slot: u64,
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_create_payment_stream(
+            ctx,
+            terms,
+            nullifier,
+            root,
+            amount,
+            fee,
+            expiry_slot,
+            proof,
+        )
+    }
+
+    /// Pay a payment stream's recipient for a due period (anyone)
+    ///
+    /// Meant to be cranked by an automation thread. Closes the stream to its
+    /// refund address once it is paid out.
+    pub fn execute_stream_payment(ctx: Context<ExecuteStreamPayment>) -> Result<()> {
+        processor::process_execute_stream_payment(ctx)
+    }
+
+    /// Cancel a payment stream, refunding what is left (refund address only)
+    pub fn cancel_payment_stream(ctx: Context<CancelPaymentStream>) -> Result<()> {
+        processor::process_cancel_payment_stream(ctx)
+    }
+
+    /// Queue a SOL withdrawal by the hash of its parameters (anyone)
+    ///
+    /// Step one of a two-phase withdrawal. The payer funds the intent's
+    /// rent, which is refunded when it is executed or cancelled, so the
+    /// refund links the payer to the withdrawal: queue from a relayer or a
+    /// burner wallet (see `queue`).
+    pub fn queue_unshield(ctx: Context<QueueUnshield>, intent_hash: [u8; 32]) -> Result<()> {
+        processor::process_queue_unshield(ctx, intent_hash)
+    }
+
+    /// Execute a queued SOL withdrawal
+    ///
+    /// As `unshield_sol`, but only for the withdrawal hashed into a queued
+    /// intent, and only between `UNSHIELD_QUEUE_MIN_DELAY_SLOTS` and
+    /// `UNSHIELD_QUEUE_WINDOW_SLOTS` after it was queued. The proof's
+    /// expiry must leave room for the delay the owner picks.
+    pub fn execute_unshield(
+        ctx: Context<ExecuteUnshield>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        expiry_slot: u64,
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_execute_unshield(ctx, nullifier, root, amount, fee, expiry_slot, proof)
+    }
+
+    /// Cancel a queued withdrawal, refunding its rent (payer only)
+    pub fn cancel_queued_unshield(ctx: Context<CancelQueuedUnshield>) -> Result<()> {
+        processor::process_cancel_queued_unshield(ctx)
+    }
+
+    /// Bond SOL as a relayer, creating its `RelayerBond` on first use
+    ///
+    /// Users dispute overcharged withdrawals against the bond with
+    /// `dispute_relayer_fee`.
+    pub fn bond_relayer(ctx: Context<BondRelayer>, amount: u64) -> Result<()> {
+        processor::process_bond_relayer(ctx, amount)
+    }
+
+    /// Start unbonding a relayer's bond (relayer only)
+    ///
+    /// The bond stays slashable for `state::RELAYER_UNBONDING_SLOTS`.
+    pub fn request_relayer_unbond(ctx: Context<UpdateRelayerBond>) -> Result<()> {
+        processor::process_request_relayer_unbond(ctx)
+    }
+
+    /// Withdraw an unbonded relayer bond, closing it (relayer only)
+    pub fn withdraw_relayer_bond(ctx: Context<WithdrawRelayerBond>) -> Result<()> {
+        processor::process_withdraw_relayer_bond(ctx)
+    }
+
+    /// Publish or update a relayer's fee quote for a pool (relayer only)
+    ///
+    /// Creates the relayer's `RelayerQuote` for the pool on first use. The
+    /// fee can't exceed the pool's relayer fee, which caps what withdrawals
+    /// may pay.
+    pub fn set_relayer_quote(
+        ctx: Context<SetRelayerQuote>,
+        fee_bps: u16,
+        min_withdrawal: u64,
+        url: String,
+    ) -> Result<()> {
+        processor::process_set_relayer_quote(ctx, fee_bps, min_withdrawal, url)
+    }
+
+    /// Withdraw a relayer's fee quote, refunding its rent (relayer only)
+    pub fn close_relayer_quote(ctx: Context<CloseRelayerQuote>) -> Result<()> {
+        processor::process_close_relayer_quote(ctx)
+    }
+
+    /// Slash a relayer that charged more than its quoted fee (anyone)
+    ///
+    /// Takes the withdrawal's public inputs and proof, right after the
+    /// relayer's Ed25519 signature over `relayer::fee_quote_message` for
+    /// `quoted_fee`. When the proof verifies with the bonded relayer and a
+    /// fee above the quote, and its nullifier is spent, the overcharge and
+    /// `state::RELAYER_SLASH_BPS` of it are paid to the recipient out of the
+    /// bond. SOL pools only, since the bond is in lamports and SPL fees are
+    /// in the mint's units. Each withdrawal can be disputed once.
+    #[allow(clippy::too_many_arguments)]
+    pub fn dispute_relayer_fee(
+        ctx: Context<DisputeRelayerFee>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        refund: u64,
+        expiry_slot: u64,
+        quoted_fee: u64,
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_dispute_relayer_fee(
+            ctx,
+            nullifier,
+            root,
+            amount,
+            fee,
+            refund,
+            expiry_slot,
+            quoted_fee,
+            proof,
+        )
+    }
+
+    /// Staged SOL unshield, step 1 - record the withdrawal and compute the
+    /// public input linear combination (Groth16 proofs only)
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_stage_1(
+        ctx: Context<VerifyStage1>,
+        nullifier: [u8; 32],
+        root: [u8; 32],
+        amount: u64,
+        fee: u64,
+        refund: u64,
+        expiry_slot: u64,
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        processor::process_verify_stage_1(ctx, nullifier, root, amount, fee, refund, expiry_slot, proof)
+    }
+
+    /// Staged SOL unshield, step 2 - run the pairing check
+    pub fn verify_stage_2(ctx: Context<VerifyStage2>) -> Result<()> {
+        processor::process_verify_stage_2(ctx)
+    }
+
+    /// Staged SOL unshield, step 2 for many withdrawals at once
+    ///
+    /// Pass the relayer's `VerificationState` accounts (writable) as remaining
+    /// accounts. One batched pairing check covers all of them; either every
+    /// state is marked verified or the instruction fails.
+    pub fn verify_batch<'info>(ctx: Context<'_, '_, 'info, 'info, VerifyBatch<'info>>) -> Result<()> {
+        processor::process_verify_batch(ctx)
+    }
+
+    /// Staged SOL unshield, step 3 - spend the nullifier and pay out
+    pub fn finalize_unshield(ctx: Context<FinalizeUnshield>) -> Result<()> {
+        processor::process_finalize_unshield(ctx)
+    }
+
+    /// Abandon a staged SOL unshield, refunding its rent (relayer only)
+    ///
+    /// For verifications that failed or expired before `finalize_unshield`.
+    /// Nothing was spent, so the note can still be withdrawn.
+    pub fn close_verification_state(ctx: Context<CloseVerificationState>) -> Result<()> {
+        processor::process_close_verification_state(ctx)
+    }
+
+    /// Commit to the recipient of an emergency withdrawal (anyone)
+    ///
+    /// Step one of `emergency_withdraw`, submitted in an earlier slot. Only
+    /// the note's owner knows its nullifier before the reveal, so nobody
+    /// else can commit first.
+    pub fn commit_emergency_withdraw(
+        ctx: Context<CommitEmergencyWithdraw>,
+        nullifier: [u8; 32],
+        recipient: Pubkey,
+    ) -> Result<()> {
+        processor::process_commit_emergency_withdraw(ctx, nullifier, recipient)
+    }
+
+    /// Reclaim a note by revealing its preimage, once unshields are stuck
+    ///
+    /// Opens when the pool's unshields have been paused for
+    /// `EMERGENCY_WITHDRAW_DELAY_SLOTS`. The caller reveals the note's
+    /// spending key, blinding and asset id plus a Merkle path to `root`;
+    /// the program recomputes the commitment and nullifier and pays
+    /// the denomination out. This links the note to its recipient, so it
+    /// gives up privacy to recover funds. Fixed-denomination pools only,
+    /// since shields don't prove a variable note's amount on-chain. SPL
+    /// pools pass the token accounts.
+    ///
+    /// The payout goes to the recipient committed for the note's nullifier
+    /// by `commit_emergency_withdraw`, whose payer must submit the reveal
+    /// and gets the commit's rent back.
+    /// The pool's relayer policy doesn't apply.
+    #[allow(clippy::too_many_arguments)]
+    pub fn emergency_withdraw(
+        ctx: Context<EmergencyWithdraw>,
+        spending_key: [u8; 32],
+        blinding: [u8; 32],
+        asset_id: u64,
+        leaf_index: u64,
+        root: [u8; 32],
+        path: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        processor::process_emergency_withdraw(
+            ctx,
+            spending_key,
+            blinding,
+            asset_id,
+            leaf_index,
+            root,
+            path,
+        )
+    }
+}
+
+// Re-export pool seed from token module
+use token::POOL_SEED;
+use verification::CircuitId;
+
+/// Initialize a new privacy pool with a specific denomination
+#[derive(Accounts)]
+#[instruction(denomination: u64, root_history_size: u16)]
+pub struct Initialize<'info> {
+    /// The pool account, derived from denomination and mint
+    /// Each denomination (0, 0.1 SOL, 1 SOL, 10 SOL) gets its own pool per mint
+    #[account(
+        init,
+        payer = payer,
+        space = state::PrivacyPool::space(root_history_size),
+        seeds = [
+            POOL_SEED,
+            &denomination.to_le_bytes(),
+            token::pool_mint_seed(&mint.as_ref().map(|mint| mint.key()).unwrap_or_default())
+        ],
+        bump
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Mint of the pool's notes; omit for a native SOL pool
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// Mints SPL pools may be created for (SPL pools only)
+    #[account(seeds = [state::MINT_ALLOWLIST_SEED], bump = mint_allowlist.bump)]
+    pub mint_allowlist: Option<Account<'info, state::MintAllowlist>>,
+
+    /// Protocol config; new pools take its default relayer fee
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Option<Account<'info, state::ProtocolConfig>>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Resize a pool's root history
+#[derive(Accounts)]
+pub struct ResizeRootHistory<'info> {
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress,
+        has_one = authority @ instructions::NyxError::Unauthorized
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Create the global pool registry (protocol admin only)
+#[derive(Accounts)]
+pub struct InitializePoolRegistry<'info> {
+    #[account(
+        seeds = [state::PROTOCOL_CONFIG_SEED],
+        bump = protocol_config.bump,
+        has_one = admin @ instructions::NyxError::Unauthorized
+    )]
+    pub protocol_config: Account<'info, state::ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + state::PoolRegistry::SIZE,
+        seeds = [state::POOL_REGISTRY_SEED],
+        bump
+    )]
+    pub pool_registry: Box<Account<'info, state::PoolRegistry>>,
+
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Create the canonical SOL pools and register them
+#[derive(Accounts)]
+#[instruction(root_history_size: u16)]
+pub struct InitializeStandardPools<'info> {
+    /// 0.1 SOL pool
+    #[account(
+        init,
+        payer = payer,
+        space = state::PrivacyPool::space(root_history_size),
+        seeds = [POOL_SEED, &state::STANDARD_DENOMINATIONS[0].to_le_bytes()],
+        bump
+    )]
+    pub pool_0: AccountLoader<'info, state::PrivacyPool>,
+
+    /// 1 SOL pool
+    #[account(
+        init,
+        payer = payer,
+        space = state::PrivacyPool::space(root_history_size),
+        seeds = [POOL_SEED, &state::STANDARD_DENOMINATIONS[1].to_le_bytes()],
+        bump
+    )]
+    pub pool_1: AccountLoader<'info, state::PrivacyPool>,
+
+    /// 10 SOL pool
+    #[account(
+        init,
+        payer = payer,
+        space = state::PrivacyPool::space(root_history_size),
+        seeds = [POOL_SEED, &state::STANDARD_DENOMINATIONS[2].to_le_bytes()],
+        bump
+    )]
+    pub pool_2: AccountLoader<'info, state::PrivacyPool>,
+
+    /// 100 SOL pool
+    #[account(
+        init,
+        payer = payer,
+        space = state::PrivacyPool::space(root_history_size),
+        seeds = [POOL_SEED, &state::STANDARD_DENOMINATIONS[3].to_le_bytes()],
+        bump
+    )]
+    pub pool_3: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Global pool registry
+    #[account(
+        mut,
+        seeds = [state::POOL_REGISTRY_SEED],
+        bump = pool_registry.bump,
+        has_one = authority @ instructions::NyxError::Unauthorized
+    )]
+    pub pool_registry: Box<Account<'info, state::PoolRegistry>>,
+
+    /// Protocol config; new pools take its default relayer fee
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Option<Account<'info, state::ProtocolConfig>>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Create the multi-asset pool
+#[derive(Accounts)]
+#[instruction(root_history_size: u16)]
+pub struct InitializeMultiAssetPool<'info> {
+    /// The multi-asset pool, keyed by `token::MULTI_ASSET_MINT`
+    #[account(
+        init,
+        payer = payer,
+        space = state::PrivacyPool::space(root_history_size),
+        seeds = [POOL_SEED, &0u64.to_le_bytes(), token::MULTI_ASSET_MINT.as_ref()],
+        bump
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol config; the pool takes its default relayer fee
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Option<Account<'info, state::ProtocolConfig>>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Create the NFT pool
+#[derive(Accounts)]
+#[instruction(root_history_size: u16)]
+pub struct InitializeNftPool<'info> {
+    /// The NFT pool, keyed by `token::NFT_POOL_MINT`
+    #[account(
+        init,
+        payer = payer,
+        space = state::PrivacyPool::space(root_history_size),
+        seeds = [POOL_SEED, &0u64.to_le_bytes(), token::NFT_POOL_MINT.as_ref()],
+        bump
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol config; the pool takes its default relayer fee
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Option<Account<'info, state::ProtocolConfig>>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Create the protocol config (program upgrade authority only)
+#[event_cpi]
+#[derive(Accounts)]
+pub struct InitializeProtocolConfig<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + state::ProtocolConfig::SIZE,
+        seeds = [state::PROTOCOL_CONFIG_SEED],
+        bump
+    )]
+    pub protocol_config: Account<'info, state::ProtocolConfig>,
+
+    /// This program's upgrade data, naming its upgrade authority
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = anchor_lang::solana_program::bpf_loader_upgradeable::ID,
+        constraint = program_data.upgrade_authority_address == Some(upgrade_authority.key())
+            @ instructions::NyxError::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub upgrade_authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Change the protocol config (admin only)
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateProtocolConfig<'info> {
+    #[account(
+        mut,
+        seeds = [state::PROTOCOL_CONFIG_SEED],
+        bump = protocol_config.bump,
+        has_one = admin @ instructions::NyxError::Unauthorized
+    )]
+    pub protocol_config: Account<'info, state::ProtocolConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+/// Take over the protocol config as its proposed admin
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AcceptProtocolAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [state::PROTOCOL_CONFIG_SEED],
+        bump = protocol_config.bump,
+        constraint = protocol_config.pending_admin == new_admin.key()
+            @ instructions::NyxError::NotPendingAdmin
+    )]
+    pub protocol_config: Account<'info, state::ProtocolConfig>,
+
+    pub new_admin: Signer<'info>,
+}
+
+/// Add or remove an allowlisted mint (protocol admin only)
+#[derive(Accounts)]
+pub struct UpdateMintAllowlist<'info> {
+    #[account(
+        seeds = [state::PROTOCOL_CONFIG_SEED],
+        bump = protocol_config.bump,
+        has_one = admin @ instructions::NyxError::Unauthorized
+    )]
+    pub protocol_config: Account<'info, state::ProtocolConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + state::MintAllowlist::SIZE,
+        seeds = [state::MINT_ALLOWLIST_SEED],
+        bump
+    )]
+    pub mint_allowlist: Box<Account<'info, state::MintAllowlist>>,
+
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Add or remove a CPI destination program (protocol admin only)
+#[derive(Accounts)]
+pub struct UpdateDestinationRegistry<'info> {
+    #[account(
+        seeds = [state::PROTOCOL_CONFIG_SEED],
+        bump = protocol_config.bump,
+        has_one = admin @ instructions::NyxError::Unauthorized
+    )]
+    pub protocol_config: Account<'info, state::ProtocolConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + state::DestinationRegistry::SIZE,
+        seeds = [state::DESTINATION_REGISTRY_SEED],
+        bump
+    )]
+    pub destination_registry: Box<Account<'info, state::DestinationRegistry>>,
+
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Add or remove an allowlisted stake pool (protocol admin only)
+#[derive(Accounts)]
+pub struct UpdateStakePoolAllowlist<'info> {
+    #[account(
+        seeds = [state::PROTOCOL_CONFIG_SEED],
+        bump = protocol_config.bump,
+        has_one = admin @ instructions::NyxError::Unauthorized
+    )]
+    pub protocol_config: Account<'info, state::ProtocolConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + state::StakePoolAllowlist::SIZE,
+        seeds = [state::STAKE_POOL_ALLOWLIST_SEED],
+        bump
+    )]
+    pub stake_pool_allowlist: Box<Account<'info, state::StakePoolAllowlist>>,
+
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Close a drained pool (authority only)
+#[derive(Accounts)]
+pub struct ClosePool<'info> {
+    #[account(
+        mut,
+        close = authority,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress,
+        has_one = authority @ instructions::NyxError::Unauthorized
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Pool's vault PDA, holding SOL or owning the token vault
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    /// Pool's token account (SPL pools only)
+    #[account(
+        constraint = vault_token_account.owner == vault.key(),
+        constraint = vault_token_account.mint == pool.load()?.mint @ token::TokenError::MintMismatch
+    )]
+    pub vault_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+/// Upgrade a pool account's layout (authority only)
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigratePool<'info> {
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress,
+        has_one = authority @ instructions::NyxError::Unauthorized
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Pool's vault PDA, holding SOL or owning the token vault
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    /// Pool's token account (SPL pools only)
+    #[account(
+        constraint = vault_token_account.owner == vault.key(),
+        constraint = vault_token_account.mint == pool.load()?.mint @ token::TokenError::MintMismatch
+    )]
+    pub vault_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Pool's mint, whose decimals v9 records (SPL pools only)
+    #[account(address = pool.load()?.mint @ token::TokenError::MintMismatch)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Propose a pool's next Groth16 verifying key for one circuit
+#[derive(Accounts)]
+#[instruction(circuit_id: u8)]
+pub struct ProposeVerifyingKey<'info> {
+    /// The pool the key belongs to
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress,
+        constraint = pool.load()?.authority == authority.key()
+            || protocol_config
+                .as_ref()
+                .is_some_and(|config| config.vk_authority == authority.key())
+            @ instructions::NyxError::Unauthorized
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol config, when the signer is its VK authority
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Option<Account<'info, state::ProtocolConfig>>,
+
+    /// Verifying key PDA for this pool, circuit and pending version
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + groth16::VerifyingKeyAccount::SIZE,
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[circuit_id],
+            &pool.load()?.pending_vk_version().to_le_bytes()
+        ],
+        bump
+    )]
+    pub verifying_key: Box<Account<'info, groth16::VerifyingKeyAccount>>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Propose a pool's next PLONK verifying key for one circuit
+#[derive(Accounts)]
+#[instruction(circuit_id: u8)]
+pub struct ProposePlonkVerifyingKey<'info> {
+    /// The pool the key belongs to
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress,
+        constraint = pool.load()?.authority == authority.key()
+            || protocol_config
+                .as_ref()
+                .is_some_and(|config| config.vk_authority == authority.key())
+            @ instructions::NyxError::Unauthorized
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol config, when the signer is its VK authority
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Option<Account<'info, state::ProtocolConfig>>,
+
+    /// PLONK verifying key PDA for this pool, circuit and pending version
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + plonk::PlonkVerifyingKeyAccount::SIZE,
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[circuit_id],
+            &pool.load()?.pending_vk_version().to_le_bytes()
+        ],
+        bump
+    )]
+    pub verifying_key: Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Update a pool configuration value (authority only)
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdatePoolConfig<'info> {
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress,
+        has_one = authority @ instructions::NyxError::Unauthorized
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Take over a pool as its proposed authority
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    pub new_authority: Signer<'info>,
+}
+
+/// Replace a pool's guardian set (authority, plus the current guardians)
+#[derive(Accounts)]
+pub struct SetGuardians<'info> {
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress,
+        has_one = authority @ instructions::NyxError::Unauthorized
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Guardian set PDA for this pool
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + state::GuardianSet::SIZE,
+        seeds = [state::GUARDIAN_SET_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub guardian_set: Account<'info, state::GuardianSet>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Configure a SOL pool's yield strategy (authority only)
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ConfigureYieldStrategy<'info> {
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress,
+        has_one = authority @ instructions::NyxError::Unauthorized
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Yield strategy PDA for this pool
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + state::YieldStrategy::SIZE,
+        seeds = [state::YIELD_STRATEGY_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub strategy: Account<'info, state::YieldStrategy>,
+
+    /// SPL stake pool the vault deposits with
+    /// CHECK: Owner checked here, data parsed in the processor
+    #[account(owner = stake::STAKE_POOL_PROGRAM_ID @ instructions::NyxError::InvalidStakePool)]
+    pub stake_pool: AccountInfo<'info>,
+
+    /// Stake pools the protocol lets strategies deposit with
+    #[account(seeds = [state::STAKE_POOL_ALLOWLIST_SEED], bump = stake_pool_allowlist.bump)]
+    pub stake_pool_allowlist: Box<Account<'info, state::StakePoolAllowlist>>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Rebalance a SOL pool's vault against its yield strategy
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RebalanceYieldStrategy<'info> {
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// The pool's yield strategy
+    #[account(
+        mut,
+        seeds = [state::YIELD_STRATEGY_SEED, pool.key().as_ref()],
+        bump = strategy.bump
+    )]
+    pub strategy: Account<'info, state::YieldStrategy>,
+
+    /// Pool's SOL vault PDA, which deposits and receives redemptions
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    /// Vault's stake pool tokens
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = pool_mint,
+        associated_token::authority = vault,
+        associated_token::token_program = token_program
+    )]
+    pub strategy_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The strategy's stake pool
+    /// CHECK: Validated by address constraint
+    #[account(mut, address = strategy.stake_pool)]
+    pub stake_pool: AccountInfo<'info>,
+
+    /// Stake pools the protocol lets strategies deposit with
+    #[account(seeds = [state::STAKE_POOL_ALLOWLIST_SEED], bump = stake_pool_allowlist.bump)]
+    pub stake_pool_allowlist: Box<Account<'info, state::StakePoolAllowlist>>,
+
+    /// Stake pool's withdraw authority
+    /// CHECK: Checked by the stake pool program
+    pub stake_pool_withdraw_authority: AccountInfo<'info>,
+
+    /// Stake pool's reserve stake account
+    /// CHECK: Checked by the stake pool program
+    #[account(mut)]
+    pub reserve_stake: AccountInfo<'info>,
+
+    /// Stake pool manager's fee account, also paid deposit referral fees
+    /// CHECK: Checked by the stake pool program
+    #[account(mut)]
+    pub manager_fee_account: AccountInfo<'info>,
+
+    /// The stake pool's token mint
+    #[account(mut, address = strategy.pool_mint @ token::TokenError::MintMismatch)]
+    pub pool_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// SPL stake pool program
+    /// CHECK: Validated by address constraint
+    #[account(address = stake::STAKE_POOL_PROGRAM_ID)]
+    pub stake_pool_program: AccountInfo<'info>,
+
+    /// Native stake program
+    /// CHECK: Validated by address constraint
+    #[account(address = solana_program::stake::program::ID)]
+    pub stake_program: AccountInfo<'info>,
+
+    /// CHECK: Validated by address constraint
+    #[account(address = solana_program::sysvar::clock::ID)]
+    pub clock: AccountInfo<'info>,
+
+    /// CHECK: Validated by address constraint
+    #[account(address = solana_program::sysvar::stake_history::ID)]
+    pub stake_history: AccountInfo<'info>,
+
+    /// Pays rent for the vault's token account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Take emergency action on a pool (guardian quorum)
+///
+/// The signing guardians are passed as remaining accounts.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct GuardianAction<'info> {
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Guardian set PDA for this pool
+    #[account(
+        seeds = [state::GUARDIAN_SET_SEED, pool.key().as_ref()],
+        bump = guardian_set.bump,
+        has_one = pool
+    )]
+    pub guardian_set: Account<'info, state::GuardianSet>,
+}
+
+/// Shield native SOL into a specific denomination pool
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(commitment: [u8; 32], amount: u64)]
+pub struct ShieldSol<'info> {
+    /// The pool for this denomination (denomination is stored in pool.denomination)
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (pause switch and fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Pool's SOL vault PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Commitment chunk archiving the inserted leaf
+    /// (required by incremental pools; compressed pools pass the tree
+    /// accounts as remaining accounts instead)
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub commitment_chunk: Option<AccountInfo<'info>>,
+
+    /// Pool's `TreeEpoch` PDA for its current tree, needed when the tree
+    /// has no room left and this insertion rolls it over
+    /// CHECK: Checked against the archive's PDA, and created, by
+    /// `rollover::roll_over`
+    #[account(mut)]
+    pub tree_archive: Option<AccountInfo<'info>>,
+
+    /// Leaf index record for the commitment, which also marks it as used
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + state::CommitmentRecord::SIZE,
+        seeds = [state::COMMITMENT_RECORD_SEED, pool.key().as_ref(), commitment.as_ref()],
+        bump
+    )]
+    pub commitment_record: Account<'info, state::CommitmentRecord>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// Depositor's wrapped SOL account, whose shielded amount is unwrapped
+    /// into the depositor before the deposit so wSOL joins the native pool's
+    /// anonymity set
+    #[account(
+        mut,
+        constraint = depositor_wsol_account.owner == depositor.key(),
+        constraint = depositor_wsol_account.mint == token::NATIVE_MINT @ token::TokenError::MintMismatch
+    )]
+    pub depositor_wsol_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Token program, required with `depositor_wsol_account`
+    pub token_program: Option<Program<'info, Token>>,
+
+    /// Scratch token account the amount is unwrapped through, required with
+    /// `depositor_wsol_account`
+    /// CHECK: Checked against the depositor's PDA, created and closed again,
+    /// by `token::unwrap_wsol`
+    #[account(mut)]
+    pub wsol_unwrap_account: Option<AccountInfo<'info>>,
+
+    /// Wrapped SOL mint, required with `depositor_wsol_account`
+    /// CHECK: Validated by address constraint
+    #[account(address = token::NATIVE_MINT @ token::TokenError::MintMismatch)]
+    pub wsol_mint: Option<AccountInfo<'info>>,
+
+    /// Instructions sysvar, required when the pool's CPI policy restricts
+    /// calling programs
+    /// CHECK: Validated by address constraint
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<AccountInfo<'info>>,
+}
+
+/// Shield SPL tokens into a specific denomination pool
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(commitment: [u8; 32], amount: u64)]
+pub struct Shield<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (pause switch and fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Mints SPL pools may be shielded into
+    #[account(seeds = [state::MINT_ALLOWLIST_SEED], bump = mint_allowlist.bump)]
+    pub mint_allowlist: Box<Account<'info, state::MintAllowlist>>,
+
+    /// Pool's vault authority PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: AccountInfo<'info>,
+
+    /// The pool's mint, for checked transfers
+    #[account(address = pool.load()?.mint @ token::TokenError::MintMismatch)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Pool's token account for this mint: the vault authority's associated
+    /// token account, created on the first deposit
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Depositor's token account
+    #[account(
+        mut,
+        constraint = depositor_token_account.mint == vault_token_account.mint
+    )]
+    pub depositor_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Commitment chunk archiving the inserted leaf
+    /// (required by incremental pools; compressed pools pass the tree
+    /// accounts as remaining accounts instead)
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub commitment_chunk: Option<AccountInfo<'info>>,
+
+    /// Pool's `TreeEpoch` PDA for its current tree, needed when the tree
+    /// has no room left and this insertion rolls it over
+    /// CHECK: Checked against the archive's PDA, and created, by
+    /// `rollover::roll_over`
+    #[account(mut)]
+    pub tree_archive: Option<AccountInfo<'info>>,
+
+    /// Leaf index record for the commitment, which also marks it as used
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + state::CommitmentRecord::SIZE,
+        seeds = [state::COMMITMENT_RECORD_SEED, pool.key().as_ref(), commitment.as_ref()],
+        bump
+    )]
+    pub commitment_record: Account<'info, state::CommitmentRecord>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// Instructions sysvar, required when the pool's CPI policy restricts
+    /// calling programs
+    /// CHECK: Validated by address constraint
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<AccountInfo<'info>>,
+}
+
+/// Shield SPL tokens into the multi-asset pool
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(commitment: [u8; 32])]
+pub struct ShieldMultiAsset<'info> {
+    /// The multi-asset pool
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (pause switch)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Mints the pool accepts
+    #[account(seeds = [state::MINT_ALLOWLIST_SEED], bump = mint_allowlist.bump)]
+    pub mint_allowlist: Box<Account<'info, state::MintAllowlist>>,
+
+    /// Registered Groth16 shield verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Shield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK shield verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Shield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's vault authority PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: AccountInfo<'info>,
+
+    /// Mint being deposited, checked against the allowlist
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Pool's vault for this mint: the vault authority's associated token
+    /// account, created on the mint's first deposit
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Depositor's token account
+    #[account(
+        mut,
+        constraint = depositor_token_account.mint == vault_token_account.mint
+    )]
+    pub depositor_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Commitment chunk archiving the inserted leaf
+    /// (required by incremental pools; compressed pools pass the tree
+    /// accounts as remaining accounts instead)
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub commitment_chunk: Option<AccountInfo<'info>>,
+
+    /// Pool's `TreeEpoch` PDA for its current tree, needed when the tree
+    /// has no room left and this insertion rolls it over
+    /// CHECK: Checked against the archive's PDA, and created, by
+    /// `rollover::roll_over`
+    #[account(mut)]
+    pub tree_archive: Option<AccountInfo<'info>>,
+
+    /// Leaf index record for the commitment, which also marks it as used
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + state::CommitmentRecord::SIZE,
+        seeds = [state::COMMITMENT_RECORD_SEED, pool.key().as_ref(), commitment.as_ref()],
+        bump
+    )]
+    pub commitment_record: Account<'info, state::CommitmentRecord>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// Instructions sysvar, required when the pool's CPI policy restricts
+    /// calling programs
+    /// CHECK: Validated by address constraint
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<AccountInfo<'info>>,
+}
+
+/// Shield an NFT into the NFT pool
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(commitment: [u8; 32])]
+pub struct ShieldNft<'info> {
+    /// The NFT pool
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (pause switch)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Registered Groth16 shield verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Shield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK shield verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Shield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's vault authority PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: AccountInfo<'info>,
+
+    /// The NFT's mint
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Pool's vault for this NFT: the vault authority's associated token
+    /// account, created on deposit
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Depositor's token account holding the NFT
+    #[account(
+        mut,
+        constraint = depositor_token_account.mint == vault_token_account.mint
+    )]
+    pub depositor_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Commitment chunk archiving the inserted leaf
+    /// (required by incremental pools; compressed pools pass the tree
+    /// accounts as remaining accounts instead)
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub commitment_chunk: Option<AccountInfo<'info>>,
+
+    /// Pool's `TreeEpoch` PDA for its current tree, needed when the tree
+    /// has no room left and this insertion rolls it over
+    /// CHECK: Checked against the archive's PDA, and created, by
+    /// `rollover::roll_over`
+    #[account(mut)]
+    pub tree_archive: Option<AccountInfo<'info>>,
+
+    /// Leaf index record for the commitment, which also marks it as used
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + state::CommitmentRecord::SIZE,
+        seeds = [state::COMMITMENT_RECORD_SEED, pool.key().as_ref(), commitment.as_ref()],
+        bump
+    )]
+    pub commitment_record: Account<'info, state::CommitmentRecord>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// Instructions sysvar, required when the pool's CPI policy restricts
+    /// calling programs
+    /// CHECK: Validated by address constraint
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<AccountInfo<'info>>,
+}
+
+/// Shield a compressed NFT into the NFT pool
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(commitment: [u8; 32])]
+pub struct ShieldCnft<'info> {
+    /// The NFT pool
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (pause switch)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Registered Groth16 shield verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Shield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK shield verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Shield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's vault authority PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: AccountInfo<'info>,
+
+    /// Current owner of the NFT
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    /// Leaf delegate of the NFT (the depositor if none was set)
+    /// CHECK: Checked by Bubblegum
+    pub leaf_delegate: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Bubblegum's config PDA for the NFT's tree
+    /// CHECK: Checked by Bubblegum
+    pub tree_config: AccountInfo<'info>,
+
+    /// Bubblegum tree holding the NFT's leaf
+    /// CHECK: Checked by Bubblegum against the leaf's proof
+    #[account(mut)]
+    pub merkle_tree: AccountInfo<'info>,
+
+    /// CHECK: spl-noop program
+    #[account(address = compression::NOOP_PROGRAM_ID)]
+    pub log_wrapper: AccountInfo<'info>,
+
+    /// CHECK: spl-account-compression program
+    #[account(address = compression::COMPRESSION_PROGRAM_ID)]
+    pub compression_program: AccountInfo<'info>,
+
+    /// CHECK: Bubblegum program
+    #[account(address = bubblegum::BUBBLEGUM_PROGRAM_ID)]
+    pub bubblegum_program: AccountInfo<'info>,
+
+    /// Commitment chunk archiving the inserted leaf
+    /// (required by incremental pools; compressed pools pass the tree
+    /// accounts as remaining accounts instead)
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub commitment_chunk: Option<AccountInfo<'info>>,
+
+    /// Pool's `TreeEpoch` PDA for its current tree, needed when the tree
+    /// has no room left and this insertion rolls it over
+    /// CHECK: Checked against the archive's PDA, and created, by
+    /// `rollover::roll_over`
+    #[account(mut)]
+    pub tree_archive: Option<AccountInfo<'info>>,
+
+    /// Leaf index record for the commitment, which also marks it as used
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + state::CommitmentRecord::SIZE,
+        seeds = [state::COMMITMENT_RECORD_SEED, pool.key().as_ref(), commitment.as_ref()],
+        bump
+    )]
+    pub commitment_record: Account<'info, state::CommitmentRecord>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// Instructions sysvar, required when the pool's CPI policy restricts
+    /// calling programs
+    /// CHECK: Validated by address constraint
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<AccountInfo<'info>>,
+}
+
+/// Shield native SOL for several commitments into a denomination pool
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ShieldSolBatch<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (pause switch and fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Pool's SOL vault PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Commitment chunk archiving the first inserted leaf
+    /// (required by incremental pools; compressed pools pass the tree
+    /// accounts as remaining accounts instead)
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub commitment_chunk: Option<AccountInfo<'info>>,
+
+    /// Following chunk, for batches that cross a chunk boundary
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub next_commitment_chunk: Option<AccountInfo<'info>>,
+
+    /// Pool's `TreeEpoch` PDA for its current tree, needed when the tree
+    /// has no room left and this insertion rolls it over
+    /// CHECK: Checked against the archive's PDA, and created, by
+    /// `rollover::roll_over`
+    #[account(mut)]
+    pub tree_archive: Option<AccountInfo<'info>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// Instructions sysvar, required when the pool's CPI policy restricts
+    /// calling programs
+    /// CHECK: Validated by address constraint
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<AccountInfo<'info>>,
+}
+
+/// Shield SPL tokens for several commitments into a denomination pool
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ShieldBatch<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (pause switch and fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Mints SPL pools may be shielded into
+    #[account(seeds = [state::MINT_ALLOWLIST_SEED], bump = mint_allowlist.bump)]
+    pub mint_allowlist: Box<Account<'info, state::MintAllowlist>>,
+
+    /// Pool's vault authority PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: AccountInfo<'info>,
+
+    /// The pool's mint, for checked transfers
+    #[account(address = pool.load()?.mint @ token::TokenError::MintMismatch)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Pool's token account for this mint: the vault authority's associated
+    /// token account, created on the first deposit
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Depositor's token account
+    #[account(
+        mut,
+        constraint = depositor_token_account.mint == vault_token_account.mint
+    )]
+    pub depositor_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Commitment chunk archiving the first inserted leaf
+    /// (required by incremental pools; compressed pools pass the tree
+    /// accounts as remaining accounts instead)
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub commitment_chunk: Option<AccountInfo<'info>>,
+
+    /// Following chunk, for batches that cross a chunk boundary
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub next_commitment_chunk: Option<AccountInfo<'info>>,
+
+    /// Pool's `TreeEpoch` PDA for its current tree, needed when the tree
+    /// has no room left and this insertion rolls it over
+    /// CHECK: Checked against the archive's PDA, and created, by
+    /// `rollover::roll_over`
+    #[account(mut)]
+    pub tree_archive: Option<AccountInfo<'info>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// Instructions sysvar, required when the pool's CPI policy restricts
+    /// calling programs
+    /// CHECK: Validated by address constraint
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<AccountInfo<'info>>,
+}
+
+/// Create a commitment chunk for the pool's current tree
+#[derive(Accounts)]
+#[instruction(chunk_index: u32)]
+pub struct InitCommitmentChunk<'info> {
+    /// The pool the chunk belongs to
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Chunk PDA for this pool, tree epoch and chunk index
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + state::CommitmentChunk::SIZE,
+        seeds = [
+            state::COMMITMENT_CHUNK_SEED,
+            pool.key().as_ref(),
+            &pool.load()?.tree_epoch.to_le_bytes(),
+            &chunk_index.to_le_bytes()
+        ],
+        bump
+    )]
+    pub commitment_chunk: AccountLoader<'info, state::CommitmentChunk>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Create a pool's spent-nullifier bloom filter
+#[derive(Accounts)]
+pub struct InitNullifierBloom<'info> {
+    /// The pool the filter belongs to
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Bloom filter PDA for this pool
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + state::NullifierBloom::SIZE,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub nullifier_bloom: AccountLoader<'info, state::NullifierBloom>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Create a pool's stats account
+#[derive(Accounts)]
+pub struct InitPoolStats<'info> {
+    /// The pool the stats belong to
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Stats PDA for this pool
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + state::PoolStats::SIZE,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub pool_stats: AccountLoader<'info, state::PoolStats>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Create a pool's indexed nullifier tree
+#[derive(Accounts)]
+pub struct InitNullifierTree<'info> {
+    /// The pool the tree belongs to
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Nullifier tree PDA for this pool
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + nullifier_tree::NullifierTree::SIZE,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub nullifier_tree: AccountLoader<'info, nullifier_tree::NullifierTree>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Insert a queued nullifier into a pool's indexed nullifier tree
+#[derive(Accounts)]
+pub struct InsertNullifierTree<'info> {
+    /// The pool the tree belongs to
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// The pool's nullifier tree
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: AccountLoader<'info, nullifier_tree::NullifierTree>,
+}
+
+/// Insert a spent nullifier missing from a pool's indexed nullifier tree
+#[derive(Accounts)]
+#[instruction(nullifier: [u8; 32])]
+pub struct BackfillNullifierTree<'info> {
+    /// The pool the tree belongs to
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// The pool's nullifier tree
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: AccountLoader<'info, nullifier_tree::NullifierTree>,
+
+    /// Nullifier set shard holding the nullifier
+    /// CHECK: Address and contents validated by the processor
+    pub nullifier_set: AccountInfo<'info>,
+}
+
+/// Switch a pool to the compressed tree backend
+#[derive(Accounts)]
+pub struct EnableCompressedTree<'info> {
+    /// The pool to switch
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress,
+        has_one = authority @ instructions::NyxError::Unauthorized
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Pre-allocated concurrent Merkle tree account
+    /// CHECK: Owner checked here, layout checked by the compression program
+    #[account(mut, owner = compression::COMPRESSION_PROGRAM_ID)]
+    pub merkle_tree: AccountInfo<'info>,
+
+    /// PDA that owns the tree on the pool's behalf
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [compression::TREE_AUTHORITY_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub tree_authority: AccountInfo<'info>,
+
+    /// CHECK: Validated by address constraint
+    #[account(address = compression::NOOP_PROGRAM_ID)]
+    pub noop_program: AccountInfo<'info>,
+
+    /// CHECK: Validated by address constraint
+    #[account(address = compression::COMPRESSION_PROGRAM_ID)]
+    pub compression_program: AccountInfo<'info>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Archive a pool's full Merkle tree and start a new one
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RolloverTree<'info> {
+    /// The pool whose tree is rolled over
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Archive of the tree being retired, keyed by its epoch
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + state::TreeEpoch::SIZE,
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &pool.load()?.tree_epoch.to_le_bytes()
+        ],
+        bump
+    )]
+    pub tree_epoch: Account<'info, state::TreeEpoch>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Close a legacy nullifier marker into the pool's nullifier set
+#[derive(Accounts)]
+pub struct CloseNullifierMarker<'info> {
+    /// The pool the nullifier was spent in
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Marker to close, with its rent going to the treasury
+    #[account(
+        mut,
+        close = treasury,
+        seeds = [nullifier::NULLIFIER_SEED, pool.key().as_ref(), &nullifier_marker.nullifier],
+        bump,
+        has_one = pool
+    )]
+    pub nullifier_marker: Account<'info, nullifier::NullifierMarker>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::insert_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, which the nullifier is added to
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: AccountLoader<'info, state::NullifierBloom>,
+
+    /// Pool treasury
+    /// CHECK: Validated by address constraint
+    #[account(mut, address = pool.load()?.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    /// Pays for nullifier set growth
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Check membership of a leaf in a pool's tree
+#[derive(Accounts)]
+#[instruction(root: [u8; 32], leaf: [u8; 32])]
+pub struct VerifyMembership<'info> {
+    /// The pool whose tree is checked
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// The leaf's record, whose slot its maturity is checked against
+    #[account(
+        seeds = [state::COMMITMENT_RECORD_SEED, pool.key().as_ref(), leaf.as_ref()],
+        bump = commitment_record.bump
+    )]
+    pub commitment_record: Option<Account<'info, state::CommitmentRecord>>,
+}
+
+/// Read a pool's tree state
+#[derive(Accounts)]
+pub struct SnapshotTree<'info> {
+    /// The pool whose tree is read
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+}
+
+/// Private transfer within a pool
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Transfer<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (pause switch and fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 transfer verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Transfer as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK transfer verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Transfer as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Commitment chunk archiving the inserted leaf
+    /// (required by incremental pools; compressed pools pass the tree
+    /// accounts as remaining accounts instead)
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub commitment_chunk: Option<AccountInfo<'info>>,
+
+    /// Pool's `TreeEpoch` PDA for its current tree, needed when the tree
+    /// has no room left and this insertion rolls it over
+    /// CHECK: Checked against the archive's PDA, and created, by
+    /// `rollover::roll_over`
+    #[account(mut)]
+    pub tree_archive: Option<AccountInfo<'info>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Spend two notes into two within a pool
+#[event_cpi]
+#[derive(Accounts)]
+pub struct JoinSplit<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (pause switch and fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Legacy nullifier marker PDA of the first nullifier, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker_a: AccountInfo<'info>,
+
+    /// Nullifier set shard for the first nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set_a: AccountInfo<'info>,
+
+    /// Legacy nullifier marker PDA of the second nullifier, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker_b: AccountInfo<'info>,
+
+    /// Nullifier set shard for the second nullifier (may be the first's)
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set_b: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifiers when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 join-split verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::JoinSplit as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK join-split verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::JoinSplit as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Archived tree the spent notes belong to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Commitment chunk archiving the first inserted leaf
+    /// (required by incremental pools; compressed pools pass the tree
+    /// accounts as remaining accounts instead)
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub commitment_chunk: Option<AccountInfo<'info>>,
+
+    /// Following chunk, for when the second leaf starts a new chunk
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub next_commitment_chunk: Option<AccountInfo<'info>>,
+
+    /// Pool's `TreeEpoch` PDA for its current tree, needed when the tree
+    /// has no room left and this insertion rolls it over
+    /// CHECK: Checked against the archive's PDA, and created, by
+    /// `rollover::roll_over`
+    #[account(mut)]
+    pub tree_archive: Option<AccountInfo<'info>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Unshield native SOL from a specific denomination pool
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UnshieldSol<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's SOL vault PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    /// Recipient receiving the SOL
+    /// CHECK: Any account can receive SOL
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    /// Pool treasury, paid its share of the relayer fee
+    /// CHECK: Validated by address constraint
+    #[account(mut, address = pool.load()?.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// Recipient's wrapped SOL account, paid instead of `recipient` when
+    /// passed
+    #[account(
+        mut,
+        constraint = recipient_wsol_account.owner == recipient.key(),
+        constraint = recipient_wsol_account.mint == token::NATIVE_MINT @ token::TokenError::MintMismatch
+    )]
+    pub recipient_wsol_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Token program, required with `recipient_wsol_account`
+    pub token_program: Option<Program<'info, Token>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Unshield native SOL to the fee payer, without a relayer
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UnshieldSelf<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's SOL vault PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    /// Recipient receiving the SOL, which signs and pays for the
+    /// transaction itself
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// The recipient's bond, required by pools that only accept bonded
+    /// relayers; the recipient is its own relayer
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, recipient.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Unshield part of a note from a variable SOL pool, keeping the change
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UnshieldSolWithChange<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 partial withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::UnshieldWithChange as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK partial withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::UnshieldWithChange as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's SOL vault PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    /// Recipient receiving the SOL
+    /// CHECK: Any account can receive SOL
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    /// Pool treasury, paid its share of the relayer fee
+    /// CHECK: Validated by address constraint
+    #[account(mut, address = pool.load()?.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// Recipient's wrapped SOL account, paid instead of `recipient` when
+    /// passed
+    #[account(
+        mut,
+        constraint = recipient_wsol_account.owner == recipient.key(),
+        constraint = recipient_wsol_account.mint == token::NATIVE_MINT @ token::TokenError::MintMismatch
+    )]
+    pub recipient_wsol_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Token program, required with `recipient_wsol_account`
+    pub token_program: Option<Program<'info, Token>>,
+
+    /// Commitment chunk archiving the change note
+    /// (required by incremental pools; compressed pools pass the tree
+    /// accounts as remaining accounts instead)
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub commitment_chunk: Option<AccountInfo<'info>>,
+
+    /// Pool's `TreeEpoch` PDA for its current tree, needed when the tree
+    /// has no room left and this insertion rolls it over
+    /// CHECK: Checked against the archive's PDA, and created, by
+    /// `rollover::roll_over`
+    #[account(mut)]
+    pub tree_archive: Option<AccountInfo<'info>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Unshield native SOL for a batch of withdrawals proven together
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UnshieldAggregated<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Registered Groth16 aggregation verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::AggregatedUnshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK aggregation verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::AggregatedUnshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Pool's SOL vault PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    /// Pool treasury, paid its share of the relayer fee
+    /// CHECK: Validated by address constraint
+    #[account(mut, address = pool.load()?.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    /// Pays rent for every nullifier marker
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Unshield native SOL for a batch of independently proven withdrawals
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UnshieldBatch<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Registered Groth16 unshield verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK unshield verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifiers when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Pool's SOL vault PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    /// Pool treasury, paid its share of the relayer fee
+    /// CHECK: Validated by address constraint
+    #[account(mut, address = pool.load()?.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    /// Pays rent for every nullifier marker
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Archived tree every spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Unshield native SOL from one note to several recipients
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UnshieldMany<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 multi-recipient unshield verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::UnshieldMany as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK multi-recipient unshield verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::UnshieldMany as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's SOL vault PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    /// Pool treasury, paid its share of the relayer fee
+    /// CHECK: Validated by address constraint
+    #[account(mut, address = pool.load()?.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Unshield SPL tokens from a specific denomination pool
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Unshield<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's vault authority PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: AccountInfo<'info>,
+
+    /// The pool's mint, for checked transfers
+    #[account(address = pool.load()?.mint @ token::TokenError::MintMismatch)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Owner of the recipient token account (receives the SOL refund)
+    /// CHECK: Any account can own the recipient's token account
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Pool's token account: the vault authority's associated token account
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Recipient's associated token account, created if missing with the
+    /// relayer paying the rent out of its fee
+    /// CHECK: Address checked here, created by the processor if missing
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            recipient.key,
+            &mint.key(),
+            token_program.key
+        ) @ token::TokenError::NotAssociatedTokenAccount
+    )]
+    pub recipient_token_account: AccountInfo<'info>,
+
+    /// Relayer's token account, paid the relayer fee
+    #[account(
+        mut,
+        constraint = relayer_token_account.owner == relayer.key(),
+        constraint = relayer_token_account.mint == vault_token_account.mint
+    )]
+    pub relayer_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Treasury's token account, paid its share of the relayer fee
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == pool.load()?.treasury,
+        constraint = treasury_token_account.mint == vault_token_account.mint
+    )]
+    pub treasury_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Unshield SPL tokens into a registered destination program
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UnshieldCpi<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Programs the payout may be withdrawn into
+    #[account(seeds = [state::DESTINATION_REGISTRY_SEED], bump = destination_registry.bump)]
+    pub destination_registry: Box<Account<'info, state::DestinationRegistry>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's vault authority PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: AccountInfo<'info>,
+
+    /// The pool's mint, for checked transfers
+    #[account(address = pool.load()?.mint @ token::TokenError::MintMismatch)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Holds the payout for the length of the instruction
+    /// CHECK: Checked against the call in `process_unshield_cpi`
+    pub destination_authority: AccountInfo<'info>,
+
+    /// Program the payout is withdrawn into
+    /// CHECK: Checked against the destination registry
+    pub destination_program: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Pool's token account: the vault authority's associated token account
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Destination authority's token account, created if missing with the
+    /// relayer paying the rent out of its fee
+    /// CHECK: Address checked here, created by the processor if missing
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            destination_authority.key,
+            &mint.key(),
+            token_program.key
+        ) @ token::TokenError::NotAssociatedTokenAccount
+    )]
+    pub destination_token_account: AccountInfo<'info>,
+
+    /// Relayer's token account, paid the relayer fee
+    #[account(
+        mut,
+        constraint = relayer_token_account.owner == relayer.key(),
+        constraint = relayer_token_account.mint == vault_token_account.mint
+    )]
+    pub relayer_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Treasury's token account, paid its share of the relayer fee
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == pool.load()?.treasury,
+        constraint = treasury_token_account.mint == vault_token_account.mint
+    )]
+    pub treasury_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Swap a note of one SPL pool into a note of another through a DEX
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(commitment: [u8; 32], min_amount_out: u64)]
+pub struct ShieldedSwap<'info> {
+    /// Pool the spent note belongs to
+    #[account(
+        mut,
+        constraint = input_pool.load()?.is_at(&input_pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub input_pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Pool the new note is shielded into
+    #[account(
+        mut,
+        constraint = output_pool.load()?.is_at(&output_pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub output_pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (pause switch and fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Mints SPL pools may be shielded into
+    #[account(seeds = [state::MINT_ALLOWLIST_SEED], bump = mint_allowlist.bump)]
+    pub mint_allowlist: Box<Account<'info, state::MintAllowlist>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Input pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, input_pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Input pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, input_pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Input pool's registered Groth16 withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            input_pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Input pool's registered PLONK withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            input_pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Input pool's vault authority PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [token::VAULT_SEED, input_pool.key().as_ref()],
+        bump
+    )]
+    pub input_vault_authority: AccountInfo<'info>,
+
+    /// Output pool's vault authority PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [token::VAULT_SEED, output_pool.key().as_ref()],
+        bump
+    )]
+    pub output_vault_authority: AccountInfo<'info>,
+
+    /// The input pool's mint
+    #[account(address = input_pool.load()?.mint @ token::TokenError::MintMismatch)]
+    pub input_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The output pool's mint
+    #[account(address = output_pool.load()?.mint @ token::TokenError::MintMismatch)]
+    pub output_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Holds the swapped tokens for the length of the instruction
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [
+            swap::SWAP_AUTHORITY_SEED,
+            output_pool.key().as_ref(),
+            commitment.as_ref(),
+            &min_amount_out.to_le_bytes()
+        ],
+        bump
+    )]
+    pub swap_authority: AccountInfo<'info>,
+
+    /// DEX program the route is invoked through
+    /// CHECK: Checked against the supported DEXes in `swap::invoke_route`
+    pub dex_program: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Input pool's token account
+    #[account(
+        mut,
+        associated_token::mint = input_mint,
+        associated_token::authority = input_vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub input_vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Output pool's token account, created on the first deposit
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        associated_token::mint = output_mint,
+        associated_token::authority = output_vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub output_vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Swap authority's input token account, closed after the swap
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        associated_token::mint = input_mint,
+        associated_token::authority = swap_authority,
+        associated_token::token_program = token_program
+    )]
+    pub swap_input_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Swap authority's output token account, closed after the swap
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        associated_token::mint = output_mint,
+        associated_token::authority = swap_authority,
+        associated_token::token_program = token_program
+    )]
+    pub swap_output_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Relayer's input token account, paid the relayer fee
+    #[account(
+        mut,
+        constraint = relayer_token_account.owner == relayer.key(),
+        constraint = relayer_token_account.mint == input_mint.key()
+    )]
+    pub relayer_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Treasury's input token account, paid its share of the relayer fee
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == input_pool.load()?.treasury,
+        constraint = treasury_token_account.mint == input_mint.key()
+    )]
+    pub treasury_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Output pool treasury's token account, paid any output above the
+    /// minimum
+    #[account(
+        mut,
+        constraint = treasury_output_token_account.owner == output_pool.load()?.treasury,
+        constraint = treasury_output_token_account.mint == output_mint.key()
+    )]
+    pub treasury_output_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Archived input tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            input_pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Output pool's commitment chunk archiving the new leaf
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub commitment_chunk: AccountInfo<'info>,
+
+    /// Output pool's `TreeEpoch` PDA for its current tree, needed when the tree
+    /// has no room left and this insertion rolls it over
+    /// CHECK: Checked against the archive's PDA, and created, by
+    /// `rollover::roll_over`
+    #[account(mut)]
+    pub tree_archive: Option<AccountInfo<'info>>,
+
+    /// Leaf index record for the new commitment, which also marks it as used
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + state::CommitmentRecord::SIZE,
+        seeds = [state::COMMITMENT_RECORD_SEED, output_pool.key().as_ref(), commitment.as_ref()],
+        bump
+    )]
+    pub commitment_record: Box<Account<'info, state::CommitmentRecord>>,
+
+    /// Input pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, input_pool.key().as_ref()],
+        bump = input_pool_stats.load()?.bump
+    )]
+    pub input_pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// Output pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, output_pool.key().as_ref()],
+        bump = output_pool_stats.load()?.bump
+    )]
+    pub output_pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Stake a note of the SOL pool into an SPL stake pool
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(commitment: [u8; 32])]
+pub struct ShieldedStake<'info> {
+    /// The SOL pool the spent note belongs to
+    #[account(
+        mut,
+        constraint = input_pool.load()?.is_at(&input_pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub input_pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Pool of the stake pool's token the new note is shielded into
+    #[account(
+        mut,
+        constraint = output_pool.load()?.is_at(&output_pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub output_pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (pause switch and fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Mints SPL pools may be shielded into
+    #[account(seeds = [state::MINT_ALLOWLIST_SEED], bump = mint_allowlist.bump)]
+    pub mint_allowlist: Box<Account<'info, state::MintAllowlist>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// SOL pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, input_pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// SOL pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, input_pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// SOL pool's registered Groth16 withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            input_pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// SOL pool's registered PLONK withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            input_pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// SOL pool's vault PDA, the depositor of the stake
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, input_pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    /// SOL pool treasury, paid its share of the relayer fee
+    /// CHECK: Validated by address constraint
+    #[account(mut, address = input_pool.load()?.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    /// Output pool's vault authority PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [token::VAULT_SEED, output_pool.key().as_ref()],
+        bump
+    )]
+    pub output_vault_authority: AccountInfo<'info>,
+
+    /// The stake pool's token mint, which the output pool holds
+    #[account(mut, address = output_pool.load()?.mint @ token::TokenError::MintMismatch)]
+    pub output_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Output pool's token account, created on the first deposit
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        associated_token::mint = output_mint,
+        associated_token::authority = output_vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub output_vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The stake pool
+    /// CHECK: Checked by the stake pool program
+    #[account(mut)]
+    pub stake_pool: AccountInfo<'info>,
+
+    /// Stake pool's withdraw authority
+    /// CHECK: Checked by the stake pool program
+    pub stake_pool_withdraw_authority: AccountInfo<'info>,
+
+    /// Stake pool's reserve stake account
+    /// CHECK: Checked by the stake pool program
+    #[account(mut)]
+    pub reserve_stake: AccountInfo<'info>,
+
+    /// Stake pool manager's fee account
+    /// CHECK: Checked by the stake pool program
+    #[account(mut)]
+    pub manager_fee_account: AccountInfo<'info>,
+
+    /// Token account paid the referral share of the deposit fee
+    /// CHECK: Checked by the stake pool program
+    #[account(mut)]
+    pub referrer_pool_tokens_account: AccountInfo<'info>,
+
+    /// SPL stake pool program
+    /// CHECK: Validated by address constraint
+    #[account(address = stake::STAKE_POOL_PROGRAM_ID)]
+    pub stake_pool_program: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Archived SOL pool tree the spent note belongs to; omit for the
+    /// current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            input_pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Output pool's commitment chunk archiving the new leaf
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub commitment_chunk: AccountInfo<'info>,
+
+    /// Output pool's `TreeEpoch` PDA for its current tree, needed when the tree
+    /// has no room left and this insertion rolls it over
+    /// CHECK: Checked against the archive's PDA, and created, by
+    /// `rollover::roll_over`
+    #[account(mut)]
+    pub tree_archive: Option<AccountInfo<'info>>,
+
+    /// Leaf index record for the new commitment, which also marks it as used
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + state::CommitmentRecord::SIZE,
+        seeds = [state::COMMITMENT_RECORD_SEED, output_pool.key().as_ref(), commitment.as_ref()],
+        bump
+    )]
+    pub commitment_record: Box<Account<'info, state::CommitmentRecord>>,
+
+    /// SOL pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, input_pool.key().as_ref()],
+        bump = input_pool_stats.load()?.bump
+    )]
+    pub input_pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// Output pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, output_pool.key().as_ref()],
+        bump = output_pool_stats.load()?.bump
+    )]
+    pub output_pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Move notes of one SOL pool into a note of another
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(commitment: [u8; 32])]
+pub struct Migrate<'info> {
+    /// The SOL pool the spent notes belong to
+    #[account(
+        mut,
+        constraint = source_pool.load()?.is_at(&source_pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub source_pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// The SOL pool the new note is shielded into
+    #[account(
+        mut,
+        constraint = destination_pool.load()?.is_at(&destination_pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub destination_pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (pause switch and fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Source pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, source_pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Source pool's indexed nullifier tree, queueing the spent nullifiers when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, source_pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Source pool's registered Groth16 withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            source_pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Source pool's registered PLONK withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            source_pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Source pool's SOL vault PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, source_pool.key().as_ref()],
+        bump
+    )]
+    pub source_vault: AccountInfo<'info>,
+
+    /// Destination pool's SOL vault PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, destination_pool.key().as_ref()],
+        bump
+    )]
+    pub destination_vault: AccountInfo<'info>,
+
+    /// Source pool treasury, paid its share of the relayer fees
+    /// CHECK: Validated by address constraint
+    #[account(mut, address = source_pool.load()?.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Archived source pool tree the spent notes belong to; omit for the
+    /// current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            source_pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Destination pool's commitment chunk archiving the new leaf
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub commitment_chunk: AccountInfo<'info>,
+
+    /// Destination pool's `TreeEpoch` PDA for its current tree, needed when the tree
+    /// has no room left and this insertion rolls it over
+    /// CHECK: Checked against the archive's PDA, and created, by
+    /// `rollover::roll_over`
+    #[account(mut)]
+    pub tree_archive: Option<AccountInfo<'info>>,
+
+    /// Leaf index record for the new commitment, which also marks it as used
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + state::CommitmentRecord::SIZE,
+        seeds = [
+            state::COMMITMENT_RECORD_SEED,
+            destination_pool.key().as_ref(),
+            commitment.as_ref()
+        ],
+        bump
+    )]
+    pub commitment_record: Box<Account<'info, state::CommitmentRecord>>,
+
+    /// Source pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, source_pool.key().as_ref()],
+        bump = source_pool_stats.load()?.bump
+    )]
+    pub source_pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// Destination pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, destination_pool.key().as_ref()],
+        bump = destination_pool_stats.load()?.bump
+    )]
+    pub destination_pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Spend a note of a SOL pool into a claim link
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(claim_hash: [u8; 32], refund_to: Pubkey, claim_expiry_slot: u64)]
+pub struct CreateClaim<'info> {
+    /// The SOL pool the spent note belongs to
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (pause switch and fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's SOL vault PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    /// Claim link holding the spent note's lamports
+    #[account(
+        init,
+        payer = relayer,
+        space = 8 + state::ClaimLink::SIZE,
+        seeds = [
+            state::CLAIM_LINK_SEED,
+            pool.key().as_ref(),
+            claim_hash.as_ref(),
+            refund_to.as_ref(),
+            &claim_expiry_slot.to_le_bytes()
+        ],
+        bump
+    )]
+    pub claim_link: Account<'info, state::ClaimLink>,
+
+    /// Pool treasury, paid its share of the relayer fee
+    /// CHECK: Validated by address constraint
+    #[account(mut, address = pool.load()?.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Claim a claim link to a recipient
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Claim<'info> {
+    /// The claim link, closed to the recipient
+    #[account(
+        mut,
+        close = recipient,
+        seeds = [
+            state::CLAIM_LINK_SEED,
+            claim_link.pool.as_ref(),
+            claim_link.claim_hash.as_ref(),
+            claim_link.refund_to.as_ref(),
+            &claim_link.expiry_slot.to_le_bytes()
+        ],
+        bump = claim_link.bump
+    )]
+    pub claim_link: Account<'info, state::ClaimLink>,
+
+    /// Account receiving the lamports
+    /// CHECK: Signed for by the link's key
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    /// Instructions sysvar, holding the claim key's signature
+    /// CHECK: Validated by address constraint
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+/// Refund an expired claim link
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ReclaimClaim<'info> {
+    /// The claim link, closed to its refund address
+    #[account(
+        mut,
+        close = refund_to,
+        has_one = refund_to,
+        seeds = [
+            state::CLAIM_LINK_SEED,
+            claim_link.pool.as_ref(),
+            claim_link.claim_hash.as_ref(),
+            claim_link.refund_to.as_ref(),
+            &claim_link.expiry_slot.to_le_bytes()
+        ],
+        bump = claim_link.bump
+    )]
+    pub claim_link: Account<'info, state::ClaimLink>,
+
+    /// Refund address the link was created with
+    /// CHECK: Validated by has_one constraint
+    #[account(mut)]
+    pub refund_to: AccountInfo<'info>,
+}
+
+/// Spend a note of a SOL pool into a payment stream
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(terms: instructions::PaymentStreamTerms)]
+pub struct CreatePaymentStream<'info> {
+    /// The SOL pool the spent note belongs to
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (pause switch and fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's SOL vault PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    /// Payment stream holding the spent note's lamports
+    #[account(
+        init,
+        payer = relayer,
+        space = 8 + state::PaymentStream::SIZE,
+        seeds = [
+            state::PAYMENT_STREAM_SEED,
+            pool.key().as_ref(),
+            terms.recipient.as_ref(),
+            terms.refund_to.as_ref(),
+            &terms.amount_per_period.to_le_bytes(),
+            &terms.period_slots.to_le_bytes(),
+            terms.stream_id.as_ref()
+        ],
+        bump
+    )]
+    pub payment_stream: Account<'info, state::PaymentStream>,
+
+    /// Pool treasury, paid its share of the relayer fee
+    /// CHECK: Validated by address constraint
+    #[account(mut, address = pool.load()?.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Pay a payment stream's recipient for a due period
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ExecuteStreamPayment<'info> {
+    /// The payment stream
+    #[account(
+        mut,
+        has_one = recipient,
+        has_one = refund_to,
+        seeds = [
+            state::PAYMENT_STREAM_SEED,
+            payment_stream.pool.as_ref(),
+            payment_stream.recipient.as_ref(),
+            payment_stream.refund_to.as_ref(),
+            &payment_stream.amount_per_period.to_le_bytes(),
+            &payment_stream.period_slots.to_le_bytes(),
+            payment_stream.stream_id.as_ref()
+        ],
+        bump = payment_stream.bump
+    )]
+    pub payment_stream: Account<'info, state::PaymentStream>,
+
+    /// Account paid each period
+    /// CHECK: Validated by has_one constraint
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    /// Refund address, paid the rent once the stream is paid out
+    /// CHECK: Validated by has_one constraint
+    #[account(mut)]
+    pub refund_to: AccountInfo<'info>,
+}
+
+/// Cancel a payment stream
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelPaymentStream<'info> {
+    /// The payment stream, closed to its refund address
+    #[account(
+        mut,
+        close = refund_to,
+        has_one = refund_to,
+        seeds = [
+            state::PAYMENT_STREAM_SEED,
+            payment_stream.pool.as_ref(),
+            payment_stream.recipient.as_ref(),
+            payment_stream.refund_to.as_ref(),
+            &payment_stream.amount_per_period.to_le_bytes(),
+            &payment_stream.period_slots.to_le_bytes(),
+            payment_stream.stream_id.as_ref()
+        ],
+        bump = payment_stream.bump
+    )]
+    pub payment_stream: Account<'info, state::PaymentStream>,
+
+    #[account(mut)]
+    pub refund_to: Signer<'info>,
+}
+
+/// Execute a queued SOL withdrawal
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ExecuteUnshield<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's SOL vault PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    /// The queued intent, closed to its payer
+    #[account(
+        mut,
+        close = payer,
+        has_one = payer,
+        constraint = queued_unshield.pool == pool.key() @ instructions::NyxError::UnshieldIntentMismatch,
+        seeds = [
+            state::QUEUED_UNSHIELD_SEED,
+            queued_unshield.pool.as_ref(),
+            queued_unshield.intent_hash.as_ref()
+        ],
+        bump = queued_unshield.bump
+    )]
+    pub queued_unshield: Account<'info, state::QueuedUnshield>,
+
+    /// Account that paid the intent's rent
+    /// CHECK: Validated by has_one constraint
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
+
+    /// Recipient receiving the SOL
+    /// CHECK: Any account can receive SOL
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    /// Pool treasury, paid its share of the relayer fee
+    /// CHECK: Validated by address constraint
+    #[account(mut, address = pool.load()?.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Queue a SOL withdrawal intent
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(intent_hash: [u8; 32])]
+pub struct QueueUnshield<'info> {
+    /// The SOL pool the withdrawal is from
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// The queued intent
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + state::QueuedUnshield::SIZE,
+        seeds = [state::QUEUED_UNSHIELD_SEED, pool.key().as_ref(), intent_hash.as_ref()],
+        bump
+    )]
+    pub queued_unshield: Account<'info, state::QueuedUnshield>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Cancel a queued SOL withdrawal intent
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelQueuedUnshield<'info> {
+    /// The queued intent, closed to its payer
+    #[account(
+        mut,
+        close = payer,
+        has_one = payer,
+        seeds = [
+            state::QUEUED_UNSHIELD_SEED,
+            queued_unshield.pool.as_ref(),
+            queued_unshield.intent_hash.as_ref()
+        ],
+        bump = queued_unshield.bump
+    )]
+    pub queued_unshield: Account<'info, state::QueuedUnshield>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+}
+
+/// Bond SOL as a relayer
+#[derive(Accounts)]
+pub struct BondRelayer<'info> {
+    /// The relayer's bond
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + state::RelayerBond::SIZE,
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump
+    )]
+    pub relayer_bond: Account<'info, state::RelayerBond>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Change a relayer's bond (relayer only)
+#[derive(Accounts)]
+pub struct UpdateRelayerBond<'info> {
+    #[account(
+        mut,
+        has_one = relayer @ instructions::NyxError::Unauthorized,
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Account<'info, state::RelayerBond>,
+
+    pub relayer: Signer<'info>,
+}
+
+/// Withdraw a relayer's bond (relayer only)
+#[derive(Accounts)]
+pub struct WithdrawRelayerBond<'info> {
+    /// The relayer's bond, closed to the relayer
+    #[account(
+        mut,
+        close = relayer,
+        has_one = relayer @ instructions::NyxError::Unauthorized,
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Account<'info, state::RelayerBond>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+}
+
+/// Publish a relayer's fee quote for a pool
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetRelayerQuote<'info> {
+    /// The pool quoted for
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// The relayer's quote for the pool
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + state::RelayerQuote::SIZE,
+        seeds = [state::RELAYER_QUOTE_SEED, pool.key().as_ref(), relayer.key().as_ref()],
+        bump
+    )]
+    pub relayer_quote: Account<'info, state::RelayerQuote>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Withdraw a relayer's fee quote
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseRelayerQuote<'info> {
+    /// The quote, closed to the relayer
+    #[account(
+        mut,
+        close = relayer,
+        has_one = relayer @ instructions::NyxError::Unauthorized,
+        seeds = [
+            state::RELAYER_QUOTE_SEED,
+            relayer_quote.pool.as_ref(),
+            relayer.key().as_ref()
+        ],
+        bump = relayer_quote.bump
+    )]
+    pub relayer_quote: Account<'info, state::RelayerQuote>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+}
+
+/// Dispute the fee a relayer charged for a withdrawal
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(nullifier: [u8; 32])]
+pub struct DisputeRelayerFee<'info> {
+    /// The pool the withdrawal was from
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Nullifier set shard that must hold the withdrawal's nullifier
+    /// CHECK: Checked in `nullifier::is_nullifier_in_set`
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Registered Groth16 withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Bond of the relayer that submitted the withdrawal
+    #[account(
+        mut,
+        seeds = [state::RELAYER_BOND_SEED, relayer_bond.relayer.as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Account<'info, state::RelayerBond>,
+
+    /// Recipient of the withdrawal, paid the slashed lamports
+    /// CHECK: Bound by the proof
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    /// Record of the dispute, so each withdrawal is disputed once
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + state::RelayerDispute::SIZE,
+        seeds = [state::RELAYER_DISPUTE_SEED, pool.key().as_ref(), nullifier.as_ref()],
+        bump
+    )]
+    pub relayer_dispute: Account<'info, state::RelayerDispute>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Instructions sysvar, holding the relayer's signed fee quote
+    /// CHECK: Validated by address constraint
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Unshield part of a note from a variable SPL pool, keeping the change
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UnshieldWithChange<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 partial withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::UnshieldWithChange as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK partial withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::UnshieldWithChange as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's vault authority PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: AccountInfo<'info>,
+
+    /// The pool's mint, for checked transfers
+    #[account(address = pool.load()?.mint @ token::TokenError::MintMismatch)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Owner of the recipient token account (receives the SOL refund)
+    /// CHECK: Any account can own the recipient's token account
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Pool's token account: the vault authority's associated token account
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Recipient's associated token account, created if missing with the
+    /// relayer paying the rent out of its fee
+    /// CHECK: Address checked here, created by the processor if missing
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            recipient.key,
+            &mint.key(),
+            token_program.key
+        ) @ token::TokenError::NotAssociatedTokenAccount
+    )]
+    pub recipient_token_account: AccountInfo<'info>,
+
+    /// Relayer's token account, paid the relayer fee
+    #[account(
+        mut,
+        constraint = relayer_token_account.owner == relayer.key(),
+        constraint = relayer_token_account.mint == vault_token_account.mint
+    )]
+    pub relayer_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Treasury's token account, paid its share of the relayer fee
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == pool.load()?.treasury,
+        constraint = treasury_token_account.mint == vault_token_account.mint
+    )]
+    pub treasury_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// Commitment chunk archiving the change note
+    /// (required by incremental pools; compressed pools pass the tree
+    /// accounts as remaining accounts instead)
+    /// CHECK: Checked against the chunk's PDA, and created if missing, by
+    /// `commitment_chunk::archive_commitments`
+    #[account(mut)]
+    pub commitment_chunk: Option<AccountInfo<'info>>,
+
+    /// Pool's `TreeEpoch` PDA for its current tree, needed when the tree
+    /// has no room left and this insertion rolls it over
+    /// CHECK: Checked against the archive's PDA, and created, by
+    /// `rollover::roll_over`
+    #[account(mut)]
+    pub tree_archive: Option<AccountInfo<'info>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Unshield a timelocked note from a SOL pool
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UnshieldSolTimelocked<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 timelocked withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::TimelockedUnshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK timelocked withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::TimelockedUnshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's SOL vault PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    /// Recipient receiving the SOL
+    /// CHECK: Any account can receive SOL
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    /// Pool treasury, paid its share of the relayer fee
+    /// CHECK: Validated by address constraint
+    #[account(mut, address = pool.load()?.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// Recipient's wrapped SOL account, paid instead of `recipient` when
+    /// passed
+    #[account(
+        mut,
+        constraint = recipient_wsol_account.owner == recipient.key(),
+        constraint = recipient_wsol_account.mint == token::NATIVE_MINT @ token::TokenError::MintMismatch
+    )]
+    pub recipient_wsol_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Token program, required with `recipient_wsol_account`
+    pub token_program: Option<Program<'info, Token>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Unshield a timelocked note from an SPL pool
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UnshieldTimelocked<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 timelocked withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::TimelockedUnshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK timelocked withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::TimelockedUnshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's vault authority PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: AccountInfo<'info>,
+
+    /// The pool's mint, for checked transfers
+    #[account(address = pool.load()?.mint @ token::TokenError::MintMismatch)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Owner of the recipient token account (receives the SOL refund)
+    /// CHECK: Any account can own the recipient's token account
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Pool's token account: the vault authority's associated token account
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Recipient's associated token account, created if missing with the
+    /// relayer paying the rent out of its fee
+    /// CHECK: Address checked here, created by the processor if missing
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            recipient.key,
+            &mint.key(),
+            token_program.key
+        ) @ token::TokenError::NotAssociatedTokenAccount
+    )]
+    pub recipient_token_account: AccountInfo<'info>,
+
+    /// Relayer's token account, paid the relayer fee
+    #[account(
+        mut,
+        constraint = relayer_token_account.owner == relayer.key(),
+        constraint = relayer_token_account.mint == vault_token_account.mint
+    )]
+    pub relayer_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Treasury's token account, paid its share of the relayer fee
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == pool.load()?.treasury,
+        constraint = treasury_token_account.mint == vault_token_account.mint
+    )]
+    pub treasury_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Unshield an escrow note from a SOL pool
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UnshieldSolEscrow<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 escrow withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::EscrowUnshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK escrow withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::EscrowUnshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's SOL vault PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        mut,
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+
+    /// Recipient receiving the SOL
+    /// CHECK: Any account can receive SOL
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    /// Pool treasury, paid its share of the relayer fee
+    /// CHECK: Validated by address constraint
+    #[account(mut, address = pool.load()?.treasury)]
+    pub treasury: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Instructions sysvar, holding the arbiter's resolution signature
+    /// CHECK: Validated by address constraint
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// Recipient's wrapped SOL account, paid instead of `recipient` when
+    /// passed
+    #[account(
+        mut,
+        constraint = recipient_wsol_account.owner == recipient.key(),
+        constraint = recipient_wsol_account.mint == token::NATIVE_MINT @ token::TokenError::MintMismatch
+    )]
+    pub recipient_wsol_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Token program, required with `recipient_wsol_account`
+    pub token_program: Option<Program<'info, Token>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Unshield an escrow note from an SPL pool
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UnshieldEscrow<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 escrow withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::EscrowUnshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK escrow withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::EscrowUnshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's vault authority PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: AccountInfo<'info>,
+
+    /// The pool's mint, for checked transfers
+    #[account(address = pool.load()?.mint @ token::TokenError::MintMismatch)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Owner of the recipient token account (receives the SOL refund)
+    /// CHECK: Any account can own the recipient's token account
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Instructions sysvar, holding the arbiter's resolution signature
+    /// CHECK: Validated by address constraint
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    /// Pool's token account: the vault authority's associated token account
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Recipient's associated token account, created if missing with the
+    /// relayer paying the rent out of its fee
+    /// CHECK: Address checked here, created by the processor if missing
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            recipient.key,
+            &mint.key(),
+            token_program.key
+        ) @ token::TokenError::NotAssociatedTokenAccount
+    )]
+    pub recipient_token_account: AccountInfo<'info>,
+
+    /// Relayer's token account, paid the relayer fee
+    #[account(
+        mut,
+        constraint = relayer_token_account.owner == relayer.key(),
+        constraint = relayer_token_account.mint == vault_token_account.mint
+    )]
+    pub relayer_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Treasury's token account, paid its share of the relayer fee
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == pool.load()?.treasury,
+        constraint = treasury_token_account.mint == vault_token_account.mint
+    )]
+    pub treasury_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
+}
+
+/// Unshield SPL tokens from the multi-asset pool
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UnshieldMultiAsset<'info> {
+    /// The multi-asset pool
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 multi-asset withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::MultiAssetUnshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK multi-asset withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::MultiAssetUnshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's vault authority PDA
+    /// CHECK: Validated by seeds constraint
+    #[account(
+        seeds = [token::VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: AccountInfo<'info>,
+
+    /// Mint being withdrawn, whose asset ID the proof binds
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Owner of the recipient token account (receives the SOL refund)
+    /// CHECK: Any account can own the recipient's token account
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Pool's vault for this mint: the vault authority's associated token
+    /// account
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Recipient's associated token account, created if missing with the
+    /// relayer paying the rent out of its fee
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        associated_token::mint = mint,
+        associated_token::authority = recipient,
+        associated_token::token_program = token_program
+    )]
+    pub recipient_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Relayer's token account, paid the relayer fee
+    #[account(
+        mut,
+        constraint = relayer_token_account.owner == relayer.key(),
+        constraint = relayer_token_account.mint == vault_token_account.mint
+    )]
+    pub relayer_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Treasury's token account, paid its share of the relayer fee
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == pool.load()?.treasury,
+        constraint = treasury_token_account.mint == vault_token_account.mint
+    )]
+    pub treasury_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
 
-    /// Unshield SPL tokens - spend commitment and withdraw tokens
-    pub fn unshield(
-        ctx: Context<Unshield>,
-        nullifier: [u8; 32],
-        amount: u64,
-        proof: Vec<u8>,
-    ) -> Result<()> {
-        processor::process_unshield(ctx, nullifier, amount, proof)
-    }
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
 }
 
-// Re-export pool seed from token module
-use token::POOL_SEED;
-
-/// Initialize a new privacy pool with a specific denomination
+/// Unshield an NFT from the NFT pool
+#[event_cpi]
 #[derive(Accounts)]
-#[instruction(denomination: u64)]
-pub struct Initialize<'info> {
-    /// The pool account, derived from denomination
-    /// Each denomination (0, 0.1 SOL, 1 SOL, 10 SOL) gets its own pool
+pub struct UnshieldNft<'info> {
+    /// The NFT pool
     #[account(
-        init,
-        payer = authority,
-        space = 8 + state::PrivacyPool::SIZE,
-        seeds = [POOL_SEED, &denomination.to_le_bytes()],
-        bump
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
     )]
-    pub pool: Account<'info, state::PrivacyPool>,
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
 
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub nullifier_set: AccountInfo<'info>,
 
-    pub system_program: Program<'info, System>,
-}
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
 
-/// Shield native SOL into a specific denomination pool
-#[derive(Accounts)]
-#[instruction(commitment: [u8; 32], amount: u64)]
-pub struct ShieldSol<'info> {
-    /// The pool for this denomination (denomination is stored in pool.denomination)
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
     #[account(
         mut,
-        seeds = [POOL_SEED, &pool.denomination.to_le_bytes()],
-        bump = pool.bump
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
     )]
-    pub pool: Account<'info, state::PrivacyPool>,
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
 
-    /// Pool's SOL vault PDA
+    /// Registered Groth16 multi-asset withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::MultiAssetUnshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK multi-asset withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::MultiAssetUnshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
+    )]
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
+
+    /// Pool's vault authority PDA
     /// CHECK: Validated by seeds constraint
     #[account(
-        mut,
         seeds = [token::VAULT_SEED, pool.key().as_ref()],
         bump
     )]
-    pub vault: AccountInfo<'info>,
+    pub vault_authority: AccountInfo<'info>,
 
+    /// The NFT's mint, whose asset ID the proof binds
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Owner of the recipient token account (receives the SOL refund)
+    /// CHECK: Any account can own the recipient's token account
     #[account(mut)]
-    pub depositor: Signer<'info>,
+    pub recipient: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
 
     pub system_program: Program<'info, System>,
+
+    /// Pool's vault for this NFT, closed once it is released
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Recipient's associated token account, created if missing with the
+    /// relayer paying the rent
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        associated_token::mint = mint,
+        associated_token::authority = recipient,
+        associated_token::token_program = token_program
+    )]
+    pub recipient_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
 }
 
-/// Shield SPL tokens into a specific denomination pool
+/// Unshield a compressed NFT from the NFT pool
+#[event_cpi]
 #[derive(Accounts)]
-#[instruction(commitment: [u8; 32], amount: u64)]
-pub struct Shield<'info> {
-    /// The pool for this denomination
+pub struct UnshieldCnft<'info> {
+    /// The NFT pool
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
     #[account(
         mut,
-        seeds = [POOL_SEED, &pool.denomination.to_le_bytes()],
-        bump = pool.bump
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Registered Groth16 multi-asset withdraw verifying key (Groth16 pools)
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::MultiAssetUnshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Option<Box<Account<'info, groth16::VerifyingKeyAccount>>>,
+
+    /// Registered PLONK multi-asset withdraw verifying key (PLONK pools)
+    #[account(
+        seeds = [
+            plonk::PLONK_VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::MultiAssetUnshield as u8],
+            &plonk_verifying_key.version.to_le_bytes()
+        ],
+        bump = plonk_verifying_key.bump
     )]
-    pub pool: Account<'info, state::PrivacyPool>,
+    pub plonk_verifying_key: Option<Box<Account<'info, plonk::PlonkVerifyingKeyAccount>>>,
 
     /// Pool's vault authority PDA
     /// CHECK: Validated by seeds constraint
@@ -143,76 +7012,246 @@ pub struct Shield<'info> {
     )]
     pub vault_authority: AccountInfo<'info>,
 
-    /// Pool's token account for this mint
+    /// New owner of the NFT (receives the SOL refund)
+    /// CHECK: Any account can own a compressed NFT
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Bubblegum's config PDA for the NFT's tree
+    /// CHECK: Checked by Bubblegum
+    pub tree_config: AccountInfo<'info>,
+
+    /// Bubblegum tree holding the NFT's leaf
+    /// CHECK: Checked by Bubblegum against the leaf's proof
+    #[account(mut)]
+    pub merkle_tree: AccountInfo<'info>,
+
+    /// CHECK: spl-noop program
+    #[account(address = compression::NOOP_PROGRAM_ID)]
+    pub log_wrapper: AccountInfo<'info>,
+
+    /// CHECK: spl-account-compression program
+    #[account(address = compression::COMPRESSION_PROGRAM_ID)]
+    pub compression_program: AccountInfo<'info>,
+
+    /// CHECK: Bubblegum program
+    #[account(address = bubblegum::BUBBLEGUM_PROGRAM_ID)]
+    pub bubblegum_program: AccountInfo<'info>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
     #[account(
-        mut,
-        constraint = vault_token_account.owner == vault_authority.key()
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
     )]
-    pub vault_token_account: Account<'info, TokenAccount>,
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
 
-    /// Depositor's token account
+    /// Pool's stats account, updated when passed
     #[account(
         mut,
-        constraint = depositor_token_account.mint == vault_token_account.mint
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
     )]
-    pub depositor_token_account: Account<'info, TokenAccount>,
-
-    #[account(mut)]
-    pub depositor: Signer<'info>,
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
 
-    pub token_program: Program<'info, Token>,
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
 }
 
-/// Private transfer within a pool
+/// Start a staged SOL unshield
 #[derive(Accounts)]
 #[instruction(nullifier: [u8; 32])]
-pub struct Transfer<'info> {
+pub struct VerifyStage1<'info> {
     /// The pool for this denomination
     #[account(
-        mut,
-        seeds = [POOL_SEED, &pool.denomination.to_le_bytes()],
-        bump = pool.bump
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
     )]
-    pub pool: Account<'info, state::PrivacyPool>,
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
 
-    /// Nullifier marker PDA - created to mark nullifier as spent
-    /// If this account already exists, the transaction fails (double-spend prevention)
+    /// Verification state PDA - one per pool and nullifier
     #[account(
         init,
         payer = relayer,
-        space = 8 + nullifier::NullifierMarker::SIZE,
-        seeds = [nullifier::NULLIFIER_SEED, pool.key().as_ref(), &nullifier],
+        space = 8 + staged::VerificationState::SIZE,
+        seeds = [
+            staged::VERIFICATION_SEED,
+            pool.key().as_ref(),
+            relayer.key().as_ref(),
+            &nullifier
+        ],
         bump
     )]
-    pub nullifier_marker: Account<'info, nullifier::NullifierMarker>,
+    pub verification_state: Box<Account<'info, staged::VerificationState>>,
+
+    /// Registered withdraw verifying key for this pool
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Box<Account<'info, groth16::VerifyingKeyAccount>>,
+
+    /// Recipient of the withdrawal (bound into the proof)
+    /// CHECK: Any account can receive SOL
+    pub recipient: AccountInfo<'info>,
 
     #[account(mut)]
     pub relayer: Signer<'info>,
 
     pub system_program: Program<'info, System>,
+
+    /// Archived tree the spent note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
 }
 
-/// Unshield native SOL from a specific denomination pool
+/// Run the pairing check of a staged SOL unshield
 #[derive(Accounts)]
-#[instruction(nullifier: [u8; 32])]
-pub struct UnshieldSol<'info> {
+pub struct VerifyStage2<'info> {
     /// The pool for this denomination
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Verification state opened in stage 1
     #[account(
         mut,
-        seeds = [POOL_SEED, &pool.denomination.to_le_bytes()],
-        bump = pool.bump
+        seeds = [
+            staged::VERIFICATION_SEED,
+            pool.key().as_ref(),
+            verification_state.relayer.as_ref(),
+            &verification_state.nullifier
+        ],
+        bump = verification_state.bump,
+        has_one = pool,
+        has_one = relayer @ instructions::NyxError::Unauthorized
     )]
-    pub pool: Account<'info, state::PrivacyPool>,
+    pub verification_state: Box<Account<'info, staged::VerificationState>>,
 
-    /// Nullifier marker PDA - created to mark nullifier as spent
+    /// Withdraw verifying key the stage 1 inputs were prepared with
     #[account(
-        init,
-        payer = relayer,
-        space = 8 + nullifier::NullifierMarker::SIZE,
-        seeds = [nullifier::NULLIFIER_SEED, pool.key().as_ref(), &nullifier],
-        bump
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &verification_state.vk_version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
     )]
-    pub nullifier_marker: Account<'info, nullifier::NullifierMarker>,
+    pub verifying_key: Box<Account<'info, groth16::VerifyingKeyAccount>>,
+
+    pub relayer: Signer<'info>,
+}
+
+/// Run a batched pairing check over several staged SOL unshields
+#[derive(Accounts)]
+pub struct VerifyBatch<'info> {
+    /// The pool for this denomination
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Registered withdraw verifying key for this pool
+    #[account(
+        seeds = [
+            groth16::VK_SEED,
+            pool.key().as_ref(),
+            &[CircuitId::Unshield as u8],
+            &verifying_key.version.to_le_bytes()
+        ],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Box<Account<'info, groth16::VerifyingKeyAccount>>,
+
+    pub relayer: Signer<'info>,
+}
+
+/// Complete a verified staged SOL unshield
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FinalizeUnshield<'info> {
+    /// The pool for this denomination
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Protocol-wide settings (fee split)
+    #[account(seeds = [state::PROTOCOL_CONFIG_SEED], bump = protocol_config.bump)]
+    pub protocol_config: Box<Account<'info, state::ProtocolConfig>>,
+
+    /// Verification state - closed back to the relayer on success
+    #[account(
+        mut,
+        close = relayer,
+        seeds = [
+            staged::VERIFICATION_SEED,
+            pool.key().as_ref(),
+            verification_state.relayer.as_ref(),
+            &verification_state.nullifier
+        ],
+        bump = verification_state.bump,
+        has_one = pool,
+        has_one = recipient,
+        has_one = relayer @ instructions::NyxError::Unauthorized
+    )]
+    pub verification_state: Box<Account<'info, staged::VerificationState>>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
 
     /// Pool's SOL vault PDA
     /// CHECK: Validated by seeds constraint
@@ -224,64 +7263,261 @@ pub struct UnshieldSol<'info> {
     pub vault: AccountInfo<'info>,
 
     /// Recipient receiving the SOL
-    /// CHECK: Any account can receive SOL
+    /// CHECK: Must match the recipient recorded in stage 1
     #[account(mut)]
     pub recipient: AccountInfo<'info>,
 
+    /// Pool treasury, paid its share of the relayer fee
+    /// CHECK: Validated by address constraint
+    #[account(mut, address = pool.load()?.treasury)]
+    pub treasury: AccountInfo<'info>,
+
     #[account(mut)]
     pub relayer: Signer<'info>,
 
     pub system_program: Program<'info, System>,
+
+    /// Pool's stats account, updated when passed
+    #[account(
+        mut,
+        seeds = [state::POOL_STATS_SEED, pool.key().as_ref()],
+        bump = pool_stats.load()?.bump
+    )]
+    pub pool_stats: Option<AccountLoader<'info, state::PoolStats>>,
+
+    /// The relayer's bond, required by pools that only accept bonded
+    /// relayers
+    #[account(
+        seeds = [state::RELAYER_BOND_SEED, relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Option<Account<'info, state::RelayerBond>>,
 }
 
-/// Unshield SPL tokens from a specific denomination pool
+/// Abandon a staged SOL unshield
 #[derive(Accounts)]
-#[instruction(nullifier: [u8; 32])]
-pub struct Unshield<'info> {
-    /// The pool for this denomination
+pub struct CloseVerificationState<'info> {
+    /// Verification state, closed back to the relayer that opened it
     #[account(
         mut,
-        seeds = [POOL_SEED, &pool.denomination.to_le_bytes()],
-        bump = pool.bump
+        close = relayer,
+        seeds = [
+            staged::VERIFICATION_SEED,
+            verification_state.pool.as_ref(),
+            relayer.key().as_ref(),
+            &verification_state.nullifier
+        ],
+        bump = verification_state.bump,
+        has_one = relayer @ instructions::NyxError::Unauthorized
+    )]
+    pub verification_state: Box<Account<'info, staged::VerificationState>>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+}
+
+/// Commit to an emergency withdrawal's recipient
+#[derive(Accounts)]
+#[instruction(nullifier: [u8; 32])]
+pub struct CommitEmergencyWithdraw<'info> {
+    #[account(
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
     )]
-    pub pool: Account<'info, state::PrivacyPool>,
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
 
-    /// Nullifier marker PDA - created to mark nullifier as spent
+    /// The commit, keyed by the note's nullifier
     #[account(
         init,
-        payer = relayer,
-        space = 8 + nullifier::NullifierMarker::SIZE,
-        seeds = [nullifier::NULLIFIER_SEED, pool.key().as_ref(), &nullifier],
+        payer = payer,
+        space = 8 + state::EmergencyCommit::SIZE,
+        seeds = [state::EMERGENCY_COMMIT_SEED, pool.key().as_ref(), nullifier.as_ref()],
         bump
     )]
-    pub nullifier_marker: Account<'info, nullifier::NullifierMarker>,
+    pub emergency_commit: Account<'info, state::EmergencyCommit>,
 
-    /// Pool's vault authority PDA
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Withdraw a note by revealing its preimage while unshields are halted
+#[event_cpi]
+#[derive(Accounts)]
+pub struct EmergencyWithdraw<'info> {
+    #[account(
+        mut,
+        constraint = pool.load()?.is_at(&pool.key())
+            @ instructions::NyxError::InvalidPoolAddress
+    )]
+    pub pool: AccountLoader<'info, state::PrivacyPool>,
+
+    /// Recipient committed for the note, closed to its payer; its address
+    /// is checked against the revealed nullifier
+    #[account(
+        mut,
+        close = payer,
+        has_one = pool,
+        has_one = recipient @ instructions::NyxError::EmergencyWithdrawNotCommitted,
+        has_one = payer @ instructions::NyxError::Unauthorized
+    )]
+    pub emergency_commit: Account<'info, state::EmergencyCommit>,
+
+    /// Legacy nullifier marker PDA, which must not exist
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    pub nullifier_marker: AccountInfo<'info>,
+
+    /// Nullifier set shard for the nullifier, created or grown as needed
+    /// CHECK: Checked in `nullifier::spend_nullifier`
+    #[account(mut)]
+    pub nullifier_set: AccountInfo<'info>,
+
+    /// Pool's spent-nullifier bloom filter, updated when passed
+    #[account(
+        mut,
+        seeds = [state::NULLIFIER_BLOOM_SEED, pool.key().as_ref()],
+        bump = nullifier_bloom.load()?.bump
+    )]
+    pub nullifier_bloom: Option<AccountLoader<'info, state::NullifierBloom>>,
+
+    /// Pool's indexed nullifier tree, queueing the spent nullifier when passed
+    #[account(
+        mut,
+        seeds = [nullifier_tree::NULLIFIER_TREE_SEED, pool.key().as_ref()],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, nullifier_tree::NullifierTree>>,
+
+    /// Pool's vault PDA, holding SOL or owning the token vault
     /// CHECK: Validated by seeds constraint
     #[account(
+        mut,
         seeds = [token::VAULT_SEED, pool.key().as_ref()],
         bump
     )]
-    pub vault_authority: AccountInfo<'info>,
+    pub vault: AccountInfo<'info>,
 
-    /// Pool's token account
+    /// Pool's token account (SPL pools only)
     #[account(
         mut,
-        constraint = vault_token_account.owner == vault_authority.key()
+        constraint = vault_token_account.owner == vault.key(),
+        constraint = vault_token_account.mint == pool.load()?.mint @ token::TokenError::MintMismatch
     )]
-    pub vault_token_account: Account<'info, TokenAccount>,
+    pub vault_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
 
-    /// Recipient's token account
+    /// Recipient's token account (SPL pools only)
     #[account(
         mut,
-        constraint = recipient_token_account.mint == vault_token_account.mint
+        constraint = recipient_token_account.owner == recipient.key(),
+        constraint = recipient_token_account.mint == pool.load()?.mint @ token::TokenError::MintMismatch
     )]
-    pub recipient_token_account: Account<'info, TokenAccount>,
+    pub recipient_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The pool's mint, for checked transfers (SPL pools only)
+    #[account(address = pool.load()?.mint @ token::TokenError::MintMismatch)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
 
+    /// Receives the withdrawn SOL, or owns `recipient_token_account`
+    /// CHECK: Must be the committed recipient
     #[account(mut)]
-    pub relayer: Signer<'info>,
+    pub recipient: AccountInfo<'info>,
+
+    /// Pays for the nullifier set
+    #[account(mut)]
+    pub payer: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
 
     pub system_program: Program<'info, System>,
+
+    /// Archived tree the note belongs to; omit for the current tree
+    #[account(
+        seeds = [
+            state::TREE_EPOCH_SEED,
+            pool.key().as_ref(),
+            &tree_epoch.epoch.to_le_bytes()
+        ],
+        bump = tree_epoch.bump
+    )]
+    pub tree_epoch: Option<Account<'info, state::TreeEpoch>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether `authority` only signs `metas`, so a multisig vault can
+    /// sign for it without holding or paying any lamports
+    fn only_signs(metas: Vec<AccountMeta>, authority: Pubkey) -> bool {
+        metas
+            .iter()
+            .find(|meta| meta.pubkey == authority)
+            .is_some_and(|meta| meta.is_signer && !meta.is_writable)
+    }
+
+    #[test]
+    fn test_admin_instructions_leave_rent_to_the_payer() {
+        let key = Pubkey::new_unique;
+        let authority = key();
+
+        let initialize = accounts::Initialize {
+            pool: key(),
+            mint: None,
+            mint_allowlist: None,
+            protocol_config: Some(key()),
+            authority,
+            payer: key(),
+            system_program: key(),
+        };
+        assert!(only_signs(initialize.to_account_metas(None), authority));
+
+        let standard_pools = accounts::InitializeStandardPools {
+            pool_0: key(),
+            pool_1: key(),
+            pool_2: key(),
+            pool_3: key(),
+            pool_registry: key(),
+            protocol_config: Some(key()),
+            authority,
+            payer: key(),
+            system_program: key(),
+        };
+        assert!(only_signs(standard_pools.to_account_metas(None), authority));
+
+        let resize = accounts::ResizeRootHistory {
+            pool: key(),
+            authority,
+            payer: key(),
+            system_program: key(),
+        };
+        assert!(only_signs(resize.to_account_metas(None), authority));
+
+        let propose_vk = accounts::ProposeVerifyingKey {
+            pool: key(),
+            protocol_config: None,
+            verifying_key: key(),
+            authority,
+            payer: key(),
+            system_program: key(),
+        };
+        assert!(only_signs(propose_vk.to_account_metas(None), authority));
+
+        let set_guardians = accounts::SetGuardians {
+            pool: key(),
+            guardian_set: key(),
+            authority,
+            payer: key(),
+            system_program: key(),
+        };
+        assert!(only_signs(set_guardians.to_account_metas(None), authority));
+
+        let update_config = accounts::UpdatePoolConfig {
+            pool: key(),
+            authority,
+            event_authority: key(),
+            program: ID,
+        };
+        assert!(only_signs(update_config.to_account_metas(None), authority));
+    }
 }
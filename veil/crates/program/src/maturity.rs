@@ -0,0 +1,138 @@
+//! Deposit Maturity
+//!
+//! Pools with a `min_spend_delay_slots` refuse spends of notes deposited
+//! within the delay. Spend proofs hide their leaf, so for them a root's age
+//! bounds the age of every leaf under it: `require_mature_root` checks the
+//! root became current at least the delay ago. `verify_membership` reveals
+//! its leaf and checks the slot its `CommitmentRecord` tracks instead, when
+//! one is passed.
+//!
+//! A pool taking more insertions per delay than its root history holds
+//! would evict every mature root before it could be used, so the oldest
+//! root of a full history counts as mature too: every leaf under it has a
+//! full history of deposits after it. Size the history for the pool's
+//! traffic to keep the delay binding.
+
+use anchor_lang::prelude::*;
+
+use crate::instructions::NyxError;
+use crate::state::{CommitmentRecord, PrivacyPool, RootEntry};
+
+/// Oldest root in the history, once the history is full
+fn oldest_root(pool: &PrivacyPool, root_history: &[RootEntry]) -> Option<[u8; 32]> {
+    let entry = root_history.get(pool.root_history_index as usize)?;
+    (entry.root != [0u8; 32]).then_some(entry.root)
+}
+
+/// Require `root`, current since `root_slot`, to be mature at `slot`
+pub fn require_mature_root(
+    pool: &PrivacyPool,
+    root: &[u8; 32],
+    root_slot: u64,
+    root_history: &[RootEntry],
+    slot: u64,
+) -> Result<()> {
+    require!(
+        slot >= root_slot.saturating_add(pool.min_spend_delay_slots)
+            || oldest_root(pool, root_history) == Some(*root),
+        NyxError::RootNotMature
+    );
+    Ok(())
+}
+
+/// Require the leaf `record` tracks to sit at `leaf_index` of the pool's
+/// current tree and be mature at `slot`
+pub fn require_mature_leaf(
+    pool: &PrivacyPool,
+    record: &CommitmentRecord,
+    leaf_index: u64,
+    slot: u64,
+) -> Result<()> {
+    require!(
+        record.tree_epoch == pool.tree_epoch && record.leaf_index == leaf_index,
+        NyxError::InvalidMerkleProof
+    );
+    require!(
+        slot >= record.slot.saturating_add(pool.min_spend_delay_slots),
+        NyxError::LeafNotMature
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HISTORY: u16 = 4;
+
+    fn pool(delay: u64) -> (Box<PrivacyPool>, Vec<RootEntry>) {
+        let mut pool: Box<PrivacyPool> = Box::new(bytemuck::Zeroable::zeroed());
+        pool.initialize(Pubkey::new_unique(), 255, 0, HISTORY, Pubkey::default(), 9);
+        pool.min_spend_delay_slots = delay;
+        let history = (0..HISTORY).map(|_| RootEntry::default()).collect();
+        (pool, history)
+    }
+
+    fn deposit(pool: &mut PrivacyPool, history: &mut [RootEntry], slot: u64) -> [u8; 32] {
+        let commitment = [(pool.commitment_count() + 1) as u8; 32];
+        pool.add_commitment(commitment, history, slot).unwrap();
+        pool.current_root()
+    }
+
+    #[test]
+    fn test_roots_mature_after_delay() {
+        let (mut pool, mut history) = pool(100);
+        let root = deposit(&mut pool, &mut history, 1_000);
+        let root_slot = pool.root_slot_of(&root, &history).unwrap();
+        assert_eq!(root_slot, 1_000);
+
+        let err = require_mature_root(&pool, &root, root_slot, &history, 1_099).unwrap_err();
+        assert_eq!(err, NyxError::RootNotMature.into());
+        assert!(require_mature_root(&pool, &root, root_slot, &history, 1_100).is_ok());
+    }
+
+    #[test]
+    fn test_oldest_root_of_full_history_is_mature() {
+        let (mut pool, mut history) = pool(100);
+        let first = deposit(&mut pool, &mut history, 1_000);
+        for _ in 0..HISTORY - 1 {
+            deposit(&mut pool, &mut history, 1_000);
+        }
+        // The empty tree's root is the oldest for now, so the first
+        // deposit's root still has to wait out the delay
+        let slot = pool.root_slot_of(&first, &history).unwrap();
+        assert!(require_mature_root(&pool, &first, slot, &history, 1_001).is_err());
+
+        // A full history of deposits after it matures the oldest root early
+        deposit(&mut pool, &mut history, 1_000);
+        let slot = pool.root_slot_of(&first, &history).unwrap();
+        assert!(require_mature_root(&pool, &first, slot, &history, 1_001).is_ok());
+        let newer = pool.recent_roots(&history).next().unwrap();
+        let slot = pool.root_slot_of(&newer, &history).unwrap();
+        assert!(require_mature_root(&pool, &newer, slot, &history, 1_001).is_err());
+    }
+
+    #[test]
+    fn test_leaves_mature_by_their_record() {
+        let (mut pool, mut history) = pool(100);
+        deposit(&mut pool, &mut history, 1_000);
+        let record = CommitmentRecord {
+            pool: Pubkey::new_unique(),
+            commitment: [1u8; 32],
+            tree_epoch: 0,
+            leaf_index: 0,
+            slot: 1_000,
+            bump: 255,
+        };
+        let err = require_mature_leaf(&pool, &record, 0, 1_099).unwrap_err();
+        assert_eq!(err, NyxError::LeafNotMature.into());
+        assert!(require_mature_leaf(&pool, &record, 0, 1_100).is_ok());
+
+        // The record must describe the leaf being checked
+        let err = require_mature_leaf(&pool, &record, 1, 1_100).unwrap_err();
+        assert_eq!(err, NyxError::InvalidMerkleProof.into());
+        pool.tree_epoch = 1;
+        let err = require_mature_leaf(&pool, &record, 0, 1_100).unwrap_err();
+        assert_eq!(err, NyxError::InvalidMerkleProof.into());
+    }
+}
@@ -1,87 +1,92 @@
 //! Incremental Merkle Tree Implementation
 //!
 //! This module implements an incremental Merkle tree for storing commitments.
-//! The tree uses SHA256 for hashing (simpler than Poseidon for MVP).
+//! Nodes are hashed with Poseidon (see `poseidon`), the same hash the withdraw
+//! circuit uses, so roots computed here are the roots proofs are made against.
 //!
 //! Tree Structure:
-//! - Depth: 20 levels (supports ~1 million leaves)
-//! - Leaves are commitments (32 bytes each)
+//! - Depth: 10 levels (1,024 leaves)
+//! - Leaves are commitments (canonical BN254 field elements, 32 bytes each)
 //! - Uses "filled subtrees" optimization for O(log n) insertions
+//! - Keeps the top `CANOPY_DEPTH` levels in a canopy, so membership proofs
+//!   only need the siblings below it
 
 use anchor_lang::prelude::*;
-use solana_program::keccak;
+
+use crate::poseidon;
 
 /// Merkle tree depth (10 levels = 2^10 = 1,024 leaves)
 /// Reduced from 20 to avoid stack overflow on Solana
 pub const TREE_DEPTH: usize = 10;
 
-/// Zero value for empty leaves (hash of empty bytes)
-pub const ZERO_VALUE: [u8; 32] = [
-    0x29, 0x0d, 0xec, 0xd9, 0x54, 0x8b, 0x62, 0xa8,
-    0xd6, 0x03, 0x45, 0xa9, 0x88, 0x38, 0x6f, 0xc8,
-    0x4b, 0xa6, 0xbc, 0x95, 0x48, 0x40, 0x08, 0xf6,
-    0x36, 0x2f, 0x93, 0x16, 0x0e, 0xf3, 0xe5, 0x63,
-];
+/// Number of upper tree levels cached in the canopy
+pub const CANOPY_DEPTH: usize = 4;
+
+/// Nodes stored in the canopy (2 + 4 + ... + 2^CANOPY_DEPTH)
+pub const CANOPY_SIZE: usize = (1 << (CANOPY_DEPTH + 1)) - 2;
 
-/// Precomputed zero hashes for each level (Keccak256)
-/// zeros[i] = keccak256(zeros[i-1] || zeros[i-1])
+/// Zero value for empty leaves (matches the circuit's empty leaf)
+pub const ZERO_VALUE: [u8; 32] = [0u8; 32];
+
+/// Precomputed zero hashes for each level (Poseidon)
+/// zeros[i] = Poseidon(zeros[i-1], zeros[i-1])
 /// Precomputed to eliminate stack allocation in get_zero_hash()
 pub const ZERO_HASHES: [[u8; 32]; TREE_DEPTH + 1] = [
     // Level 0
-    [0x29, 0x0d, 0xec, 0xd9, 0x54, 0x8b, 0x62, 0xa8,
-     0xd6, 0x03, 0x45, 0xa9, 0x88, 0x38, 0x6f, 0xc8,
-     0x4b, 0xa6, 0xbc, 0x95, 0x48, 0x40, 0x08, 0xf6,
-     0x36, 0x2f, 0x93, 0x16, 0x0e, 0xf3, 0xe5, 0x63],
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
     // Level 1
-    [0x63, 0x3d, 0xc4, 0xd7, 0xda, 0x72, 0x56, 0x66,
-     0x0a, 0x89, 0x2f, 0x8f, 0x16, 0x04, 0xa4, 0x4b,
-     0x54, 0x32, 0x64, 0x9c, 0xc8, 0xec, 0x5c, 0xb3,
-     0xce, 0xd4, 0xc4, 0xe6, 0xac, 0x94, 0xdd, 0x1d],
+    [0x20, 0x98, 0xf5, 0xfb, 0x9e, 0x23, 0x9e, 0xab,
+     0x3c, 0xea, 0xc3, 0xf2, 0x7b, 0x81, 0xe4, 0x81,
+     0xdc, 0x31, 0x24, 0xd5, 0x5f, 0xfe, 0xd5, 0x23,
+     0xa8, 0x39, 0xee, 0x84, 0x46, 0xb6, 0x48, 0x64],
     // Level 2
-    [0x89, 0x07, 0x40, 0xa8, 0xeb, 0x06, 0xce, 0x9b,
-     0xe4, 0x22, 0xcb, 0x8d, 0xa5, 0xcd, 0xaf, 0xc2,
-     0xb5, 0x8c, 0x0a, 0x5e, 0x24, 0x03, 0x6c, 0x57,
-     0x8d, 0xe2, 0xa4, 0x33, 0xc8, 0x28, 0xff, 0x7d],
+    [0x10, 0x69, 0x67, 0x3d, 0xcd, 0xb1, 0x22, 0x63,
+     0xdf, 0x30, 0x1a, 0x6f, 0xf5, 0x84, 0xa7, 0xec,
+     0x26, 0x1a, 0x44, 0xcb, 0x9d, 0xc6, 0x8d, 0xf0,
+     0x67, 0xa4, 0x77, 0x44, 0x60, 0xb1, 0xf1, 0xe1],
     // Level 3
-    [0x3b, 0x8e, 0xc0, 0x9e, 0x02, 0x6f, 0xdc, 0x30,
-     0x53, 0x65, 0xdf, 0xc9, 0x4e, 0x18, 0x9a, 0x81,
-     0xb3, 0x8c, 0x75, 0x97, 0xb3, 0xd9, 0x41, 0xc2,
-     0x79, 0xf0, 0x42, 0xe8, 0x20, 0x6e, 0x0b, 0xd8],
+    [0x18, 0xf4, 0x33, 0x31, 0x53, 0x7e, 0xe2, 0xaf,
+     0x2e, 0x3d, 0x75, 0x8d, 0x50, 0xf7, 0x21, 0x06,
+     0x46, 0x7c, 0x6e, 0xea, 0x50, 0x37, 0x1d, 0xd5,
+     0x28, 0xd5, 0x7e, 0xb2, 0xb8, 0x56, 0xd2, 0x38],
     // Level 4
-    [0xec, 0xd5, 0x0e, 0xee, 0x38, 0xe3, 0x86, 0xbd,
-     0x62, 0xbe, 0x9b, 0xed, 0xb9, 0x90, 0x70, 0x69,
-     0x51, 0xb6, 0x5f, 0xe0, 0x53, 0xbd, 0x9d, 0x8a,
-     0x52, 0x1a, 0xf7, 0x53, 0xd1, 0x39, 0xe2, 0xda],
+    [0x07, 0xf9, 0xd8, 0x37, 0xcb, 0x17, 0xb0, 0xd3,
+     0x63, 0x20, 0xff, 0xe9, 0x3b, 0xa5, 0x23, 0x45,
+     0xf1, 0xb7, 0x28, 0x57, 0x1a, 0x56, 0x82, 0x65,
+     0xca, 0xac, 0x97, 0x55, 0x9d, 0xbc, 0x95, 0x2a],
     // Level 5
-    [0xde, 0xff, 0xf6, 0xd3, 0x30, 0xbb, 0x54, 0x03,
-     0xf6, 0x3b, 0x14, 0xf3, 0x3b, 0x57, 0x82, 0x74,
-     0x16, 0x0d, 0xe3, 0xa5, 0x0d, 0xf4, 0xef, 0xec,
-     0xf0, 0xe0, 0xdb, 0x73, 0xbc, 0xdd, 0x3d, 0xa5],
+    [0x2b, 0x94, 0xcf, 0x5e, 0x87, 0x46, 0xb3, 0xf5,
+     0xc9, 0x63, 0x1f, 0x4c, 0x5d, 0xf3, 0x29, 0x07,
+     0xa6, 0x99, 0xc5, 0x8c, 0x94, 0xb2, 0xad, 0x4d,
+     0x7b, 0x5c, 0xec, 0x16, 0x39, 0x18, 0x3f, 0x55],
     // Level 6
-    [0x61, 0x7b, 0xdd, 0x11, 0xf7, 0xc0, 0xa1, 0x1f,
-     0x49, 0xdb, 0x22, 0xf6, 0x29, 0x38, 0x7a, 0x12,
-     0xda, 0x75, 0x96, 0xf9, 0xd1, 0x70, 0x4d, 0x74,
-     0x65, 0x17, 0x7c, 0x63, 0xd8, 0x8e, 0xc7, 0xd7],
+    [0x2d, 0xee, 0x93, 0xc5, 0xa6, 0x66, 0x45, 0x96,
+     0x46, 0xea, 0x7d, 0x22, 0xcc, 0xa9, 0xe1, 0xbc,
+     0xfe, 0xd7, 0x1e, 0x69, 0x51, 0xb9, 0x53, 0x61,
+     0x1d, 0x11, 0xdd, 0xa3, 0x2e, 0xa0, 0x9d, 0x78],
     // Level 7
-    [0x29, 0x2c, 0x23, 0xa9, 0xaa, 0x1d, 0x8b, 0xea,
-     0x7e, 0x24, 0x35, 0xe5, 0x55, 0xa4, 0xa6, 0x0e,
-     0x37, 0x9a, 0x5a, 0x35, 0xf3, 0xf4, 0x52, 0xba,
-     0xe6, 0x01, 0x21, 0x07, 0x3f, 0xb6, 0xee, 0xad],
+    [0x07, 0x82, 0x95, 0xe5, 0xa2, 0x2b, 0x84, 0xe9,
+     0x82, 0xcf, 0x60, 0x1e, 0xb6, 0x39, 0x59, 0x7b,
+     0x8b, 0x05, 0x15, 0xa8, 0x8c, 0xb5, 0xac, 0x7f,
+     0xa8, 0xa4, 0xaa, 0xbe, 0x3c, 0x87, 0x34, 0x9d],
     // Level 8
-    [0xe1, 0xce, 0xa9, 0x2e, 0xd9, 0x9a, 0xcd, 0xcb,
-     0x04, 0x5a, 0x67, 0x26, 0xb2, 0xf8, 0x71, 0x07,
-     0xe8, 0xa6, 0x16, 0x20, 0xa2, 0x32, 0xcf, 0x4d,
-     0x7d, 0x5b, 0x57, 0x66, 0xb3, 0x95, 0x2e, 0x10],
+    [0x2f, 0xa5, 0xe5, 0xf1, 0x8f, 0x60, 0x27, 0xa6,
+     0x50, 0x1b, 0xec, 0x86, 0x45, 0x64, 0x47, 0x2a,
+     0x61, 0x6b, 0x2e, 0x27, 0x4a, 0x41, 0x21, 0x1a,
+     0x44, 0x4c, 0xbe, 0x3a, 0x99, 0xf3, 0xcc, 0x61],
     // Level 9
-    [0x7a, 0xd6, 0x6c, 0x0a, 0x68, 0xc7, 0x2c, 0xb8,
-     0x9e, 0x4f, 0xb4, 0x30, 0x38, 0x41, 0x96, 0x6e,
-     0x40, 0x62, 0xa7, 0x6a, 0xb9, 0x74, 0x51, 0xe3,
-     0xb9, 0xfb, 0x52, 0x6a, 0x5c, 0xeb, 0x7f, 0x82],
+    [0x0e, 0x88, 0x43, 0x76, 0xd0, 0xd8, 0xfd, 0x21,
+     0xec, 0xb7, 0x80, 0x38, 0x9e, 0x94, 0x1f, 0x66,
+     0xe4, 0x5e, 0x7a, 0xcc, 0xe3, 0xe2, 0x28, 0xab,
+     0x3e, 0x21, 0x56, 0xa6, 0x14, 0xfc, 0xd7, 0x47],
     // Level 10
-    [0xe0, 0x26, 0xcc, 0x5a, 0x4a, 0xed, 0x3c, 0x22,
-     0xa5, 0x8c, 0xbd, 0x3d, 0x2a, 0xc7, 0x54, 0xc9,
-     0x35, 0x2c, 0x54, 0x36, 0xf6, 0x38, 0x04, 0x2d,
-     0xca, 0x99, 0x03, 0x4e, 0x83, 0x63, 0x65, 0x16],
+    [0x1b, 0x72, 0x01, 0xda, 0x72, 0x49, 0x4f, 0x1e,
+     0x28, 0x71, 0x7a, 0xd1, 0xa5, 0x2e, 0xb4, 0x69,
+     0xf9, 0x58, 0x92, 0xf9, 0x57, 0x71, 0x35, 0x33,
+     0xde, 0x61, 0x75, 0xe5, 0xda, 0x19, 0x0a, 0xf2],
 ];
 
 /// Get zero hash for a specific level (O(1) lookup, no stack allocation)
@@ -90,14 +95,12 @@ pub fn get_zero_hash(level: usize) -> [u8; 32] {
     ZERO_HASHES[level]
 }
 
-/// Hash two 32-byte values together using Keccak256
-/// Note: Using Keccak256 for Solana compatibility (cheaper than SHA256 on-chain)
-pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
-    let mut combined = [0u8; 64];
-    combined[..32].copy_from_slice(left);
-    combined[32..].copy_from_slice(right);
-
-    keccak::hash(&combined).to_bytes()
+/// Hash two child nodes into their parent with Poseidon
+///
+/// Fails if either child is not a canonical field element; commitments are
+/// checked on shield, so this only trips on a corrupted tree or a bad proof.
+pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> Result<[u8; 32]> {
+    poseidon::hash_pair(left, right).ok_or_else(|| error!(MerkleError::NonCanonicalNode))
 }
 
 /// Incremental Merkle Tree state
@@ -106,7 +109,8 @@ pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
 /// 1. Insert new leaves efficiently
 /// 2. Compute the current root
 /// 3. Generate membership proofs
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+#[zero_copy]
+#[derive(Debug)]
 pub struct IncrementalMerkleTree {
     /// Current number of leaves in the tree
     pub next_index: u64,
@@ -155,6 +159,29 @@ impl IncrementalMerkleTree {
     ///
     /// Returns the index of the inserted leaf
     pub fn insert(&mut self, leaf: [u8; 32]) -> Result<u64> {
+        self.insert_with(leaf, |_, _, _| {})
+    }
+
+    /// Insert a new leaf and update the canopy along its path
+    ///
+    /// Returns the index of the inserted leaf
+    pub fn insert_with_canopy(&mut self, leaf: [u8; 32], canopy: &mut MerkleCanopy) -> Result<u64> {
+        self.insert_with(leaf, |level, position, node| {
+            if (TREE_DEPTH - CANOPY_DEPTH..TREE_DEPTH).contains(&level) {
+                canopy.set(level, position, node);
+            }
+        })
+    }
+
+    /// Insert a leaf, reporting each recomputed node on its path
+    ///
+    /// `on_node` receives (level, position, node) for every level above
+    /// the leaves, up to and including the root.
+    fn insert_with(
+        &mut self,
+        leaf: [u8; 32],
+        mut on_node: impl FnMut(usize, u64, [u8; 32]),
+    ) -> Result<u64> {
         require!(
             self.next_index < Self::MAX_LEAVES,
             MerkleError::TreeFull
@@ -175,14 +202,15 @@ impl IncrementalMerkleTree {
                 // Store this node as the filled subtree at this level
                 self.filled_subtrees[level] = current_hash;
 
-                current_hash = hash_pair(&current_hash, &right);
+                current_hash = hash_pair(&current_hash, &right)?;
             } else {
                 // We're on the right side - use filled subtree for left sibling
                 let left = self.filled_subtrees[level];
-                current_hash = hash_pair(&left, &current_hash);
+                current_hash = hash_pair(&left, &current_hash)?;
             }
 
             current_index /= 2;
+            on_node(level + 1, current_index, current_hash);
         }
 
         // Update the root
@@ -204,6 +232,77 @@ impl IncrementalMerkleTree {
     }
 }
 
+/// Cached upper levels of the tree
+///
+/// Holds every node of the top `CANOPY_DEPTH` levels below the root,
+/// ordered level by level from the top: the two children of the root
+/// first, then their four children, and so on. Empty subtrees hold their
+/// zero hash, so the canopy always matches the current tree.
+#[zero_copy]
+#[derive(Debug)]
+pub struct MerkleCanopy {
+    /// Canopy nodes, top level first
+    pub nodes: [[u8; 32]; CANOPY_SIZE],
+}
+
+impl Default for MerkleCanopy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MerkleCanopy {
+    /// Size of the canopy in bytes
+    pub const SIZE: usize = 32 * CANOPY_SIZE;
+
+    /// Create the canopy of an empty tree
+    pub fn new() -> Self {
+        let mut nodes = [[0u8; 32]; CANOPY_SIZE];
+        for level in TREE_DEPTH - CANOPY_DEPTH..TREE_DEPTH {
+            let start = Self::level_offset(level);
+            let end = start + (1 << (TREE_DEPTH - level));
+            nodes[start..end].fill(get_zero_hash(level));
+        }
+        Self { nodes }
+    }
+
+    /// Offset of the first node of a canopy level
+    fn level_offset(level: usize) -> usize {
+        (1 << (TREE_DEPTH - level)) - 2
+    }
+
+    /// Get the node at a canopy level and position
+    pub fn get(&self, level: usize, position: u64) -> [u8; 32] {
+        self.nodes[Self::level_offset(level) + position as usize]
+    }
+
+    /// Set the node at a canopy level and position
+    pub fn set(&mut self, level: usize, position: u64, node: [u8; 32]) {
+        self.nodes[Self::level_offset(level) + position as usize] = node;
+    }
+
+    /// Extend the bottom siblings of a proof with the canopy's siblings
+    ///
+    /// Returns `None` unless exactly `TREE_DEPTH - CANOPY_DEPTH` siblings
+    /// are given.
+    pub fn complete_proof(
+        &self,
+        leaf_index: u64,
+        siblings: &[[u8; 32]],
+    ) -> Option<[[u8; 32]; TREE_DEPTH]> {
+        if siblings.len() != TREE_DEPTH - CANOPY_DEPTH || leaf_index >= 1 << TREE_DEPTH {
+            return None;
+        }
+
+        let mut proof = [[0u8; 32]; TREE_DEPTH];
+        proof[..siblings.len()].copy_from_slice(siblings);
+        for (level, node) in proof.iter_mut().enumerate().skip(siblings.len()) {
+            *node = self.get(level, (leaf_index >> level) ^ 1);
+        }
+        Some(proof)
+    }
+}
+
 /// Verify a Merkle proof
 ///
 /// # Arguments
@@ -227,18 +326,39 @@ pub fn verify_merkle_proof(
         let sibling = &siblings[level];
         let is_left = current_index % 2 == 0;
 
-        current_hash = if is_left {
+        let parent = if is_left {
             hash_pair(&current_hash, sibling)
         } else {
             hash_pair(sibling, &current_hash)
         };
 
+        current_hash = match parent {
+            Ok(hash) => hash,
+            Err(_) => return false,
+        };
+
         current_index /= 2;
     }
 
     current_hash == *root
 }
 
+/// Verify a Merkle proof whose upper siblings come from the canopy
+///
+/// `siblings` holds only the bottom `TREE_DEPTH - CANOPY_DEPTH` siblings.
+pub fn verify_merkle_proof_with_canopy(
+    leaf: &[u8; 32],
+    leaf_index: u64,
+    siblings: &[[u8; 32]],
+    canopy: &MerkleCanopy,
+    root: &[u8; 32],
+) -> bool {
+    match canopy.complete_proof(leaf_index, siblings) {
+        Some(proof) => verify_merkle_proof(leaf, leaf_index, &proof, root),
+        None => false,
+    }
+}
+
 /// Generate a Merkle proof for a leaf
 ///
 /// Note: This requires knowing all leaves, so it's typically done client-side.
@@ -277,7 +397,7 @@ pub fn generate_merkle_proof(
         for i in (0..level_nodes.len()).step_by(2) {
             let left = &level_nodes[i];
             let right = &level_nodes[i + 1];
-            next_level.push(hash_pair(left, right));
+            next_level.push(hash_pair(left, right).ok()?);
         }
 
         level_nodes = next_level;
@@ -296,6 +416,8 @@ pub enum MerkleError {
     InvalidProof,
     #[msg("Invalid leaf index")]
     InvalidLeafIndex,
+    #[msg("Merkle node is not a canonical field element")]
+    NonCanonicalNode,
 }
 
 #[cfg(test)]
@@ -303,27 +425,22 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_compute_zero_hashes_for_depth_10() {
-        // Compute zero hashes for TREE_DEPTH = 10
-        let mut zeros = [[0u8; 32]; 11];
-        zeros[0] = ZERO_VALUE;
+    fn test_zero_hashes_match_poseidon_chain() {
+        let mut zero = ZERO_VALUE;
+        assert_eq!(ZERO_HASHES[0], zero);
 
-        for i in 1..=10 {
-            zeros[i] = hash_pair(&zeros[i - 1], &zeros[i - 1]);
+        for expected in &ZERO_HASHES[1..] {
+            zero = hash_pair(&zero, &zero).unwrap();
+            assert_eq!(*expected, zero);
         }
+    }
 
-        println!("\n// Precomputed ZERO_HASHES for TREE_DEPTH = 10");
-        println!("pub const ZERO_HASHES: [[u8; 32]; 11] = [");
-        for (level, z) in zeros.iter().enumerate() {
-            print!("    // Level {}\n    [", level);
-            for (j, b) in z.iter().enumerate() {
-                if j > 0 { print!(", "); }
-                if j % 8 == 0 && j > 0 { print!("\n     "); }
-                print!("0x{:02x}", b);
-            }
-            println!("],");
-        }
-        println!("];");
+    #[test]
+    fn test_rejects_non_canonical_leaf() {
+        let mut tree = IncrementalMerkleTree::new();
+        assert!(tree.insert([0xffu8; 32]).is_err());
+        assert_eq!(tree.next_index, 0);
+        assert_eq!(tree.root(), get_zero_hash(TREE_DEPTH));
     }
 
     #[test]
@@ -375,6 +492,50 @@ mod tests {
         assert_eq!(tree1.root(), tree2.root());
     }
 
+    #[test]
+    fn test_empty_canopy_holds_zero_hashes() {
+        let canopy = MerkleCanopy::new();
+        assert_eq!(canopy.get(TREE_DEPTH - 1, 0), get_zero_hash(TREE_DEPTH - 1));
+        assert_eq!(canopy.get(TREE_DEPTH - 1, 1), get_zero_hash(TREE_DEPTH - 1));
+        let bottom = TREE_DEPTH - CANOPY_DEPTH;
+        assert_eq!(canopy.get(bottom, (1 << CANOPY_DEPTH) - 1), get_zero_hash(bottom));
+    }
+
+    #[test]
+    fn test_canopy_tracks_full_proofs() {
+        let leaves: Vec<[u8; 32]> = (0..70u8)
+            .map(|i| {
+                let mut leaf = [0u8; 32];
+                leaf[31] = i;
+                leaf
+            })
+            .collect();
+
+        let mut tree = IncrementalMerkleTree::new();
+        let mut canopy = MerkleCanopy::new();
+        for leaf in &leaves {
+            tree.insert_with_canopy(*leaf, &mut canopy).unwrap();
+        }
+
+        for leaf_index in [0usize, 63, 64, 69] {
+            let proof = generate_merkle_proof(&leaves, leaf_index).unwrap();
+            let bottom = &proof[..TREE_DEPTH - CANOPY_DEPTH];
+
+            assert_eq!(canopy.complete_proof(leaf_index as u64, bottom), Some(proof));
+            assert!(verify_merkle_proof_with_canopy(
+                &leaves[leaf_index],
+                leaf_index as u64,
+                bottom,
+                &canopy,
+                &tree.root()
+            ));
+        }
+
+        // Full-length proofs are rejected
+        let proof = generate_merkle_proof(&leaves, 0).unwrap();
+        assert!(!verify_merkle_proof_with_canopy(&leaves[0], 0, &proof, &canopy, &tree.root()));
+    }
+
     #[test]
     fn test_verify_proof() {
         let leaves: Vec<[u8; 32]> = (0..4)
@@ -0,0 +1,91 @@
+//! Cross-Pool Migrations
+//!
+//! `migrate` moves value between two SOL pools, such as from the 0.1 SOL
+//! pool to the 1 SOL pool, without it surfacing in a public wallet. Notes
+//! of the source pool are spent with unshield proofs, the source vault pays
+//! their value straight into the destination vault, and one note of the
+//! destination pool is shielded under the caller's new commitment.
+//!
+//! Every proof binds the migration authority as its recipient: a PDA of the
+//! destination pool, the new commitment and its amount. A relayer can
+//! neither swap the commitment nor change what it is worth, and the spent
+//! notes net of their fees must add up to exactly that amount.
+//!
+//! A migration spends at most `MAX_UNSHIELD_BATCH_SIZE` notes. Moving more
+//! notes into a fixed pool goes through a variable pool: migrate them in
+//! batches, merge the resulting notes with `join_split`, then migrate the
+//! merged note.
+
+use anchor_lang::prelude::*;
+
+use crate::instructions::BatchWithdrawal;
+
+/// Seeds prefix for the PDA that proofs of a migration pay out to
+pub const MIGRATION_AUTHORITY_SEED: &[u8] = b"migration_authority";
+
+/// Derive the migration authority for shielding `amount_out` into
+/// `destination_pool` under `commitment`
+pub fn derive_migration_authority(
+    program_id: &Pubkey,
+    destination_pool: &Pubkey,
+    commitment: &[u8; 32],
+    amount_out: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            MIGRATION_AUTHORITY_SEED,
+            destination_pool.as_ref(),
+            commitment.as_ref(),
+            &amount_out.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Value the spent notes carry into the destination pool, net of fees
+///
+/// `None` on overflow or if a fee exceeds its note.
+pub fn migrated_amount(withdrawals: &[BatchWithdrawal]) -> Option<u64> {
+    withdrawals.iter().try_fold(0u64, |sum, withdrawal| {
+        sum.checked_add(withdrawal.amount.checked_sub(withdrawal.fee)?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn withdrawal(amount: u64, fee: u64) -> BatchWithdrawal {
+        BatchWithdrawal {
+            nullifier: [0u8; 32],
+            root: [0u8; 32],
+            amount,
+            fee,
+            expiry_slot: 0,
+            proof: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_migration_authority_binds_commitment_and_amount() {
+        let pool = Pubkey::new_unique();
+        let (authority, _) = derive_migration_authority(&crate::ID, &pool, &[1u8; 32], 100);
+        assert_ne!(authority, derive_migration_authority(&crate::ID, &pool, &[2u8; 32], 100).0);
+        assert_ne!(authority, derive_migration_authority(&crate::ID, &pool, &[1u8; 32], 101).0);
+        assert_ne!(
+            authority,
+            derive_migration_authority(&crate::ID, &Pubkey::new_unique(), &[1u8; 32], 100).0
+        );
+    }
+
+    #[test]
+    fn test_migrated_amount() {
+        // Four 0.1 SOL notes, one paying the relayer, make 0.399 SOL
+        let mut notes = vec![withdrawal(100_000_000, 0); 4];
+        notes[0].fee = 1_000_000;
+        assert_eq!(migrated_amount(&notes), Some(399_000_000));
+
+        assert_eq!(migrated_amount(&[withdrawal(10, 11)]), None);
+        assert_eq!(migrated_amount(&[withdrawal(u64::MAX, 0), withdrawal(1, 0)]), None);
+    }
+}
@@ -1,26 +1,56 @@
 //! Nullifier Set Implementation
 //!
-//! Uses PDAs (Program Derived Addresses) to track spent nullifiers.
-//! Each spent nullifier creates a small PDA account that serves as a marker.
+//! Spent nullifiers are stored in `NullifierSet` shards: one PDA per pool
+//! and last nullifier byte, created on first use and grown as it fills.
 //!
 //! This approach:
-//! - Has NO false positives (unlike bloom filters)
-//! - Uses ~128 bytes per nullifier (account overhead + data)
-//! - Allows O(1) lookup via PDA derivation
-//! - Is standard practice for Solana privacy protocols
+//! - Has NO false positives (the shard bitmap only pre-filters exact lookups)
+//! - Finds a nullifier by binary search of its shard's sorted slots
+//! - Uses 32 bytes of rent per nullifier instead of a whole account
+//! - Allows lookup via PDA derivation of the shard
+//!
+//! Nullifiers spent before the sets existed have a per-nullifier
+//! `NullifierMarker` PDA instead, so spends also check that no marker exists.
 
 use anchor_lang::prelude::*;
+use anchor_lang::error::ErrorCode;
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+use solana_program::instruction::Instruction;
 use solana_program::keccak;
+use solana_program::program::{invoke, invoke_signed};
+use solana_program::system_instruction;
+
+use crate::events::NullifierSpent;
+use crate::instructions::NyxError;
+use crate::state::NullifierSet;
 
 /// Seeds prefix for nullifier PDAs
 pub const NULLIFIER_SEED: &[u8] = b"nullifier";
 
+/// Seeds prefix for nullifier set shard PDAs
+pub const NULLIFIER_SET_SEED: &[u8] = b"nullifier_set";
+
+/// Nullifier slots a shard is created with and grows by
+///
+/// Keeps each growth step well inside the 10 KiB realloc limit.
+pub const NULLIFIER_SET_GROWTH: u32 = 64;
+
+/// Most nullifier slots a shard grows to
+///
+/// Caps a shard at about 1 MiB, and each pool at 256 times that many
+/// nullifiers (8M); past it spends of nullifiers in the shard fail with
+/// `NullifierSetFull`.
+pub const NULLIFIER_SET_MAX_CAPACITY: u32 = 32_768;
+
 /// Size of a nullifier marker account
 /// Discriminator (8) + pool pubkey (32) + nullifier hash (32) + spent_at slot (8)
 pub const NULLIFIER_ACCOUNT_SIZE: usize = 8 + 32 + 32 + 8;
 
 /// Nullifier marker account
-/// Created when a nullifier is spent to prevent double-spending
+/// Created by spends before nullifier sets existed; no longer created.
+/// `close_nullifier_marker` reclaims its rent once the nullifier is
+/// mirrored into the pool's nullifier set.
 #[account]
 #[derive(Debug)]
 pub struct NullifierMarker {
@@ -62,6 +92,240 @@ pub fn derive_nullifier_pda(
     )
 }
 
+/// Derive the PDA address of the nullifier set shard holding a nullifier
+pub fn derive_nullifier_set_pda(
+    program_id: &Pubkey,
+    pool: &Pubkey,
+    nullifier: &[u8; 32],
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            NULLIFIER_SET_SEED,
+            pool.as_ref(),
+            &[NullifierSet::shard_of(nullifier)],
+        ],
+        program_id,
+    )
+}
+
+/// System instructions creating a `space`-byte account owned by
+/// `program_id` at `address`, which already holds `lamports`
+///
+/// Same sequence as Anchor's `init`: an account that was prefunded to
+/// block creation is topped up, allocated and assigned instead.
+fn pda_creation_instructions(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    address: &Pubkey,
+    lamports: u64,
+    rent: u64,
+    space: usize,
+) -> Vec<Instruction> {
+    if lamports == 0 {
+        return vec![system_instruction::create_account(
+            payer,
+            address,
+            rent,
+            space as u64,
+            program_id,
+        )];
+    }
+    let mut instructions = Vec::with_capacity(3);
+    if lamports < rent {
+        instructions.push(system_instruction::transfer(payer, address, rent - lamports));
+    }
+    instructions.push(system_instruction::allocate(address, space as u64));
+    instructions.push(system_instruction::assign(address, program_id));
+    instructions
+}
+
+/// Create a program-owned PDA outside of an Anchor `init` constraint
+pub(crate) fn create_pda_account<'info>(
+    program_id: &Pubkey,
+    account_info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    require!(
+        account_info.data_is_empty() && account_info.owner == &system_program::ID,
+        ErrorCode::ConstraintZero
+    );
+    let rent = Rent::get()?.minimum_balance(space);
+    let accounts = [payer.clone(), account_info.clone(), system_program.clone()];
+    for instruction in pda_creation_instructions(
+        program_id,
+        payer.key,
+        account_info.key,
+        account_info.lamports(),
+        rent,
+        space,
+    ) {
+        invoke_signed(&instruction, &accounts, signer_seeds)?;
+    }
+    Ok(())
+}
+
+/// Grow a program-owned account, topping its rent up from `payer`
+fn grow_account<'info>(
+    account_info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let lamports = account_info.lamports();
+    if lamports < rent {
+        invoke(
+            &system_instruction::transfer(payer.key, account_info.key, rent - lamports),
+            &[payer.clone(), account_info.clone(), system_program.clone()],
+        )?;
+    }
+    account_info.realloc(space, true)?;
+    Ok(())
+}
+
+/// Split nullifier set account data into the shard and its nullifier slots
+fn split_nullifier_set(data: &mut [u8]) -> Result<(&mut NullifierSet, &mut [[u8; 32]])> {
+    require!(
+        data.len() >= 8 + NullifierSet::SIZE && data[..8] == NullifierSet::DISCRIMINATOR,
+        NyxError::InvalidNullifierSet
+    );
+    let (header, slots) = data.split_at_mut(8 + NullifierSet::SIZE);
+    let set: &mut NullifierSet = bytemuck::from_bytes_mut(&mut header[8..]);
+    let slots = slots
+        .get_mut(..32 * set.capacity as usize)
+        .ok_or(NyxError::InvalidNullifierSet)?;
+    Ok((set, bytemuck::cast_slice_mut(slots)))
+}
+
+/// Mark a nullifier as spent in the pool's nullifier set
+///
+/// Fails with `NullifierSpent` if the shard already holds the nullifier or
+/// a legacy marker exists for it, which is what prevents the nullifier from
+/// being spent twice. Returns the `NullifierSpent` event to emit.
+#[allow(clippy::too_many_arguments)]
+pub fn spend_nullifier<'info>(
+    program_id: &Pubkey,
+    pool: &Pubkey,
+    nullifier: &[u8; 32],
+    legacy_marker: &AccountInfo<'info>,
+    nullifier_set: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<NullifierSpent> {
+    let (expected, _) = derive_nullifier_pda(program_id, pool, nullifier);
+    require_keys_eq!(expected, legacy_marker.key(), ErrorCode::ConstraintSeeds);
+    require!(
+        legacy_marker.owner != program_id,
+        NyxError::NullifierSpent
+    );
+
+    let inserted = insert_nullifier(
+        program_id,
+        pool,
+        nullifier,
+        nullifier_set,
+        payer,
+        system_program,
+    )?;
+    require!(inserted, NyxError::NullifierSpent);
+
+    Ok(NullifierSpent {
+        pool: *pool,
+        nullifier: *nullifier,
+        slot: Clock::get()?.slot,
+    })
+}
+
+/// Add a nullifier to the pool's nullifier set, returning whether it was new
+///
+/// Creates the nullifier's shard on first use and grows it when full, with
+/// `payer` covering the rent.
+pub fn insert_nullifier<'info>(
+    program_id: &Pubkey,
+    pool: &Pubkey,
+    nullifier: &[u8; 32],
+    nullifier_set: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<bool> {
+    let (expected, bump) = derive_nullifier_set_pda(program_id, pool, nullifier);
+    require_keys_eq!(expected, nullifier_set.key(), NyxError::InvalidNullifierSet);
+
+    let shard = NullifierSet::shard_of(nullifier);
+    if nullifier_set.owner != program_id {
+        let signer_seeds: &[&[&[u8]]] = &[&[NULLIFIER_SET_SEED, pool.as_ref(), &[shard], &[bump]]];
+        create_pda_account(
+            program_id,
+            nullifier_set,
+            payer,
+            system_program,
+            NullifierSet::space(NULLIFIER_SET_GROWTH),
+            signer_seeds,
+        )?;
+
+        let mut data = nullifier_set.try_borrow_mut_data()?;
+        data[..8].copy_from_slice(&NullifierSet::DISCRIMINATOR);
+        let (set, _) = split_nullifier_set(&mut data)?;
+        set.initialize(*pool, shard, bump, NULLIFIER_SET_GROWTH);
+    }
+
+    let grow_to = {
+        let mut data = nullifier_set.try_borrow_mut_data()?;
+        let (set, slots) = split_nullifier_set(&mut data)?;
+        require_keys_eq!(set.pool, *pool, NyxError::InvalidNullifierSet);
+        if set.contains(slots, nullifier) {
+            return Ok(false);
+        }
+        if set.is_full() {
+            require!(set.capacity < NULLIFIER_SET_MAX_CAPACITY, NyxError::NullifierSetFull);
+            Some((set.capacity + NULLIFIER_SET_GROWTH).min(NULLIFIER_SET_MAX_CAPACITY))
+        } else {
+            None
+        }
+    };
+
+    if let Some(capacity) = grow_to {
+        grow_account(
+            nullifier_set,
+            payer,
+            system_program,
+            NullifierSet::space(capacity),
+        )?;
+        let mut data = nullifier_set.try_borrow_mut_data()?;
+        let (set, _) = split_nullifier_set(&mut data)?;
+        set.capacity = capacity;
+    }
+
+    let mut data = nullifier_set.try_borrow_mut_data()?;
+    let (set, slots) = split_nullifier_set(&mut data)?;
+    set.insert(slots, *nullifier)?;
+    Ok(true)
+}
+
+/// Whether the pool's nullifier set holds a nullifier
+///
+/// A shard that was never created holds none.
+pub fn is_nullifier_in_set(
+    program_id: &Pubkey,
+    pool: &Pubkey,
+    nullifier: &[u8; 32],
+    nullifier_set: &AccountInfo,
+) -> Result<bool> {
+    let (expected, _) = derive_nullifier_set_pda(program_id, pool, nullifier);
+    require_keys_eq!(expected, nullifier_set.key(), NyxError::InvalidNullifierSet);
+    if nullifier_set.owner != program_id {
+        return Ok(false);
+    }
+
+    let mut data = nullifier_set.try_borrow_mut_data()?;
+    let (set, slots) = split_nullifier_set(&mut data)?;
+    require_keys_eq!(set.pool, *pool, NyxError::InvalidNullifierSet);
+    Ok(set.contains(slots, nullifier))
+}
+
 /// Check if a nullifier PDA account exists (meaning it's spent)
 ///
 /// This is a helper for off-chain checks.
@@ -90,6 +354,7 @@ pub fn hash_nullifier_for_pool(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::state::NullifierBloom;
     use solana_program::pubkey::Pubkey;
 
     #[test]
@@ -111,6 +376,114 @@ mod tests {
         assert_ne!(pda, pda3);
     }
 
+    #[test]
+    fn test_prefunded_accounts_are_still_created() {
+        let (program_id, payer, address) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+
+        // An untouched address is created in one go
+        let fresh = pda_creation_instructions(&program_id, &payer, &address, 0, 1_000, 64);
+        assert_eq!(
+            fresh,
+            [system_instruction::create_account(&payer, &address, 1_000, 64, &program_id)]
+        );
+
+        // Lamports sent ahead to make `create_account` fail are topped up
+        let prefunded = pda_creation_instructions(&program_id, &payer, &address, 1, 1_000, 64);
+        assert_eq!(
+            prefunded,
+            [
+                system_instruction::transfer(&payer, &address, 999),
+                system_instruction::allocate(&address, 64),
+                system_instruction::assign(&address, &program_id),
+            ]
+        );
+
+        // Already rent exempt: nothing to pay
+        let funded = pda_creation_instructions(&program_id, &payer, &address, 5_000, 1_000, 64);
+        assert_eq!(funded, prefunded[1..]);
+    }
+
+    #[test]
+    fn test_nullifier_set_membership() {
+        let mut set: NullifierSet = bytemuck::Zeroable::zeroed();
+        set.initialize(Pubkey::new_unique(), 7, 255, 3);
+        let mut slots = [[0u8; 32]; 3];
+
+        let first = [1u8; 32];
+        // Same filter bit as `first`, different nullifier
+        let mut collision = first;
+        collision[0] = 2;
+        assert_eq!(NullifierSet::filter_bit(&first), NullifierSet::filter_bit(&collision));
+
+        assert!(!set.contains(&slots, &first));
+        set.insert(&mut slots, first).unwrap();
+        assert!(set.contains(&slots, &first));
+        assert!(!set.contains(&slots, &collision));
+
+        set.insert(&mut slots, collision).unwrap();
+        set.insert(&mut slots, [3u8; 32]).unwrap();
+        assert_eq!(slots, [first, collision, [3u8; 32]], "slots stay sorted");
+        assert!(set.is_full());
+        assert!(set.insert(&mut slots, [4u8; 32]).is_err());
+    }
+
+    #[test]
+    fn test_unsorted_shard_is_sorted_on_insert() {
+        let mut set: NullifierSet = bytemuck::Zeroable::zeroed();
+        set.initialize(Pubkey::new_unique(), 7, 255, 4);
+        set.sorted = 0;
+        let mut slots = [[9u8; 32], [1u8; 32], [5u8; 32], [0u8; 32]];
+        for nullifier in &slots[..3] {
+            let bit = NullifierSet::filter_bit(nullifier);
+            set.bitmap[bit / 8] |= 1 << (bit % 8);
+        }
+        set.count = 3;
+        assert!(set.contains(&slots, &[1u8; 32]), "legacy shards are scanned");
+
+        set.insert(&mut slots, [3u8; 32]).unwrap();
+        assert_eq!(slots, [[1u8; 32], [3u8; 32], [5u8; 32], [9u8; 32]]);
+        assert!(set.contains(&slots, &[9u8; 32]));
+        assert!(!set.contains(&slots, &[4u8; 32]));
+    }
+
+    #[test]
+    fn test_nullifier_bloom() {
+        let mut bloom: NullifierBloom = bytemuck::Zeroable::zeroed();
+        bloom.initialize(Pubkey::new_unique(), 255);
+
+        let spent = [1u8; 32];
+        let mut unspent = spent;
+        unspent[25] = 2;
+
+        assert!(!bloom.may_contain(&spent));
+        bloom.insert(&spent);
+        assert!(bloom.may_contain(&spent));
+        assert!(!bloom.may_contain(&unspent));
+        assert_eq!(bloom.count, 1);
+        assert!(!bloom.is_saturated());
+    }
+
+    #[test]
+    fn test_nullifier_bloom_saturates() {
+        use crate::state::NULLIFIER_BLOOM_CAPACITY;
+
+        let mut bloom: NullifierBloom = bytemuck::Zeroable::zeroed();
+        bloom.initialize(Pubkey::new_unique(), 255);
+        let nullifier = |i: u64| solana_program::hash::hash(&i.to_le_bytes()).to_bytes();
+        for i in 0..NULLIFIER_BLOOM_CAPACITY {
+            bloom.insert(&nullifier(i));
+        }
+        assert!(bloom.is_saturated());
+
+        // Still no false negatives, but many unspent nullifiers look spent
+        assert!((0..100).all(|i| bloom.may_contain(&nullifier(i))));
+        let false_positives = (NULLIFIER_BLOOM_CAPACITY..NULLIFIER_BLOOM_CAPACITY + 1_000)
+            .filter(|&i| bloom.may_contain(&nullifier(i)))
+            .count();
+        assert!(false_positives > 100, "{false_positives} false positives");
+    }
+
     #[test]
     fn test_hash_nullifier_for_pool() {
         let pool1 = Pubkey::new_unique();
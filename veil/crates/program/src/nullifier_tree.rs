@@ -0,0 +1,473 @@
+//! Indexed Nullifier Tree
+//!
+//! An indexed Merkle tree of a pool's spent nullifiers. Every leaf holds a
+//! nullifier together with the next larger nullifier in the tree and that
+//! nullifier's leaf index, so the leaves form a sorted linked list. A value
+//! is absent iff some leaf `low` has `low.value < value < low.next_value`
+//! (or `low.next_value == 0` for the largest leaf), which lets circuits prove
+//! non-membership against the root instead of relying on PDA existence.
+//!
+//! Spends only push their nullifier onto the tree's queue; inserting needs
+//! the low leaf and its Merkle path, which the spender doesn't have, so
+//! `insert_nullifier_tree` moves queued nullifiers into the tree one at a
+//! time with a witness from an indexer. The queue is bounded; spends that
+//! find it full still succeed, and `backfill_nullifier_tree` inserts their
+//! nullifiers from the pool's nullifier set instead, so a stalled indexer
+//! can delay the tree but never block spends.
+//!
+//! Leaf hash = Poseidon(Poseidon(value, next_value), next_index). Leaf 0 is
+//! the all-zero leaf, the low leaf of the smallest nullifier.
+
+use anchor_lang::prelude::*;
+
+use crate::groth16::u64_to_field_bytes;
+use crate::instructions::NyxError;
+use crate::merkle::{hash_pair, ZERO_VALUE};
+
+/// Seeds prefix for nullifier tree PDAs
+pub const NULLIFIER_TREE_SEED: &[u8] = b"nullifier_tree";
+
+/// Nullifier tree depth (2^20 = 1,048,576 leaves)
+pub const NULLIFIER_TREE_DEPTH: usize = 20;
+
+/// Nullifiers that can wait in the queue for insertion
+pub const NULLIFIER_QUEUE_CAPACITY: usize = 256;
+
+/// A leaf of the indexed tree
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IndexedLeaf {
+    /// The nullifier
+    pub value: [u8; 32],
+    /// Next larger nullifier in the tree (zero for the largest)
+    pub next_value: [u8; 32],
+    /// Leaf index of `next_value`
+    pub next_index: u64,
+}
+
+impl IndexedLeaf {
+    /// Hash of the leaf as stored in the tree
+    pub fn hash(&self) -> Result<[u8; 32]> {
+        let values = hash_pair(&self.value, &self.next_value)?;
+        hash_pair(&values, &u64_to_field_bytes(self.next_index))
+    }
+
+    /// Whether `value` falls in the gap this leaf covers
+    pub fn is_low_leaf_of(&self, value: &[u8; 32]) -> bool {
+        self.value < *value && (self.next_value == ZERO_VALUE || *value < self.next_value)
+    }
+}
+
+/// Indexed Merkle tree of a pool's spent nullifiers, with its insertion queue
+///
+/// `frontier` holds the most recent left node at each level, as in
+/// `merkle::IncrementalMerkleTree`, so appending needs no witness; only
+/// the low leaf update is proven by the caller.
+#[account(zero_copy)]
+pub struct NullifierTree {
+    /// Pool this tree belongs to
+    pub pool: Pubkey,
+
+    /// Current root
+    pub root: [u8; 32],
+
+    /// Most recent left node at each level
+    pub frontier: [[u8; 32]; NULLIFIER_TREE_DEPTH],
+
+    /// Root of an empty subtree at each level
+    pub zero_hashes: [[u8; 32]; NULLIFIER_TREE_DEPTH],
+
+    /// Number of leaves in the tree
+    pub next_index: u64,
+
+    /// Ring buffer of nullifiers waiting for insertion
+    pub queue: [[u8; 32]; NULLIFIER_QUEUE_CAPACITY],
+
+    /// Position of the oldest queued nullifier
+    pub queue_head: u32,
+
+    /// Number of queued nullifiers
+    pub queue_len: u32,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+
+    /// Keeps the struct size a multiple of its alignment
+    pub _padding: [u8; 7],
+}
+
+impl NullifierTree {
+    pub const SIZE: usize = 32  // pool
+        + 32  // root
+        + 32 * NULLIFIER_TREE_DEPTH  // frontier
+        + 32 * NULLIFIER_TREE_DEPTH  // zero_hashes
+        + 8   // next_index
+        + 32 * NULLIFIER_QUEUE_CAPACITY  // queue
+        + 4   // queue_head
+        + 4   // queue_len
+        + 1   // bump
+        + 7;  // _padding
+
+    /// Maximum number of leaves
+    pub const MAX_LEAVES: u64 = 1 << NULLIFIER_TREE_DEPTH;
+
+    /// Initialize the tree with its zero leaf
+    pub fn initialize(&mut self, pool: Pubkey, bump: u8) -> Result<()> {
+        self.pool = pool;
+        self.bump = bump;
+        self.next_index = 0;
+        self.queue_head = 0;
+        self.queue_len = 0;
+
+        let mut zero = ZERO_VALUE;
+        for level in 0..NULLIFIER_TREE_DEPTH {
+            self.zero_hashes[level] = zero;
+            self.frontier[level] = zero;
+            zero = hash_pair(&zero, &zero)?;
+        }
+        self.root = zero;
+
+        self.append(IndexedLeaf::default().hash()?)?;
+        Ok(())
+    }
+
+    /// Queue a spent nullifier for insertion, returning whether it was
+    /// queued
+    ///
+    /// Zero is the value of the tree's first leaf and can never be
+    /// inserted, so it is rejected here rather than blocking the queue. A
+    /// full queue leaves the nullifier to `backfill`.
+    pub fn enqueue(&mut self, nullifier: [u8; 32]) -> Result<bool> {
+        require!(nullifier != ZERO_VALUE, NyxError::ZeroNullifier);
+        if self.queue_len as usize == NULLIFIER_QUEUE_CAPACITY {
+            return Ok(false);
+        }
+        let tail = (self.queue_head + self.queue_len) as usize % NULLIFIER_QUEUE_CAPACITY;
+        self.queue[tail] = nullifier;
+        self.queue_len += 1;
+        Ok(true)
+    }
+
+    /// Whether `nullifier` is waiting in the queue
+    pub fn is_queued(&self, nullifier: &[u8; 32]) -> bool {
+        (0..self.queue_len)
+            .map(|i| (self.queue_head + i) as usize % NULLIFIER_QUEUE_CAPACITY)
+            .any(|position| self.queue[position] == *nullifier)
+    }
+
+    /// Oldest queued nullifier
+    pub fn peek(&self) -> Option<[u8; 32]> {
+        (self.queue_len > 0).then(|| self.queue[self.queue_head as usize])
+    }
+
+    /// Insert the oldest queued nullifier
+    ///
+    /// `low_leaf` is the leaf at `low_index` whose gap contains the
+    /// nullifier, and `low_path` its siblings from the leaf level up.
+    /// Returns the new leaf's index.
+    pub fn insert_next(
+        &mut self,
+        low_leaf: &IndexedLeaf,
+        low_index: u64,
+        low_path: &[[u8; 32]],
+    ) -> Result<u64> {
+        let value = self.peek().ok_or(NyxError::NullifierQueueEmpty)?;
+        let new_index = self.insert(value, low_leaf, low_index, low_path)?;
+        self.queue_head = (self.queue_head + 1) % NULLIFIER_QUEUE_CAPACITY as u32;
+        self.queue_len -= 1;
+        Ok(new_index)
+    }
+
+    /// Insert a spent nullifier that never made it into the queue
+    ///
+    /// The caller checks the pool's nullifier set holds it. Queued
+    /// nullifiers are refused, since inserting one here would leave the
+    /// queue stuck on a value already in the tree; inserted ones have no
+    /// low leaf, so they can't be inserted twice.
+    pub fn backfill(
+        &mut self,
+        value: [u8; 32],
+        low_leaf: &IndexedLeaf,
+        low_index: u64,
+        low_path: &[[u8; 32]],
+    ) -> Result<u64> {
+        require!(!self.is_queued(&value), NyxError::NullifierQueued);
+        self.insert(value, low_leaf, low_index, low_path)
+    }
+
+    /// Insert `value` after `low_leaf`, returning the new leaf's index
+    fn insert(
+        &mut self,
+        value: [u8; 32],
+        low_leaf: &IndexedLeaf,
+        low_index: u64,
+        low_path: &[[u8; 32]],
+    ) -> Result<u64> {
+        require!(
+            self.next_index < Self::MAX_LEAVES,
+            NyxError::NullifierTreeFull
+        );
+        require!(
+            low_index < self.next_index
+                && low_path.len() == NULLIFIER_TREE_DEPTH
+                && low_leaf.is_low_leaf_of(&value),
+            NyxError::InvalidLowLeaf
+        );
+
+        let new_index = self.next_index;
+        let new_leaf = IndexedLeaf {
+            value,
+            next_value: low_leaf.next_value,
+            next_index: low_leaf.next_index,
+        };
+        let updated_low = IndexedLeaf {
+            next_value: value,
+            next_index: new_index,
+            ..*low_leaf
+        };
+
+        self.update_leaf(low_index, &low_leaf.hash()?, &updated_low.hash()?, low_path)?;
+        self.append(new_leaf.hash()?)?;
+        Ok(new_index)
+    }
+
+    /// Replace an existing leaf, proving its old value against the root
+    ///
+    /// Frontier nodes on the leaf's path are refreshed so the next append
+    /// hashes against the updated subtree.
+    fn update_leaf(
+        &mut self,
+        index: u64,
+        old_leaf: &[u8; 32],
+        new_leaf: &[u8; 32],
+        path: &[[u8; 32]],
+    ) -> Result<()> {
+        let last_index = self.next_index - 1;
+        let mut frontier = self.frontier;
+        let mut old_node = *old_leaf;
+        let mut new_node = *new_leaf;
+
+        for (level, sibling) in path.iter().enumerate() {
+            let position = index >> level;
+            if position == (last_index >> level) & !1 {
+                frontier[level] = new_node;
+            }
+            if position & 1 == 0 {
+                old_node = hash_pair(&old_node, sibling)?;
+                new_node = hash_pair(&new_node, sibling)?;
+            } else {
+                old_node = hash_pair(sibling, &old_node)?;
+                new_node = hash_pair(sibling, &new_node)?;
+            }
+        }
+
+        require!(old_node == self.root, NyxError::InvalidLowLeaf);
+        self.frontier = frontier;
+        self.root = new_node;
+        Ok(())
+    }
+
+    /// Append a leaf after the last one
+    fn append(&mut self, leaf: [u8; 32]) -> Result<u64> {
+        let index = self.next_index;
+        let mut node = leaf;
+
+        for level in 0..NULLIFIER_TREE_DEPTH {
+            if (index >> level) & 1 == 0 {
+                self.frontier[level] = node;
+                node = hash_pair(&node, &self.zero_hashes[level])?;
+            } else {
+                node = hash_pair(&self.frontier[level], &node)?;
+            }
+        }
+
+        self.root = node;
+        self.next_index += 1;
+        Ok(index)
+    }
+}
+
+const _: () = assert!(NullifierTree::SIZE == std::mem::size_of::<NullifierTree>());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Off-chain mirror of the tree, as an indexer would keep it
+    struct Mirror {
+        leaves: Vec<IndexedLeaf>,
+        zero_hashes: Vec<[u8; 32]>,
+    }
+
+    impl Mirror {
+        fn new(tree: &NullifierTree) -> Self {
+            Self {
+                leaves: vec![IndexedLeaf::default()],
+                zero_hashes: tree.zero_hashes.to_vec(),
+            }
+        }
+
+        fn node(&self, level: usize, position: u64) -> [u8; 32] {
+            let first = (position << level) as usize;
+            if first >= self.leaves.len() {
+                return self.zero_hashes[level];
+            }
+            if level == 0 {
+                return self.leaves[first].hash().unwrap();
+            }
+            let left = self.node(level - 1, position * 2);
+            let right = self.node(level - 1, position * 2 + 1);
+            hash_pair(&left, &right).unwrap()
+        }
+
+        fn path(&self, index: u64) -> Vec<[u8; 32]> {
+            (0..NULLIFIER_TREE_DEPTH)
+                .map(|level| self.node(level, (index >> level) ^ 1))
+                .collect()
+        }
+
+        fn root(&self) -> [u8; 32] {
+            let left = self.node(NULLIFIER_TREE_DEPTH - 1, 0);
+            let right = self.node(NULLIFIER_TREE_DEPTH - 1, 1);
+            hash_pair(&left, &right).unwrap()
+        }
+
+        fn low_leaf(&self, value: &[u8; 32]) -> (u64, IndexedLeaf) {
+            let index = self
+                .leaves
+                .iter()
+                .position(|leaf| leaf.is_low_leaf_of(value))
+                .unwrap();
+            (index as u64, self.leaves[index])
+        }
+
+        fn insert(&mut self, value: [u8; 32]) {
+            let (low_index, low) = self.low_leaf(&value);
+            let new_index = self.leaves.len() as u64;
+            self.leaves.push(IndexedLeaf {
+                value,
+                next_value: low.next_value,
+                next_index: low.next_index,
+            });
+            self.leaves[low_index as usize].next_value = value;
+            self.leaves[low_index as usize].next_index = new_index;
+        }
+    }
+
+    fn nullifier(byte: u8) -> [u8; 32] {
+        let mut value = [0u8; 32];
+        value[31] = byte;
+        value
+    }
+
+    fn new_tree() -> Box<NullifierTree> {
+        let mut tree: Box<NullifierTree> = Box::new(bytemuck::Zeroable::zeroed());
+        tree.initialize(Pubkey::new_unique(), 255).unwrap();
+        tree
+    }
+
+    #[test]
+    fn test_insertions_match_mirror() {
+        let mut tree = new_tree();
+        let mut mirror = Mirror::new(&tree);
+        assert_eq!(tree.root, mirror.root());
+
+        // Out of order, so low leaves come from all over the tree
+        for byte in [50, 20, 80, 30, 10, 90, 60] {
+            let value = nullifier(byte);
+            tree.enqueue(value).unwrap();
+
+            let (low_index, low) = mirror.low_leaf(&value);
+            let index = tree.insert_next(&low, low_index, &mirror.path(low_index)).unwrap();
+            mirror.insert(value);
+
+            assert_eq!(index, mirror.leaves.len() as u64 - 1);
+            assert_eq!(tree.root, mirror.root());
+        }
+        assert_eq!(tree.queue_len, 0);
+    }
+
+    #[test]
+    fn test_rejects_wrong_low_leaf() {
+        let mut tree = new_tree();
+        let mut mirror = Mirror::new(&tree);
+        for byte in [10, 30] {
+            tree.enqueue(nullifier(byte)).unwrap();
+            let (low_index, low) = mirror.low_leaf(&nullifier(byte));
+            tree.insert_next(&low, low_index, &mirror.path(low_index)).unwrap();
+            mirror.insert(nullifier(byte));
+        }
+
+        // 20 belongs after 10, not after 30
+        tree.enqueue(nullifier(20)).unwrap();
+        let wrong = mirror.leaves[2];
+        assert!(tree.insert_next(&wrong, 2, &mirror.path(2)).is_err());
+
+        // A leaf that brackets the value but isn't in the tree
+        let forged = IndexedLeaf { value: nullifier(15), next_value: nullifier(25), next_index: 1 };
+        assert!(tree.insert_next(&forged, 1, &mirror.path(1)).is_err());
+
+        // Failed insertions leave the tree untouched
+        let (low_index, low) = mirror.low_leaf(&nullifier(20));
+        tree.insert_next(&low, low_index, &mirror.path(low_index)).unwrap();
+        mirror.insert(nullifier(20));
+        assert_eq!(tree.root, mirror.root());
+
+        // Inserted values have no low leaf, so they can't be inserted again
+        assert!(mirror.leaves.iter().all(|leaf| !leaf.is_low_leaf_of(&nullifier(20))));
+    }
+
+    #[test]
+    fn test_queue_bounds() {
+        let mut tree = new_tree();
+        assert!(tree.peek().is_none());
+        assert!(tree.enqueue(ZERO_VALUE).is_err());
+        let queued = |i: usize| {
+            let mut value = [0u8; 32];
+            value[30..].copy_from_slice(&(i as u16 + 1).to_be_bytes());
+            value
+        };
+        for i in 0..NULLIFIER_QUEUE_CAPACITY {
+            assert!(tree.enqueue(queued(i)).unwrap());
+        }
+        assert!(tree.is_queued(&nullifier(1)));
+
+        // A full queue doesn't fail the spend, it leaves the nullifier out
+        let mut missed = [0u8; 32];
+        missed[0] = 1;
+        assert!(!tree.enqueue(missed).unwrap());
+        assert!(!tree.is_queued(&missed));
+        assert_eq!(tree.queue_len as usize, NULLIFIER_QUEUE_CAPACITY);
+        assert_eq!(tree.peek(), Some(nullifier(1)));
+    }
+
+    #[test]
+    fn test_backfills_missed_nullifiers() {
+        let mut tree = new_tree();
+        let mut mirror = Mirror::new(&tree);
+        tree.enqueue(nullifier(20)).unwrap();
+
+        // Queued nullifiers go through the queue, in order
+        let (low_index, low) = mirror.low_leaf(&nullifier(20));
+        let path = mirror.path(low_index);
+        assert_eq!(
+            tree.backfill(nullifier(20), &low, low_index, &path).unwrap_err(),
+            NyxError::NullifierQueued.into()
+        );
+
+        let (low_index, low) = mirror.low_leaf(&nullifier(40));
+        let index = tree.backfill(nullifier(40), &low, low_index, &mirror.path(low_index)).unwrap();
+        mirror.insert(nullifier(40));
+        assert_eq!(index, 1);
+        assert_eq!(tree.root, mirror.root());
+        assert_eq!(tree.queue_len, 1);
+
+        // The queue still drains around the backfilled leaf
+        let (low_index, low) = mirror.low_leaf(&nullifier(20));
+        tree.insert_next(&low, low_index, &mirror.path(low_index)).unwrap();
+        mirror.insert(nullifier(20));
+        assert_eq!(tree.root, mirror.root());
+
+        // A backfilled nullifier can't be inserted twice
+        assert!(mirror.leaves.iter().all(|leaf| !leaf.is_low_leaf_of(&nullifier(40))));
+    }
+}
@@ -0,0 +1,729 @@
+//! PLONK zkSNARK Verification for Solana
+//!
+//! A second verifier backend next to `groth16`, for pools whose circuits are
+//! proven with PLONK. PLONK uses a universal setup, so new circuits don't need
+//! their own trusted setup ceremony.
+//!
+//! The verifier follows the snarkjs PLONK protocol (Keccak256 transcript,
+//! KZG commitments over BN254) and runs on the same alt_bn128 syscalls.
+//!
+//! Only this vanilla PLONK is supported. UltraPlonk is not: Barretenberg's
+//! custom gates, lookup tables and transcript need a verifier of their own,
+//! and there is no `ProofSystem` for them. Noir circuits have to be proven
+//! through a snarkjs-compatible PLONK backend instead.
+//!
+//! Proof Format (768 bytes, big-endian):
+//! - commitments: A, B, C, Z, T1, T2, T3, Wxi, Wxiw (9 G1 points, 64 bytes each)
+//! - evaluations: a, b, c, s1, s2, zw (6 field elements, 32 bytes each)
+
+use anchor_lang::prelude::*;
+use solana_program::alt_bn128::prelude::*;
+use solana_program::keccak;
+
+use crate::groth16::{g1_add, g1_mul, negate_g1, MAX_PUBLIC_INPUTS};
+
+/// PLONK proof size in bytes
+pub const PLONK_PROOF_SIZE: usize = 9 * 64 + 6 * 32;
+
+/// Seeds prefix for PLONK verifying key PDAs
+pub const PLONK_VK_SEED: &[u8] = b"plonk_verifying_key";
+
+/// Largest supported evaluation domain (2^MAX_POWER constraints)
+pub const MAX_POWER: u8 = 28;
+
+/// BN254 G1 generator (1, 2)
+const G1_GENERATOR: [u8; 64] = {
+    let mut g = [0u8; 64];
+    g[31] = 1;
+    g[63] = 2;
+    g
+};
+
+/// BN254 G2 generator, EIP-197 layout (x_c1, x_c0, y_c1, y_c0)
+const G2_GENERATOR: [u8; 128] = [
+    25, 142, 147, 147, 146, 13, 72, 58, 114, 96, 191, 183, 49, 251, 93, 37,
+    241, 170, 73, 51, 53, 169, 231, 18, 151, 228, 133, 183, 174, 243, 18, 194,
+    24, 0, 222, 239, 18, 31, 30, 118, 66, 106, 0, 102, 94, 92, 68, 121,
+    103, 67, 34, 212, 247, 94, 218, 221, 70, 222, 189, 92, 217, 146, 246, 237,
+    9, 6, 137, 208, 88, 95, 240, 117, 236, 158, 153, 173, 105, 12, 51, 149,
+    188, 75, 49, 51, 112, 179, 142, 243, 85, 172, 218, 220, 209, 34, 151, 91,
+    18, 200, 94, 165, 219, 140, 109, 235, 74, 171, 113, 128, 141, 203, 64, 143,
+    227, 209, 231, 105, 12, 67, 211, 123, 76, 230, 204, 1, 102, 250, 125, 170,
+];
+
+/// Verifying key data, as passed to `propose_plonk_verifying_key`
+///
+/// Field elements and points are big-endian, G2 in EIP-197 layout.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PlonkVerifyingKeyData {
+    /// log2 of the evaluation domain size
+    pub power: u8,
+    /// Number of public inputs
+    pub n_public: u8,
+    /// Coset shift for the second wire
+    pub k1: [u8; 32],
+    /// Coset shift for the third wire
+    pub k2: [u8; 32],
+    /// Root of unity generating the domain
+    pub omega: [u8; 32],
+    /// Selector commitments
+    pub qm: [u8; 64],
+    pub ql: [u8; 64],
+    pub qr: [u8; 64],
+    pub qo: [u8; 64],
+    pub qc: [u8; 64],
+    /// Permutation commitments
+    pub s1: [u8; 64],
+    pub s2: [u8; 64],
+    pub s3: [u8; 64],
+    /// tau * G2 from the universal setup
+    pub x2: [u8; 128],
+}
+
+/// On-chain PLONK verifying key for one pool and circuit
+#[account]
+pub struct PlonkVerifyingKeyAccount {
+    /// The pool this key belongs to
+    pub pool: Pubkey,
+    /// Circuit this key verifies (`verification::CircuitId`)
+    pub circuit_id: u8,
+    /// Pool verifying key version this key was proposed for
+    pub version: u32,
+    /// Key material
+    pub key: PlonkVerifyingKeyData,
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl PlonkVerifyingKeyAccount {
+    pub const SIZE: usize = 32  // pool
+        + 1   // circuit_id
+        + 4   // version
+        + 1 + 1  // power, n_public
+        + 32 * 3  // k1, k2, omega
+        + 64 * 8  // selector and permutation commitments
+        + 128  // x2
+        + 1;  // bump
+}
+
+impl PlonkVerifyingKeyData {
+    /// Check the key is usable: domain in range, field elements canonical
+    pub fn is_valid(&self) -> bool {
+        self.power > 0
+            && self.power <= MAX_POWER
+            && self.n_public as usize <= MAX_PUBLIC_INPUTS
+            && Fr::from_be_bytes(&self.k1).is_some()
+            && Fr::from_be_bytes(&self.k2).is_some()
+            && Fr::from_be_bytes(&self.omega).is_some()
+            && self.x2.iter().any(|&b| b != 0)
+    }
+}
+
+/// PLONK proof structure
+#[derive(Clone, Debug)]
+pub struct PlonkProof {
+    pub a: [u8; 64],
+    pub b: [u8; 64],
+    pub c: [u8; 64],
+    pub z: [u8; 64],
+    pub t1: [u8; 64],
+    pub t2: [u8; 64],
+    pub t3: [u8; 64],
+    pub wxi: [u8; 64],
+    pub wxiw: [u8; 64],
+    pub eval_a: [u8; 32],
+    pub eval_b: [u8; 32],
+    pub eval_c: [u8; 32],
+    pub eval_s1: [u8; 32],
+    pub eval_s2: [u8; 32],
+    pub eval_zw: [u8; 32],
+}
+
+impl PlonkProof {
+    /// Parse proof from raw bytes
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != PLONK_PROOF_SIZE {
+            return None;
+        }
+
+        let point = |i: usize| -> [u8; 64] {
+            let mut p = [0u8; 64];
+            p.copy_from_slice(&bytes[i * 64..(i + 1) * 64]);
+            p
+        };
+        let scalar = |i: usize| -> [u8; 32] {
+            let start = 9 * 64 + i * 32;
+            let mut s = [0u8; 32];
+            s.copy_from_slice(&bytes[start..start + 32]);
+            s
+        };
+
+        Some(Self {
+            a: point(0),
+            b: point(1),
+            c: point(2),
+            z: point(3),
+            t1: point(4),
+            t2: point(5),
+            t3: point(6),
+            wxi: point(7),
+            wxiw: point(8),
+            eval_a: scalar(0),
+            eval_b: scalar(1),
+            eval_c: scalar(2),
+            eval_s1: scalar(3),
+            eval_s2: scalar(4),
+            eval_zw: scalar(5),
+        })
+    }
+}
+
+/// Verify a PLONK proof against a pool's verifying key
+///
+/// Follows the snarkjs verifier: derive the Fiat-Shamir challenges, compute
+/// the linearisation commitment F and evaluation E, then check
+/// e(-(Wxi + u*Wxiw), X2) * e(xi*Wxi + u*xi*omega*Wxiw + F - E, G2) = 1
+pub fn verify_plonk(
+    proof_bytes: &[u8],
+    public_inputs: &[[u8; 32]],
+    vk: &PlonkVerifyingKeyData,
+) -> Result<bool> {
+    require!(proof_bytes.len() == PLONK_PROOF_SIZE, PlonkError::InvalidProofSize);
+    require!(vk.is_valid(), PlonkError::VkNotInitialized);
+    require!(
+        public_inputs.len() == vk.n_public as usize,
+        PlonkError::InvalidPublicInputs
+    );
+
+    let proof = PlonkProof::from_bytes(proof_bytes).ok_or(PlonkError::InvalidProofSize)?;
+
+    // Evaluations and public inputs must be canonical field elements
+    let scalar = |bytes: &[u8; 32]| Fr::from_be_bytes(bytes).ok_or(PlonkError::NonCanonicalScalar);
+    let eval_a = scalar(&proof.eval_a)?;
+    let eval_b = scalar(&proof.eval_b)?;
+    let eval_c = scalar(&proof.eval_c)?;
+    let eval_s1 = scalar(&proof.eval_s1)?;
+    let eval_s2 = scalar(&proof.eval_s2)?;
+    let eval_zw = scalar(&proof.eval_zw)?;
+    let public_inputs = public_inputs
+        .iter()
+        .map(scalar)
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let k1 = scalar(&vk.k1)?;
+    let k2 = scalar(&vk.k2)?;
+    let omega = scalar(&vk.omega)?;
+
+    // ===== Fiat-Shamir challenges =====
+    let mut transcript = Transcript::default();
+    for commitment in [&vk.qm, &vk.ql, &vk.qr, &vk.qo, &vk.qc, &vk.s1, &vk.s2, &vk.s3] {
+        transcript.add_point(commitment);
+    }
+    for input in &public_inputs {
+        transcript.add_scalar(input);
+    }
+    transcript.add_point(&proof.a);
+    transcript.add_point(&proof.b);
+    transcript.add_point(&proof.c);
+    let beta = transcript.challenge();
+
+    transcript.add_scalar(&beta);
+    let gamma = transcript.challenge();
+
+    transcript.add_scalar(&beta);
+    transcript.add_scalar(&gamma);
+    transcript.add_point(&proof.z);
+    let alpha = transcript.challenge();
+
+    transcript.add_scalar(&alpha);
+    transcript.add_point(&proof.t1);
+    transcript.add_point(&proof.t2);
+    transcript.add_point(&proof.t3);
+    let xi = transcript.challenge();
+
+    transcript.add_scalar(&xi);
+    for eval in [&eval_a, &eval_b, &eval_c, &eval_s1, &eval_s2, &eval_zw] {
+        transcript.add_scalar(eval);
+    }
+    let v1 = transcript.challenge();
+    let v2 = v1.mul(&v1);
+    let v3 = v2.mul(&v1);
+    let v4 = v3.mul(&v1);
+    let v5 = v4.mul(&v1);
+
+    transcript.add_point(&proof.wxi);
+    transcript.add_point(&proof.wxiw);
+    let u = transcript.challenge();
+
+    // ===== Vanishing polynomial and Lagrange evaluations =====
+    let mut xin = xi;
+    for _ in 0..vk.power {
+        xin = xin.square();
+    }
+    let zh = xin.sub(&Fr::one());
+    let n = Fr::from_u64(1u64 << vk.power);
+
+    // L_i(xi) = w^(i-1) * zh / (n * (xi - w^(i-1))), one inversion for all
+    let num_lagrange = public_inputs.len().max(1);
+    let mut roots = Vec::with_capacity(num_lagrange);
+    let mut denominators = Vec::with_capacity(num_lagrange);
+    let mut w = Fr::one();
+    for _ in 0..num_lagrange {
+        roots.push(w);
+        denominators.push(n.mul(&xi.sub(&w)));
+        w = w.mul(&omega);
+    }
+    let inverses = Fr::batch_inverse(&denominators).ok_or(PlonkError::VerificationFailed)?;
+    let lagrange: Vec<Fr> = roots
+        .iter()
+        .zip(&inverses)
+        .map(|(w, inv)| w.mul(&zh).mul(inv))
+        .collect();
+
+    // PI(xi) = -sum(public_input[i] * L_{i+1}(xi))
+    let mut pi = Fr::zero();
+    for (input, l) in public_inputs.iter().zip(&lagrange) {
+        pi = pi.sub(&input.mul(l));
+    }
+
+    let l1 = lagrange[0];
+    let alpha_sq = alpha.square();
+
+    // ===== r0 =====
+    let e3a = eval_a.add(&beta.mul(&eval_s1)).add(&gamma);
+    let e3b = eval_b.add(&beta.mul(&eval_s2)).add(&gamma);
+    let e3c = eval_c.add(&gamma);
+    let e3 = e3a.mul(&e3b).mul(&e3c).mul(&eval_zw).mul(&alpha);
+    let r0 = pi.sub(&l1.mul(&alpha_sq)).sub(&e3);
+
+    // ===== D: linearisation commitment =====
+    let mut d1 = g1_mul(&vk.qm, &eval_a.mul(&eval_b).to_be_bytes())?;
+    d1 = g1_add(&d1, &g1_mul(&vk.ql, &eval_a.to_be_bytes())?)?;
+    d1 = g1_add(&d1, &g1_mul(&vk.qr, &eval_b.to_be_bytes())?)?;
+    d1 = g1_add(&d1, &g1_mul(&vk.qo, &eval_c.to_be_bytes())?)?;
+    d1 = g1_add(&d1, &vk.qc)?;
+
+    let betaxi = beta.mul(&xi);
+    let d2a = eval_a
+        .add(&betaxi)
+        .add(&gamma)
+        .mul(&eval_b.add(&betaxi.mul(&k1)).add(&gamma))
+        .mul(&eval_c.add(&betaxi.mul(&k2)).add(&gamma))
+        .mul(&alpha);
+    let d2b = l1.mul(&alpha_sq);
+    let d2 = g1_mul(&proof.z, &d2a.add(&d2b).add(&u).to_be_bytes())?;
+
+    let d3 = g1_mul(
+        &vk.s3,
+        &e3a.mul(&e3b).mul(&alpha).mul(&beta).mul(&eval_zw).to_be_bytes(),
+    )?;
+
+    let mut d4 = g1_add(&proof.t1, &g1_mul(&proof.t2, &xin.to_be_bytes())?)?;
+    d4 = g1_add(&d4, &g1_mul(&proof.t3, &xin.square().to_be_bytes())?)?;
+    d4 = g1_mul(&d4, &zh.to_be_bytes())?;
+
+    let mut d = g1_add(&d1, &d2)?;
+    d = g1_add(&d, &negate_g1(&d3))?;
+    d = g1_add(&d, &negate_g1(&d4))?;
+
+    // ===== F = D + v1*A + v2*B + v3*C + v4*S1 + v5*S2 =====
+    let mut f = d;
+    for (point, v) in [
+        (&proof.a, &v1),
+        (&proof.b, &v2),
+        (&proof.c, &v3),
+        (&vk.s1, &v4),
+        (&vk.s2, &v5),
+    ] {
+        f = g1_add(&f, &g1_mul(point, &v.to_be_bytes())?)?;
+    }
+
+    // ===== E = (-r0 + v1*a + v2*b + v3*c + v4*s1 + v5*s2 + u*zw) * G1 =====
+    let e = Fr::zero()
+        .sub(&r0)
+        .add(&v1.mul(&eval_a))
+        .add(&v2.mul(&eval_b))
+        .add(&v3.mul(&eval_c))
+        .add(&v4.mul(&eval_s1))
+        .add(&v5.mul(&eval_s2))
+        .add(&u.mul(&eval_zw));
+    let e_point = g1_mul(&G1_GENERATOR, &e.to_be_bytes())?;
+
+    // ===== Pairing check =====
+    let a1 = g1_add(&proof.wxi, &g1_mul(&proof.wxiw, &u.to_be_bytes())?)?;
+
+    let s = u.mul(&xi).mul(&omega);
+    let mut b1 = g1_mul(&proof.wxi, &xi.to_be_bytes())?;
+    b1 = g1_add(&b1, &g1_mul(&proof.wxiw, &s.to_be_bytes())?)?;
+    b1 = g1_add(&b1, &f)?;
+    b1 = g1_add(&b1, &negate_g1(&e_point))?;
+
+    let mut pairing_input = [0u8; 384];
+    pairing_input[0..64].copy_from_slice(&negate_g1(&a1));
+    pairing_input[64..192].copy_from_slice(&vk.x2);
+    pairing_input[192..256].copy_from_slice(&b1);
+    pairing_input[256..384].copy_from_slice(&G2_GENERATOR);
+
+    let pairing_result = alt_bn128_pairing(&pairing_input)
+        .map_err(|_| PlonkError::PairingFailed)?;
+
+    Ok(pairing_result.len() == 32
+        && pairing_result[..31].iter().all(|&b| b == 0)
+        && pairing_result[31] == 1)
+}
+
+/// Keccak256 Fiat-Shamir transcript, compatible with snarkjs
+///
+/// Points are absorbed as big-endian x || y, scalars as 32 big-endian bytes.
+/// Taking a challenge hashes everything absorbed since the last challenge.
+#[derive(Default)]
+struct Transcript {
+    data: Vec<u8>,
+}
+
+impl Transcript {
+    fn add_point(&mut self, point: &[u8; 64]) {
+        self.data.extend_from_slice(point);
+    }
+
+    fn add_scalar(&mut self, scalar: &Fr) {
+        self.data.extend_from_slice(&scalar.to_be_bytes());
+    }
+
+    fn challenge(&mut self) -> Fr {
+        let hash = keccak::hash(&self.data).to_bytes();
+        self.data.clear();
+        Fr::from_be_bytes_reduced(&hash)
+    }
+}
+
+/// BN254 scalar field modulus r, little-endian 64-bit limbs
+const MODULUS: [u64; 4] = [
+    0x43e1f593f0000001,
+    0x2833e84879b97091,
+    0xb85045b68181585d,
+    0x30644e72e131a029,
+];
+
+/// -r^-1 mod 2^64
+const INV: u64 = 0xc2e1f593efffffff;
+
+/// R^2 mod r, for converting into Montgomery form (R = 2^256)
+const R2: [u64; 4] = [
+    0x1bb8e645ae216da7,
+    0x53fe3ab1e35c59e3,
+    0x8c49833d53bb8085,
+    0x0216d0b17f4e44a5,
+];
+
+/// Element of the BN254 scalar field, stored in Montgomery form
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fr([u64; 4]);
+
+impl Fr {
+    pub fn zero() -> Self {
+        Fr([0; 4])
+    }
+
+    pub fn one() -> Self {
+        Self::from_u64(1)
+    }
+
+    pub fn from_u64(value: u64) -> Self {
+        Fr(mont_mul(&[value, 0, 0, 0], &R2))
+    }
+
+    /// Parse a canonical big-endian field element (rejects values >= r)
+    pub fn from_be_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        let limbs = limbs_from_be(bytes);
+        if !lt(&limbs, &MODULUS) {
+            return None;
+        }
+        Some(Fr(mont_mul(&limbs, &R2)))
+    }
+
+    /// Parse any 32 big-endian bytes, reducing modulo r
+    pub fn from_be_bytes_reduced(bytes: &[u8; 32]) -> Self {
+        let mut limbs = limbs_from_be(bytes);
+        while !lt(&limbs, &MODULUS) {
+            limbs = sub_limbs(&limbs, &MODULUS);
+        }
+        Fr(mont_mul(&limbs, &R2))
+    }
+
+    /// Canonical big-endian encoding
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        let limbs = mont_mul(&self.0, &[1, 0, 0, 0]);
+        let mut bytes = [0u8; 32];
+        for (i, limb) in limbs.iter().enumerate() {
+            bytes[32 - 8 * (i + 1)..32 - 8 * i].copy_from_slice(&limb.to_be_bytes());
+        }
+        bytes
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0; 4]
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        // Both operands are < r < 2^254, so the sum can't overflow 256 bits
+        let sum = add_limbs(&self.0, &other.0);
+        if lt(&sum, &MODULUS) {
+            Fr(sum)
+        } else {
+            Fr(sub_limbs(&sum, &MODULUS))
+        }
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        if lt(&self.0, &other.0) {
+            Fr(sub_limbs(&add_limbs(&self.0, &MODULUS), &other.0))
+        } else {
+            Fr(sub_limbs(&self.0, &other.0))
+        }
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        Fr(mont_mul(&self.0, &other.0))
+    }
+
+    pub fn square(&self) -> Self {
+        self.mul(self)
+    }
+
+    /// Multiplicative inverse via Fermat's little theorem
+    pub fn inverse(&self) -> Option<Self> {
+        if self.is_zero() {
+            return None;
+        }
+        // r - 2
+        let exp = sub_limbs(&MODULUS, &[2, 0, 0, 0]);
+        let mut result = Self::one();
+        for limb in exp.iter().rev() {
+            for bit in (0..64).rev() {
+                result = result.square();
+                if (limb >> bit) & 1 == 1 {
+                    result = result.mul(self);
+                }
+            }
+        }
+        Some(result)
+    }
+
+    /// Invert every element with a single field inversion
+    pub fn batch_inverse(values: &[Self]) -> Option<Vec<Self>> {
+        let mut prefix = Vec::with_capacity(values.len());
+        let mut acc = Self::one();
+        for value in values {
+            prefix.push(acc);
+            acc = acc.mul(value);
+        }
+
+        let mut inv = acc.inverse()?;
+        let mut result = vec![Self::zero(); values.len()];
+        for i in (0..values.len()).rev() {
+            result[i] = inv.mul(&prefix[i]);
+            inv = inv.mul(&values[i]);
+        }
+        Some(result)
+    }
+}
+
+fn limbs_from_be(bytes: &[u8; 32]) -> [u64; 4] {
+    let mut limbs = [0u64; 4];
+    for (i, limb) in limbs.iter_mut().enumerate() {
+        let mut chunk = [0u8; 8];
+        chunk.copy_from_slice(&bytes[32 - 8 * (i + 1)..32 - 8 * i]);
+        *limb = u64::from_be_bytes(chunk);
+    }
+    limbs
+}
+
+fn lt(a: &[u64; 4], b: &[u64; 4]) -> bool {
+    for i in (0..4).rev() {
+        if a[i] != b[i] {
+            return a[i] < b[i];
+        }
+    }
+    false
+}
+
+fn add_limbs(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    let mut result = [0u64; 4];
+    let mut carry = 0u128;
+    for i in 0..4 {
+        let sum = a[i] as u128 + b[i] as u128 + carry;
+        result[i] = sum as u64;
+        carry = sum >> 64;
+    }
+    result
+}
+
+fn sub_limbs(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    let mut result = [0u64; 4];
+    let mut borrow = 0u64;
+    for i in 0..4 {
+        let (d1, b1) = a[i].overflowing_sub(b[i]);
+        let (d2, b2) = d1.overflowing_sub(borrow);
+        result[i] = d2;
+        borrow = (b1 || b2) as u64;
+    }
+    result
+}
+
+/// Montgomery multiplication (CIOS): a * b * R^-1 mod r
+fn mont_mul(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    let mut t = [0u64; 6];
+    for &limb in b {
+        let mut carry = 0u128;
+        for j in 0..4 {
+            let tmp = t[j] as u128 + (a[j] as u128) * (limb as u128) + carry;
+            t[j] = tmp as u64;
+            carry = tmp >> 64;
+        }
+        let tmp = t[4] as u128 + carry;
+        t[4] = tmp as u64;
+        t[5] = (tmp >> 64) as u64;
+
+        let m = t[0].wrapping_mul(INV);
+        let tmp = t[0] as u128 + (m as u128) * (MODULUS[0] as u128);
+        let mut carry = tmp >> 64;
+        for j in 1..4 {
+            let tmp = t[j] as u128 + (m as u128) * (MODULUS[j] as u128) + carry;
+            t[j - 1] = tmp as u64;
+            carry = tmp >> 64;
+        }
+        let tmp = t[4] as u128 + carry;
+        t[3] = tmp as u64;
+        t[4] = t[5] + (tmp >> 64) as u64;
+    }
+
+    let result = [t[0], t[1], t[2], t[3]];
+    if t[4] != 0 || !lt(&result, &MODULUS) {
+        sub_limbs(&result, &MODULUS)
+    } else {
+        result
+    }
+}
+
+/// Errors for PLONK verification
+#[error_code]
+pub enum PlonkError {
+    #[msg("Invalid PLONK proof size")]
+    InvalidProofSize,
+    #[msg("Invalid public inputs")]
+    InvalidPublicInputs,
+    #[msg("PLONK verifying key not initialized or malformed")]
+    VkNotInitialized,
+    #[msg("Proof scalar is not a canonical field element")]
+    NonCanonicalScalar,
+    #[msg("Proof verification failed")]
+    VerificationFailed,
+    #[msg("Pairing computation failed")]
+    PairingFailed,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fr_bytes(value: u64) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[24..32].copy_from_slice(&value.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_fr_roundtrip() {
+        for value in [0u64, 1, 2, 12345, u64::MAX] {
+            let fr = Fr::from_u64(value);
+            assert_eq!(fr.to_be_bytes(), fr_bytes(value));
+            assert_eq!(Fr::from_be_bytes(&fr_bytes(value)), Some(fr));
+        }
+    }
+
+    #[test]
+    fn test_fr_arithmetic() {
+        let a = Fr::from_u64(1_000_000_007);
+        let b = Fr::from_u64(998_244_353);
+
+        assert_eq!(a.add(&b), Fr::from_u64(1_000_000_007 + 998_244_353));
+        assert_eq!(a.sub(&b), Fr::from_u64(1_000_000_007 - 998_244_353));
+        assert_eq!(a.mul(&b).to_be_bytes()[16..], (1_000_000_007u128 * 998_244_353u128).to_be_bytes());
+        // b - a wraps around the modulus
+        assert_eq!(b.sub(&a).add(&a), b);
+        assert_eq!(a.mul(&a.inverse().unwrap()), Fr::one());
+        assert!(Fr::zero().inverse().is_none());
+    }
+
+    #[test]
+    fn test_fr_canonical() {
+        let mut modulus = [0u8; 32];
+        for (i, limb) in MODULUS.iter().enumerate() {
+            modulus[32 - 8 * (i + 1)..32 - 8 * i].copy_from_slice(&limb.to_be_bytes());
+        }
+        // r itself is not canonical, but reduces to zero
+        assert!(Fr::from_be_bytes(&modulus).is_none());
+        assert!(Fr::from_be_bytes_reduced(&modulus).is_zero());
+        // r - 1 = -1
+        assert_eq!(Fr::zero().sub(&Fr::one()).to_be_bytes()[31], modulus[31] - 1);
+        // Any 32 bytes reduce to a canonical element
+        let reduced = Fr::from_be_bytes_reduced(&[0xff; 32]);
+        assert_eq!(Fr::from_be_bytes(&reduced.to_be_bytes()), Some(reduced));
+    }
+
+    #[test]
+    fn test_batch_inverse() {
+        let values: Vec<Fr> = (1..6).map(Fr::from_u64).collect();
+        let inverses = Fr::batch_inverse(&values).unwrap();
+        for (value, inverse) in values.iter().zip(&inverses) {
+            assert_eq!(value.mul(inverse), Fr::one());
+        }
+        assert!(Fr::batch_inverse(&[Fr::one(), Fr::zero()]).is_none());
+    }
+
+    #[test]
+    fn test_plonk_proof_parsing() {
+        let mut bytes = vec![0u8; PLONK_PROOF_SIZE];
+        bytes[0] = 1; // A
+        bytes[8 * 64] = 2; // Wxiw
+        bytes[9 * 64 + 5 * 32] = 3; // eval_zw
+
+        let proof = PlonkProof::from_bytes(&bytes).unwrap();
+        assert_eq!(proof.a[0], 1);
+        assert_eq!(proof.wxiw[0], 2);
+        assert_eq!(proof.eval_zw[0], 3);
+        assert!(PlonkProof::from_bytes(&bytes[1..]).is_none());
+    }
+
+    #[test]
+    fn test_transcript_resets_after_challenge() {
+        let mut t1 = Transcript::default();
+        t1.add_scalar(&Fr::from_u64(7));
+        let c1 = t1.challenge();
+        t1.add_scalar(&Fr::from_u64(7));
+        assert_eq!(t1.challenge(), c1);
+    }
+
+    #[test]
+    fn test_verify_rejects_wrong_sizes() {
+        let vk = PlonkVerifyingKeyData {
+            power: 10,
+            n_public: 2,
+            k1: fr_bytes(2),
+            k2: fr_bytes(3),
+            omega: fr_bytes(5),
+            qm: [1u8; 64],
+            ql: [1u8; 64],
+            qr: [1u8; 64],
+            qo: [1u8; 64],
+            qc: [1u8; 64],
+            s1: [1u8; 64],
+            s2: [1u8; 64],
+            s3: [1u8; 64],
+            x2: [1u8; 128],
+        };
+        assert!(vk.is_valid());
+        assert!(verify_plonk(&[0u8; PLONK_PROOF_SIZE - 1], &[[0u8; 32]; 2], &vk).is_err());
+        assert!(verify_plonk(&[0u8; PLONK_PROOF_SIZE], &[[0u8; 32]; 3], &vk).is_err());
+    }
+}
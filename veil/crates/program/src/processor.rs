@@ -5,298 +5,7235 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
 use anchor_spl::token;
+use anchor_spl::token_interface::{self, TokenAccount, TransferChecked};
 
-use crate::instructions::NyxError;
-use crate::merkle::TREE_DEPTH;
+use crate::bubblegum::{self, CnftLeaf};
+use crate::claim;
+use crate::commitment_chunk;
+use crate::commitment_record;
+use crate::compression::{self, CompressionAccounts};
+use crate::cpi_guard;
+use crate::destination;
+use crate::escrow;
+use crate::events::{
+    AuthorityProposed, AuthorityTransferred, ClaimLinkClosed, ClaimLinkCreated, EmergencyWithdrawal, EncryptedNote, MerkleLeafInserted, NullifierSpent, PauseUpdated, PaymentStreamClosed, PaymentStreamCreated, PoolConfigChanged, PoolMigrated, RelayerFeeUpdated, RelayerQuoteClosed, RelayerQuoteUpdated, RelayerSlashed,
+    ProtocolAdminProposed, ProtocolConfigUpdated, QueuedUnshieldClosed, RootUpdated, ShieldEvent, StreamPaymentMade, TransferEvent, UnshieldEvent, UnshieldQueued, VkFreezeUpdated, YieldStrategyRebalanced, YieldStrategyUpdated,
+};
+use crate::instructions::{
+    self, AggregatedWithdrawal, BatchWithdrawal, NyxError, ShieldBatchData, UnshieldAggregatedData,
+    UnshieldBatchData,
+};
+use crate::groth16::{
+    self, Groth16Proof, Groth16VerifyingKey, VerifyingKeyAccount, WithdrawPublicInputs,
+    MAX_BATCH_SIZE, MAX_PUBLIC_INPUTS,
+};
+use crate::maturity;
+use crate::merkle::{self, CANOPY_DEPTH, TREE_DEPTH};
+use crate::migration;
+use crate::nullifier;
+use crate::nullifier_tree::{IndexedLeaf, NullifierTree};
+use crate::plonk::{PlonkVerifyingKeyAccount, PlonkVerifyingKeyData};
+use crate::queue;
+use crate::relayer;
+use crate::rollover;
+use crate::snapshot::TreeSnapshot;
+use crate::stake::{self, DepositSolAccounts, StakePoolState, WithdrawSolAccounts};
+use crate::staged::{VerificationStage, VerificationState, VERIFICATION_SEED};
+use crate::state::{
+    self, AssetMode, CommitmentChunk, GuardianSet, PoolStats, RegisteredPool, PrivacyPool, ProtocolConfig, ProofSystem, RootEntry, TreeBackend, TreeEpoch,
+    EPHEMERAL_KEY_LEN, MAX_MARKER_FINALITY_SLOTS, MAX_MIN_ANONYMITY_SET, MAX_RELAYER_FEE_BPS, MAX_ROOT_HISTORY_SIZE, MAX_SPEND_DELAY_SLOTS, MAX_VK_TIMELOCK_SLOTS, MIN_ROOT_HISTORY_SIZE, POOL_CLOSE_DELAY_SLOTS, POOL_VERSION, STANDARD_DENOMINATIONS,
+};
+use crate::stream;
+use crate::swap;
 use crate::token as pool_token;
-use crate::verification::{self, MvpProof};
-use crate::{Initialize, Shield, ShieldSol, Transfer, Unshield, UnshieldSol};
+use crate::verification::{self, CircuitId, ProofType, Verifier};
+use crate::{
+    AcceptAuthority, BondRelayer, Claim, CloseRelayerQuote, ConfigureYieldStrategy, CreateClaim, EmergencyWithdraw, InitializeMultiAssetPool, InitializeNftPool, JoinSplit, InitializeStandardPools, InitializePoolRegistry, CloseNullifierMarker, ClosePool, BackfillNullifierTree, CommitEmergencyWithdraw, EnableCompressedTree, FinalizeUnshield, CloseVerificationState, GuardianAction, InitCommitmentChunk, InitNullifierBloom, InitNullifierTree, InitPoolStats, Initialize, InsertNullifierTree, UpdateDestinationRegistry, ProposePlonkVerifyingKey, RebalanceYieldStrategy, ReclaimClaim, CreatePaymentStream, DisputeRelayerFee, ExecuteStreamPayment, CancelPaymentStream, QueueUnshield, ExecuteUnshield, CancelQueuedUnshield, Migrate, MigratePool, ProposeVerifyingKey, InitializeProtocolConfig, AcceptProtocolAdmin, UpdateMintAllowlist, UpdateProtocolConfig, UpdateStakePoolAllowlist, ResizeRootHistory, RolloverTree, SetGuardians,
+    Shield, ShieldBatch, ShieldedStake, ShieldedSwap, ShieldCnft, ShieldMultiAsset, ShieldNft, ShieldSol, ShieldSolBatch, SnapshotTree, Transfer, Unshield, UnshieldAggregated, UnshieldBatch, UnshieldCnft, UnshieldCpi, UnshieldMany, UnshieldMultiAsset, UnshieldNft, UnshieldSelf, UnshieldSol, UnshieldSolEscrow, UnshieldSolTimelocked, UnshieldSolWithChange, UnshieldEscrow, UnshieldTimelocked, UnshieldWithChange,
+    UpdatePoolConfig, UpdateRelayerBond, SetRelayerQuote, VerifyBatch, VerifyMembership, VerifyStage1, VerifyStage2, WithdrawRelayerBond,
+};
 
-/// Maximum leaves in tree (2^20)
-const MAX_COMMITMENTS: u64 = 1 << TREE_DEPTH;
+// `emit_cpi!` reads `ctx` by name, so the helpers below bind the handler's
+// context to it before emitting
+
+/// Emit every event of an iterator (a `Vec` or an `Option`) with `emit_cpi!`
+macro_rules! emit_all {
+    ($ctx:ident, $events:expr) => {{
+        let ctx = &$ctx;
+        for event in IntoIterator::into_iter($events) {
+            emit_cpi!(event);
+        }
+    }};
+}
+
+/// Emit the `InsertionEvents` of `insert_commitments` with `emit_cpi!`
+macro_rules! emit_inserted {
+    ($ctx:ident, $inserted:expr) => {{
+        let ctx = &$ctx;
+        let inserted: InsertionEvents = $inserted;
+        if let Some(rolled_over) = inserted.rolled_over {
+            emit_cpi!(rolled_over);
+        }
+        for leaf in inserted.leaves {
+            emit_cpi!(leaf);
+        }
+        emit_cpi!(inserted.root_updated);
+    }};
+}
 
 /// Process Initialize instruction
 ///
 /// # Arguments
 /// * `denomination` - Fixed deposit amount in lamports (0 = custom/variable pool)
-pub fn process_initialize(ctx: Context<Initialize>, denomination: u64) -> Result<()> {
-    let pool = &mut ctx.accounts.pool;
+/// * `root_history_size` - Number of past roots proofs may use
+/// * `decimals` - Decimals the caller expects the mint to have, so a
+///   denomination meant in UI units isn't taken as base units
+pub fn process_initialize(
+    ctx: Context<Initialize>,
+    denomination: u64,
+    root_history_size: u16,
+    decimals: u8,
+) -> Result<()> {
+    require!(
+        (MIN_ROOT_HISTORY_SIZE..=MAX_ROOT_HISTORY_SIZE).contains(&root_history_size),
+        NyxError::InvalidRootHistorySize
+    );
+    if let Some(mint) = &ctx.accounts.mint {
+        // wSOL shares the native SOL pools instead of splitting their liquidity
+        require_keys_neq!(
+            mint.key(),
+            pool_token::NATIVE_MINT,
+            pool_token::TokenError::WrappedSolPool
+        );
+        pool_token::validate_mint_extensions(&mint.to_account_info())?;
+        ctx.accounts
+            .mint_allowlist
+            .as_ref()
+            .ok_or(NyxError::MintNotAllowlisted)?
+            .require_allowed(&mint.key())?;
+    }
+    let (mint, mint_decimals) = ctx
+        .accounts
+        .mint
+        .as_ref()
+        .map(|mint| (mint.key(), mint.decimals))
+        .unwrap_or((Pubkey::default(), pool_token::NATIVE_DECIMALS));
+    require!(
+        decimals == mint_decimals,
+        pool_token::TokenError::DecimalsMismatch
+    );
+    let mut pool = ctx.accounts.pool.load_init()?;
 
     // Initialize with real Merkle tree and denomination
-    pool.initialize(ctx.accounts.authority.key(), ctx.bumps.pool, denomination);
+    pool.initialize(
+        ctx.accounts.authority.key(),
+        ctx.bumps.pool,
+        denomination,
+        root_history_size,
+        mint,
+        decimals,
+    );
+    if let Some(config) = &ctx.accounts.protocol_config {
+        pool.relayer_fee_bps = config.default_relayer_fee_bps;
+    }
 
     msg!("Privacy pool initialized");
-    msg!("Denomination: {} lamports (0 = custom)", denomination);
+    let (whole, fraction) = pool.denomination_ui();
+    msg!(
+        "Denomination: {} base units, {}.{:0width$} tokens (0 = custom)",
+        denomination,
+        whole,
+        fraction,
+        width = decimals as usize
+    );
+    msg!("Root history: {} roots", root_history_size);
     msg!("Initial root: {:?}", pool.current_root());
     Ok(())
 }
 
-/// Process Shield SOL instruction
-pub fn process_shield_sol(ctx: Context<ShieldSol>, commitment: [u8; 32], amount: u64) -> Result<()> {
-    let pool = &mut ctx.accounts.pool;
+/// Process InitializePoolRegistry instruction
+pub fn process_initialize_pool_registry(ctx: Context<InitializePoolRegistry>) -> Result<()> {
+    let registry = &mut ctx.accounts.pool_registry;
+    registry.authority = ctx.accounts.admin.key();
+    registry.pools = Vec::new();
+    registry.bump = ctx.bumps.pool_registry;
+    msg!("Pool registry initialized");
+    Ok(())
+}
 
-    // Validate amount
-    require!(amount > 0, NyxError::InvalidAmount);
+/// Process InitializeStandardPools instruction
+///
+/// Initializes one native SOL pool per standard denomination and appends
+/// them to the registry.
+pub fn process_initialize_standard_pools(
+    ctx: Context<InitializeStandardPools>,
+    root_history_size: u16,
+) -> Result<()> {
     require!(
-        pool.commitment_count() < MAX_COMMITMENTS,
-        NyxError::PoolFull
+        (MIN_ROOT_HISTORY_SIZE..=MAX_ROOT_HISTORY_SIZE).contains(&root_history_size),
+        NyxError::InvalidRootHistorySize
     );
+    let authority = ctx.accounts.authority.key();
 
-    // Validate denomination (if fixed pool, amount must match exactly)
+    let registry = &mut ctx.accounts.pool_registry;
+
+    let pools = [
+        (&ctx.accounts.pool_0, ctx.bumps.pool_0),
+        (&ctx.accounts.pool_1, ctx.bumps.pool_1),
+        (&ctx.accounts.pool_2, ctx.bumps.pool_2),
+        (&ctx.accounts.pool_3, ctx.bumps.pool_3),
+    ];
+    let relayer_fee_bps = ctx
+        .accounts
+        .protocol_config
+        .as_ref()
+        .map(|config| config.default_relayer_fee_bps);
+    for ((pool, bump), denomination) in pools.into_iter().zip(STANDARD_DENOMINATIONS) {
+        let mut pool_data = pool.load_init()?;
+        pool_data.initialize(
+            authority,
+            bump,
+            denomination,
+            root_history_size,
+            Pubkey::default(),
+            pool_token::NATIVE_DECIMALS,
+        );
+        if let Some(relayer_fee_bps) = relayer_fee_bps {
+            pool_data.relayer_fee_bps = relayer_fee_bps;
+        }
+        registry.register(RegisteredPool {
+            pool: pool.key(),
+            mint: Pubkey::default(),
+            denomination,
+        })?;
+        msg!("Standard pool initialized: {} lamports", denomination);
+    }
+
+    msg!("Pool registry holds {} pools", registry.pools.len());
+    Ok(())
+}
+
+/// Process InitializeMultiAssetPool instruction
+///
+/// The pool is a variable-amount pool recording `MULTI_ASSET_MINT`, so the
+/// single-asset SPL handlers never match its mint.
+pub fn process_initialize_multi_asset_pool(
+    ctx: Context<InitializeMultiAssetPool>,
+    root_history_size: u16,
+) -> Result<()> {
     require!(
-        pool.validate_amount(amount),
-        NyxError::InvalidDenomination
+        (MIN_ROOT_HISTORY_SIZE..=MAX_ROOT_HISTORY_SIZE).contains(&root_history_size),
+        NyxError::InvalidRootHistorySize
+    );
+    let mut pool = ctx.accounts.pool.load_init()?;
+    pool.initialize(
+        ctx.accounts.authority.key(),
+        ctx.bumps.pool,
+        0,
+        root_history_size,
+        pool_token::MULTI_ASSET_MINT,
+        0,
     );
+    pool.asset_mode = AssetMode::Multi as u8;
+    if let Some(config) = &ctx.accounts.protocol_config {
+        pool.relayer_fee_bps = config.default_relayer_fee_bps;
+    }
 
-    // Transfer SOL from depositor to vault
-    let cpi_context = CpiContext::new(
-        ctx.accounts.system_program.to_account_info(),
-        system_program::Transfer {
-            from: ctx.accounts.depositor.to_account_info(),
-            to: ctx.accounts.vault.to_account_info(),
-        },
+    msg!("Multi-asset pool initialized");
+    msg!("Root history: {} roots", root_history_size);
+    Ok(())
+}
+
+/// Process InitializeNftPool instruction
+///
+/// Like the multi-asset pool, but recording `NFT_POOL_MINT` and holding
+/// one NFT per note.
+pub fn process_initialize_nft_pool(
+    ctx: Context<InitializeNftPool>,
+    root_history_size: u16,
+) -> Result<()> {
+    require!(
+        (MIN_ROOT_HISTORY_SIZE..=MAX_ROOT_HISTORY_SIZE).contains(&root_history_size),
+        NyxError::InvalidRootHistorySize
     );
-    system_program::transfer(cpi_context, amount)?;
+    let mut pool = ctx.accounts.pool.load_init()?;
+    pool.initialize(
+        ctx.accounts.authority.key(),
+        ctx.bumps.pool,
+        0,
+        root_history_size,
+        pool_token::NFT_POOL_MINT,
+        0,
+    );
+    pool.asset_mode = AssetMode::Nft as u8;
+    if let Some(config) = &ctx.accounts.protocol_config {
+        pool.relayer_fee_bps = config.default_relayer_fee_bps;
+    }
+
+    msg!("NFT pool initialized");
+    msg!("Root history: {} roots", root_history_size);
+    Ok(())
+}
+
+/// Process InitializeProtocolConfig instruction
+pub fn process_initialize_protocol_config(
+    ctx: Context<InitializeProtocolConfig>,
+    admin: Pubkey,
+    vk_authority: Pubkey,
+    default_relayer_fee_bps: u16,
+    treasury_fee_share_bps: u16,
+) -> Result<()> {
+    let config = &mut ctx.accounts.protocol_config;
+    config.admin = admin;
+    config.vk_authority = vk_authority;
+    config.default_relayer_fee_bps = default_relayer_fee_bps;
+    config.treasury_fee_share_bps = treasury_fee_share_bps;
+    config.paused = false;
+    config.bump = ctx.bumps.protocol_config;
+    config.paused_at = 0;
+    config.pending_admin = Pubkey::default();
+    config.validate()?;
+
+    emit_cpi!(protocol_config_updated(config)?);
+    msg!("Protocol config initialized");
+    Ok(())
+}
+
+/// Process UpdateProtocolConfig instruction
+pub fn process_update_protocol_config(
+    ctx: Context<UpdateProtocolConfig>,
+    vk_authority: Pubkey,
+    default_relayer_fee_bps: u16,
+    treasury_fee_share_bps: u16,
+) -> Result<()> {
+    let config = &mut ctx.accounts.protocol_config;
+    config.vk_authority = vk_authority;
+    config.default_relayer_fee_bps = default_relayer_fee_bps;
+    config.treasury_fee_share_bps = treasury_fee_share_bps;
+    config.validate()?;
+
+    emit_cpi!(protocol_config_updated(config)?);
+    msg!("Protocol config updated");
+    Ok(())
+}
+
+/// Process ProposeProtocolAdmin instruction
+pub fn process_propose_protocol_admin(
+    ctx: Context<UpdateProtocolConfig>,
+    new_admin: Pubkey,
+) -> Result<()> {
+    let config = &mut ctx.accounts.protocol_config;
+    config.pending_admin = new_admin;
+
+    emit_cpi!(ProtocolAdminProposed {
+        admin: config.admin,
+        pending_admin: new_admin,
+        slot: Clock::get()?.slot,
+    });
+    msg!("Proposed protocol admin {}", new_admin);
+    Ok(())
+}
+
+/// Process AcceptProtocolAdmin instruction
+pub fn process_accept_protocol_admin(ctx: Context<AcceptProtocolAdmin>) -> Result<()> {
+    let config = &mut ctx.accounts.protocol_config;
+    config.admin = config.pending_admin;
+    config.pending_admin = Pubkey::default();
+
+    emit_cpi!(protocol_config_updated(config)?);
+    msg!("Protocol admin transferred to {}", config.admin);
+    Ok(())
+}
+
+/// Process SetProtocolPaused instruction
+pub fn process_set_protocol_paused(ctx: Context<UpdateProtocolConfig>, paused: bool) -> Result<()> {
+    let config = &mut ctx.accounts.protocol_config;
+    config.set_paused(paused, Clock::get()?.slot);
+
+    emit_cpi!(protocol_config_updated(config)?);
+    msg!("Protocol paused: {}", paused);
+    Ok(())
+}
+
+/// Event announcing the protocol config after a change
+fn protocol_config_updated(config: &ProtocolConfig) -> Result<ProtocolConfigUpdated> {
+    Ok(ProtocolConfigUpdated {
+        admin: config.admin,
+        vk_authority: config.vk_authority,
+        default_relayer_fee_bps: config.default_relayer_fee_bps,
+        treasury_fee_share_bps: config.treasury_fee_share_bps,
+        paused: config.paused,
+        slot: Clock::get()?.slot,
+    })
+}
+
+/// Process AllowMint instruction
+pub fn process_allow_mint(ctx: Context<UpdateMintAllowlist>, mint: Pubkey) -> Result<()> {
+    let allowlist = &mut ctx.accounts.mint_allowlist;
+    allowlist.bump = ctx.bumps.mint_allowlist;
+    allowlist.add(mint)?;
+
+    msg!("Mint allowlisted: {}", mint);
+    Ok(())
+}
+
+/// Process DisallowMint instruction
+pub fn process_disallow_mint(ctx: Context<UpdateMintAllowlist>, mint: Pubkey) -> Result<()> {
+    let allowlist = &mut ctx.accounts.mint_allowlist;
+    allowlist.bump = ctx.bumps.mint_allowlist;
+    allowlist.remove(&mint);
+
+    msg!("Mint removed from allowlist: {}", mint);
+    Ok(())
+}
+
+/// Process AllowStakePool instruction
+pub fn process_allow_stake_pool(
+    ctx: Context<UpdateStakePoolAllowlist>,
+    stake_pool: Pubkey,
+) -> Result<()> {
+    let allowlist = &mut ctx.accounts.stake_pool_allowlist;
+    allowlist.bump = ctx.bumps.stake_pool_allowlist;
+    allowlist.add(stake_pool)?;
+
+    msg!("Stake pool allowlisted: {}", stake_pool);
+    Ok(())
+}
+
+/// Process DisallowStakePool instruction
+pub fn process_disallow_stake_pool(
+    ctx: Context<UpdateStakePoolAllowlist>,
+    stake_pool: Pubkey,
+) -> Result<()> {
+    let allowlist = &mut ctx.accounts.stake_pool_allowlist;
+    allowlist.bump = ctx.bumps.stake_pool_allowlist;
+    allowlist.remove(&stake_pool);
+
+    msg!("Stake pool removed from allowlist: {}", stake_pool);
+    Ok(())
+}
+
+/// Process AllowDestinationProgram instruction
+pub fn process_allow_destination_program(
+    ctx: Context<UpdateDestinationRegistry>,
+    program: Pubkey,
+) -> Result<()> {
+    let registry = &mut ctx.accounts.destination_registry;
+    registry.bump = ctx.bumps.destination_registry;
+    registry.add(program)?;
+
+    msg!("Destination program registered: {}", program);
+    Ok(())
+}
+
+/// Process DisallowDestinationProgram instruction
+pub fn process_disallow_destination_program(
+    ctx: Context<UpdateDestinationRegistry>,
+    program: Pubkey,
+) -> Result<()> {
+    let registry = &mut ctx.accounts.destination_registry;
+    registry.bump = ctx.bumps.destination_registry;
+    registry.remove(&program);
+
+    msg!("Destination program removed from registry: {}", program);
+    Ok(())
+}
+
+/// Process ProposeVerifyingKey instruction
+///
+/// Writes the pool's Groth16 verifying key for one circuit under the pending
+/// version and restarts the update timelock. Every circuit the pool spends
+/// with needs a key under the new version before it is activated.
+/// The key's IC length must match the circuit's public input count.
+pub fn process_propose_verifying_key(
+    ctx: Context<ProposeVerifyingKey>,
+    circuit_id: u8,
+    alpha_g1: [u8; 64],
+    beta_g2: [u8; 128],
+    gamma_g2: [u8; 128],
+    delta_g2: [u8; 128],
+    ic: Vec<[u8; 64]>,
+) -> Result<()> {
+    let circuit = CircuitId::from_u8(circuit_id).ok_or(NyxError::InvalidVerifyingKey)?;
+    require!(
+        ic.len() <= MAX_PUBLIC_INPUTS + 1 && ic.len() == circuit.num_public_inputs() + 1,
+        NyxError::InvalidVerifyingKey
+    );
+    let candidate = Groth16VerifyingKey {
+        alpha_g1: &alpha_g1,
+        beta_g2: &beta_g2,
+        gamma_g2: &gamma_g2,
+        delta_g2: &delta_g2,
+        ic: &ic,
+    };
+    require!(candidate.is_initialized(), NyxError::InvalidVerifyingKey);
+
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    let verifying_key = &mut ctx.accounts.verifying_key;
+    verifying_key.pool = pool_key;
+    verifying_key.circuit_id = circuit_id;
+    verifying_key.version = pool.pending_vk_version();
+    verifying_key.alpha_g1 = alpha_g1;
+    verifying_key.beta_g2 = beta_g2;
+    verifying_key.gamma_g2 = gamma_g2;
+    verifying_key.delta_g2 = delta_g2;
+    verifying_key.ic = ic;
+    verifying_key.bump = ctx.bumps.verifying_key;
+
+    pool.schedule_vk_update(Clock::get()?.slot)?;
+
+    msg!(
+        "Verifying key v{} proposed for {:?} ({} public inputs), activatable at slot {}",
+        verifying_key.version,
+        circuit,
+        verifying_key.ic.len() - 1,
+        pool.vk_activation_slot
+    );
+    Ok(())
+}
+
+/// Process ProposePlonkVerifyingKey instruction
+///
+/// Writes the pool's PLONK verifying key for one circuit under the pending
+/// version and restarts the update timelock.
+/// The key's public input count must match the circuit.
+pub fn process_propose_plonk_verifying_key(
+    ctx: Context<ProposePlonkVerifyingKey>,
+    circuit_id: u8,
+    key: PlonkVerifyingKeyData,
+) -> Result<()> {
+    let circuit = CircuitId::from_u8(circuit_id).ok_or(NyxError::InvalidVerifyingKey)?;
+    require!(
+        key.n_public as usize == circuit.num_public_inputs() && key.is_valid(),
+        NyxError::InvalidVerifyingKey
+    );
+
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    let verifying_key = &mut ctx.accounts.verifying_key;
+    verifying_key.pool = pool_key;
+    verifying_key.circuit_id = circuit_id;
+    verifying_key.version = pool.pending_vk_version();
+    verifying_key.key = key;
+    verifying_key.bump = ctx.bumps.verifying_key;
+
+    pool.schedule_vk_update(Clock::get()?.slot)?;
+
+    msg!(
+        "PLONK verifying key v{} proposed for {:?} (domain 2^{}), activatable at slot {}",
+        verifying_key.version,
+        circuit,
+        verifying_key.key.power,
+        pool.vk_activation_slot
+    );
+    Ok(())
+}
+
+/// Process ActivateVerifyingKeys instruction
+///
+/// Makes the proposed key version current. The previous version keeps
+/// verifying proofs for the pool's grace period. `remaining_accounts` must
+/// hold the proposed key of every circuit, so no spend path loses its key.
+pub fn process_activate_verifying_keys(ctx: Context<UpdatePoolConfig>) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    verification::require_verifying_keys(
+        &pool_key,
+        pool.proof_system(),
+        pool.pending_vk_version(),
+        ctx.remaining_accounts,
+    )?;
+    pool.activate_pending_vk(Clock::get()?.slot)?;
+
+    emit_cpi!(pool_config_changed(&pool_key, &pool)?);
+    msg!("Verifying keys v{} activated", pool.vk_version);
+    Ok(())
+}
+
+/// Process SetVkTimelock instruction
+///
+/// The new values apply once the current update delay has passed, and then
+/// to the next proposal; a pending update keeps its activation slot.
+pub fn process_set_vk_timelock(
+    ctx: Context<UpdatePoolConfig>,
+    update_delay_slots: u64,
+    grace_period_slots: u64,
+) -> Result<()> {
+    require!(
+        update_delay_slots <= MAX_VK_TIMELOCK_SLOTS && grace_period_slots <= MAX_VK_TIMELOCK_SLOTS,
+        NyxError::InvalidTimelock
+    );
+
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.schedule_vk_timelock(update_delay_slots, grace_period_slots, Clock::get()?.slot);
+
+    emit_cpi!(pool_config_changed(&pool_key, &pool)?);
+    msg!(
+        "VK timelock set: {} slot delay, {} slot grace period, from slot {}",
+        update_delay_slots,
+        grace_period_slots,
+        pool.vk_timelock_change_slot
+    );
+    Ok(())
+}
+
+/// Process SetProofSystem instruction
+///
+/// Spends are verified with the new backend from the next instruction on,
+/// so its verifying keys should be registered first.
+pub fn process_set_proof_system(
+    ctx: Context<UpdatePoolConfig>,
+    proof_system: ProofSystem,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.set_proof_system(proof_system);
+
+    emit_cpi!(pool_config_changed(&pool_key, &pool)?);
+    msg!("Proof system set to {:?}", proof_system);
+    Ok(())
+}
+
+/// Process SetCpiPolicy instruction
+pub fn process_set_cpi_policy(
+    ctx: Context<UpdatePoolConfig>,
+    policy: state::CpiPolicy,
+    allowed_callers: Vec<Pubkey>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.set_cpi_policy(policy, &allowed_callers)?;
+
+    emit_cpi!(pool_config_changed(&pool_key, &pool)?);
+    msg!("CPI policy set to {:?} ({} allowed callers)", policy, allowed_callers.len());
+    Ok(())
+}
+
+/// Process SetRelayerPolicy instruction
+pub fn process_set_relayer_policy(
+    ctx: Context<UpdatePoolConfig>,
+    policy: state::RelayerPolicy,
+    allowed_relayers: Vec<Pubkey>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.set_relayer_policy(policy, &allowed_relayers)?;
+
+    emit_cpi!(pool_config_changed(&pool_key, &pool)?);
+    msg!("Relayer policy set to {:?} ({} allowed relayers)", policy, allowed_relayers.len());
+    Ok(())
+}
+
+/// Process ConfigureYieldStrategy instruction
+pub fn process_configure_yield_strategy(
+    ctx: Context<ConfigureYieldStrategy>,
+    buffer_bps: u16,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let pool = ctx.accounts.pool.load()?;
+    require!(
+        pool.asset_mode() == AssetMode::Single && pool.mint == Pubkey::default(),
+        NyxError::YieldStrategyUnsupported
+    );
+    let stake_pool_key = ctx.accounts.stake_pool.key();
+    let stake_pool = StakePoolState::read(&ctx.accounts.stake_pool.try_borrow_data()?)
+        .ok_or(NyxError::InvalidStakePool)?;
+
+    // Tokens of the old stake pool would no longer be accounted for. A
+    // strategy keeps its stake pool if it leaves the allowlist, to unwind
+    let strategy = &mut ctx.accounts.strategy;
+    if strategy.stake_pool != stake_pool_key {
+        if strategy.pool != Pubkey::default() {
+            require!(pool.strategy_deployed == 0, NyxError::StrategyInUse);
+        }
+        ctx.accounts.stake_pool_allowlist.require_allowed(&stake_pool_key)?;
+    }
+    strategy.pool = pool_key;
+    strategy.stake_pool = stake_pool_key;
+    strategy.pool_mint = stake_pool.pool_mint;
+    strategy.buffer_bps = buffer_bps;
+    strategy.bump = ctx.bumps.strategy;
+    strategy.validate()?;
+
+    emit_cpi!(YieldStrategyUpdated {
+        pool: pool_key,
+        stake_pool: stake_pool_key,
+        buffer_bps,
+        slot: Clock::get()?.slot,
+    });
+    msg!("Yield strategy set to {} with a {} bps buffer", stake_pool_key, buffer_bps);
+    Ok(())
+}
+
+/// Process RebalanceYieldStrategy instruction
+///
+/// Marks the vault's stake pool tokens to market, then compares the
+/// vault's liquid lamports, above its own rent exemption, to the
+/// strategy's buffer of the value locked and moves the difference to or
+/// from the stake pool. Redemptions release their share of the marked
+/// value and are capped at what the stake pool's reserve can pay.
+pub fn process_rebalance_yield_strategy(ctx: Context<RebalanceYieldStrategy>) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    let slot = Clock::get()?.slot;
+
+    let target = ctx.accounts.strategy.target_buffer(pool.total_value_locked());
+    let rent = Rent::get()?;
+    let liquid = ctx.accounts.vault.lamports().saturating_sub(rent.minimum_balance(0));
+    let held = ctx.accounts.strategy_token_account.amount;
+    let stake_pool = StakePoolState::read(&ctx.accounts.stake_pool.try_borrow_data()?)
+        .ok_or(NyxError::InvalidStakePool)?;
+    pool.mark_strategy(stake_pool.lamports_for(held));
+
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[ctx.bumps.vault],
+    ]];
+    let vault = ctx.accounts.vault.to_account_info();
+    let pool_mint = ctx.accounts.pool_mint.to_account_info();
+    let strategy_token_account = ctx.accounts.strategy_token_account.to_account_info();
+    let token_program = ctx.accounts.token_program.to_account_info();
+
+    // WithdrawSol only draws on the reserve, which keeps its rent
+    let reserve_stake = &ctx.accounts.reserve_stake;
+    let reserve_available = reserve_stake
+        .lamports()
+        .saturating_sub(rent.minimum_balance(reserve_stake.data_len()));
+    let redeemable = stake_pool.pool_tokens_within(reserve_available).min(held);
+
+    let (deposited, withdrawn) = if liquid > target {
+        pool.require_not_paused()?;
+        ctx.accounts
+            .stake_pool_allowlist
+            .require_allowed(&ctx.accounts.stake_pool.key())?;
+        let lamports = liquid - target;
+        DepositSolAccounts {
+            stake_pool: &ctx.accounts.stake_pool,
+            withdraw_authority: &ctx.accounts.stake_pool_withdraw_authority,
+            reserve_stake: &ctx.accounts.reserve_stake,
+            lamports_from: &vault,
+            pool_tokens_to: &strategy_token_account,
+            manager_fee_account: &ctx.accounts.manager_fee_account,
+            referrer_pool_tokens_account: &ctx.accounts.manager_fee_account,
+            pool_mint: &pool_mint,
+            system_program: &ctx.accounts.system_program.to_account_info(),
+            token_program: &token_program,
+            stake_pool_program: &ctx.accounts.stake_pool_program,
+        }
+        .deposit_sol(lamports, signer_seeds)?;
+        pool.record_strategy_deposit(lamports);
+        (lamports, 0)
+    } else if liquid < target && redeemable > 0 {
+        let tokens = stake_pool
+            .pool_tokens_for(target - liquid)
+            .map_or(redeemable, |tokens| tokens.min(redeemable));
+        let balance_before = vault.lamports();
+        WithdrawSolAccounts {
+            stake_pool: &ctx.accounts.stake_pool,
+            withdraw_authority: &ctx.accounts.stake_pool_withdraw_authority,
+            user_transfer_authority: &vault,
+            pool_tokens_from: &strategy_token_account,
+            reserve_stake: &ctx.accounts.reserve_stake,
+            lamports_to: &vault,
+            manager_fee_account: &ctx.accounts.manager_fee_account,
+            pool_mint: &pool_mint,
+            clock: &ctx.accounts.clock,
+            stake_history: &ctx.accounts.stake_history,
+            stake_program: &ctx.accounts.stake_program,
+            token_program: &token_program,
+            stake_pool_program: &ctx.accounts.stake_pool_program,
+        }
+        .withdraw_sol(tokens, signer_seeds)?;
+        pool.record_strategy_withdrawal(tokens, held);
+        (0, vault.lamports().saturating_sub(balance_before))
+    } else {
+        msg!("Vault already holds its buffer, or the reserve can't refill it");
+        return Ok(());
+    };
+
+    let strategy = &mut ctx.accounts.strategy;
+    strategy.total_deposited = strategy.total_deposited.saturating_add(deposited);
+    strategy.total_withdrawn = strategy.total_withdrawn.saturating_add(withdrawn);
+
+    emit_cpi!(YieldStrategyRebalanced {
+        pool: pool_key,
+        deposited,
+        withdrawn,
+        deployed: pool.strategy_deployed,
+        slot,
+    });
+    msg!(
+        "Rebalanced: {} lamports deposited, {} withdrawn, {} deployed",
+        deposited,
+        withdrawn,
+        pool.strategy_deployed
+    );
+    Ok(())
+}
+
+/// Pick the verifying key matching the pool's proof system
+///
+/// The key's version must be active (or within its grace period) at `slot`.
+fn pool_verifier<'a>(
+    pool: &PrivacyPool,
+    slot: u64,
+    groth16_vk: &'a Option<Box<Account<'_, VerifyingKeyAccount>>>,
+    plonk_vk: &'a Option<Box<Account<'_, PlonkVerifyingKeyAccount>>>,
+) -> Result<Verifier<'a>> {
+    let (verifier, version) = match pool.proof_system() {
+        ProofSystem::Groth16 => {
+            let vk = groth16_vk.as_ref().ok_or(NyxError::InvalidVerifyingKey)?;
+            (Verifier::Groth16(vk.as_groth16_vk()), vk.version)
+        }
+        ProofSystem::Plonk => {
+            let vk = plonk_vk.as_ref().ok_or(NyxError::InvalidVerifyingKey)?;
+            (Verifier::Plonk(&vk.key), vk.version)
+        }
+    };
+    require!(
+        pool.is_vk_version_accepted(version, slot),
+        NyxError::VkVersionNotAccepted
+    );
+    Ok(verifier)
+}
+
+/// Event announcing the pool's configuration after an authority changed it
+fn pool_config_changed(pool_key: &Pubkey, pool: &PrivacyPool) -> Result<PoolConfigChanged> {
+    Ok(PoolConfigChanged {
+        pool: *pool_key,
+        authority: pool.authority,
+        treasury: pool.treasury,
+        relayer_fee_bps: pool.relayer_fee_bps,
+        proof_system: pool.proof_system,
+        vk_version: pool.vk_version,
+        vk_update_delay_slots: pool.vk_update_delay_slots,
+        vk_grace_period_slots: pool.vk_grace_period_slots,
+        min_spend_delay_slots: pool.min_spend_delay_slots,
+        min_anonymity_set: pool.min_anonymity_set,
+        withdrawal_window_slots: pool.withdrawal_limit.window_slots,
+        withdrawal_max_amount: pool.withdrawal_limit.max_amount,
+        withdrawal_max_count: pool.withdrawal_limit.max_count,
+        deposit_cap: pool.deposit_cap,
+        max_rent_fee: pool.max_rent_fee,
+        marker_finality_slots: pool.marker_finality_slots,
+        cpi_policy: pool.cpi_policy,
+        allowed_callers: pool
+            .allowed_callers
+            .iter()
+            .filter(|caller| **caller != Pubkey::default())
+            .copied()
+            .collect(),
+        relayer_policy: pool.relayer_policy,
+        allowed_relayers: pool
+            .allowed_relayers
+            .iter()
+            .filter(|relayer| **relayer != Pubkey::default())
+            .copied()
+            .collect(),
+        close_scheduled_at: pool.close_scheduled_at,
+        slot: Clock::get()?.slot,
+    })
+}
+
+/// Process SetRelayerFee instruction
+pub fn process_set_relayer_fee(ctx: Context<UpdatePoolConfig>, fee_bps: u16) -> Result<()> {
+    require!(fee_bps <= MAX_RELAYER_FEE_BPS, NyxError::InvalidRelayerFee);
+
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    let old_fee_bps = pool.relayer_fee_bps;
+    pool.relayer_fee_bps = fee_bps;
+
+    emit_cpi!(RelayerFeeUpdated {
+        pool: pool_key,
+        old_fee_bps,
+        new_fee_bps: fee_bps,
+        slot: Clock::get()?.slot,
+    });
+    emit_cpi!(pool_config_changed(&pool_key, &pool)?);
+    msg!("Relayer fee set to {} bps", fee_bps);
+    Ok(())
+}
+
+/// Process ProposeAuthority instruction
+pub fn process_propose_authority(
+    ctx: Context<UpdatePoolConfig>,
+    new_authority: Pubkey,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.pending_authority = new_authority;
+
+    emit_cpi!(AuthorityProposed {
+        pool: pool_key,
+        authority: pool.authority,
+        pending_authority: new_authority,
+        slot: Clock::get()?.slot,
+    });
+    msg!("Proposed pool authority {}", new_authority);
+    Ok(())
+}
+
+/// Process AcceptAuthority instruction
+pub fn process_accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    let old_authority = pool.accept_authority(&ctx.accounts.new_authority.key())?;
+
+    emit_cpi!(AuthorityTransferred {
+        pool: pool_key,
+        old_authority,
+        new_authority: pool.authority,
+        slot: Clock::get()?.slot,
+    });
+    msg!("Pool authority transferred to {}", pool.authority);
+    Ok(())
+}
+
+/// Process SetTreasury instruction
+pub fn process_set_treasury(ctx: Context<UpdatePoolConfig>, treasury: Pubkey) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.treasury = treasury;
+
+    emit_cpi!(pool_config_changed(&pool_key, &pool)?);
+    msg!("Treasury set to {}", treasury);
+    Ok(())
+}
+
+/// Process SetGuardians instruction
+///
+/// Replacing appointed guardians takes their quorum, whose signers lead
+/// `remaining_accounts`.
+pub fn process_set_guardians(
+    ctx: Context<SetGuardians>,
+    guardians: Vec<Pubkey>,
+    threshold: u8,
+) -> Result<()> {
+    ctx.accounts.guardian_set.require_replaceable(ctx.remaining_accounts)?;
+    GuardianSet::validate(&guardians, threshold)?;
+
+    let guardian_set = &mut ctx.accounts.guardian_set;
+    guardian_set.pool = ctx.accounts.pool.key();
+    guardian_set.guardians = guardians;
+    guardian_set.threshold = threshold;
+    guardian_set.bump = ctx.bumps.guardian_set;
+
+    msg!(
+        "Guardian set updated: {} of {}",
+        threshold,
+        guardian_set.guardians.len()
+    );
+    Ok(())
+}
+
+/// Process Pause instruction
+pub fn process_pause(ctx: Context<GuardianAction>, allow_unshields: bool) -> Result<()> {
+    ctx.accounts.guardian_set.require_quorum(ctx.remaining_accounts)?;
+
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.pause(allow_unshields, Clock::get()?.slot);
+
+    emit_cpi!(PauseUpdated {
+        pool: pool_key,
+        paused: true,
+        unshields_paused: !allow_unshields,
+        slot: Clock::get()?.slot,
+    });
+    msg!("Pool paused (unshields allowed: {})", allow_unshields);
+    Ok(())
+}
+
+/// Process Unpause instruction
+pub fn process_unpause(ctx: Context<GuardianAction>) -> Result<()> {
+    ctx.accounts.guardian_set.require_quorum(ctx.remaining_accounts)?;
+
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.unpause();
+
+    emit_cpi!(PauseUpdated {
+        pool: pool_key,
+        paused: false,
+        unshields_paused: false,
+        slot: Clock::get()?.slot,
+    });
+    msg!("Pool unpaused");
+    Ok(())
+}
+
+/// Process FreezeVkUpdates instruction
+pub fn process_freeze_vk_updates(ctx: Context<GuardianAction>, frozen: bool) -> Result<()> {
+    ctx.accounts.guardian_set.require_quorum(ctx.remaining_accounts)?;
+
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.vk_updates_frozen = u8::from(frozen);
+
+    emit_cpi!(VkFreezeUpdated {
+        pool: pool_key,
+        frozen,
+        slot: Clock::get()?.slot,
+    });
+    msg!("Verifying key updates frozen: {}", frozen);
+    Ok(())
+}
+
+/// Process SetMinSpendDelay instruction
+pub fn process_set_min_spend_delay(
+    ctx: Context<UpdatePoolConfig>,
+    delay_slots: u64,
+) -> Result<()> {
+    require!(
+        delay_slots <= MAX_SPEND_DELAY_SLOTS,
+        NyxError::InvalidSpendDelay
+    );
+
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.min_spend_delay_slots = delay_slots;
+
+    emit_cpi!(pool_config_changed(&pool_key, &pool)?);
+    msg!("Minimum spend delay set to {} slots", delay_slots);
+    Ok(())
+}
+
+/// Process SetMinAnonymitySet instruction
+pub fn process_set_min_anonymity_set(
+    ctx: Context<UpdatePoolConfig>,
+    min_anonymity_set: u64,
+) -> Result<()> {
+    require!(
+        min_anonymity_set <= MAX_MIN_ANONYMITY_SET,
+        NyxError::InvalidAnonymitySet
+    );
+
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.min_anonymity_set = min_anonymity_set;
+
+    emit_cpi!(pool_config_changed(&pool_key, &pool)?);
+    msg!("Minimum anonymity set set to {} deposits", min_anonymity_set);
+    Ok(())
+}
+
+/// Process SetWithdrawalRateLimit instruction
+pub fn process_set_withdrawal_rate_limit(
+    ctx: Context<UpdatePoolConfig>,
+    window_slots: u64,
+    max_amount: u64,
+    max_count: u64,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.withdrawal_limit.configure(window_slots, max_amount, max_count);
+
+    emit_cpi!(pool_config_changed(&pool_key, &pool)?);
+    msg!(
+        "Withdrawal rate limit set to {} units and {} withdrawals per {} slots",
+        max_amount,
+        max_count,
+        window_slots
+    );
+    Ok(())
+}
+
+/// Process SetDepositCap instruction
+pub fn process_set_deposit_cap(ctx: Context<UpdatePoolConfig>, deposit_cap: u64) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.deposit_cap = deposit_cap;
+
+    emit_cpi!(pool_config_changed(&pool_key, &pool)?);
+    msg!("Deposit cap set to {} (0 = uncapped)", deposit_cap);
+    Ok(())
+}
+
+/// Process SetMaxRentFee instruction
+pub fn process_set_max_rent_fee(ctx: Context<UpdatePoolConfig>, max_rent_fee: u64) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.max_rent_fee = max_rent_fee;
+
+    emit_cpi!(pool_config_changed(&pool_key, &pool)?);
+    msg!("Max rent fee set to {} (0 = none)", max_rent_fee);
+    Ok(())
+}
+
+/// Process SetMarkerFinalityWindow instruction
+pub fn process_set_marker_finality_window(
+    ctx: Context<UpdatePoolConfig>,
+    finality_slots: u64,
+) -> Result<()> {
+    require!(
+        finality_slots <= MAX_MARKER_FINALITY_SLOTS,
+        NyxError::InvalidFinalityWindow
+    );
+
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.marker_finality_slots = finality_slots;
+
+    emit_cpi!(pool_config_changed(&pool_key, &pool)?);
+    msg!("Nullifier marker finality window set to {} slots", finality_slots);
+    Ok(())
+}
+
+/// Check the root a spend proof was generated against
+///
+/// Notes in an archived tree are spent against that tree's final root,
+/// everything else against the current root or one still in the pool's
+/// root history. The root must also be mature, see `maturity`.
+fn check_spend_root(
+    pool: &PrivacyPool,
+    root_history: &[RootEntry],
+    tree_epoch: &Option<Account<'_, TreeEpoch>>,
+    root: &[u8; 32],
+    slot: u64,
+) -> Result<()> {
+    pool.require_poseidon_tree()?;
+    let root_slot = match tree_epoch {
+        Some(epoch) => (epoch.root == *root).then_some(epoch.archived_at),
+        None => pool.root_slot_of(root, root_history),
+    };
+    let root_slot = root_slot.ok_or(NyxError::StaleRoot)?;
+    maturity::require_mature_root(pool, root, root_slot, root_history, slot)
+}
+
+/// Add a spent nullifier to the pool's bloom filter, if the spend passed it
+fn record_in_bloom(
+    bloom: &Option<AccountLoader<'_, state::NullifierBloom>>,
+    nullifier: &[u8; 32],
+) -> Result<()> {
+    if let Some(bloom) = bloom {
+        bloom.load_mut()?.insert(nullifier);
+    }
+    Ok(())
+}
+
+/// Queue a spent nullifier in the pool's indexed tree, if the spend passed it
+fn record_in_tree(
+    tree: &Option<AccountLoader<'_, NullifierTree>>,
+    nullifier: &[u8; 32],
+) -> Result<()> {
+    if let Some(tree) = tree {
+        tree.load_mut()?.enqueue(*nullifier)?;
+    }
+    Ok(())
+}
+
+/// Pay `amount` lamports out of the pool's SOL vault (no-op for zero)
+fn pay_from_vault<'info>(
+    vault: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    anchor_lang::solana_program::program::invoke_signed(
+        &anchor_lang::solana_program::system_instruction::transfer(vault.key, to.key, amount),
+        &[vault.clone(), to.clone(), system_program.clone()],
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+/// Checks a relayed unshield makes before its proof is verified
+///
+/// The relayer must be allowed to relay, the fee within the pool's rate,
+/// the proof of a known system and unexpired, and its root accepted.
+#[allow(clippy::too_many_arguments)]
+fn check_unshield(
+    pool: &PrivacyPool,
+    root_history: &[RootEntry],
+    tree_epoch: &Option<Account<'_, TreeEpoch>>,
+    relayer: &Pubkey,
+    relayer_bond: Option<&state::RelayerBond>,
+    root: &[u8; 32],
+    amount: u64,
+    fee: u64,
+    proof: &[u8],
+    expiry_slot: u64,
+    slot: u64,
+) -> Result<()> {
+    relayer::require_relayer(pool, relayer, relayer_bond)?;
+    pool.require_anonymity_set()?;
+    require!(amount > 0, NyxError::InvalidAmount);
+    require!(fee <= amount, NyxError::FeeExceedsAmount);
+    require!(fee <= pool.calculate_relayer_fee(amount), NyxError::FeeAboveRelayerRate);
+    require!(ProofType::detect(proof).is_some(), NyxError::InvalidProof);
+    require!(slot <= expiry_slot, NyxError::ProofExpired);
+
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(pool, root_history, tree_epoch, root, slot)
+}
+
+/// Spend a note's nullifier, failing if it was already spent
+///
+/// Records the spend in the pool, its bloom filter and nullifier tree, and
+/// returns the event for the handler to emit.
+#[allow(clippy::too_many_arguments)]
+fn spend_note<'info>(
+    program_id: &Pubkey,
+    pool: &mut PrivacyPool,
+    pool_key: &Pubkey,
+    nullifier: &[u8; 32],
+    marker: &AccountInfo<'info>,
+    nullifier_set: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    bloom: &Option<AccountLoader<'info, state::NullifierBloom>>,
+    tree: &Option<AccountLoader<'info, NullifierTree>>,
+) -> Result<NullifierSpent> {
+    let spent = nullifier::spend_nullifier(
+        program_id,
+        pool_key,
+        nullifier,
+        marker,
+        nullifier_set,
+        payer,
+        system_program,
+    )?;
+    pool.record_nullifier_spent();
+    record_in_bloom(bloom, nullifier)?;
+    record_in_tree(tree, nullifier)?;
+    Ok(spent)
+}
+
+/// Record a paid out unshield against the pool, its rate limit and stats
+fn record_unshield(
+    pool: &mut PrivacyPool,
+    pool_stats: &Option<AccountLoader<'_, PoolStats>>,
+    amount: u64,
+    fee: u64,
+    slot: u64,
+) -> Result<()> {
+    pool.record_fee_collected(fee);
+    pool.record_unshielded(amount)?;
+    pool.withdrawal_limit.record(slot, amount, 1)?;
+    record_withdrawal_stats(pool_stats, 1, amount, fee)
+}
+
+/// Accounts a relayed SOL unshield pays out to
+struct SolPayout<'info> {
+    /// The pool's SOL vault PDA
+    vault: AccountInfo<'info>,
+    /// Recipient wallet
+    recipient: AccountInfo<'info>,
+    /// Recipient's wSOL account, credited instead of the wallet when passed
+    recipient_wsol_account: Option<AccountInfo<'info>>,
+    /// Relayer, paid its share of the fee
+    relayer: AccountInfo<'info>,
+    /// Treasury, paid the protocol's share of the fee
+    treasury: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    /// Token program, needed to sync a wSOL account
+    token_program: Option<AccountInfo<'info>>,
+}
+
+/// Take a SOL unshield handler's `SolPayout` from its context
+macro_rules! sol_payout {
+    ($ctx:ident) => {
+        SolPayout {
+            vault: $ctx.accounts.vault.to_account_info(),
+            recipient: $ctx.accounts.recipient.to_account_info(),
+            recipient_wsol_account: $ctx
+                .accounts
+                .recipient_wsol_account
+                .as_ref()
+                .map(|wsol_account| wsol_account.to_account_info()),
+            relayer: $ctx.accounts.relayer.to_account_info(),
+            treasury: $ctx.accounts.treasury.to_account_info(),
+            system_program: $ctx.accounts.system_program.to_account_info(),
+            token_program: $ctx
+                .accounts
+                .token_program
+                .as_ref()
+                .map(|program| program.to_account_info()),
+        }
+    };
+}
+
+/// Pay a SOL unshield out of the vault, net of the fee
+///
+/// The fee is split between relayer and treasury. A recipient wSOL account
+/// is credited directly and synced, so the recipient gets wrapped SOL
+/// without a separate wrap step.
+fn pay_sol_unshield(
+    pool_key: &Pubkey,
+    vault_bump: u8,
+    payout: &SolPayout<'_>,
+    protocol_config: &ProtocolConfig,
+    amount: u64,
+    fee: u64,
+) -> Result<()> {
+    require!(payout.vault.lamports() >= amount, pool_token::TokenError::InsufficientFunds);
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[vault_bump],
+    ]];
+
+    let (relayer_fee, treasury_fee) = protocol_config.split_relayer_fee(fee);
+    let recipient = payout.recipient_wsol_account.as_ref().unwrap_or(&payout.recipient);
+    for (to, lamports) in [
+        (recipient, amount - fee),
+        (&payout.relayer, relayer_fee),
+        (&payout.treasury, treasury_fee),
+    ] {
+        pay_from_vault(&payout.vault, to, &payout.system_program, lamports, signer_seeds)?;
+    }
+    if let Some(wsol_account) = &payout.recipient_wsol_account {
+        let token_program = payout
+            .token_program
+            .as_ref()
+            .ok_or(pool_token::TokenError::InvalidTokenAccount)?;
+        token::sync_native(CpiContext::new(
+            token_program.clone(),
+            token::SyncNative {
+                account: wsol_account.clone(),
+            },
+        ))?;
+    }
+    Ok(())
+}
+
+/// Process SchedulePoolClose instruction
+pub fn process_schedule_pool_close(ctx: Context<UpdatePoolConfig>) -> Result<()> {
+    let slot = Clock::get()?.slot;
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.close_scheduled_at = slot;
+
+    emit_cpi!(pool_config_changed(&pool_key, &pool)?);
+    msg!(
+        "Pool closure scheduled, closable from slot {}",
+        slot.saturating_add(POOL_CLOSE_DELAY_SLOTS)
+    );
+    Ok(())
+}
+
+/// Process CancelPoolClose instruction
+pub fn process_cancel_pool_close(ctx: Context<UpdatePoolConfig>) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    require!(pool.close_scheduled_at != 0, NyxError::CloseNotScheduled);
+    pool.close_scheduled_at = 0;
+
+    emit_cpi!(pool_config_changed(&pool_key, &pool)?);
+    msg!("Pool closure cancelled");
+    Ok(())
+}
+
+/// Process ClosePool instruction
+///
+/// Anchor closes the pool account to the authority once the checks pass.
+pub fn process_close_pool(ctx: Context<ClosePool>) -> Result<()> {
+    let pool = ctx.accounts.pool.load()?;
+    let slot = Clock::get()?.slot;
+
+    require!(pool.close_scheduled_at != 0, NyxError::CloseNotScheduled);
+    require!(
+        slot >= pool.close_scheduled_at.saturating_add(POOL_CLOSE_DELAY_SLOTS),
+        NyxError::CloseTimelockActive
+    );
+    require!(pool.total_value_locked() == 0, NyxError::PoolNotDrained);
+    require!(pool.strategy_deployed == 0, NyxError::PoolNotDrained);
+
+    // SPL pools hold their value in the token vault, SOL pools in the PDA
+    let vault_balance = if pool.mint == Pubkey::default() {
+        ctx.accounts.vault.lamports()
+    } else {
+        let vault_token_account = ctx
+            .accounts
+            .vault_token_account
+            .as_ref()
+            .ok_or(pool_token::TokenError::InvalidTokenAccount)?;
+        vault_token_account.amount
+    };
+    require!(vault_balance == 0, NyxError::PoolNotDrained);
+
+    msg!("Pool closed after {} deposits", pool.deposit_count);
+    Ok(())
+}
+
+/// Process MigratePool instruction
+///
+/// Grows the account to the current layout's size if needed, moves the
+/// root history behind the appended fields, then backfills the fields added
+/// since the pool's version.
+pub fn process_migrate_pool(ctx: Context<MigratePool>) -> Result<()> {
+    let (from_version, root_history_size, mint, is_multi_asset) = {
+        let pool = ctx.accounts.pool.load()?;
+        (pool.version, pool.root_history_size, pool.mint, pool.is_multi_asset())
+    };
+    require!(from_version < POOL_VERSION, NyxError::PoolUpToDate);
+    let space = PrivacyPool::space(root_history_size);
+
+    // SPL pools hold their value in the token vault, SOL pools in the PDA.
+    // Pools of several mints postdate the steps that need the balance or
+    // decimals and hold no single mint to read them from.
+    let (vault_balance, decimals) = if is_multi_asset {
+        (0, 0)
+    } else if mint == Pubkey::default() {
+        (ctx.accounts.vault.lamports(), pool_token::NATIVE_DECIMALS)
+    } else {
+        let vault_token_account = ctx
+            .accounts
+            .vault_token_account
+            .as_ref()
+            .ok_or(pool_token::TokenError::InvalidTokenAccount)?;
+        let mint_account = ctx
+            .accounts
+            .mint
+            .as_ref()
+            .ok_or(pool_token::TokenError::InvalidMint)?;
+        (vault_token_account.amount, mint_account.decimals)
+    };
+
+    let pool_info = ctx.accounts.pool.to_account_info();
+    if pool_info.data_len() < space {
+        let rent_exempt = Rent::get()?.minimum_balance(space);
+        let lamports = pool_info.lamports();
+        if rent_exempt > lamports {
+            let cpi_context = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: pool_info.clone(),
+                },
+            );
+            system_program::transfer(cpi_context, rent_exempt - lamports)?;
+        }
+        pool_info.realloc(space, true)?;
+    }
+
+    // Move the root history behind the fields appended since `from_version`,
+    // widening bare roots into root entries
+    let old_offset = 8 + PrivacyPool::layout_size(from_version);
+    let old_stride = PrivacyPool::history_entry_size(from_version);
+    let new_offset = 8 + PrivacyPool::SIZE;
+    if (old_offset, old_stride) != (new_offset, RootEntry::SIZE) {
+        let mut data = pool_info.try_borrow_mut_data()?;
+        // Back to front, so no root is overwritten before it has moved
+        for i in (0..root_history_size as usize).rev() {
+            let from = old_offset + i * old_stride;
+            let to = new_offset + i * RootEntry::SIZE;
+            data.copy_within(from..from + 32, to);
+            data[to + 32..to + RootEntry::SIZE].fill(0);
+        }
+        data[old_offset..new_offset].fill(0);
+    }
+
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.migrate(vault_balance, Clock::get()?.slot, decimals);
+
+    emit_cpi!(PoolMigrated {
+        pool: ctx.accounts.pool.key(),
+        from_version,
+        to_version: POOL_VERSION,
+        slot: Clock::get()?.slot,
+    });
+    msg!("Pool migrated from v{} to v{}", from_version, POOL_VERSION);
+    Ok(())
+}
+
+/// Record deposits in the pool's stats account, if one was passed
+fn record_deposit_stats(
+    pool_stats: &Option<AccountLoader<'_, PoolStats>>,
+    count: u64,
+    amount: u64,
+    anonymity_set: u64,
+) -> Result<()> {
+    if let Some(pool_stats) = pool_stats {
+        let epoch = Clock::get()?.epoch;
+        pool_stats.load_mut()?.record_deposits(epoch, count, amount, anonymity_set);
+    }
+    Ok(())
+}
+
+/// Record withdrawals in the pool's stats account, if one was passed
+fn record_withdrawal_stats(
+    pool_stats: &Option<AccountLoader<'_, PoolStats>>,
+    count: u64,
+    amount: u64,
+    fees: u64,
+) -> Result<()> {
+    if let Some(pool_stats) = pool_stats {
+        let epoch = Clock::get()?.epoch;
+        pool_stats.load_mut()?.record_withdrawals(epoch, count, amount, fees);
+    }
+    Ok(())
+}
+
+/// Process ResizeRootHistory instruction
+///
+/// Keeps the newest roots that fit and reallocates the pool account,
+/// topping up rent from the authority or refunding the excess to it.
+pub fn process_resize_root_history(
+    ctx: Context<ResizeRootHistory>,
+    root_history_size: u16,
+) -> Result<()> {
+    require!(
+        (MIN_ROOT_HISTORY_SIZE..=MAX_ROOT_HISTORY_SIZE).contains(&root_history_size),
+        NyxError::InvalidRootHistorySize
+    );
+
+    // Copy out the current history before the account changes size
+    let (old_size, old_history) = {
+        let (pool, root_history) = state::load_pool(&ctx.accounts.pool)?;
+        (pool.root_history_size, root_history.to_vec())
+    };
+
+    let pool_info = ctx.accounts.pool.to_account_info();
+    let new_space = PrivacyPool::space(root_history_size);
+    let rent_exempt = Rent::get()?.minimum_balance(new_space);
+    let lamports = pool_info.lamports();
+    if rent_exempt > lamports {
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.payer.to_account_info(),
+                to: pool_info.clone(),
+            },
+        );
+        system_program::transfer(cpi_context, rent_exempt - lamports)?;
+    }
+    pool_info.realloc(new_space, false)?;
+    if lamports > rent_exempt {
+        let excess = lamports - rent_exempt;
+        **pool_info.try_borrow_mut_lamports()? -= excess;
+        **ctx.accounts.payer.to_account_info().try_borrow_mut_lamports()? += excess;
+    }
+
+    // Write the kept roots into the resized history
+    // The history slice is sized by the recorded length, so record it first
+    let mut data = pool_info.try_borrow_mut_data()?;
+    state::split_pool_data_mut(&mut data).0.root_history_size = root_history_size;
+    let (pool, root_history) = state::split_pool_data_mut(&mut data);
+    pool.resize_root_history(&old_history, root_history);
+
+    msg!(
+        "Root history resized from {} to {} roots",
+        old_size,
+        root_history_size
+    );
+    Ok(())
+}
+
+/// Events of an insertion into the pool's tree, in emission order
+struct InsertionEvents {
+    /// `RootUpdated` of a full tree rolled over to make room
+    rolled_over: Option<RootUpdated>,
+    /// A `MerkleLeafInserted` per leaf
+    leaves: Vec<MerkleLeafInserted>,
+    /// `RootUpdated` once every leaf is in
+    root_updated: RootUpdated,
+}
+
+/// Insert commitments into the pool's tree, returning the first leaf index
+/// and the events the instruction emits for them with `emit_inserted!`
+///
+/// Incremental pools insert into the in-account tree and archive the leaves
+/// to their commitment chunks, `payer` covering the rent of new chunks. A
+/// tree without room for the leaves is first rolled over, archiving it at
+/// `tree_archive`. Compressed pools append each leaf to the compression
+/// tree, whose accounts lead `remaining_accounts`.
+#[allow(clippy::too_many_arguments)]
+fn insert_commitments<'info>(
+    program_id: &Pubkey,
+    pool: &mut PrivacyPool,
+    root_history: &mut [RootEntry],
+    pool_key: &Pubkey,
+    commitments: &[[u8; 32]],
+    chunk: Option<&AccountInfo<'info>>,
+    next_chunk: Option<&AccountInfo<'info>>,
+    tree_archive: Option<&AccountInfo<'info>>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'_>],
+) -> Result<(u64, InsertionEvents)> {
+    let mut rolled_over = None;
+    if pool.tree_backend() == TreeBackend::Incremental
+        && !pool.has_room_for(commitments.len() as u64)
+    {
+        let tree_archive = tree_archive.ok_or(NyxError::InvalidTreeArchive)?;
+        rolled_over = Some(rollover::roll_over(
+            program_id,
+            pool,
+            root_history,
+            pool_key,
+            tree_archive,
+            payer,
+            system_program,
+        )?);
+    }
+
+    let first_index = pool.commitment_count();
+    let old_root = pool.current_root();
+    let slot = Clock::get()?.slot;
+    let leaf_inserted = |pool: &PrivacyPool, leaf: [u8; 32], leaf_index: u64| MerkleLeafInserted {
+        pool: *pool_key,
+        tree_epoch: pool.tree_epoch,
+        leaf,
+        leaf_index,
+        new_root: pool.current_root(),
+        slot,
+    };
+
+    let mut leaves = Vec::with_capacity(commitments.len());
+    match pool.tree_backend() {
+        TreeBackend::Incremental => {
+            for commitment in commitments {
+                let leaf_index = pool.add_commitment(*commitment, root_history, slot)?;
+                leaves.push(leaf_inserted(pool, *commitment, leaf_index));
+            }
+            let chunk = chunk.ok_or(NyxError::InvalidCommitmentChunk)?;
+            commitment_chunk::archive_commitments(
+                program_id,
+                pool_key,
+                pool.tree_epoch,
+                first_index,
+                commitments,
+                chunk,
+                next_chunk,
+                payer,
+                system_program,
+            )?;
+        }
+        TreeBackend::Compressed => {
+            let authority_bump = pool.tree_authority_bump;
+            let tree_authority = Pubkey::create_program_address(
+                &[compression::TREE_AUTHORITY_SEED, pool_key.as_ref(), &[authority_bump]],
+                program_id,
+            )
+            .map_err(|_| NyxError::InvalidCompressionAccounts)?;
+            let accounts = CompressionAccounts::from_remaining(
+                remaining_accounts,
+                &pool.compression_tree,
+                &tree_authority,
+            )?;
+            for commitment in commitments {
+                let new_root = accounts.append(pool_key, authority_bump, commitment)?;
+                let leaf_index = pool.record_compressed_commitment(new_root, root_history, slot);
+                leaves.push(leaf_inserted(pool, *commitment, leaf_index));
+            }
+        }
+    }
+
+    let root_updated = RootUpdated {
+        pool: *pool_key,
+        tree_epoch: pool.tree_epoch,
+        old_root,
+        new_root: pool.current_root(),
+        leaf_count: pool.commitment_count(),
+        slot,
+    };
+    Ok((first_index, InsertionEvents { rolled_over, leaves, root_updated }))
+}
+
+/// Event carrying the note encrypted to a new leaf's recipient, if the
+/// depositor or sender attached one
+fn note_event(
+    pool_key: &Pubkey,
+    tree_epoch: u64,
+    commitment: [u8; 32],
+    leaf_index: u64,
+    encrypted_note: Option<Vec<u8>>,
+) -> Result<Option<EncryptedNote>> {
+    instructions::validate_encrypted_note(encrypted_note.as_deref())?;
+    Ok(encrypted_note.map(|encrypted_note| {
+        let mut ephemeral_key = [0u8; EPHEMERAL_KEY_LEN];
+        ephemeral_key.copy_from_slice(&encrypted_note[..EPHEMERAL_KEY_LEN]);
+        EncryptedNote {
+            pool: *pool_key,
+            tree_epoch,
+            commitment,
+            leaf_index,
+            ephemeral_key,
+            encrypted_note,
+        }
+    }))
+}
+
+/// A `ShieldEvent` per deposit, each a commitment and its amount, the
+/// first inserted at `first_index`
+fn shield_events(
+    pool_key: &Pubkey,
+    pool: &PrivacyPool,
+    mint: Pubkey,
+    depositor: Pubkey,
+    first_index: u64,
+    deposits: impl IntoIterator<Item = ([u8; 32], u64)>,
+) -> Result<Vec<ShieldEvent>> {
+    let slot = Clock::get()?.slot;
+    Ok((first_index..)
+        .zip(deposits)
+        .map(|(leaf_index, (commitment, amount))| ShieldEvent {
+            pool: *pool_key,
+            mint,
+            depositor,
+            commitment,
+            tree_epoch: pool.tree_epoch,
+            leaf_index,
+            root: pool.current_root(),
+            amount,
+            slot,
+        })
+        .collect())
+}
+
+/// Process Shield SOL instruction
+pub fn process_shield_sol(
+    ctx: Context<ShieldSol>,
+    commitment: [u8; 32],
+    amount: u64,
+    encrypted_note: Option<Vec<u8>>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, mut root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_not_paused()?;
+    pool.require_not_closing()?;
+    pool.require_sol_pool()?;
+    ctx.accounts.protocol_config.require_not_paused()?;
+    cpi_guard::require_shield_caller(&pool, ctx.accounts.instructions_sysvar.as_ref())?;
+
+    // Validate amount
+    require!(amount > 0, NyxError::InvalidAmount);
+    require!(
+        verification::is_canonical_field_element(&commitment),
+        NyxError::NonCanonicalFieldElement
+    );
+    require!(pool.can_insert(1), NyxError::PoolFull);
+    pool.check_deposit_cap(ctx.accounts.vault.lamports(), amount)?;
+    // A record that already exists means the commitment was shielded before
+    require!(
+        ctx.accounts.commitment_record.pool == Pubkey::default(),
+        NyxError::DuplicateCommitment
+    );
+
+    // Validate denomination (if fixed pool, amount must match exactly)
+    require!(
+        pool.validate_amount(amount),
+        NyxError::InvalidDenomination
+    );
+
+    // Unwrap the shielded amount of the depositor's wSOL first, so it joins
+    // the native pool
+    if let Some(wsol_account) = &ctx.accounts.depositor_wsol_account {
+        let missing = || pool_token::TokenError::InvalidTokenAccount;
+        let token_program = ctx.accounts.token_program.as_ref().ok_or_else(missing)?;
+        let scratch_account = ctx.accounts.wsol_unwrap_account.as_ref().ok_or_else(missing)?;
+        let wsol_mint = ctx.accounts.wsol_mint.as_ref().ok_or_else(missing)?;
+        pool_token::unwrap_wsol(
+            ctx.program_id,
+            &wsol_account.to_account_info(),
+            scratch_account,
+            wsol_mint,
+            &ctx.accounts.depositor.to_account_info(),
+            &token_program.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            amount,
+        )?;
+    }
+
+    // Transfer SOL from depositor to vault
+    let cpi_context = CpiContext::new(
+        ctx.accounts.system_program.to_account_info(),
+        system_program::Transfer {
+            from: ctx.accounts.depositor.to_account_info(),
+            to: ctx.accounts.vault.to_account_info(),
+        },
+    );
+    system_program::transfer(cpi_context, amount)?;
+
+    // Add commitment to tree
+    let (leaf_index, inserted) = insert_commitments(
+        ctx.program_id,
+        &mut pool,
+        &mut root_history,
+        &pool_key,
+        &[commitment],
+        ctx.accounts.commitment_chunk.as_ref(),
+        None,
+        ctx.accounts.tree_archive.as_ref(),
+        &ctx.accounts.depositor.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+    emit_inserted!(ctx, inserted);
+
+    let note = note_event(&pool_key, pool.tree_epoch, commitment, leaf_index, encrypted_note)?;
+    emit_all!(ctx, note);
+    let shields = shield_events(
+        &pool_key,
+        &pool,
+        pool.mint,
+        ctx.accounts.depositor.key(),
+        leaf_index,
+        [(commitment, amount)],
+    )?;
+    emit_all!(ctx, shields);
+
+    // Record where the leaf landed
+    let record = &mut ctx.accounts.commitment_record;
+    record.pool = pool_key;
+    record.commitment = commitment;
+    record.tree_epoch = pool.tree_epoch;
+    record.leaf_index = leaf_index;
+    record.slot = Clock::get()?.slot;
+    record.bump = ctx.bumps.commitment_record;
+
+    // Record deposit for anonymity set tracking
+    pool.record_deposit();
+    pool.record_shielded(amount)?;
+    record_deposit_stats(&ctx.accounts.pool_stats, 1, amount, pool.deposit_count)?;
+
+    msg!("Shielded {} lamports at index {}", amount, leaf_index);
+    msg!("Pool denomination: {} (0=custom)", pool.denomination);
+    msg!("Pool deposit count: {}", pool.deposit_count);
+    msg!("New root: {:?}", pool.current_root());
+
+    Ok(())
+}
+
+/// Process Shield SPL token instruction
+pub fn process_shield(
+    ctx: Context<Shield>,
+    commitment: [u8; 32],
+    amount: u64,
+    encrypted_note: Option<Vec<u8>>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, mut root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_not_paused()?;
+    pool.require_not_closing()?;
+    pool.require_spl_pool()?;
+    ctx.accounts.protocol_config.require_not_paused()?;
+    cpi_guard::require_shield_caller(&pool, ctx.accounts.instructions_sysvar.as_ref())?;
+    ctx.accounts.mint_allowlist.require_allowed(&pool.mint)?;
+
+    // Validate amount
+    require!(amount > 0, NyxError::InvalidAmount);
+    require!(
+        verification::is_canonical_field_element(&commitment),
+        NyxError::NonCanonicalFieldElement
+    );
+    require!(pool.can_insert(1), NyxError::PoolFull);
+    pool.check_deposit_cap(ctx.accounts.vault_token_account.amount, amount)?;
+    // A record that already exists means the commitment was shielded before
+    require!(
+        ctx.accounts.commitment_record.pool == Pubkey::default(),
+        NyxError::DuplicateCommitment
+    );
+
+    // Validate denomination (if fixed pool, amount must match exactly)
+    require!(
+        pool.validate_amount(amount),
+        NyxError::InvalidDenomination
+    );
+
+    // Transfer SPL tokens from depositor to vault
+    let balance_before = ctx.accounts.vault_token_account.amount;
+    let cpi_accounts = TransferChecked {
+        from: ctx.accounts.depositor_token_account.to_account_info(),
+        mint: ctx.accounts.mint.to_account_info(),
+        to: ctx.accounts.vault_token_account.to_account_info(),
+        authority: ctx.accounts.depositor.to_account_info(),
+    };
+    let cpi_context = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        cpi_accounts,
+    );
+    token_interface::transfer_checked(cpi_context, amount, ctx.accounts.mint.decimals)?;
+    check_vault_received(&mut ctx.accounts.vault_token_account, balance_before, amount)?;
+
+    // Add commitment to tree
+    let (leaf_index, inserted) = insert_commitments(
+        ctx.program_id,
+        &mut pool,
+        &mut root_history,
+        &pool_key,
+        &[commitment],
+        ctx.accounts.commitment_chunk.as_ref(),
+        None,
+        ctx.accounts.tree_archive.as_ref(),
+        &ctx.accounts.depositor.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+    emit_inserted!(ctx, inserted);
+
+    let note = note_event(&pool_key, pool.tree_epoch, commitment, leaf_index, encrypted_note)?;
+    emit_all!(ctx, note);
+    let shields = shield_events(
+        &pool_key,
+        &pool,
+        pool.mint,
+        ctx.accounts.depositor.key(),
+        leaf_index,
+        [(commitment, amount)],
+    )?;
+    emit_all!(ctx, shields);
+
+    // Record where the leaf landed
+    let record = &mut ctx.accounts.commitment_record;
+    record.pool = pool_key;
+    record.commitment = commitment;
+    record.tree_epoch = pool.tree_epoch;
+    record.leaf_index = leaf_index;
+    record.slot = Clock::get()?.slot;
+    record.bump = ctx.bumps.commitment_record;
+
+    // Record deposit for anonymity set tracking
+    pool.record_deposit();
+    pool.record_shielded(amount)?;
+    record_deposit_stats(&ctx.accounts.pool_stats, 1, amount, pool.deposit_count)?;
+
+    msg!("Shielded {} tokens at index {}", amount, leaf_index);
+    msg!("Pool denomination: {} (0=custom)", pool.denomination);
+    msg!("Pool deposit count: {}", pool.deposit_count);
+    msg!("New root: {:?}", pool.current_root());
+
+    Ok(())
+}
+
+/// Process ShieldSolFor instruction
+///
+/// A shield whose note belongs to someone else. The recipient can only
+/// find and open it through the `EncryptedNote` event, so the note is
+/// required rather than optional.
+pub fn process_shield_sol_for(
+    ctx: Context<ShieldSol>,
+    commitment: [u8; 32],
+    amount: u64,
+    encrypted_note: Vec<u8>,
+) -> Result<()> {
+    require!(!encrypted_note.is_empty(), NyxError::MissingEncryptedNote);
+    process_shield_sol(ctx, commitment, amount, Some(encrypted_note))
+}
+
+/// Process ShieldFor instruction
+pub fn process_shield_for(
+    ctx: Context<Shield>,
+    commitment: [u8; 32],
+    amount: u64,
+    encrypted_note: Vec<u8>,
+) -> Result<()> {
+    require!(!encrypted_note.is_empty(), NyxError::MissingEncryptedNote);
+    process_shield(ctx, commitment, amount, Some(encrypted_note))
+}
+
+/// Process ShieldMultiAsset instruction
+///
+/// Each mint has its own vault, so the pool's deposit cap and solvency
+/// checks, which count one unit, don't apply; a vault can only pay out
+/// what it holds.
+pub fn process_shield_multi_asset(
+    ctx: Context<ShieldMultiAsset>,
+    commitment: [u8; 32],
+    amount: u64,
+    proof: Vec<u8>,
+    encrypted_note: Option<Vec<u8>>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, mut root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_multi_asset()?;
+    pool.require_not_paused()?;
+    pool.require_not_closing()?;
+    ctx.accounts.protocol_config.require_not_paused()?;
+    cpi_guard::require_shield_caller(&pool, ctx.accounts.instructions_sysvar.as_ref())?;
+    let mint = ctx.accounts.mint.key();
+    ctx.accounts.mint_allowlist.require_allowed(&mint)?;
+    pool_token::validate_mint_extensions(&ctx.accounts.mint.to_account_info())?;
+
+    // Validate amount
+    require!(amount > 0, NyxError::InvalidAmount);
+    require!(pool.can_insert(1), NyxError::PoolFull);
+    // A record that already exists means the commitment was shielded before
+    require!(
+        ctx.accounts.commitment_record.pool == Pubkey::default(),
+        NyxError::DuplicateCommitment
+    );
+
+    // The commitment must open to this amount of this mint's asset
+    let clock = Clock::get()?;
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_shield_proof(
+        &verifier,
+        &proof,
+        &commitment,
+        amount,
+        verification::asset_id(&mint),
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Transfer SPL tokens from depositor to the mint's vault
+    let balance_before = ctx.accounts.vault_token_account.amount;
+    let cpi_accounts = TransferChecked {
+        from: ctx.accounts.depositor_token_account.to_account_info(),
+        mint: ctx.accounts.mint.to_account_info(),
+        to: ctx.accounts.vault_token_account.to_account_info(),
+        authority: ctx.accounts.depositor.to_account_info(),
+    };
+    let cpi_context = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        cpi_accounts,
+    );
+    token_interface::transfer_checked(cpi_context, amount, ctx.accounts.mint.decimals)?;
+    check_vault_received(&mut ctx.accounts.vault_token_account, balance_before, amount)?;
+
+    // Add commitment to tree
+    let (leaf_index, inserted) = insert_commitments(
+        ctx.program_id,
+        &mut pool,
+        &mut root_history,
+        &pool_key,
+        &[commitment],
+        ctx.accounts.commitment_chunk.as_ref(),
+        None,
+        ctx.accounts.tree_archive.as_ref(),
+        &ctx.accounts.depositor.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+    emit_inserted!(ctx, inserted);
+
+    let note = note_event(&pool_key, pool.tree_epoch, commitment, leaf_index, encrypted_note)?;
+    emit_all!(ctx, note);
+    let shields = shield_events(
+        &pool_key,
+        &pool,
+        mint,
+        ctx.accounts.depositor.key(),
+        leaf_index,
+        [(commitment, amount)],
+    )?;
+    emit_all!(ctx, shields);
+
+    // Record where the leaf landed
+    let record = &mut ctx.accounts.commitment_record;
+    record.pool = pool_key;
+    record.commitment = commitment;
+    record.tree_epoch = pool.tree_epoch;
+    record.leaf_index = leaf_index;
+    record.slot = clock.slot;
+    record.bump = ctx.bumps.commitment_record;
+
+    // Record deposit for anonymity set tracking
+    pool.record_deposit();
+    pool.record_shielded(amount)?;
+    record_deposit_stats(&ctx.accounts.pool_stats, 1, amount, pool.deposit_count)?;
+
+    msg!("Shielded {} tokens of {} at index {}", amount, mint, leaf_index);
+    msg!("Pool deposit count: {}", pool.deposit_count);
+    msg!("New root: {:?}", pool.current_root());
+
+    Ok(())
+}
+
+/// Process ShieldNft instruction
+///
+/// Any non-fungible mint is accepted: each NFT sits in its own vault, so a
+/// worthless one can't dilute the others.
+pub fn process_shield_nft(
+    ctx: Context<ShieldNft>,
+    commitment: [u8; 32],
+    proof: Vec<u8>,
+    encrypted_note: Option<Vec<u8>>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, mut root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_nft()?;
+    pool.require_not_paused()?;
+    pool.require_not_closing()?;
+    ctx.accounts.protocol_config.require_not_paused()?;
+    cpi_guard::require_shield_caller(&pool, ctx.accounts.instructions_sysvar.as_ref())?;
+    let mint = ctx.accounts.mint.key();
+    pool_token::require_non_fungible(&ctx.accounts.mint)?;
+    pool_token::validate_mint_extensions(&ctx.accounts.mint.to_account_info())?;
+
+    require!(pool.can_insert(1), NyxError::PoolFull);
+    // A record that already exists means the commitment was shielded before
+    require!(
+        ctx.accounts.commitment_record.pool == Pubkey::default(),
+        NyxError::DuplicateCommitment
+    );
+
+    // The commitment must open to the one token of this NFT
+    let clock = Clock::get()?;
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_shield_proof(
+        &verifier,
+        &proof,
+        &commitment,
+        1,
+        verification::asset_id(&mint),
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Transfer the NFT from depositor to its vault
+    let balance_before = ctx.accounts.vault_token_account.amount;
+    let cpi_accounts = TransferChecked {
+        from: ctx.accounts.depositor_token_account.to_account_info(),
+        mint: ctx.accounts.mint.to_account_info(),
+        to: ctx.accounts.vault_token_account.to_account_info(),
+        authority: ctx.accounts.depositor.to_account_info(),
+    };
+    let cpi_context = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        cpi_accounts,
+    );
+    token_interface::transfer_checked(cpi_context, 1, ctx.accounts.mint.decimals)?;
+    check_vault_received(&mut ctx.accounts.vault_token_account, balance_before, 1)?;
+
+    // Add commitment to tree
+    let (leaf_index, inserted) = insert_commitments(
+        ctx.program_id,
+        &mut pool,
+        &mut root_history,
+        &pool_key,
+        &[commitment],
+        ctx.accounts.commitment_chunk.as_ref(),
+        None,
+        ctx.accounts.tree_archive.as_ref(),
+        &ctx.accounts.depositor.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+    emit_inserted!(ctx, inserted);
+
+    let note = note_event(&pool_key, pool.tree_epoch, commitment, leaf_index, encrypted_note)?;
+    emit_all!(ctx, note);
+    let shields = shield_events(
+        &pool_key,
+        &pool,
+        mint,
+        ctx.accounts.depositor.key(),
+        leaf_index,
+        [(commitment, 1)],
+    )?;
+    emit_all!(ctx, shields);
+
+    // Record where the leaf landed
+    let record = &mut ctx.accounts.commitment_record;
+    record.pool = pool_key;
+    record.commitment = commitment;
+    record.tree_epoch = pool.tree_epoch;
+    record.leaf_index = leaf_index;
+    record.slot = clock.slot;
+    record.bump = ctx.bumps.commitment_record;
+
+    // Record deposit for anonymity set tracking
+    pool.record_deposit();
+    pool.record_shielded(1)?;
+    record_deposit_stats(&ctx.accounts.pool_stats, 1, 1, pool.deposit_count)?;
+
+    msg!("Shielded NFT {} at index {}", mint, leaf_index);
+    msg!("Pool deposit count: {}", pool.deposit_count);
+    msg!("New root: {:?}", pool.current_root());
+
+    Ok(())
+}
+
+/// Process ShieldCnft instruction
+///
+/// As `process_shield_nft`, with the note bound to the compressed NFT's
+/// asset ID and the leaf moved to the vault authority through Bubblegum.
+pub fn process_shield_cnft<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ShieldCnft<'info>>,
+    commitment: [u8; 32],
+    leaf: CnftLeaf,
+    proof: Vec<u8>,
+    encrypted_note: Option<Vec<u8>>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, mut root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_nft()?;
+    pool.require_not_paused()?;
+    pool.require_not_closing()?;
+    ctx.accounts.protocol_config.require_not_paused()?;
+    cpi_guard::require_shield_caller(&pool, ctx.accounts.instructions_sysvar.as_ref())?;
+    let asset = leaf.asset_id(&ctx.accounts.merkle_tree.key());
+
+    require!(pool.can_insert(1), NyxError::PoolFull);
+    // A record that already exists means the commitment was shielded before
+    require!(
+        ctx.accounts.commitment_record.pool == Pubkey::default(),
+        NyxError::DuplicateCommitment
+    );
+
+    // The commitment must open to this NFT
+    let clock = Clock::get()?;
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_shield_proof(
+        &verifier,
+        &proof,
+        &commitment,
+        1,
+        verification::asset_id(&asset),
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Transfer the leaf from depositor to the vault authority; Bubblegum
+    // fails unless the depositor owns the leaf the proof nodes open to
+    let depositor = ctx.accounts.depositor.to_account_info();
+    bubblegum::TransferAccounts {
+        tree_config: &ctx.accounts.tree_config,
+        leaf_owner: &depositor,
+        leaf_delegate: &ctx.accounts.leaf_delegate,
+        new_leaf_owner: &ctx.accounts.vault_authority,
+        merkle_tree: &ctx.accounts.merkle_tree,
+        log_wrapper: &ctx.accounts.log_wrapper,
+        compression_program: &ctx.accounts.compression_program,
+        system_program: &ctx.accounts.system_program.to_account_info(),
+        bubblegum_program: &ctx.accounts.bubblegum_program,
+    }
+    .transfer(&leaf, accounts_after_compression(&pool, ctx.remaining_accounts), &[])?;
+
+    // Add commitment to tree
+    let (leaf_index, inserted) = insert_commitments(
+        ctx.program_id,
+        &mut pool,
+        &mut root_history,
+        &pool_key,
+        &[commitment],
+        ctx.accounts.commitment_chunk.as_ref(),
+        None,
+        ctx.accounts.tree_archive.as_ref(),
+        &ctx.accounts.depositor.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+    emit_inserted!(ctx, inserted);
+
+    let note = note_event(&pool_key, pool.tree_epoch, commitment, leaf_index, encrypted_note)?;
+    emit_all!(ctx, note);
+    let shields = shield_events(
+        &pool_key,
+        &pool,
+        asset,
+        ctx.accounts.depositor.key(),
+        leaf_index,
+        [(commitment, 1)],
+    )?;
+    emit_all!(ctx, shields);
+
+    // Record where the leaf landed
+    let record = &mut ctx.accounts.commitment_record;
+    record.pool = pool_key;
+    record.commitment = commitment;
+    record.tree_epoch = pool.tree_epoch;
+    record.leaf_index = leaf_index;
+    record.slot = clock.slot;
+    record.bump = ctx.bumps.commitment_record;
+
+    // Record deposit for anonymity set tracking
+    pool.record_deposit();
+    pool.record_shielded(1)?;
+    record_deposit_stats(&ctx.accounts.pool_stats, 1, 1, pool.deposit_count)?;
+
+    msg!("Shielded compressed NFT {} at index {}", asset, leaf_index);
+    msg!("Pool deposit count: {}", pool.deposit_count);
+    msg!("New root: {:?}", pool.current_root());
+
+    Ok(())
+}
+
+/// Check that a shield transfer delivered exactly `amount` to the vault
+fn check_vault_received(
+    vault_token_account: &mut InterfaceAccount<'_, TokenAccount>,
+    balance_before: u64,
+    amount: u64,
+) -> Result<()> {
+    vault_token_account.reload()?;
+    pool_token::require_received(balance_before, vault_token_account.amount, amount)
+}
+
+/// Check a shield batch against the pool and return the total deposit
+///
+/// Every commitment must be a canonical field element, every amount must
+/// satisfy the pool's denomination, and the tree must have room for all
+/// of them, so nothing is inserted unless the whole batch fits.
+fn validate_shield_batch(pool: &PrivacyPool, data: &ShieldBatchData) -> Result<u64> {
+    data.validate()?;
+    for commitment in data.commitments.iter() {
+        require!(
+            verification::is_canonical_field_element(commitment),
+            NyxError::NonCanonicalFieldElement
+        );
+    }
+    require!(
+        data.amounts.iter().all(|&amount| pool.validate_amount(amount)),
+        NyxError::InvalidDenomination
+    );
+    require!(
+        pool.can_insert(data.commitments.len() as u64),
+        NyxError::PoolFull
+    );
+    data.total_amount().ok_or_else(|| error!(NyxError::InvalidAmount))
+}
+
+/// Remaining accounts following any compression accounts
+///
+/// Batch shields pass their commitment records here and cNFT shields the
+/// leaf's Merkle proof nodes.
+fn accounts_after_compression<'a, 'info>(
+    pool: &PrivacyPool,
+    remaining_accounts: &'a [AccountInfo<'info>],
+) -> &'a [AccountInfo<'info>] {
+    let offset = match pool.tree_backend() {
+        TreeBackend::Incremental => 0,
+        TreeBackend::Compressed => compression::COMPRESSION_ACCOUNTS_LEN,
+    };
+    remaining_accounts.get(offset..).unwrap_or(&[])
+}
+
+/// Process Shield SOL batch instruction
+pub fn process_shield_sol_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ShieldSolBatch<'info>>,
+    commitments: Vec<[u8; 32]>,
+    amounts: Vec<u64>,
+    encrypted_notes: Vec<Vec<u8>>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, mut root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_not_paused()?;
+    pool.require_not_closing()?;
+    pool.require_sol_pool()?;
+    ctx.accounts.protocol_config.require_not_paused()?;
+    cpi_guard::require_shield_caller(&pool, ctx.accounts.instructions_sysvar.as_ref())?;
+
+    // Validate
+    let data = ShieldBatchData {
+        commitments,
+        amounts,
+        encrypted_notes,
+    };
+    let total = validate_shield_batch(&pool, &data)?;
+    pool.check_deposit_cap(ctx.accounts.vault.lamports(), total)?;
+
+    // Transfer the whole batch from depositor to vault at once
+    let cpi_context = CpiContext::new(
+        ctx.accounts.system_program.to_account_info(),
+        system_program::Transfer {
+            from: ctx.accounts.depositor.to_account_info(),
+            to: ctx.accounts.vault.to_account_info(),
+        },
+    );
+    system_program::transfer(cpi_context, total)?;
+
+    // Add commitments to tree
+    let (first_index, inserted) = insert_commitments(
+        ctx.program_id,
+        &mut pool,
+        &mut root_history,
+        &pool_key,
+        &data.commitments,
+        ctx.accounts.commitment_chunk.as_ref(),
+        ctx.accounts.next_commitment_chunk.as_ref(),
+        ctx.accounts.tree_archive.as_ref(),
+        &ctx.accounts.depositor.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+    emit_inserted!(ctx, inserted);
+
+    // Create the commitment records, rejecting commitments shielded before
+    commitment_record::create_commitment_records(
+        ctx.program_id,
+        &ctx.accounts.depositor.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        accounts_after_compression(&pool, ctx.remaining_accounts),
+        &pool_key,
+        pool.tree_epoch,
+        first_index,
+        &data.commitments,
+    )?;
+    for (leaf_index, (commitment, note)) in
+        (first_index..).zip(data.commitments.iter().zip(data.encrypted_notes))
+    {
+        let event = note_event(&pool_key, pool.tree_epoch, *commitment, leaf_index, Some(note))?;
+        emit_all!(ctx, event);
+    }
+    let shields = shield_events(
+        &pool_key,
+        &pool,
+        pool.mint,
+        ctx.accounts.depositor.key(),
+        first_index,
+        data.commitments.iter().copied().zip(data.amounts.iter().copied()),
+    )?;
+    emit_all!(ctx, shields);
+    for _ in data.commitments.iter() {
+        pool.record_deposit();
+    }
+    pool.record_shielded(total)?;
+    record_deposit_stats(
+        &ctx.accounts.pool_stats,
+        data.commitments.len() as u64,
+        total,
+        pool.deposit_count,
+    )?;
+
+    msg!(
+        "Shielded {} lamports into {} commitments from index {}",
+        total,
+        data.commitments.len(),
+        first_index
+    );
+    msg!("Pool deposit count: {}", pool.deposit_count);
+    msg!("New root: {:?}", pool.current_root());
+
+    Ok(())
+}
+
+/// Process Shield SPL token batch instruction
+pub fn process_shield_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ShieldBatch<'info>>,
+    commitments: Vec<[u8; 32]>,
+    amounts: Vec<u64>,
+    encrypted_notes: Vec<Vec<u8>>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, mut root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_not_paused()?;
+    pool.require_not_closing()?;
+    pool.require_spl_pool()?;
+    ctx.accounts.protocol_config.require_not_paused()?;
+    cpi_guard::require_shield_caller(&pool, ctx.accounts.instructions_sysvar.as_ref())?;
+    ctx.accounts.mint_allowlist.require_allowed(&pool.mint)?;
+
+    // Validate
+    let data = ShieldBatchData {
+        commitments,
+        amounts,
+        encrypted_notes,
+    };
+    let total = validate_shield_batch(&pool, &data)?;
+    pool.check_deposit_cap(ctx.accounts.vault_token_account.amount, total)?;
+
+    // Transfer the whole batch from depositor to vault at once
+    let balance_before = ctx.accounts.vault_token_account.amount;
+    let cpi_accounts = TransferChecked {
+        from: ctx.accounts.depositor_token_account.to_account_info(),
+        mint: ctx.accounts.mint.to_account_info(),
+        to: ctx.accounts.vault_token_account.to_account_info(),
+        authority: ctx.accounts.depositor.to_account_info(),
+    };
+    let cpi_context = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        cpi_accounts,
+    );
+    token_interface::transfer_checked(cpi_context, total, ctx.accounts.mint.decimals)?;
+    check_vault_received(&mut ctx.accounts.vault_token_account, balance_before, total)?;
+
+    // Add commitments to tree
+    let (first_index, inserted) = insert_commitments(
+        ctx.program_id,
+        &mut pool,
+        &mut root_history,
+        &pool_key,
+        &data.commitments,
+        ctx.accounts.commitment_chunk.as_ref(),
+        ctx.accounts.next_commitment_chunk.as_ref(),
+        ctx.accounts.tree_archive.as_ref(),
+        &ctx.accounts.depositor.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+    emit_inserted!(ctx, inserted);
+
+    // Create the commitment records, rejecting commitments shielded before
+    commitment_record::create_commitment_records(
+        ctx.program_id,
+        &ctx.accounts.depositor.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        accounts_after_compression(&pool, ctx.remaining_accounts),
+        &pool_key,
+        pool.tree_epoch,
+        first_index,
+        &data.commitments,
+    )?;
+    for (leaf_index, (commitment, note)) in
+        (first_index..).zip(data.commitments.iter().zip(data.encrypted_notes))
+    {
+        let event = note_event(&pool_key, pool.tree_epoch, *commitment, leaf_index, Some(note))?;
+        emit_all!(ctx, event);
+    }
+    let shields = shield_events(
+        &pool_key,
+        &pool,
+        pool.mint,
+        ctx.accounts.depositor.key(),
+        first_index,
+        data.commitments.iter().copied().zip(data.amounts.iter().copied()),
+    )?;
+    emit_all!(ctx, shields);
+    for _ in data.commitments.iter() {
+        pool.record_deposit();
+    }
+    pool.record_shielded(total)?;
+    record_deposit_stats(
+        &ctx.accounts.pool_stats,
+        data.commitments.len() as u64,
+        total,
+        pool.deposit_count,
+    )?;
+
+    msg!(
+        "Shielded {} tokens into {} commitments from index {}",
+        total,
+        data.commitments.len(),
+        first_index
+    );
+    msg!("Pool deposit count: {}", pool.deposit_count);
+    msg!("New root: {:?}", pool.current_root());
+
+    Ok(())
+}
+
+/// Process InitCommitmentChunk instruction
+///
+/// Chunks can only be created for the current tree epoch.
+pub fn process_init_commitment_chunk(
+    ctx: Context<InitCommitmentChunk>,
+    chunk_index: u32,
+) -> Result<()> {
+    require!(
+        chunk_index < CommitmentChunk::CHUNKS_PER_TREE,
+        NyxError::InvalidCommitmentChunk
+    );
+    let pool = ctx.accounts.pool.load()?;
+    let mut chunk = ctx.accounts.commitment_chunk.load_init()?;
+    chunk.initialize(ctx.accounts.pool.key(), pool.tree_epoch, chunk_index);
+
+    msg!(
+        "Commitment chunk {} created for tree epoch {}",
+        chunk_index,
+        pool.tree_epoch
+    );
+    Ok(())
+}
+
+/// Process InitNullifierBloom instruction
+pub fn process_init_nullifier_bloom(ctx: Context<InitNullifierBloom>) -> Result<()> {
+    let mut bloom = ctx.accounts.nullifier_bloom.load_init()?;
+    bloom.initialize(ctx.accounts.pool.key(), ctx.bumps.nullifier_bloom);
+
+    msg!("Nullifier bloom filter created");
+    Ok(())
+}
+
+/// Process InitPoolStats instruction
+pub fn process_init_pool_stats(ctx: Context<InitPoolStats>) -> Result<()> {
+    let deposit_count = ctx.accounts.pool.load()?.deposit_count;
+    let mut pool_stats = ctx.accounts.pool_stats.load_init()?;
+    pool_stats.initialize(ctx.accounts.pool.key(), deposit_count, ctx.bumps.pool_stats);
+
+    msg!("Pool stats created");
+    Ok(())
+}
+
+/// Process InitNullifierTree instruction
+pub fn process_init_nullifier_tree(ctx: Context<InitNullifierTree>) -> Result<()> {
+    let mut tree = ctx.accounts.nullifier_tree.load_init()?;
+    tree.initialize(ctx.accounts.pool.key(), ctx.bumps.nullifier_tree)?;
+
+    msg!("Nullifier tree created");
+    Ok(())
+}
+
+/// Process InsertNullifierTree instruction
+pub fn process_insert_nullifier_tree(
+    ctx: Context<InsertNullifierTree>,
+    low_leaf: IndexedLeaf,
+    low_index: u64,
+    low_path: Vec<[u8; 32]>,
+) -> Result<()> {
+    let mut tree = ctx.accounts.nullifier_tree.load_mut()?;
+    let leaf_index = tree.insert_next(&low_leaf, low_index, &low_path)?;
+
+    msg!(
+        "Nullifier inserted at leaf {}, {} still queued",
+        leaf_index,
+        tree.queue_len
+    );
+    Ok(())
+}
+
+/// Process BackfillNullifierTree instruction
+///
+/// Only nullifiers the pool has spent get in, so the tree never holds a
+/// value a note could still be spent with.
+pub fn process_backfill_nullifier_tree(
+    ctx: Context<BackfillNullifierTree>,
+    nullifier: [u8; 32],
+    low_leaf: IndexedLeaf,
+    low_index: u64,
+    low_path: Vec<[u8; 32]>,
+) -> Result<()> {
+    require!(
+        nullifier::is_nullifier_in_set(
+            ctx.program_id,
+            &ctx.accounts.pool.key(),
+            &nullifier,
+            &ctx.accounts.nullifier_set,
+        )?,
+        NyxError::NullifierNotSpent
+    );
+    let mut tree = ctx.accounts.nullifier_tree.load_mut()?;
+    let leaf_index = tree.backfill(nullifier, &low_leaf, low_index, &low_path)?;
+
+    msg!("Nullifier backfilled at leaf {}", leaf_index);
+    Ok(())
+}
+
+/// Process EnableCompressedTree instruction
+///
+/// Only a pool that has never inserted a commitment can switch, so no note
+/// is left behind in the in-account tree.
+///
+/// Refused until the spend circuits prove keccak paths: spends check
+/// `require_poseidon_tree`, so notes shielded into a compressed pool would
+/// be locked in it.
+pub fn process_enable_compressed_tree(
+    ctx: Context<EnableCompressedTree>,
+    max_depth: u32,
+    max_buffer_size: u32,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+
+    require!(compression::COMPRESSED_SPENDS_SUPPORTED, NyxError::UnsupportedTreeBackend);
+    pool.require_poseidon_tree()?;
+    require!(
+        pool.commitment_count() == 0 && pool.tree_epoch == 0,
+        NyxError::PoolNotEmpty
+    );
+    require!(
+        (compression::MIN_COMPRESSED_TREE_DEPTH..=compression::MAX_COMPRESSED_TREE_DEPTH)
+            .contains(&max_depth),
+        NyxError::InvalidTreeDepth
+    );
+
+    let authority_bump = ctx.bumps.tree_authority;
+    let accounts = CompressionAccounts {
+        merkle_tree: &ctx.accounts.merkle_tree,
+        tree_authority: &ctx.accounts.tree_authority,
+        noop_program: &ctx.accounts.noop_program,
+        compression_program: &ctx.accounts.compression_program,
+    };
+    accounts.init_empty_merkle_tree(&pool_key, authority_bump, max_depth, max_buffer_size)?;
+
+    let empty_root = {
+        let tree_data = ctx.accounts.merkle_tree.try_borrow_data()?;
+        compression::current_root(&tree_data).ok_or(NyxError::InvalidCompressionAccounts)?
+    };
+    pool.enable_compressed_tree(
+        ctx.accounts.merkle_tree.key(),
+        max_depth,
+        authority_bump,
+        empty_root,
+    );
+
+    msg!("Pool switched to compressed tree {}", ctx.accounts.merkle_tree.key());
+    msg!("Capacity: {} commitments", pool.tree_capacity());
+    msg!("New root: {:?}", pool.current_root());
+
+    Ok(())
+}
+
+/// Process RolloverTree instruction
+///
+/// Archives the final root of the full tree under the current epoch and
+/// resets the pool to an empty tree, so shielding can continue.
+pub fn process_rollover_tree(ctx: Context<RolloverTree>) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, mut root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    let clock = Clock::get()?;
+
+    require!(
+        pool.tree_backend() == TreeBackend::Incremental,
+        NyxError::UnsupportedTreeBackend
+    );
+    require!(pool.is_tree_full(), NyxError::TreeNotFull);
+
+    let bump = ctx.bumps.tree_epoch;
+    let archive = rollover::archive_tree(&mut pool, &mut root_history, &pool_key, clock.slot, bump);
+    let (epoch, leaf_count, final_root) = (archive.epoch, archive.leaf_count, archive.root);
+    ctx.accounts.tree_epoch.set_inner(archive);
+
+    emit_cpi!(RootUpdated {
+        pool: pool_key,
+        tree_epoch: pool.tree_epoch,
+        old_root: final_root,
+        new_root: pool.current_root(),
+        leaf_count: pool.commitment_count(),
+        slot: clock.slot,
+    });
+
+    msg!("Tree epoch {} archived with {} leaves", epoch, leaf_count);
+    msg!("Archived root: {:?}", final_root);
+    msg!("New root: {:?}", pool.current_root());
+
+    Ok(())
+}
+
+/// Process CloseNullifierMarker instruction
+///
+/// Anchor's `close` constraint moves the marker's rent to the treasury once
+/// the nullifier is in the nullifier set and the bloom filter.
+pub fn process_close_nullifier_marker(ctx: Context<CloseNullifierMarker>) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let finality_slots = ctx.accounts.pool.load()?.marker_finality_slots;
+    let marker = &ctx.accounts.nullifier_marker;
+    let clock = Clock::get()?;
+
+    require!(
+        clock.slot >= marker.spent_at.saturating_add(finality_slots),
+        NyxError::MarkerNotFinal
+    );
+
+    // Mirror the nullifier first so closing the marker can't unspend it
+    nullifier::insert_nullifier(
+        ctx.program_id,
+        &pool_key,
+        &marker.nullifier,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    ctx.accounts.nullifier_bloom.load_mut()?.insert(&marker.nullifier);
+
+    msg!("Nullifier marker closed after mirroring into the nullifier set");
+    Ok(())
+}
+
+/// Process VerifyMembership instruction
+pub fn process_verify_membership(
+    ctx: Context<VerifyMembership>,
+    root: [u8; 32],
+    leaf: [u8; 32],
+    leaf_index: u64,
+    path: Vec<[u8; 32]>,
+) -> Result<()> {
+    let (pool, root_history) = state::load_pool(&ctx.accounts.pool)?;
+
+    // Compressed trees hash with keccak; paths here are Poseidon
+    pool.require_poseidon_tree()?;
+    let root_slot = pool.root_slot_of(&root, &root_history).ok_or(NyxError::StaleRoot)?;
+    let slot = Clock::get()?.slot;
+    match ctx.accounts.commitment_record.as_deref() {
+        Some(record) => maturity::require_mature_leaf(&pool, record, leaf_index, slot)?,
+        None => maturity::require_mature_root(&pool, &root, root_slot, &root_history, slot)?,
+    }
+    require!(
+        leaf_index < pool.commitment_count(),
+        NyxError::InvalidMerkleProof
+    );
+
+    let valid = if path.len() == TREE_DEPTH {
+        let siblings: [[u8; 32]; TREE_DEPTH] = path
+            .try_into()
+            .map_err(|_| NyxError::InvalidMerkleProof)?;
+        merkle::verify_merkle_proof(&leaf, leaf_index, &siblings, &root)
+    } else if path.len() == TREE_DEPTH - CANOPY_DEPTH && root == pool.current_root() {
+        merkle::verify_merkle_proof_with_canopy(&leaf, leaf_index, &path, &pool.canopy, &root)
+    } else {
+        false
+    };
+    require!(valid, NyxError::InvalidMerkleProof);
+
+    msg!("Leaf {} is in the pool's tree", leaf_index);
+    Ok(())
+}
+
+/// Process SnapshotTree instruction
+pub fn process_snapshot_tree(ctx: Context<SnapshotTree>) -> Result<TreeSnapshot> {
+    let (pool, root_history) = state::load_pool(&ctx.accounts.pool)?;
+    let snapshot = TreeSnapshot::from_pool(ctx.accounts.pool.key(), &pool, &root_history);
+
+    msg!(
+        "Tree snapshot: epoch {}, {} leaves",
+        snapshot.tree_epoch,
+        snapshot.next_index
+    );
+    Ok(snapshot)
+}
+
+/// Process Transfer instruction
+pub fn process_transfer(
+    ctx: Context<Transfer>,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    new_commitment: [u8; 32],
+    expiry_slot: u64,
+    proof: Vec<u8>,
+    encrypted_note: Option<Vec<u8>>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, mut root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_not_paused()?;
+    ctx.accounts.protocol_config.require_not_paused()?;
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    let clock = Clock::get()?;
+
+    // Validate proof format (Groth16, or MVP signature with the mock-prover feature)
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+    // Stale or leaked proofs can't be replayed after they expire
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
+
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, clock.slot)?;
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_transfer_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &new_commitment,
+        &root,
+        expiry_slot,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
+
+    // Record in pool stats
+    pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &nullifier)?;
+
+    // Add new commitment
+    let (leaf_index, inserted) = insert_commitments(
+        ctx.program_id,
+        &mut pool,
+        &mut root_history,
+        &pool_key,
+        &[new_commitment],
+        ctx.accounts.commitment_chunk.as_ref(),
+        None,
+        ctx.accounts.tree_archive.as_ref(),
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+    emit_inserted!(ctx, inserted);
+    let note = note_event(&pool_key, pool.tree_epoch, new_commitment, leaf_index, encrypted_note)?;
+    emit_all!(ctx, note);
+
+    emit_cpi!(TransferEvent {
+        pool: pool_key,
+        nullifiers: vec![nullifier],
+        commitments: vec![new_commitment],
+        output_pool: pool_key,
+        first_leaf_index: leaf_index,
+        root: pool.current_root(),
+        relayer: ctx.accounts.relayer.key(),
+        slot: clock.slot,
+    });
+    msg!("Private transfer complete");
+    msg!("New commitment at index {}", leaf_index);
+    msg!("Nullifier spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process JoinSplit instruction
+pub fn process_join_split(
+    ctx: Context<JoinSplit>,
+    nullifiers: [[u8; 32]; 2],
+    root: [u8; 32],
+    commitments: [[u8; 32]; 2],
+    expiry_slot: u64,
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, mut root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_not_paused()?;
+    ctx.accounts.protocol_config.require_not_paused()?;
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    let clock = Clock::get()?;
+
+    // Validate proof format (Groth16, or MVP signature with the mock-prover feature)
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
+    require!(nullifiers[0] != nullifiers[1], NyxError::DuplicateNullifier);
+    require!(pool.can_insert(2), NyxError::PoolFull);
+
+    // The claimed root must belong to the notes' tree and still be accepted
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, clock.slot)?;
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_join_split_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifiers,
+        &commitments,
+        &root,
+        expiry_slot,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add both nullifiers to the pool's nullifier set (fails if either was spent)
+    for (nullifier, marker, set) in [
+        (&nullifiers[0], &ctx.accounts.nullifier_marker_a, &ctx.accounts.nullifier_set_a),
+        (&nullifiers[1], &ctx.accounts.nullifier_marker_b, &ctx.accounts.nullifier_set_b),
+    ] {
+        let spent = nullifier::spend_nullifier(
+            ctx.program_id,
+            &pool_key,
+            nullifier,
+            marker,
+            set,
+            &ctx.accounts.relayer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        emit_cpi!(spent);
+        pool.record_nullifier_spent();
+        record_in_bloom(&ctx.accounts.nullifier_bloom, nullifier)?;
+        record_in_tree(&ctx.accounts.nullifier_tree, nullifier)?;
+    }
+
+    // Add the new commitments
+    let (leaf_index, inserted) = insert_commitments(
+        ctx.program_id,
+        &mut pool,
+        &mut root_history,
+        &pool_key,
+        &commitments,
+        ctx.accounts.commitment_chunk.as_ref(),
+        ctx.accounts.next_commitment_chunk.as_ref(),
+        ctx.accounts.tree_archive.as_ref(),
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+    emit_inserted!(ctx, inserted);
+
+    emit_cpi!(TransferEvent {
+        pool: pool_key,
+        nullifiers: nullifiers.to_vec(),
+        commitments: commitments.to_vec(),
+        output_pool: pool_key,
+        first_leaf_index: leaf_index,
+        root: pool.current_root(),
+        relayer: ctx.accounts.relayer.key(),
+        slot: clock.slot,
+    });
+    msg!("Join-split complete");
+    msg!("New commitments at indices {} and {}", leaf_index, leaf_index + 1);
+    msg!("Nullifiers spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process Unshield SOL instruction
+#[allow(clippy::too_many_arguments)]
+pub fn process_unshield_sol(
+    ctx: Context<UnshieldSol>,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    fee: u64,
+    refund: u64,
+    expiry_slot: u64,
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    pool.require_sol_pool()?;
+    let clock = Clock::get()?;
+    let recipient_key = ctx.accounts.recipient.key();
+    let relayer_key = ctx.accounts.relayer.key();
+
+    // Validate. Refunds only make sense for SPL withdrawals
+    require!(refund == 0, NyxError::InvalidRefund);
+    check_unshield(
+        &pool,
+        &root_history,
+        &ctx.accounts.tree_epoch,
+        &relayer_key,
+        ctx.accounts.relayer_bond.as_deref(),
+        &root,
+        amount,
+        fee,
+        &proof,
+        expiry_slot,
+        clock.slot,
+    )?;
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_unshield_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &recipient_key,
+        amount,
+        &relayer_key,
+        fee,
+        refund,
+        &root,
+        expiry_slot,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = spend_note(
+        ctx.program_id,
+        &mut pool,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &ctx.accounts.nullifier_bloom,
+        &ctx.accounts.nullifier_tree,
+    )?;
+    emit_cpi!(spent);
+
+    // Pay the recipient net of the fee from the vault PDA
+    let payout = sol_payout!(ctx);
+    let protocol_config = &ctx.accounts.protocol_config;
+    pay_sol_unshield(&pool_key, ctx.bumps.vault, &payout, protocol_config, amount, fee)?;
+    record_unshield(&mut pool, &ctx.accounts.pool_stats, amount, fee, clock.slot)?;
+    pool.check_solvency(ctx.accounts.vault.lamports())?;
+
+    emit_cpi!(UnshieldEvent {
+        pool: pool_key,
+        mint: pool.mint,
+        nullifier,
+        recipient: recipient_key,
+        amount,
+        fee,
+        relayer: relayer_key,
+        slot: clock.slot,
+    });
+    msg!("Unshielded {} lamports ({} fee)", amount, fee);
+    msg!("Nullifier spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process UnshieldSelf instruction
+///
+/// `process_unshield_sol` without a relayer: the recipient signs, pays
+/// for the nullifier set, and receives the full amount.
+pub fn process_unshield_self(
+    ctx: Context<UnshieldSelf>,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    expiry_slot: u64,
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    pool.require_single_asset()?;
+    // The recipient submits, so it must be a relayer the pool accepts
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.recipient.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    pool.require_anonymity_set()?;
+    let clock = Clock::get()?;
+
+    // Validate
+    require!(pool.mint == Pubkey::default(), pool_token::TokenError::MintMismatch);
+    require!(amount > 0, NyxError::InvalidAmount);
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
+
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, clock.slot)?;
+    let recipient_key = ctx.accounts.recipient.key();
+
+    // Verify the proof; the recipient is its own relayer, with no fee
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_unshield_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &recipient_key,
+        amount,
+        &recipient_key,
+        0,
+        0,
+        &root,
+        expiry_slot,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.recipient.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
+
+    // Record in pool stats
+    pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &nullifier)?;
+
+    // Transfer SOL from vault PDA to recipient using invoke_signed
+    require!(
+        ctx.accounts.vault.lamports() >= amount,
+        pool_token::TokenError::InsufficientFunds
+    );
+    let vault_bump = ctx.bumps.vault;
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[vault_bump],
+    ]];
+    pay_from_vault(
+        &ctx.accounts.vault.to_account_info(),
+        &ctx.accounts.recipient.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        amount,
+        signer_seeds,
+    )?;
+    pool.record_unshielded(amount)?;
+    pool.withdrawal_limit.record(clock.slot, amount, 1)?;
+    pool.check_solvency(ctx.accounts.vault.lamports())?;
+    record_withdrawal_stats(&ctx.accounts.pool_stats, 1, amount, 0)?;
+
+    emit_cpi!(UnshieldEvent {
+        pool: pool_key,
+        mint: pool.mint,
+        nullifier,
+        recipient: recipient_key,
+        amount,
+        fee: 0,
+        relayer: recipient_key,
+        slot: clock.slot,
+    });
+    msg!("Unshielded {} lamports without a relayer", amount);
+    msg!("Nullifier spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process UnshieldSolWithChange instruction
+///
+/// Like `process_unshield_sol`, but the spent note may hold more than
+/// `amount`: the proof also commits to a change note, which is inserted
+/// back into the tree. Only `amount` leaves the vault, so the change stays
+/// backed by the pool's balance.
+#[allow(clippy::too_many_arguments)]
+pub fn process_unshield_sol_with_change(
+    ctx: Context<UnshieldSolWithChange>,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    fee: u64,
+    refund: u64,
+    expiry_slot: u64,
+    change_commitment: [u8; 32],
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, mut root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    pool.require_sol_pool()?;
+    let clock = Clock::get()?;
+    let recipient_key = ctx.accounts.recipient.key();
+    let relayer_key = ctx.accounts.relayer.key();
+
+    // Change notes would break a fixed pool's uniform note amounts
+    require!(!pool.is_fixed_denomination(), NyxError::InvalidDenomination);
+    require!(pool.can_insert(1), NyxError::PoolFull);
+
+    // Validate. Refunds only make sense for SPL withdrawals
+    require!(refund == 0, NyxError::InvalidRefund);
+    check_unshield(
+        &pool,
+        &root_history,
+        &ctx.accounts.tree_epoch,
+        &relayer_key,
+        ctx.accounts.relayer_bond.as_deref(),
+        &root,
+        amount,
+        fee,
+        &proof,
+        expiry_slot,
+        clock.slot,
+    )?;
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_unshield_with_change_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &recipient_key,
+        amount,
+        &relayer_key,
+        fee,
+        refund,
+        &root,
+        expiry_slot,
+        &change_commitment,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = spend_note(
+        ctx.program_id,
+        &mut pool,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &ctx.accounts.nullifier_bloom,
+        &ctx.accounts.nullifier_tree,
+    )?;
+    emit_cpi!(spent);
+
+    // Return the unspent remainder to the owner as a new note
+    let (change_index, inserted) = insert_commitments(
+        ctx.program_id,
+        &mut pool,
+        &mut root_history,
+        &pool_key,
+        &[change_commitment],
+        ctx.accounts.commitment_chunk.as_ref(),
+        None,
+        ctx.accounts.tree_archive.as_ref(),
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+    emit_inserted!(ctx, inserted);
+
+    // Pay the recipient net of the fee from the vault PDA
+    let payout = sol_payout!(ctx);
+    let protocol_config = &ctx.accounts.protocol_config;
+    pay_sol_unshield(&pool_key, ctx.bumps.vault, &payout, protocol_config, amount, fee)?;
+    record_unshield(&mut pool, &ctx.accounts.pool_stats, amount, fee, clock.slot)?;
+    pool.check_solvency(ctx.accounts.vault.lamports())?;
+
+    emit_cpi!(UnshieldEvent {
+        pool: pool_key,
+        mint: pool.mint,
+        nullifier,
+        recipient: recipient_key,
+        amount,
+        fee,
+        relayer: relayer_key,
+        slot: clock.slot,
+    });
+    msg!("Unshielded {} lamports ({} fee)", amount, fee);
+    msg!("Change note inserted at index {}", change_index);
+    msg!("Nullifier spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process UnshieldAggregated instruction
+///
+/// Verifies one proof for the whole batch, then creates each withdrawal's
+/// nullifier marker and pays its recipient from the vault.
+pub fn process_unshield_aggregated<'info>(
+    ctx: Context<'_, '_, 'info, 'info, UnshieldAggregated<'info>>,
+    withdrawals: Vec<AggregatedWithdrawal>,
+    root: [u8; 32],
+    expiry_slot: u64,
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    pool.require_sol_pool()?;
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    pool.require_anonymity_set()?;
+    let clock = Clock::get()?;
+
+    // Validate
+    let data = UnshieldAggregatedData { withdrawals, root, expiry_slot, proof };
+    data.validate()?;
+    let UnshieldAggregatedData { withdrawals, proof, .. } = data;
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
+    let remaining = ctx.remaining_accounts;
+    require!(
+        remaining.len() == withdrawals.len() * 3,
+        NyxError::InvalidAggregatedAccounts
+    );
+
+    let total = withdrawals
+        .iter()
+        .try_fold(0u64, |sum, withdrawal| sum.checked_add(withdrawal.amount))
+        .ok_or(NyxError::InvalidAmount)?;
+    for withdrawal in &withdrawals {
+        require!(
+            withdrawal.fee <= pool.calculate_relayer_fee(withdrawal.amount),
+            NyxError::FeeAboveRelayerRate
+        );
+    }
+    require!(
+        ctx.accounts.vault.lamports() >= total,
+        pool_token::TokenError::InsufficientFunds
+    );
+
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, clock.slot)?;
+    let recipients: Vec<Pubkey> = remaining.iter().step_by(3).map(|info| info.key()).collect();
+    let relayer_key = ctx.accounts.relayer.key();
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_aggregated_unshield_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &withdrawals,
+        &recipients,
+        &relayer_key,
+        &root,
+        expiry_slot,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    let vault_bump = ctx.bumps.vault;
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[vault_bump],
+    ]];
+
+    let vault = ctx.accounts.vault.to_account_info();
+    let system_program = ctx.accounts.system_program.to_account_info();
+    let mut total_fee = 0u64;
+    let mut bloom =
+        ctx.accounts.nullifier_bloom.as_ref().map(|bloom| bloom.load_mut()).transpose()?;
+    let mut nullifier_tree =
+        ctx.accounts.nullifier_tree.as_ref().map(|tree| tree.load_mut()).transpose()?;
+    for (withdrawal, accounts) in withdrawals.iter().zip(remaining.chunks(3)) {
+        let (recipient, marker_info, set_info) = (&accounts[0], &accounts[1], &accounts[2]);
+
+        // Fails if the nullifier was already spent
+        let spent = nullifier::spend_nullifier(
+            ctx.program_id,
+            &pool_key,
+            &withdrawal.nullifier,
+            marker_info,
+            set_info,
+            &ctx.accounts.relayer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        emit_cpi!(spent);
+        pool.record_nullifier_spent();
+        if let Some(bloom) = bloom.as_mut() {
+            bloom.insert(&withdrawal.nullifier);
+        }
+        if let Some(tree) = nullifier_tree.as_mut() {
+            tree.enqueue(withdrawal.nullifier)?;
+        }
+
+        pay_from_vault(
+            &vault,
+            recipient,
+            &system_program,
+            withdrawal.amount - withdrawal.fee,
+            signer_seeds,
+        )?;
+        emit_cpi!(UnshieldEvent {
+            pool: pool_key,
+            mint: pool.mint,
+            nullifier: withdrawal.nullifier,
+            recipient: recipient.key(),
+            amount: withdrawal.amount,
+            fee: withdrawal.fee,
+            relayer: relayer_key,
+            slot: clock.slot,
+        });
+        total_fee += withdrawal.fee;
+    }
+
+    // Pay the batch's fees in one transfer each to the relayer and treasury
+    let (relayer_fee, treasury_fee) = ctx.accounts.protocol_config.split_relayer_fee(total_fee);
+    let relayer = ctx.accounts.relayer.to_account_info();
+    pay_from_vault(&vault, &relayer, &system_program, relayer_fee, signer_seeds)?;
+    let treasury = ctx.accounts.treasury.to_account_info();
+    pay_from_vault(&vault, &treasury, &system_program, treasury_fee, signer_seeds)?;
+    pool.record_fee_collected(total_fee);
+    pool.record_unshielded(total)?;
+    pool.withdrawal_limit.record(clock.slot, total, withdrawals.len() as u64)?;
+    pool.check_solvency(ctx.accounts.vault.lamports())?;
+    record_withdrawal_stats(
+        &ctx.accounts.pool_stats,
+        withdrawals.len() as u64,
+        total,
+        total_fee,
+    )?;
+
+    msg!("Unshielded {} lamports across {} withdrawals", total, withdrawals.len());
+    msg!("Nullifiers spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process UnshieldMany instruction
+///
+/// Verifies one proof for every payout, spends the note's nullifier, then
+/// pays each recipient its amount and the fee on top to the relayer and
+/// treasury.
+#[allow(clippy::too_many_arguments)]
+pub fn process_unshield_many<'info>(
+    ctx: Context<'_, '_, 'info, 'info, UnshieldMany<'info>>,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amounts: Vec<u64>,
+    fee: u64,
+    expiry_slot: u64,
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    pool.require_sol_pool()?;
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    pool.require_anonymity_set()?;
+    let clock = Clock::get()?;
+
+    // Validate
+    require!(
+        !amounts.is_empty() && amounts.len() <= verification::MAX_UNSHIELD_RECIPIENTS,
+        NyxError::InvalidBatchSize
+    );
+    require!(amounts.iter().all(|&amount| amount > 0), NyxError::InvalidAmount);
+    let recipients = ctx.remaining_accounts;
+    require!(recipients.len() == amounts.len(), NyxError::InvalidRecipientAccounts);
+    // The note holds every payout plus the fee
+    let total = amounts
+        .iter()
+        .try_fold(fee, |sum, &amount| sum.checked_add(amount))
+        .ok_or(NyxError::InvalidAmount)?;
+    require!(fee <= pool.calculate_relayer_fee(total), NyxError::FeeAboveRelayerRate);
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
+    require!(
+        ctx.accounts.vault.lamports() >= total,
+        pool_token::TokenError::InsufficientFunds
+    );
+
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, clock.slot)?;
+    let recipient_keys: Vec<Pubkey> = recipients.iter().map(|info| info.key()).collect();
+    let relayer_key = ctx.accounts.relayer.key();
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_unshield_many_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &recipient_keys,
+        &amounts,
+        &relayer_key,
+        fee,
+        &root,
+        expiry_slot,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
+
+    // Record in pool stats
+    pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &nullifier)?;
+
+    let vault_bump = ctx.bumps.vault;
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[vault_bump],
+    ]];
+
+    // Pay every recipient, then the fee split between relayer and treasury
+    let vault = ctx.accounts.vault.to_account_info();
+    let system_program = ctx.accounts.system_program.to_account_info();
+    for (recipient, &amount) in recipients.iter().zip(&amounts) {
+        pay_from_vault(&vault, recipient, &system_program, amount, signer_seeds)?;
+    }
+    let (relayer_fee, treasury_fee) = ctx.accounts.protocol_config.split_relayer_fee(fee);
+    let relayer = ctx.accounts.relayer.to_account_info();
+    pay_from_vault(&vault, &relayer, &system_program, relayer_fee, signer_seeds)?;
+    let treasury = ctx.accounts.treasury.to_account_info();
+    pay_from_vault(&vault, &treasury, &system_program, treasury_fee, signer_seeds)?;
+    pool.record_fee_collected(fee);
+    pool.record_unshielded(total)?;
+    pool.withdrawal_limit.record(clock.slot, total, 1)?;
+    pool.check_solvency(ctx.accounts.vault.lamports())?;
+    record_withdrawal_stats(&ctx.accounts.pool_stats, 1, total, fee)?;
+
+    // One event per recipient, the first counting the fee
+    for (index, (recipient, &amount)) in recipients.iter().zip(&amounts).enumerate() {
+        let fee = if index == 0 { fee } else { 0 };
+        emit_cpi!(UnshieldEvent {
+            pool: pool_key,
+            mint: pool.mint,
+            nullifier,
+            recipient: recipient.key(),
+            amount: amount + fee,
+            fee,
+            relayer: relayer_key,
+            slot: clock.slot,
+        });
+    }
+    msg!(
+        "Unshielded {} lamports to {} recipients ({} fee)",
+        total - fee,
+        amounts.len(),
+        fee
+    );
+    msg!("Nullifier spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process UnshieldBatch instruction
+///
+/// Verifies each withdrawal's own unshield proof, spends its nullifier and
+/// pays its recipient, then pays the batch's fees to the relayer and
+/// treasury in one transfer each.
+pub fn process_unshield_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, UnshieldBatch<'info>>,
+    withdrawals: Vec<BatchWithdrawal>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    pool.require_sol_pool()?;
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    pool.require_anonymity_set()?;
+    let clock = Clock::get()?;
+
+    // Validate
+    let data = UnshieldBatchData { withdrawals };
+    data.validate()?;
+    let UnshieldBatchData { withdrawals } = data;
+    let remaining = ctx.remaining_accounts;
+    require!(
+        remaining.len() == withdrawals.len() * 3,
+        NyxError::InvalidBatchAccounts
+    );
+
+    let total = withdrawals
+        .iter()
+        .try_fold(0u64, |sum, withdrawal| sum.checked_add(withdrawal.amount))
+        .ok_or(NyxError::InvalidAmount)?;
+    for withdrawal in &withdrawals {
+        require!(clock.slot <= withdrawal.expiry_slot, NyxError::ProofExpired);
+        require!(
+            withdrawal.fee <= pool.calculate_relayer_fee(withdrawal.amount),
+            NyxError::FeeAboveRelayerRate
+        );
+        // Each root must belong to the notes' tree and still be accepted
+        check_spend_root(
+            &pool,
+            &root_history,
+            &ctx.accounts.tree_epoch,
+            &withdrawal.root,
+            clock.slot,
+        )?;
+    }
+    require!(
+        ctx.accounts.vault.lamports() >= total,
+        pool_token::TokenError::InsufficientFunds
+    );
+
+    // Verify every proof before spending anything
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let relayer_key = ctx.accounts.relayer.key();
+    for (withdrawal, accounts) in withdrawals.iter().zip(remaining.chunks(3)) {
+        let valid = verification::verify_unshield_proof(
+            &verifier,
+            &withdrawal.proof,
+            &pool_key,
+            &pool.mint,
+            &withdrawal.nullifier,
+            &accounts[0].key(),
+            withdrawal.amount,
+            &relayer_key,
+            withdrawal.fee,
+            0,
+            &withdrawal.root,
+            withdrawal.expiry_slot,
+        )?;
+        require!(valid, NyxError::InvalidProof);
+    }
+
+    let vault_bump = ctx.bumps.vault;
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[vault_bump],
+    ]];
+
+    let vault = ctx.accounts.vault.to_account_info();
+    let system_program = ctx.accounts.system_program.to_account_info();
+    let mut total_fee = 0u64;
+    let mut bloom =
+        ctx.accounts.nullifier_bloom.as_ref().map(|bloom| bloom.load_mut()).transpose()?;
+    let mut nullifier_tree =
+        ctx.accounts.nullifier_tree.as_ref().map(|tree| tree.load_mut()).transpose()?;
+    for (withdrawal, accounts) in withdrawals.iter().zip(remaining.chunks(3)) {
+        let (recipient, marker_info, set_info) = (&accounts[0], &accounts[1], &accounts[2]);
+
+        // Fails if the nullifier was already spent
+        let spent = nullifier::spend_nullifier(
+            ctx.program_id,
+            &pool_key,
+            &withdrawal.nullifier,
+            marker_info,
+            set_info,
+            &ctx.accounts.relayer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        emit_cpi!(spent);
+        pool.record_nullifier_spent();
+        if let Some(bloom) = bloom.as_mut() {
+            bloom.insert(&withdrawal.nullifier);
+        }
+        if let Some(tree) = nullifier_tree.as_mut() {
+            tree.enqueue(withdrawal.nullifier)?;
+        }
+
+        pay_from_vault(
+            &vault,
+            recipient,
+            &system_program,
+            withdrawal.amount - withdrawal.fee,
+            signer_seeds,
+        )?;
+        emit_cpi!(UnshieldEvent {
+            pool: pool_key,
+            mint: pool.mint,
+            nullifier: withdrawal.nullifier,
+            recipient: recipient.key(),
+            amount: withdrawal.amount,
+            fee: withdrawal.fee,
+            relayer: relayer_key,
+            slot: clock.slot,
+        });
+        total_fee += withdrawal.fee;
+    }
+
+    // Pay the batch's fees in one transfer each to the relayer and treasury
+    let (relayer_fee, treasury_fee) = ctx.accounts.protocol_config.split_relayer_fee(total_fee);
+    let relayer = ctx.accounts.relayer.to_account_info();
+    pay_from_vault(&vault, &relayer, &system_program, relayer_fee, signer_seeds)?;
+    let treasury = ctx.accounts.treasury.to_account_info();
+    pay_from_vault(&vault, &treasury, &system_program, treasury_fee, signer_seeds)?;
+    pool.record_fee_collected(total_fee);
+    pool.record_unshielded(total)?;
+    pool.withdrawal_limit.record(clock.slot, total, withdrawals.len() as u64)?;
+    pool.check_solvency(ctx.accounts.vault.lamports())?;
+    record_withdrawal_stats(
+        &ctx.accounts.pool_stats,
+        withdrawals.len() as u64,
+        total,
+        total_fee,
+    )?;
+
+    msg!("Unshielded {} lamports across {} batched withdrawals", total, withdrawals.len());
+    msg!("Nullifiers spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process Unshield SPL token instruction
+#[allow(clippy::too_many_arguments)]
+pub fn process_unshield(
+    ctx: Context<Unshield>,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    fee: u64,
+    refund: u64,
+    expiry_slot: u64,
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    pool.require_spl_pool()?;
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    pool.require_anonymity_set()?;
+    let clock = Clock::get()?;
+
+    // Validate
+    require!(amount > 0, NyxError::InvalidAmount);
+    require!(fee <= amount, NyxError::FeeExceedsAmount);
+    // The fee may also reimburse the SOL the relayer fronts, if it fronts any
+    let created = pool_token::create_associated_token_account_if_missing(
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.recipient_token_account,
+        &ctx.accounts.recipient,
+        &ctx.accounts.mint.to_account_info(),
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.associated_token_program.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    require!(
+        fee <= pool.max_unshield_fee(amount, created || refund > 0),
+        NyxError::FeeAboveRelayerRate
+    );
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
+
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, clock.slot)?;
+    // For SPL tokens, the recipient owns the token account
+    let recipient_key = ctx.accounts.recipient.key();
+    let relayer_key = ctx.accounts.relayer.key();
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_unshield_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &recipient_key,
+        amount,
+        &relayer_key,
+        fee,
+        refund,
+        &root,
+        expiry_slot,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
+
+    // Record in pool stats
+    pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &nullifier)?;
+
+    // Transfer SPL tokens from vault to recipient
+    let vault_bump = ctx.bumps.vault_authority;
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[vault_bump],
+    ]];
+
+    // Pay the recipient net of the fee, split between relayer and treasury.
+    // The rent reimbursement is the relayer's alone
+    let (rate_fee, rent_fee) = pool.split_rent_fee(amount, fee);
+    let (relayer_fee, treasury_fee) = ctx.accounts.protocol_config.split_relayer_fee(rate_fee);
+    for (to, tokens) in [
+        (ctx.accounts.recipient_token_account.to_account_info(), amount - fee),
+        (ctx.accounts.relayer_token_account.to_account_info(), relayer_fee + rent_fee),
+        (ctx.accounts.treasury_token_account.to_account_info(), treasury_fee),
+    ] {
+        if tokens == 0 {
+            continue;
+        }
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.vault_token_account.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to,
+            authority: ctx.accounts.vault_authority.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        token_interface::transfer_checked(cpi_context, tokens, ctx.accounts.mint.decimals)?;
+    }
+    pool.record_fee_collected(fee);
+    pool.record_unshielded(amount)?;
+    pool.withdrawal_limit.record(clock.slot, amount, 1)?;
+    ctx.accounts.vault_token_account.reload()?;
+    pool.check_solvency(ctx.accounts.vault_token_account.amount)?;
+    record_withdrawal_stats(&ctx.accounts.pool_stats, 1, amount, fee)?;
+
+    // Forward the refund so the recipient can pay for its first transactions
+    if refund > 0 {
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.relayer.to_account_info(),
+                to: ctx.accounts.recipient.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, refund)?;
+    }
+
+    emit_cpi!(UnshieldEvent {
+        pool: pool_key,
+        mint: pool.mint,
+        nullifier,
+        recipient: recipient_key,
+        amount,
+        fee,
+        relayer: relayer_key,
+        slot: clock.slot,
+    });
+    msg!("Unshielded {} tokens ({} fee)", amount, fee);
+    msg!("Nullifier spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process UnshieldCpi instruction
+///
+/// `process_unshield` paying the destination authority of the call, which
+/// then signs the call into the destination program.
+#[allow(clippy::too_many_arguments)]
+pub fn process_unshield_cpi<'info>(
+    ctx: Context<'_, '_, 'info, 'info, UnshieldCpi<'info>>,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    fee: u64,
+    expiry_slot: u64,
+    proof: Vec<u8>,
+    data: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    pool.require_spl_pool()?;
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    pool.require_anonymity_set()?;
+    let clock = Clock::get()?;
+
+    // Validate
+    require!(amount > 0, NyxError::InvalidAmount);
+    require!(fee < amount, NyxError::FeeExceedsAmount);
+    // The fee may also reimburse the SOL the relayer fronts, if it fronts any
+    let created = pool_token::create_associated_token_account_if_missing(
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.destination_token_account,
+        &ctx.accounts.destination_authority,
+        &ctx.accounts.mint.to_account_info(),
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.associated_token_program.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    require!(fee <= pool.max_unshield_fee(amount, created), NyxError::FeeAboveRelayerRate);
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
+    let destination_program_key = ctx.accounts.destination_program.key();
+    ctx.accounts
+        .destination_registry
+        .require_allowed(&destination_program_key)?;
+
+    // The destination authority is the recipient, which pins the program,
+    // accounts and data the relayer must call with
+    let destination_authority_key = ctx.accounts.destination_authority.key();
+    let call_hash = destination::call_hash(
+        &destination_program_key,
+        ctx.remaining_accounts,
+        &[destination_authority_key, ctx.accounts.destination_token_account.key()],
+        &data,
+    );
+    let (expected_authority, authority_bump) =
+        destination::derive_destination_authority(ctx.program_id, &pool_key, &call_hash);
+    require_keys_eq!(
+        destination_authority_key,
+        expected_authority,
+        NyxError::InvalidDestinationCall
+    );
+
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, clock.slot)?;
+    let relayer_key = ctx.accounts.relayer.key();
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_unshield_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &destination_authority_key,
+        amount,
+        &relayer_key,
+        fee,
+        0,
+        &root,
+        expiry_slot,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
+
+    // Record in pool stats
+    pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &nullifier)?;
+
+    // Pay the destination authority net of the fee, split between relayer
+    // and treasury. The rent reimbursement is the relayer's alone
+    let vault_bump = ctx.bumps.vault_authority;
+    let vault_signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[vault_bump],
+    ]];
+    let (rate_fee, rent_fee) = pool.split_rent_fee(amount, fee);
+    let (relayer_fee, treasury_fee) = ctx.accounts.protocol_config.split_relayer_fee(rate_fee);
+    let balance_before =
+        pool_token::token_account_amount(&ctx.accounts.destination_token_account)?;
+    for (to, tokens) in [
+        (ctx.accounts.destination_token_account.to_account_info(), amount - fee),
+        (ctx.accounts.relayer_token_account.to_account_info(), relayer_fee + rent_fee),
+        (ctx.accounts.treasury_token_account.to_account_info(), treasury_fee),
+    ] {
+        if tokens == 0 {
+            continue;
+        }
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.vault_token_account.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to,
+            authority: ctx.accounts.vault_authority.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            vault_signer_seeds,
+        );
+        token_interface::transfer_checked(cpi_context, tokens, ctx.accounts.mint.decimals)?;
+    }
+    pool.record_fee_collected(fee);
+    pool.record_unshielded(amount)?;
+    pool.withdrawal_limit.record(clock.slot, amount, 1)?;
+    ctx.accounts.vault_token_account.reload()?;
+    pool.check_solvency(ctx.accounts.vault_token_account.amount)?;
+    record_withdrawal_stats(&ctx.accounts.pool_stats, 1, amount, fee)?;
+
+    // Call the destination, signing as the destination authority
+    let authority_signer_seeds: &[&[&[u8]]] = &[&[
+        destination::DESTINATION_AUTHORITY_SEED,
+        pool_key.as_ref(),
+        call_hash.as_ref(),
+        &[authority_bump],
+    ]];
+    destination::invoke_destination(
+        &ctx.accounts.destination_program,
+        ctx.remaining_accounts,
+        &destination_authority_key,
+        data,
+        authority_signer_seeds,
+    )?;
+
+    // The destination must take the whole payout
+    require!(
+        pool_token::token_account_amount(&ctx.accounts.destination_token_account)?
+            <= balance_before,
+        NyxError::InvalidDestinationCall
+    );
+
+    emit_cpi!(UnshieldEvent {
+        pool: pool_key,
+        mint: pool.mint,
+        nullifier,
+        recipient: destination_authority_key,
+        amount,
+        fee,
+        relayer: relayer_key,
+        slot: clock.slot,
+    });
+    msg!("Unshielded {} tokens into {} ({} fee)", amount, destination_program_key, fee);
+    msg!("Nullifier spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process ShieldedSwap instruction
+///
+/// Spends a note of the input pool to the swap authority, runs the route
+/// through the DEX, and shields `min_amount_out` of the output into the
+/// output pool, all before the transaction ends. The surplus goes to the
+/// output pool's treasury (see `swap`).
+#[allow(clippy::too_many_arguments)]
+pub fn process_shielded_swap<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ShieldedSwap<'info>>,
+    commitment: [u8; 32],
+    min_amount_out: u64,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    fee: u64,
+    expiry_slot: u64,
+    proof: Vec<u8>,
+    route_data: Vec<u8>,
+    encrypted_note: Option<Vec<u8>>,
+) -> Result<()> {
+    let input_pool_key = ctx.accounts.input_pool.key();
+    let output_pool_key = ctx.accounts.output_pool.key();
+    require_keys_neq!(input_pool_key, output_pool_key, NyxError::InvalidSwapPools);
+    let (mut input_pool, input_root_history) = state::load_pool_mut(&ctx.accounts.input_pool)?;
+    let (mut output_pool, mut output_root_history) =
+        state::load_pool_mut(&ctx.accounts.output_pool)?;
+    input_pool.require_unshields_allowed()?;
+    output_pool.require_not_paused()?;
+    output_pool.require_not_closing()?;
+    ctx.accounts.protocol_config.require_not_paused()?;
+    relayer::require_relayer(
+        &input_pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    ctx.accounts.mint_allowlist.require_allowed(&output_pool.mint)?;
+    input_pool.require_anonymity_set()?;
+    let clock = Clock::get()?;
+
+    // Validate the pools
+    require!(
+        input_pool.asset_mode() == AssetMode::Single
+            && output_pool.asset_mode() == AssetMode::Single
+            && input_pool.mint != output_pool.mint,
+        NyxError::InvalidSwapPools
+    );
+    // The route takes the remaining accounts, so there is no room for the
+    // compressed tree's accounts
+    require!(
+        output_pool.tree_backend() == TreeBackend::Incremental,
+        NyxError::UnsupportedTreeBackend
+    );
+
+    // Validate the spend
+    require!(amount > 0, NyxError::InvalidAmount);
+    require!(fee < amount, NyxError::FeeExceedsAmount);
+    require!(fee <= input_pool.calculate_relayer_fee(amount), NyxError::FeeAboveRelayerRate);
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
+
+    // Validate the deposit
+    require!(min_amount_out > 0, NyxError::InvalidAmount);
+    require!(
+        verification::is_canonical_field_element(&commitment),
+        NyxError::NonCanonicalFieldElement
+    );
+    require!(output_pool.can_insert(1), NyxError::PoolFull);
+    require!(output_pool.validate_amount(min_amount_out), NyxError::InvalidDenomination);
+    output_pool.check_deposit_cap(ctx.accounts.output_vault_token_account.amount, min_amount_out)?;
+    require!(
+        ctx.accounts.commitment_record.pool == Pubkey::default(),
+        NyxError::DuplicateCommitment
+    );
+
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(
+        &input_pool,
+        &input_root_history,
+        &ctx.accounts.tree_epoch,
+        &root,
+        clock.slot,
+    )?;
+    // The swap authority is the recipient, which pins the commitment and
+    // minimum output the relayer must use
+    let swap_authority_key = ctx.accounts.swap_authority.key();
+    let relayer_key = ctx.accounts.relayer.key();
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &input_pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_unshield_proof(
+        &verifier,
+        &proof,
+        &input_pool_key,
+        &input_pool.mint,
+        &nullifier,
+        &swap_authority_key,
+        amount,
+        &relayer_key,
+        fee,
+        0,
+        &root,
+        expiry_slot,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &input_pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
+
+    // Record in pool stats
+    input_pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &nullifier)?;
+
+    // Pay the swap authority net of the fee, split between relayer and treasury
+    let vault_bump = ctx.bumps.input_vault_authority;
+    let vault_signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        input_pool_key.as_ref(),
+        &[vault_bump],
+    ]];
+    let (relayer_fee, treasury_fee) = ctx.accounts.protocol_config.split_relayer_fee(fee);
+    for (to, tokens) in [
+        (ctx.accounts.swap_input_token_account.to_account_info(), amount - fee),
+        (ctx.accounts.relayer_token_account.to_account_info(), relayer_fee),
+        (ctx.accounts.treasury_token_account.to_account_info(), treasury_fee),
+    ] {
+        if tokens == 0 {
+            continue;
+        }
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.input_vault_token_account.to_account_info(),
+            mint: ctx.accounts.input_mint.to_account_info(),
+            to,
+            authority: ctx.accounts.input_vault_authority.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            vault_signer_seeds,
+        );
+        token_interface::transfer_checked(cpi_context, tokens, ctx.accounts.input_mint.decimals)?;
+    }
+    input_pool.record_fee_collected(fee);
+    input_pool.record_unshielded(amount)?;
+    input_pool.withdrawal_limit.record(clock.slot, amount, 1)?;
+    ctx.accounts.input_vault_token_account.reload()?;
+    input_pool.check_solvency(ctx.accounts.input_vault_token_account.amount)?;
+    record_withdrawal_stats(&ctx.accounts.input_pool_stats, 1, amount, fee)?;
+
+    // Run the route, signing as the swap authority
+    let swap_bump = ctx.bumps.swap_authority;
+    let min_amount_out_bytes = min_amount_out.to_le_bytes();
+    let swap_signer_seeds: &[&[&[u8]]] = &[&[
+        swap::SWAP_AUTHORITY_SEED,
+        output_pool_key.as_ref(),
+        commitment.as_ref(),
+        &min_amount_out_bytes,
+        &[swap_bump],
+    ]];
+    let output_before = ctx.accounts.swap_output_token_account.amount;
+    swap::invoke_route(
+        &ctx.accounts.dex_program,
+        ctx.remaining_accounts,
+        &swap_authority_key,
+        route_data,
+        swap_signer_seeds,
+    )?;
+    ctx.accounts.swap_input_token_account.reload()?;
+    ctx.accounts.swap_output_token_account.reload()?;
+    require!(
+        ctx.accounts.swap_input_token_account.amount == 0,
+        NyxError::SwapInputNotSpent
+    );
+    let amount_out = ctx
+        .accounts
+        .swap_output_token_account
+        .amount
+        .saturating_sub(output_before);
+    require!(amount_out >= min_amount_out, NyxError::SwapSlippageExceeded);
+
+    // Shield the minimum output and send the surplus to the treasury
+    let balance_before = ctx.accounts.output_vault_token_account.amount;
+    let (shielded, surplus) =
+        swap::split_output(ctx.accounts.swap_output_token_account.amount, min_amount_out)?;
+    for (to, tokens) in [
+        (ctx.accounts.output_vault_token_account.to_account_info(), shielded),
+        (ctx.accounts.treasury_output_token_account.to_account_info(), surplus),
+    ] {
+        if tokens == 0 {
+            continue;
+        }
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.swap_output_token_account.to_account_info(),
+            mint: ctx.accounts.output_mint.to_account_info(),
+            to,
+            authority: ctx.accounts.swap_authority.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            swap_signer_seeds,
+        );
+        token_interface::transfer_checked(cpi_context, tokens, ctx.accounts.output_mint.decimals)?;
+    }
+    check_vault_received(
+        &mut ctx.accounts.output_vault_token_account,
+        balance_before,
+        min_amount_out,
+    )?;
+
+    // Close the swap authority's token accounts, returning the rent
+    for account in [
+        ctx.accounts.swap_input_token_account.to_account_info(),
+        ctx.accounts.swap_output_token_account.to_account_info(),
+    ] {
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token_interface::CloseAccount {
+                account,
+                destination: ctx.accounts.relayer.to_account_info(),
+                authority: ctx.accounts.swap_authority.to_account_info(),
+            },
+            swap_signer_seeds,
+        );
+        token_interface::close_account(cpi_context)?;
+    }
+
+    // Add commitment to the output pool's tree
+    let (leaf_index, inserted) = insert_commitments(
+        ctx.program_id,
+        &mut output_pool,
+        &mut output_root_history,
+        &output_pool_key,
+        &[commitment],
+        Some(&ctx.accounts.commitment_chunk),
+        None,
+        ctx.accounts.tree_archive.as_ref(),
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &[],
+    )?;
+    emit_inserted!(ctx, inserted);
+
+    let note = note_event(
+        &output_pool_key,
+        output_pool.tree_epoch,
+        commitment,
+        leaf_index,
+        encrypted_note,
+    )?;
+    emit_all!(ctx, note);
+
+    // Record where the leaf landed
+    let record = &mut ctx.accounts.commitment_record;
+    record.pool = output_pool_key;
+    record.commitment = commitment;
+    record.tree_epoch = output_pool.tree_epoch;
+    record.leaf_index = leaf_index;
+    record.slot = clock.slot;
+    record.bump = ctx.bumps.commitment_record;
+
+    output_pool.record_deposit();
+    output_pool.record_shielded(min_amount_out)?;
+    record_deposit_stats(
+        &ctx.accounts.output_pool_stats,
+        1,
+        min_amount_out,
+        output_pool.deposit_count,
+    )?;
+
+    emit_cpi!(TransferEvent {
+        pool: input_pool_key,
+        nullifiers: vec![nullifier],
+        commitments: vec![commitment],
+        output_pool: output_pool_key,
+        first_leaf_index: leaf_index,
+        root: output_pool.current_root(),
+        relayer: ctx.accounts.relayer.key(),
+        slot: clock.slot,
+    });
+    msg!("Swapped {} input tokens for {} output tokens", amount - fee, amount_out);
+    msg!("Shielded {} tokens at index {}", min_amount_out, leaf_index);
+    msg!("Nullifier spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process ShieldedStake instruction
+///
+/// Spends a note of the SOL pool into a stake pool deposit made by the
+/// vault, and shields `pool_tokens` of the minted tokens into the output
+/// pool, whose vault they were minted to.
+#[allow(clippy::too_many_arguments)]
+pub fn process_shielded_stake(
+    ctx: Context<ShieldedStake>,
+    commitment: [u8; 32],
+    pool_tokens: u64,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    fee: u64,
+    expiry_slot: u64,
+    proof: Vec<u8>,
+    encrypted_note: Option<Vec<u8>>,
+) -> Result<()> {
+    let input_pool_key = ctx.accounts.input_pool.key();
+    let output_pool_key = ctx.accounts.output_pool.key();
+    require_keys_neq!(input_pool_key, output_pool_key, NyxError::InvalidStakePools);
+    let (mut input_pool, input_root_history) = state::load_pool_mut(&ctx.accounts.input_pool)?;
+    let (mut output_pool, mut output_root_history) =
+        state::load_pool_mut(&ctx.accounts.output_pool)?;
+    input_pool.require_unshields_allowed()?;
+    output_pool.require_not_paused()?;
+    output_pool.require_not_closing()?;
+    ctx.accounts.protocol_config.require_not_paused()?;
+    relayer::require_relayer(
+        &input_pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    ctx.accounts.mint_allowlist.require_allowed(&output_pool.mint)?;
+    input_pool.require_anonymity_set()?;
+    let clock = Clock::get()?;
+
+    // Validate the pools
+    require!(
+        input_pool.asset_mode() == AssetMode::Single
+            && input_pool.mint == Pubkey::default()
+            && output_pool.asset_mode() == AssetMode::Single,
+        NyxError::InvalidStakePools
+    );
+    require!(
+        output_pool.tree_backend() == TreeBackend::Incremental,
+        NyxError::UnsupportedTreeBackend
+    );
+
+    // Validate the spend
+    require!(amount > 0, NyxError::InvalidAmount);
+    require!(fee < amount, NyxError::FeeExceedsAmount);
+    require!(fee <= input_pool.calculate_relayer_fee(amount), NyxError::FeeAboveRelayerRate);
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
+    require!(
+        ctx.accounts.vault.lamports() >= amount,
+        pool_token::TokenError::InsufficientFunds
+    );
+
+    // Validate the deposit
+    require!(pool_tokens > 0, NyxError::InvalidAmount);
+    require!(
+        verification::is_canonical_field_element(&commitment),
+        NyxError::NonCanonicalFieldElement
+    );
+    require!(output_pool.can_insert(1), NyxError::PoolFull);
+    require!(output_pool.validate_amount(pool_tokens), NyxError::InvalidDenomination);
+    output_pool.check_deposit_cap(ctx.accounts.output_vault_token_account.amount, pool_tokens)?;
+    require!(
+        ctx.accounts.commitment_record.pool == Pubkey::default(),
+        NyxError::DuplicateCommitment
+    );
+
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(
+        &input_pool,
+        &input_root_history,
+        &ctx.accounts.tree_epoch,
+        &root,
+        clock.slot,
+    )?;
+    // The recipient pins the note the deposit is shielded under
+    let (recipient_key, _) =
+        stake::derive_deposit_authority(ctx.program_id, &output_pool_key, &commitment, pool_tokens);
+    let relayer_key = ctx.accounts.relayer.key();
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &input_pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_unshield_proof(
+        &verifier,
+        &proof,
+        &input_pool_key,
+        &input_pool.mint,
+        &nullifier,
+        &recipient_key,
+        amount,
+        &relayer_key,
+        fee,
+        0,
+        &root,
+        expiry_slot,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &input_pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
+
+    // Record in pool stats
+    input_pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &nullifier)?;
+
+    let vault_bump = ctx.bumps.vault;
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        input_pool_key.as_ref(),
+        &[vault_bump],
+    ]];
+
+    // Pay the fee, split between relayer and treasury
+    let (relayer_fee, treasury_fee) = ctx.accounts.protocol_config.split_relayer_fee(fee);
+    let vault = ctx.accounts.vault.to_account_info();
+    let system_program = ctx.accounts.system_program.to_account_info();
+    let relayer = ctx.accounts.relayer.to_account_info();
+    pay_from_vault(&vault, &relayer, &system_program, relayer_fee, signer_seeds)?;
+    let treasury = ctx.accounts.treasury.to_account_info();
+    pay_from_vault(&vault, &treasury, &system_program, treasury_fee, signer_seeds)?;
+
+    // Deposit the rest with the stake pool, minting into the output vault
+    let balance_before = ctx.accounts.output_vault_token_account.amount;
+    let output_mint = ctx.accounts.output_mint.to_account_info();
+    let output_vault_token_account = ctx.accounts.output_vault_token_account.to_account_info();
+    let token_program = ctx.accounts.token_program.to_account_info();
+    DepositSolAccounts {
+        stake_pool: &ctx.accounts.stake_pool,
+        withdraw_authority: &ctx.accounts.stake_pool_withdraw_authority,
+        reserve_stake: &ctx.accounts.reserve_stake,
+        lamports_from: &vault,
+        pool_tokens_to: &output_vault_token_account,
+        manager_fee_account: &ctx.accounts.manager_fee_account,
+        referrer_pool_tokens_account: &ctx.accounts.referrer_pool_tokens_account,
+        pool_mint: &output_mint,
+        system_program: &system_program,
+        token_program: &token_program,
+        stake_pool_program: &ctx.accounts.stake_pool_program,
+    }
+    .deposit_sol(amount - fee, signer_seeds)?;
+
+    input_pool.record_fee_collected(fee);
+    input_pool.record_unshielded(amount)?;
+    input_pool.withdrawal_limit.record(clock.slot, amount, 1)?;
+    input_pool.check_solvency(ctx.accounts.vault.lamports())?;
+    record_withdrawal_stats(&ctx.accounts.input_pool_stats, 1, amount, fee)?;
+
+    ctx.accounts.output_vault_token_account.reload()?;
+    let minted = ctx
+        .accounts
+        .output_vault_token_account
+        .amount
+        .saturating_sub(balance_before);
+    require!(minted >= pool_tokens, NyxError::StakeDepositTooSmall);
+
+    // Add commitment to the output pool's tree
+    let (leaf_index, inserted) = insert_commitments(
+        ctx.program_id,
+        &mut output_pool,
+        &mut output_root_history,
+        &output_pool_key,
+        &[commitment],
+        Some(&ctx.accounts.commitment_chunk),
+        None,
+        ctx.accounts.tree_archive.as_ref(),
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &[],
+    )?;
+    emit_inserted!(ctx, inserted);
+
+    let note = note_event(
+        &output_pool_key,
+        output_pool.tree_epoch,
+        commitment,
+        leaf_index,
+        encrypted_note,
+    )?;
+    emit_all!(ctx, note);
+
+    // Record where the leaf landed
+    let record = &mut ctx.accounts.commitment_record;
+    record.pool = output_pool_key;
+    record.commitment = commitment;
+    record.tree_epoch = output_pool.tree_epoch;
+    record.leaf_index = leaf_index;
+    record.slot = clock.slot;
+    record.bump = ctx.bumps.commitment_record;
+
+    output_pool.record_deposit();
+    output_pool.record_shielded(pool_tokens)?;
+    record_deposit_stats(
+        &ctx.accounts.output_pool_stats,
+        1,
+        pool_tokens,
+        output_pool.deposit_count,
+    )?;
+
+    emit_cpi!(TransferEvent {
+        pool: input_pool_key,
+        nullifiers: vec![nullifier],
+        commitments: vec![commitment],
+        output_pool: output_pool_key,
+        first_leaf_index: leaf_index,
+        root: output_pool.current_root(),
+        relayer: ctx.accounts.relayer.key(),
+        slot: clock.slot,
+    });
+    msg!("Staked {} lamports for {} pool tokens", amount - fee, minted);
+    msg!("Shielded {} tokens at index {}", pool_tokens, leaf_index);
+    msg!("Nullifier spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process Migrate instruction
+///
+/// Spends notes of the source pool to the migration authority, moves their
+/// value net of fees from the source vault into the destination vault, and
+/// shields it there under `commitment`. Every proof is verified before
+/// anything is spent.
+pub fn process_migrate<'info>(
+    ctx: Context<'_, '_, 'info, 'info, Migrate<'info>>,
+    commitment: [u8; 32],
+    amount_out: u64,
+    withdrawals: Vec<BatchWithdrawal>,
+    encrypted_note: Option<Vec<u8>>,
+) -> Result<()> {
+    let source_pool_key = ctx.accounts.source_pool.key();
+    let destination_pool_key = ctx.accounts.destination_pool.key();
+    require_keys_neq!(source_pool_key, destination_pool_key, NyxError::InvalidMigrationPools);
+    let (mut source_pool, source_root_history) = state::load_pool_mut(&ctx.accounts.source_pool)?;
+    let (mut destination_pool, mut destination_root_history) =
+        state::load_pool_mut(&ctx.accounts.destination_pool)?;
+    source_pool.require_unshields_allowed()?;
+    destination_pool.require_not_paused()?;
+    destination_pool.require_not_closing()?;
+    ctx.accounts.protocol_config.require_not_paused()?;
+    relayer::require_relayer(
+        &source_pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    source_pool.require_anonymity_set()?;
+    let clock = Clock::get()?;
+
+    // Validate the pools
+    require!(
+        source_pool.asset_mode() == AssetMode::Single
+            && source_pool.mint == Pubkey::default()
+            && destination_pool.asset_mode() == AssetMode::Single
+            && destination_pool.mint == Pubkey::default(),
+        NyxError::InvalidMigrationPools
+    );
+    require!(
+        destination_pool.tree_backend() == TreeBackend::Incremental,
+        NyxError::UnsupportedTreeBackend
+    );
+
+    // Validate the spends
+    let data = UnshieldBatchData { withdrawals };
+    data.validate()?;
+    let UnshieldBatchData { withdrawals } = data;
+    let remaining = ctx.remaining_accounts;
+    require!(
+        remaining.len() == withdrawals.len() * 2,
+        NyxError::InvalidBatchAccounts
+    );
+    let total = withdrawals
+        .iter()
+        .try_fold(0u64, |sum, withdrawal| sum.checked_add(withdrawal.amount))
+        .ok_or(NyxError::InvalidAmount)?;
+    for withdrawal in &withdrawals {
+        require!(clock.slot <= withdrawal.expiry_slot, NyxError::ProofExpired);
+        require!(
+            withdrawal.fee <= source_pool.calculate_relayer_fee(withdrawal.amount),
+            NyxError::FeeAboveRelayerRate
+        );
+        // Each root must belong to the notes' tree and still be accepted
+        check_spend_root(
+            &source_pool,
+            &source_root_history,
+            &ctx.accounts.tree_epoch,
+            &withdrawal.root,
+            clock.slot,
+        )?;
+    }
+    require!(
+        ctx.accounts.source_vault.lamports() >= total,
+        pool_token::TokenError::InsufficientFunds
+    );
+
+    // Conservation: the new note holds exactly what the spent notes carry
+    require!(
+        migration::migrated_amount(&withdrawals) == Some(amount_out),
+        NyxError::MigrationValueMismatch
+    );
+
+    // Validate the deposit
+    require!(amount_out > 0, NyxError::InvalidAmount);
+    require!(
+        verification::is_canonical_field_element(&commitment),
+        NyxError::NonCanonicalFieldElement
+    );
+    require!(destination_pool.can_insert(1), NyxError::PoolFull);
+    require!(destination_pool.validate_amount(amount_out), NyxError::InvalidDenomination);
+    destination_pool.check_deposit_cap(ctx.accounts.destination_vault.lamports(), amount_out)?;
+    require!(
+        ctx.accounts.commitment_record.pool == Pubkey::default(),
+        NyxError::DuplicateCommitment
+    );
+
+    // Verify every proof before spending anything. The recipient pins the
+    // note the value is shielded under
+    let (recipient_key, _) = migration::derive_migration_authority(
+        ctx.program_id,
+        &destination_pool_key,
+        &commitment,
+        amount_out,
+    );
+    let relayer_key = ctx.accounts.relayer.key();
+    let verifier = pool_verifier(
+        &source_pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    for withdrawal in &withdrawals {
+        let valid = verification::verify_unshield_proof(
+            &verifier,
+            &withdrawal.proof,
+            &source_pool_key,
+            &source_pool.mint,
+            &withdrawal.nullifier,
+            &recipient_key,
+            withdrawal.amount,
+            &relayer_key,
+            withdrawal.fee,
+            0,
+            &withdrawal.root,
+            withdrawal.expiry_slot,
+        )?;
+        require!(valid, NyxError::InvalidProof);
+    }
+
+    let mut bloom =
+        ctx.accounts.nullifier_bloom.as_ref().map(|bloom| bloom.load_mut()).transpose()?;
+    let mut nullifier_tree =
+        ctx.accounts.nullifier_tree.as_ref().map(|tree| tree.load_mut()).transpose()?;
+    for (withdrawal, accounts) in withdrawals.iter().zip(remaining.chunks(2)) {
+        // Fails if the nullifier was already spent
+        let spent = nullifier::spend_nullifier(
+            ctx.program_id,
+            &source_pool_key,
+            &withdrawal.nullifier,
+            &accounts[0],
+            &accounts[1],
+            &ctx.accounts.relayer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        emit_cpi!(spent);
+        source_pool.record_nullifier_spent();
+        if let Some(bloom) = bloom.as_mut() {
+            bloom.insert(&withdrawal.nullifier);
+        }
+        if let Some(tree) = nullifier_tree.as_mut() {
+            tree.enqueue(withdrawal.nullifier)?;
+        }
+    }
+
+    let vault_bump = ctx.bumps.source_vault;
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        source_pool_key.as_ref(),
+        &[vault_bump],
+    ]];
+
+    // Move the value vault to vault, and the fees to relayer and treasury
+    let total_fee = total - amount_out;
+    let (relayer_fee, treasury_fee) = ctx.accounts.protocol_config.split_relayer_fee(total_fee);
+    let source_vault = ctx.accounts.source_vault.to_account_info();
+    let system_program = ctx.accounts.system_program.to_account_info();
+    for (to, lamports) in [
+        (ctx.accounts.destination_vault.to_account_info(), amount_out),
+        (ctx.accounts.relayer.to_account_info(), relayer_fee),
+        (ctx.accounts.treasury.to_account_info(), treasury_fee),
+    ] {
+        pay_from_vault(&source_vault, &to, &system_program, lamports, signer_seeds)?;
+    }
+    source_pool.record_fee_collected(total_fee);
+    source_pool.record_unshielded(total)?;
+    source_pool.withdrawal_limit.record(clock.slot, total, withdrawals.len() as u64)?;
+    source_pool.check_solvency(ctx.accounts.source_vault.lamports())?;
+    record_withdrawal_stats(
+        &ctx.accounts.source_pool_stats,
+        withdrawals.len() as u64,
+        total,
+        total_fee,
+    )?;
+
+    // Add commitment to the destination pool's tree
+    let (leaf_index, inserted) = insert_commitments(
+        ctx.program_id,
+        &mut destination_pool,
+        &mut destination_root_history,
+        &destination_pool_key,
+        &[commitment],
+        Some(&ctx.accounts.commitment_chunk),
+        None,
+        ctx.accounts.tree_archive.as_ref(),
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &[],
+    )?;
+    emit_inserted!(ctx, inserted);
+
+    let note = note_event(
+        &destination_pool_key,
+        destination_pool.tree_epoch,
+        commitment,
+        leaf_index,
+        encrypted_note,
+    )?;
+    emit_all!(ctx, note);
+
+    // Record where the leaf landed
+    let record = &mut ctx.accounts.commitment_record;
+    record.pool = destination_pool_key;
+    record.commitment = commitment;
+    record.tree_epoch = destination_pool.tree_epoch;
+    record.leaf_index = leaf_index;
+    record.slot = clock.slot;
+    record.bump = ctx.bumps.commitment_record;
+
+    destination_pool.record_deposit();
+    destination_pool.record_shielded(amount_out)?;
+    destination_pool.check_solvency(ctx.accounts.destination_vault.lamports())?;
+    record_deposit_stats(
+        &ctx.accounts.destination_pool_stats,
+        1,
+        amount_out,
+        destination_pool.deposit_count,
+    )?;
+
+    emit_cpi!(TransferEvent {
+        pool: source_pool_key,
+        nullifiers: withdrawals.iter().map(|withdrawal| withdrawal.nullifier).collect(),
+        commitments: vec![commitment],
+        output_pool: destination_pool_key,
+        first_leaf_index: leaf_index,
+        root: destination_pool.current_root(),
+        relayer: ctx.accounts.relayer.key(),
+        slot: clock.slot,
+    });
+    msg!("Migrated {} notes into {} lamports at index {}", withdrawals.len(), amount_out, leaf_index);
+    msg!("Nullifiers spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process CreateClaim instruction
+///
+/// Spends a note of a SOL pool to the claim link PDA, which keeps the
+/// lamports net of the fee until the link is claimed or refunded.
+#[allow(clippy::too_many_arguments)]
+pub fn process_create_claim(
+    ctx: Context<CreateClaim>,
+    claim_hash: [u8; 32],
+    refund_to: Pubkey,
+    claim_expiry_slot: u64,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    fee: u64,
+    expiry_slot: u64,
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    ctx.accounts.protocol_config.require_not_paused()?;
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    pool.require_anonymity_set()?;
+    let clock = Clock::get()?;
+
+    // Validate the link
+    require!(
+        pool.asset_mode() == AssetMode::Single && pool.mint == Pubkey::default(),
+        NyxError::ClaimLinkUnsupported
+    );
+    require!(claim_expiry_slot > clock.slot, NyxError::InvalidClaimExpiry);
+
+    // Validate the spend; something must be left to claim
+    require!(amount > 0, NyxError::InvalidAmount);
+    require!(fee < amount, NyxError::FeeExceedsAmount);
+    require!(fee <= pool.calculate_relayer_fee(amount), NyxError::FeeAboveRelayerRate);
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
+
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, clock.slot)?;
+    // The recipient pins the link's hash, refund address and expiry
+    let recipient_key = ctx.accounts.claim_link.key();
+    let relayer_key = ctx.accounts.relayer.key();
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_unshield_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &recipient_key,
+        amount,
+        &relayer_key,
+        fee,
+        0,
+        &root,
+        expiry_slot,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
+
+    // Record in pool stats
+    pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &nullifier)?;
+
+    require!(
+        ctx.accounts.vault.lamports() >= amount,
+        pool_token::TokenError::InsufficientFunds
+    );
+    let vault_bump = ctx.bumps.vault;
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[vault_bump],
+    ]];
+
+    // Fund the link net of the fee, split between relayer and treasury
+    let (relayer_fee, treasury_fee) = ctx.accounts.protocol_config.split_relayer_fee(fee);
+    let vault = ctx.accounts.vault.to_account_info();
+    let system_program = ctx.accounts.system_program.to_account_info();
+    for (to, lamports) in [
+        (ctx.accounts.claim_link.to_account_info(), amount - fee),
+        (ctx.accounts.relayer.to_account_info(), relayer_fee),
+        (ctx.accounts.treasury.to_account_info(), treasury_fee),
+    ] {
+        pay_from_vault(&vault, &to, &system_program, lamports, signer_seeds)?;
+    }
+    pool.record_fee_collected(fee);
+    pool.record_unshielded(amount)?;
+    pool.withdrawal_limit.record(clock.slot, amount, 1)?;
+    pool.check_solvency(ctx.accounts.vault.lamports())?;
+    record_withdrawal_stats(&ctx.accounts.pool_stats, 1, amount, fee)?;
+
+    let claim_link = &mut ctx.accounts.claim_link;
+    claim_link.pool = pool_key;
+    claim_link.claim_hash = claim_hash;
+    claim_link.refund_to = refund_to;
+    claim_link.expiry_slot = claim_expiry_slot;
+    claim_link.amount = amount - fee;
+    claim_link.bump = ctx.bumps.claim_link;
+
+    emit_cpi!(ClaimLinkCreated {
+        pool: pool_key,
+        claim_link: claim_link.key(),
+        amount: claim_link.amount,
+        expiry_slot: claim_expiry_slot,
+    });
+
+    msg!("Claim link created for {} lamports ({} fee)", amount - fee, fee);
+    msg!("Claimable until slot {}", claim_expiry_slot);
+
+    Ok(())
+}
+
+/// Process Claim instruction
+///
+/// Checks the link's key signed the claim to the recipient; the `close`
+/// constraint pays the link's lamports to the recipient.
+pub fn process_claim(ctx: Context<Claim>, claim_key: Pubkey) -> Result<()> {
+    let claim_link = &ctx.accounts.claim_link;
+    let clock = Clock::get()?;
+
+    claim::require_claim_signature(
+        &ctx.accounts.instructions_sysvar,
+        &claim_link.claim_hash,
+        &claim_key,
+        &claim_link.key(),
+        &ctx.accounts.recipient.key(),
+    )?;
+    require!(claim_link.is_claimable(clock.slot), NyxError::ClaimLinkExpired);
+
+    emit_cpi!(ClaimLinkClosed {
+        pool: claim_link.pool,
+        claim_link: claim_link.key(),
+        recipient: ctx.accounts.recipient.key(),
+        amount: claim_link.to_account_info().lamports(),
+        refunded: false,
+        slot: clock.slot,
+    });
+
+    msg!("Claimed {} lamports", claim_link.amount);
+
+    Ok(())
+}
+
+/// Process ReclaimClaim instruction
+///
+/// The `close` constraint returns the link's lamports to its refund
+/// address once the link has expired.
+pub fn process_reclaim_claim(ctx: Context<ReclaimClaim>) -> Result<()> {
+    let claim_link = &ctx.accounts.claim_link;
+    let clock = Clock::get()?;
+
+    require!(!claim_link.is_claimable(clock.slot), NyxError::ClaimLinkNotExpired);
+
+    emit_cpi!(ClaimLinkClosed {
+        pool: claim_link.pool,
+        claim_link: claim_link.key(),
+        recipient: claim_link.refund_to,
+        amount: claim_link.to_account_info().lamports(),
+        refunded: true,
+        slot: clock.slot,
+    });
+
+    msg!("Refunded {} lamports of an expired claim link", claim_link.amount);
+
+    Ok(())
+}
+
+/// Process CreatePaymentStream instruction
+///
+/// Spends a note of a SOL pool to the payment stream PDA, which keeps the
+/// lamports net of the fee and pays them out period by period.
+#[allow(clippy::too_many_arguments)]
+pub fn process_create_payment_stream(
+    ctx: Context<CreatePaymentStream>,
+    terms: instructions::PaymentStreamTerms,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    fee: u64,
+    expiry_slot: u64,
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    ctx.accounts.protocol_config.require_not_paused()?;
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    pool.require_anonymity_set()?;
+    let clock = Clock::get()?;
+
+    // Validate the stream
+    require!(
+        pool.asset_mode() == AssetMode::Single && pool.mint == Pubkey::default(),
+        NyxError::InvalidPaymentStream
+    );
+    terms.validate()?;
+
+    // Validate the spend; something must be left to pay out
+    require!(amount > 0, NyxError::InvalidAmount);
+    require!(fee < amount, NyxError::FeeExceedsAmount);
+    require!(fee <= pool.calculate_relayer_fee(amount), NyxError::FeeAboveRelayerRate);
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
+
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, clock.slot)?;
+    // The recipient pins the stream's terms
+    let recipient_key = ctx.accounts.payment_stream.key();
+    let relayer_key = ctx.accounts.relayer.key();
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_unshield_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &recipient_key,
+        amount,
+        &relayer_key,
+        fee,
+        0,
+        &root,
+        expiry_slot,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
+
+    // Record in pool stats
+    pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &nullifier)?;
+
+    require!(
+        ctx.accounts.vault.lamports() >= amount,
+        pool_token::TokenError::InsufficientFunds
+    );
+    let vault_bump = ctx.bumps.vault;
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[vault_bump],
+    ]];
+
+    // Fund the stream net of the fee, split between relayer and treasury
+    let (relayer_fee, treasury_fee) = ctx.accounts.protocol_config.split_relayer_fee(fee);
+    let vault = ctx.accounts.vault.to_account_info();
+    let system_program = ctx.accounts.system_program.to_account_info();
+    for (to, lamports) in [
+        (ctx.accounts.payment_stream.to_account_info(), amount - fee),
+        (ctx.accounts.relayer.to_account_info(), relayer_fee),
+        (ctx.accounts.treasury.to_account_info(), treasury_fee),
+    ] {
+        pay_from_vault(&vault, &to, &system_program, lamports, signer_seeds)?;
+    }
+    pool.record_fee_collected(fee);
+    pool.record_unshielded(amount)?;
+    pool.withdrawal_limit.record(clock.slot, amount, 1)?;
+    pool.check_solvency(ctx.accounts.vault.lamports())?;
+    record_withdrawal_stats(&ctx.accounts.pool_stats, 1, amount, fee)?;
+
+    let payment_stream = &mut ctx.accounts.payment_stream;
+    payment_stream.pool = pool_key;
+    payment_stream.stream_id = terms.stream_id;
+    payment_stream.recipient = terms.recipient;
+    payment_stream.refund_to = terms.refund_to;
+    payment_stream.amount_per_period = terms.amount_per_period;
+    payment_stream.period_slots = terms.period_slots;
+    payment_stream.next_payment_slot = clock.slot;
+    payment_stream.remaining = amount - fee;
+    payment_stream.bump = ctx.bumps.payment_stream;
+
+    emit_cpi!(PaymentStreamCreated {
+        pool: pool_key,
+        payment_stream: payment_stream.key(),
+        amount: payment_stream.remaining,
+        amount_per_period: terms.amount_per_period,
+        period_slots: terms.period_slots,
+    });
+
+    msg!("Payment stream created for {} lamports ({} fee)", amount - fee, fee);
+    msg!("Pays up to {} lamports every {} slots", terms.amount_per_period, terms.period_slots);
+
+    Ok(())
+}
+
+/// Process ExecuteStreamPayment instruction
+///
+/// Pays one due period from the stream's lamports, which the program owns,
+/// and closes the stream to its refund address once nothing is left.
+pub fn process_execute_stream_payment(ctx: Context<ExecuteStreamPayment>) -> Result<()> {
+    let clock = Clock::get()?;
+    let payment_stream = &mut ctx.accounts.payment_stream;
+
+    let payment = stream::pay_period(payment_stream, clock.slot)?;
+
+    let stream_info = payment_stream.to_account_info();
+    **stream_info.try_borrow_mut_lamports()? -= payment;
+    **ctx.accounts.recipient.try_borrow_mut_lamports()? += payment;
+
+    emit_cpi!(StreamPaymentMade {
+        payment_stream: payment_stream.key(),
+        recipient: payment_stream.recipient,
+        amount: payment,
+        remaining: payment_stream.remaining,
+        slot: clock.slot,
+    });
+
+    msg!("Stream paid {} lamports, {} left", payment, payment_stream.remaining);
+
+    if payment_stream.remaining == 0 {
+        emit_cpi!(PaymentStreamClosed {
+            pool: payment_stream.pool,
+            payment_stream: payment_stream.key(),
+            refunded: stream_info.lamports(),
+            cancelled: false,
+            slot: clock.slot,
+        });
+        payment_stream.close(ctx.accounts.refund_to.to_account_info())?;
+    }
+
+    Ok(())
+}
+
+/// Process CancelPaymentStream instruction
+///
+/// The `close` constraint returns the unpaid balance and rent to the
+/// refund address.
+pub fn process_cancel_payment_stream(ctx: Context<CancelPaymentStream>) -> Result<()> {
+    let payment_stream = &ctx.accounts.payment_stream;
+
+    emit_cpi!(PaymentStreamClosed {
+        pool: payment_stream.pool,
+        payment_stream: payment_stream.key(),
+        refunded: payment_stream.to_account_info().lamports(),
+        cancelled: true,
+        slot: Clock::get()?.slot,
+    });
+
+    msg!("Payment stream cancelled with {} lamports unpaid", payment_stream.remaining);
+
+    Ok(())
+}
+
+/// Process QueueUnshield instruction
+pub fn process_queue_unshield(ctx: Context<QueueUnshield>, intent_hash: [u8; 32]) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let pool = ctx.accounts.pool.load()?;
+    pool.require_unshields_allowed()?;
+    pool.require_sol_pool()?;
+    let clock = Clock::get()?;
+
+    let queued_unshield = &mut ctx.accounts.queued_unshield;
+    queued_unshield.pool = pool_key;
+    queued_unshield.intent_hash = intent_hash;
+    queued_unshield.payer = ctx.accounts.payer.key();
+    queued_unshield.queued_slot = clock.slot;
+    queued_unshield.bump = ctx.bumps.queued_unshield;
+
+    emit_cpi!(UnshieldQueued {
+        pool: pool_key,
+        intent_hash,
+        queued_slot: clock.slot,
+    });
+
+    msg!("Unshield queued at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process ExecuteUnshield instruction
+///
+/// `process_unshield_sol` for the withdrawal a queued intent hashes, within
+/// its execution window. The `close` constraint refunds the intent's rent.
+pub fn process_execute_unshield(
+    ctx: Context<ExecuteUnshield>,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    fee: u64,
+    expiry_slot: u64,
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    pool.require_sol_pool()?;
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    pool.require_anonymity_set()?;
+    let clock = Clock::get()?;
+    let recipient_key = ctx.accounts.recipient.key();
+    let relayer_key = ctx.accounts.relayer.key();
+
+    // Only the queued withdrawal, and only within its window
+    let queued_unshield = &ctx.accounts.queued_unshield;
+    queue::require_executable(
+        queued_unshield,
+        &nullifier,
+        &recipient_key,
+        amount,
+        fee,
+        expiry_slot,
+        clock.slot,
+    )?;
+
+    // Validate
+    require!(amount > 0, NyxError::InvalidAmount);
+    require!(fee <= amount, NyxError::FeeExceedsAmount);
+    require!(fee <= pool.calculate_relayer_fee(amount), NyxError::FeeAboveRelayerRate);
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
+
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, clock.slot)?;
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_unshield_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &recipient_key,
+        amount,
+        &relayer_key,
+        fee,
+        0,
+        &root,
+        expiry_slot,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
+
+    // Record in pool stats
+    pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &nullifier)?;
+
+    let vault_lamports = ctx.accounts.vault.lamports();
+    require!(vault_lamports >= amount, pool_token::TokenError::InsufficientFunds);
+
+    let vault_bump = ctx.bumps.vault;
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[vault_bump],
+    ]];
+
+    // Pay the recipient net of the fee, split between relayer and treasury
+    let (relayer_fee, treasury_fee) = ctx.accounts.protocol_config.split_relayer_fee(fee);
+    let vault = ctx.accounts.vault.to_account_info();
+    let system_program = ctx.accounts.system_program.to_account_info();
+    for (to, lamports) in [
+        (ctx.accounts.recipient.to_account_info(), amount - fee),
+        (ctx.accounts.relayer.to_account_info(), relayer_fee),
+        (ctx.accounts.treasury.to_account_info(), treasury_fee),
+    ] {
+        pay_from_vault(&vault, &to, &system_program, lamports, signer_seeds)?;
+    }
+    pool.record_fee_collected(fee);
+    pool.record_unshielded(amount)?;
+    pool.withdrawal_limit.record(clock.slot, amount, 1)?;
+    pool.check_solvency(ctx.accounts.vault.lamports())?;
+    record_withdrawal_stats(&ctx.accounts.pool_stats, 1, amount, fee)?;
+
+    emit_cpi!(QueuedUnshieldClosed {
+        pool: pool_key,
+        intent_hash: queued_unshield.intent_hash,
+        executed: true,
+        slot: clock.slot,
+    });
+
+    emit_cpi!(UnshieldEvent {
+        pool: pool_key,
+        mint: pool.mint,
+        nullifier,
+        recipient: recipient_key,
+        amount,
+        fee,
+        relayer: relayer_key,
+        slot: clock.slot,
+    });
+    msg!("Executed queued unshield of {} lamports ({} fee)", amount, fee);
+
+    Ok(())
+}
+
+/// Process CancelQueuedUnshield instruction
+///
+/// Nothing was spent when the intent was queued, so its payer can drop it
+/// at any time; the `close` constraint refunds the rent.
+pub fn process_cancel_queued_unshield(ctx: Context<CancelQueuedUnshield>) -> Result<()> {
+    let queued_unshield = &ctx.accounts.queued_unshield;
+
+    emit_cpi!(QueuedUnshieldClosed {
+        pool: queued_unshield.pool,
+        intent_hash: queued_unshield.intent_hash,
+        executed: false,
+        slot: Clock::get()?.slot,
+    });
+
+    msg!("Queued unshield cancelled");
+
+    Ok(())
+}
+
+/// Process BondRelayer instruction
+///
+/// Bonding more cancels a pending unbond.
+pub fn process_bond_relayer(ctx: Context<BondRelayer>, amount: u64) -> Result<()> {
+    require!(amount > 0, NyxError::InvalidAmount);
+
+    let cpi_context = CpiContext::new(
+        ctx.accounts.system_program.to_account_info(),
+        system_program::Transfer {
+            from: ctx.accounts.relayer.to_account_info(),
+            to: ctx.accounts.relayer_bond.to_account_info(),
+        },
+    );
+    system_program::transfer(cpi_context, amount)?;
+
+    let relayer_bond = &mut ctx.accounts.relayer_bond;
+    relayer_bond.relayer = ctx.accounts.relayer.key();
+    relayer_bond.unbond_requested_at = 0;
+    relayer_bond.bump = ctx.bumps.relayer_bond;
+
+    msg!("Relayer bonded {} lamports", amount);
+    Ok(())
+}
+
+/// Process RequestRelayerUnbond instruction
+pub fn process_request_relayer_unbond(ctx: Context<UpdateRelayerBond>) -> Result<()> {
+    let slot = Clock::get()?.slot;
+    ctx.accounts.relayer_bond.unbond_requested_at = slot;
+
+    msg!(
+        "Relayer bond withdrawable from slot {}",
+        slot.saturating_add(state::RELAYER_UNBONDING_SLOTS)
+    );
+    Ok(())
+}
+
+/// Process WithdrawRelayerBond instruction
+///
+/// The bond account is closed to the relayer by the `close` constraint.
+pub fn process_withdraw_relayer_bond(ctx: Context<WithdrawRelayerBond>) -> Result<()> {
+    require!(
+        ctx.accounts.relayer_bond.is_withdrawable(Clock::get()?.slot),
+        NyxError::BondNotWithdrawable
+    );
+
+    msg!("Relayer bond withdrawn");
+    Ok(())
+}
+
+/// Process SetRelayerQuote instruction
+pub fn process_set_relayer_quote(
+    ctx: Context<SetRelayerQuote>,
+    fee_bps: u16,
+    min_withdrawal: u64,
+    url: String,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    {
+        let pool = ctx.accounts.pool.load()?;
+        state::RelayerQuote::validate(&pool, fee_bps, &url)?;
+    }
+    let slot = Clock::get()?.slot;
+
+    let quote = &mut ctx.accounts.relayer_quote;
+    quote.pool = pool_key;
+    quote.relayer = ctx.accounts.relayer.key();
+    quote.fee_bps = fee_bps;
+    quote.min_withdrawal = min_withdrawal;
+    quote.updated_at = slot;
+    quote.url = url;
+    quote.bump = ctx.bumps.relayer_quote;
+
+    emit_cpi!(RelayerQuoteUpdated {
+        pool: pool_key,
+        relayer: quote.relayer,
+        fee_bps,
+        min_withdrawal,
+        url: quote.url.clone(),
+        slot,
+    });
+    msg!("Relayer quote set to {} bps from {}", fee_bps, min_withdrawal);
+    Ok(())
+}
+
+/// Process CloseRelayerQuote instruction
+pub fn process_close_relayer_quote(ctx: Context<CloseRelayerQuote>) -> Result<()> {
+    let quote = &ctx.accounts.relayer_quote;
+
+    emit_cpi!(RelayerQuoteClosed {
+        pool: quote.pool,
+        relayer: quote.relayer,
+        slot: Clock::get()?.slot,
+    });
+    msg!("Relayer quote closed");
+    Ok(())
+}
+
+/// Process DisputeRelayerFee instruction
+///
+/// Slashes the relayer when a withdrawal it submitted paid more than the
+/// fee it quoted: the proof must verify with the relayer and the disputed
+/// fee, and its nullifier must be spent.
+#[allow(clippy::too_many_arguments)]
+pub fn process_dispute_relayer_fee(
+    ctx: Context<DisputeRelayerFee>,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    fee: u64,
+    refund: u64,
+    expiry_slot: u64,
+    quoted_fee: u64,
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let pool = ctx.accounts.pool.load()?;
+    let clock = Clock::get()?;
+    let relayer_key = ctx.accounts.relayer_bond.relayer;
+    let recipient_key = ctx.accounts.recipient.key();
+
+    // The overcharge is paid back from the bond, so it must be in lamports
+    pool.require_sol_pool()?;
+    require!(fee > quoted_fee, NyxError::FeeWithinQuote);
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+
+    // The relayer signed the quote in the instruction before this one
+    let quote = relayer::fee_quote_message(&pool_key, &nullifier, &relayer_key, quoted_fee);
+    require!(
+        escrow::preceding_signature_is(&ctx.accounts.instructions_sysvar, &relayer_key, &quote)?,
+        NyxError::MissingFeeQuote
+    );
+
+    // The withdrawal happened, and was proven with this relayer and fee
+    require!(
+        nullifier::is_nullifier_in_set(
+            ctx.program_id,
+            &pool_key,
+            &nullifier,
+            &ctx.accounts.nullifier_set,
+        )?,
+        NyxError::NullifierNotSpent
+    );
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_unshield_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &recipient_key,
+        amount,
+        &relayer_key,
+        fee,
+        refund,
+        &root,
+        expiry_slot,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Pay the recipient out of the bond, above the bond account's rent
+    let bond_info = ctx.accounts.relayer_bond.to_account_info();
+    let rent = Rent::get()?.minimum_balance(bond_info.data_len());
+    let slashed = relayer::slash_amount(bond_info.lamports().saturating_sub(rent), fee - quoted_fee);
+    **bond_info.try_borrow_mut_lamports()? -= slashed;
+    **ctx.accounts.recipient.try_borrow_mut_lamports()? += slashed;
+    ctx.accounts.relayer_bond.total_slashed += slashed;
+
+    let dispute = &mut ctx.accounts.relayer_dispute;
+    dispute.pool = pool_key;
+    dispute.nullifier = nullifier;
+    dispute.relayer = relayer_key;
+    dispute.slashed = slashed;
+    dispute.bump = ctx.bumps.relayer_dispute;
+
+    emit_cpi!(RelayerSlashed {
+        pool: pool_key,
+        relayer: relayer_key,
+        nullifier,
+        recipient: recipient_key,
+        quoted_fee,
+        fee,
+        slashed,
+        slot: clock.slot,
+    });
+    msg!("Relayer slashed {} lamports for a {} fee quoted at {}", slashed, fee, quoted_fee);
+
+    Ok(())
+}
+
+/// Process UnshieldWithChange instruction
+///
+/// SPL counterpart of `process_unshield_sol_with_change`.
+#[allow(clippy::too_many_arguments)]
+pub fn process_unshield_with_change(
+    ctx: Context<UnshieldWithChange>,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    fee: u64,
+    refund: u64,
+    expiry_slot: u64,
+    change_commitment: [u8; 32],
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, mut root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    pool.require_spl_pool()?;
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    pool.require_anonymity_set()?;
+    let clock = Clock::get()?;
+
+    // Change notes would break a fixed pool's uniform note amounts
+    require!(!pool.is_fixed_denomination(), NyxError::InvalidDenomination);
+    require!(pool.can_insert(1), NyxError::PoolFull);
+
+    // Validate
+    require!(amount > 0, NyxError::InvalidAmount);
+    require!(fee <= amount, NyxError::FeeExceedsAmount);
+    // The fee may also reimburse the SOL the relayer fronts, if it fronts any
+    let created = pool_token::create_associated_token_account_if_missing(
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.recipient_token_account,
+        &ctx.accounts.recipient,
+        &ctx.accounts.mint.to_account_info(),
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.associated_token_program.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    require!(
+        fee <= pool.max_unshield_fee(amount, created || refund > 0),
+        NyxError::FeeAboveRelayerRate
+    );
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
+
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, clock.slot)?;
+    // For SPL tokens, the recipient owns the token account
+    let recipient_key = ctx.accounts.recipient.key();
+    let relayer_key = ctx.accounts.relayer.key();
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_unshield_with_change_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &recipient_key,
+        amount,
+        &relayer_key,
+        fee,
+        refund,
+        &root,
+        expiry_slot,
+        &change_commitment,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
+
+    // Record in pool stats
+    pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &nullifier)?;
+
+    // Return the unspent remainder to the owner as a new note
+    let (change_index, inserted) = insert_commitments(
+        ctx.program_id,
+        &mut pool,
+        &mut root_history,
+        &pool_key,
+        &[change_commitment],
+        ctx.accounts.commitment_chunk.as_ref(),
+        None,
+        ctx.accounts.tree_archive.as_ref(),
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+    emit_inserted!(ctx, inserted);
+
+    // Transfer SPL tokens from vault to recipient
+    let vault_bump = ctx.bumps.vault_authority;
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[vault_bump],
+    ]];
+
+    // Pay the recipient net of the fee, split between relayer and treasury.
+    // The rent reimbursement is the relayer's alone
+    let (rate_fee, rent_fee) = pool.split_rent_fee(amount, fee);
+    let (relayer_fee, treasury_fee) = ctx.accounts.protocol_config.split_relayer_fee(rate_fee);
+    for (to, tokens) in [
+        (ctx.accounts.recipient_token_account.to_account_info(), amount - fee),
+        (ctx.accounts.relayer_token_account.to_account_info(), relayer_fee + rent_fee),
+        (ctx.accounts.treasury_token_account.to_account_info(), treasury_fee),
+    ] {
+        if tokens == 0 {
+            continue;
+        }
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.vault_token_account.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to,
+            authority: ctx.accounts.vault_authority.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        token_interface::transfer_checked(cpi_context, tokens, ctx.accounts.mint.decimals)?;
+    }
+    pool.record_fee_collected(fee);
+    pool.record_unshielded(amount)?;
+    pool.withdrawal_limit.record(clock.slot, amount, 1)?;
+    ctx.accounts.vault_token_account.reload()?;
+    pool.check_solvency(ctx.accounts.vault_token_account.amount)?;
+    record_withdrawal_stats(&ctx.accounts.pool_stats, 1, amount, fee)?;
+
+    // Forward the refund so the recipient can pay for its first transactions
+    if refund > 0 {
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.relayer.to_account_info(),
+                to: ctx.accounts.recipient.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, refund)?;
+    }
+
+    emit_cpi!(UnshieldEvent {
+        pool: pool_key,
+        mint: pool.mint,
+        nullifier,
+        recipient: recipient_key,
+        amount,
+        fee,
+        relayer: relayer_key,
+        slot: clock.slot,
+    });
+    msg!("Unshielded {} tokens ({} fee)", amount, fee);
+    msg!("Change note inserted at index {}", change_index);
+    msg!("Nullifier spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process UnshieldSolTimelocked instruction
+///
+/// `process_unshield_sol` for a timelocked note: the proof binds the
+/// note's unlock slot, checked against the clock before verification.
+#[allow(clippy::too_many_arguments)]
+pub fn process_unshield_sol_timelocked(
+    ctx: Context<UnshieldSolTimelocked>,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    fee: u64,
+    refund: u64,
+    expiry_slot: u64,
+    not_before_slot: u64,
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    pool.require_sol_pool()?;
+    let clock = Clock::get()?;
+    let recipient_key = ctx.accounts.recipient.key();
+    let relayer_key = ctx.accounts.relayer.key();
+
+    // Validate. Refunds only make sense for SPL withdrawals
+    require!(refund == 0, NyxError::InvalidRefund);
+    check_unshield(
+        &pool,
+        &root_history,
+        &ctx.accounts.tree_epoch,
+        &relayer_key,
+        ctx.accounts.relayer_bond.as_deref(),
+        &root,
+        amount,
+        fee,
+        &proof,
+        expiry_slot,
+        clock.slot,
+    )?;
+    verification::check_timelock(not_before_slot, clock.slot)?;
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_timelocked_unshield_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &recipient_key,
+        amount,
+        &relayer_key,
+        fee,
+        refund,
+        &root,
+        expiry_slot,
+        not_before_slot,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = spend_note(
+        ctx.program_id,
+        &mut pool,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &ctx.accounts.nullifier_bloom,
+        &ctx.accounts.nullifier_tree,
+    )?;
+    emit_cpi!(spent);
+
+    // Pay the recipient net of the fee from the vault PDA
+    let payout = sol_payout!(ctx);
+    let protocol_config = &ctx.accounts.protocol_config;
+    pay_sol_unshield(&pool_key, ctx.bumps.vault, &payout, protocol_config, amount, fee)?;
+    record_unshield(&mut pool, &ctx.accounts.pool_stats, amount, fee, clock.slot)?;
+    pool.check_solvency(ctx.accounts.vault.lamports())?;
+
+    emit_cpi!(UnshieldEvent {
+        pool: pool_key,
+        mint: pool.mint,
+        nullifier,
+        recipient: recipient_key,
+        amount,
+        fee,
+        relayer: relayer_key,
+        slot: clock.slot,
+    });
+    msg!("Unshielded {} lamports ({} fee)", amount, fee);
+    msg!("Nullifier spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process UnshieldTimelocked instruction
+///
+/// SPL counterpart of `process_unshield_sol_timelocked`.
+#[allow(clippy::too_many_arguments)]
+pub fn process_unshield_timelocked(
+    ctx: Context<UnshieldTimelocked>,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    fee: u64,
+    refund: u64,
+    expiry_slot: u64,
+    not_before_slot: u64,
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    pool.require_spl_pool()?;
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    pool.require_anonymity_set()?;
+    let clock = Clock::get()?;
+
+    // Validate
+    require!(amount > 0, NyxError::InvalidAmount);
+    require!(fee <= amount, NyxError::FeeExceedsAmount);
+    // The fee may also reimburse the SOL the relayer fronts, if it fronts any
+    let created = pool_token::create_associated_token_account_if_missing(
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.recipient_token_account,
+        &ctx.accounts.recipient,
+        &ctx.accounts.mint.to_account_info(),
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.associated_token_program.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    require!(
+        fee <= pool.max_unshield_fee(amount, created || refund > 0),
+        NyxError::FeeAboveRelayerRate
+    );
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
+    verification::check_timelock(not_before_slot, clock.slot)?;
+
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, clock.slot)?;
+    // For SPL tokens, the recipient owns the token account
+    let recipient_key = ctx.accounts.recipient.key();
+    let relayer_key = ctx.accounts.relayer.key();
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_timelocked_unshield_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &recipient_key,
+        amount,
+        &relayer_key,
+        fee,
+        refund,
+        &root,
+        expiry_slot,
+        not_before_slot,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
+
+    // Record in pool stats
+    pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &nullifier)?;
+
+    // Transfer SPL tokens from vault to recipient
+    let vault_bump = ctx.bumps.vault_authority;
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[vault_bump],
+    ]];
+
+    // Pay the recipient net of the fee, split between relayer and treasury.
+    // The rent reimbursement is the relayer's alone
+    let (rate_fee, rent_fee) = pool.split_rent_fee(amount, fee);
+    let (relayer_fee, treasury_fee) = ctx.accounts.protocol_config.split_relayer_fee(rate_fee);
+    for (to, tokens) in [
+        (ctx.accounts.recipient_token_account.to_account_info(), amount - fee),
+        (ctx.accounts.relayer_token_account.to_account_info(), relayer_fee + rent_fee),
+        (ctx.accounts.treasury_token_account.to_account_info(), treasury_fee),
+    ] {
+        if tokens == 0 {
+            continue;
+        }
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.vault_token_account.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to,
+            authority: ctx.accounts.vault_authority.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        token_interface::transfer_checked(cpi_context, tokens, ctx.accounts.mint.decimals)?;
+    }
+    pool.record_fee_collected(fee);
+    pool.record_unshielded(amount)?;
+    pool.withdrawal_limit.record(clock.slot, amount, 1)?;
+    ctx.accounts.vault_token_account.reload()?;
+    pool.check_solvency(ctx.accounts.vault_token_account.amount)?;
+    record_withdrawal_stats(&ctx.accounts.pool_stats, 1, amount, fee)?;
+
+    // Forward the refund so the recipient can pay for its first transactions
+    if refund > 0 {
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.relayer.to_account_info(),
+                to: ctx.accounts.recipient.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, refund)?;
+    }
+
+    emit_cpi!(UnshieldEvent {
+        pool: pool_key,
+        mint: pool.mint,
+        nullifier,
+        recipient: recipient_key,
+        amount,
+        fee,
+        relayer: relayer_key,
+        slot: clock.slot,
+    });
+    msg!("Unshielded {} tokens ({} fee)", amount, fee);
+    msg!("Nullifier spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process UnshieldSolEscrow instruction
+///
+/// `process_unshield_sol` for an escrow note: the proof binds the note's
+/// arbiter and deal, and the arbiter must have signed the resolution paying
+/// the recipient in the previous instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn process_unshield_sol_escrow(
+    ctx: Context<UnshieldSolEscrow>,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    fee: u64,
+    refund: u64,
+    expiry_slot: u64,
+    arbiter: Pubkey,
+    escrow_id: [u8; 32],
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    pool.require_sol_pool()?;
+    let clock = Clock::get()?;
+    let recipient_key = ctx.accounts.recipient.key();
+    let relayer_key = ctx.accounts.relayer.key();
+
+    // Validate. Refunds only make sense for SPL withdrawals
+    require!(refund == 0, NyxError::InvalidRefund);
+    check_unshield(
+        &pool,
+        &root_history,
+        &ctx.accounts.tree_epoch,
+        &relayer_key,
+        ctx.accounts.relayer_bond.as_deref(),
+        &root,
+        amount,
+        fee,
+        &proof,
+        expiry_slot,
+        clock.slot,
+    )?;
+
+    // Only the arbiter decides who an escrow note is paid to
+    escrow::require_arbiter_resolution(
+        &ctx.accounts.instructions_sysvar,
+        &arbiter,
+        &escrow::resolution_message(&pool_key, &escrow_id, &recipient_key),
+    )?;
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_escrow_unshield_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &recipient_key,
+        amount,
+        &relayer_key,
+        fee,
+        refund,
+        &root,
+        expiry_slot,
+        &arbiter,
+        &escrow_id,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = spend_note(
+        ctx.program_id,
+        &mut pool,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &ctx.accounts.nullifier_bloom,
+        &ctx.accounts.nullifier_tree,
+    )?;
+    emit_cpi!(spent);
+
+    // Pay the recipient net of the fee from the vault PDA
+    let payout = sol_payout!(ctx);
+    let protocol_config = &ctx.accounts.protocol_config;
+    pay_sol_unshield(&pool_key, ctx.bumps.vault, &payout, protocol_config, amount, fee)?;
+    record_unshield(&mut pool, &ctx.accounts.pool_stats, amount, fee, clock.slot)?;
+    pool.check_solvency(ctx.accounts.vault.lamports())?;
+
+    emit_cpi!(UnshieldEvent {
+        pool: pool_key,
+        mint: pool.mint,
+        nullifier,
+        recipient: recipient_key,
+        amount,
+        fee,
+        relayer: relayer_key,
+        slot: clock.slot,
+    });
+    msg!("Unshielded {} lamports ({} fee)", amount, fee);
+    msg!("Nullifier spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process UnshieldEscrow instruction
+///
+/// SPL counterpart of `process_unshield_sol_escrow`.
+#[allow(clippy::too_many_arguments)]
+pub fn process_unshield_escrow(
+    ctx: Context<UnshieldEscrow>,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    fee: u64,
+    refund: u64,
+    expiry_slot: u64,
+    arbiter: Pubkey,
+    escrow_id: [u8; 32],
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    pool.require_spl_pool()?;
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    pool.require_anonymity_set()?;
+    let clock = Clock::get()?;
+
+    // Validate
+    require!(amount > 0, NyxError::InvalidAmount);
+    require!(fee <= amount, NyxError::FeeExceedsAmount);
+    // The fee may also reimburse the SOL the relayer fronts, if it fronts any
+    let created = pool_token::create_associated_token_account_if_missing(
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.recipient_token_account,
+        &ctx.accounts.recipient,
+        &ctx.accounts.mint.to_account_info(),
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.associated_token_program.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    require!(
+        fee <= pool.max_unshield_fee(amount, created || refund > 0),
+        NyxError::FeeAboveRelayerRate
+    );
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
+
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, clock.slot)?;
+    // For SPL tokens, the recipient owns the token account
+    let recipient_key = ctx.accounts.recipient.key();
+    let relayer_key = ctx.accounts.relayer.key();
+
+    // Only the arbiter decides who an escrow note is paid to
+    escrow::require_arbiter_resolution(
+        &ctx.accounts.instructions_sysvar,
+        &arbiter,
+        &escrow::resolution_message(&pool_key, &escrow_id, &recipient_key),
+    )?;
+
+    // Verify the proof
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_escrow_unshield_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &recipient_key,
+        amount,
+        &relayer_key,
+        fee,
+        refund,
+        &root,
+        expiry_slot,
+        &arbiter,
+        &escrow_id,
+    )?;
+    require!(valid, NyxError::InvalidProof);
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
+
+    // Record in pool stats
+    pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &nullifier)?;
+
+    // Transfer SPL tokens from vault to recipient
+    let vault_bump = ctx.bumps.vault_authority;
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[vault_bump],
+    ]];
 
-    // Add commitment to tree
-    let leaf_index = pool.add_commitment(commitment)?;
+    // Pay the recipient net of the fee, split between relayer and treasury.
+    // The rent reimbursement is the relayer's alone
+    let (rate_fee, rent_fee) = pool.split_rent_fee(amount, fee);
+    let (relayer_fee, treasury_fee) = ctx.accounts.protocol_config.split_relayer_fee(rate_fee);
+    for (to, tokens) in [
+        (ctx.accounts.recipient_token_account.to_account_info(), amount - fee),
+        (ctx.accounts.relayer_token_account.to_account_info(), relayer_fee + rent_fee),
+        (ctx.accounts.treasury_token_account.to_account_info(), treasury_fee),
+    ] {
+        if tokens == 0 {
+            continue;
+        }
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.vault_token_account.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to,
+            authority: ctx.accounts.vault_authority.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        token_interface::transfer_checked(cpi_context, tokens, ctx.accounts.mint.decimals)?;
+    }
+    pool.record_fee_collected(fee);
+    pool.record_unshielded(amount)?;
+    pool.withdrawal_limit.record(clock.slot, amount, 1)?;
+    ctx.accounts.vault_token_account.reload()?;
+    pool.check_solvency(ctx.accounts.vault_token_account.amount)?;
+    record_withdrawal_stats(&ctx.accounts.pool_stats, 1, amount, fee)?;
 
-    // Record deposit for anonymity set tracking
-    pool.record_deposit();
+    // Forward the refund so the recipient can pay for its first transactions
+    if refund > 0 {
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.relayer.to_account_info(),
+                to: ctx.accounts.recipient.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, refund)?;
+    }
 
-    msg!("Shielded {} lamports at index {}", amount, leaf_index);
-    msg!("Pool denomination: {} (0=custom)", pool.denomination);
-    msg!("Pool deposit count: {}", pool.deposit_count);
-    msg!("New root: {:?}", pool.current_root());
+    emit_cpi!(UnshieldEvent {
+        pool: pool_key,
+        mint: pool.mint,
+        nullifier,
+        recipient: recipient_key,
+        amount,
+        fee,
+        relayer: relayer_key,
+        slot: clock.slot,
+    });
+    msg!("Unshielded {} tokens ({} fee)", amount, fee);
+    msg!("Nullifier spent at slot {}", clock.slot);
 
     Ok(())
 }
 
-/// Process Shield SPL token instruction
-pub fn process_shield(ctx: Context<Shield>, commitment: [u8; 32], amount: u64) -> Result<()> {
-    let pool = &mut ctx.accounts.pool;
+/// Process UnshieldMultiAsset instruction
+///
+/// As `process_unshield`, paying out of the vault for the given mint with
+/// the mint's asset ID bound into the proof.
+#[allow(clippy::too_many_arguments)]
+pub fn process_unshield_multi_asset(
+    ctx: Context<UnshieldMultiAsset>,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    amount: u64,
+    fee: u64,
+    refund: u64,
+    expiry_slot: u64,
+    proof: Vec<u8>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_multi_asset()?;
+    pool.require_unshields_allowed()?;
+    let clock = Clock::get()?;
+    let recipient_key = ctx.accounts.recipient_token_account.owner;
+    let relayer_key = ctx.accounts.relayer.key();
 
-    // Validate amount
-    require!(amount > 0, NyxError::InvalidAmount);
-    require!(
-        pool.commitment_count() < MAX_COMMITMENTS,
-        NyxError::PoolFull
-    );
+    // Validate
+    check_unshield(
+        &pool,
+        &root_history,
+        &ctx.accounts.tree_epoch,
+        &relayer_key,
+        ctx.accounts.relayer_bond.as_deref(),
+        &root,
+        amount,
+        fee,
+        &proof,
+        expiry_slot,
+        clock.slot,
+    )?;
 
-    // Validate denomination (if fixed pool, amount must match exactly)
-    require!(
-        pool.validate_amount(amount),
-        NyxError::InvalidDenomination
-    );
+    // Verify the proof, binding the withdrawn mint's asset
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_multi_asset_unshield_proof(
+        &verifier,
+        &proof,
+        &pool_key,
+        &pool.mint,
+        &nullifier,
+        &recipient_key,
+        amount,
+        &relayer_key,
+        fee,
+        refund,
+        &root,
+        expiry_slot,
+        verification::asset_id(&ctx.accounts.mint.key()),
+    )?;
+    require!(valid, NyxError::InvalidProof);
 
-    // Transfer SPL tokens from depositor to vault
-    let cpi_accounts = token::Transfer {
-        from: ctx.accounts.depositor_token_account.to_account_info(),
-        to: ctx.accounts.vault_token_account.to_account_info(),
-        authority: ctx.accounts.depositor.to_account_info(),
-    };
-    let cpi_context = CpiContext::new(
-        ctx.accounts.token_program.to_account_info(),
-        cpi_accounts,
-    );
-    token::transfer(cpi_context, amount)?;
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = spend_note(
+        ctx.program_id,
+        &mut pool,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &ctx.accounts.nullifier_bloom,
+        &ctx.accounts.nullifier_tree,
+    )?;
+    emit_cpi!(spent);
 
-    // Add commitment to tree
-    let leaf_index = pool.add_commitment(commitment)?;
+    // Transfer SPL tokens from the mint's vault to recipient
+    let vault_bump = ctx.bumps.vault_authority;
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[vault_bump],
+    ]];
 
-    // Record deposit for anonymity set tracking
-    pool.record_deposit();
+    // Pay the recipient net of the fee, split between relayer and treasury
+    let (relayer_fee, treasury_fee) = ctx.accounts.protocol_config.split_relayer_fee(fee);
+    for (to, tokens) in [
+        (ctx.accounts.recipient_token_account.to_account_info(), amount - fee),
+        (ctx.accounts.relayer_token_account.to_account_info(), relayer_fee),
+        (ctx.accounts.treasury_token_account.to_account_info(), treasury_fee),
+    ] {
+        if tokens == 0 {
+            continue;
+        }
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.vault_token_account.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to,
+            authority: ctx.accounts.vault_authority.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        token_interface::transfer_checked(cpi_context, tokens, ctx.accounts.mint.decimals)?;
+    }
+    record_unshield(&mut pool, &ctx.accounts.pool_stats, amount, fee, clock.slot)?;
 
-    msg!("Shielded {} tokens at index {}", amount, leaf_index);
-    msg!("Pool denomination: {} (0=custom)", pool.denomination);
-    msg!("Pool deposit count: {}", pool.deposit_count);
-    msg!("New root: {:?}", pool.current_root());
+    // Forward the refund so the recipient can pay for its first transactions
+    if refund > 0 {
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.relayer.to_account_info(),
+                to: ctx.accounts.recipient.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, refund)?;
+    }
+
+    emit_cpi!(UnshieldEvent {
+        pool: pool_key,
+        mint: ctx.accounts.mint.key(),
+        nullifier,
+        recipient: recipient_key,
+        amount,
+        fee,
+        relayer: relayer_key,
+        slot: clock.slot,
+    });
+    msg!("Unshielded {} tokens of {} ({} fee)", amount, ctx.accounts.mint.key(), fee);
+    msg!("Nullifier spent at slot {}", clock.slot);
 
     Ok(())
 }
 
-/// Process Transfer instruction
-pub fn process_transfer(
-    ctx: Context<Transfer>,
+/// Process UnshieldNft instruction
+///
+/// As `process_unshield_multi_asset` for an amount of 1 with no fee. The
+/// emptied vault is closed and its rent returned to the relayer, which
+/// covers the recipient's token account.
+pub fn process_unshield_nft(
+    ctx: Context<UnshieldNft>,
     nullifier: [u8; 32],
-    new_commitment: [u8; 32],
+    root: [u8; 32],
+    refund: u64,
+    expiry_slot: u64,
     proof: Vec<u8>,
 ) -> Result<()> {
-    let pool = &mut ctx.accounts.pool;
-    let nullifier_marker = &mut ctx.accounts.nullifier_marker;
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_nft()?;
+    pool.require_unshields_allowed()?;
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    pool.require_anonymity_set()?;
     let clock = Clock::get()?;
 
-    // Validate proof length (96 bytes for MVP: 64 signature + 32 pubkey)
-    require!(proof.len() >= MvpProof::SIZE, NyxError::InvalidProof);
-
-    // Note: Double-spend prevention is handled by Anchor's init constraint
+    // Validate
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
 
-    // Get current root for verification
-    let root = pool.current_root();
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, clock.slot)?;
+    let recipient_key = ctx.accounts.recipient_token_account.owner;
+    let relayer_key = ctx.accounts.relayer.key();
+    let mint = ctx.accounts.mint.key();
 
-    // Verify the proof
-    let valid = verification::verify_transfer_proof(
+    // Verify the proof, binding the withdrawn NFT
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_multi_asset_unshield_proof(
+        &verifier,
         &proof,
+        &pool_key,
+        &pool.mint,
         &nullifier,
-        &new_commitment,
+        &recipient_key,
+        1,
+        &relayer_key,
+        0,
+        refund,
         &root,
+        expiry_slot,
+        verification::asset_id(&mint),
     )?;
     require!(valid, NyxError::InvalidProof);
 
-    // Initialize nullifier marker (marks nullifier as spent)
-    nullifier_marker.pool = pool.key();
-    nullifier_marker.nullifier = nullifier;
-    nullifier_marker.spent_at = clock.slot;
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
 
     // Record in pool stats
     pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &nullifier)?;
 
-    // Add new commitment
-    let leaf_index = pool.add_commitment(new_commitment)?;
+    // Release the NFT to the recipient and close its vault
+    let vault_bump = ctx.bumps.vault_authority;
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[vault_bump],
+    ]];
+    let cpi_accounts = TransferChecked {
+        from: ctx.accounts.vault_token_account.to_account_info(),
+        mint: ctx.accounts.mint.to_account_info(),
+        to: ctx.accounts.recipient_token_account.to_account_info(),
+        authority: ctx.accounts.vault_authority.to_account_info(),
+    };
+    let cpi_context = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        cpi_accounts,
+        signer_seeds,
+    );
+    token_interface::transfer_checked(cpi_context, 1, ctx.accounts.mint.decimals)?;
+    let cpi_context = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        token_interface::CloseAccount {
+            account: ctx.accounts.vault_token_account.to_account_info(),
+            destination: ctx.accounts.relayer.to_account_info(),
+            authority: ctx.accounts.vault_authority.to_account_info(),
+        },
+        signer_seeds,
+    );
+    token_interface::close_account(cpi_context)?;
+    pool.record_unshielded(1)?;
+    pool.withdrawal_limit.record(clock.slot, 1, 1)?;
+    record_withdrawal_stats(&ctx.accounts.pool_stats, 1, 1, 0)?;
 
-    msg!("Private transfer complete");
-    msg!("New commitment at index {}", leaf_index);
+    // Forward the refund so the recipient can pay for its first transactions
+    if refund > 0 {
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.relayer.to_account_info(),
+                to: ctx.accounts.recipient.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, refund)?;
+    }
+
+    emit_cpi!(UnshieldEvent {
+        pool: pool_key,
+        mint,
+        nullifier,
+        recipient: recipient_key,
+        amount: 1,
+        fee: 0,
+        relayer: relayer_key,
+        slot: clock.slot,
+    });
+    msg!("Unshielded NFT {}", mint);
     msg!("Nullifier spent at slot {}", clock.slot);
 
     Ok(())
 }
 
-/// Process Unshield SOL instruction
-pub fn process_unshield_sol(
-    ctx: Context<UnshieldSol>,
+/// Process UnshieldCnft instruction
+///
+/// As `process_unshield_nft`, with the vault authority signing the leaf
+/// over to the recipient through Bubblegum.
+#[allow(clippy::too_many_arguments)]
+pub fn process_unshield_cnft<'info>(
+    ctx: Context<'_, '_, 'info, 'info, UnshieldCnft<'info>>,
     nullifier: [u8; 32],
-    amount: u64,
+    root: [u8; 32],
+    refund: u64,
+    expiry_slot: u64,
+    leaf: CnftLeaf,
     proof: Vec<u8>,
 ) -> Result<()> {
-    let pool = &mut ctx.accounts.pool;
-    let nullifier_marker = &mut ctx.accounts.nullifier_marker;
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    pool.require_nft()?;
+    pool.require_unshields_allowed()?;
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    pool.require_anonymity_set()?;
     let clock = Clock::get()?;
 
     // Validate
-    require!(amount > 0, NyxError::InvalidAmount);
-    require!(proof.len() >= MvpProof::SIZE, NyxError::InvalidProof);
+    require!(ProofType::detect(&proof).is_some(), NyxError::InvalidProof);
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
 
-    // Note: Double-spend prevention is handled by Anchor's init constraint
-
-    // Get current root for verification
-    let root = pool.current_root();
+    // The claimed root must belong to the note's tree and still be accepted
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, clock.slot)?;
     let recipient_key = ctx.accounts.recipient.key();
+    let relayer_key = ctx.accounts.relayer.key();
+    let asset = leaf.asset_id(&ctx.accounts.merkle_tree.key());
 
-    // Verify the proof
-    let valid = verification::verify_unshield_proof(
+    // Verify the proof, binding the withdrawn NFT
+    let verifier = pool_verifier(
+        &pool,
+        clock.slot,
+        &ctx.accounts.verifying_key,
+        &ctx.accounts.plonk_verifying_key,
+    )?;
+    let valid = verification::verify_multi_asset_unshield_proof(
+        &verifier,
         &proof,
+        &pool_key,
+        &pool.mint,
         &nullifier,
         &recipient_key,
-        amount,
+        1,
+        &relayer_key,
+        0,
+        refund,
         &root,
+        expiry_slot,
+        verification::asset_id(&asset),
     )?;
     require!(valid, NyxError::InvalidProof);
 
-    // Initialize nullifier marker (marks nullifier as spent)
-    nullifier_marker.pool = pool.key();
-    nullifier_marker.nullifier = nullifier;
-    nullifier_marker.spent_at = clock.slot;
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
 
     // Record in pool stats
     pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &nullifier)?;
 
-    // Transfer SOL from vault PDA to recipient using invoke_signed
-    let vault_lamports = ctx.accounts.vault.lamports();
-    require!(vault_lamports >= amount, pool_token::TokenError::InsufficientFunds);
-
-    // Get vault bump for PDA signing
-    let pool_key = pool.key();
-    let vault_bump = ctx.bumps.vault;
+    // Release the leaf to the recipient, signing as its owner
+    let vault_bump = ctx.bumps.vault_authority;
     let signer_seeds: &[&[&[u8]]] = &[&[
         pool_token::VAULT_SEED,
         pool_key.as_ref(),
         &[vault_bump],
     ]];
+    bubblegum::TransferAccounts {
+        tree_config: &ctx.accounts.tree_config,
+        leaf_owner: &ctx.accounts.vault_authority,
+        leaf_delegate: &ctx.accounts.vault_authority,
+        new_leaf_owner: &ctx.accounts.recipient,
+        merkle_tree: &ctx.accounts.merkle_tree,
+        log_wrapper: &ctx.accounts.log_wrapper,
+        compression_program: &ctx.accounts.compression_program,
+        system_program: &ctx.accounts.system_program.to_account_info(),
+        bubblegum_program: &ctx.accounts.bubblegum_program,
+    }
+    .transfer(&leaf, ctx.remaining_accounts, signer_seeds)?;
+    pool.record_unshielded(1)?;
+    pool.withdrawal_limit.record(clock.slot, 1, 1)?;
+    record_withdrawal_stats(&ctx.accounts.pool_stats, 1, 1, 0)?;
 
-    // Transfer SOL from vault PDA (requires PDA signature)
-    anchor_lang::solana_program::program::invoke_signed(
-        &anchor_lang::solana_program::system_instruction::transfer(
-            ctx.accounts.vault.key,
-            ctx.accounts.recipient.key,
-            amount,
-        ),
-        &[
-            ctx.accounts.vault.to_account_info(),
-            ctx.accounts.recipient.to_account_info(),
+    // Forward the refund so the recipient can pay for its first transactions
+    if refund > 0 {
+        let cpi_context = CpiContext::new(
             ctx.accounts.system_program.to_account_info(),
-        ],
-        signer_seeds,
-    )?;
+            system_program::Transfer {
+                from: ctx.accounts.relayer.to_account_info(),
+                to: ctx.accounts.recipient.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, refund)?;
+    }
 
-    msg!("Unshielded {} lamports", amount);
+    emit_cpi!(UnshieldEvent {
+        pool: pool_key,
+        mint: asset,
+        nullifier,
+        recipient: recipient_key,
+        amount: 1,
+        fee: 0,
+        relayer: relayer_key,
+        slot: clock.slot,
+    });
+    msg!("Unshielded compressed NFT {}", asset);
     msg!("Nullifier spent at slot {}", clock.slot);
 
     Ok(())
 }
 
-/// Process Unshield SPL token instruction
-pub fn process_unshield(
-    ctx: Context<Unshield>,
+/// Process VerifyStage1 instruction
+///
+/// Validates the withdrawal parameters, parses the proof and computes the
+/// public input linear combination, storing both for stage 2.
+#[allow(clippy::too_many_arguments)]
+pub fn process_verify_stage_1(
+    ctx: Context<VerifyStage1>,
     nullifier: [u8; 32],
+    root: [u8; 32],
     amount: u64,
+    fee: u64,
+    refund: u64,
+    expiry_slot: u64,
     proof: Vec<u8>,
 ) -> Result<()> {
-    let pool = &mut ctx.accounts.pool;
-    let nullifier_marker = &mut ctx.accounts.nullifier_marker;
+    let (pool, root_history) = state::load_pool(&ctx.accounts.pool)?;
+    pool.require_unshields_allowed()?;
+    pool.require_single_asset()?;
+    pool.require_anonymity_set()?;
     let clock = Clock::get()?;
 
     // Validate
     require!(amount > 0, NyxError::InvalidAmount);
-    require!(proof.len() >= MvpProof::SIZE, NyxError::InvalidProof);
-
-    // Note: Double-spend prevention is handled by Anchor's init constraint
+    require!(fee <= amount, NyxError::FeeExceedsAmount);
+    require!(fee <= pool.calculate_relayer_fee(amount), NyxError::FeeAboveRelayerRate);
+    // Staged withdrawals pay out SOL, which never carries a refund
+    require!(refund == 0, NyxError::InvalidRefund);
+    require!(clock.slot <= expiry_slot, NyxError::ProofExpired);
+    // Only Groth16 proofs are expensive enough to need staging
+    require!(
+        pool.proof_system() == ProofSystem::Groth16,
+        NyxError::UnsupportedProofSystem
+    );
+    require!(
+        pool.is_vk_version_accepted(ctx.accounts.verifying_key.version, clock.slot),
+        NyxError::VkVersionNotAccepted
+    );
+    require!(
+        ProofType::detect(&proof) == Some(ProofType::Groth16),
+        NyxError::InvalidProof
+    );
+    let parsed = Groth16Proof::deserialize(&proof).ok_or(NyxError::InvalidProof)?;
 
-    // Get current root for verification
-    let root = pool.current_root();
-    // For SPL tokens, use the token account owner as recipient
-    let recipient_key = ctx.accounts.recipient_token_account.owner;
+    verification::require_canonical_inputs(&[&nullifier])?;
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, clock.slot)?;
+    let recipient_key = ctx.accounts.recipient.key();
+    let relayer_key = ctx.accounts.relayer.key();
 
-    // Verify the proof
-    let valid = verification::verify_unshield_proof(
-        &proof,
+    let public_inputs = WithdrawPublicInputs::new(
+        &root,
         &nullifier,
         &recipient_key,
         amount,
-        &root,
-    )?;
+        &relayer_key,
+        fee,
+        refund,
+        expiry_slot,
+        &verification::nullifier_domain(&ctx.accounts.pool.key(), &pool.mint),
+    );
+    let vk = ctx.accounts.verifying_key.as_groth16_vk();
+    let l_point = groth16::compute_linear_combination(&public_inputs.to_verifier_inputs(), &vk)?;
+
+    let state = &mut ctx.accounts.verification_state;
+    state.pool = ctx.accounts.pool.key();
+    state.relayer = relayer_key;
+    state.nullifier = nullifier;
+    state.recipient = recipient_key;
+    state.amount = amount;
+    state.fee = fee;
+    state.refund = refund;
+    state.expiry_slot = expiry_slot;
+    state.root = root;
+    state.vk_version = ctx.accounts.verifying_key.version;
+    state.proof = parsed.to_bytes();
+    state.l_point = l_point;
+    state.stage = VerificationStage::InputsPrepared;
+    state.bump = ctx.bumps.verification_state;
+
+    msg!("Staged verification: inputs prepared");
+    Ok(())
+}
+
+/// Process VerifyStage2 instruction
+///
+/// Runs the pairing check against the L point computed in stage 1.
+pub fn process_verify_stage_2(ctx: Context<VerifyStage2>) -> Result<()> {
+    let state = &mut ctx.accounts.verification_state;
+    require!(
+        state.stage == VerificationStage::InputsPrepared,
+        NyxError::InvalidVerificationStage
+    );
+
+    let proof = state.groth16_proof().ok_or(NyxError::InvalidProof)?;
+    let vk = ctx.accounts.verifying_key.as_groth16_vk();
+    let valid = groth16::check_pairing(&proof, &state.l_point, &vk)?;
+    require!(valid, NyxError::InvalidProof);
+
+    state.stage = VerificationStage::Verified;
+
+    msg!("Staged verification: proof verified");
+    Ok(())
+}
+
+/// Process VerifyBatch instruction
+///
+/// Loads every verification state from the remaining accounts, checks they
+/// belong to this pool and relayer and are awaiting the pairing check, then
+/// verifies all of them with one batched pairing.
+pub fn process_verify_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, VerifyBatch<'info>>,
+) -> Result<()> {
+    let remaining = ctx.remaining_accounts;
+    require!(
+        !remaining.is_empty() && remaining.len() <= MAX_BATCH_SIZE,
+        NyxError::InvalidBatchSize
+    );
+
+    let pool = ctx.accounts.pool.load()?;
+    require!(
+        pool.proof_system() == ProofSystem::Groth16,
+        NyxError::UnsupportedProofSystem
+    );
+    let vk_version = ctx.accounts.verifying_key.version;
+    require!(
+        pool.is_vk_version_accepted(vk_version, Clock::get()?.slot),
+        NyxError::VkVersionNotAccepted
+    );
+
+    let pool_key = ctx.accounts.pool.key();
+    let relayer_key = ctx.accounts.relayer.key();
+
+    let mut states = Vec::with_capacity(remaining.len());
+    let mut proofs = Vec::with_capacity(remaining.len());
+    let mut l_points = Vec::with_capacity(remaining.len());
+
+    for account_info in remaining {
+        let state: Account<'info, VerificationState> = Account::try_from(account_info)?;
+
+        // The account must be the canonical PDA for its pool, relayer and
+        // nullifier
+        let expected = Pubkey::create_program_address(
+            &[
+                VERIFICATION_SEED,
+                pool_key.as_ref(),
+                relayer_key.as_ref(),
+                &state.nullifier,
+                &[state.bump],
+            ],
+            ctx.program_id,
+        )
+        .map_err(|_| NyxError::InvalidVerificationState)?;
+        require_keys_eq!(expected, account_info.key(), NyxError::InvalidVerificationState);
+        require_keys_eq!(state.pool, pool_key, NyxError::InvalidVerificationState);
+        require_keys_eq!(state.relayer, relayer_key, NyxError::Unauthorized);
+        require!(
+            state.stage == VerificationStage::InputsPrepared,
+            NyxError::InvalidVerificationStage
+        );
+        // L was computed against this key's IC points in stage 1
+        require!(state.vk_version == vk_version, NyxError::InvalidVerificationState);
+
+        proofs.push(state.groth16_proof().ok_or(NyxError::InvalidProof)?);
+        l_points.push(state.l_point);
+        states.push(state);
+    }
+
+    let vk = ctx.accounts.verifying_key.as_groth16_vk();
+    let valid = verification::verify_batch(&vk, &proofs, &l_points)?;
     require!(valid, NyxError::InvalidProof);
 
-    // Initialize nullifier marker (marks nullifier as spent)
-    nullifier_marker.pool = pool.key();
-    nullifier_marker.nullifier = nullifier;
-    nullifier_marker.spent_at = clock.slot;
+    for state in states.iter_mut() {
+        state.stage = VerificationStage::Verified;
+        state.exit(ctx.program_id)?;
+    }
+
+    msg!("Batch verified: {} proofs", states.len());
+    Ok(())
+}
+
+/// Process FinalizeUnshield instruction
+///
+/// Spends the nullifier and transfers SOL to the recipient recorded in
+/// stage 1. The verification state is closed back to the relayer.
+pub fn process_finalize_unshield(ctx: Context<FinalizeUnshield>) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    pool.require_unshields_allowed()?;
+    relayer::require_relayer(
+        &pool,
+        &ctx.accounts.relayer.key(),
+        ctx.accounts.relayer_bond.as_deref(),
+    )?;
+    pool.require_anonymity_set()?;
+    let state = &ctx.accounts.verification_state;
+    let clock = Clock::get()?;
+
+    require!(
+        state.stage == VerificationStage::Verified,
+        NyxError::InvalidVerificationStage
+    );
+    require!(clock.slot <= state.expiry_slot, NyxError::ProofExpired);
+
+    let amount = state.amount;
+    let fee = state.fee;
+
+    // Add the nullifier to the pool's nullifier set (fails if already spent)
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &pool_key,
+        &state.nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.relayer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
 
     // Record in pool stats
     pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &state.nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &state.nullifier)?;
 
-    // Transfer SPL tokens from vault to recipient
-    let pool_key = pool.key();
-    let vault_bump = ctx.bumps.vault_authority;
+    // Transfer SOL from vault PDA to recipient using invoke_signed
+    let vault_lamports = ctx.accounts.vault.lamports();
+    require!(vault_lamports >= amount, pool_token::TokenError::InsufficientFunds);
+
+    let vault_bump = ctx.bumps.vault;
     let signer_seeds: &[&[&[u8]]] = &[&[
         pool_token::VAULT_SEED,
         pool_key.as_ref(),
         &[vault_bump],
     ]];
 
-    let cpi_accounts = token::Transfer {
-        from: ctx.accounts.vault_token_account.to_account_info(),
-        to: ctx.accounts.recipient_token_account.to_account_info(),
-        authority: ctx.accounts.vault_authority.to_account_info(),
+    // Pay the recipient net of the fee, split between relayer and treasury
+    let (relayer_fee, treasury_fee) = ctx.accounts.protocol_config.split_relayer_fee(fee);
+    let vault = ctx.accounts.vault.to_account_info();
+    let system_program = ctx.accounts.system_program.to_account_info();
+    for (to, lamports) in [
+        (ctx.accounts.recipient.to_account_info(), amount - fee),
+        (ctx.accounts.relayer.to_account_info(), relayer_fee),
+        (ctx.accounts.treasury.to_account_info(), treasury_fee),
+    ] {
+        pay_from_vault(&vault, &to, &system_program, lamports, signer_seeds)?;
+    }
+    pool.record_fee_collected(fee);
+    pool.record_unshielded(amount)?;
+    pool.withdrawal_limit.record(clock.slot, amount, 1)?;
+    pool.check_solvency(ctx.accounts.vault.lamports())?;
+    record_withdrawal_stats(&ctx.accounts.pool_stats, 1, amount, fee)?;
+
+    emit_cpi!(UnshieldEvent {
+        pool: pool_key,
+        mint: pool.mint,
+        nullifier: state.nullifier,
+        recipient: ctx.accounts.recipient.key(),
+        amount,
+        fee,
+        relayer: ctx.accounts.relayer.key(),
+        slot: clock.slot,
+    });
+    msg!("Unshielded {} lamports ({} fee, staged)", amount, fee);
+    msg!("Nullifier spent at slot {}", clock.slot);
+
+    Ok(())
+}
+
+/// Process CloseVerificationState instruction
+///
+/// Anchor closes the verification state back to the relayer.
+pub fn process_close_verification_state(ctx: Context<CloseVerificationState>) -> Result<()> {
+    let state = &ctx.accounts.verification_state;
+    msg!("Staged unshield abandoned at stage {:?}", state.stage);
+    Ok(())
+}
+
+/// Process CommitEmergencyWithdraw instruction
+pub fn process_commit_emergency_withdraw(
+    ctx: Context<CommitEmergencyWithdraw>,
+    _nullifier: [u8; 32],
+    recipient: Pubkey,
+) -> Result<()> {
+    let slot = Clock::get()?.slot;
+    let emergency_commit = &mut ctx.accounts.emergency_commit;
+    emergency_commit.pool = ctx.accounts.pool.key();
+    emergency_commit.recipient = recipient;
+    emergency_commit.payer = ctx.accounts.payer.key();
+    emergency_commit.committed_slot = slot;
+    emergency_commit.bump = ctx.bumps.emergency_commit;
+
+    msg!("Emergency withdrawal committed at slot {}", slot);
+    Ok(())
+}
+
+/// Process EmergencyWithdraw instruction
+///
+/// Recomputes the note's commitment and nullifier from the revealed
+/// preimage, checks the commitment is in the tree, spends the nullifier so
+/// the note can't also be unshielded later, and pays out the denomination.
+/// The pool's relayer policy doesn't apply (see `relayer::require_relayer`).
+#[allow(clippy::too_many_arguments)]
+pub fn process_emergency_withdraw(
+    ctx: Context<EmergencyWithdraw>,
+    spending_key: [u8; 32],
+    blinding: [u8; 32],
+    asset_id: u64,
+    leaf_index: u64,
+    root: [u8; 32],
+    path: Vec<[u8; 32]>,
+) -> Result<()> {
+    let pool_key = ctx.accounts.pool.key();
+    let (mut pool, root_history) = state::load_pool_mut(&ctx.accounts.pool)?;
+    let slot = Clock::get()?.slot;
+    pool.require_emergency_withdrawals_allowed(slot)?;
+
+    // Shields don't prove a note's amount, so only fixed pools can trust it
+    require!(pool.denomination > 0, NyxError::EmergencyWithdrawUnsupported);
+    pool.require_poseidon_tree()?;
+    let amount = pool.denomination;
+
+    // The commitment must be a leaf under a root of the note's tree
+    check_spend_root(&pool, &root_history, &ctx.accounts.tree_epoch, &root, slot)?;
+    let commitment = verification::note_commitment(&spending_key, amount, &blinding, asset_id)?;
+    let valid = if path.len() == TREE_DEPTH {
+        let siblings: [[u8; 32]; TREE_DEPTH] = path
+            .try_into()
+            .map_err(|_| NyxError::InvalidMerkleProof)?;
+        merkle::verify_merkle_proof(&commitment, leaf_index, &siblings, &root)
+    } else if path.len() == TREE_DEPTH - CANOPY_DEPTH && root == pool.current_root() {
+        merkle::verify_merkle_proof_with_canopy(&commitment, leaf_index, &path, &pool.canopy, &root)
+    } else {
+        false
     };
-    let cpi_context = CpiContext::new_with_signer(
-        ctx.accounts.token_program.to_account_info(),
-        cpi_accounts,
-        signer_seeds,
+    require!(valid, NyxError::InvalidMerkleProof);
+
+    // Spend the note's nullifier so it can't be unshielded as well
+    let nullifier = verification::note_nullifier(
+        &spending_key,
+        leaf_index,
+        &verification::nullifier_domain(&pool_key, &pool.mint),
+    )?;
+
+    // The recipient must have been committed for this note before the reveal
+    let emergency_commit = &ctx.accounts.emergency_commit;
+    let commit_address = Pubkey::create_program_address(
+        &[
+            state::EMERGENCY_COMMIT_SEED,
+            pool_key.as_ref(),
+            &nullifier,
+            &[emergency_commit.bump],
+        ],
+        ctx.program_id,
+    )
+    .map_err(|_| NyxError::EmergencyWithdrawNotCommitted)?;
+    require!(
+        commit_address == emergency_commit.key() && emergency_commit.committed_slot < slot,
+        NyxError::EmergencyWithdrawNotCommitted
     );
-    token::transfer(cpi_context, amount)?;
+    let spent = nullifier::spend_nullifier(
+        ctx.program_id,
+        &pool_key,
+        &nullifier,
+        &ctx.accounts.nullifier_marker,
+        &ctx.accounts.nullifier_set,
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    emit_cpi!(spent);
+    pool.record_nullifier_spent();
+    record_in_bloom(&ctx.accounts.nullifier_bloom, &nullifier)?;
+    record_in_tree(&ctx.accounts.nullifier_tree, &nullifier)?;
 
-    msg!("Unshielded {} tokens", amount);
-    msg!("Nullifier spent at slot {}", clock.slot);
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        pool_token::VAULT_SEED,
+        pool_key.as_ref(),
+        &[ctx.bumps.vault],
+    ]];
+    pool.record_unshielded(amount)?;
+    if pool.mint == Pubkey::default() {
+        require!(
+            ctx.accounts.vault.lamports() >= amount,
+            pool_token::TokenError::InsufficientFunds
+        );
+        pay_from_vault(
+            &ctx.accounts.vault,
+            &ctx.accounts.recipient,
+            &ctx.accounts.system_program.to_account_info(),
+            amount,
+            signer_seeds,
+        )?;
+        pool.check_solvency(ctx.accounts.vault.lamports())?;
+    } else {
+        let (
+            Some(vault_token_account),
+            Some(recipient_token_account),
+            Some(mint),
+            Some(token_program),
+        ) = (
+            ctx.accounts.vault_token_account.as_mut(),
+            ctx.accounts.recipient_token_account.as_ref(),
+            ctx.accounts.mint.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+        ) else {
+            return err!(pool_token::TokenError::InvalidTokenAccount);
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: vault_token_account.to_account_info(),
+                mint: mint.to_account_info(),
+                to: recipient_token_account.to_account_info(),
+                authority: ctx.accounts.vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token_interface::transfer_checked(cpi_context, amount, mint.decimals)?;
+        vault_token_account.reload()?;
+        pool.check_solvency(vault_token_account.amount)?;
+    }
 
+    emit_cpi!(EmergencyWithdrawal {
+        pool: pool_key,
+        commitment,
+        leaf_index,
+        nullifier,
+        recipient: ctx.accounts.recipient.key(),
+        amount,
+        slot,
+    });
+    msg!("Emergency withdrawal of leaf {} ({} paid out)", leaf_index, amount);
     Ok(())
 }
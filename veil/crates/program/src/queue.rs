@@ -0,0 +1,114 @@
+//! Queued Withdrawals
+//!
+//! `queue_unshield` records only the hash of a SOL withdrawal, and
+//! `execute_unshield` reveals and performs it inside the window that
+//! opens `UNSHIELD_QUEUE_MIN_DELAY_SLOTS` after queueing. Nothing is
+//! spent until then, so the payer can cancel the intent at any time.
+//!
+//! The intent's rent goes back to its payer when it closes, which ties the
+//! payer to the withdrawal once executed. Queue from a relayer or a burner
+//! wallet, never from one linked to the note's owner.
+
+use anchor_lang::prelude::*;
+
+use crate::instructions::NyxError;
+use crate::state::{QueuedUnshield, QUEUED_UNSHIELD_SEED};
+
+/// Address of the intent queued in `pool` under `intent_hash`
+pub fn queued_unshield_address(pool: &Pubkey, intent_hash: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[QUEUED_UNSHIELD_SEED, pool.as_ref(), intent_hash],
+        &crate::ID,
+    )
+}
+
+/// Require `queued_unshield` to hash the given withdrawal and be
+/// executable at `slot`
+pub fn require_executable(
+    queued_unshield: &QueuedUnshield,
+    nullifier: &[u8; 32],
+    recipient: &Pubkey,
+    amount: u64,
+    fee: u64,
+    expiry_slot: u64,
+    slot: u64,
+) -> Result<()> {
+    require!(
+        queued_unshield.intent_hash
+            == QueuedUnshield::intent_hash(nullifier, recipient, amount, fee, expiry_slot),
+        NyxError::UnshieldIntentMismatch
+    );
+    require!(queued_unshield.is_executable(slot), NyxError::UnshieldOutsideWindow);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{UNSHIELD_QUEUE_MIN_DELAY_SLOTS, UNSHIELD_QUEUE_WINDOW_SLOTS};
+
+    const NULLIFIER: [u8; 32] = [7u8; 32];
+
+    fn queued(recipient: &Pubkey, queued_slot: u64) -> QueuedUnshield {
+        QueuedUnshield {
+            pool: Pubkey::new_unique(),
+            intent_hash: QueuedUnshield::intent_hash(&NULLIFIER, recipient, 1_000, 10, 500_000),
+            payer: Pubkey::new_unique(),
+            queued_slot,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn test_intent_hash_binds_withdrawal() {
+        let recipient = Pubkey::new_unique();
+        let hash = QueuedUnshield::intent_hash(&NULLIFIER, &recipient, 1_000, 10, 500_000);
+        assert_ne!(hash, QueuedUnshield::intent_hash(&[8u8; 32], &recipient, 1_000, 10, 500_000));
+        assert_ne!(
+            hash,
+            QueuedUnshield::intent_hash(&NULLIFIER, &Pubkey::new_unique(), 1_000, 10, 500_000)
+        );
+        assert_ne!(hash, QueuedUnshield::intent_hash(&NULLIFIER, &recipient, 999, 10, 500_000));
+        assert_ne!(hash, QueuedUnshield::intent_hash(&NULLIFIER, &recipient, 1_000, 11, 500_000));
+        assert_ne!(hash, QueuedUnshield::intent_hash(&NULLIFIER, &recipient, 1_000, 10, 500_001));
+    }
+
+    #[test]
+    fn test_intents_are_queued_per_pool() {
+        let (pool, hash) = (Pubkey::new_unique(), [1u8; 32]);
+        let (address, _) = queued_unshield_address(&pool, &hash);
+        assert_eq!(queued_unshield_address(&pool, &hash).0, address);
+        assert_ne!(queued_unshield_address(&Pubkey::new_unique(), &hash).0, address);
+        assert_ne!(queued_unshield_address(&pool, &[2u8; 32]).0, address);
+    }
+
+    #[test]
+    fn test_execution_window() {
+        let queued = queued(&Pubkey::new_unique(), 1_000);
+        assert!(!queued.is_executable(1_000));
+        assert!(!queued.is_executable(1_000 + UNSHIELD_QUEUE_MIN_DELAY_SLOTS - 1));
+        assert!(queued.is_executable(1_000 + UNSHIELD_QUEUE_MIN_DELAY_SLOTS));
+        assert!(queued.is_executable(1_000 + UNSHIELD_QUEUE_WINDOW_SLOTS));
+        assert!(!queued.is_executable(1_000 + UNSHIELD_QUEUE_WINDOW_SLOTS + 1));
+        // A slot before the queueing slot never executes
+        assert!(!queued.is_executable(0));
+    }
+
+    #[test]
+    fn test_executes_only_the_queued_withdrawal() {
+        let recipient = Pubkey::new_unique();
+        let queued = queued(&recipient, 0);
+        let slot = UNSHIELD_QUEUE_MIN_DELAY_SLOTS;
+        let execute = |recipient: &Pubkey, fee: u64, slot: u64| {
+            require_executable(&queued, &NULLIFIER, recipient, 1_000, fee, 500_000, slot)
+        };
+        assert!(execute(&recipient, 10, slot).is_ok());
+
+        let err = execute(&Pubkey::new_unique(), 10, slot).unwrap_err();
+        assert_eq!(err, NyxError::UnshieldIntentMismatch.into());
+        let err = execute(&recipient, 20, slot).unwrap_err();
+        assert_eq!(err, NyxError::UnshieldIntentMismatch.into());
+        let err = execute(&recipient, 10, slot - 1).unwrap_err();
+        assert_eq!(err, NyxError::UnshieldOutsideWindow.into());
+    }
+}
@@ -0,0 +1,169 @@
+//! Withdrawal Rate Limits
+//!
+//! A pool's `RateLimiter` caps the amount and number of withdrawals over a
+//! trailing window of slots, so a broken verifier or leaked key can't
+//! empty the pool at once.
+
+use anchor_lang::prelude::*;
+
+use crate::instructions::NyxError;
+
+/// Sliding-window limit on the amount and number of withdrawals
+///
+/// Slots are split into fixed windows of `window_slots`. The load over the
+/// trailing window is estimated as the current window's total plus the
+/// previous window's, weighted by how much of it the trailing window still
+/// covers. A zero `window_slots` disables the limit, and a zero maximum
+/// leaves that dimension unlimited.
+#[zero_copy]
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    /// Window length in slots (0 = disabled)
+    pub window_slots: u64,
+
+    /// Maximum amount withdrawn per window (0 = unlimited)
+    pub max_amount: u64,
+
+    /// Maximum withdrawals per window (0 = unlimited)
+    pub max_count: u64,
+
+    /// Index (`slot / window_slots`) of the current window
+    pub window_index: u64,
+
+    /// Amount withdrawn in the current window
+    pub amount: u64,
+
+    /// Withdrawals in the current window
+    pub count: u64,
+
+    /// Amount withdrawn in the previous window
+    pub prev_amount: u64,
+
+    /// Withdrawals in the previous window
+    pub prev_count: u64,
+}
+
+impl RateLimiter {
+    pub const SIZE: usize = 8 * 8;
+
+    /// Replace the limits, clearing the recorded load
+    pub fn configure(&mut self, window_slots: u64, max_amount: u64, max_count: u64) {
+        *self = RateLimiter {
+            window_slots,
+            max_amount,
+            max_count,
+            ..RateLimiter::default()
+        };
+    }
+
+    /// Record `count` withdrawals totalling `amount` at `slot`
+    ///
+    /// Fails, recording nothing, if they would exceed either limit.
+    pub fn record(&mut self, slot: u64, amount: u64, count: u64) -> Result<()> {
+        if self.window_slots == 0 {
+            return Ok(());
+        }
+
+        // Move to the window containing `slot`
+        let index = slot / self.window_slots;
+        if index != self.window_index {
+            let adjacent = index == self.window_index.saturating_add(1);
+            self.prev_amount = if adjacent { self.amount } else { 0 };
+            self.prev_count = if adjacent { self.count } else { 0 };
+            self.amount = 0;
+            self.count = 0;
+            self.window_index = index;
+        }
+
+        // Share of the previous window still inside the trailing window
+        let remaining = (self.window_slots - slot % self.window_slots) as u128;
+        let window = self.window_slots as u128;
+        let trailing = |prev: u64, current: u64, added: u64| {
+            prev as u128 * remaining / window + current as u128 + added as u128
+        };
+        require!(
+            self.max_amount == 0
+                || trailing(self.prev_amount, self.amount, amount) <= self.max_amount as u128,
+            NyxError::WithdrawalRateLimited
+        );
+        require!(
+            self.max_count == 0
+                || trailing(self.prev_count, self.count, count) <= self.max_count as u128,
+            NyxError::WithdrawalRateLimited
+        );
+
+        self.amount = self.amount.saturating_add(amount);
+        self.count = self.count.saturating_add(count);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configured(window_slots: u64, max_amount: u64, max_count: u64) -> RateLimiter {
+        let mut limiter = RateLimiter::default();
+        limiter.configure(window_slots, max_amount, max_count);
+        limiter
+    }
+
+    #[test]
+    fn test_disabled_limit_records_nothing() {
+        let mut limiter = configured(0, 100, 1);
+        limiter.record(5, u64::MAX, u64::MAX).unwrap();
+        assert_eq!((limiter.amount, limiter.count), (0, 0));
+    }
+
+    #[test]
+    fn test_amount_and_count_are_capped_per_window() {
+        let mut limiter = configured(100, 1_000, 3);
+        limiter.record(0, 600, 1).unwrap();
+        limiter.record(10, 400, 1).unwrap();
+        let err = limiter.record(20, 1, 1).unwrap_err();
+        assert_eq!(err, NyxError::WithdrawalRateLimited.into());
+
+        // The count limit holds on its own when amounts stay low
+        let mut by_count = configured(100, 0, 2);
+        by_count.record(0, 1_000_000, 1).unwrap();
+        by_count.record(1, 1_000_000, 1).unwrap();
+        let err = by_count.record(2, 1, 1).unwrap_err();
+        assert_eq!(err, NyxError::WithdrawalRateLimited.into());
+    }
+
+    #[test]
+    fn test_previous_window_weighs_by_its_overlap() {
+        let mut limiter = configured(100, 1_000, 0);
+        limiter.record(50, 1_000, 1).unwrap();
+
+        // A quarter into the next window, three quarters of the previous
+        // window's 1_000 still count
+        let err = limiter.record(125, 251, 1).unwrap_err();
+        assert_eq!(err, NyxError::WithdrawalRateLimited.into());
+        limiter.record(125, 250, 1).unwrap();
+        assert_eq!((limiter.window_index, limiter.prev_amount, limiter.amount), (1, 1_000, 250));
+
+        // Halfway in, half of it counts
+        limiter.record(150, 250, 1).unwrap();
+        let err = limiter.record(150, 1, 1).unwrap_err();
+        assert_eq!(err, NyxError::WithdrawalRateLimited.into());
+    }
+
+    #[test]
+    fn test_skipped_windows_forget_the_load() {
+        let mut limiter = configured(100, 1_000, 0);
+        limiter.record(50, 1_000, 1).unwrap();
+
+        // Two windows later nothing of the first one is left
+        limiter.record(250, 1_000, 1).unwrap();
+        assert_eq!((limiter.window_index, limiter.prev_amount), (2, 0));
+    }
+
+    #[test]
+    fn test_configure_clears_the_load() {
+        let mut limiter = configured(100, 1_000, 0);
+        limiter.record(50, 1_000, 1).unwrap();
+        limiter.configure(100, 1_000, 0);
+        limiter.record(60, 1_000, 1).unwrap();
+    }
+}
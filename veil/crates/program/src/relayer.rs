@@ -0,0 +1,159 @@
+//! Relayer Bonds
+//!
+//! Relayers register by bonding SOL in a `RelayerBond` PDA, and quote
+//! users a fee ceiling for a withdrawal by signing
+//! `fee_quote_message(pool, nullifier, relayer, max_fee)`. The fee a
+//! withdrawal pays is a public input of its proof, so a relayer that
+//! submits a withdrawal charging more than it quoted can be shown to have
+//! done so: `dispute_relayer_fee` takes the relayer's quote, signed with
+//! the Ed25519 precompile in the instruction before it, and the
+//! withdrawal's proof, checks that the proof verifies with that relayer
+//! and fee and that its nullifier was spent, and pays part of the bond to
+//! the withdrawal's recipient.
+//!
+//! The note's owner can also prove a fee for their own note that was
+//! never charged, so a quote should only be signed for a user the relayer
+//! is prepared to serve; the quote names the nullifier, which the user
+//! reveals to ask for it.
+//!
+//! Bonds can only be withdrawn `RELAYER_UNBONDING_SLOTS` after the relayer
+//! asked to unbond, leaving users time to dispute its last withdrawals.
+//!
+//! A pool's `RelayerPolicy` decides who may submit its transfers and
+//! withdrawals: anyone, only relayers with a bond that isn't unbonding, or
+//! only the relayers its authority allowed.
+
+use anchor_lang::prelude::*;
+use solana_program::hash;
+
+use crate::state::{PrivacyPool, RelayerBond, RELAYER_SLASH_BPS};
+
+/// Domain separator of fee quote messages
+pub const FEE_QUOTE_DOMAIN: &[u8] = b"NYX_RELAYER_FEE_QUOTE";
+
+/// Message a relayer signs to charge at most `max_fee` for withdrawing
+/// `nullifier`'s note from `pool`
+///
+/// Message = sha256(FEE_QUOTE_DOMAIN || pool || nullifier || relayer || max_fee)
+pub fn fee_quote_message(
+    pool: &Pubkey,
+    nullifier: &[u8; 32],
+    relayer: &Pubkey,
+    max_fee: u64,
+) -> [u8; 32] {
+    hash::hashv(&[
+        FEE_QUOTE_DOMAIN,
+        pool.as_ref(),
+        nullifier,
+        relayer.as_ref(),
+        &max_fee.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Lamports slashed from a bond of `bond` lamports for overcharging by
+/// `overcharge` lamports: the overcharge, plus `RELAYER_SLASH_BPS` of it as
+/// a penalty, capped at the bond
+///
+/// The penalty scales with the overcharge, so a withdrawal proven a unit
+/// above its quote only costs the relayer a unit or two.
+pub fn slash_amount(bond: u64, overcharge: u64) -> u64 {
+    let penalty = (overcharge as u128 * RELAYER_SLASH_BPS as u128 / 10_000) as u64;
+    overcharge.saturating_add(penalty).min(bond)
+}
+
+/// Fail unless the pool's relayer policy lets `relayer` submit a spend,
+/// `relayer_bond` being its bond when passed
+///
+/// Every spend checks it, `unshield_self` against its recipient, except
+/// `emergency_withdraw`: the escape hatch for when proofs can't be
+/// verified stays open to a note's owner whatever the policy.
+pub fn require_relayer(
+    pool: &PrivacyPool,
+    relayer: &Pubkey,
+    relayer_bond: Option<&RelayerBond>,
+) -> Result<()> {
+    let bonded = relayer_bond
+        .is_some_and(|bond| bond.relayer == *relayer && bond.unbond_requested_at == 0);
+    pool.require_relayer_allowed(relayer, bonded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{RelayerPolicy, RelayerQuote, MAX_RELAYER_URL_LEN};
+
+    fn pool_with_policy(policy: RelayerPolicy, allowed_relayers: &[Pubkey]) -> Box<PrivacyPool> {
+        let mut pool: Box<PrivacyPool> = Box::new(bytemuck::Zeroable::zeroed());
+        pool.set_relayer_policy(policy, allowed_relayers).unwrap();
+        pool
+    }
+
+    fn bond(relayer: Pubkey, unbond_requested_at: u64) -> RelayerBond {
+        RelayerBond { relayer, unbond_requested_at, total_slashed: 0, bump: 255 }
+    }
+
+    #[test]
+    fn test_fee_quote_message_binds_withdrawal_and_fee() {
+        let (pool, relayer) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let message = fee_quote_message(&pool, &[1u8; 32], &relayer, 100);
+        assert_ne!(message, fee_quote_message(&pool, &[1u8; 32], &relayer, 101));
+        assert_ne!(message, fee_quote_message(&pool, &[2u8; 32], &relayer, 100));
+        assert_ne!(message, fee_quote_message(&pool, &[1u8; 32], &Pubkey::new_unique(), 100));
+        assert_ne!(message, fee_quote_message(&Pubkey::new_unique(), &[1u8; 32], &relayer, 100));
+    }
+
+    #[test]
+    fn test_slash_amount() {
+        // The overcharge is refunded with as much again as a penalty
+        assert_eq!(slash_amount(1_000_000, 5_000), 10_000);
+        // A one-lamport overcharge costs two, not a share of the bond
+        assert_eq!(slash_amount(1_000_000, 1), 2);
+        // Never more than the bond holds
+        assert_eq!(slash_amount(50_000, 100_000), 50_000);
+        assert_eq!(slash_amount(0, 5_000), 0);
+    }
+
+    #[test]
+    fn test_relayer_quote_validation() {
+        let mut pool: Box<PrivacyPool> = Box::new(bytemuck::Zeroable::zeroed());
+        pool.relayer_fee_bps = 50;
+        assert!(RelayerQuote::validate(&pool, 50, "https://relayer.example").is_ok());
+        assert!(RelayerQuote::validate(&pool, 51, "https://relayer.example").is_err());
+        let url = "x".repeat(MAX_RELAYER_URL_LEN + 1);
+        assert!(RelayerQuote::validate(&pool, 10, &url).is_err());
+        assert!(RelayerQuote::validate(&pool, 10, &url[1..]).is_ok());
+    }
+
+    #[test]
+    fn test_relayer_policies() {
+        let (relayer, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let pool = pool_with_policy(RelayerPolicy::Open, &[]);
+        assert!(require_relayer(&pool, &other, None).is_ok());
+
+        let pool = pool_with_policy(RelayerPolicy::RegistryOnly, &[]);
+        assert!(require_relayer(&pool, &relayer, Some(&bond(relayer, 0))).is_ok());
+        assert!(require_relayer(&pool, &relayer, None).is_err());
+        // Unbonding relayers and someone else's bond don't count
+        assert!(require_relayer(&pool, &relayer, Some(&bond(relayer, 10))).is_err());
+        assert!(require_relayer(&pool, &other, Some(&bond(relayer, 0))).is_err());
+
+        let pool = pool_with_policy(RelayerPolicy::Allowlist, &[relayer]);
+        assert!(require_relayer(&pool, &relayer, None).is_ok());
+        assert!(require_relayer(&pool, &other, Some(&bond(other, 0))).is_err());
+    }
+
+    #[test]
+    fn test_allowed_relayers_fit_the_pool() {
+        let mut pool: Box<PrivacyPool> = Box::new(bytemuck::Zeroable::zeroed());
+        let relayers: Vec<Pubkey> = (0..9).map(|_| Pubkey::new_unique()).collect();
+        assert!(pool.set_relayer_policy(RelayerPolicy::Allowlist, &relayers).is_err());
+        assert!(pool.set_relayer_policy(RelayerPolicy::Allowlist, &[Pubkey::default()]).is_err());
+
+        // Narrowing the list drops the relayers left out
+        pool.set_relayer_policy(RelayerPolicy::Allowlist, &relayers[..8]).unwrap();
+        pool.set_relayer_policy(RelayerPolicy::Allowlist, &relayers[..1]).unwrap();
+        assert!(require_relayer(&pool, &relayers[7], None).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! Tree Rollover
+//!
+//! An incremental tree holds `IncrementalMerkleTree::MAX_LEAVES` leaves.
+//! When an insertion doesn't fit, the inserting instruction first rolls the
+//! pool over to an empty tree, archiving the final root of the full one in
+//! a `TreeEpoch` PDA it passes and pays for. So a full tree never blocks
+//! deposits; `rollover_tree` only lets anyone do it ahead of time.
+
+use anchor_lang::prelude::*;
+
+use crate::events::RootUpdated;
+use crate::instructions::NyxError;
+use crate::nullifier;
+use crate::state::{PrivacyPool, RootEntry, TreeEpoch, TREE_EPOCH_SEED};
+
+/// Address of the archive of tree `epoch` of `pool`
+pub fn tree_epoch_address(pool: &Pubkey, epoch: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[TREE_EPOCH_SEED, pool.as_ref(), &epoch.to_le_bytes()],
+        &crate::ID,
+    )
+}
+
+/// Require `archive_info` to be the not yet created archive of tree
+/// `epoch` of `pool`, returning its bump
+pub fn require_new_archive(archive_info: &AccountInfo, pool: &Pubkey, epoch: u64) -> Result<u8> {
+    let (expected, bump) = tree_epoch_address(pool, epoch);
+    require_keys_eq!(archive_info.key(), expected, NyxError::InvalidTreeArchive);
+    require!(archive_info.owner != &crate::ID, NyxError::InvalidTreeArchive);
+    Ok(bump)
+}
+
+/// Start a new tree, returning the archive of the old one
+pub fn archive_tree(
+    pool: &mut PrivacyPool,
+    root_history: &mut [RootEntry],
+    pool_key: &Pubkey,
+    slot: u64,
+    bump: u8,
+) -> TreeEpoch {
+    let epoch = pool.tree_epoch;
+    let leaf_count = pool.commitment_count();
+    let root = pool.rollover_tree(root_history);
+    TreeEpoch {
+        pool: *pool_key,
+        epoch,
+        root,
+        leaf_count,
+        archived_at: slot,
+        bump,
+    }
+}
+
+/// Roll the pool over to a new tree, creating the archive of the old one
+/// at `archive_info` with `payer` covering the rent
+///
+/// Returns the `RootUpdated` event of the rollover.
+pub fn roll_over<'info>(
+    program_id: &Pubkey,
+    pool: &mut PrivacyPool,
+    root_history: &mut [RootEntry],
+    pool_key: &Pubkey,
+    archive_info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<RootUpdated> {
+    let epoch = pool.tree_epoch;
+    let bump = require_new_archive(archive_info, pool_key, epoch)?;
+    nullifier::create_pda_account(
+        program_id,
+        archive_info,
+        payer,
+        system_program,
+        8 + TreeEpoch::SIZE,
+        &[&[TREE_EPOCH_SEED, pool_key.as_ref(), &epoch.to_le_bytes(), &[bump]]],
+    )?;
+
+    let slot = Clock::get()?.slot;
+    let archive = archive_tree(pool, root_history, pool_key, slot, bump);
+    archive.try_serialize(&mut &mut archive_info.try_borrow_mut_data()?[..])?;
+
+    msg!("Tree epoch {} archived with {} leaves", epoch, archive.leaf_count);
+    Ok(RootUpdated {
+        pool: *pool_key,
+        tree_epoch: pool.tree_epoch,
+        old_root: archive.root,
+        new_root: pool.current_root(),
+        leaf_count: pool.commitment_count(),
+        slot,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::IncrementalMerkleTree;
+
+    #[test]
+    fn test_archives_are_kept_per_pool_and_epoch() {
+        let pool = Pubkey::new_unique();
+        let (address, bump) = tree_epoch_address(&pool, 0);
+        assert_ne!(tree_epoch_address(&pool, 1).0, address);
+        assert_ne!(tree_epoch_address(&Pubkey::new_unique(), 0).0, address);
+
+        let (mut lamports, mut data) = (0, Vec::new());
+        let system = solana_program::system_program::ID;
+        let mut archive =
+            AccountInfo::new(&address, false, true, &mut lamports, &mut data, &system, false, 0);
+        assert_eq!(require_new_archive(&archive, &pool, 0).unwrap(), bump);
+
+        let err = require_new_archive(&archive, &pool, 1).unwrap_err();
+        assert_eq!(err, NyxError::InvalidTreeArchive.into());
+        archive.owner = &crate::ID;
+        let err = require_new_archive(&archive, &pool, 0).unwrap_err();
+        assert_eq!(err, NyxError::InvalidTreeArchive.into());
+    }
+
+    #[test]
+    fn test_full_tree_rolls_over() {
+        let pool_key = Pubkey::new_unique();
+        let mut pool: Box<PrivacyPool> = Box::new(bytemuck::Zeroable::zeroed());
+        pool.initialize(Pubkey::new_unique(), 255, 0, 4, Pubkey::default(), 9);
+        let mut history: Vec<RootEntry> = (0..4).map(|_| RootEntry::default()).collect();
+        pool.merkle_tree.next_index = IncrementalMerkleTree::MAX_LEAVES;
+        pool.merkle_tree.current_root = [7u8; 32];
+
+        // No room left, but a fresh tree takes the leaf
+        assert!(!pool.has_room_for(1));
+        assert!(pool.can_insert(1));
+        assert!(!pool.can_insert(IncrementalMerkleTree::MAX_LEAVES + 1));
+
+        let archive = archive_tree(&mut pool, &mut history, &pool_key, 42, 254);
+        assert_eq!(archive.epoch, 0);
+        assert_eq!(archive.root, [7u8; 32]);
+        assert_eq!(archive.leaf_count, IncrementalMerkleTree::MAX_LEAVES);
+        assert_eq!(archive.archived_at, 42);
+        assert_eq!(pool.tree_epoch, 1);
+        assert!(pool.has_room_for(1));
+    }
+}
@@ -0,0 +1,94 @@
+//! Tree Snapshots
+//!
+//! A versioned, Borsh-serialized copy of a pool's tree state: the frontier,
+//! next index and most recent roots. `snapshot_tree` returns it as return data, so
+//! a new indexer can start from the current tree instead of replaying every
+//! insertion since the pool was created.
+
+use anchor_lang::prelude::*;
+
+use crate::merkle::TREE_DEPTH;
+use crate::state::{PrivacyPool, RootEntry};
+
+/// Current snapshot layout version
+pub const TREE_SNAPSHOT_VERSION: u8 = 2;
+
+/// Most history roots included, so the snapshot fits in return data
+pub const MAX_SNAPSHOT_ROOTS: usize = 16;
+
+/// Snapshot of a pool's tree
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TreeSnapshot {
+    /// Layout version, bumped whenever fields change
+    pub version: u8,
+
+    /// Pool the tree belongs to
+    pub pool: Pubkey,
+
+    /// Tree epoch of the snapshot
+    pub tree_epoch: u64,
+
+    /// Tree backend, as stored in the pool
+    pub tree_backend: u8,
+
+    /// Number of leaves in the tree
+    pub next_index: u64,
+
+    /// Current root
+    pub root: [u8; 32],
+
+    /// Rightmost filled node per level (incremental backend only)
+    pub frontier: [[u8; 32]; TREE_DEPTH],
+
+    /// Configured length of the pool's root history
+    pub root_history_size: u16,
+
+    /// Most recent history roots, newest first
+    pub root_history: Vec<[u8; 32]>,
+}
+
+impl TreeSnapshot {
+    /// Take a snapshot of `pool`'s tree
+    pub fn from_pool(pool_key: Pubkey, pool: &PrivacyPool, root_history: &[RootEntry]) -> Self {
+        Self {
+            version: TREE_SNAPSHOT_VERSION,
+            pool: pool_key,
+            tree_epoch: pool.tree_epoch,
+            tree_backend: pool.tree_backend,
+            next_index: pool.commitment_count(),
+            root: pool.current_root(),
+            frontier: pool.merkle_tree.filled_subtrees,
+            root_history_size: pool.root_history_size,
+            root_history: pool
+                .recent_roots(root_history)
+                .take(MAX_SNAPSHOT_ROOTS)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::program::MAX_RETURN_DATA;
+
+    #[test]
+    fn test_snapshot_fits_in_return_data() {
+        let snapshot = TreeSnapshot {
+            version: TREE_SNAPSHOT_VERSION,
+            pool: Pubkey::new_unique(),
+            tree_epoch: 3,
+            tree_backend: 0,
+            next_index: 17,
+            root: [1u8; 32],
+            frontier: [[2u8; 32]; TREE_DEPTH],
+            root_history_size: 64,
+            root_history: vec![[3u8; 32]; MAX_SNAPSHOT_ROOTS],
+        };
+
+        let bytes = snapshot.try_to_vec().unwrap();
+        assert!(bytes.len() <= MAX_RETURN_DATA);
+        assert_eq!(bytes[0], TREE_SNAPSHOT_VERSION);
+        assert_eq!(TreeSnapshot::try_from_slice(&bytes).unwrap(), snapshot);
+    }
+}
@@ -0,0 +1,163 @@
+//! Staged Proof Verification
+//!
+//! Groth16 verification with a large public input set can exceed the compute
+//! budget of a single transaction. Staged verification splits it up:
+//!
+//! 1. `verify_stage_1`: record the withdrawal, parse the proof and compute
+//!    the public input linear combination L
+//! 2. `verify_stage_2`: run the pairing check against the stored L
+//! 3. `finalize_unshield`: spend the nullifier and pay out the withdrawal
+//!
+//! Intermediate results live in a `VerificationState` PDA keyed by pool,
+//! relayer and nullifier, so nobody can take the address a relayer's
+//! withdrawal needs. Only the relayer that opened it can advance or finalize
+//! it, and it is closed back to that relayer once the withdrawal completes,
+//! or by `close_verification_state` when the relayer abandons it.
+
+use anchor_lang::prelude::*;
+
+use crate::groth16::{Groth16Proof, PROOF_SIZE};
+
+/// Seeds prefix for verification state PDAs
+pub const VERIFICATION_SEED: &[u8] = b"verification";
+
+/// Progress of a staged verification
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerificationStage {
+    /// Proof parsed and L computed, pairing check pending
+    InputsPrepared,
+    /// Pairing check passed, ready to finalize
+    Verified,
+}
+
+/// Intermediate state for a withdrawal verified across several transactions
+#[account]
+pub struct VerificationState {
+    /// The pool being withdrawn from
+    pub pool: Pubkey,
+
+    /// Relayer that opened the verification (pays rent, receives it back)
+    pub relayer: Pubkey,
+
+    /// Nullifier being spent
+    pub nullifier: [u8; 32],
+
+    /// Recipient of the withdrawal
+    pub recipient: Pubkey,
+
+    /// Amount being withdrawn
+    pub amount: u64,
+
+    /// Fee paid to the relayer
+    pub fee: u64,
+
+    /// Lamports the relayer forwards to the recipient
+    pub refund: u64,
+
+    /// Last slot at which the withdrawal may be finalized
+    pub expiry_slot: u64,
+
+    /// Merkle root the proof was checked against
+    pub root: [u8; 32],
+
+    /// Version of the verifying key L was computed with
+    pub vk_version: u32,
+
+    /// Proof points in the syscall layout
+    pub proof: [u8; PROOF_SIZE],
+
+    /// Public input linear combination computed in stage 1
+    pub l_point: [u8; 64],
+
+    /// Current stage
+    pub stage: VerificationStage,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl VerificationState {
+    pub const SIZE: usize = 32  // pool
+        + 32   // relayer
+        + 32   // nullifier
+        + 32   // recipient
+        + 8    // amount
+        + 8    // fee
+        + 8    // refund
+        + 8    // expiry_slot
+        + 32   // root
+        + 4    // vk_version
+        + PROOF_SIZE  // proof
+        + 64   // l_point
+        + 1    // stage
+        + 1;   // bump
+
+    /// The stored proof, parsed back into its points
+    pub fn groth16_proof(&self) -> Option<Groth16Proof> {
+        Groth16Proof::from_bytes(&self.proof)
+    }
+}
+
+/// Derive the PDA address for a verification state
+pub fn derive_verification_pda(
+    program_id: &Pubkey,
+    pool: &Pubkey,
+    relayer: &Pubkey,
+    nullifier: &[u8; 32],
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[VERIFICATION_SEED, pool.as_ref(), relayer.as_ref(), nullifier],
+        program_id,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verification_state_size() {
+        let state = VerificationState {
+            pool: Pubkey::new_unique(),
+            relayer: Pubkey::new_unique(),
+            nullifier: [1u8; 32],
+            recipient: Pubkey::new_unique(),
+            amount: 1000,
+            fee: 3,
+            refund: 0,
+            expiry_slot: 500,
+            root: [2u8; 32],
+            vk_version: 1,
+            proof: [3u8; PROOF_SIZE],
+            l_point: [4u8; 64],
+            stage: VerificationStage::InputsPrepared,
+            bump: 255,
+        };
+
+        let serialized = state.try_to_vec().unwrap();
+        assert_eq!(serialized.len(), VerificationState::SIZE);
+        assert_eq!(state.groth16_proof().unwrap().to_bytes(), [3u8; PROOF_SIZE]);
+    }
+
+    #[test]
+    fn test_verification_pda_per_nullifier() {
+        let program_id = Pubkey::new_unique();
+        let pool = Pubkey::new_unique();
+        let relayer = Pubkey::new_unique();
+
+        let (pda1, _) = derive_verification_pda(&program_id, &pool, &relayer, &[1u8; 32]);
+        let (pda2, _) = derive_verification_pda(&program_id, &pool, &relayer, &[2u8; 32]);
+        assert_ne!(pda1, pda2);
+    }
+
+    #[test]
+    fn test_verification_pda_per_relayer() {
+        let (program_id, pool) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (relayer, squatter) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        // Another payer opening a state for the same nullifier lands elsewhere
+        let (pda, _) = derive_verification_pda(&program_id, &pool, &relayer, &[1u8; 32]);
+        let (squatted, _) = derive_verification_pda(&program_id, &pool, &squatter, &[1u8; 32]);
+        assert_ne!(pda, squatted);
+    }
+}
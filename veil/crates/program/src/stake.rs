@@ -0,0 +1,424 @@
+//! Shielded Staking
+//!
+//! `shielded_stake` spends a note of the SOL pool straight into an SPL
+//! stake pool: the SOL vault is the depositor of a `DepositSol`, the stake
+//! pool mints its pool tokens into the vault of the pool for that token,
+//! and the minted tokens are committed under a new note. Holders earn the
+//! stake pool's yield without their funds leaving the privacy set.
+//!
+//! As with swaps, the unshield proof binds a PDA of the output pool, the
+//! new commitment and its amount as the recipient, so a relayer can't
+//! change the note the deposit ends up in.
+//!
+//! SOL pools with a yield strategy (see `state::YieldStrategy`) use the same
+//! deposit, and `WithdrawSol` to redeem, to keep idle vault SOL staked.
+//! `WithdrawSol` only draws on the stake pool's reserve, so redemptions are
+//! capped at what the reserve holds above its rent.
+//!
+//! The CPIs are built by hand rather than through the `spl-stake-pool` crate,
+//! which pins its own Solana versions. Stake pools that restrict SOL
+//! deposits or withdrawals to an authority are not supported.
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program::invoke_signed;
+
+/// SPL stake pool program
+pub const STAKE_POOL_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
+
+/// Seeds prefix for the PDA a staking proof names as its recipient
+pub const STAKE_DEPOSIT_SEED: &[u8] = b"stake_deposit";
+
+/// Instruction index of `DepositSol`
+const DEPOSIT_SOL_TAG: u8 = 14;
+
+/// Instruction index of `WithdrawSol`
+const WITHDRAW_SOL_TAG: u8 = 16;
+
+/// Account type tag of an initialized stake pool
+const STAKE_POOL_ACCOUNT_TYPE: u8 = 1;
+
+/// Offset of `pool_mint` in a stake pool account: account_type, manager,
+/// staker, stake_deposit_authority, stake_withdraw_bump_seed, validator_list
+/// and reserve_stake precede it
+const POOL_MINT_OFFSET: usize = 1 + 32 + 32 + 32 + 1 + 32 + 32;
+
+/// Offset of `total_lamports`, after pool_mint, manager_fee_account and
+/// token_program_id
+const TOTAL_LAMPORTS_OFFSET: usize = POOL_MINT_OFFSET + 32 + 32 + 32;
+
+/// The fields of a stake pool account that price its token
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StakePoolState {
+    /// The stake pool's token mint
+    pub pool_mint: Pubkey,
+    /// Lamports the stake pool manages
+    pub total_lamports: u64,
+    /// Supply of the stake pool's token
+    pub pool_token_supply: u64,
+}
+
+impl StakePoolState {
+    /// Read a stake pool account's data, or `None` if it isn't one
+    pub fn read(data: &[u8]) -> Option<Self> {
+        if data.first() != Some(&STAKE_POOL_ACCOUNT_TYPE) {
+            return None;
+        }
+        let read_u64 = |offset: usize| -> Option<u64> {
+            Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().ok()?))
+        };
+        let pool_mint: [u8; 32] = data
+            .get(POOL_MINT_OFFSET..POOL_MINT_OFFSET + 32)?
+            .try_into()
+            .ok()?;
+        Some(Self {
+            pool_mint: Pubkey::new_from_array(pool_mint),
+            total_lamports: read_u64(TOTAL_LAMPORTS_OFFSET)?,
+            pool_token_supply: read_u64(TOTAL_LAMPORTS_OFFSET + 8)?,
+        })
+    }
+
+    /// Pool tokens worth at least `lamports`, before withdrawal fees
+    pub fn pool_tokens_for(&self, lamports: u64) -> Option<u64> {
+        if self.total_lamports == 0 {
+            return None;
+        }
+        let tokens = (lamports as u128 * self.pool_token_supply as u128)
+            .div_ceil(self.total_lamports as u128);
+        u64::try_from(tokens).ok()
+    }
+
+    /// Pool tokens worth at most `lamports`, before withdrawal fees
+    pub fn pool_tokens_within(&self, lamports: u64) -> u64 {
+        if self.total_lamports == 0 {
+            return 0;
+        }
+        let tokens = lamports as u128 * self.pool_token_supply as u128 / self.total_lamports as u128;
+        u64::try_from(tokens).unwrap_or(u64::MAX)
+    }
+
+    /// Lamports `pool_tokens` are worth, before withdrawal fees
+    pub fn lamports_for(&self, pool_tokens: u64) -> u64 {
+        if self.pool_token_supply == 0 {
+            return 0;
+        }
+        let lamports =
+            pool_tokens as u128 * self.total_lamports as u128 / self.pool_token_supply as u128;
+        u64::try_from(lamports).unwrap_or(u64::MAX)
+    }
+}
+
+/// Derive the recipient a staking proof binds for shielding `pool_tokens`
+/// into `output_pool` under `commitment`
+pub fn derive_deposit_authority(
+    program_id: &Pubkey,
+    output_pool: &Pubkey,
+    commitment: &[u8; 32],
+    pool_tokens: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            STAKE_DEPOSIT_SEED,
+            output_pool.as_ref(),
+            commitment.as_ref(),
+            &pool_tokens.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Instruction data of `DepositSol` for `lamports`
+fn deposit_sol_data(lamports: u64) -> Vec<u8> {
+    let mut data = vec![DEPOSIT_SOL_TAG];
+    data.extend_from_slice(&lamports.to_le_bytes());
+    data
+}
+
+/// Instruction data of `WithdrawSol` for `pool_tokens`
+fn withdraw_sol_data(pool_tokens: u64) -> Vec<u8> {
+    let mut data = vec![WITHDRAW_SOL_TAG];
+    data.extend_from_slice(&pool_tokens.to_le_bytes());
+    data
+}
+
+/// Accounts for a stake pool SOL deposit
+pub struct DepositSolAccounts<'a, 'info> {
+    /// The stake pool
+    pub stake_pool: &'a AccountInfo<'info>,
+    /// Stake pool's withdraw authority PDA
+    pub withdraw_authority: &'a AccountInfo<'info>,
+    /// Stake pool's reserve stake account
+    pub reserve_stake: &'a AccountInfo<'info>,
+    /// System account the lamports come from, which signs the deposit
+    pub lamports_from: &'a AccountInfo<'info>,
+    /// Token account the pool tokens are minted to
+    pub pool_tokens_to: &'a AccountInfo<'info>,
+    /// Stake pool manager's fee account
+    pub manager_fee_account: &'a AccountInfo<'info>,
+    /// Token account paid the referral share of the deposit fee
+    pub referrer_pool_tokens_account: &'a AccountInfo<'info>,
+    /// Stake pool's token mint
+    pub pool_mint: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+    pub token_program: &'a AccountInfo<'info>,
+    /// SPL stake pool program
+    pub stake_pool_program: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> DepositSolAccounts<'a, 'info> {
+    /// Deposit `lamports`, signing for a PDA `lamports_from` with
+    /// `signer_seeds`
+    pub fn deposit_sol(&self, lamports: u64, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        let instruction = Instruction {
+            program_id: STAKE_POOL_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(self.stake_pool.key(), false),
+                AccountMeta::new_readonly(self.withdraw_authority.key(), false),
+                AccountMeta::new(self.reserve_stake.key(), false),
+                AccountMeta::new(self.lamports_from.key(), true),
+                AccountMeta::new(self.pool_tokens_to.key(), false),
+                AccountMeta::new(self.manager_fee_account.key(), false),
+                AccountMeta::new(self.referrer_pool_tokens_account.key(), false),
+                AccountMeta::new(self.pool_mint.key(), false),
+                AccountMeta::new_readonly(self.system_program.key(), false),
+                AccountMeta::new_readonly(self.token_program.key(), false),
+            ],
+            data: deposit_sol_data(lamports),
+        };
+
+        invoke_signed(
+            &instruction,
+            &[
+                self.stake_pool.clone(),
+                self.withdraw_authority.clone(),
+                self.reserve_stake.clone(),
+                self.lamports_from.clone(),
+                self.pool_tokens_to.clone(),
+                self.manager_fee_account.clone(),
+                self.referrer_pool_tokens_account.clone(),
+                self.pool_mint.clone(),
+                self.system_program.clone(),
+                self.token_program.clone(),
+                self.stake_pool_program.clone(),
+            ],
+            signer_seeds,
+        )?;
+        Ok(())
+    }
+}
+
+/// Accounts for redeeming stake pool tokens for SOL
+pub struct WithdrawSolAccounts<'a, 'info> {
+    /// The stake pool
+    pub stake_pool: &'a AccountInfo<'info>,
+    /// Stake pool's withdraw authority PDA
+    pub withdraw_authority: &'a AccountInfo<'info>,
+    /// Owner of `pool_tokens_from`, which signs the withdrawal
+    pub user_transfer_authority: &'a AccountInfo<'info>,
+    /// Token account the pool tokens are burned from
+    pub pool_tokens_from: &'a AccountInfo<'info>,
+    /// Stake pool's reserve stake account
+    pub reserve_stake: &'a AccountInfo<'info>,
+    /// System account the lamports are paid to
+    pub lamports_to: &'a AccountInfo<'info>,
+    /// Stake pool manager's fee account
+    pub manager_fee_account: &'a AccountInfo<'info>,
+    /// Stake pool's token mint
+    pub pool_mint: &'a AccountInfo<'info>,
+    /// Clock sysvar
+    pub clock: &'a AccountInfo<'info>,
+    /// Stake history sysvar
+    pub stake_history: &'a AccountInfo<'info>,
+    /// Native stake program
+    pub stake_program: &'a AccountInfo<'info>,
+    pub token_program: &'a AccountInfo<'info>,
+    /// SPL stake pool program
+    pub stake_pool_program: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> WithdrawSolAccounts<'a, 'info> {
+    /// Redeem `pool_tokens`, signing for a PDA `user_transfer_authority`
+    /// with `signer_seeds`
+    pub fn withdraw_sol(&self, pool_tokens: u64, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        let instruction = Instruction {
+            program_id: STAKE_POOL_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(self.stake_pool.key(), false),
+                AccountMeta::new_readonly(self.withdraw_authority.key(), false),
+                AccountMeta::new_readonly(self.user_transfer_authority.key(), true),
+                AccountMeta::new(self.pool_tokens_from.key(), false),
+                AccountMeta::new(self.reserve_stake.key(), false),
+                AccountMeta::new(self.lamports_to.key(), false),
+                AccountMeta::new(self.manager_fee_account.key(), false),
+                AccountMeta::new(self.pool_mint.key(), false),
+                AccountMeta::new_readonly(self.clock.key(), false),
+                AccountMeta::new_readonly(self.stake_history.key(), false),
+                AccountMeta::new_readonly(self.stake_program.key(), false),
+                AccountMeta::new_readonly(self.token_program.key(), false),
+            ],
+            data: withdraw_sol_data(pool_tokens),
+        };
+
+        invoke_signed(
+            &instruction,
+            &[
+                self.stake_pool.clone(),
+                self.withdraw_authority.clone(),
+                self.user_transfer_authority.clone(),
+                self.pool_tokens_from.clone(),
+                self.reserve_stake.clone(),
+                self.lamports_to.clone(),
+                self.manager_fee_account.clone(),
+                self.pool_mint.clone(),
+                self.clock.clone(),
+                self.stake_history.clone(),
+                self.stake_program.clone(),
+                self.token_program.clone(),
+                self.stake_pool_program.clone(),
+            ],
+            signer_seeds,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{PrivacyPool, StakePoolAllowlist, MAX_ALLOWLISTED_STAKE_POOLS};
+
+    #[test]
+    fn test_deposit_sol_data() {
+        let data = deposit_sol_data(1_000_000_000);
+        assert_eq!(data[0], DEPOSIT_SOL_TAG);
+        assert_eq!(data[1..], 1_000_000_000u64.to_le_bytes());
+    }
+
+    #[test]
+    fn test_withdraw_sol_data() {
+        let data = withdraw_sol_data(42);
+        assert_eq!(data[0], WITHDRAW_SOL_TAG);
+        assert_eq!(data[1..], 42u64.to_le_bytes());
+    }
+
+    fn stake_pool_account(pool_mint: Pubkey, total_lamports: u64, supply: u64) -> Vec<u8> {
+        let mut data = vec![0u8; TOTAL_LAMPORTS_OFFSET + 64];
+        data[0] = STAKE_POOL_ACCOUNT_TYPE;
+        data[POOL_MINT_OFFSET..POOL_MINT_OFFSET + 32].copy_from_slice(pool_mint.as_ref());
+        data[TOTAL_LAMPORTS_OFFSET..TOTAL_LAMPORTS_OFFSET + 8]
+            .copy_from_slice(&total_lamports.to_le_bytes());
+        data[TOTAL_LAMPORTS_OFFSET + 8..TOTAL_LAMPORTS_OFFSET + 16]
+            .copy_from_slice(&supply.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_read_stake_pool_state() {
+        let mint = Pubkey::new_unique();
+        let state = StakePoolState::read(&stake_pool_account(mint, 1_100, 1_000)).unwrap();
+        assert_eq!(
+            state,
+            StakePoolState {
+                pool_mint: mint,
+                total_lamports: 1_100,
+                pool_token_supply: 1_000,
+            }
+        );
+
+        // Wrong account type, or too short
+        let mut data = stake_pool_account(mint, 1_100, 1_000);
+        data[0] = 2;
+        assert_eq!(StakePoolState::read(&data), None);
+        assert_eq!(StakePoolState::read(&[STAKE_POOL_ACCOUNT_TYPE; 64]), None);
+    }
+
+    #[test]
+    fn test_pool_tokens_for_rounds_up() {
+        let state = StakePoolState {
+            pool_mint: Pubkey::new_unique(),
+            total_lamports: 1_100,
+            pool_token_supply: 1_000,
+        };
+        // 100 lamports are worth 90.9 tokens
+        assert_eq!(state.pool_tokens_for(100), Some(91));
+        assert_eq!(state.pool_tokens_for(1_100), Some(1_000));
+        let empty = StakePoolState { total_lamports: 0, ..state };
+        assert_eq!(empty.pool_tokens_for(100), None);
+    }
+
+    #[test]
+    fn test_pool_tokens_within_and_value_round_down() {
+        let state = StakePoolState {
+            pool_mint: Pubkey::new_unique(),
+            total_lamports: 1_100,
+            pool_token_supply: 1_000,
+        };
+        assert_eq!(state.pool_tokens_within(100), 90);
+        assert_eq!(state.lamports_for(91), 100);
+        assert_eq!(state.lamports_for(1_000), 1_100);
+        let empty = StakePoolState { total_lamports: 0, pool_token_supply: 0, ..state };
+        assert_eq!(empty.pool_tokens_within(100), 0);
+        assert_eq!(empty.lamports_for(100), 0);
+    }
+
+    #[test]
+    fn test_strategy_marked_to_market() {
+        let mut pool: Box<PrivacyPool> = Box::new(bytemuck::Zeroable::zeroed());
+        pool.total_shielded = 1_000;
+
+        // 600 lamports deposited at cost, 400 left in the vault
+        pool.record_strategy_deposit(600);
+        assert!(pool.check_solvency(400).is_ok());
+
+        // The stake pool lost a sixth of its value
+        let state = StakePoolState {
+            pool_mint: Pubkey::new_unique(),
+            total_lamports: 1_000,
+            pool_token_supply: 1_200,
+        };
+        pool.mark_strategy(state.lamports_for(600));
+        assert_eq!(pool.strategy_deployed, 500);
+        assert!(pool.check_solvency(400).is_err());
+        assert!(pool.check_solvency(500).is_ok());
+
+        // Redeeming half the tokens releases half the marked value
+        pool.record_strategy_withdrawal(300, 600);
+        assert_eq!(pool.strategy_deployed, 250);
+        pool.record_strategy_withdrawal(300, 300);
+        assert_eq!(pool.strategy_deployed, 0);
+    }
+
+    #[test]
+    fn test_stake_pool_allowlist() {
+        let mut allowlist = StakePoolAllowlist { stake_pools: Vec::new(), bump: 255 };
+        let stake_pool = Pubkey::new_unique();
+        assert!(allowlist.require_allowed(&stake_pool).is_err());
+
+        allowlist.add(stake_pool).unwrap();
+        allowlist.add(stake_pool).unwrap();
+        assert_eq!(allowlist.stake_pools, [stake_pool]);
+        assert!(allowlist.require_allowed(&stake_pool).is_ok());
+
+        for _ in 1..MAX_ALLOWLISTED_STAKE_POOLS {
+            allowlist.add(Pubkey::new_unique()).unwrap();
+        }
+        assert!(allowlist.add(Pubkey::new_unique()).is_err());
+
+        allowlist.remove(&stake_pool);
+        assert!(!allowlist.contains(&stake_pool));
+    }
+
+    #[test]
+    fn test_deposit_authority_binds_commitment_and_amount() {
+        let pool = Pubkey::new_unique();
+        let (authority, _) = derive_deposit_authority(&crate::ID, &pool, &[1u8; 32], 100);
+        assert_ne!(authority, derive_deposit_authority(&crate::ID, &pool, &[2u8; 32], 100).0);
+        assert_ne!(authority, derive_deposit_authority(&crate::ID, &pool, &[1u8; 32], 99).0);
+        // Distinct from the swap authority for the same note
+        assert_ne!(
+            authority,
+            crate::swap::derive_swap_authority(&crate::ID, &pool, &[1u8; 32], 100).0
+        );
+    }
+}
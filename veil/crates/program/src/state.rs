@@ -2,14 +2,40 @@
 //!
 //! Defines the on-chain data structures for the privacy pool.
 
+use std::cell::{Ref, RefMut};
+
 use anchor_lang::prelude::*;
 
 use crate::instructions::NyxError;
-use crate::merkle::IncrementalMerkleTree;
+use crate::merkle::{IncrementalMerkleTree, MerkleCanopy};
+use crate::rate_limit::RateLimiter;
+use crate::token;
+
+/// Default number of recent roots to keep for validity window
+pub const DEFAULT_ROOT_HISTORY_SIZE: u16 = 10;
+
+/// Current layout version of pool accounts
+pub const POOL_VERSION: u8 = 14;
+
+/// Smallest root history a pool can be configured with
+pub const MIN_ROOT_HISTORY_SIZE: u16 = 10;
+
+/// Largest root history a pool can be configured with
+///
+/// Keeps the pool account within the 10 KiB that can be allocated at once.
+pub const MAX_ROOT_HISTORY_SIZE: u16 = 200;
+
+/// Delay between scheduling a pool's closure and closing it (~1 day)
+pub const POOL_CLOSE_DELAY_SLOTS: u64 = 216_000;
+
+/// Upper bound for a pool's minimum anonymity set
+pub const MAX_MIN_ANONYMITY_SET: u64 = 10_000;
+
+/// How long unshields must stay halted before emergency withdrawals open (~5 epochs)
+pub const EMERGENCY_WITHDRAW_DELAY_SLOTS: u64 = 2_160_000;
 
-/// Number of recent roots to keep for validity window
-/// Reduced from 30 to 10 to save account space
-pub const ROOT_HISTORY_SIZE: usize = 10;
+/// Upper bound for the deposit maturity delay (~3 days)
+pub const MAX_SPEND_DELAY_SLOTS: u64 = 648_000;
 
 /// Default relayer fee in basis points (0.3%)
 pub const DEFAULT_RELAYER_FEE_BPS: u16 = 30;
@@ -17,40 +43,278 @@ pub const DEFAULT_RELAYER_FEE_BPS: u16 = 30;
 /// Maximum relayer fee in basis points (5%)
 pub const MAX_RELAYER_FEE_BPS: u16 = 500;
 
+/// Default share of each relayer fee paid to the pool treasury, in basis points (10%)
+pub const TREASURY_FEE_SHARE_BPS: u16 = 1_000;
+
+/// Seeds prefix for archived tree epoch PDAs
+pub const TREE_EPOCH_SEED: &[u8] = b"tree_epoch";
+
+/// Seeds prefix for commitment chunk PDAs
+pub const COMMITMENT_CHUNK_SEED: &[u8] = b"commitment_chunk";
+
+/// Seeds prefix for commitment record PDAs
+pub const COMMITMENT_RECORD_SEED: &[u8] = b"commitment";
+
+/// Seeds of the global pool registry PDA
+pub const POOL_REGISTRY_SEED: &[u8] = b"pool_registry";
+
+/// Maximum number of pools in the registry
+pub const MAX_REGISTERED_POOLS: usize = 32;
+
+/// Denominations of the canonical SOL pools (0.1, 1, 10 and 100 SOL)
+pub const STANDARD_DENOMINATIONS: [u64; 4] = [
+    100_000_000,
+    1_000_000_000,
+    10_000_000_000,
+    100_000_000_000,
+];
+
+/// Seeds of the global protocol config PDA
+pub const PROTOCOL_CONFIG_SEED: &[u8] = b"protocol_config";
+
+/// Seeds of the SPL mint allowlist PDA
+pub const MINT_ALLOWLIST_SEED: &[u8] = b"mint_allowlist";
+
+/// Maximum number of allowlisted mints
+pub const MAX_ALLOWLISTED_MINTS: usize = 64;
+
+/// Maximum number of programs a pool lets shield under `CpiPolicy::Allowlist`
+pub const MAX_ALLOWED_CALLERS: usize = 4;
+
+/// Maximum number of relayers a pool accepts under `RelayerPolicy::Allowlist`
+pub const MAX_ALLOWED_RELAYERS: usize = 8;
+
+/// Seeds of the CPI destination program registry PDA
+pub const DESTINATION_REGISTRY_SEED: &[u8] = b"destination_registry";
+
+/// Maximum number of registered destination programs
+pub const MAX_DESTINATION_PROGRAMS: usize = 32;
+
+/// Seeds of the stake pool allowlist PDA
+pub const STAKE_POOL_ALLOWLIST_SEED: &[u8] = b"stake_pool_allowlist";
+
+/// Maximum number of allowlisted stake pools
+pub const MAX_ALLOWLISTED_STAKE_POOLS: usize = 16;
+
+/// Seeds prefix for guardian set PDAs
+pub const GUARDIAN_SET_SEED: &[u8] = b"guardian_set";
+
+/// Maximum number of guardians in a pool's guardian set
+pub const MAX_GUARDIANS: usize = 8;
+
+/// Seeds prefix for nullifier bloom filter PDAs
+pub const NULLIFIER_BLOOM_SEED: &[u8] = b"nullifier_bloom";
+
+/// Size of a pool's nullifier bloom filter in bytes
+pub const NULLIFIER_BLOOM_BYTES: usize = 4096;
+
+/// Nullifiers a bloom filter holds before about a fifth of unspent ones
+/// look spent
+pub const NULLIFIER_BLOOM_CAPACITY: u64 = 10_000;
+
+/// Seeds prefix for pool stats PDAs
+pub const POOL_STATS_SEED: &[u8] = b"pool_stats";
+
+/// Epochs of history kept by a pool stats account
+pub const POOL_STATS_BUCKETS: usize = 32;
+
+/// Leaves stored per commitment chunk
+pub const COMMITMENT_CHUNK_LEAVES: usize = 256;
+
+/// Seeds prefix of a SOL pool's yield strategy
+pub const YIELD_STRATEGY_SEED: &[u8] = b"yield_strategy";
+
+/// Seeds prefix of a claim link
+pub const CLAIM_LINK_SEED: &[u8] = b"claim_link";
+
+/// Seeds prefix of a payment stream
+pub const PAYMENT_STREAM_SEED: &[u8] = b"payment_stream";
+
+/// Seeds prefix of a queued unshield
+pub const QUEUED_UNSHIELD_SEED: &[u8] = b"queued_unshield";
+
+/// Slots a queued unshield waits before it can be executed (~1 minute)
+pub const UNSHIELD_QUEUE_MIN_DELAY_SLOTS: u64 = 150;
+
+/// Slots after queueing within which an unshield must be executed (~1 day)
+pub const UNSHIELD_QUEUE_WINDOW_SLOTS: u64 = 216_000;
+
+/// Seeds prefix of the recipient committed ahead of an emergency withdrawal
+pub const EMERGENCY_COMMIT_SEED: &[u8] = b"emergency_commit";
+
+/// Seeds prefix of a relayer's bond
+pub const RELAYER_BOND_SEED: &[u8] = b"relayer_bond";
+
+/// Seeds prefix of the record that a withdrawal's fee was disputed
+pub const RELAYER_DISPUTE_SEED: &[u8] = b"relayer_dispute";
+
+/// Slots between a relayer asking to unbond and withdrawing its bond (~2 days)
+pub const RELAYER_UNBONDING_SLOTS: u64 = 432_000;
+
+/// Penalty slashed on top of an overcharge, in basis points of the overcharge
+pub const RELAYER_SLASH_BPS: u64 = 10_000;
+
+/// Seeds prefix of a relayer's fee quote for a pool
+pub const RELAYER_QUOTE_SEED: &[u8] = b"relayer_quote";
+
+/// Maximum length of the endpoint URL a relayer publishes with its quote
+pub const MAX_RELAYER_URL_LEN: usize = 128;
+
+/// Smallest share of the value locked a yield strategy keeps in the vault
+pub const MIN_STRATEGY_BUFFER_BPS: u16 = 1_000;
+
+/// Maximum commitments inserted by one shield_batch instruction
+pub const MAX_SHIELD_BATCH_SIZE: usize = 16;
+
+/// Maximum independently proven withdrawals in one unshield_batch
+pub const MAX_UNSHIELD_BATCH_SIZE: usize = 4;
+
+/// Maximum length of an encrypted note emitted with a commitment
+///
+/// Bounds what one leaf adds to the transaction log, which Solana
+/// truncates at 10 KB.
+pub const MAX_ENCRYPTED_NOTE_LEN: usize = 256;
+
+/// Length of the ephemeral public key every encrypted note starts with
+pub const EPHEMERAL_KEY_LEN: usize = 32;
+
 /// Minimum withdrawal amount (to cover fees)
 pub const MIN_WITHDRAWAL_AMOUNT: u64 = 10_000; // 0.00001 SOL
 
+/// Default delay before a proposed verifying key can be activated (~1 day)
+pub const DEFAULT_VK_UPDATE_DELAY_SLOTS: u64 = 216_000;
+
+/// Default window during which the previous verifying key stays valid (~1 day)
+pub const DEFAULT_VK_GRACE_PERIOD_SLOTS: u64 = 216_000;
+
+/// Upper bound for the verifying key delay and grace period (~30 days)
+pub const MAX_VK_TIMELOCK_SLOTS: u64 = 6_480_000;
+
+/// Default time a legacy nullifier marker must age before it can be closed (~1 day)
+pub const DEFAULT_MARKER_FINALITY_SLOTS: u64 = 216_000;
+
+/// Upper bound for the nullifier marker finality window (~30 days)
+pub const MAX_MARKER_FINALITY_SLOTS: u64 = 6_480_000;
+
+/// Proof system a pool verifies spends with
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ProofSystem {
+    /// Groth16 with a per-circuit trusted setup
+    #[default]
+    Groth16,
+    /// PLONK with a universal setup, as snarkjs proves it
+    ///
+    /// UltraPlonk proofs from Barretenberg (Noir's default backend) don't
+    /// verify under it; see `plonk` for the supported format.
+    Plonk,
+}
+
+/// Merkle tree a pool inserts commitments into
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TreeBackend {
+    /// Incremental Poseidon tree stored in the pool account
+    #[default]
+    Incremental,
+    /// spl-account-compression concurrent tree in its own account
+    Compressed,
+}
+
+/// Which programs may shield into a pool through CPI
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum CpiPolicy {
+    /// Direct transactions and any calling program
+    #[default]
+    AllowAny,
+    /// Direct transactions only
+    DenyCpi,
+    /// Only the pool's allowed caller programs
+    Allowlist,
+}
+
+/// Which relayers may submit a pool's transfers and withdrawals
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum RelayerPolicy {
+    /// Any signer
+    #[default]
+    Open,
+    /// Relayers with a bond that isn't unbonding
+    RegistryOnly,
+    /// Only the pool's allowed relayers
+    Allowlist,
+}
+
+/// Which notes a pool holds
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum AssetMode {
+    /// Notes of the pool's own mint, or native SOL
+    #[default]
+    Single,
+    /// Notes of any allowlisted mint, each bound to its asset ID
+    Multi,
+    /// One NFT per note, each bound to its mint's asset ID
+    Nft,
+}
+
+/// A root in a pool's root history
+#[zero_copy]
+#[derive(Debug, Default)]
+pub struct RootEntry {
+    /// The root
+    pub root: [u8; 32],
+
+    /// Slot at which the root became current, so no leaf in it is newer
+    pub slot: u64,
+}
+
+impl RootEntry {
+    pub const SIZE: usize = 32 + 8;
+}
+
 /// Privacy pool state
-#[account]
+///
+/// Zero-copy: instructions read and write the account data in place through
+/// `AccountLoader` instead of deserializing it onto the stack. Fields are
+/// ordered by alignment so the `repr(C)` layout has no implicit padding.
+///
+/// The root history follows the struct in the account data, as
+/// `root_history_size` root entries, so each pool sizes its own validity
+/// window. Use `load_pool` / `load_pool_mut` to borrow both.
+#[account(zero_copy)]
 pub struct PrivacyPool {
     /// Pool authority
     pub authority: Pubkey,
 
     /// Incremental Merkle tree for commitments
     /// - next_index: u64 (8 bytes)
-    /// - filled_subtrees: [[u8; 32]; 20] (640 bytes)
+    /// - filled_subtrees: [[u8; 32]; 10] (320 bytes)
     /// - current_root: [u8; 32] (32 bytes)
     pub merkle_tree: IncrementalMerkleTree,
 
-    /// Recent Merkle roots (for validity window)
-    /// Allows proofs against slightly older roots during concurrent transactions
-    pub root_history: [[u8; 32]; ROOT_HISTORY_SIZE],
+    /// Top levels of the current tree, so clients can fetch the upper
+    /// half of a Merkle path from the pool instead of an indexer
+    pub canopy: MerkleCanopy,
 
-    /// Index of the oldest root in history (circular buffer)
-    pub root_history_index: u8,
+    /// Concurrent Merkle tree account (compressed backend only)
+    pub compression_tree: Pubkey,
+
+    /// Current root of the compressed tree, mirrored after every append
+    pub compressed_root: [u8; 32],
+
+    /// Account receiving protocol funds, such as reclaimed marker rent
+    pub treasury: Pubkey,
+
+    /// Mint of the pool's notes (default pubkey for native SOL)
+    pub mint: Pubkey,
+
+    /// Proposed next authority, awaiting acceptance (default pubkey if none)
+    pub pending_authority: Pubkey,
 
     /// Number of spent nullifiers (for stats)
     pub nullifier_count: u64,
 
-    /// Relayer fee in basis points (e.g., 30 = 0.3%)
-    pub relayer_fee_bps: u16,
-
     /// Total fees collected (for stats)
     pub total_fees_collected: u64,
 
-    /// Bump seed for PDA
-    pub bump: u8,
-
     /// Fixed denomination for this pool (in lamports)
     /// 0 = custom amounts allowed (variable pool)
     /// Non-zero = only this exact amount can be shielded
@@ -58,20 +322,254 @@ pub struct PrivacyPool {
 
     /// Number of deposits in this pool (anonymity set size)
     pub deposit_count: u64,
+
+    /// Slot from which the pending verifying keys can be activated
+    pub vk_activation_slot: u64,
+
+    /// Slot at which the current verifying key version was activated
+    pub vk_activated_at: u64,
+
+    /// Delay between proposing and activating new verifying keys
+    pub vk_update_delay_slots: u64,
+
+    /// How long the previous verifying key version stays valid after rotation
+    pub vk_grace_period_slots: u64,
+
+    /// Index of the current Merkle tree (incremented on every rollover)
+    pub tree_epoch: u64,
+
+    /// Number of leaves in the compressed tree
+    pub compressed_leaf_count: u64,
+
+    /// Slots after spending before a legacy nullifier marker can be closed
+    pub marker_finality_slots: u64,
+
+    /// Active verifying key version (0 = no key activated yet)
+    pub vk_version: u32,
+
+    /// Depth of the compressed tree (0 for the incremental backend)
+    pub compression_max_depth: u32,
+
+    /// Relayer fee in basis points (e.g., 30 = 0.3%)
+    pub relayer_fee_bps: u16,
+
+    /// Number of roots in the trailing root history
+    pub root_history_size: u16,
+
+    /// Index of the oldest root in history (circular buffer)
+    pub root_history_index: u16,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+
+    /// Verifier backend for spend proofs (`ProofSystem` as u8)
+    pub proof_system: u8,
+
+    /// Whether keys for version `vk_version + 1` have been proposed (0 or 1)
+    pub vk_update_pending: u8,
+
+    /// Tree backend commitments are inserted into (`TreeBackend` as u8)
+    pub tree_backend: u8,
+
+    /// Bump of the PDA that owns the compressed tree
+    pub tree_authority_bump: u8,
+
+    /// Whether shields and transfers are paused (0 or 1)
+    pub paused: u8,
+
+    /// Whether unshields are paused too (0 or 1, only set while paused)
+    pub unshields_paused: u8,
+
+    /// Whether guardians froze verifying key updates (0 or 1)
+    pub vk_updates_frozen: u8,
+
+    /// Account layout version (0 for pools created before versioning)
+    pub version: u8,
+
+    /// Which notes the pool holds (`AssetMode` as u8)
+    ///
+    /// Takes the byte that aligned the fields appended by later layout
+    /// versions, which older pools left zeroed.
+    pub asset_mode: u8,
+
+    /// Total amount ever shielded, in lamports or token base units (v2)
+    pub total_shielded: u64,
+
+    /// Total amount ever unshielded, fees included (v2)
+    pub total_unshielded: u64,
+
+    /// Slot at which the current root became current (v3)
+    pub root_slot: u64,
+
+    /// Slots a root must age before proofs may use it (v3)
+    pub min_spend_delay_slots: u64,
+
+    /// Deposits the pool must exceed before unshields are allowed (v4)
+    pub min_anonymity_set: u64,
+
+    /// Limit on withdrawals per window of slots (v5)
+    pub withdrawal_limit: RateLimiter,
+
+    /// Maximum vault balance shields may reach (0 = uncapped) (v6)
+    pub deposit_cap: u64,
+
+    /// Slot at which closing the pool was scheduled (0 = not scheduled) (v7)
+    pub close_scheduled_at: u64,
+
+    /// Slot at which unshields were paused (0 = not paused) (v8)
+    pub unshields_paused_at: u64,
+
+    /// Decimals of the pool's mint (9 for native SOL, 0 for pools of
+    /// several mints) (v9)
+    pub decimals: u8,
+
+    /// Keeps the struct size a multiple of 8 (v9)
+    pub _padding: [u8; 7],
+
+    /// Lamports the vault holds with its yield strategy, at cost until the
+    /// next rebalance marks them to market; they still back the value
+    /// locked (v10)
+    pub strategy_deployed: u64,
+
+    /// Rent reimbursement SPL unshields may charge on top of the relayer
+    /// fee rate, in the pool's units (0 = none) (v11)
+    pub max_rent_fee: u64,
+
+    /// Programs allowed to shield under `CpiPolicy::Allowlist`, unused
+    /// slots holding the default pubkey (v12)
+    pub allowed_callers: [Pubkey; MAX_ALLOWED_CALLERS],
+
+    /// Which programs may shield through CPI (`CpiPolicy` as u8) (v12)
+    pub cpi_policy: u8,
+
+    /// Keeps the struct size a multiple of 8 (v12)
+    pub _padding2: [u8; 7],
+
+    /// Relayers accepted under `RelayerPolicy::Allowlist`, unused slots
+    /// holding the default pubkey (v13)
+    pub allowed_relayers: [Pubkey; MAX_ALLOWED_RELAYERS],
+
+    /// Which relayers may submit transfers and withdrawals
+    /// (`RelayerPolicy` as u8) (v13)
+    pub relayer_policy: u8,
+
+    /// Keeps the struct size a multiple of 8 (v13)
+    pub _padding3: [u8; 7],
+
+    /// Update delay taking effect at `vk_timelock_change_slot` (v14)
+    pub pending_vk_update_delay_slots: u64,
+
+    /// Grace period taking effect at `vk_timelock_change_slot` (v14)
+    pub pending_vk_grace_period_slots: u64,
+
+    /// Slot from which the pending timelock applies (v14)
+    pub vk_timelock_change_slot: u64,
+
+    /// Whether a timelock change is pending (0 or 1) (v14)
+    pub vk_timelock_pending: u8,
+
+    /// Keeps the struct size a multiple of 8 (v14)
+    pub _padding4: [u8; 7],
 }
 
 impl PrivacyPool {
     /// Account size calculation
     pub const SIZE: usize = 32  // authority
-        + IncrementalMerkleTree::SIZE  // merkle_tree (680 bytes)
-        + (32 * ROOT_HISTORY_SIZE)  // root_history (320 bytes for 10 roots)
-        + 1   // root_history_index
+        + IncrementalMerkleTree::SIZE  // merkle_tree (360 bytes)
+        + MerkleCanopy::SIZE  // canopy (960 bytes for 4 levels)
+        + 32  // compression_tree
+        + 32  // compressed_root
+        + 32  // treasury
+        + 32  // mint
+        + 32  // pending_authority
         + 8   // nullifier_count
-        + 2   // relayer_fee_bps
         + 8   // total_fees_collected
-        + 1   // bump
         + 8   // denomination
-        + 8;  // deposit_count
+        + 8   // deposit_count
+        + 8   // vk_activation_slot
+        + 8   // vk_activated_at
+        + 8   // vk_update_delay_slots
+        + 8   // vk_grace_period_slots
+        + 8   // tree_epoch
+        + 8   // compressed_leaf_count
+        + 8   // marker_finality_slots
+        + 4   // vk_version
+        + 4   // compression_max_depth
+        + 2   // relayer_fee_bps
+        + 2   // root_history_size
+        + 2   // root_history_index
+        + 1   // bump
+        + 1   // proof_system
+        + 1   // vk_update_pending
+        + 1   // tree_backend
+        + 1   // tree_authority_bump
+        + 1   // paused
+        + 1   // unshields_paused
+        + 1   // vk_updates_frozen
+        + 1   // version
+        + 1   // asset_mode
+        + 8   // total_shielded
+        + 8   // total_unshielded
+        + 8   // root_slot
+        + 8   // min_spend_delay_slots
+        + 8   // min_anonymity_set
+        + RateLimiter::SIZE  // withdrawal_limit
+        + 8   // deposit_cap
+        + 8   // close_scheduled_at
+        + 8   // unshields_paused_at
+        + 1   // decimals
+        + 7   // _padding
+        + 8   // strategy_deployed
+        + 8   // max_rent_fee
+        + 32 * MAX_ALLOWED_CALLERS  // allowed_callers
+        + 1   // cpi_policy
+        + 7   // _padding2
+        + 32 * MAX_ALLOWED_RELAYERS  // allowed_relayers
+        + 1   // relayer_policy
+        + 7   // _padding3
+        + 8   // pending_vk_update_delay_slots
+        + 8   // pending_vk_grace_period_slots
+        + 8   // vk_timelock_change_slot
+        + 1   // vk_timelock_pending
+        + 7;  // _padding4
+
+    /// Size of the pool struct in an older layout version
+    ///
+    /// Later versions only append fields, so the root history of an older
+    /// pool starts this far after the discriminator.
+    pub fn layout_size(version: u8) -> usize {
+        match version {
+            0 | 1 => Self::SIZE - 520 - RateLimiter::SIZE,
+            2 => Self::SIZE - 504 - RateLimiter::SIZE,
+            3 => Self::SIZE - 488 - RateLimiter::SIZE,
+            4 => Self::SIZE - 480 - RateLimiter::SIZE,
+            5 => Self::SIZE - 480,
+            6 => Self::SIZE - 472,
+            7 => Self::SIZE - 464,
+            8 => Self::SIZE - 456,
+            9 => Self::SIZE - 448,
+            10 => Self::SIZE - 440,
+            11 => Self::SIZE - 432,
+            12 => Self::SIZE - 296,
+            13 => Self::SIZE - 32,
+            _ => Self::SIZE,
+        }
+    }
+
+    /// Size of one root history entry in an older layout version
+    ///
+    /// Before version 3 the history held bare 32-byte roots.
+    pub fn history_entry_size(version: u8) -> usize {
+        match version {
+            0..=2 => 32,
+            _ => RootEntry::SIZE,
+        }
+    }
+
+    /// Pool account size, with discriminator and root history
+    pub fn space(root_history_size: u16) -> usize {
+        8 + Self::SIZE + RootEntry::SIZE * root_history_size as usize
+    }
 
     /// Initialize a new privacy pool
     ///
@@ -79,10 +577,24 @@ impl PrivacyPool {
     /// * `authority` - Pool authority pubkey
     /// * `bump` - PDA bump seed
     /// * `denomination` - Fixed deposit amount in lamports (0 = custom/variable)
-    pub fn initialize(&mut self, authority: Pubkey, bump: u8, denomination: u64) {
+    /// * `root_history_size` - Number of past roots proofs may use
+    /// * `mint` - Mint of the pool's notes (default pubkey for native SOL)
+    /// * `decimals` - Decimals of that mint
+    pub fn initialize(
+        &mut self,
+        authority: Pubkey,
+        bump: u8,
+        denomination: u64,
+        root_history_size: u16,
+        mint: Pubkey,
+        decimals: u8,
+    ) {
         self.authority = authority;
+        self.mint = mint;
+        self.decimals = decimals;
         self.merkle_tree = IncrementalMerkleTree::new();
-        self.root_history = [[0u8; 32]; ROOT_HISTORY_SIZE];
+        self.canopy = MerkleCanopy::new();
+        self.root_history_size = root_history_size;
         self.root_history_index = 0;
         self.nullifier_count = 0;
         self.relayer_fee_bps = DEFAULT_RELAYER_FEE_BPS;
@@ -90,102 +602,2108 @@ impl PrivacyPool {
         self.bump = bump;
         self.denomination = denomination;
         self.deposit_count = 0;
+        self.proof_system = ProofSystem::Groth16 as u8;
+        self.vk_version = 0;
+        self.vk_update_pending = 0;
+        self.vk_activation_slot = 0;
+        self.vk_activated_at = 0;
+        self.vk_update_delay_slots = DEFAULT_VK_UPDATE_DELAY_SLOTS;
+        self.vk_grace_period_slots = DEFAULT_VK_GRACE_PERIOD_SLOTS;
+        self.tree_epoch = 0;
+        self.tree_backend = TreeBackend::Incremental as u8;
+        self.treasury = authority;
+        self.pending_authority = Pubkey::default();
+        self.paused = 0;
+        self.unshields_paused = 0;
+        self.vk_updates_frozen = 0;
+        self.asset_mode = AssetMode::Single as u8;
+        self.version = POOL_VERSION;
+        self.total_shielded = 0;
+        self.total_unshielded = 0;
+        self.root_slot = 0;
+        self.min_spend_delay_slots = 0;
+        self.min_anonymity_set = 0;
+        self.withdrawal_limit = RateLimiter::default();
+        self.deposit_cap = 0;
+        self.close_scheduled_at = 0;
+        self.unshields_paused_at = 0;
+        self._padding = [0; 7];
+        self.strategy_deployed = 0;
+        self.max_rent_fee = 0;
+        self.allowed_callers = [Pubkey::default(); MAX_ALLOWED_CALLERS];
+        self.cpi_policy = CpiPolicy::AllowAny as u8;
+        self._padding2 = [0; 7];
+        self.allowed_relayers = [Pubkey::default(); MAX_ALLOWED_RELAYERS];
+        self.relayer_policy = RelayerPolicy::Open as u8;
+        self._padding3 = [0; 7];
+        self.clear_vk_timelock_change();
+        self.marker_finality_slots = DEFAULT_MARKER_FINALITY_SLOTS;
     }
 
-    /// Check if this is a fixed denomination pool
-    pub fn is_fixed_denomination(&self) -> bool {
-        self.denomination > 0
+    /// Verifier backend for spend proofs
+    pub fn proof_system(&self) -> ProofSystem {
+        if self.proof_system == ProofSystem::Plonk as u8 {
+            ProofSystem::Plonk
+        } else {
+            ProofSystem::Groth16
+        }
     }
 
-    /// Check if amount matches pool denomination (for fixed pools)
-    pub fn validate_amount(&self, amount: u64) -> bool {
-        if self.denomination == 0 {
-            // Custom pool accepts any amount > 0
-            amount > 0
+    /// Select the verifier backend for spend proofs
+    pub fn set_proof_system(&mut self, proof_system: ProofSystem) {
+        self.proof_system = proof_system as u8;
+    }
+
+    /// Merkle tree commitments are inserted into
+    pub fn tree_backend(&self) -> TreeBackend {
+        if self.tree_backend == TreeBackend::Compressed as u8 {
+            TreeBackend::Compressed
         } else {
-            // Fixed pool requires exact match
-            amount == self.denomination
+            TreeBackend::Incremental
         }
     }
 
-    /// Increment deposit count (call after successful shield)
-    pub fn record_deposit(&mut self) {
-        self.deposit_count = self.deposit_count.saturating_add(1);
+    /// Fail for compressed trees: their roots hash with keccak, and the
+    /// spend circuits prove Poseidon paths
+    pub fn require_poseidon_tree(&self) -> Result<()> {
+        require!(
+            self.tree_backend() == TreeBackend::Incremental,
+            NyxError::UnsupportedTreeBackend
+        );
+        Ok(())
     }
 
-    /// Calculate relayer fee for a given amount
-    pub fn calculate_relayer_fee(&self, amount: u64) -> u64 {
-        // fee = amount * fee_bps / 10000
-        (amount as u128 * self.relayer_fee_bps as u128 / 10000) as u64
+    /// Switch to a freshly initialized compressed tree
+    ///
+    /// Only valid while the pool has no commitments; the caller checks that.
+    pub fn enable_compressed_tree(
+        &mut self,
+        tree: Pubkey,
+        max_depth: u32,
+        authority_bump: u8,
+        empty_root: [u8; 32],
+    ) {
+        self.tree_backend = TreeBackend::Compressed as u8;
+        self.compression_tree = tree;
+        self.compression_max_depth = max_depth;
+        self.tree_authority_bump = authority_bump;
+        self.compressed_root = empty_root;
+        self.compressed_leaf_count = 0;
     }
 
-    /// Record a fee payment
-    pub fn record_fee_collected(&mut self, fee: u64) {
-        self.total_fees_collected = self.total_fees_collected.saturating_add(fee);
+    /// Version that proposed verifying keys are registered under
+    pub fn pending_vk_version(&self) -> u32 {
+        self.vk_version.saturating_add(1)
     }
 
-    /// Add a commitment to the tree
-    pub fn add_commitment(&mut self, commitment: [u8; 32]) -> Result<u64> {
-        // Store old root in history before updating
-        let old_root = self.merkle_tree.current_root;
+    /// Record a verifying key proposal
+    ///
+    /// Every proposal restarts the timelock, so keys can't be swapped out
+    /// after users have audited them. The first key set of a pool has
+    /// nothing to protect yet and can be activated immediately.
+    pub fn schedule_vk_update(&mut self, slot: u64) -> Result<()> {
+        require!(self.vk_updates_frozen == 0, NyxError::VkUpdatesFrozen);
+        self.vk_update_pending = 1;
+        self.vk_activation_slot = if self.vk_version == 0 {
+            slot
+        } else {
+            slot.saturating_add(self.vk_timelock(slot).0)
+        };
+        Ok(())
+    }
 
-        // Insert into Merkle tree
-        let leaf_index = self.merkle_tree.insert(commitment)
-            .map_err(|_| NyxError::PoolFull)?;
+    /// Update delay and grace period in force at `slot`
+    pub fn vk_timelock(&self, slot: u64) -> (u64, u64) {
+        if self.vk_timelock_pending != 0 && slot >= self.vk_timelock_change_slot {
+            (self.pending_vk_update_delay_slots, self.pending_vk_grace_period_slots)
+        } else {
+            (self.vk_update_delay_slots, self.vk_grace_period_slots)
+        }
+    }
 
-        // Add old root to history (circular buffer)
-        self.root_history[self.root_history_index as usize] = old_root;
-        self.root_history_index = ((self.root_history_index as usize + 1) % ROOT_HISTORY_SIZE) as u8;
+    /// Schedule a new update delay and grace period
+    ///
+    /// The change is itself timelocked by the delay in force, so the
+    /// authority can't drop the delay and swap keys in at once. A change
+    /// that already took effect is applied first; a later call replaces a
+    /// change still waiting.
+    pub fn schedule_vk_timelock(
+        &mut self,
+        update_delay_slots: u64,
+        grace_period_slots: u64,
+        slot: u64,
+    ) {
+        (self.vk_update_delay_slots, self.vk_grace_period_slots) = self.vk_timelock(slot);
+        self.pending_vk_update_delay_slots = update_delay_slots;
+        self.pending_vk_grace_period_slots = grace_period_slots;
+        self.vk_timelock_change_slot = slot.saturating_add(self.vk_update_delay_slots);
+        self.vk_timelock_pending = 1;
+    }
 
-        Ok(leaf_index)
+    /// Forget any pending timelock change
+    fn clear_vk_timelock_change(&mut self) {
+        self.pending_vk_update_delay_slots = 0;
+        self.pending_vk_grace_period_slots = 0;
+        self.vk_timelock_change_slot = 0;
+        self.vk_timelock_pending = 0;
+        self._padding4 = [0; 7];
     }
 
-    /// Get current Merkle root
-    pub fn current_root(&self) -> [u8; 32] {
-        self.merkle_tree.current_root
+    /// Activate the pending verifying key version once its timelock expired
+    pub fn activate_pending_vk(&mut self, slot: u64) -> Result<()> {
+        require!(self.vk_updates_frozen == 0, NyxError::VkUpdatesFrozen);
+        require!(self.vk_update_pending != 0, NyxError::NoPendingVkUpdate);
+        require!(slot >= self.vk_activation_slot, NyxError::VkTimelockActive);
+
+        self.vk_version = self.pending_vk_version();
+        self.vk_update_pending = 0;
+        self.vk_activated_at = slot;
+        Ok(())
     }
 
-    /// Get number of commitments in tree
-    pub fn commitment_count(&self) -> u64 {
-        self.merkle_tree.next_index
+    /// Backfill the fields added since the pool's layout version
+    ///
+    /// Each step upgrades one version, so pools can skip several at once.
+    /// Steps that grow the account must reallocate it and move the root
+    /// history before this runs.
+    pub fn migrate(&mut self, vault_balance: u64, slot: u64, decimals: u8) {
+        if self.version < 1 {
+            // Version 1 pools send reclaimed rent and fee shares to a treasury
+            if self.treasury == Pubkey::default() {
+                self.treasury = self.authority;
+            }
+        }
+        if self.version < 2 {
+            // Version 2 pools track the value locked; start from the vault
+            self.total_shielded = vault_balance;
+            self.total_unshielded = 0;
+        }
+        if self.version < 3 {
+            // Version 3 roots carry their slot; older ones count as mature
+            self.root_slot = 0;
+            self.min_spend_delay_slots = 0;
+        }
+        if self.version < 4 {
+            self.min_anonymity_set = 0;
+        }
+        if self.version < 5 {
+            self.withdrawal_limit = RateLimiter::default();
+        }
+        if self.version < 6 {
+            self.deposit_cap = 0;
+        }
+        if self.version < 7 {
+            self.close_scheduled_at = 0;
+        }
+        if self.version < 8 {
+            // Version 8 pools time unshield pauses; start the clock now
+            self.unshields_paused_at = if self.unshields_paused != 0 { slot } else { 0 };
+        }
+        if self.version < 9 {
+            // Version 9 pools record their mint's decimals
+            self.decimals = decimals;
+            self._padding = [0; 7];
+        }
+        if self.version < 10 {
+            self.strategy_deployed = 0;
+        }
+        if self.version < 11 {
+            self.max_rent_fee = 0;
+        }
+        if self.version < 12 {
+            self.allowed_callers = [Pubkey::default(); MAX_ALLOWED_CALLERS];
+            self.cpi_policy = CpiPolicy::AllowAny as u8;
+            self._padding2 = [0; 7];
+        }
+        if self.version < 13 {
+            self.allowed_relayers = [Pubkey::default(); MAX_ALLOWED_RELAYERS];
+            self.relayer_policy = RelayerPolicy::Open as u8;
+            self._padding3 = [0; 7];
+        }
+        if self.version < 14 {
+            self.clear_vk_timelock_change();
+        }
+        self.version = POOL_VERSION;
     }
 
-    /// Check if root is valid (current or in history)
-    pub fn is_valid_root(&self, root: &[u8; 32]) -> bool {
-        // Check current root
-        if *root == self.merkle_tree.current_root {
-            return true;
+    /// Hand the pool to its proposed authority, returning the old one
+    pub fn accept_authority(&mut self, new_authority: &Pubkey) -> Result<Pubkey> {
+        require_keys_eq!(
+            self.pending_authority,
+            *new_authority,
+            NyxError::NotPendingAuthority
+        );
+        let old_authority = self.authority;
+        self.authority = *new_authority;
+        self.pending_authority = Pubkey::default();
+        Ok(old_authority)
+    }
+
+    /// Pause shields and transfers, and unshields unless `allow_unshields`
+    ///
+    /// Re-pausing keeps the slot unshields were first paused at, so the
+    /// emergency withdrawal delay can't be pushed back.
+    pub fn pause(&mut self, allow_unshields: bool, slot: u64) {
+        self.paused = 1;
+        if allow_unshields {
+            self.unshields_paused = 0;
+            self.unshields_paused_at = 0;
+        } else if self.unshields_paused == 0 {
+            self.unshields_paused = 1;
+            self.unshields_paused_at = slot;
         }
-        // Check history
-        self.root_history.iter().any(|r| r == root && *r != [0u8; 32])
     }
 
-    /// Check if nullifier is spent
-    /// Note: This requires a separate NullifierSet account for actual lookup
-    /// For now, this is a placeholder that always returns false
-    pub fn is_nullifier_spent(&self, _nullifier: &[u8; 32]) -> bool {
-        // Real implementation uses NullifierSet account
-        false
+    /// Lift a pause
+    pub fn unpause(&mut self) {
+        self.paused = 0;
+        self.unshields_paused = 0;
+        self.unshields_paused_at = 0;
     }
 
-    /// Mark nullifier as spent (increment counter only)
-    /// Note: Actual nullifier storage is in NullifierSet account
-    pub fn record_nullifier_spent(&mut self) {
-        self.nullifier_count += 1;
+    /// Fail unless unshields have been halted for `EMERGENCY_WITHDRAW_DELAY_SLOTS`
+    ///
+    /// Only the pool's unshield pause counts: the protocol pause leaves
+    /// unshields open, so it never strands a note.
+    pub fn require_emergency_withdrawals_allowed(&self, slot: u64) -> Result<()> {
+        require!(self.unshields_paused != 0, NyxError::EmergencyWithdrawLocked);
+        require!(
+            slot >= self
+                .unshields_paused_at
+                .saturating_add(EMERGENCY_WITHDRAW_DELAY_SLOTS),
+            NyxError::EmergencyWithdrawLocked
+        );
+        Ok(())
     }
-}
 
-/// Nullifier account (separate account for nullifier set)
-#[account]
-pub struct NullifierSet {
-    /// Pool this nullifier set belongs to
-    pub pool: Pubkey,
+    /// Fail if shields and transfers are paused
+    pub fn require_not_paused(&self) -> Result<()> {
+        require!(self.paused == 0, NyxError::PoolPaused);
+        Ok(())
+    }
 
-    /// Nullifier bitmap (each bit represents a nullifier slot)
-    pub bitmap: [u8; 1024],
-}
+    /// Fail once the pool is scheduled to close
+    ///
+    /// A deposit would leave the pool undrained and block the close, so
+    /// pools stop taking them until the close is cancelled.
+    pub fn require_not_closing(&self) -> Result<()> {
+        require!(self.close_scheduled_at == 0, NyxError::PoolClosing);
+        Ok(())
+    }
 
-impl NullifierSet {
-    /// Account size
-    pub const SIZE: usize = 32 + 1024;
+    /// Fail if unshields are paused
+    pub fn require_unshields_allowed(&self) -> Result<()> {
+        require!(self.unshields_paused == 0, NyxError::PoolPaused);
+        Ok(())
+    }
+
+    /// Fail until the pool has more deposits than its minimum anonymity set
+    pub fn require_anonymity_set(&self) -> Result<()> {
+        require!(
+            self.deposit_count > self.min_anonymity_set,
+            NyxError::AnonymitySetTooSmall
+        );
+        Ok(())
+    }
+
+    /// Check whether proofs may be verified with a key of the given version
+    ///
+    /// The active version is always accepted. The previous version stays
+    /// valid for the grace period after a rotation, so proofs generated
+    /// before it still go through.
+    pub fn is_vk_version_accepted(&self, version: u32, slot: u64) -> bool {
+        if version == 0 || self.vk_version == 0 {
+            return false;
+        }
+        version == self.vk_version
+            || (version + 1 == self.vk_version
+                && slot < self.vk_activated_at.saturating_add(self.vk_timelock(slot).1))
+    }
+
+    /// Which notes the pool holds
+    pub fn asset_mode(&self) -> AssetMode {
+        match self.asset_mode {
+            1 => AssetMode::Multi,
+            2 => AssetMode::Nft,
+            _ => AssetMode::Single,
+        }
+    }
+
+    /// Which programs may shield through CPI
+    pub fn cpi_policy(&self) -> CpiPolicy {
+        match self.cpi_policy {
+            1 => CpiPolicy::DenyCpi,
+            2 => CpiPolicy::Allowlist,
+            _ => CpiPolicy::AllowAny,
+        }
+    }
+
+    /// Set the CPI policy and, for `CpiPolicy::Allowlist`, its callers
+    pub fn set_cpi_policy(&mut self, policy: CpiPolicy, allowed_callers: &[Pubkey]) -> Result<()> {
+        require!(
+            allowed_callers.len() <= MAX_ALLOWED_CALLERS
+                && !allowed_callers.contains(&Pubkey::default()),
+            NyxError::InvalidAllowedCallers
+        );
+        self.allowed_callers = [Pubkey::default(); MAX_ALLOWED_CALLERS];
+        self.allowed_callers[..allowed_callers.len()].copy_from_slice(allowed_callers);
+        self.cpi_policy = policy as u8;
+        Ok(())
+    }
+
+    /// Fail unless the CPI policy lets `caller` shield, `None` being a
+    /// direct transaction
+    pub fn require_caller_allowed(&self, caller: Option<&Pubkey>) -> Result<()> {
+        let allowed = match (self.cpi_policy(), caller) {
+            (CpiPolicy::AllowAny, _) | (CpiPolicy::DenyCpi, None) => true,
+            (CpiPolicy::DenyCpi, Some(_)) | (CpiPolicy::Allowlist, None) => false,
+            (CpiPolicy::Allowlist, Some(caller)) => self.allowed_callers.contains(caller),
+        };
+        require!(allowed, NyxError::CallerNotAllowed);
+        Ok(())
+    }
+
+    /// Which relayers may submit transfers and withdrawals
+    pub fn relayer_policy(&self) -> RelayerPolicy {
+        match self.relayer_policy {
+            1 => RelayerPolicy::RegistryOnly,
+            2 => RelayerPolicy::Allowlist,
+            _ => RelayerPolicy::Open,
+        }
+    }
+
+    /// Set the relayer policy and, for `RelayerPolicy::Allowlist`, its
+    /// relayers
+    pub fn set_relayer_policy(
+        &mut self,
+        policy: RelayerPolicy,
+        allowed_relayers: &[Pubkey],
+    ) -> Result<()> {
+        require!(
+            allowed_relayers.len() <= MAX_ALLOWED_RELAYERS
+                && !allowed_relayers.contains(&Pubkey::default()),
+            NyxError::InvalidAllowedRelayers
+        );
+        self.allowed_relayers = [Pubkey::default(); MAX_ALLOWED_RELAYERS];
+        self.allowed_relayers[..allowed_relayers.len()].copy_from_slice(allowed_relayers);
+        self.relayer_policy = policy as u8;
+        Ok(())
+    }
+
+    /// Fail unless the relayer policy lets `relayer` submit a spend,
+    /// `bonded` telling whether it holds a bond that isn't unbonding
+    pub fn require_relayer_allowed(&self, relayer: &Pubkey, bonded: bool) -> Result<()> {
+        let allowed = match self.relayer_policy() {
+            RelayerPolicy::Open => true,
+            RelayerPolicy::RegistryOnly => bonded,
+            RelayerPolicy::Allowlist => self.allowed_relayers.contains(relayer),
+        };
+        require!(allowed, NyxError::RelayerNotAllowed);
+        Ok(())
+    }
+
+    /// Check if the pool holds notes of several mints (multi-asset and NFT pools)
+    pub fn is_multi_asset(&self) -> bool {
+        self.asset_mode() != AssetMode::Single
+    }
+
+    /// Fail for multi-asset pools, whose shields and unshields must bind
+    /// the asset
+    pub fn require_single_asset(&self) -> Result<()> {
+        require!(!self.is_multi_asset(), NyxError::MultiAssetPoolUnsupported);
+        Ok(())
+    }
+
+    /// Fail unless the pool holds native SOL
+    ///
+    /// SOL shields and withdrawals move lamports through the vault PDA,
+    /// which an SPL pool has too, as the authority of its token vault:
+    /// lamports shielded into an SPL pool would mint notes its token
+    /// withdrawals pay out.
+    pub fn require_sol_pool(&self) -> Result<()> {
+        self.require_single_asset()?;
+        require!(self.mint == Pubkey::default(), token::TokenError::MintMismatch);
+        Ok(())
+    }
+
+    /// Fail unless the pool holds a single SPL mint
+    pub fn require_spl_pool(&self) -> Result<()> {
+        self.require_single_asset()?;
+        require!(self.mint != Pubkey::default(), token::TokenError::MintMismatch);
+        Ok(())
+    }
+
+    /// Whether `address` is the pool's PDA
+    ///
+    /// SPL pools created before pool addresses were keyed by mint keep
+    /// their denomination-only address, which only they can hold: since
+    /// then that address is reserved for the native SOL pool.
+    pub fn is_at(&self, address: &Pubkey) -> bool {
+        let denomination = self.denomination.to_le_bytes();
+        let bump = [self.bump];
+        let derives = |mint_seed: &[u8]| {
+            let seeds: &[&[u8]] = &[token::POOL_SEED, &denomination, mint_seed, &bump];
+            Pubkey::create_program_address(seeds, &crate::ID).is_ok_and(|pda| pda == *address)
+        };
+        derives(token::pool_mint_seed(&self.mint))
+            || (self.mint != Pubkey::default() && derives(&[]))
+    }
+
+    /// Fail unless the pool is a multi-asset pool
+    pub fn require_multi_asset(&self) -> Result<()> {
+        require!(self.asset_mode() == AssetMode::Multi, NyxError::NotMultiAssetPool);
+        Ok(())
+    }
+
+    /// Fail unless the pool is an NFT pool
+    pub fn require_nft(&self) -> Result<()> {
+        require!(self.asset_mode() == AssetMode::Nft, NyxError::NotNftPool);
+        Ok(())
+    }
+
+    /// Check if this is a fixed denomination pool
+    pub fn is_fixed_denomination(&self) -> bool {
+        self.denomination > 0
+    }
+
+    /// Convert a whole number of the mint's UI units to base units
+    pub fn from_ui_units(&self, ui_amount: u64) -> Option<u64> {
+        token::ui_to_base_units(ui_amount, self.decimals)
+    }
+
+    /// Split an amount into whole UI units and remaining base units
+    pub fn to_ui_units(&self, amount: u64) -> (u64, u64) {
+        token::base_to_ui_units(amount, self.decimals)
+    }
+
+    /// The pool's denomination in UI units (whole units, remaining base units)
+    pub fn denomination_ui(&self) -> (u64, u64) {
+        self.to_ui_units(self.denomination)
+    }
+
+    /// Check if amount matches pool denomination (for fixed pools)
+    pub fn validate_amount(&self, amount: u64) -> bool {
+        if self.denomination == 0 {
+            // Custom pool accepts any amount > 0
+            amount > 0
+        } else {
+            // Fixed pool requires exact match
+            amount == self.denomination
+        }
+    }
+
+    /// Add a shielded amount to the value locked in the pool
+    pub fn record_shielded(&mut self, amount: u64) -> Result<()> {
+        self.total_shielded = self
+            .total_shielded
+            .checked_add(amount)
+            .ok_or(NyxError::AccountingOverflow)?;
+        Ok(())
+    }
+
+    /// Add an unshielded amount, fees included, to the pool's outflows
+    ///
+    /// Fails if the pool would pay out more than was ever shielded.
+    pub fn record_unshielded(&mut self, amount: u64) -> Result<()> {
+        let total = self
+            .total_unshielded
+            .checked_add(amount)
+            .ok_or(NyxError::AccountingOverflow)?;
+        require!(total <= self.total_shielded, NyxError::PoolInsolvent);
+        self.total_unshielded = total;
+        Ok(())
+    }
+
+    /// Fail if depositing `amount` would push the vault past the deposit cap
+    pub fn check_deposit_cap(&self, vault_balance: u64, amount: u64) -> Result<()> {
+        require!(
+            self.deposit_cap == 0 || vault_balance.saturating_add(amount) <= self.deposit_cap,
+            NyxError::DepositCapExceeded
+        );
+        Ok(())
+    }
+
+    /// Value currently locked in the pool
+    pub fn total_value_locked(&self) -> u64 {
+        self.total_shielded - self.total_unshielded
+    }
+
+    /// Fail unless the vault, with what it deposited with its yield
+    /// strategy, still holds the value locked in the pool
+    pub fn check_solvency(&self, vault_balance: u64) -> Result<()> {
+        require!(
+            vault_balance.saturating_add(self.strategy_deployed) >= self.total_value_locked(),
+            NyxError::PoolInsolvent
+        );
+        Ok(())
+    }
+
+    /// Revalue what the yield strategy holds at `lamports`, gains and
+    /// losses alike
+    pub fn mark_strategy(&mut self, lamports: u64) {
+        self.strategy_deployed = lamports;
+    }
+
+    /// Record lamports deposited with the yield strategy
+    pub fn record_strategy_deposit(&mut self, lamports: u64) {
+        self.strategy_deployed = self.strategy_deployed.saturating_add(lamports);
+    }
+
+    /// Record redeeming `tokens` of the `held` stake pool tokens, releasing
+    /// their share of the deposited lamports
+    pub fn record_strategy_withdrawal(&mut self, tokens: u64, held: u64) {
+        if held == 0 {
+            return;
+        }
+        let released = (self.strategy_deployed as u128 * tokens.min(held) as u128 / held as u128) as u64;
+        self.strategy_deployed -= released;
+    }
+
+    /// Increment deposit count (call after successful shield)
+    pub fn record_deposit(&mut self) {
+        self.deposit_count = self.deposit_count.saturating_add(1);
+    }
+
+    /// Calculate relayer fee for a given amount
+    pub fn calculate_relayer_fee(&self, amount: u64) -> u64 {
+        // fee = amount * fee_bps / 10000
+        (amount as u128 * self.relayer_fee_bps as u128 / 10000) as u64
+    }
+
+    /// Largest fee a relayer may charge on an SPL unshield of `amount`
+    ///
+    /// The relayer fee rate, plus up to `max_rent_fee` reimbursing the SOL
+    /// the relayer `fronted`: the rent of a recipient token account it
+    /// created or a refund it paid the recipient.
+    pub fn max_unshield_fee(&self, amount: u64, fronted: bool) -> u64 {
+        let rent_fee = if fronted { self.max_rent_fee } else { 0 };
+        self.calculate_relayer_fee(amount).saturating_add(rent_fee)
+    }
+
+    /// Split an SPL unshield fee into its rate part, shared with the
+    /// treasury, and the rent reimbursement above the rate
+    pub fn split_rent_fee(&self, amount: u64, fee: u64) -> (u64, u64) {
+        let rate_fee = fee.min(self.calculate_relayer_fee(amount));
+        (rate_fee, fee - rate_fee)
+    }
+
+    /// Record a fee payment
+    pub fn record_fee_collected(&mut self, fee: u64) {
+        self.total_fees_collected = self.total_fees_collected.saturating_add(fee);
+    }
+
+    /// Add a commitment to the tree at `slot`
+    pub fn add_commitment(
+        &mut self,
+        commitment: [u8; 32],
+        root_history: &mut [RootEntry],
+        slot: u64,
+    ) -> Result<u64> {
+        // Store old root in history before updating
+        let old_root = self.merkle_tree.current_root;
+
+        // Insert into Merkle tree
+        let leaf_index = self.merkle_tree.insert_with_canopy(commitment, &mut self.canopy)
+            .map_err(|_| NyxError::PoolFull)?;
+
+        self.push_root_history(old_root, root_history, slot);
+        Ok(leaf_index)
+    }
+
+    /// Record a leaf appended to the compressed tree
+    ///
+    /// `new_root` is the compressed tree's root after the append at `slot`.
+    pub fn record_compressed_commitment(
+        &mut self,
+        new_root: [u8; 32],
+        root_history: &mut [RootEntry],
+        slot: u64,
+    ) -> u64 {
+        let old_root = self.compressed_root;
+        let leaf_index = self.compressed_leaf_count;
+
+        self.compressed_root = new_root;
+        self.compressed_leaf_count += 1;
+
+        self.push_root_history(old_root, root_history, slot);
+        leaf_index
+    }
+
+    /// Add a replaced root to history (circular buffer)
+    ///
+    /// The replacement became current at `slot`.
+    fn push_root_history(&mut self, old_root: [u8; 32], root_history: &mut [RootEntry], slot: u64) {
+        root_history[self.root_history_index as usize] = RootEntry {
+            root: old_root,
+            slot: self.root_slot,
+        };
+        self.root_history_index = (self.root_history_index + 1) % self.root_history_size;
+        self.root_slot = slot;
+    }
+
+    /// Entries in history, newest first
+    fn recent_entries<'a>(
+        &self,
+        root_history: &'a [RootEntry],
+    ) -> impl Iterator<Item = RootEntry> + 'a {
+        let size = root_history.len();
+        let newest = self.root_history_index as usize + size;
+        (1..=size)
+            .map(move |age| root_history[(newest - age) % size])
+            .filter(|entry| entry.root != [0u8; 32])
+    }
+
+    /// Roots in history, newest first
+    pub fn recent_roots<'a>(
+        &self,
+        root_history: &'a [RootEntry],
+    ) -> impl Iterator<Item = [u8; 32]> + 'a {
+        self.recent_entries(root_history).map(|entry| entry.root)
+    }
+
+    /// Resize the root history, keeping the newest roots that still fit
+    ///
+    /// `old_history` is the current history; `new_history` (of the new
+    /// size) is overwritten with the kept roots, oldest first.
+    pub fn resize_root_history(
+        &mut self,
+        old_history: &[RootEntry],
+        new_history: &mut [RootEntry],
+    ) {
+        let mut kept: Vec<RootEntry> = self
+            .recent_entries(old_history)
+            .take(new_history.len())
+            .collect();
+        kept.reverse();
+
+        new_history.fill(RootEntry::default());
+        new_history[..kept.len()].copy_from_slice(&kept);
+        self.root_history_size = new_history.len() as u16;
+        self.root_history_index = (kept.len() % new_history.len()) as u16;
+    }
+
+    /// Get current Merkle root
+    pub fn current_root(&self) -> [u8; 32] {
+        match self.tree_backend() {
+            TreeBackend::Incremental => self.merkle_tree.current_root,
+            TreeBackend::Compressed => self.compressed_root,
+        }
+    }
+
+    /// Get number of commitments in tree
+    pub fn commitment_count(&self) -> u64 {
+        match self.tree_backend() {
+            TreeBackend::Incremental => self.merkle_tree.next_index,
+            TreeBackend::Compressed => self.compressed_leaf_count,
+        }
+    }
+
+    /// Maximum number of commitments the current tree holds
+    pub fn tree_capacity(&self) -> u64 {
+        match self.tree_backend() {
+            TreeBackend::Incremental => IncrementalMerkleTree::MAX_LEAVES,
+            TreeBackend::Compressed => 1u64 << self.compression_max_depth,
+        }
+    }
+
+    /// Check if the current tree has room for `count` more commitments
+    pub fn has_room_for(&self, count: u64) -> bool {
+        self.commitment_count().saturating_add(count) <= self.tree_capacity()
+    }
+
+    /// Check if `count` more commitments can be inserted, incremental pools
+    /// rolling over to a new tree when the current one is out of room
+    pub fn can_insert(&self, count: u64) -> bool {
+        self.has_room_for(count)
+            || (self.tree_backend() == TreeBackend::Incremental
+                && count <= IncrementalMerkleTree::MAX_LEAVES)
+    }
+
+    /// Index of the commitment chunk the next leaf is written to
+    pub fn current_chunk_index(&self) -> u32 {
+        CommitmentChunk::index_of(self.commitment_count())
+    }
+
+    /// Check if the current tree has no room left
+    pub fn is_tree_full(&self) -> bool {
+        !self.has_room_for(1)
+    }
+
+    /// Start a new empty tree, returning the final root of the old one
+    ///
+    /// The caller archives the returned root in a `TreeEpoch` so notes in
+    /// the old tree stay withdrawable. Root history and canopy belong to the
+    /// old tree and are cleared.
+    pub fn rollover_tree(&mut self, root_history: &mut [RootEntry]) -> [u8; 32] {
+        let final_root = self.merkle_tree.current_root;
+        self.merkle_tree = IncrementalMerkleTree::new();
+        root_history.fill(RootEntry::default());
+        self.canopy = MerkleCanopy::new();
+        self.root_history_index = 0;
+        self.tree_epoch = self.tree_epoch.saturating_add(1);
+        final_root
+    }
+
+    /// Check if root is valid (current or in history)
+    pub fn is_valid_root(&self, root: &[u8; 32], root_history: &[RootEntry]) -> bool {
+        self.root_slot_of(root, root_history).is_some()
+    }
+
+    /// Slot at which a valid root became current, or `None` if it isn't valid
+    pub fn root_slot_of(&self, root: &[u8; 32], root_history: &[RootEntry]) -> Option<u64> {
+        // Check current root
+        if *root == self.current_root() {
+            return Some(self.root_slot);
+        }
+        // Check history
+        root_history
+            .iter()
+            .find(|entry| entry.root == *root && entry.root != [0u8; 32])
+            .map(|entry| entry.slot)
+    }
+
+    /// Mark nullifier as spent (increment counter only)
+    /// Note: Actual nullifier storage is in the NullifierSet shards
+    pub fn record_nullifier_spent(&mut self) {
+        self.nullifier_count += 1;
+    }
+}
+
+const _: () = assert!(PrivacyPool::SIZE == std::mem::size_of::<PrivacyPool>());
+
+/// Split pool account data into the pool and its root history
+pub fn split_pool_data(data: &[u8]) -> (&PrivacyPool, &[RootEntry]) {
+    let (header, roots) = data.split_at(8 + PrivacyPool::SIZE);
+    let pool: &PrivacyPool = bytemuck::from_bytes(&header[8..]);
+    let len = (pool.root_history_size as usize).min(roots.len() / RootEntry::SIZE);
+    (pool, bytemuck::cast_slice(&roots[..RootEntry::SIZE * len]))
+}
+
+/// Split mutable pool account data into the pool and its root history
+pub fn split_pool_data_mut(data: &mut [u8]) -> (&mut PrivacyPool, &mut [RootEntry]) {
+    let (header, roots) = data.split_at_mut(8 + PrivacyPool::SIZE);
+    let pool: &mut PrivacyPool = bytemuck::from_bytes_mut(&mut header[8..]);
+    let len = (pool.root_history_size as usize).min(roots.len() / RootEntry::SIZE);
+    (pool, bytemuck::cast_slice_mut(&mut roots[..RootEntry::SIZE * len]))
+}
+
+/// Borrow a pool and its root history
+///
+/// The account must already have passed `AccountLoader` validation.
+pub fn load_pool<'a>(
+    pool: &'a AccountLoader<'_, PrivacyPool>,
+) -> Result<(Ref<'a, PrivacyPool>, Ref<'a, [RootEntry]>)> {
+    let data = pool.as_ref().try_borrow_data()?;
+    require!(
+        data.len() >= 8 + PrivacyPool::SIZE,
+        NyxError::InvalidRootHistorySize
+    );
+    let (pool, roots) = Ref::map_split(data, |data| split_pool_data(data));
+    require!(
+        roots.len() == pool.root_history_size as usize,
+        NyxError::InvalidRootHistorySize
+    );
+    Ok((pool, roots))
+}
+
+/// Mutably borrow a pool and its root history
+///
+/// The account must already have passed `AccountLoader` validation.
+pub fn load_pool_mut<'a>(
+    pool: &'a AccountLoader<'_, PrivacyPool>,
+) -> Result<(RefMut<'a, PrivacyPool>, RefMut<'a, [RootEntry]>)> {
+    let data = pool.as_ref().try_borrow_mut_data()?;
+    require!(
+        data.len() >= 8 + PrivacyPool::SIZE,
+        NyxError::InvalidRootHistorySize
+    );
+    let (pool, roots) = RefMut::map_split(data, |data| split_pool_data_mut(data));
+    require!(
+        roots.len() == pool.root_history_size as usize,
+        NyxError::InvalidRootHistorySize
+    );
+    Ok((pool, roots))
+}
+
+/// A pool listed in the registry
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RegisteredPool {
+    /// Pool PDA
+    pub pool: Pubkey,
+
+    /// Mint of the pool's notes (default pubkey for native SOL)
+    pub mint: Pubkey,
+
+    /// Fixed denomination of the pool
+    pub denomination: u64,
+}
+
+impl RegisteredPool {
+    pub const SIZE: usize = 32 + 32 + 8;
+}
+
+/// Global list of canonical pools
+///
+/// Singleton PDA filled by `initialize_standard_pools`, so front-ends can
+/// discover pools instead of hard-coding denominations. The protocol admin
+/// creates it with `initialize_pool_registry` and becomes its authority.
+#[account]
+pub struct PoolRegistry {
+    /// Account allowed to register pools
+    pub authority: Pubkey,
+
+    /// Registered pools, in registration order
+    pub pools: Vec<RegisteredPool>,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl PoolRegistry {
+    /// Account size, with room for `MAX_REGISTERED_POOLS` pools
+    pub const SIZE: usize = 32 + 4 + RegisteredPool::SIZE * MAX_REGISTERED_POOLS + 1;
+
+    /// Add a pool to the registry
+    pub fn register(&mut self, pool: RegisteredPool) -> Result<()> {
+        require!(
+            self.pools.len() < MAX_REGISTERED_POOLS,
+            NyxError::PoolRegistryFull
+        );
+        self.pools.push(pool);
+        Ok(())
+    }
+}
+
+/// Protocol-wide settings shared by every pool
+///
+/// Singleton PDA created once per deployment by the program's upgrade
+/// authority. Pools read it for the protocol pause switch, which halts
+/// shields and transfers but not unshields, and the treasury's share of
+/// relayer fees. New pools take their relayer fee from it, and its VK
+/// authority may propose verifying keys for any pool.
+#[account]
+pub struct ProtocolConfig {
+    /// Account allowed to change the config
+    pub admin: Pubkey,
+
+    /// Account allowed to propose verifying keys for every pool
+    pub vk_authority: Pubkey,
+
+    /// Relayer fee new pools start with, in basis points
+    pub default_relayer_fee_bps: u16,
+
+    /// Share of each relayer fee paid to the pool treasury, in basis points
+    pub treasury_fee_share_bps: u16,
+
+    /// Whether shields and transfers are paused on every pool
+    pub paused: bool,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+
+    /// Slot at which the protocol was paused (0 = not paused)
+    pub paused_at: u64,
+
+    /// Admin proposed by the current one, until it accepts
+    pub pending_admin: Pubkey,
+}
+
+impl ProtocolConfig {
+    pub const SIZE: usize = 32 + 32 + 2 + 2 + 1 + 1 + 8 + 32;
+
+    /// Check the fee settings are within bounds
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.default_relayer_fee_bps <= MAX_RELAYER_FEE_BPS,
+            NyxError::InvalidRelayerFee
+        );
+        require!(
+            self.treasury_fee_share_bps <= 10000,
+            NyxError::InvalidProtocolConfig
+        );
+        Ok(())
+    }
+
+    /// Fail if the protocol is paused
+    pub fn require_not_paused(&self) -> Result<()> {
+        require!(!self.paused, NyxError::ProtocolPaused);
+        Ok(())
+    }
+
+    /// Pause or unpause every pool, keeping the slot of the first pause
+    pub fn set_paused(&mut self, paused: bool, slot: u64) {
+        if paused && !self.paused {
+            self.paused_at = slot;
+        } else if !paused {
+            self.paused_at = 0;
+        }
+        self.paused = paused;
+    }
+
+    /// Split a relayer fee into the relayer's and the treasury's shares
+    pub fn split_relayer_fee(&self, fee: u64) -> (u64, u64) {
+        let treasury_share = (fee as u128 * self.treasury_fee_share_bps as u128 / 10000) as u64;
+        (fee - treasury_share, treasury_share)
+    }
+}
+
+/// Yield strategy of a SOL pool
+///
+/// One PDA per pool, created by the authority to opt the pool in with a
+/// stake pool from the protocol's `StakePoolAllowlist`. The vault keeps
+/// `buffer_bps` of the value locked liquid for withdrawals, and
+/// `rebalance_yield_strategy` deposits anything above that with the stake
+/// pool, or redeems stake pool tokens when the vault falls short.
+/// Redemptions are paid from the stake pool's reserve only, so the buffer
+/// is what withdrawals can count on. Raising the buffer to 100% unwinds
+/// the strategy.
+#[account]
+pub struct YieldStrategy {
+    /// Pool whose vault the strategy manages
+    pub pool: Pubkey,
+
+    /// SPL stake pool the vault deposits with
+    pub stake_pool: Pubkey,
+
+    /// The stake pool's token, held in the vault's associated token account
+    pub pool_mint: Pubkey,
+
+    /// Share of the value locked kept in the vault, in basis points
+    pub buffer_bps: u16,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+
+    /// Lamports ever deposited with the stake pool
+    pub total_deposited: u64,
+
+    /// Lamports ever redeemed from the stake pool, yield included
+    pub total_withdrawn: u64,
+}
+
+impl YieldStrategy {
+    pub const SIZE: usize = 32 + 32 + 32 + 2 + 1 + 8 + 8;
+
+    /// Check the buffer is within bounds
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            (MIN_STRATEGY_BUFFER_BPS..=10000).contains(&self.buffer_bps),
+            NyxError::InvalidStrategyBuffer
+        );
+        Ok(())
+    }
+
+    /// Lamports the vault should keep liquid for `value_locked`
+    pub fn target_buffer(&self, value_locked: u64) -> u64 {
+        (value_locked as u128 * self.buffer_bps as u128 / 10000) as u64
+    }
+}
+
+/// Note of a SOL pool spent into a claimable link
+///
+/// `create_claim` spends the note to this PDA, which holds the lamports
+/// until the key committed to by `claim_hash` signs them over to a
+/// recipient (see `claim`), or, once `expiry_slot` passes, until they are
+/// returned to `refund_to`. The seeds bind the hash, the refund address
+/// and the expiry, so the proof binding the PDA as its recipient fixes all
+/// three.
+#[account]
+pub struct ClaimLink {
+    /// Pool the note was spent from
+    pub pool: Pubkey,
+
+    /// `claim::claim_hash` of the link's key
+    pub claim_hash: [u8; 32],
+
+    /// Where unclaimed lamports go after expiry
+    pub refund_to: Pubkey,
+
+    /// Last slot at which the link can be claimed
+    pub expiry_slot: u64,
+
+    /// Lamports claimable, excluding the account's rent
+    pub amount: u64,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl ClaimLink {
+    pub const SIZE: usize = 32 + 32 + 32 + 8 + 8 + 1;
+
+    /// Whether the link can still be claimed at `slot`
+    pub fn is_claimable(&self, slot: u64) -> bool {
+        slot <= self.expiry_slot
+    }
+}
+
+/// Note of a SOL pool spent into a stream of periodic payments
+///
+/// `create_payment_stream` spends the note to this PDA, and from then on
+/// anyone, typically an automation thread such as Clockwork's, can crank
+/// `execute_stream_payment` to pay `recipient` up to `amount_per_period`
+/// once per `period_slots`. Payments missed by a late crank can be caught
+/// up, one period per call, but never more than the periods elapsed. The
+/// seeds bind every term, so the proof binding the PDA as its recipient
+/// fixes them. The schedule is public; what stays private is the note
+/// that funded it.
+#[account]
+pub struct PaymentStream {
+    /// Pool the note was spent from
+    pub pool: Pubkey,
+
+    /// Caller-chosen nonce, so streams with the same terms don't collide
+    pub stream_id: [u8; 32],
+
+    /// Account paid each period
+    pub recipient: Pubkey,
+
+    /// Where the unpaid balance goes when the stream is cancelled or ends
+    pub refund_to: Pubkey,
+
+    /// Most lamports paid per period
+    pub amount_per_period: u64,
+
+    /// Length of a period in slots
+    pub period_slots: u64,
+
+    /// First slot at which the next payment is due
+    pub next_payment_slot: u64,
+
+    /// Lamports left to pay, excluding the account's rent
+    pub remaining: u64,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl PaymentStream {
+    pub const SIZE: usize = 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 1;
+
+    /// Whether a payment is due at `slot`
+    pub fn is_due(&self, slot: u64) -> bool {
+        self.remaining > 0 && slot >= self.next_payment_slot
+    }
+
+    /// Amount of the next payment
+    pub fn next_payment(&self) -> u64 {
+        self.amount_per_period.min(self.remaining)
+    }
+}
+
+/// Withdrawal intent recorded ahead of its execution
+///
+/// `queue_unshield` stores only a hash of the withdrawal, and
+/// `execute_unshield` reveals and performs it at a slot of the owner's
+/// choosing within the window. Picking that slot at random decouples the
+/// withdrawal's timing from anything observable when it was decided. The
+/// hash covers the nullifier, so it can't be matched to a withdrawal
+/// before the withdrawal is revealed.
+#[account]
+pub struct QueuedUnshield {
+    /// Pool the withdrawal is from
+    pub pool: Pubkey,
+
+    /// `intent_hash` of the withdrawal
+    pub intent_hash: [u8; 32],
+
+    /// Account that paid the rent, refunded when the intent is closed
+    pub payer: Pubkey,
+
+    /// Slot the intent was queued at
+    pub queued_slot: u64,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl QueuedUnshield {
+    pub const SIZE: usize = 32 + 32 + 32 + 8 + 1;
+
+    /// Hash of a SOL withdrawal's parameters
+    pub fn intent_hash(
+        nullifier: &[u8; 32],
+        recipient: &Pubkey,
+        amount: u64,
+        fee: u64,
+        expiry_slot: u64,
+    ) -> [u8; 32] {
+        solana_program::hash::hashv(&[
+            QUEUED_UNSHIELD_SEED,
+            nullifier,
+            recipient.as_ref(),
+            &amount.to_le_bytes(),
+            &fee.to_le_bytes(),
+            &expiry_slot.to_le_bytes(),
+        ])
+        .to_bytes()
+    }
+
+    /// Whether the withdrawal can be executed at `slot`
+    pub fn is_executable(&self, slot: u64) -> bool {
+        let since_queued = slot.saturating_sub(self.queued_slot);
+        (UNSHIELD_QUEUE_MIN_DELAY_SLOTS..=UNSHIELD_QUEUE_WINDOW_SLOTS).contains(&since_queued)
+    }
+}
+
+/// Recipient of an emergency withdrawal, committed before the note is revealed
+///
+/// Keyed by the note's nullifier, which only the owner can compute until
+/// `emergency_withdraw` reveals the preimage. By then the commit already
+/// exists, so a copy of the reveal can't redirect the payout.
+#[account]
+pub struct EmergencyCommit {
+    /// Pool the note is in
+    pub pool: Pubkey,
+
+    /// Account the withdrawal must pay
+    pub recipient: Pubkey,
+
+    /// Account that paid the rent, which must submit the withdrawal
+    pub payer: Pubkey,
+
+    /// Slot of the commit
+    pub committed_slot: u64,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl EmergencyCommit {
+    pub const SIZE: usize = 32 + 32 + 32 + 8 + 1;
+}
+
+/// SOL a relayer bonded against overcharging its quoted fees
+///
+/// The bond is the account's lamports above its rent. Disputes slash it
+/// until the relayer withdraws it, which closes the account.
+#[account]
+pub struct RelayerBond {
+    /// The bonded relayer
+    pub relayer: Pubkey,
+
+    /// Slot the relayer asked to unbond at (0 = bonded)
+    pub unbond_requested_at: u64,
+
+    /// Lamports slashed from the bond so far
+    pub total_slashed: u64,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl RelayerBond {
+    pub const SIZE: usize = 32 + 8 + 8 + 1;
+
+    /// Whether the bond can be withdrawn at `slot`
+    pub fn is_withdrawable(&self, slot: u64) -> bool {
+        self.unbond_requested_at != 0
+            && slot >= self.unbond_requested_at.saturating_add(RELAYER_UNBONDING_SLOTS)
+    }
+}
+
+/// Record that a withdrawal's fee was disputed, so a relayer is slashed
+/// once per overcharged withdrawal
+#[account]
+pub struct RelayerDispute {
+    /// Pool the withdrawal was from
+    pub pool: Pubkey,
+
+    /// Nullifier of the withdrawal
+    pub nullifier: [u8; 32],
+
+    /// Relayer that was slashed
+    pub relayer: Pubkey,
+
+    /// Lamports slashed
+    pub slashed: u64,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl RelayerDispute {
+    pub const SIZE: usize = 32 + 32 + 32 + 8 + 1;
+}
+
+/// Terms a relayer currently offers for a pool's withdrawals
+///
+/// Wallets list a pool's quotes with a `getProgramAccounts` filter on
+/// `pool`, the first field, and compare relayers by fee and minimum
+/// instead of trusting a single hard-coded endpoint.
+#[account]
+pub struct RelayerQuote {
+    /// Pool the quote is for
+    pub pool: Pubkey,
+
+    /// The quoting relayer, which submits withdrawals as their signer
+    pub relayer: Pubkey,
+
+    /// Fee the relayer charges, in basis points of the withdrawal
+    pub fee_bps: u16,
+
+    /// Smallest withdrawal the relayer accepts, in the pool's units
+    pub min_withdrawal: u64,
+
+    /// Slot of the last update
+    pub updated_at: u64,
+
+    /// Endpoint wallets submit withdrawals to
+    pub url: String,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl RelayerQuote {
+    pub const SIZE: usize = 32 + 32 + 2 + 8 + 8 + (4 + MAX_RELAYER_URL_LEN) + 1;
+
+    /// Fail unless the quote is within the pool's relayer fee and the URL fits
+    pub fn validate(pool: &PrivacyPool, fee_bps: u16, url: &str) -> Result<()> {
+        require!(
+            fee_bps <= pool.relayer_fee_bps && url.len() <= MAX_RELAYER_URL_LEN,
+            NyxError::InvalidRelayerQuote
+        );
+        Ok(())
+    }
+}
+
+/// Mints SPL pools may be created for and shielded into
+///
+/// Singleton PDA managed by the protocol admin, so pools for spam tokens
+/// can't be created. Removing a mint stops new deposits into its pools but
+/// leaves withdrawals open.
+#[account]
+pub struct MintAllowlist {
+    /// Allowlisted mints, in insertion order
+    pub mints: Vec<Pubkey>,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl MintAllowlist {
+    /// Account size, with room for `MAX_ALLOWLISTED_MINTS` mints
+    pub const SIZE: usize = 4 + 32 * MAX_ALLOWLISTED_MINTS + 1;
+
+    /// Whether `mint` is allowlisted
+    pub fn contains(&self, mint: &Pubkey) -> bool {
+        self.mints.contains(mint)
+    }
+
+    /// Fail unless `mint` is allowlisted
+    pub fn require_allowed(&self, mint: &Pubkey) -> Result<()> {
+        require!(self.contains(mint), NyxError::MintNotAllowlisted);
+        Ok(())
+    }
+
+    /// Add a mint (no-op if already present)
+    pub fn add(&mut self, mint: Pubkey) -> Result<()> {
+        if self.contains(&mint) {
+            return Ok(());
+        }
+        require!(
+            self.mints.len() < MAX_ALLOWLISTED_MINTS,
+            NyxError::MintAllowlistFull
+        );
+        self.mints.push(mint);
+        Ok(())
+    }
+
+    /// Remove a mint (no-op if absent)
+    pub fn remove(&mut self, mint: &Pubkey) {
+        self.mints.retain(|m| m != mint);
+    }
+}
+
+/// Programs `unshield_cpi` may withdraw into
+///
+/// Singleton PDA managed by the protocol admin. The destination program is
+/// invoked with the payout's authority as signer, so only programs trusted
+/// to credit it to the caller's instruction belong here. The Veil program
+/// itself is never allowed.
+#[account]
+pub struct DestinationRegistry {
+    /// Registered programs, in insertion order
+    pub programs: Vec<Pubkey>,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl DestinationRegistry {
+    /// Account size, with room for `MAX_DESTINATION_PROGRAMS` programs
+    pub const SIZE: usize = 4 + 32 * MAX_DESTINATION_PROGRAMS + 1;
+
+    /// Whether `program` is registered
+    pub fn contains(&self, program: &Pubkey) -> bool {
+        self.programs.contains(program)
+    }
+
+    /// Fail unless `program` is registered
+    pub fn require_allowed(&self, program: &Pubkey) -> Result<()> {
+        require!(self.contains(program), NyxError::DestinationNotAllowlisted);
+        Ok(())
+    }
+
+    /// Add a program (no-op if already present)
+    pub fn add(&mut self, program: Pubkey) -> Result<()> {
+        require_keys_neq!(program, crate::ID, NyxError::DestinationNotAllowlisted);
+        if self.contains(&program) {
+            return Ok(());
+        }
+        require!(
+            self.programs.len() < MAX_DESTINATION_PROGRAMS,
+            NyxError::DestinationRegistryFull
+        );
+        self.programs.push(program);
+        Ok(())
+    }
+
+    /// Remove a program (no-op if absent)
+    pub fn remove(&mut self, program: &Pubkey) {
+        self.programs.retain(|p| p != program);
+    }
+}
+
+/// Stake pools yield strategies may deposit with
+///
+/// Singleton PDA managed by the protocol admin, since a pool authority
+/// picking its own stake pool could route vault SOL to one whose fees it
+/// collects. Removing a stake pool stops new deposits into it but leaves
+/// redemptions open.
+#[account]
+pub struct StakePoolAllowlist {
+    /// Allowlisted stake pools, in insertion order
+    pub stake_pools: Vec<Pubkey>,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl StakePoolAllowlist {
+    /// Account size, with room for `MAX_ALLOWLISTED_STAKE_POOLS` stake pools
+    pub const SIZE: usize = 4 + 32 * MAX_ALLOWLISTED_STAKE_POOLS + 1;
+
+    /// Whether `stake_pool` is allowlisted
+    pub fn contains(&self, stake_pool: &Pubkey) -> bool {
+        self.stake_pools.contains(stake_pool)
+    }
+
+    /// Fail unless `stake_pool` is allowlisted
+    pub fn require_allowed(&self, stake_pool: &Pubkey) -> Result<()> {
+        require!(self.contains(stake_pool), NyxError::StakePoolNotAllowlisted);
+        Ok(())
+    }
+
+    /// Add a stake pool (no-op if already present)
+    pub fn add(&mut self, stake_pool: Pubkey) -> Result<()> {
+        if self.contains(&stake_pool) {
+            return Ok(());
+        }
+        require!(
+            self.stake_pools.len() < MAX_ALLOWLISTED_STAKE_POOLS,
+            NyxError::StakePoolAllowlistFull
+        );
+        self.stake_pools.push(stake_pool);
+        Ok(())
+    }
+
+    /// Remove a stake pool (no-op if absent)
+    pub fn remove(&mut self, stake_pool: &Pubkey) {
+        self.stake_pools.retain(|p| p != stake_pool);
+    }
+}
+
+/// Guardians allowed to take emergency action on a pool
+///
+/// One PDA per pool, managed by the authority. Guardians can only pause the
+/// pool and freeze verifying key updates, and only when at least
+/// `threshold` of them sign the same instruction. Once appointed, they must
+/// also sign their replacement, so the authority can't dismiss guardians to
+/// undo a freeze.
+#[account]
+pub struct GuardianSet {
+    /// Pool the guardians watch over
+    pub pool: Pubkey,
+
+    /// Guardian pubkeys (at most `MAX_GUARDIANS`, no duplicates)
+    pub guardians: Vec<Pubkey>,
+
+    /// Number of guardian signatures required
+    pub threshold: u8,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl GuardianSet {
+    /// Account size, with room for `MAX_GUARDIANS` guardians
+    pub const SIZE: usize = 32 + 4 + 32 * MAX_GUARDIANS + 1 + 1;
+
+    /// Check a guardian list and threshold before storing them
+    pub fn validate(guardians: &[Pubkey], threshold: u8) -> Result<()> {
+        require!(
+            !guardians.is_empty() && guardians.len() <= MAX_GUARDIANS,
+            NyxError::InvalidGuardianSet
+        );
+        require!(
+            threshold > 0 && threshold as usize <= guardians.len(),
+            NyxError::InvalidGuardianSet
+        );
+        for (i, guardian) in guardians.iter().enumerate() {
+            require!(!guardians[..i].contains(guardian), NyxError::InvalidGuardianSet);
+        }
+        Ok(())
+    }
+
+    /// Fail unless the set may be replaced with `signers` signing
+    ///
+    /// The authority appoints the first guardians alone; later sets need
+    /// the current guardians' quorum.
+    pub fn require_replaceable(&self, signers: &[AccountInfo]) -> Result<()> {
+        if self.threshold == 0 {
+            return Ok(());
+        }
+        self.require_quorum(signers)
+    }
+
+    /// Fail unless at least `threshold` distinct guardians signed
+    pub fn require_quorum(&self, signers: &[AccountInfo]) -> Result<()> {
+        let mut signed = [false; MAX_GUARDIANS];
+        for signer in signers.iter().filter(|info| info.is_signer) {
+            if let Some(i) = self.guardians.iter().position(|g| g == signer.key) {
+                signed[i] = true;
+            }
+        }
+        let count = signed.iter().filter(|&&s| s).count();
+        require!(count >= self.threshold as usize, NyxError::GuardianQuorumNotMet);
+        Ok(())
+    }
+}
+
+/// Final state of a Merkle tree the pool rolled over from
+///
+/// One PDA per pool and epoch. Its root stays valid for spends forever,
+/// since the notes it commits to can't move to the new tree.
+#[account]
+pub struct TreeEpoch {
+    /// Pool the tree belonged to
+    pub pool: Pubkey,
+
+    /// Epoch index of the archived tree
+    pub epoch: u64,
+
+    /// Final root of the archived tree
+    pub root: [u8; 32],
+
+    /// Number of leaves in the archived tree
+    pub leaf_count: u64,
+
+    /// Slot at which the tree was archived
+    pub archived_at: u64,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl TreeEpoch {
+    /// Account size
+    pub const SIZE: usize = 32 + 8 + 32 + 8 + 8 + 1;
+}
+
+/// Where a shielded commitment landed in the pool's tree
+///
+/// Optional PDA per pool and commitment, created by the depositor when
+/// shielding. Recipients look their leaf up by commitment instead of
+/// scanning the history, and since the PDA can only be created once, a
+/// commitment that has a record can't be shielded again.
+#[account]
+pub struct CommitmentRecord {
+    /// Pool the commitment was inserted into
+    pub pool: Pubkey,
+
+    /// The commitment
+    pub commitment: [u8; 32],
+
+    /// Tree epoch the leaf belongs to
+    pub tree_epoch: u64,
+
+    /// Leaf index within that tree
+    pub leaf_index: u64,
+
+    /// Slot at which the commitment was inserted
+    pub slot: u64,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+impl CommitmentRecord {
+    /// Account size
+    pub const SIZE: usize = 32 + 32 + 8 + 8 + 8 + 1;
+}
+
+/// On-chain archive of a run of consecutive leaves
+///
+/// One PDA per pool, tree epoch and chunk index, holding leaves
+/// `[chunk_index * COMMITMENT_CHUNK_LEAVES, ..)` of that tree in insertion
+/// order. Wallets read these to rebuild Merkle paths without an indexer.
+#[account(zero_copy)]
+pub struct CommitmentChunk {
+    /// Pool the leaves belong to
+    pub pool: Pubkey,
+
+    /// Tree epoch the leaves belong to
+    pub tree_epoch: u64,
+
+    /// Leaf index of `leaves[0]`
+    pub start_index: u64,
+
+    /// Position of this chunk within its tree
+    pub chunk_index: u32,
+
+    /// Number of leaves written so far
+    pub count: u32,
+
+    /// Leaves in insertion order; entries past `count` are unset
+    pub leaves: [[u8; 32]; COMMITMENT_CHUNK_LEAVES],
+}
+
+impl CommitmentChunk {
+    /// Account size
+    pub const SIZE: usize = 32 + 8 + 8 + 4 + 4 + (32 * COMMITMENT_CHUNK_LEAVES);
+
+    /// Number of chunks that cover a full tree
+    pub const CHUNKS_PER_TREE: u32 =
+        (IncrementalMerkleTree::MAX_LEAVES / COMMITMENT_CHUNK_LEAVES as u64) as u32;
+
+    /// Chunk index holding a given leaf
+    pub fn index_of(leaf_index: u64) -> u32 {
+        (leaf_index / COMMITMENT_CHUNK_LEAVES as u64) as u32
+    }
+
+    /// Initialize an empty chunk
+    pub fn initialize(&mut self, pool: Pubkey, tree_epoch: u64, chunk_index: u32) {
+        self.pool = pool;
+        self.tree_epoch = tree_epoch;
+        self.start_index = chunk_index as u64 * COMMITMENT_CHUNK_LEAVES as u64;
+        self.chunk_index = chunk_index;
+        self.count = 0;
+    }
+
+    /// Check whether this chunk is where the given leaf must be written
+    pub fn covers(&self, pool: &Pubkey, tree_epoch: u64, leaf_index: u64) -> bool {
+        self.pool == *pool
+            && self.tree_epoch == tree_epoch
+            && self.chunk_index == Self::index_of(leaf_index)
+    }
+
+    /// Append the next leaf of the tree
+    ///
+    /// Leaves must arrive in order, which holds as long as every
+    /// instruction inserting into the tree also writes its chunk.
+    pub fn append(&mut self, leaf_index: u64, commitment: [u8; 32]) -> Result<()> {
+        require!(
+            leaf_index == self.start_index + self.count as u64,
+            NyxError::InvalidCommitmentChunk
+        );
+        self.leaves[self.count as usize] = commitment;
+        self.count += 1;
+        Ok(())
+    }
+}
+
+/// Shard of a pool's set of spent nullifiers
+///
+/// One PDA per pool and shard, holding every spent nullifier whose last
+/// byte is the shard index. The nullifiers follow the struct in the account
+/// data (`capacity` slots, the first `count` used), and the account grows
+/// by `NULLIFIER_SET_GROWTH` slots whenever it fills up, up to
+/// `NULLIFIER_SET_MAX_CAPACITY`. The bitmap is an exact-match pre-filter: a
+/// clear bit means the nullifier is not in the shard, a set bit means the
+/// slots have to be searched. Slots are kept sorted, so a search is a
+/// binary search.
+#[account(zero_copy)]
+pub struct NullifierSet {
+    /// Pool this nullifier set belongs to
+    pub pool: Pubkey,
+
+    /// Filter bitmap (one bit per `filter_bit` value)
+    pub bitmap: [u8; 1024],
+
+    /// Number of nullifiers stored
+    pub count: u32,
+
+    /// Number of nullifier slots the account has room for
+    pub capacity: u32,
+
+    /// Shard index (last byte of every nullifier in the shard)
+    pub shard: u8,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+
+    /// Whether the slots are sorted; shards created before lookups were
+    /// indexed hold them in insertion order until their next insert
+    pub sorted: u8,
+
+    /// Keeps the struct size a multiple of its alignment
+    pub _padding: [u8; 1],
+}
+
+impl NullifierSet {
+    /// Account size, without the nullifier slots
+    pub const SIZE: usize = 32 + 1024 + 4 + 4 + 1 + 1 + 1 + 1;
+
+    /// Account size, with discriminator, for `capacity` nullifiers
+    pub fn space(capacity: u32) -> usize {
+        8 + Self::SIZE + 32 * capacity as usize
+    }
+
+    /// Shard a nullifier belongs to
+    ///
+    /// Nullifiers are big-endian field elements, so the last byte is the
+    /// uniformly distributed one.
+    pub fn shard_of(nullifier: &[u8; 32]) -> u8 {
+        nullifier[31]
+    }
+
+    /// Bitmap position of a nullifier
+    pub fn filter_bit(nullifier: &[u8; 32]) -> usize {
+        u16::from_be_bytes([nullifier[29], nullifier[30]]) as usize % (8 * 1024)
+    }
+
+    /// Initialize an empty shard
+    pub fn initialize(&mut self, pool: Pubkey, shard: u8, bump: u8, capacity: u32) {
+        self.pool = pool;
+        self.bitmap = [0u8; 1024];
+        self.count = 0;
+        self.capacity = capacity;
+        self.shard = shard;
+        self.bump = bump;
+        self.sorted = 1;
+    }
+
+    /// Check if the shard holds a nullifier
+    pub fn contains(&self, nullifiers: &[[u8; 32]], nullifier: &[u8; 32]) -> bool {
+        let bit = Self::filter_bit(nullifier);
+        if self.bitmap[bit / 8] & (1 << (bit % 8)) == 0 {
+            return false;
+        }
+        let nullifiers = &nullifiers[..self.count as usize];
+        if self.sorted != 0 {
+            nullifiers.binary_search(nullifier).is_ok()
+        } else {
+            nullifiers.contains(nullifier)
+        }
+    }
+
+    /// Whether every nullifier slot is used
+    pub fn is_full(&self) -> bool {
+        self.count >= self.capacity
+    }
+
+    /// Add a nullifier to the shard, at its sorted position
+    pub fn insert(&mut self, nullifiers: &mut [[u8; 32]], nullifier: [u8; 32]) -> Result<()> {
+        require!(!self.is_full(), NyxError::NullifierSetFull);
+        let count = self.count as usize;
+        if self.sorted == 0 {
+            nullifiers[..count].sort_unstable();
+            self.sorted = 1;
+        }
+        let bit = Self::filter_bit(&nullifier);
+        self.bitmap[bit / 8] |= 1 << (bit % 8);
+        let index = nullifiers[..count].binary_search(&nullifier).unwrap_or_else(|index| index);
+        nullifiers.copy_within(index..count, index + 1);
+        nullifiers[index] = nullifier;
+        self.count += 1;
+        Ok(())
+    }
+}
+
+const _: () = assert!(NullifierSet::SIZE == std::mem::size_of::<NullifierSet>());
+
+/// Bloom filter of a pool's spent nullifiers
+///
+/// Lets wallets pre-check their notes with a single account read: a clear
+/// bit at any of a nullifier's `bit_positions` means it is unspent, all set
+/// means it is probably spent and the nullifier set has to be checked. The
+/// filter only covers nullifiers spent after it was created and is never
+/// consulted on-chain.
+///
+/// It is never cleared, since a clear bit must keep meaning unspent, so it
+/// fills up: past `NULLIFIER_BLOOM_CAPACITY` it stops saving wallets
+/// lookups. Spends update it only when they pass it, so they can drop it
+/// once it is saturated.
+#[account(zero_copy)]
+pub struct NullifierBloom {
+    /// Pool this filter belongs to
+    pub pool: Pubkey,
+
+    /// Filter bits
+    pub bits: [u8; NULLIFIER_BLOOM_BYTES],
+
+    /// Number of nullifiers inserted
+    pub count: u64,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+
+    /// Keeps the struct size a multiple of its alignment
+    pub _padding: [u8; 7],
+}
+
+impl NullifierBloom {
+    pub const SIZE: usize = 32 + NULLIFIER_BLOOM_BYTES + 8 + 1 + 7;
+
+    /// Filter positions of a nullifier
+    ///
+    /// Three big-endian `u16`s from the nullifier's low bytes (bytes 25-30),
+    /// each reduced modulo the filter's bit count.
+    pub fn bit_positions(nullifier: &[u8; 32]) -> [usize; 3] {
+        let bits = 8 * NULLIFIER_BLOOM_BYTES;
+        [25, 27, 29].map(|i| u16::from_be_bytes([nullifier[i], nullifier[i + 1]]) as usize % bits)
+    }
+
+    /// Initialize an empty filter
+    pub fn initialize(&mut self, pool: Pubkey, bump: u8) {
+        self.pool = pool;
+        self.bits = [0u8; NULLIFIER_BLOOM_BYTES];
+        self.count = 0;
+        self.bump = bump;
+    }
+
+    /// Whether a nullifier may have been inserted (false positives possible)
+    pub fn may_contain(&self, nullifier: &[u8; 32]) -> bool {
+        Self::bit_positions(nullifier)
+            .iter()
+            .all(|bit| self.bits[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    /// Whether the filter holds too many nullifiers to be worth checking
+    pub fn is_saturated(&self) -> bool {
+        self.count >= NULLIFIER_BLOOM_CAPACITY
+    }
+
+    /// Add a nullifier to the filter
+    pub fn insert(&mut self, nullifier: &[u8; 32]) {
+        for bit in Self::bit_positions(nullifier) {
+            self.bits[bit / 8] |= 1 << (bit % 8);
+        }
+        self.count += 1;
+    }
+}
+
+const _: () = assert!(NullifierBloom::SIZE == std::mem::size_of::<NullifierBloom>());
+
+/// Pool activity during one Solana epoch
+#[zero_copy]
+#[derive(Debug, Default)]
+pub struct StatsBucket {
+    /// Epoch the bucket covers (`u64::MAX` while unused)
+    pub epoch: u64,
+
+    /// Number of deposits
+    pub deposits: u64,
+
+    /// Number of withdrawals
+    pub withdrawals: u64,
+
+    /// Amount shielded
+    pub shielded: u64,
+
+    /// Amount unshielded, fees included
+    pub unshielded: u64,
+
+    /// Relayer and treasury fees paid
+    pub fees: u64,
+
+    /// Anonymity set size (pool deposit count) when the epoch started
+    pub first_anonymity_set: u64,
+
+    /// Anonymity set size at the bucket's last update
+    pub last_anonymity_set: u64,
+}
+
+impl StatsBucket {
+    pub const SIZE: usize = 8 * 8;
+}
+
+/// Time-bucketed activity of a pool
+///
+/// Optional PDA per pool, updated by shields and unshields that pass it.
+/// Keeps the last `POOL_STATS_BUCKETS` epochs in a ring indexed by epoch,
+/// so wallets can show how much the anonymity set grew recently.
+#[account(zero_copy)]
+pub struct PoolStats {
+    /// Pool these stats belong to
+    pub pool: Pubkey,
+
+    /// Per-epoch buckets, at index `epoch % POOL_STATS_BUCKETS`
+    pub buckets: [StatsBucket; POOL_STATS_BUCKETS],
+
+    /// Anonymity set size at the last update
+    pub anonymity_set: u64,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+
+    /// Keeps the struct size a multiple of its alignment
+    pub _padding: [u8; 7],
+}
+
+impl PoolStats {
+    pub const SIZE: usize = 32 + StatsBucket::SIZE * POOL_STATS_BUCKETS + 8 + 1 + 7;
+
+    /// Initialize empty stats, starting from the pool's current deposit count
+    pub fn initialize(&mut self, pool: Pubkey, anonymity_set: u64, bump: u8) {
+        self.pool = pool;
+        let unused = StatsBucket { epoch: u64::MAX, ..StatsBucket::default() };
+        self.buckets = [unused; POOL_STATS_BUCKETS];
+        self.anonymity_set = anonymity_set;
+        self.bump = bump;
+    }
+
+    /// Bucket for `epoch`, reset first if it still holds an older epoch
+    fn bucket_mut(&mut self, epoch: u64) -> &mut StatsBucket {
+        let anonymity_set = self.anonymity_set;
+        let bucket = &mut self.buckets[(epoch % POOL_STATS_BUCKETS as u64) as usize];
+        if bucket.epoch != epoch {
+            *bucket = StatsBucket {
+                epoch,
+                first_anonymity_set: anonymity_set,
+                last_anonymity_set: anonymity_set,
+                ..StatsBucket::default()
+            };
+        }
+        bucket
+    }
+
+    /// Record `count` deposits totalling `amount`
+    pub fn record_deposits(&mut self, epoch: u64, count: u64, amount: u64, anonymity_set: u64) {
+        let bucket = self.bucket_mut(epoch);
+        bucket.deposits = bucket.deposits.saturating_add(count);
+        bucket.shielded = bucket.shielded.saturating_add(amount);
+        bucket.last_anonymity_set = anonymity_set;
+        self.anonymity_set = anonymity_set;
+    }
+
+    /// Record `count` withdrawals totalling `amount`, of which `fees` went to fees
+    pub fn record_withdrawals(&mut self, epoch: u64, count: u64, amount: u64, fees: u64) {
+        let bucket = self.bucket_mut(epoch);
+        bucket.withdrawals = bucket.withdrawals.saturating_add(count);
+        bucket.unshielded = bucket.unshielded.saturating_add(amount);
+        bucket.fees = bucket.fees.saturating_add(fees);
+    }
+}
+
+const _: () = assert!(PoolStats::SIZE == std::mem::size_of::<PoolStats>());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> Box<PrivacyPool> {
+        Box::new(bytemuck::Zeroable::zeroed())
+    }
+
+    fn protocol_config(treasury_fee_share_bps: u16) -> ProtocolConfig {
+        ProtocolConfig {
+            admin: Pubkey::new_unique(),
+            vk_authority: Pubkey::new_unique(),
+            default_relayer_fee_bps: DEFAULT_RELAYER_FEE_BPS,
+            treasury_fee_share_bps,
+            paused: false,
+            bump: 255,
+            paused_at: 0,
+            pending_admin: Pubkey::default(),
+        }
+    }
+
+    #[test]
+    fn test_relayer_fee_rate() {
+        let mut pool = pool();
+        pool.relayer_fee_bps = DEFAULT_RELAYER_FEE_BPS;
+        assert_eq!(pool.calculate_relayer_fee(1_000_000), 3_000);
+        // Rounds down, so tiny withdrawals pay no fee
+        assert_eq!(pool.calculate_relayer_fee(333), 0);
+
+        pool.relayer_fee_bps = MAX_RELAYER_FEE_BPS;
+        assert_eq!(pool.calculate_relayer_fee(u64::MAX), u64::MAX / 20);
+    }
+
+    #[test]
+    fn test_relayer_fee_is_split_with_the_treasury() {
+        let config = protocol_config(TREASURY_FEE_SHARE_BPS);
+        assert_eq!(config.split_relayer_fee(3_000), (2_700, 300));
+        // The treasury's share rounds down, the relayer keeps the rest
+        assert_eq!(config.split_relayer_fee(9), (9, 0));
+        assert_eq!(protocol_config(0).split_relayer_fee(3_000), (3_000, 0));
+        assert_eq!(protocol_config(10_000).split_relayer_fee(3_000), (0, 3_000));
+
+        assert!(config.validate().is_ok());
+        let err = protocol_config(10_001).validate().unwrap_err();
+        assert_eq!(err, NyxError::InvalidProtocolConfig.into());
+        let config = ProtocolConfig { default_relayer_fee_bps: MAX_RELAYER_FEE_BPS + 1, ..config };
+        assert_eq!(config.validate().unwrap_err(), NyxError::InvalidRelayerFee.into());
+    }
+
+    #[test]
+    fn test_authority_changes_hands_only_when_accepted() {
+        let (authority, proposed) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut pool = pool();
+        pool.initialize(authority, 255, 0, DEFAULT_ROOT_HISTORY_SIZE, Pubkey::default(), 9);
+
+        // Nobody can take over a pool without a proposal
+        let err = pool.accept_authority(&proposed).unwrap_err();
+        assert_eq!(err, NyxError::NotPendingAuthority.into());
+
+        pool.pending_authority = proposed;
+        let err = pool.accept_authority(&Pubkey::new_unique()).unwrap_err();
+        assert_eq!(err, NyxError::NotPendingAuthority.into());
+        assert_eq!(pool.authority, authority);
+
+        assert_eq!(pool.accept_authority(&proposed).unwrap(), authority);
+        assert_eq!(pool.authority, proposed);
+        assert_eq!(pool.pending_authority, Pubkey::default());
+        assert!(pool.accept_authority(&proposed).is_err());
+    }
+
+    #[test]
+    fn test_pause_can_leave_unshields_open() {
+        let mut pool = pool();
+        assert!(pool.require_not_paused().is_ok());
+
+        pool.pause(true, 100);
+        assert_eq!(pool.require_not_paused().unwrap_err(), NyxError::PoolPaused.into());
+        assert!(pool.require_unshields_allowed().is_ok());
+
+        pool.pause(false, 200);
+        assert_eq!(pool.require_unshields_allowed().unwrap_err(), NyxError::PoolPaused.into());
+        assert_eq!(pool.unshields_paused_at, 200);
+        // Pausing again doesn't push back the emergency withdrawal delay
+        pool.pause(false, 300);
+        assert_eq!(pool.unshields_paused_at, 200);
+
+        pool.unpause();
+        assert!(pool.require_not_paused().is_ok());
+        assert!(pool.require_unshields_allowed().is_ok());
+        assert_eq!(pool.unshields_paused_at, 0);
+    }
+
+    #[test]
+    fn test_value_locked_tracks_shields_and_unshields() {
+        let mut pool = pool();
+        pool.record_shielded(1_000).unwrap();
+        pool.record_unshielded(400).unwrap();
+        assert_eq!(pool.total_value_locked(), 600);
+
+        // The pool never pays out more than was shielded
+        let err = pool.record_unshielded(601).unwrap_err();
+        assert_eq!(err, NyxError::PoolInsolvent.into());
+        assert_eq!(pool.total_unshielded, 400);
+        let err = pool.record_shielded(u64::MAX).unwrap_err();
+        assert_eq!(err, NyxError::AccountingOverflow.into());
+        assert_eq!(pool.total_shielded, 1_000);
+    }
+
+    #[test]
+    fn test_solvency_checks_the_vault_against_value_locked() {
+        let mut pool = pool();
+        pool.record_shielded(1_000).unwrap();
+        pool.record_unshielded(400).unwrap();
+        assert!(pool.check_solvency(600).is_ok());
+        assert!(pool.check_solvency(700).is_ok());
+        assert_eq!(pool.check_solvency(599).unwrap_err(), NyxError::PoolInsolvent.into());
+    }
+
+    #[test]
+    fn test_pool_stats_bucket_activity_by_epoch() {
+        let mut stats: Box<PoolStats> = Box::new(bytemuck::Zeroable::zeroed());
+        stats.initialize(Pubkey::new_unique(), 10, 255);
+        stats.record_deposits(3, 2, 500, 12);
+        stats.record_withdrawals(3, 1, 200, 6);
+        stats.record_deposits(3, 1, 100, 13);
+
+        let bucket = stats.buckets[3];
+        assert_eq!((bucket.epoch, bucket.deposits, bucket.shielded), (3, 3, 600));
+        assert_eq!((bucket.withdrawals, bucket.unshielded, bucket.fees), (1, 200, 6));
+        // The anonymity set grew from 10 to 13 over the epoch
+        assert_eq!((bucket.first_anonymity_set, bucket.last_anonymity_set), (10, 13));
+        assert_eq!(stats.anonymity_set, 13);
+    }
+
+    #[test]
+    fn test_pool_stats_reuse_buckets_of_old_epochs() {
+        let mut stats: Box<PoolStats> = Box::new(bytemuck::Zeroable::zeroed());
+        stats.initialize(Pubkey::new_unique(), 0, 255);
+        stats.record_deposits(1, 5, 500, 5);
+
+        let epoch = 1 + POOL_STATS_BUCKETS as u64;
+        stats.record_withdrawals(epoch, 1, 100, 1);
+        let bucket = stats.buckets[1];
+        assert_eq!((bucket.epoch, bucket.deposits, bucket.withdrawals), (epoch, 0, 1));
+        assert_eq!((bucket.first_anonymity_set, bucket.last_anonymity_set), (5, 5));
+        // Buckets no epoch reached yet are marked unused
+        assert_eq!(stats.buckets[0].epoch, u64::MAX);
+    }
+
+    #[test]
+    fn test_unshields_wait_for_the_anonymity_set() {
+        let mut pool = pool();
+        // Without a minimum, the first deposit can be withdrawn
+        pool.record_deposit();
+        assert!(pool.require_anonymity_set().is_ok());
+
+        pool.min_anonymity_set = 3;
+        for _ in 0..2 {
+            let err = pool.require_anonymity_set().unwrap_err();
+            assert_eq!(err, NyxError::AnonymitySetTooSmall.into());
+            pool.record_deposit();
+        }
+        // Withdrawals open once deposits exceed the minimum, not reach it
+        assert_eq!(pool.deposit_count, 3);
+        assert!(pool.require_anonymity_set().is_err());
+        pool.record_deposit();
+        assert!(pool.require_anonymity_set().is_ok());
+    }
+
+    #[test]
+    fn test_deposit_cap_bounds_the_vault_balance() {
+        let mut pool = pool();
+        // No cap by default
+        assert!(pool.check_deposit_cap(u64::MAX, u64::MAX).is_ok());
+
+        pool.deposit_cap = 1_000;
+        assert!(pool.check_deposit_cap(600, 400).is_ok());
+        let err = pool.check_deposit_cap(600, 401).unwrap_err();
+        assert_eq!(err, NyxError::DepositCapExceeded.into());
+        assert!(pool.check_deposit_cap(u64::MAX, 1).is_err());
+    }
+
+    #[test]
+    fn test_mint_allowlist() {
+        let mut allowlist = MintAllowlist { mints: Vec::new(), bump: 255 };
+        let mint = Pubkey::new_unique();
+        let err = allowlist.require_allowed(&mint).unwrap_err();
+        assert_eq!(err, NyxError::MintNotAllowlisted.into());
+
+        allowlist.add(mint).unwrap();
+        allowlist.add(mint).unwrap();
+        assert_eq!(allowlist.mints, [mint]);
+        assert!(allowlist.require_allowed(&mint).is_ok());
+
+        for _ in 1..MAX_ALLOWLISTED_MINTS {
+            allowlist.add(Pubkey::new_unique()).unwrap();
+        }
+        let err = allowlist.add(Pubkey::new_unique()).unwrap_err();
+        assert_eq!(err, NyxError::MintAllowlistFull.into());
+        // Mints already listed still go through on a full list
+        assert!(allowlist.add(mint).is_ok());
+
+        allowlist.remove(&mint);
+        assert!(allowlist.require_allowed(&mint).is_err());
+        assert!(allowlist.add(mint).is_ok());
+    }
 }
@@ -0,0 +1,126 @@
+//! Payment Streams
+//!
+//! `create_payment_stream` spends a SOL note into a `PaymentStream` PDA
+//! whose seeds bind the stream's terms, and `execute_stream_payment` pays
+//! it out one period at a time until nothing is left.
+
+use anchor_lang::prelude::*;
+
+use crate::instructions::{NyxError, PaymentStreamTerms};
+use crate::state::{PaymentStream, PAYMENT_STREAM_SEED};
+
+/// Address of the stream of `pool` with the given terms
+pub fn payment_stream_address(pool: &Pubkey, terms: &PaymentStreamTerms) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            PAYMENT_STREAM_SEED,
+            pool.as_ref(),
+            terms.recipient.as_ref(),
+            terms.refund_to.as_ref(),
+            &terms.amount_per_period.to_le_bytes(),
+            &terms.period_slots.to_le_bytes(),
+            terms.stream_id.as_ref(),
+        ],
+        &crate::ID,
+    )
+}
+
+/// Take the payment due at `slot` off the stream, returning its amount
+///
+/// The next payment falls due one period after this one was, not after
+/// `slot`, so a late crank can catch up on missed periods.
+pub fn pay_period(payment_stream: &mut PaymentStream, slot: u64) -> Result<u64> {
+    require!(payment_stream.is_due(slot), NyxError::StreamPaymentNotDue);
+
+    let payment = payment_stream.next_payment();
+    payment_stream.remaining -= payment;
+    payment_stream.next_payment_slot = payment_stream
+        .next_payment_slot
+        .checked_add(payment_stream.period_slots)
+        .ok_or(NyxError::AccountingOverflow)?;
+    Ok(payment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms() -> PaymentStreamTerms {
+        PaymentStreamTerms {
+            stream_id: [1u8; 32],
+            recipient: Pubkey::new_unique(),
+            refund_to: Pubkey::new_unique(),
+            amount_per_period: 400,
+            period_slots: 100,
+        }
+    }
+
+    fn stream(remaining: u64, next_payment_slot: u64) -> PaymentStream {
+        let terms = terms();
+        PaymentStream {
+            pool: Pubkey::new_unique(),
+            stream_id: terms.stream_id,
+            recipient: terms.recipient,
+            refund_to: terms.refund_to,
+            amount_per_period: terms.amount_per_period,
+            period_slots: terms.period_slots,
+            next_payment_slot,
+            remaining,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn test_stream_address_binds_every_term() {
+        let (pool, terms) = (Pubkey::new_unique(), terms());
+        let (address, _) = payment_stream_address(&pool, &terms);
+        assert_ne!(payment_stream_address(&Pubkey::new_unique(), &terms).0, address);
+
+        let changed = [
+            PaymentStreamTerms { stream_id: [2u8; 32], ..terms.clone() },
+            PaymentStreamTerms { recipient: Pubkey::new_unique(), ..terms.clone() },
+            PaymentStreamTerms { refund_to: Pubkey::new_unique(), ..terms.clone() },
+            PaymentStreamTerms { amount_per_period: 401, ..terms.clone() },
+            PaymentStreamTerms { period_slots: 101, ..terms.clone() },
+        ];
+        for terms in changed {
+            assert_ne!(payment_stream_address(&pool, &terms).0, address);
+        }
+    }
+
+    #[test]
+    fn test_payment_is_due_from_its_slot_while_funds_remain() {
+        let stream = stream(1_000, 50);
+        assert!(!stream.is_due(49));
+        assert!(stream.is_due(50));
+        assert!(stream.is_due(500));
+
+        let empty = PaymentStream { remaining: 0, ..stream };
+        assert!(!empty.is_due(500));
+    }
+
+    #[test]
+    fn test_next_payment_is_capped_by_the_remaining_balance() {
+        assert_eq!(stream(1_000, 0).next_payment(), 400);
+        assert_eq!(stream(400, 0).next_payment(), 400);
+        assert_eq!(stream(150, 0).next_payment(), 150);
+    }
+
+    #[test]
+    fn test_stream_pays_one_period_per_call() {
+        let mut stream = stream(1_000, 50);
+        assert_eq!(pay_period(&mut stream, 50).unwrap(), 400);
+        assert_eq!((stream.remaining, stream.next_payment_slot), (600, 150));
+
+        // Not due again until the next period starts
+        let err = pay_period(&mut stream, 149).unwrap_err();
+        assert_eq!(err, NyxError::StreamPaymentNotDue.into());
+
+        // A late crank catches up on the missed period, then pays the rest
+        assert_eq!(pay_period(&mut stream, 400).unwrap(), 400);
+        assert_eq!(pay_period(&mut stream, 400).unwrap(), 200);
+        assert_eq!((stream.remaining, stream.next_payment_slot), (0, 350));
+        let err = pay_period(&mut stream, 400).unwrap_err();
+        assert_eq!(err, NyxError::StreamPaymentNotDue.into());
+    }
+}
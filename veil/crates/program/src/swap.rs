@@ -0,0 +1,177 @@
+//! Shielded Swaps
+//!
+//! `shielded_swap` moves value from one pool to another through a DEX in a
+//! single transaction, so the tokens never sit in a wallet that could link
+//! the spent note to the new one. The input pool pays the note out to a
+//! transient token account of the swap authority, the program invokes the
+//! caller's route signing as that authority, and the route's output is
+//! shielded into the output pool under the caller's new commitment.
+//!
+//! The swap authority is a PDA of the output pool, the new commitment and
+//! the minimum output. The unshield proof binds it as the recipient, so a
+//! relayer can neither redirect the output nor loosen the slippage bound.
+//! The route itself is caller-provided; only its program is checked, against
+//! the supported DEXes.
+//!
+//! The new note opens to exactly the minimum output, so any surplus can't
+//! be shielded with it. The surplus goes to the output pool's treasury
+//! rather than the relayer, whose only pay is the proven fee: set the
+//! minimum close to the route's quote.
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program::invoke_signed;
+
+use crate::instructions::NyxError;
+
+/// Jupiter aggregator v6 program
+pub const JUPITER_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
+
+/// Orca Whirlpool program
+pub const ORCA_WHIRLPOOL_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
+
+/// Programs a swap route may invoke
+pub const SUPPORTED_DEX_PROGRAMS: [Pubkey; 2] = [JUPITER_PROGRAM_ID, ORCA_WHIRLPOOL_PROGRAM_ID];
+
+/// Seeds prefix for the PDA that holds tokens during a swap
+pub const SWAP_AUTHORITY_SEED: &[u8] = b"swap_authority";
+
+/// Largest route instruction data accepted
+pub const MAX_ROUTE_DATA_LEN: usize = 512;
+
+/// Whether `program_id` is a DEX a swap route may invoke
+pub fn is_supported_dex(program_id: &Pubkey) -> bool {
+    SUPPORTED_DEX_PROGRAMS.contains(program_id)
+}
+
+/// Derive the swap authority for shielding at least `min_amount_out` into
+/// `output_pool` under `commitment`
+pub fn derive_swap_authority(
+    program_id: &Pubkey,
+    output_pool: &Pubkey,
+    commitment: &[u8; 32],
+    min_amount_out: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            SWAP_AUTHORITY_SEED,
+            output_pool.as_ref(),
+            commitment.as_ref(),
+            &min_amount_out.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Split a route's output into the amount shielded and the surplus
+pub fn split_output(amount_out: u64, min_amount_out: u64) -> Result<(u64, u64)> {
+    let surplus = amount_out
+        .checked_sub(min_amount_out)
+        .ok_or(NyxError::SwapSlippageExceeded)?;
+    Ok((min_amount_out, surplus))
+}
+
+/// Build the route instruction
+///
+/// Accounts keep the writability they were passed with. The swap authority
+/// is marked as a signer wherever it appears; any other signer must have
+/// signed the transaction.
+pub fn route_instruction(
+    dex_program: &Pubkey,
+    route_accounts: &[AccountInfo<'_>],
+    swap_authority: &Pubkey,
+    data: Vec<u8>,
+) -> Instruction {
+    let accounts = route_accounts
+        .iter()
+        .map(|info| AccountMeta {
+            pubkey: info.key(),
+            is_signer: info.is_signer || info.key == swap_authority,
+            is_writable: info.is_writable,
+        })
+        .collect();
+    Instruction {
+        program_id: *dex_program,
+        accounts,
+        data,
+    }
+}
+
+/// Invoke a route through a supported DEX, signing as the swap authority
+pub fn invoke_route<'info>(
+    dex_program: &AccountInfo<'info>,
+    route_accounts: &[AccountInfo<'info>],
+    swap_authority: &Pubkey,
+    data: Vec<u8>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    require!(is_supported_dex(dex_program.key), NyxError::UnsupportedSwapProgram);
+    require!(data.len() <= MAX_ROUTE_DATA_LEN, NyxError::InvalidSwapRoute);
+
+    let instruction = route_instruction(dex_program.key, route_accounts, swap_authority, data);
+    let mut infos = route_accounts.to_vec();
+    infos.push(dex_program.clone());
+    invoke_signed(&instruction, &infos, signer_seeds)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supported_dexes() {
+        assert!(is_supported_dex(&JUPITER_PROGRAM_ID));
+        assert!(is_supported_dex(&ORCA_WHIRLPOOL_PROGRAM_ID));
+        assert!(!is_supported_dex(&crate::ID));
+        assert!(!is_supported_dex(&Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn test_swap_authority_binds_commitment_and_min_output() {
+        let pool = Pubkey::new_unique();
+        let (authority, _) = derive_swap_authority(&crate::ID, &pool, &[1u8; 32], 100);
+        assert_ne!(authority, derive_swap_authority(&crate::ID, &pool, &[2u8; 32], 100).0);
+        assert_ne!(authority, derive_swap_authority(&crate::ID, &pool, &[1u8; 32], 99).0);
+        assert_ne!(
+            authority,
+            derive_swap_authority(&crate::ID, &Pubkey::new_unique(), &[1u8; 32], 100).0
+        );
+    }
+
+    #[test]
+    fn test_output_split() {
+        assert_eq!(split_output(1_000, 1_000).unwrap(), (1_000, 0));
+        assert_eq!(split_output(1_250, 1_000).unwrap(), (1_000, 250));
+        let err = split_output(999, 1_000).unwrap_err();
+        assert_eq!(err, NyxError::SwapSlippageExceeded.into());
+    }
+
+    #[test]
+    fn test_route_instruction_signs_as_swap_authority() {
+        let owner = Pubkey::new_unique();
+        let (authority_key, pool_key, relayer_key) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut lamports, mut data) = ([0u64; 3], [[0u8; 0]; 3]);
+        let [l0, l1, l2] = &mut lamports;
+        let [d0, d1, d2] = &mut data;
+        let accounts = [
+            AccountInfo::new(&authority_key, false, false, l0, d0, &owner, false, 0),
+            AccountInfo::new(&pool_key, false, true, l1, d1, &owner, false, 0),
+            AccountInfo::new(&relayer_key, true, true, l2, d2, &owner, false, 0),
+        ];
+
+        let instruction =
+            route_instruction(&JUPITER_PROGRAM_ID, &accounts, &authority_key, vec![1, 2, 3]);
+        assert_eq!(instruction.program_id, JUPITER_PROGRAM_ID);
+        assert_eq!(instruction.data, vec![1, 2, 3]);
+        let flags: Vec<(bool, bool)> = instruction
+            .accounts
+            .iter()
+            .map(|meta| (meta.is_signer, meta.is_writable))
+            .collect();
+        assert_eq!(flags, vec![(true, false), (false, true), (true, true)]);
+    }
+}
@@ -0,0 +1,332 @@
+//! Golden Test Vectors
+//!
+//! Deterministic commitments, nullifiers and Merkle roots produced by the
+//! withdraw circuit (`circuits/src/withdraw.circom`, circomlib Poseidon over
+//! BN254 with t = 3, x^5, 8 full and 57 partial rounds). Regenerate with
+//! `npm run test-vectors` in `circuits/`.
+//!
+//! Circuit derivations:
+//! - commitment = Poseidon(amount, secret)
+//! - nullifier_hash = Poseidon(commitment, secret)
+//! - node = Poseidon(left, right), empty leaf = 0
+//!
+//! All values are big-endian field elements, as passed to the verifier.
+//! Any hashing the program does on the same data must reproduce them
+//! exactly, or proofs for real notes will never verify.
+
+use crate::merkle::TREE_DEPTH;
+
+/// A note and the values the circuit derives from it
+#[derive(Clone, Copy, Debug)]
+pub struct NoteVector {
+    /// Note secret
+    pub secret: [u8; 32],
+    /// Note amount
+    pub amount: u64,
+    /// Poseidon(amount, secret)
+    pub commitment: [u8; 32],
+    /// Poseidon(commitment, secret)
+    pub nullifier_hash: [u8; 32],
+}
+
+/// Poseidon(left, right) for selected inputs: (left, right, hash)
+pub const HASH_PAIRS: [([u8; 32], [u8; 32], [u8; 32]); 3] = [
+    (
+        [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        ],
+        [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        ],
+        [
+            0x11, 0x5c, 0xc0, 0xf5, 0xe7, 0xd6, 0x90, 0x41,
+            0x3d, 0xf6, 0x4c, 0x6b, 0x96, 0x62, 0xe9, 0xcf,
+            0x2a, 0x36, 0x17, 0xf2, 0x74, 0x32, 0x45, 0x51,
+            0x9e, 0x19, 0x60, 0x7a, 0x44, 0x17, 0x18, 0x9a,
+        ],
+    ),
+    (
+        [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ],
+        [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ],
+        [
+            0x20, 0x98, 0xf5, 0xfb, 0x9e, 0x23, 0x9e, 0xab,
+            0x3c, 0xea, 0xc3, 0xf2, 0x7b, 0x81, 0xe4, 0x81,
+            0xdc, 0x31, 0x24, 0xd5, 0x5f, 0xfe, 0xd5, 0x23,
+            0xa8, 0x39, 0xee, 0x84, 0x46, 0xb6, 0x48, 0x64,
+        ],
+    ),
+    (
+        [
+            0x1b, 0xfb, 0x04, 0xfd, 0x05, 0xcc, 0x94, 0xa6,
+            0x14, 0xdb, 0x43, 0xa0, 0x99, 0x9b, 0x93, 0x7b,
+            0x8d, 0xbd, 0x52, 0x1c, 0xd0, 0x3b, 0x70, 0x8e,
+            0x09, 0xf8, 0xa2, 0xcf, 0xc3, 0xaf, 0xcd, 0x85,
+        ],
+        [
+            0x03, 0x83, 0x80, 0x5b, 0x9f, 0x2a, 0x4c, 0xf8,
+            0xc4, 0xdc, 0xfb, 0x92, 0x57, 0x72, 0x8b, 0xcc,
+            0x52, 0x6e, 0x25, 0x84, 0x81, 0x52, 0x51, 0x0b,
+            0xed, 0x22, 0x64, 0x8d, 0xaa, 0x73, 0x87, 0xb7,
+        ],
+        [
+            0x03, 0x2d, 0xbd, 0x9c, 0xcf, 0x1f, 0x19, 0xfb,
+            0x8a, 0xd8, 0xd2, 0x44, 0xb8, 0xda, 0xcf, 0xec,
+            0x5c, 0x7b, 0x5f, 0x9f, 0xf0, 0xd9, 0xfa, 0x53,
+            0xa4, 0xa8, 0x8f, 0xf2, 0xcf, 0x98, 0x35, 0xc2,
+        ],
+    ),
+];
+
+/// Notes inserted into the tree, in leaf order
+pub const NOTES: [NoteVector; 3] = [
+    NoteVector {
+        secret: [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x12, 0x34, 0x56, 0x78, 0x90, 0xab, 0xcd, 0xef,
+        ],
+        amount: 1_000_000_000,
+        commitment: [
+            0x1b, 0xfb, 0x04, 0xfd, 0x05, 0xcc, 0x94, 0xa6,
+            0x14, 0xdb, 0x43, 0xa0, 0x99, 0x9b, 0x93, 0x7b,
+            0x8d, 0xbd, 0x52, 0x1c, 0xd0, 0x3b, 0x70, 0x8e,
+            0x09, 0xf8, 0xa2, 0xcf, 0xc3, 0xaf, 0xcd, 0x85,
+        ],
+        nullifier_hash: [
+            0x2c, 0xf6, 0x56, 0x19, 0x06, 0x01, 0x72, 0x98,
+            0xc1, 0xbc, 0x2f, 0x23, 0x69, 0xcc, 0xbb, 0xcf,
+            0xad, 0x1b, 0xc4, 0xde, 0x3f, 0xf3, 0xee, 0xf0,
+            0x44, 0xd2, 0x70, 0x6d, 0x01, 0x8b, 0xdb, 0x3b,
+        ],
+    },
+    NoteVector {
+        secret: [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0xfe, 0xdc, 0xba, 0x09, 0x87, 0x65, 0x43, 0x21,
+        ],
+        amount: 100_000_000,
+        commitment: [
+            0x03, 0x83, 0x80, 0x5b, 0x9f, 0x2a, 0x4c, 0xf8,
+            0xc4, 0xdc, 0xfb, 0x92, 0x57, 0x72, 0x8b, 0xcc,
+            0x52, 0x6e, 0x25, 0x84, 0x81, 0x52, 0x51, 0x0b,
+            0xed, 0x22, 0x64, 0x8d, 0xaa, 0x73, 0x87, 0xb7,
+        ],
+        nullifier_hash: [
+            0x1b, 0xc6, 0x4d, 0x19, 0x33, 0xab, 0xbb, 0xdd,
+            0xe3, 0x7c, 0xa8, 0x9f, 0x39, 0x06, 0x6a, 0xf3,
+            0x3a, 0xca, 0x35, 0x05, 0x12, 0xef, 0x58, 0x9e,
+            0x3b, 0x07, 0xe1, 0x9e, 0x21, 0xa9, 0xcb, 0x2d,
+        ],
+    },
+    NoteVector {
+        secret: [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x0b, 0xad, 0xc0, 0xff, 0xee,
+        ],
+        amount: 10_000_000,
+        commitment: [
+            0x1e, 0xb1, 0xea, 0xef, 0x44, 0x19, 0xc0, 0xb0,
+            0x94, 0x14, 0xaa, 0xc3, 0x33, 0xc3, 0x36, 0x4f,
+            0xa7, 0x6f, 0x8a, 0xcf, 0xde, 0xbc, 0x07, 0x25,
+            0x7a, 0x4f, 0xea, 0x24, 0xd5, 0xb4, 0xa8, 0x20,
+        ],
+        nullifier_hash: [
+            0x0a, 0x29, 0x1e, 0x80, 0xea, 0x35, 0x65, 0x51,
+            0x8e, 0xac, 0x98, 0x3e, 0x07, 0xc9, 0x16, 0xbd,
+            0x56, 0xd6, 0xae, 0x1c, 0x15, 0xaa, 0xc9, 0x77,
+            0xbd, 0xc9, 0xec, 0x2f, 0x39, 0xc9, 0xb4, 0xaf,
+        ],
+    },
+];
+
+/// Empty subtree roots: zeros[0] = 0, zeros[i] = Poseidon(zeros[i-1], zeros[i-1])
+pub const ZERO_HASHES: [[u8; 32]; TREE_DEPTH + 1] = [
+    // Level 0
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ],
+    // Level 1
+    [
+        0x20, 0x98, 0xf5, 0xfb, 0x9e, 0x23, 0x9e, 0xab,
+        0x3c, 0xea, 0xc3, 0xf2, 0x7b, 0x81, 0xe4, 0x81,
+        0xdc, 0x31, 0x24, 0xd5, 0x5f, 0xfe, 0xd5, 0x23,
+        0xa8, 0x39, 0xee, 0x84, 0x46, 0xb6, 0x48, 0x64,
+    ],
+    // Level 2
+    [
+        0x10, 0x69, 0x67, 0x3d, 0xcd, 0xb1, 0x22, 0x63,
+        0xdf, 0x30, 0x1a, 0x6f, 0xf5, 0x84, 0xa7, 0xec,
+        0x26, 0x1a, 0x44, 0xcb, 0x9d, 0xc6, 0x8d, 0xf0,
+        0x67, 0xa4, 0x77, 0x44, 0x60, 0xb1, 0xf1, 0xe1,
+    ],
+    // Level 3
+    [
+        0x18, 0xf4, 0x33, 0x31, 0x53, 0x7e, 0xe2, 0xaf,
+        0x2e, 0x3d, 0x75, 0x8d, 0x50, 0xf7, 0x21, 0x06,
+        0x46, 0x7c, 0x6e, 0xea, 0x50, 0x37, 0x1d, 0xd5,
+        0x28, 0xd5, 0x7e, 0xb2, 0xb8, 0x56, 0xd2, 0x38,
+    ],
+    // Level 4
+    [
+        0x07, 0xf9, 0xd8, 0x37, 0xcb, 0x17, 0xb0, 0xd3,
+        0x63, 0x20, 0xff, 0xe9, 0x3b, 0xa5, 0x23, 0x45,
+        0xf1, 0xb7, 0x28, 0x57, 0x1a, 0x56, 0x82, 0x65,
+        0xca, 0xac, 0x97, 0x55, 0x9d, 0xbc, 0x95, 0x2a,
+    ],
+    // Level 5
+    [
+        0x2b, 0x94, 0xcf, 0x5e, 0x87, 0x46, 0xb3, 0xf5,
+        0xc9, 0x63, 0x1f, 0x4c, 0x5d, 0xf3, 0x29, 0x07,
+        0xa6, 0x99, 0xc5, 0x8c, 0x94, 0xb2, 0xad, 0x4d,
+        0x7b, 0x5c, 0xec, 0x16, 0x39, 0x18, 0x3f, 0x55,
+    ],
+    // Level 6
+    [
+        0x2d, 0xee, 0x93, 0xc5, 0xa6, 0x66, 0x45, 0x96,
+        0x46, 0xea, 0x7d, 0x22, 0xcc, 0xa9, 0xe1, 0xbc,
+        0xfe, 0xd7, 0x1e, 0x69, 0x51, 0xb9, 0x53, 0x61,
+        0x1d, 0x11, 0xdd, 0xa3, 0x2e, 0xa0, 0x9d, 0x78,
+    ],
+    // Level 7
+    [
+        0x07, 0x82, 0x95, 0xe5, 0xa2, 0x2b, 0x84, 0xe9,
+        0x82, 0xcf, 0x60, 0x1e, 0xb6, 0x39, 0x59, 0x7b,
+        0x8b, 0x05, 0x15, 0xa8, 0x8c, 0xb5, 0xac, 0x7f,
+        0xa8, 0xa4, 0xaa, 0xbe, 0x3c, 0x87, 0x34, 0x9d,
+    ],
+    // Level 8
+    [
+        0x2f, 0xa5, 0xe5, 0xf1, 0x8f, 0x60, 0x27, 0xa6,
+        0x50, 0x1b, 0xec, 0x86, 0x45, 0x64, 0x47, 0x2a,
+        0x61, 0x6b, 0x2e, 0x27, 0x4a, 0x41, 0x21, 0x1a,
+        0x44, 0x4c, 0xbe, 0x3a, 0x99, 0xf3, 0xcc, 0x61,
+    ],
+    // Level 9
+    [
+        0x0e, 0x88, 0x43, 0x76, 0xd0, 0xd8, 0xfd, 0x21,
+        0xec, 0xb7, 0x80, 0x38, 0x9e, 0x94, 0x1f, 0x66,
+        0xe4, 0x5e, 0x7a, 0xcc, 0xe3, 0xe2, 0x28, 0xab,
+        0x3e, 0x21, 0x56, 0xa6, 0x14, 0xfc, 0xd7, 0x47,
+    ],
+    // Level 10
+    [
+        0x1b, 0x72, 0x01, 0xda, 0x72, 0x49, 0x4f, 0x1e,
+        0x28, 0x71, 0x7a, 0xd1, 0xa5, 0x2e, 0xb4, 0x69,
+        0xf9, 0x58, 0x92, 0xf9, 0x57, 0x71, 0x35, 0x33,
+        0xde, 0x61, 0x75, 0xe5, 0xda, 0x19, 0x0a, 0xf2,
+    ],
+];
+
+/// Tree root after inserting `NOTES[..=i]`
+pub const ROOTS: [[u8; 32]; 3] = [
+    [
+        0x04, 0x85, 0x53, 0x17, 0x22, 0x63, 0x49, 0xa1,
+        0x66, 0xb9, 0x51, 0x91, 0xf6, 0x2a, 0x4e, 0x4b,
+        0x6a, 0x08, 0x92, 0x82, 0x9b, 0xd8, 0xe0, 0x05,
+        0x88, 0x01, 0x80, 0xbb, 0xc5, 0xfd, 0x5b, 0x18,
+    ],
+    [
+        0x27, 0x7d, 0x17, 0x68, 0x31, 0x8b, 0x6e, 0x77,
+        0xb8, 0xcc, 0xd2, 0x1c, 0xf2, 0xbd, 0x1c, 0xce,
+        0x6f, 0x32, 0xf2, 0xda, 0xd9, 0xdc, 0x7f, 0x6e,
+        0xe2, 0x31, 0x68, 0xdb, 0x60, 0x73, 0x90, 0xd1,
+    ],
+    [
+        0x18, 0xa0, 0x35, 0x41, 0xc5, 0xa2, 0xb5, 0x07,
+        0x22, 0x3e, 0xa1, 0x59, 0x9d, 0x9e, 0xee, 0xdd,
+        0x26, 0x8c, 0xac, 0x36, 0x1f, 0x11, 0xac, 0x14,
+        0x20, 0xc9, 0xb4, 0x0c, 0xe1, 0x0c, 0x4f, 0x53,
+    ],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::poseidon::{hashv, Endianness, Parameters};
+
+    use crate::groth16::u64_to_field_bytes;
+    use crate::merkle::{self, IncrementalMerkleTree};
+    use crate::verification::is_canonical_field_element;
+
+    fn poseidon(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        hashv(Parameters::Bn254X5, Endianness::BigEndian, &[left.as_slice(), right.as_slice()])
+            .unwrap()
+            .to_bytes()
+    }
+
+    #[test]
+    fn test_vectors_are_canonical() {
+        for note in NOTES {
+            assert!(is_canonical_field_element(&note.commitment));
+            assert!(is_canonical_field_element(&note.nullifier_hash));
+        }
+        for value in ZERO_HASHES.iter().chain(ROOTS.iter()) {
+            assert!(is_canonical_field_element(value));
+        }
+    }
+
+    #[test]
+    fn test_poseidon_syscall_matches_circuit() {
+        for (left, right, hash) in HASH_PAIRS {
+            assert_eq!(poseidon(&left, &right), hash);
+        }
+    }
+
+    #[test]
+    fn test_note_derivations_match_circuit() {
+        for note in NOTES {
+            let commitment = poseidon(&u64_to_field_bytes(note.amount), &note.secret);
+            assert_eq!(commitment, note.commitment);
+            assert_eq!(poseidon(&commitment, &note.secret), note.nullifier_hash);
+        }
+    }
+
+    #[test]
+    fn test_zero_hashes_match_circuit() {
+        for level in 1..=TREE_DEPTH {
+            assert_eq!(
+                poseidon(&ZERO_HASHES[level - 1], &ZERO_HASHES[level - 1]),
+                ZERO_HASHES[level]
+            );
+        }
+    }
+
+    #[test]
+    fn test_program_tree_matches_circuit() {
+        for (level, zero) in ZERO_HASHES.iter().enumerate() {
+            assert_eq!(merkle::get_zero_hash(level), *zero);
+        }
+
+        let mut tree = IncrementalMerkleTree::new();
+        assert_eq!(tree.root(), ZERO_HASHES[TREE_DEPTH]);
+
+        for (note, root) in NOTES.iter().zip(ROOTS.iter()) {
+            tree.insert(note.commitment).unwrap();
+            assert_eq!(tree.root(), *root);
+        }
+    }
+}
@@ -2,17 +2,49 @@
 //!
 //! Provides CPI (Cross-Program Invocation) helpers for:
 //! - Native SOL transfers (via System Program)
-//! - SPL Token transfers (via Token Program)
+//! - SPL Token transfers (via the Token or Token-2022 program)
 //!
 //! The pool uses PDAs as vault authorities, enabling trustless custody.
 
 use anchor_lang::prelude::*;
 use anchor_lang::system_program::{self, Transfer as SolTransfer};
-use anchor_spl::token::{self, Transfer as TokenTransfer, Token, TokenAccount};
+use anchor_spl::associated_token;
+use anchor_spl::token;
+use anchor_spl::token_2022::spl_token_2022::extension::{
+    transfer_hook::TransferHook, BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+};
+use anchor_spl::token_2022::spl_token_2022::state::Mint as MintState;
+use anchor_spl::token_interface::{
+    self, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+use crate::nullifier;
 
 /// Seeds for the pool vault PDA (controls pool's token accounts)
 pub const VAULT_SEED: &[u8] = b"vault";
 
+/// Wrapped SOL mint, whose deposits go to the native SOL pools
+pub const NATIVE_MINT: Pubkey = anchor_spl::token::spl_token::native_mint::ID;
+
+/// Seeds for the scratch token account a wSOL shield unwraps through
+pub const WSOL_UNWRAP_SEED: &[u8] = b"wsol_unwrap";
+
+/// Decimals of native SOL (lamports per SOL)
+pub const NATIVE_DECIMALS: u8 = 9;
+
+/// Mint recorded by multi-asset pools, which hold notes of every
+/// allowlisted mint
+///
+/// No mint account can exist at this address, so handlers that check a
+/// mint against `pool.mint` never match a multi-asset pool. It also keys
+/// the pool PDA like any other mint.
+pub const MULTI_ASSET_MINT: Pubkey = Pubkey::new_from_array([0xff; 32]);
+
+/// Mint recorded by the NFT pool, which holds one NFT per note
+///
+/// Like `MULTI_ASSET_MINT`, no mint account can exist at this address.
+pub const NFT_POOL_MINT: Pubkey = Pubkey::new_from_array([0xfe; 32]);
+
 /// Transfer native SOL from depositor to pool vault
 ///
 /// # Arguments
@@ -80,23 +112,26 @@ pub fn transfer_sol_from_pool<'info>(
 /// * `depositor_token_account` - Depositor's token account
 /// * `vault_token_account` - Pool's vault token account
 /// * `depositor` - Signer authority
-/// * `token_program` - SPL Token program
+/// * `mint` - The pool's mint
+/// * `token_program` - Token or Token-2022 program owning the mint
 /// * `amount` - Amount of tokens to transfer
 pub fn transfer_spl_to_pool<'info>(
-    depositor_token_account: &Account<'info, TokenAccount>,
-    vault_token_account: &Account<'info, TokenAccount>,
+    depositor_token_account: &InterfaceAccount<'info, TokenAccount>,
+    vault_token_account: &InterfaceAccount<'info, TokenAccount>,
     depositor: &Signer<'info>,
-    token_program: &Program<'info, Token>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
     amount: u64,
 ) -> Result<()> {
-    let cpi_accounts = TokenTransfer {
+    let cpi_accounts = TransferChecked {
         from: depositor_token_account.to_account_info(),
+        mint: mint.to_account_info(),
         to: vault_token_account.to_account_info(),
         authority: depositor.to_account_info(),
     };
 
     let cpi_context = CpiContext::new(token_program.to_account_info(), cpi_accounts);
-    token::transfer(cpi_context, amount)
+    token_interface::transfer_checked(cpi_context, amount, mint.decimals)
 }
 
 /// Transfer SPL tokens from pool vault to recipient
@@ -107,15 +142,18 @@ pub fn transfer_spl_to_pool<'info>(
 /// * `vault_token_account` - Pool's vault token account
 /// * `recipient_token_account` - Recipient's token account
 /// * `vault_authority` - PDA that owns the vault token account
-/// * `token_program` - SPL Token program
+/// * `mint` - The pool's mint
+/// * `token_program` - Token or Token-2022 program owning the mint
 /// * `amount` - Amount of tokens to transfer
 /// * `pool_key` - Pool pubkey for PDA derivation
 /// * `vault_bump` - Bump seed for vault PDA
+#[allow(clippy::too_many_arguments)]
 pub fn transfer_spl_from_pool<'info>(
-    vault_token_account: &Account<'info, TokenAccount>,
-    recipient_token_account: &Account<'info, TokenAccount>,
+    vault_token_account: &InterfaceAccount<'info, TokenAccount>,
+    recipient_token_account: &InterfaceAccount<'info, TokenAccount>,
     vault_authority: &AccountInfo<'info>,
-    token_program: &Program<'info, Token>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
     amount: u64,
     pool_key: &Pubkey,
     vault_bump: u8,
@@ -124,8 +162,9 @@ pub fn transfer_spl_from_pool<'info>(
     let bump_bytes = [vault_bump];
     let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, pool_key_bytes, &bump_bytes]];
 
-    let cpi_accounts = TokenTransfer {
+    let cpi_accounts = TransferChecked {
         from: vault_token_account.to_account_info(),
+        mint: mint.to_account_info(),
         to: recipient_token_account.to_account_info(),
         authority: vault_authority.to_account_info(),
     };
@@ -136,7 +175,198 @@ pub fn transfer_spl_from_pool<'info>(
         signer_seeds,
     );
 
-    token::transfer(cpi_context, amount)
+    token_interface::transfer_checked(cpi_context, amount, mint.decimals)
+}
+
+/// Create `owner`'s associated token account for `mint` unless it exists
+///
+/// Returns whether `payer` funded a new account, which is the only case an
+/// unshield may charge the rent back out of its fee.
+pub fn create_associated_token_account_if_missing<'info>(
+    payer: &AccountInfo<'info>,
+    token_account: &AccountInfo<'info>,
+    owner: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    associated_token_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<bool> {
+    if !token_account.data_is_empty() {
+        return Ok(false);
+    }
+    let cpi_context = CpiContext::new(
+        associated_token_program.clone(),
+        associated_token::Create {
+            payer: payer.clone(),
+            associated_token: token_account.clone(),
+            authority: owner.clone(),
+            mint: mint.clone(),
+            system_program: system_program.clone(),
+            token_program: token_program.clone(),
+        },
+    );
+    associated_token::create(cpi_context)?;
+    Ok(true)
+}
+
+/// Balance of a token account not deserialized by the accounts struct
+pub fn token_account_amount(token_account: &AccountInfo) -> Result<u64> {
+    let data = token_account.try_borrow_data()?;
+    Ok(TokenAccount::try_deserialize(&mut &data[..])?.amount)
+}
+
+/// Unwrap `amount` of the depositor's wSOL into lamports
+///
+/// The amount moves into a scratch token account at the depositor's
+/// `WSOL_UNWRAP_SEED` PDA, which is closed back to the depositor in the
+/// same instruction. The rest of the wSOL stays in the depositor's account.
+#[allow(clippy::too_many_arguments)]
+pub fn unwrap_wsol<'info>(
+    program_id: &Pubkey,
+    wsol_account: &AccountInfo<'info>,
+    scratch_account: &AccountInfo<'info>,
+    wsol_mint: &AccountInfo<'info>,
+    depositor: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let (expected, bump) = derive_wsol_unwrap_pda(program_id, depositor.key);
+    require_keys_eq!(scratch_account.key(), expected, TokenError::InvalidTokenAccount);
+    nullifier::create_pda_account(
+        token_program.key,
+        scratch_account,
+        depositor,
+        system_program,
+        token::TokenAccount::LEN,
+        &[&[WSOL_UNWRAP_SEED, depositor.key.as_ref(), &[bump]]],
+    )?;
+
+    token::initialize_account3(CpiContext::new(
+        token_program.clone(),
+        token::InitializeAccount3 {
+            account: scratch_account.clone(),
+            mint: wsol_mint.clone(),
+            authority: depositor.clone(),
+        },
+    ))?;
+    token::transfer_checked(
+        CpiContext::new(
+            token_program.clone(),
+            token::TransferChecked {
+                from: wsol_account.clone(),
+                mint: wsol_mint.clone(),
+                to: scratch_account.clone(),
+                authority: depositor.clone(),
+            },
+        ),
+        amount,
+        NATIVE_DECIMALS,
+    )?;
+    token::close_account(CpiContext::new(
+        token_program.clone(),
+        token::CloseAccount {
+            account: scratch_account.clone(),
+            destination: depositor.clone(),
+            authority: depositor.clone(),
+        },
+    ))
+}
+
+/// Reject Token-2022 mints whose extensions would break the pool
+///
+/// A permanent delegate could drain the vault, a transfer hook (or an
+/// authority able to add one) would run arbitrary code on every deposit and
+/// withdrawal, transfer fees would make
+/// the vault receive less than the notes are worth, and non-transferable
+/// tokens could never be withdrawn. A default account state lets the freeze
+/// authority have the vault or the recipients' new accounts created frozen,
+/// locking the notes in. Extension types newer than the Token-2022 version
+/// this program is built against don't parse and are rejected too, which
+/// covers `Pausable`: pausing the mint would stop every deposit and
+/// withdrawal. Legacy Token program mints have no extensions and always
+/// pass.
+pub fn validate_mint_extensions(mint: &AccountInfo) -> Result<()> {
+    if *mint.owner != anchor_spl::token_2022::ID {
+        return Ok(());
+    }
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<MintState>::unpack(&data)
+        .map_err(|_| TokenError::InvalidMint)?;
+    let extensions = state
+        .get_extension_types()
+        .map_err(|_| TokenError::UnsupportedMintExtension)?;
+    for extension in extensions {
+        match extension {
+            ExtensionType::PermanentDelegate
+            | ExtensionType::TransferFeeConfig
+            | ExtensionType::NonTransferable
+            | ExtensionType::DefaultAccountState => {
+                return err!(TokenError::UnsupportedMintExtension);
+            }
+            ExtensionType::TransferHook => {
+                let hook = state
+                    .get_extension::<TransferHook>()
+                    .map_err(|_| TokenError::InvalidMint)?;
+                // An authority could point an unset hook at a program later
+                require!(
+                    Option::<Pubkey>::from(hook.program_id).is_none()
+                        && Option::<Pubkey>::from(hook.authority).is_none(),
+                    TokenError::UnsupportedMintExtension
+                );
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Fail unless the mint is a non-fungible token: no decimals and a supply of one
+///
+/// Metaplex programmable NFTs pass but are frozen in their owner's account,
+/// so their transfers into the vault fail.
+pub fn require_non_fungible(mint: &Mint) -> Result<()> {
+    require!(
+        mint.decimals == 0 && mint.supply == 1,
+        TokenError::NotNonFungible
+    );
+    Ok(())
+}
+
+/// Fail unless a vault went from `balance_before` to `balance_after` by
+/// receiving exactly `amount`
+///
+/// Mints with Token-2022 transfer fees deliver less than was sent, but the
+/// commitment is bound to the full amount, so accepting the deposit would
+/// leave the pool unable to pay every note out.
+pub fn require_received(balance_before: u64, balance_after: u64, amount: u64) -> Result<()> {
+    require!(
+        balance_after.checked_sub(balance_before) == Some(amount),
+        TokenError::TransferAmountMismatch
+    );
+    Ok(())
+}
+
+/// Base units in one UI unit of a mint with `decimals` decimals
+///
+/// Returns `None` past 19 decimals, whose UI unit doesn't fit a u64.
+pub fn ui_unit(decimals: u8) -> Option<u64> {
+    10u64.checked_pow(decimals as u32)
+}
+
+/// Convert a whole number of UI units (e.g. 100 USDC) to base units
+pub fn ui_to_base_units(ui_amount: u64, decimals: u8) -> Option<u64> {
+    ui_amount.checked_mul(ui_unit(decimals)?)
+}
+
+/// Split a base-unit amount into whole UI units and the remaining base units
+///
+/// Mints with more decimals than a u64 can scale have no whole units.
+pub fn base_to_ui_units(amount: u64, decimals: u8) -> (u64, u64) {
+    match ui_unit(decimals) {
+        Some(unit) => (amount / unit, amount % unit),
+        None => (0, amount),
+    }
 }
 
 /// Derive the vault PDA for a pool
@@ -144,20 +374,40 @@ pub fn derive_vault_pda(program_id: &Pubkey, pool: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[VAULT_SEED, pool.as_ref()], program_id)
 }
 
+/// Derive the scratch token account a depositor unwraps wSOL through
+pub fn derive_wsol_unwrap_pda(program_id: &Pubkey, depositor: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[WSOL_UNWRAP_SEED, depositor.as_ref()], program_id)
+}
+
 /// Pool seed prefix for denomination-based pools
 pub const POOL_SEED: &[u8] = b"pool";
 
-/// Derive the pool PDA for a specific denomination
+/// Mint seed of a pool PDA
+///
+/// SPL pools add their mint to the seeds so each mint gets its own pool per
+/// denomination. Native SOL pools add nothing, which keeps their addresses.
+/// SPL pools created before then keep their denomination-only address,
+/// which `PrivacyPool::is_at` still accepts for them.
+pub fn pool_mint_seed(mint: &Pubkey) -> &[u8] {
+    if *mint == Pubkey::default() {
+        &[]
+    } else {
+        mint.as_ref()
+    }
+}
+
+/// Derive the pool PDA for a specific denomination and mint
 ///
 /// # Arguments
 /// * `program_id` - The program ID
 /// * `denomination` - Fixed deposit amount in lamports (0 = custom pool)
+/// * `mint` - The pool's mint (default pubkey for native SOL)
 ///
 /// # Returns
 /// Tuple of (pool_pubkey, bump_seed)
-pub fn derive_pool_pda(program_id: &Pubkey, denomination: u64) -> (Pubkey, u8) {
+pub fn derive_pool_pda(program_id: &Pubkey, denomination: u64, mint: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(
-        &[POOL_SEED, &denomination.to_le_bytes()],
+        &[POOL_SEED, &denomination.to_le_bytes(), pool_mint_seed(mint)],
         program_id,
     )
 }
@@ -171,11 +421,33 @@ pub enum TokenError {
     InvalidTokenAccount,
     #[msg("Token mint mismatch")]
     MintMismatch,
+    #[msg("Invalid mint account")]
+    InvalidMint,
+    #[msg("Mint has an extension the pool can't support")]
+    UnsupportedMintExtension,
+    #[msg("Vault received a different amount than was shielded (fee-on-transfer mint)")]
+    TransferAmountMismatch,
+    #[msg("Wrapped SOL uses the native SOL pools")]
+    WrappedSolPool,
+    #[msg("Mint is not a non-fungible token")]
+    NotNonFungible,
+    #[msg("Decimals don't match the pool's mint")]
+    DecimalsMismatch,
+    #[msg("Token account is not its owner's associated token account")]
+    NotAssociatedTokenAccount,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::state::{AssetMode, PrivacyPool};
+    use anchor_lang::solana_program::program_option::COption;
+    use anchor_lang::solana_program::program_pack::Pack;
+    use anchor_spl::token_2022::spl_token_2022::extension::{
+        default_account_state::DefaultAccountState, BaseStateWithExtensionsMut,
+        StateWithExtensionsMut,
+    };
+    use anchor_spl::token_2022::spl_token_2022::state::{Account as TokenAccountState, AccountState};
 
     #[test]
     fn test_derive_vault_pda() {
@@ -194,4 +466,174 @@ mod tests {
         let (pda3, _) = derive_vault_pda(&program_id, &pool2);
         assert_ne!(pda, pda3);
     }
+
+    #[test]
+    fn test_pool_is_at_its_pda() {
+        let mint = Pubkey::new_unique();
+        let mut pool: Box<PrivacyPool> = Box::new(bytemuck::Zeroable::zeroed());
+        pool.denomination = 1_000;
+        pool.mint = mint;
+        let (address, bump) = derive_pool_pda(&crate::ID, 1_000, &mint);
+        pool.bump = bump;
+        assert!(pool.is_at(&address));
+        assert!(!pool.is_at(&derive_pool_pda(&crate::ID, 2_000, &mint).0));
+        assert!(!pool.is_at(&derive_pool_pda(&crate::ID, 1_000, &Pubkey::new_unique()).0));
+
+        // SPL pools from before mint-keyed addresses keep theirs
+        let (legacy, bump) =
+            Pubkey::find_program_address(&[POOL_SEED, &1_000u64.to_le_bytes()], &crate::ID);
+        pool.bump = bump;
+        assert!(pool.is_at(&legacy));
+
+        // A SOL pool is only ever at the denomination-only address
+        pool.mint = Pubkey::default();
+        assert!(pool.is_at(&legacy));
+        assert!(pool.require_sol_pool().is_ok());
+        assert!(pool.require_spl_pool().is_err());
+        pool.mint = mint;
+        assert!(pool.require_sol_pool().is_err());
+        assert!(pool.require_spl_pool().is_ok());
+    }
+
+    #[test]
+    fn test_derive_pool_pda_keys_spl_pools_by_mint() {
+        let program_id = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        // Native SOL pools keep their denomination-only address
+        let (sol_pool, _) = derive_pool_pda(&program_id, 1_000, &Pubkey::default());
+        let (legacy, _) =
+            Pubkey::find_program_address(&[POOL_SEED, &1_000u64.to_le_bytes()], &program_id);
+        assert_eq!(sol_pool, legacy);
+
+        // Same denomination, different mints = different pools
+        let (spl_pool, _) = derive_pool_pda(&program_id, 1_000, &mint);
+        let (other_pool, _) = derive_pool_pda(&program_id, 1_000, &Pubkey::new_unique());
+        assert_ne!(spl_pool, sol_pool);
+        assert_ne!(spl_pool, other_pool);
+
+        // The multi-asset pool has its own address
+        let (multi_asset_pool, _) = derive_pool_pda(&program_id, 0, &MULTI_ASSET_MINT);
+        let (variable_sol_pool, _) = derive_pool_pda(&program_id, 0, &Pubkey::default());
+        assert_ne!(multi_asset_pool, variable_sol_pool);
+        let (nft_pool, _) = derive_pool_pda(&program_id, 0, &NFT_POOL_MINT);
+        assert_ne!(nft_pool, multi_asset_pool);
+    }
+
+    #[test]
+    fn test_ui_units() {
+        // 100 USDC is 100_000_000 base units, not 100
+        assert_eq!(ui_to_base_units(100, 6), Some(100_000_000));
+        assert_eq!(ui_to_base_units(1, NATIVE_DECIMALS), Some(1_000_000_000));
+        assert_eq!(ui_to_base_units(7, 0), Some(7));
+        assert_eq!(ui_to_base_units(u64::MAX, 1), None);
+        assert_eq!(ui_to_base_units(1, 20), None);
+
+        assert_eq!(base_to_ui_units(100_000_000, 6), (100, 0));
+        assert_eq!(base_to_ui_units(1_500_000, 6), (1, 500_000));
+        assert_eq!(base_to_ui_units(100, 6), (0, 100));
+        assert_eq!(base_to_ui_units(42, 20), (0, 42));
+    }
+
+    /// Token-2022 mint data carrying a `DefaultAccountState` of frozen
+    fn frozen_by_default_mint() -> Vec<u8> {
+        let extensions = [ExtensionType::DefaultAccountState];
+        let len = ExtensionType::try_calculate_account_len::<MintState>(&extensions).unwrap();
+        let mut data = vec![0u8; len];
+        let mut state =
+            StateWithExtensionsMut::<MintState>::unpack_uninitialized(&mut data).unwrap();
+        state.base.is_initialized = true;
+        state.base.freeze_authority = COption::Some(Pubkey::new_unique());
+        state.pack_base();
+        state.init_account_type().unwrap();
+        state.init_extension::<DefaultAccountState>(true).unwrap().state =
+            AccountState::Frozen as u8;
+        data
+    }
+
+    #[test]
+    fn test_frozen_and_unknown_extensions_are_rejected() {
+        let (key, owner) = (Pubkey::new_unique(), anchor_spl::token_2022::ID);
+        let (mut lamports, mut data) = (0, frozen_by_default_mint());
+        let mint = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        let err = validate_mint_extensions(&mint).unwrap_err();
+        assert_eq!(err, TokenError::UnsupportedMintExtension.into());
+
+        // Same mint with the extension retyped as `Pausable`, which this
+        // program's Token-2022 doesn't know
+        let (mut lamports, mut data) = (0, frozen_by_default_mint());
+        let tlv_start = TokenAccountState::LEN + 1;
+        data[tlv_start..tlv_start + 2].copy_from_slice(&26u16.to_le_bytes());
+        let mint = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        let err = validate_mint_extensions(&mint).unwrap_err();
+        assert_eq!(err, TokenError::UnsupportedMintExtension.into());
+    }
+
+    #[test]
+    fn test_vault_must_receive_the_shielded_amount() {
+        assert!(require_received(500, 1_500, 1_000).is_ok());
+        // A transfer fee withheld on the way in
+        let err = require_received(500, 1_490, 1_000).unwrap_err();
+        assert_eq!(err, TokenError::TransferAmountMismatch.into());
+        assert!(require_received(500, 1_510, 1_000).is_err());
+        assert!(require_received(500, 400, 1_000).is_err());
+    }
+
+    /// Mint account with the given supply and decimals
+    fn mint(supply: u64, decimals: u8) -> Mint {
+        let state = MintState {
+            mint_authority: COption::None,
+            supply,
+            decimals,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        };
+        let mut data = vec![0u8; MintState::LEN];
+        MintState::pack(state, &mut data).unwrap();
+        Mint::try_deserialize(&mut &data[..]).unwrap()
+    }
+
+    #[test]
+    fn test_nft_pools_only_take_non_fungible_tokens() {
+        assert!(require_non_fungible(&mint(1, 0)).is_ok());
+        let err = require_non_fungible(&mint(2, 0)).unwrap_err();
+        assert_eq!(err, TokenError::NotNonFungible.into());
+        // A fungible token with one base unit in circulation isn't an NFT either
+        assert!(require_non_fungible(&mint(1, 6)).is_err());
+
+        let mut pool: Box<PrivacyPool> = Box::new(bytemuck::Zeroable::zeroed());
+        assert!(pool.require_nft().is_err());
+        pool.asset_mode = AssetMode::Nft as u8;
+        assert!(pool.require_nft().is_ok());
+        assert!(pool.require_multi_asset().is_err());
+        assert!(pool.require_single_asset().is_err());
+    }
+
+    #[test]
+    fn test_rent_fee_only_when_fronted() {
+        let mut pool: Box<PrivacyPool> = Box::new(bytemuck::Zeroable::zeroed());
+        pool.relayer_fee_bps = 100;
+        pool.max_rent_fee = 2_000;
+        // An existing token account and no refund leave only the rate
+        assert_eq!(pool.max_unshield_fee(100_000, false), 1_000);
+        assert_eq!(pool.max_unshield_fee(100_000, true), 3_000);
+
+        // Whatever the fee takes above the rate is the rent reimbursement
+        assert_eq!(pool.split_rent_fee(100_000, 2_500), (1_000, 1_500));
+        assert_eq!(pool.split_rent_fee(100_000, 600), (600, 0));
+    }
+
+    #[test]
+    fn test_existing_token_account_is_not_created() {
+        let (key, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut lamports, mut data) = (0, vec![0u8; 165]);
+        let info = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &owner, false, 0);
+        // Nothing is invoked for an account that already exists, so the
+        // relayer fronts no rent for it
+        let created = create_associated_token_account_if_missing(
+            &info, &info, &info, &info, &info, &info, &info,
+        )
+        .unwrap();
+        assert!(!created);
+    }
 }
@@ -1,29 +1,163 @@
 //! Proof Verification Module
 //!
 //! This module provides proof verification for the Nyx privacy protocol.
-//! It supports two proof types:
+//! It supports three proof types:
 //!
 //! 1. **MVP/Signature Mode** (96 bytes):
 //!    - Ed25519 signature-based proofs for testing
 //!    - NOT private - reveals signer's public key
 //!    - Format: [signature (64) | pubkey (32)]
+//!    - Only accepted when built with the `mock-prover` feature
 //!
-//! 2. **Groth16 zkSNARK Mode** (256 bytes):
+//! 2. **Groth16 zkSNARK Mode** (257 bytes):
 //!    - Full privacy via zero-knowledge proofs
 //!    - Uses Solana's BN254 precompiles (available since 1.18.x)
-//!    - Format: [proof_a (64) | proof_b (128) | proof_c (64)]
+//!    - Format: [format (1) | proof_a (64) | proof_b (128) | proof_c (64)]
+//!    - The format byte selects snarkjs or arkworks point encoding
+//!
+//! 3. **PLONK zkSNARK Mode** (768 bytes):
+//!    - Universal setup, no per-circuit ceremony
+//!    - Only accepted by pools whose `proof_system` is PLONK
 //!
 //! The proof type is detected automatically based on proof size.
 
 use anchor_lang::prelude::*;
 use solana_program::ed25519_program;
+use solana_program::hash;
 use solana_program::keccak;
 
-use crate::groth16::{verify_groth16_withdraw, PROOF_SIZE as GROTH16_PROOF_SIZE};
+use crate::groth16::{
+    u64_to_field_bytes, verify_groth16, verify_groth16_batch, verify_groth16_withdraw,
+    Groth16Proof, Groth16VerifyingKey, WithdrawPublicInputs,
+    SERIALIZED_PROOF_SIZE as GROTH16_PROOF_SIZE, VK_SEED,
+};
+use crate::instructions::{AggregatedWithdrawal, NyxError};
+use crate::plonk::{verify_plonk, PlonkVerifyingKeyData, PLONK_PROOF_SIZE, PLONK_VK_SEED};
+use crate::poseidon;
+use crate::state::ProofSystem;
+use crate::token::NATIVE_MINT;
 
 /// MVP proof size (signature + pubkey)
 pub const MVP_PROOF_SIZE: usize = 96;
 
+/// Maximum withdrawals attested by one aggregated proof
+pub const MAX_AGGREGATED_WITHDRAWALS: usize = 8;
+
+/// Payout slots of the multi-recipient unshield circuit
+pub const MAX_UNSHIELD_RECIPIENTS: usize = 4;
+
+/// BN254 scalar field modulus r (big-endian)
+pub const SCALAR_FIELD_MODULUS: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29,
+    0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x28, 0x33, 0xe8, 0x48, 0x79, 0xb9, 0x70, 0x91,
+    0x43, 0xe1, 0xf5, 0x93, 0xf0, 0x00, 0x00, 0x01,
+];
+
+/// Domain separator for the nullifier domain tag
+pub const NULLIFIER_DOMAIN: &[u8] = b"NYX_NULLIFIER";
+
+/// Tag binding a pool's nullifiers to the pool and its mint
+///
+/// Tag = sha256(NULLIFIER_DOMAIN || pool || mint), with the top three bits
+/// cleared so it fits in the scalar field. Spend circuits derive the
+/// nullifier as Poseidon(spending_key, Poseidon(leaf_index, tag)) and expose
+/// the tag as a public input, so the same secret produces unrelated
+/// nullifiers in different pools and a proof for one pool can't be replayed
+/// in another. Native SOL pools use the default pubkey as their mint.
+pub fn nullifier_domain(pool: &Pubkey, mint: &Pubkey) -> [u8; 32] {
+    let mut tag = hash::hashv(&[NULLIFIER_DOMAIN, pool.as_ref(), mint.as_ref()]).to_bytes();
+    tag[0] &= 0x1f;
+    tag
+}
+
+/// Domain separator for asset IDs
+pub const ASSET_ID_DOMAIN: &[u8] = b"NYX_ASSET";
+
+/// Asset ID a note of `mint` commits to
+///
+/// Native SOL is 0, and so is wSOL, whose deposits join the native pools.
+/// Other mints take the first 8 bytes of sha256(ASSET_ID_DOMAIN || mint) as
+/// a little-endian u64. Multi-asset pools bind it into shield and unshield
+/// proofs, and only take allowlisted mints, so nobody can grind a mint that
+/// collides with a listed one.
+pub fn asset_id(mint: &Pubkey) -> u64 {
+    if *mint == Pubkey::default() || *mint == NATIVE_MINT {
+        return 0;
+    }
+    let hash = hash::hashv(&[ASSET_ID_DOMAIN, mint.as_ref()]).to_bytes();
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash[..8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Commitment of a note, recomputed from its revealed preimage
+///
+/// commitment = Poseidon(Poseidon(spending_key, amount), Poseidon(blinding, asset_id)),
+/// the derivation the shield circuit constrains.
+pub fn note_commitment(
+    spending_key: &[u8; 32],
+    amount: u64,
+    blinding: &[u8; 32],
+    asset_id: u64,
+) -> Result<[u8; 32]> {
+    let h1 = hash_field_pair(spending_key, &u64_to_field_bytes(amount))?;
+    let h2 = hash_field_pair(blinding, &u64_to_field_bytes(asset_id))?;
+    hash_field_pair(&h1, &h2)
+}
+
+/// Leaf of a note that can't be spent before `not_before_slot`
+///
+/// leaf = Poseidon(commitment, not_before_slot), the leaf the timelocked
+/// unshield circuit proves membership of.
+pub fn timelocked_commitment(commitment: &[u8; 32], not_before_slot: u64) -> Result<[u8; 32]> {
+    hash_field_pair(commitment, &u64_to_field_bytes(not_before_slot))
+}
+
+/// Reject spending a timelocked note before its unlock slot
+///
+/// The counterpart of the `expiry_slot` check: a timelocked proof is only
+/// accepted from `not_before_slot` on.
+pub fn check_timelock(not_before_slot: u64, current_slot: u64) -> Result<()> {
+    require!(current_slot >= not_before_slot, NyxError::NoteTimelocked);
+    Ok(())
+}
+
+/// Nullifier of the note at `leaf_index`, derived as the spend circuits do
+///
+/// nullifier = Poseidon(spending_key, Poseidon(leaf_index, domain))
+pub fn note_nullifier(
+    spending_key: &[u8; 32],
+    leaf_index: u64,
+    domain: &[u8; 32],
+) -> Result<[u8; 32]> {
+    let index_with_domain = hash_field_pair(&u64_to_field_bytes(leaf_index), domain)?;
+    hash_field_pair(spending_key, &index_with_domain)
+}
+
+fn hash_field_pair(left: &[u8; 32], right: &[u8; 32]) -> Result<[u8; 32]> {
+    poseidon::hash_pair(left, right).ok_or_else(|| error!(NyxError::NonCanonicalFieldElement))
+}
+
+/// Check that 32 big-endian bytes encode a canonical scalar field element
+///
+/// Values >= r reduce to the same field element as `value - r`, so accepting
+/// them would let one nullifier be spent under several byte encodings.
+pub fn is_canonical_field_element(bytes: &[u8; 32]) -> bool {
+    *bytes < SCALAR_FIELD_MODULUS
+}
+
+/// Reject any non-canonical field element among the given public inputs
+///
+/// Only caller-supplied values need this; the root is read from the pool.
+pub fn require_canonical_inputs(inputs: &[&[u8; 32]]) -> Result<()> {
+    require!(
+        inputs.iter().all(|input| is_canonical_field_element(input)),
+        NyxError::NonCanonicalFieldElement
+    );
+    Ok(())
+}
+
 /// Proof types supported by the protocol
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
 pub enum ProofType {
@@ -31,19 +165,176 @@ pub enum ProofType {
     Signature,
     /// Production: Groth16 zkSNARK proof (fully private)
     Groth16,
+    /// Production: PLONK zkSNARK proof (fully private)
+    Plonk,
 }
 
 impl ProofType {
     /// Detect proof type from proof bytes
+    ///
+    /// Signature proofs are only detected with the `mock-prover` feature;
+    /// default builds reject them as an invalid format before any
+    /// verification runs.
     pub fn detect(proof: &[u8]) -> Option<Self> {
         match proof.len() {
+            #[cfg(feature = "mock-prover")]
             MVP_PROOF_SIZE => Some(ProofType::Signature),
             GROTH16_PROOF_SIZE => Some(ProofType::Groth16),
+            PLONK_PROOF_SIZE => Some(ProofType::Plonk),
             _ => None,
         }
     }
 }
 
+/// Circuit identifiers
+///
+/// Each circuit has its own public input layout and verifying key.
+/// The ID is part of the verifying key PDA seeds, so each processor
+/// derives the key for the circuit it verifies.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CircuitId {
+    /// Private transfer: root, nullifier, new_commitment, expiry_slot, nullifier_domain
+    Transfer = 0,
+    /// Withdrawal: root, nullifierHash, recipient, amount, relayer, fee, refund, expiry_slot,
+    /// nullifier_domain
+    Unshield = 1,
+    /// 2-in/2-out join-split: root, nullifier_a, nullifier_b, commitment_a, commitment_b,
+    /// expiry_slot, nullifier_domain
+    JoinSplit = 2,
+    /// N withdrawals in one proof: root, withdrawals_hash, relayer, expiry_slot, nullifier_domain
+    AggregatedUnshield = 3,
+    /// Deposit into a multi-asset pool: commitment, amount, asset_id
+    Shield = 4,
+    /// Withdrawal from a multi-asset pool: the `Unshield` inputs followed by asset_id
+    MultiAssetUnshield = 5,
+    /// Partial withdrawal: the `Unshield` inputs followed by change_commitment
+    UnshieldWithChange = 6,
+    /// One note paid to several recipients: root, nullifier, recipients, amounts, relayer,
+    /// fee, expiry_slot, nullifier_domain, with `MAX_UNSHIELD_RECIPIENTS` recipients and
+    /// amounts
+    UnshieldMany = 7,
+    /// Withdrawal of a timelocked note: the `Unshield` inputs followed by not_before_slot
+    TimelockedUnshield = 8,
+    /// Withdrawal of an escrow note: the `Unshield` inputs followed by arbiter and escrow_id
+    EscrowUnshield = 9,
+}
+
+impl CircuitId {
+    /// Every circuit, in discriminator order
+    pub const ALL: [CircuitId; 10] = [
+        CircuitId::Transfer,
+        CircuitId::Unshield,
+        CircuitId::JoinSplit,
+        CircuitId::AggregatedUnshield,
+        CircuitId::Shield,
+        CircuitId::MultiAssetUnshield,
+        CircuitId::UnshieldWithChange,
+        CircuitId::UnshieldMany,
+        CircuitId::TimelockedUnshield,
+        CircuitId::EscrowUnshield,
+    ];
+
+    /// Parse from the raw discriminator byte
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(CircuitId::Transfer),
+            1 => Some(CircuitId::Unshield),
+            2 => Some(CircuitId::JoinSplit),
+            3 => Some(CircuitId::AggregatedUnshield),
+            4 => Some(CircuitId::Shield),
+            5 => Some(CircuitId::MultiAssetUnshield),
+            6 => Some(CircuitId::UnshieldWithChange),
+            7 => Some(CircuitId::UnshieldMany),
+            8 => Some(CircuitId::TimelockedUnshield),
+            9 => Some(CircuitId::EscrowUnshield),
+            _ => None,
+        }
+    }
+
+    /// Number of public inputs the circuit exposes
+    pub fn num_public_inputs(&self) -> usize {
+        match self {
+            CircuitId::Transfer => 5,
+            CircuitId::Unshield => 9,
+            CircuitId::JoinSplit => 7,
+            CircuitId::AggregatedUnshield => 5,
+            CircuitId::Shield => 3,
+            CircuitId::MultiAssetUnshield => 10,
+            CircuitId::UnshieldWithChange => 10,
+            CircuitId::UnshieldMany => 6 + 2 * MAX_UNSHIELD_RECIPIENTS,
+            CircuitId::TimelockedUnshield => 10,
+            CircuitId::EscrowUnshield => 11,
+        }
+    }
+}
+
+/// Address of a pool's verifying key for `circuit` at `version`
+pub fn verifying_key_address(
+    proof_system: ProofSystem,
+    pool: &Pubkey,
+    circuit: CircuitId,
+    version: u32,
+) -> Pubkey {
+    let seed = match proof_system {
+        ProofSystem::Groth16 => VK_SEED,
+        ProofSystem::Plonk => PLONK_VK_SEED,
+    };
+    Pubkey::find_program_address(
+        &[seed, pool.as_ref(), &[circuit as u8], &version.to_le_bytes()],
+        &crate::ID,
+    )
+    .0
+}
+
+/// Require `accounts` to be the pool's keys at `version` for every circuit,
+/// in `CircuitId::ALL` order
+///
+/// Only `propose_verifying_key` and `propose_plonk_verifying_key` create
+/// accounts at these addresses, so a live account is a proposed key.
+pub fn require_verifying_keys(
+    pool: &Pubkey,
+    proof_system: ProofSystem,
+    version: u32,
+    accounts: &[AccountInfo],
+) -> Result<()> {
+    require!(accounts.len() == CircuitId::ALL.len(), NyxError::MissingVerifyingKey);
+    for (circuit, account) in CircuitId::ALL.into_iter().zip(accounts) {
+        require!(
+            *account.key == verifying_key_address(proof_system, pool, circuit, version)
+                && *account.owner == crate::ID
+                && !account.data_is_empty(),
+            NyxError::MissingVerifyingKey
+        );
+    }
+    Ok(())
+}
+
+/// Verifying key for a pool's proof system
+///
+/// Processors build this from the pool's `proof_system` and the matching
+/// verifying key account; a proof of the other system is rejected.
+pub enum Verifier<'a> {
+    Groth16(Groth16VerifyingKey<'a>),
+    Plonk(&'a PlonkVerifyingKeyData),
+}
+
+impl Verifier<'_> {
+    /// Verify a zkSNARK proof against ordered public inputs
+    fn verify_snark(
+        &self,
+        proof_type: ProofType,
+        proof: &[u8],
+        public_inputs: &[[u8; 32]],
+    ) -> Result<bool> {
+        match (proof_type, self) {
+            (ProofType::Groth16, Verifier::Groth16(vk)) => verify_groth16(proof, public_inputs, vk),
+            (ProofType::Plonk, Verifier::Plonk(vk)) => verify_plonk(proof, public_inputs, vk),
+            _ => Err(VerificationError::ProofSystemMismatch.into()),
+        }
+    }
+}
+
 /// MVP proof structure (signature-based)
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
 pub struct MvpProof {
@@ -75,33 +366,188 @@ impl MvpProof {
 
 /// Build the message to be signed for a transfer proof
 ///
-/// Message = keccak256(nullifier || new_commitment || root)
+/// Message = keccak256(nullifier_domain || nullifier || new_commitment || root || expiry_slot)
 pub fn build_transfer_message(
+    nullifier_domain: &[u8; 32],
     nullifier: &[u8; 32],
     new_commitment: &[u8; 32],
     root: &[u8; 32],
+    expiry_slot: u64,
 ) -> [u8; 32] {
-    let mut data = Vec::with_capacity(96);
+    let mut data = Vec::with_capacity(136);
+    data.extend_from_slice(nullifier_domain);
     data.extend_from_slice(nullifier);
     data.extend_from_slice(new_commitment);
     data.extend_from_slice(root);
+    data.extend_from_slice(&expiry_slot.to_le_bytes());
     keccak::hash(&data).to_bytes()
 }
 
+/// Build the message to be signed for a join-split proof
+///
+/// Message = keccak256(nullifier_domain || nullifier_a || nullifier_b || commitment_a
+///                     || commitment_b || root || expiry_slot)
+pub fn build_join_split_message(
+    nullifier_domain: &[u8; 32],
+    nullifiers: &[[u8; 32]; 2],
+    commitments: &[[u8; 32]; 2],
+    root: &[u8; 32],
+    expiry_slot: u64,
+) -> [u8; 32] {
+    keccak::hashv(&[
+        nullifier_domain,
+        &nullifiers[0],
+        &nullifiers[1],
+        &commitments[0],
+        &commitments[1],
+        root,
+        &expiry_slot.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
 /// Build the message to be signed for an unshield proof
 ///
-/// Message = keccak256(nullifier || recipient || amount || root)
+/// Message = keccak256(nullifier_domain || nullifier || recipient || amount || relayer || fee
+///                     || refund || root || expiry_slot)
+#[allow(clippy::too_many_arguments)]
 pub fn build_unshield_message(
+    nullifier_domain: &[u8; 32],
     nullifier: &[u8; 32],
     recipient: &Pubkey,
     amount: u64,
+    relayer: &Pubkey,
+    fee: u64,
+    refund: u64,
     root: &[u8; 32],
+    expiry_slot: u64,
 ) -> [u8; 32] {
-    let mut data = Vec::with_capacity(104);
+    let mut data = Vec::with_capacity(192);
+    data.extend_from_slice(nullifier_domain);
     data.extend_from_slice(nullifier);
     data.extend_from_slice(recipient.as_ref());
     data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(relayer.as_ref());
+    data.extend_from_slice(&fee.to_le_bytes());
+    data.extend_from_slice(&refund.to_le_bytes());
+    data.extend_from_slice(root);
+    data.extend_from_slice(&expiry_slot.to_le_bytes());
+    keccak::hash(&data).to_bytes()
+}
+
+/// Build the message to be signed for a multi-asset unshield proof
+///
+/// Message = keccak256(unshield_message || asset_id)
+pub fn build_multi_asset_unshield_message(unshield_message: &[u8; 32], asset_id: u64) -> [u8; 32] {
+    keccak::hashv(&[unshield_message, &asset_id.to_le_bytes()]).to_bytes()
+}
+
+/// Build the message to be signed for a partial withdrawal proof
+///
+/// Message = keccak256(unshield_message || change_commitment)
+pub fn build_unshield_with_change_message(
+    unshield_message: &[u8; 32],
+    change_commitment: &[u8; 32],
+) -> [u8; 32] {
+    keccak::hashv(&[unshield_message, change_commitment]).to_bytes()
+}
+
+/// Build the message to be signed for a timelocked unshield proof
+///
+/// Message = keccak256(unshield_message || not_before_slot)
+pub fn build_timelocked_unshield_message(
+    unshield_message: &[u8; 32],
+    not_before_slot: u64,
+) -> [u8; 32] {
+    keccak::hashv(&[unshield_message, &not_before_slot.to_le_bytes()]).to_bytes()
+}
+
+/// Build the message to be signed for an escrow unshield proof
+///
+/// Message = keccak256(unshield_message || arbiter || escrow_id)
+pub fn build_escrow_unshield_message(
+    unshield_message: &[u8; 32],
+    arbiter: &Pubkey,
+    escrow_id: &[u8; 32],
+) -> [u8; 32] {
+    keccak::hashv(&[unshield_message, arbiter.as_ref(), escrow_id]).to_bytes()
+}
+
+/// Build the message to be signed for a multi-recipient unshield proof
+///
+/// Message = keccak256(nullifier_domain || nullifier || count || (recipient || amount)*
+///                     || relayer || fee || root || expiry_slot)
+#[allow(clippy::too_many_arguments)]
+pub fn build_unshield_many_message(
+    nullifier_domain: &[u8; 32],
+    nullifier: &[u8; 32],
+    recipients: &[Pubkey],
+    amounts: &[u64],
+    relayer: &Pubkey,
+    fee: u64,
+    root: &[u8; 32],
+    expiry_slot: u64,
+) -> [u8; 32] {
+    let mut data = Vec::with_capacity(145 + recipients.len() * 40);
+    data.extend_from_slice(nullifier_domain);
+    data.extend_from_slice(nullifier);
+    data.push(recipients.len() as u8);
+    for (recipient, amount) in recipients.iter().zip(amounts) {
+        data.extend_from_slice(recipient.as_ref());
+        data.extend_from_slice(&amount.to_le_bytes());
+    }
+    data.extend_from_slice(relayer.as_ref());
+    data.extend_from_slice(&fee.to_le_bytes());
+    data.extend_from_slice(root);
+    data.extend_from_slice(&expiry_slot.to_le_bytes());
+    keccak::hash(&data).to_bytes()
+}
+
+/// Build the message to be signed for a shield proof
+///
+/// Message = keccak256(commitment || amount || asset_id)
+pub fn build_shield_message(commitment: &[u8; 32], amount: u64, asset_id: u64) -> [u8; 32] {
+    keccak::hashv(&[commitment, &amount.to_le_bytes(), &asset_id.to_le_bytes()]).to_bytes()
+}
+
+/// Hash the withdrawals attested by an aggregated proof
+///
+/// Hash = keccak256(count || (nullifier || recipient || amount || fee)*),
+/// with the top three bits cleared so it fits in the scalar field. The
+/// aggregation circuit recomputes it from its private per-withdrawal inputs.
+pub fn hash_aggregated_withdrawals(
+    withdrawals: &[AggregatedWithdrawal],
+    recipients: &[Pubkey],
+) -> [u8; 32] {
+    let mut data = Vec::with_capacity(1 + withdrawals.len() * 112);
+    data.push(withdrawals.len() as u8);
+    for (withdrawal, recipient) in withdrawals.iter().zip(recipients) {
+        data.extend_from_slice(&withdrawal.nullifier);
+        data.extend_from_slice(recipient.as_ref());
+        data.extend_from_slice(&withdrawal.amount.to_le_bytes());
+        data.extend_from_slice(&withdrawal.fee.to_le_bytes());
+    }
+    let mut hash = keccak::hash(&data).to_bytes();
+    hash[0] &= 0x1f;
+    hash
+}
+
+/// Build the message to be signed for an aggregated unshield proof
+///
+/// Message = keccak256(nullifier_domain || withdrawals_hash || relayer || root || expiry_slot)
+pub fn build_aggregated_unshield_message(
+    nullifier_domain: &[u8; 32],
+    withdrawals_hash: &[u8; 32],
+    relayer: &Pubkey,
+    root: &[u8; 32],
+    expiry_slot: u64,
+) -> [u8; 32] {
+    let mut data = Vec::with_capacity(136);
+    data.extend_from_slice(nullifier_domain);
+    data.extend_from_slice(withdrawals_hash);
+    data.extend_from_slice(relayer.as_ref());
     data.extend_from_slice(root);
+    data.extend_from_slice(&expiry_slot.to_le_bytes());
     keccak::hash(&data).to_bytes()
 }
 
@@ -136,19 +582,31 @@ pub fn verify_signature(
 ///
 /// Automatically detects proof type based on size:
 /// - 96 bytes: MVP signature proof
-/// - 256 bytes: Groth16 zkSNARK proof
+/// - 257 bytes: Groth16 zkSNARK proof (format byte + points)
 ///
 /// # Arguments
-/// * `proof` - The proof bytes (96 or 256 bytes)
+/// * `verifier` - The pool's registered transfer verifying key
+/// * `proof` - The proof bytes (96, 257 or 768 bytes)
+/// * `pool` - The pool the note is spent from
+/// * `mint` - The pool's mint (default pubkey for native SOL)
 /// * `nullifier` - The nullifier being spent
 /// * `new_commitment` - The new commitment being created
 /// * `root` - The Merkle root
+/// * `expiry_slot` - Last slot at which the proof may be submitted
+#[allow(clippy::too_many_arguments)]
 pub fn verify_transfer_proof(
+    verifier: &Verifier,
     proof: &[u8],
+    pool: &Pubkey,
+    mint: &Pubkey,
     nullifier: &[u8; 32],
     new_commitment: &[u8; 32],
     root: &[u8; 32],
+    expiry_slot: u64,
 ) -> Result<bool> {
+    require_canonical_inputs(&[nullifier, new_commitment])?;
+    let domain = nullifier_domain(pool, mint);
+
     // Detect proof type
     let proof_type = ProofType::detect(proof)
         .ok_or(VerificationError::InvalidProofFormat)?;
@@ -158,17 +616,91 @@ pub fn verify_transfer_proof(
             // MVP: Ed25519 signature verification
             let mvp_proof = MvpProof::from_bytes(proof)
                 .ok_or(VerificationError::InvalidProofFormat)?;
-            let message = build_transfer_message(nullifier, new_commitment, root);
+            let message =
+                build_transfer_message(&domain, nullifier, new_commitment, root, expiry_slot);
             let valid = verify_signature(&message, &mvp_proof.signature, &mvp_proof.pubkey);
             Ok(valid)
         }
-        ProofType::Groth16 => {
-            // Production: Groth16 zkSNARK verification
-            // Note: The transfer circuit would use different public inputs
-            // For now, using the withdraw circuit with placeholder values
-            let recipient_bytes = [0u8; 32]; // Placeholder for transfers
-            let amount_bytes = [0u8; 32]; // Placeholder for transfers
-            verify_groth16_withdraw(proof, root, nullifier, &recipient_bytes, &amount_bytes)
+        ProofType::Groth16 | ProofType::Plonk => {
+            // Production: zkSNARK verification
+            // Transfer circuit public inputs:
+            // root, nullifier, new_commitment, expiry_slot, nullifier_domain
+            let public_inputs = [
+                *root,
+                *nullifier,
+                *new_commitment,
+                u64_to_field_bytes(expiry_slot),
+                domain,
+            ];
+            verifier
+                .verify_snark(proof_type, proof, &public_inputs)
+                .map_err(|_| VerificationError::VerificationFailed.into())
+        }
+    }
+}
+
+/// Verify a join-split proof
+///
+/// Automatically detects proof type based on size, like
+/// `verify_transfer_proof`.
+///
+/// # Arguments
+/// * `verifier` - The pool's registered join-split verifying key
+/// * `proof` - The proof bytes (96, 257 or 768 bytes)
+/// * `pool` - The pool the notes are spent from
+/// * `mint` - The pool's mint (default pubkey for native SOL)
+/// * `nullifiers` - The nullifiers being spent
+/// * `commitments` - The commitments being created
+/// * `root` - The Merkle root
+/// * `expiry_slot` - Last slot at which the proof may be submitted
+#[allow(clippy::too_many_arguments)]
+pub fn verify_join_split_proof(
+    verifier: &Verifier,
+    proof: &[u8],
+    pool: &Pubkey,
+    mint: &Pubkey,
+    nullifiers: &[[u8; 32]; 2],
+    commitments: &[[u8; 32]; 2],
+    root: &[u8; 32],
+    expiry_slot: u64,
+) -> Result<bool> {
+    require_canonical_inputs(&[
+        &nullifiers[0],
+        &nullifiers[1],
+        &commitments[0],
+        &commitments[1],
+    ])?;
+    let domain = nullifier_domain(pool, mint);
+
+    // Detect proof type
+    let proof_type = ProofType::detect(proof)
+        .ok_or(VerificationError::InvalidProofFormat)?;
+
+    match proof_type {
+        ProofType::Signature => {
+            // MVP: Ed25519 signature verification
+            let mvp_proof = MvpProof::from_bytes(proof)
+                .ok_or(VerificationError::InvalidProofFormat)?;
+            let message =
+                build_join_split_message(&domain, nullifiers, commitments, root, expiry_slot);
+            let valid = verify_signature(&message, &mvp_proof.signature, &mvp_proof.pubkey);
+            Ok(valid)
+        }
+        ProofType::Groth16 | ProofType::Plonk => {
+            // Production: zkSNARK verification
+            // Join-split circuit public inputs: root, nullifier_a, nullifier_b,
+            // commitment_a, commitment_b, expiry_slot, nullifier_domain
+            let public_inputs = [
+                *root,
+                nullifiers[0],
+                nullifiers[1],
+                commitments[0],
+                commitments[1],
+                u64_to_field_bytes(expiry_slot),
+                domain,
+            ];
+            verifier
+                .verify_snark(proof_type, proof, &public_inputs)
                 .map_err(|_| VerificationError::VerificationFailed.into())
         }
     }
@@ -178,51 +710,554 @@ pub fn verify_transfer_proof(
 ///
 /// Automatically detects proof type based on size:
 /// - 96 bytes: MVP signature proof
-/// - 256 bytes: Groth16 zkSNARK proof
+/// - 257 bytes: Groth16 zkSNARK proof (format byte + points)
 ///
-/// For Groth16 proofs, the recipient and amount are derived from
-/// the public inputs embedded in the proof verification.
+/// The recipient, relayer, fee and refund are all bound into the proof,
+/// so a relayer that sees the transaction can't resubmit it with a
+/// different recipient or redirect the fee to itself.
 ///
 /// # Arguments
-/// * `proof` - The proof bytes (96 or 256 bytes)
+/// * `verifier` - The pool's registered withdraw verifying key
+/// * `proof` - The proof bytes (96, 257 or 768 bytes)
+/// * `pool` - The pool the note is spent from
+/// * `mint` - The pool's mint (default pubkey for native SOL)
 /// * `nullifier` - The nullifier being spent
-/// * `recipient` - The recipient pubkey (used for MVP only)
-/// * `amount` - The amount being withdrawn (used for MVP only)
+/// * `recipient` - The recipient pubkey
+/// * `amount` - The amount being withdrawn
+/// * `relayer` - The relayer submitting the transaction
+/// * `fee` - The fee paid to the relayer
+/// * `refund` - Lamports the relayer forwards to the recipient
 /// * `root` - The Merkle root
+/// * `expiry_slot` - Last slot at which the proof may be submitted
+#[allow(clippy::too_many_arguments)]
 pub fn verify_unshield_proof(
+    verifier: &Verifier,
+    proof: &[u8],
+    pool: &Pubkey,
+    mint: &Pubkey,
+    nullifier: &[u8; 32],
+    recipient: &Pubkey,
+    amount: u64,
+    relayer: &Pubkey,
+    fee: u64,
+    refund: u64,
+    root: &[u8; 32],
+    expiry_slot: u64,
+) -> Result<bool> {
+    require_canonical_inputs(&[nullifier])?;
+    let domain = nullifier_domain(pool, mint);
+
+    // Detect proof type
+    let proof_type = ProofType::detect(proof)
+        .ok_or(VerificationError::InvalidProofFormat)?;
+
+    match proof_type {
+        ProofType::Signature => {
+            // MVP: Ed25519 signature verification
+            let mvp_proof = MvpProof::from_bytes(proof)
+                .ok_or(VerificationError::InvalidProofFormat)?;
+            let message = build_unshield_message(
+                &domain, nullifier, recipient, amount, relayer, fee, refund, root, expiry_slot,
+            );
+            let valid = verify_signature(&message, &mvp_proof.signature, &mvp_proof.pubkey);
+            Ok(valid)
+        }
+        ProofType::Groth16 | ProofType::Plonk => {
+            // Production: zkSNARK verification
+            let public_inputs = WithdrawPublicInputs::new(
+                root, nullifier, recipient, amount, relayer, fee, refund, expiry_slot, &domain,
+            );
+            let valid = match verifier {
+                Verifier::Groth16(vk) if proof_type == ProofType::Groth16 => {
+                    verify_groth16_withdraw(vk, proof, &public_inputs)
+                }
+                _ => verifier.verify_snark(proof_type, proof, &public_inputs.to_verifier_inputs()),
+            };
+            valid.map_err(|_| VerificationError::VerificationFailed.into())
+        }
+    }
+}
+
+/// Verify a multi-asset unshield proof
+///
+/// As `verify_unshield_proof`, with the asset ID of the withdrawn mint as a
+/// tenth public input. A multi-asset pool's vaults all back the same tree,
+/// so without it a note of one asset could be withdrawn as another.
+///
+/// # Arguments
+/// * `asset_id` - `asset_id` of the mint being paid out
+#[allow(clippy::too_many_arguments)]
+pub fn verify_multi_asset_unshield_proof(
+    verifier: &Verifier,
+    proof: &[u8],
+    pool: &Pubkey,
+    mint: &Pubkey,
+    nullifier: &[u8; 32],
+    recipient: &Pubkey,
+    amount: u64,
+    relayer: &Pubkey,
+    fee: u64,
+    refund: u64,
+    root: &[u8; 32],
+    expiry_slot: u64,
+    asset_id: u64,
+) -> Result<bool> {
+    require_canonical_inputs(&[nullifier])?;
+    let domain = nullifier_domain(pool, mint);
+
+    // Detect proof type
+    let proof_type = ProofType::detect(proof)
+        .ok_or(VerificationError::InvalidProofFormat)?;
+
+    match proof_type {
+        ProofType::Signature => {
+            // MVP: Ed25519 signature verification
+            let mvp_proof = MvpProof::from_bytes(proof)
+                .ok_or(VerificationError::InvalidProofFormat)?;
+            let message = build_multi_asset_unshield_message(
+                &build_unshield_message(
+                    &domain, nullifier, recipient, amount, relayer, fee, refund, root, expiry_slot,
+                ),
+                asset_id,
+            );
+            let valid = verify_signature(&message, &mvp_proof.signature, &mvp_proof.pubkey);
+            Ok(valid)
+        }
+        ProofType::Groth16 | ProofType::Plonk => {
+            // Production: zkSNARK verification
+            // Multi-asset unshield circuit public inputs:
+            // the withdraw inputs, then asset_id
+            let withdraw_inputs = WithdrawPublicInputs::new(
+                root, nullifier, recipient, amount, relayer, fee, refund, expiry_slot, &domain,
+            )
+            .to_verifier_inputs();
+            let mut public_inputs = Vec::with_capacity(withdraw_inputs.len() + 1);
+            public_inputs.extend_from_slice(&withdraw_inputs);
+            public_inputs.push(u64_to_field_bytes(asset_id));
+            verifier
+                .verify_snark(proof_type, proof, &public_inputs)
+                .map_err(|_| VerificationError::VerificationFailed.into())
+        }
+    }
+}
+
+/// Verify a partial withdrawal proof
+///
+/// As `verify_unshield_proof`, with the commitment of the change note as a
+/// tenth public input. The spent note holds `amount` plus the change, which
+/// goes back to the owner and is inserted into the tree.
+///
+/// # Arguments
+/// * `change_commitment` - Commitment of the change note
+#[allow(clippy::too_many_arguments)]
+pub fn verify_unshield_with_change_proof(
+    verifier: &Verifier,
+    proof: &[u8],
+    pool: &Pubkey,
+    mint: &Pubkey,
+    nullifier: &[u8; 32],
+    recipient: &Pubkey,
+    amount: u64,
+    relayer: &Pubkey,
+    fee: u64,
+    refund: u64,
+    root: &[u8; 32],
+    expiry_slot: u64,
+    change_commitment: &[u8; 32],
+) -> Result<bool> {
+    require_canonical_inputs(&[nullifier, change_commitment])?;
+    let domain = nullifier_domain(pool, mint);
+
+    // Detect proof type
+    let proof_type = ProofType::detect(proof)
+        .ok_or(VerificationError::InvalidProofFormat)?;
+
+    match proof_type {
+        ProofType::Signature => {
+            // MVP: Ed25519 signature verification
+            let mvp_proof = MvpProof::from_bytes(proof)
+                .ok_or(VerificationError::InvalidProofFormat)?;
+            let message = build_unshield_with_change_message(
+                &build_unshield_message(
+                    &domain, nullifier, recipient, amount, relayer, fee, refund, root, expiry_slot,
+                ),
+                change_commitment,
+            );
+            let valid = verify_signature(&message, &mvp_proof.signature, &mvp_proof.pubkey);
+            Ok(valid)
+        }
+        ProofType::Groth16 | ProofType::Plonk => {
+            // Production: zkSNARK verification
+            // Partial withdrawal circuit public inputs:
+            // the withdraw inputs, then change_commitment
+            let withdraw_inputs = WithdrawPublicInputs::new(
+                root, nullifier, recipient, amount, relayer, fee, refund, expiry_slot, &domain,
+            )
+            .to_verifier_inputs();
+            let mut public_inputs = Vec::with_capacity(withdraw_inputs.len() + 1);
+            public_inputs.extend_from_slice(&withdraw_inputs);
+            public_inputs.push(*change_commitment);
+            verifier
+                .verify_snark(proof_type, proof, &public_inputs)
+                .map_err(|_| VerificationError::VerificationFailed.into())
+        }
+    }
+}
+
+/// Verify a timelocked unshield proof
+///
+/// As `verify_unshield_proof`, with the note's unlock slot as a tenth
+/// public input. The circuit proves membership of
+/// `timelocked_commitment(commitment, not_before_slot)`, so the slot can't
+/// be lowered; callers must still `check_timelock` it against the clock.
+///
+/// # Arguments
+/// * `not_before_slot` - First slot at which the note may be spent
+#[allow(clippy::too_many_arguments)]
+pub fn verify_timelocked_unshield_proof(
+    verifier: &Verifier,
     proof: &[u8],
+    pool: &Pubkey,
+    mint: &Pubkey,
     nullifier: &[u8; 32],
     recipient: &Pubkey,
     amount: u64,
+    relayer: &Pubkey,
+    fee: u64,
+    refund: u64,
+    root: &[u8; 32],
+    expiry_slot: u64,
+    not_before_slot: u64,
+) -> Result<bool> {
+    require_canonical_inputs(&[nullifier])?;
+    let domain = nullifier_domain(pool, mint);
+
+    // Detect proof type
+    let proof_type = ProofType::detect(proof)
+        .ok_or(VerificationError::InvalidProofFormat)?;
+
+    match proof_type {
+        ProofType::Signature => {
+            // MVP: Ed25519 signature verification
+            let mvp_proof = MvpProof::from_bytes(proof)
+                .ok_or(VerificationError::InvalidProofFormat)?;
+            let message = build_timelocked_unshield_message(
+                &build_unshield_message(
+                    &domain, nullifier, recipient, amount, relayer, fee, refund, root, expiry_slot,
+                ),
+                not_before_slot,
+            );
+            let valid = verify_signature(&message, &mvp_proof.signature, &mvp_proof.pubkey);
+            Ok(valid)
+        }
+        ProofType::Groth16 | ProofType::Plonk => {
+            // Production: zkSNARK verification
+            // Timelocked unshield circuit public inputs:
+            // the withdraw inputs, then not_before_slot
+            let withdraw_inputs = WithdrawPublicInputs::new(
+                root, nullifier, recipient, amount, relayer, fee, refund, expiry_slot, &domain,
+            )
+            .to_verifier_inputs();
+            let mut public_inputs = Vec::with_capacity(withdraw_inputs.len() + 1);
+            public_inputs.extend_from_slice(&withdraw_inputs);
+            public_inputs.push(u64_to_field_bytes(not_before_slot));
+            verifier
+                .verify_snark(proof_type, proof, &public_inputs)
+                .map_err(|_| VerificationError::VerificationFailed.into())
+        }
+    }
+}
+
+/// Verify an escrow unshield proof
+///
+/// As `verify_unshield_proof`, with the note's arbiter and escrow ID as
+/// the tenth and eleventh public inputs. The circuit proves membership of
+/// `escrow_commitment(commitment, arbiter, escrow_id)`, so neither can be
+/// swapped; callers must still `escrow::require_arbiter_resolution` for
+/// the recipient.
+///
+/// # Arguments
+/// * `arbiter` - Arbiter whose resolution releases the note
+/// * `escrow_id` - The deal the note is escrowed for
+#[allow(clippy::too_many_arguments)]
+pub fn verify_escrow_unshield_proof(
+    verifier: &Verifier,
+    proof: &[u8],
+    pool: &Pubkey,
+    mint: &Pubkey,
+    nullifier: &[u8; 32],
+    recipient: &Pubkey,
+    amount: u64,
+    relayer: &Pubkey,
+    fee: u64,
+    refund: u64,
+    root: &[u8; 32],
+    expiry_slot: u64,
+    arbiter: &Pubkey,
+    escrow_id: &[u8; 32],
+) -> Result<bool> {
+    require_canonical_inputs(&[nullifier, escrow_id])?;
+    let domain = nullifier_domain(pool, mint);
+
+    // Detect proof type
+    let proof_type = ProofType::detect(proof)
+        .ok_or(VerificationError::InvalidProofFormat)?;
+
+    match proof_type {
+        ProofType::Signature => {
+            // MVP: Ed25519 signature verification
+            let mvp_proof = MvpProof::from_bytes(proof)
+                .ok_or(VerificationError::InvalidProofFormat)?;
+            let message = build_escrow_unshield_message(
+                &build_unshield_message(
+                    &domain, nullifier, recipient, amount, relayer, fee, refund, root, expiry_slot,
+                ),
+                arbiter,
+                escrow_id,
+            );
+            let valid = verify_signature(&message, &mvp_proof.signature, &mvp_proof.pubkey);
+            Ok(valid)
+        }
+        ProofType::Groth16 | ProofType::Plonk => {
+            // Production: zkSNARK verification
+            // Escrow unshield circuit public inputs:
+            // the withdraw inputs, then arbiter and escrow_id
+            let withdraw_inputs = WithdrawPublicInputs::new(
+                root, nullifier, recipient, amount, relayer, fee, refund, expiry_slot, &domain,
+            )
+            .to_verifier_inputs();
+            let mut public_inputs = Vec::with_capacity(withdraw_inputs.len() + 2);
+            public_inputs.extend_from_slice(&withdraw_inputs);
+            public_inputs.push(arbiter.to_bytes());
+            public_inputs.push(*escrow_id);
+            verifier
+                .verify_snark(proof_type, proof, &public_inputs)
+                .map_err(|_| VerificationError::VerificationFailed.into())
+        }
+    }
+}
+
+/// Verify a multi-recipient unshield proof
+///
+/// One note is paid out to every recipient, each amount being a public
+/// input. The note holds the sum of the amounts plus `fee`, which goes to
+/// the relayer on top of the payouts.
+///
+/// # Arguments
+/// * `recipients` - Recipient of each payout, at most `MAX_UNSHIELD_RECIPIENTS`
+/// * `amounts` - Amount of each payout, in the same order
+#[allow(clippy::too_many_arguments)]
+pub fn verify_unshield_many_proof(
+    verifier: &Verifier,
+    proof: &[u8],
+    pool: &Pubkey,
+    mint: &Pubkey,
+    nullifier: &[u8; 32],
+    recipients: &[Pubkey],
+    amounts: &[u64],
+    relayer: &Pubkey,
+    fee: u64,
+    root: &[u8; 32],
+    expiry_slot: u64,
+) -> Result<bool> {
+    require!(
+        !recipients.is_empty()
+            && recipients.len() <= MAX_UNSHIELD_RECIPIENTS
+            && recipients.len() == amounts.len(),
+        NyxError::InvalidBatchSize
+    );
+    require_canonical_inputs(&[nullifier])?;
+    let domain = nullifier_domain(pool, mint);
+
+    // Detect proof type
+    let proof_type = ProofType::detect(proof)
+        .ok_or(VerificationError::InvalidProofFormat)?;
+
+    match proof_type {
+        ProofType::Signature => {
+            // MVP: Ed25519 signature verification
+            let mvp_proof = MvpProof::from_bytes(proof)
+                .ok_or(VerificationError::InvalidProofFormat)?;
+            let message = build_unshield_many_message(
+                &domain, nullifier, recipients, amounts, relayer, fee, root, expiry_slot,
+            );
+            let valid = verify_signature(&message, &mvp_proof.signature, &mvp_proof.pubkey);
+            Ok(valid)
+        }
+        ProofType::Groth16 | ProofType::Plonk => {
+            // Production: zkSNARK verification
+            // Multi-recipient unshield circuit public inputs: root, nullifier,
+            // recipients, amounts, relayer, fee, expiry_slot, nullifier_domain,
+            // with unused slots zeroed
+            let mut recipient_inputs = [[0u8; 32]; MAX_UNSHIELD_RECIPIENTS];
+            let mut amount_inputs = [u64_to_field_bytes(0); MAX_UNSHIELD_RECIPIENTS];
+            for (i, (recipient, amount)) in recipients.iter().zip(amounts).enumerate() {
+                recipient_inputs[i] = recipient.to_bytes();
+                amount_inputs[i] = u64_to_field_bytes(*amount);
+            }
+            let mut public_inputs = Vec::with_capacity(CircuitId::UnshieldMany.num_public_inputs());
+            public_inputs.push(*root);
+            public_inputs.push(*nullifier);
+            public_inputs.extend_from_slice(&recipient_inputs);
+            public_inputs.extend_from_slice(&amount_inputs);
+            public_inputs.push(relayer.to_bytes());
+            public_inputs.push(u64_to_field_bytes(fee));
+            public_inputs.push(u64_to_field_bytes(expiry_slot));
+            public_inputs.push(domain);
+            verifier
+                .verify_snark(proof_type, proof, &public_inputs)
+                .map_err(|_| VerificationError::VerificationFailed.into())
+        }
+    }
+}
+
+/// Verify a shield proof
+///
+/// Shields into single-asset pools don't need one: each pool has a single
+/// vault, and a note committing to the wrong amount or asset can only be
+/// spent against it. Multi-asset pools check that the commitment opens to
+/// the deposited amount and asset before inserting it.
+///
+/// # Arguments
+/// * `verifier` - The pool's registered shield verifying key
+/// * `proof` - The proof bytes (96, 257 or 768 bytes)
+/// * `commitment` - The commitment being inserted
+/// * `amount` - The deposited amount
+/// * `asset_id` - `asset_id` of the deposited mint
+pub fn verify_shield_proof(
+    verifier: &Verifier,
+    proof: &[u8],
+    commitment: &[u8; 32],
+    amount: u64,
+    asset_id: u64,
+) -> Result<bool> {
+    require_canonical_inputs(&[commitment])?;
+
+    // Detect proof type
+    let proof_type = ProofType::detect(proof)
+        .ok_or(VerificationError::InvalidProofFormat)?;
+
+    match proof_type {
+        ProofType::Signature => {
+            // MVP: Ed25519 signature verification
+            let mvp_proof = MvpProof::from_bytes(proof)
+                .ok_or(VerificationError::InvalidProofFormat)?;
+            let message = build_shield_message(commitment, amount, asset_id);
+            let valid = verify_signature(&message, &mvp_proof.signature, &mvp_proof.pubkey);
+            Ok(valid)
+        }
+        ProofType::Groth16 | ProofType::Plonk => {
+            // Production: zkSNARK verification
+            // Shield circuit public inputs: commitment, amount, asset_id
+            let public_inputs = [
+                *commitment,
+                u64_to_field_bytes(amount),
+                u64_to_field_bytes(asset_id),
+            ];
+            verifier
+                .verify_snark(proof_type, proof, &public_inputs)
+                .map_err(|_| VerificationError::VerificationFailed.into())
+        }
+    }
+}
+
+/// Verify an aggregated unshield proof
+///
+/// One proof attests to every withdrawal in the batch: the circuit checks
+/// each note's membership and nullifier and exposes only the hash of the
+/// withdrawal list, so the verification cost doesn't grow with the batch.
+///
+/// # Arguments
+/// * `verifier` - The pool's registered aggregated unshield verifying key
+/// * `proof` - The proof bytes (96, 257 or 768 bytes)
+/// * `pool` - The pool the notes are spent from
+/// * `mint` - The pool's mint (default pubkey for native SOL)
+/// * `withdrawals` - Nullifier, amount and fee of each withdrawal
+/// * `recipients` - Recipient of each withdrawal, in the same order
+/// * `relayer` - The relayer submitting the transaction
+/// * `root` - The Merkle root
+/// * `expiry_slot` - Last slot at which the proof may be submitted
+#[allow(clippy::too_many_arguments)]
+pub fn verify_aggregated_unshield_proof(
+    verifier: &Verifier,
+    proof: &[u8],
+    pool: &Pubkey,
+    mint: &Pubkey,
+    withdrawals: &[AggregatedWithdrawal],
+    recipients: &[Pubkey],
+    relayer: &Pubkey,
     root: &[u8; 32],
+    expiry_slot: u64,
 ) -> Result<bool> {
+    require!(
+        !withdrawals.is_empty()
+            && withdrawals.len() <= MAX_AGGREGATED_WITHDRAWALS
+            && withdrawals.len() == recipients.len(),
+        NyxError::InvalidBatchSize
+    );
+    for withdrawal in withdrawals {
+        require_canonical_inputs(&[&withdrawal.nullifier])?;
+    }
+
     // Detect proof type
     let proof_type = ProofType::detect(proof)
         .ok_or(VerificationError::InvalidProofFormat)?;
+    let withdrawals_hash = hash_aggregated_withdrawals(withdrawals, recipients);
+    let domain = nullifier_domain(pool, mint);
 
     match proof_type {
         ProofType::Signature => {
             // MVP: Ed25519 signature verification
             let mvp_proof = MvpProof::from_bytes(proof)
                 .ok_or(VerificationError::InvalidProofFormat)?;
-            let message = build_unshield_message(nullifier, recipient, amount, root);
+            let message = build_aggregated_unshield_message(
+                &domain,
+                &withdrawals_hash,
+                relayer,
+                root,
+                expiry_slot,
+            );
             let valid = verify_signature(&message, &mvp_proof.signature, &mvp_proof.pubkey);
             Ok(valid)
         }
-        ProofType::Groth16 => {
-            // Production: Groth16 zkSNARK verification
-            // Convert recipient pubkey to 32 bytes
-            let recipient_bytes = recipient.to_bytes();
-            // Convert amount to 32-byte big-endian representation
-            let mut amount_bytes = [0u8; 32];
-            amount_bytes[24..32].copy_from_slice(&amount.to_be_bytes());
-
-            verify_groth16_withdraw(proof, root, nullifier, &recipient_bytes, &amount_bytes)
+        ProofType::Groth16 | ProofType::Plonk => {
+            // Production: zkSNARK verification
+            // Aggregation circuit public inputs:
+            // root, withdrawals_hash, relayer, expiry_slot, nullifier_domain
+            let public_inputs = [
+                *root,
+                withdrawals_hash,
+                relayer.to_bytes(),
+                u64_to_field_bytes(expiry_slot),
+                domain,
+            ];
+            verifier
+                .verify_snark(proof_type, proof, &public_inputs)
                 .map_err(|_| VerificationError::VerificationFailed.into())
         }
     }
 }
 
+/// Verify a batch of Groth16 proofs with all-or-nothing semantics
+///
+/// Relayers submitting many withdrawals at once share one multi-pairing
+/// across the batch instead of paying four pairings per proof. Each proof
+/// is paired with the L point computed from its public inputs (see
+/// `groth16::compute_linear_combination`); signature proofs can't be batched.
+///
+/// # Arguments
+/// * `vk` - The verifying key every proof in the batch was made for
+/// * `proofs` - Parsed proofs in the syscall layout
+/// * `l_points` - Public input linear combination for each proof
+pub fn verify_batch(
+    vk: &Groth16VerifyingKey,
+    proofs: &[Groth16Proof],
+    l_points: &[[u8; 64]],
+) -> Result<bool> {
+    verify_groth16_batch(proofs, l_points, vk)
+        .map_err(|_| VerificationError::VerificationFailed.into())
+}
+
 /// Custom errors for verification
 #[error_code]
 pub enum VerificationError {
@@ -232,6 +1267,8 @@ pub enum VerificationError {
     VerificationFailed,
     #[msg("Invalid public key")]
     InvalidPublicKey,
+    #[msg("Proof does not match the pool's proof system")]
+    ProofSystemMismatch,
 }
 
 #[cfg(test)]
@@ -243,17 +1280,252 @@ mod tests {
         let nullifier = [1u8; 32];
         let new_commitment = [2u8; 32];
         let root = [3u8; 32];
+        let domain = nullifier_domain(&Pubkey::new_unique(), &Pubkey::default());
 
-        let msg1 = build_transfer_message(&nullifier, &new_commitment, &root);
-        let msg2 = build_transfer_message(&nullifier, &new_commitment, &root);
+        let msg1 = build_transfer_message(&domain, &nullifier, &new_commitment, &root, 100);
+        let msg2 = build_transfer_message(&domain, &nullifier, &new_commitment, &root, 100);
 
         // Should be deterministic
         assert_eq!(msg1, msg2);
 
         // Different inputs should produce different messages
         let nullifier2 = [4u8; 32];
-        let msg3 = build_transfer_message(&nullifier2, &new_commitment, &root);
+        let msg3 = build_transfer_message(&domain, &nullifier2, &new_commitment, &root, 100);
         assert_ne!(msg1, msg3);
+
+        // The expiry slot is bound into the message
+        let msg4 = build_transfer_message(&domain, &nullifier, &new_commitment, &root, 101);
+        assert_ne!(msg1, msg4);
+
+        // So is the pool's nullifier domain
+        let other_domain = nullifier_domain(&Pubkey::new_unique(), &Pubkey::default());
+        let msg5 = build_transfer_message(&other_domain, &nullifier, &new_commitment, &root, 100);
+        assert_ne!(msg1, msg5);
+    }
+
+    #[test]
+    fn test_build_join_split_message() {
+        let nullifiers = [[1u8; 32], [2u8; 32]];
+        let commitments = [[3u8; 32], [4u8; 32]];
+        let root = [5u8; 32];
+        let domain = nullifier_domain(&Pubkey::new_unique(), &Pubkey::default());
+
+        let msg = build_join_split_message(&domain, &nullifiers, &commitments, &root, 100);
+        assert_eq!(msg, build_join_split_message(&domain, &nullifiers, &commitments, &root, 100));
+
+        // The order of the notes is bound, as it is in the circuit
+        let swapped = [commitments[1], commitments[0]];
+        assert_ne!(msg, build_join_split_message(&domain, &nullifiers, &swapped, &root, 100));
+
+        // So is the expiry slot
+        assert_ne!(msg, build_join_split_message(&domain, &nullifiers, &commitments, &root, 101));
+    }
+
+    #[test]
+    fn test_build_unshield_many_message() {
+        let domain = nullifier_domain(&Pubkey::new_unique(), &Pubkey::default());
+        let nullifier = [1u8; 32];
+        let recipients = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let relayer = Pubkey::new_unique();
+        let root = [2u8; 32];
+        let message = |recipients: &[Pubkey], amounts: &[u64]| {
+            build_unshield_many_message(&domain, &nullifier, recipients, amounts, &relayer, 5, &root, 100)
+        };
+
+        let msg = message(&recipients, &[300, 700]);
+        assert_eq!(msg, message(&recipients, &[300, 700]));
+
+        // Each amount is bound to its recipient
+        assert_ne!(msg, message(&recipients, &[700, 300]));
+        assert_ne!(msg, message(&[recipients[1], recipients[0]], &[300, 700]));
+
+        // Dropping a payout changes the message
+        assert_ne!(msg, message(&recipients[..1], &[300]));
+        assert_eq!(CircuitId::UnshieldMany.num_public_inputs(), 14);
+    }
+
+    #[test]
+    fn test_timelock() {
+        assert!(check_timelock(100, 99).is_err());
+        assert!(check_timelock(100, 100).is_ok());
+        assert!(check_timelock(0, 0).is_ok());
+
+        // The unlock slot is bound into both the leaf and the message
+        let commitment = note_commitment(&[7u8; 32], 1000, &[9u8; 32], 0).unwrap();
+        let leaf = timelocked_commitment(&commitment, 100).unwrap();
+        assert_ne!(leaf, commitment);
+        assert_ne!(leaf, timelocked_commitment(&commitment, 99).unwrap());
+
+        let unshield_message = [5u8; 32];
+        assert_ne!(
+            build_timelocked_unshield_message(&unshield_message, 100),
+            build_timelocked_unshield_message(&unshield_message, 99)
+        );
+        assert_eq!(CircuitId::TimelockedUnshield.num_public_inputs(), 10);
+    }
+
+    #[test]
+    fn test_escrow_unshield_message_binds_arbiter_and_escrow() {
+        let unshield_message = [5u8; 32];
+        let arbiter = Pubkey::new_unique();
+        let message = build_escrow_unshield_message(&unshield_message, &arbiter, &[1u8; 32]);
+        assert_ne!(
+            message,
+            build_escrow_unshield_message(&unshield_message, &Pubkey::new_unique(), &[1u8; 32])
+        );
+        assert_ne!(message, build_escrow_unshield_message(&unshield_message, &arbiter, &[2u8; 32]));
+        assert_eq!(CircuitId::EscrowUnshield.num_public_inputs(), 11);
+    }
+
+    #[test]
+    fn test_nullifier_domain_binds_pool_and_mint() {
+        let pool = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        let domain = nullifier_domain(&pool, &mint);
+        assert!(is_canonical_field_element(&domain));
+        assert_eq!(domain, nullifier_domain(&pool, &mint));
+
+        assert_ne!(domain, nullifier_domain(&Pubkey::new_unique(), &mint));
+        assert_ne!(domain, nullifier_domain(&pool, &Pubkey::default()));
+    }
+
+    #[test]
+    fn test_asset_id() {
+        // Native SOL and wSOL notes share asset 0
+        assert_eq!(asset_id(&Pubkey::default()), 0);
+        assert_eq!(asset_id(&NATIVE_MINT), 0);
+
+        let mint = Pubkey::new_unique();
+        assert_ne!(asset_id(&mint), 0);
+        assert_eq!(asset_id(&mint), asset_id(&mint));
+        assert_ne!(asset_id(&mint), asset_id(&Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn test_note_preimage_derivations() {
+        let key = [7u8; 32];
+        let blinding = [9u8; 32];
+        let domain = nullifier_domain(&Pubkey::new_unique(), &Pubkey::default());
+
+        let commitment = note_commitment(&key, 1000, &blinding, 0).unwrap();
+        assert!(is_canonical_field_element(&commitment));
+        assert_ne!(commitment, note_commitment(&key, 1001, &blinding, 0).unwrap());
+        assert_ne!(commitment, note_commitment(&key, 1000, &blinding, 1).unwrap());
+
+        let nullifier = note_nullifier(&key, 3, &domain).unwrap();
+        assert_ne!(nullifier, note_nullifier(&key, 4, &domain).unwrap());
+        assert!(note_nullifier(&SCALAR_FIELD_MODULUS, 3, &domain).is_err());
+    }
+
+    #[test]
+    fn test_circuit_id_roundtrip() {
+        for id in [
+            CircuitId::Transfer,
+            CircuitId::Unshield,
+            CircuitId::JoinSplit,
+            CircuitId::AggregatedUnshield,
+            CircuitId::Shield,
+            CircuitId::MultiAssetUnshield,
+            CircuitId::UnshieldWithChange,
+            CircuitId::UnshieldMany,
+            CircuitId::TimelockedUnshield,
+            CircuitId::EscrowUnshield,
+        ] {
+            assert_eq!(CircuitId::from_u8(id as u8), Some(id));
+        }
+        assert_eq!(CircuitId::from_u8(10), None);
+        assert_eq!(CircuitId::Unshield.num_public_inputs(), crate::groth16::NUM_PUBLIC_INPUTS);
+    }
+
+    #[test]
+    fn test_proof_type_detection() {
+        assert_eq!(ProofType::detect(&[0u8; GROTH16_PROOF_SIZE]), Some(ProofType::Groth16));
+        assert_eq!(ProofType::detect(&[0u8; PLONK_PROOF_SIZE]), Some(ProofType::Plonk));
+        assert_eq!(ProofType::detect(&[0u8; 64]), None);
+
+        #[cfg(feature = "mock-prover")]
+        assert_eq!(ProofType::detect(&[0u8; MVP_PROOF_SIZE]), Some(ProofType::Signature));
+        #[cfg(not(feature = "mock-prover"))]
+        assert_eq!(ProofType::detect(&[0u8; MVP_PROOF_SIZE]), None);
+    }
+
+    #[test]
+    fn test_unshield_message_binds_relayer_and_fee() {
+        let nullifier = [1u8; 32];
+        let recipient = Pubkey::new_unique();
+        let relayer = Pubkey::new_unique();
+        let root = [3u8; 32];
+        let d = nullifier_domain(&Pubkey::new_unique(), &Pubkey::default());
+
+        let msg1 = build_unshield_message(&d, &nullifier, &recipient, 1000, &relayer, 3, 0, &root, 100);
+
+        let other_relayer = Pubkey::new_unique();
+        let msg2 = build_unshield_message(&d, &nullifier, &recipient, 1000, &other_relayer, 3, 0, &root, 100);
+        assert_ne!(msg1, msg2);
+
+        let msg3 = build_unshield_message(&d, &nullifier, &recipient, 1000, &relayer, 4, 0, &root, 100);
+        assert_ne!(msg1, msg3);
+
+        let msg4 = build_unshield_message(&d, &nullifier, &recipient, 1000, &relayer, 3, 0, &root, 101);
+        assert_ne!(msg1, msg4);
+    }
+
+    #[test]
+    fn test_canonical_field_elements() {
+        let mut below = SCALAR_FIELD_MODULUS;
+        below[31] -= 1;
+        assert!(is_canonical_field_element(&[0u8; 32]));
+        assert!(is_canonical_field_element(&below));
+        assert!(!is_canonical_field_element(&SCALAR_FIELD_MODULUS));
+        assert!(!is_canonical_field_element(&[0xffu8; 32]));
+
+        assert!(require_canonical_inputs(&[&[1u8; 32], &below]).is_ok());
+        assert!(require_canonical_inputs(&[&[1u8; 32], &SCALAR_FIELD_MODULUS]).is_err());
+    }
+
+    #[test]
+    fn test_non_canonical_nullifier_rejected_before_verification() {
+        let proof = [1u8; GROTH16_PROOF_SIZE];
+        let verifier = Verifier::Groth16(Groth16VerifyingKey {
+            alpha_g1: &[0u8; 64],
+            beta_g2: &[0u8; 128],
+            gamma_g2: &[0u8; 128],
+            delta_g2: &[0u8; 128],
+            ic: &[],
+        });
+
+        // r + 1 is the same field element as 1
+        let mut aliased = SCALAR_FIELD_MODULUS;
+        aliased[31] += 1;
+
+        let pool = Pubkey::new_unique();
+        let mint = Pubkey::default();
+        let err = verify_transfer_proof(
+            &verifier, &proof, &pool, &mint, &aliased, &[2u8; 32], &[3u8; 32], 100,
+        )
+        .unwrap_err();
+        assert_eq!(err, NyxError::NonCanonicalFieldElement.into());
+    }
+
+    #[test]
+    fn test_aggregated_withdrawals_hash() {
+        let withdrawals = [
+            AggregatedWithdrawal { nullifier: [1u8; 32], amount: 1000, fee: 3 },
+            AggregatedWithdrawal { nullifier: [2u8; 32], amount: 2000, fee: 5 },
+        ];
+        let recipients = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+        let hash = hash_aggregated_withdrawals(&withdrawals, &recipients);
+        assert!(is_canonical_field_element(&hash));
+        assert_eq!(hash, hash_aggregated_withdrawals(&withdrawals, &recipients));
+
+        // Swapping recipients changes the attested payouts
+        let swapped = [recipients[1], recipients[0]];
+        assert_ne!(hash, hash_aggregated_withdrawals(&withdrawals, &swapped));
+
+        // Dropping a withdrawal changes the hash too
+        assert_ne!(hash, hash_aggregated_withdrawals(&withdrawals[..1], &recipients[..1]));
     }
 
     #[test]
@@ -273,4 +1545,76 @@ mod tests {
         let proof_bytes = vec![0u8; 64]; // Too short
         assert!(MvpProof::from_bytes(&proof_bytes).is_none());
     }
+
+    #[test]
+    fn test_circuit_ids_cover_every_discriminator() {
+        for (i, circuit) in CircuitId::ALL.into_iter().enumerate() {
+            assert_eq!(circuit as usize, i);
+            assert_eq!(CircuitId::from_u8(i as u8), Some(circuit));
+        }
+        assert_eq!(CircuitId::from_u8(CircuitId::ALL.len() as u8), None);
+    }
+
+    #[test]
+    fn test_activation_requires_every_key() {
+        let pool = Pubkey::new_unique();
+        let keys: Vec<Pubkey> = CircuitId::ALL
+            .into_iter()
+            .map(|circuit| verifying_key_address(ProofSystem::Groth16, &pool, circuit, 2))
+            .collect();
+        let mut lamports = [1u64; 10];
+        let mut data = [[1u8; 8]; 10];
+        let accounts: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .map(|((key, lamports), data)| {
+                AccountInfo::new(key, false, false, lamports, data, &crate::ID, false, 0)
+            })
+            .collect();
+        assert!(require_verifying_keys(&pool, ProofSystem::Groth16, 2, &accounts).is_ok());
+
+        // Every circuit needs its key, at the proposed version
+        let err = require_verifying_keys(&pool, ProofSystem::Groth16, 2, &accounts[..9]);
+        assert_eq!(err.unwrap_err(), NyxError::MissingVerifyingKey.into());
+        let err = require_verifying_keys(&pool, ProofSystem::Groth16, 3, &accounts);
+        assert_eq!(err.unwrap_err(), NyxError::MissingVerifyingKey.into());
+        // and for the pool's proof system
+        let err = require_verifying_keys(&pool, ProofSystem::Plonk, 2, &accounts);
+        assert_eq!(err.unwrap_err(), NyxError::MissingVerifyingKey.into());
+
+        let mut reordered = accounts.clone();
+        reordered.swap(0, 1);
+        let err = require_verifying_keys(&pool, ProofSystem::Groth16, 2, &reordered);
+        assert_eq!(err.unwrap_err(), NyxError::MissingVerifyingKey.into());
+    }
+
+    #[test]
+    fn test_vk_timelock_change_is_timelocked() {
+        use crate::state::PrivacyPool;
+
+        let mut pool: Box<PrivacyPool> = Box::new(bytemuck::Zeroable::zeroed());
+        pool.vk_version = 1;
+        pool.vk_update_delay_slots = 100;
+        pool.vk_grace_period_slots = 50;
+
+        // Dropping the delay waits out the current one
+        pool.schedule_vk_timelock(0, 0, 1_000);
+        assert_eq!(pool.vk_timelock(1_099), (100, 50));
+        pool.schedule_vk_update(1_050).unwrap();
+        assert_eq!(pool.vk_activation_slot, 1_150);
+        assert_eq!(pool.vk_timelock(1_100), (0, 0));
+        pool.schedule_vk_update(1_100).unwrap();
+        assert_eq!(pool.vk_activation_slot, 1_100);
+
+        // So does cutting the grace period of the previous keys
+        let mut pool: Box<PrivacyPool> = Box::new(bytemuck::Zeroable::zeroed());
+        pool.vk_version = 2;
+        pool.vk_activated_at = 1_000;
+        pool.vk_update_delay_slots = 100;
+        pool.vk_grace_period_slots = 1_000;
+        pool.schedule_vk_timelock(100, 0, 1_000);
+        assert!(pool.is_vk_version_accepted(1, 1_099));
+        assert!(!pool.is_vk_version_accepted(1, 1_100));
+    }
 }
@@ -0,0 +1,73 @@
+//! HTTP API
+//!
+//! - `GET /health`: the key requests are sealed to, the circuits proven and
+//!   the proving slots free
+//! - `POST /prove`: a request sealed with `veil_sdk::remote`, answered with
+//!   its sealed proof as `application/octet-stream`; 503 when every proving
+//!   slot is busy, for the client to retry or go elsewhere
+//!
+//! Keys are hex. Requests and responses are sealed end to end, so TLS in
+//! front of the service only hides that a device asked for a proof.
+
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Serialize;
+use tokio::sync::Semaphore;
+
+use crate::error::{ProverError, Result};
+use crate::prover::Prover;
+
+/// State shared by the handlers
+#[derive(Clone)]
+pub struct AppState {
+    pub prover: Arc<Prover>,
+    /// One permit per proof generated at once
+    pub jobs: Arc<Semaphore>,
+    pub max_jobs: usize,
+}
+
+/// Routes of the proving service
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/prove", post(prove))
+        .with_state(state)
+}
+
+/// Body answering `GET /health`
+#[derive(Debug, Serialize)]
+pub struct Health {
+    pub public_key: String,
+    pub circuits: Vec<&'static str>,
+    pub max_jobs: usize,
+    pub free_jobs: usize,
+}
+
+async fn health(State(state): State<AppState>) -> Json<Health> {
+    Json(Health {
+        public_key: hex::encode(state.prover.public_key()),
+        circuits: state.prover.circuits(),
+        max_jobs: state.max_jobs,
+        free_jobs: state.jobs.available_permits(),
+    })
+}
+
+async fn prove(State(state): State<AppState>, body: Bytes) -> Result<impl IntoResponse> {
+    let permit = state.jobs.clone().try_acquire_owned().map_err(|_| ProverError::Busy)?;
+    let prover = state.prover.clone();
+    // The permit moves into the job, so a proof keeps its slot until done
+    // even if the client hangs up
+    let sealed = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        prover.prove(&body)
+    })
+    .await
+    .map_err(|err| ProverError::Internal(err.to_string()))??;
+    Ok(([(header::CONTENT_TYPE, "application/octet-stream")], sealed))
+}
@@ -0,0 +1,48 @@
+//! Prover configuration
+//!
+//! Read from `VEIL_PROVER_*` environment variables, each with a default fit
+//! for a local setup.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::error::{ProverError, Result};
+
+/// Settings of the proving service
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Address the HTTP server listens on (`VEIL_PROVER_BIND`)
+    pub bind: SocketAddr,
+    /// Directory of the proving keys written by `export-vks`
+    /// (`VEIL_PROVER_KEYS`)
+    pub keys_dir: PathBuf,
+    /// File of the key requests are sealed to, as hex; generated when
+    /// missing (`VEIL_PROVER_KEY`)
+    pub key_path: PathBuf,
+    /// Proofs generated at once; requests past it are turned away
+    /// (`VEIL_PROVER_MAX_JOBS`)
+    pub max_jobs: usize,
+}
+
+impl Config {
+    /// Read the configuration from the environment
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            bind: var("VEIL_PROVER_BIND", SocketAddr::from(([0, 0, 0, 0], 8081)))?,
+            keys_dir: var("VEIL_PROVER_KEYS", PathBuf::from("target/vks"))?,
+            key_path: var("VEIL_PROVER_KEY", PathBuf::from("prover.key"))?,
+            max_jobs: var("VEIL_PROVER_MAX_JOBS", 2)?,
+        })
+    }
+}
+
+/// Parse an environment variable, or fall back to `default` when unset
+fn var<T: FromStr>(name: &str, default: T) -> Result<T> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| ProverError::Config(format!("{name}: cannot parse {value:?}"))),
+        Err(_) => Ok(default),
+    }
+}
@@ -0,0 +1,73 @@
+//! Prover errors
+//!
+//! Errors are answered in the clear, so once a request is opened they say
+//! nothing of its contents: a request that can't be proven is answered
+//! with `Rejected` whatever the reason.
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use thiserror::Error;
+use veil_sdk::SdkError;
+
+/// Errors of the proving service
+#[derive(Error, Debug)]
+pub enum ProverError {
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
+    #[error("Invalid prover key file {0}")]
+    InvalidKey(String),
+
+    #[error("Proving key {0}: {1}")]
+    ProvingKey(String, String),
+
+    #[error("No proving keys in {0}")]
+    NoProvingKeys(String),
+
+    #[error("Request cannot be opened: {0}")]
+    Sealed(#[source] SdkError),
+
+    #[error("No {0} proving key")]
+    UnsupportedCircuit(&'static str),
+
+    #[error("Request cannot be proven")]
+    Rejected,
+
+    #[error("All proving slots are busy")]
+    Busy,
+
+    #[error("Proving failed: {0}")]
+    Internal(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl ProverError {
+    /// HTTP status the error is answered with
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::Sealed(_) => StatusCode::BAD_REQUEST,
+            Self::UnsupportedCircuit(_) => StatusCode::NOT_FOUND,
+            Self::Rejected => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Busy => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Config(_)
+            | Self::InvalidKey(_)
+            | Self::ProvingKey(..)
+            | Self::NoProvingKeys(_)
+            | Self::Internal(_)
+            | Self::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for ProverError {
+    fn into_response(self) -> Response {
+        (self.status(), Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+/// Result type for prover operations
+pub type Result<T> = std::result::Result<T, ProverError>;
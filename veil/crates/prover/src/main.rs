@@ -0,0 +1,73 @@
+//! Veil Prover
+//!
+//! Proving service for devices too weak to generate Groth16 proofs, such as
+//! phones. A wallet seals a transfer or withdrawal witness to the service's
+//! key with `veil_sdk::remote`; the service proves it and seals the proof
+//! back, so only the wallet and the service see either.
+//!
+//! The witness is the note's full opening, which is enough to spend it.
+//! Run your own prover, or use one you would trust with the funds.
+//!
+//! Usage: veil-prover   (with `transfer.pk` and `unshield.pk` from
+//! `export-vks` in `VEIL_PROVER_KEYS`)
+//!
+//! See `config` for the settings and `api` for the endpoints. Proofs come
+//! from the arkworks prover, multithreaded across the machine's cores. The
+//! circuits are arkworks R1CS, not circom, so rapidsnark and GPU provers
+//! can't run them as they stand.
+
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+
+mod api;
+mod config;
+mod error;
+mod prover;
+
+use api::AppState;
+use config::Config;
+use error::Result;
+use prover::Prover;
+
+async fn run(config: Config) -> Result<()> {
+    let prover = Arc::new(Prover::load(&config)?);
+    let bind = config.bind;
+    tracing::info!(
+        key = %hex::encode(prover.public_key()),
+        circuits = ?prover.circuits(),
+        %bind,
+        "prover listening"
+    );
+
+    let state = AppState {
+        prover,
+        jobs: Arc::new(Semaphore::new(config.max_jobs)),
+        max_jobs: config.max_jobs,
+    };
+    let listener = tokio::net::TcpListener::bind(bind).await?;
+    axum::serve(listener, api::router(state))
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt::init();
+
+    let result = match Config::from_env() {
+        Ok(config) => run(config).await,
+        Err(err) => Err(err),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
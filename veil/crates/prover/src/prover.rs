@@ -0,0 +1,157 @@
+//! Proof generation
+//!
+//! Opens sealed requests with the server's key, proves them with the
+//! circuit's proving key and seals the proof back to the requester.
+//! Proving blocks for seconds, so callers run it off the async runtime.
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, ErrorKind, Write};
+use std::path::Path;
+
+use ark_bn254::Bn254;
+use ark_groth16::ProvingKey;
+use ark_serialize::CanonicalDeserialize;
+use rand::rngs::OsRng;
+use veil_sdk::{ProofRequest, ProverKey};
+
+use crate::config::Config;
+use crate::error::{ProverError, Result};
+
+/// Circuits the service proves, by the name of their `export-vks` keys
+pub const CIRCUITS: [&str; 2] = ["transfer", "unshield"];
+
+/// The server's key and the proving keys of its circuits
+pub struct Prover {
+    key: ProverKey,
+    proving_keys: BTreeMap<&'static str, ProvingKey<Bn254>>,
+}
+
+impl Prover {
+    /// A prover of the circuits whose keys are in `config.keys_dir`
+    pub fn load(config: &Config) -> Result<Self> {
+        let mut proving_keys = BTreeMap::new();
+        for circuit in CIRCUITS {
+            let path = config.keys_dir.join(format!("{circuit}.pk"));
+            match File::open(&path) {
+                Ok(file) => {
+                    let pk = ProvingKey::deserialize_compressed(BufReader::new(file)).map_err(
+                        |err| ProverError::ProvingKey(path.display().to_string(), err.to_string()),
+                    )?;
+                    proving_keys.insert(circuit, pk);
+                }
+                Err(err) if err.kind() == ErrorKind::NotFound => {}
+                Err(err) => {
+                    return Err(ProverError::ProvingKey(
+                        path.display().to_string(),
+                        err.to_string(),
+                    ));
+                }
+            }
+        }
+        if proving_keys.is_empty() {
+            return Err(ProverError::NoProvingKeys(config.keys_dir.display().to_string()));
+        }
+        Ok(Self::new(load_key(&config.key_path)?, proving_keys))
+    }
+
+    /// A prover with the given keys
+    pub fn new(key: ProverKey, proving_keys: BTreeMap<&'static str, ProvingKey<Bn254>>) -> Self {
+        Self { key, proving_keys }
+    }
+
+    /// The public key requests are sealed to
+    pub fn public_key(&self) -> [u8; 32] {
+        self.key.public_key()
+    }
+
+    /// Names of the circuits with a proving key
+    pub fn circuits(&self) -> Vec<&'static str> {
+        self.proving_keys.keys().copied().collect()
+    }
+
+    /// Prove the sealed request `body`, returning the sealed response
+    pub fn prove(&self, body: &[u8]) -> Result<Vec<u8>> {
+        let (request, response_key) =
+            ProofRequest::open(&self.key, body).map_err(ProverError::Sealed)?;
+        let circuit = request.circuit();
+        let pk = self.proving_keys.get(circuit).ok_or(ProverError::UnsupportedCircuit(circuit))?;
+        let response = request.prove(pk, &mut OsRng).map_err(|_| ProverError::Rejected)?;
+        response_key.seal(&response).map_err(|err| ProverError::Internal(err.to_string()))
+    }
+}
+
+/// Read the server key at `path`, generating it when missing
+fn load_key(path: &Path) -> Result<ProverKey> {
+    match std::fs::read_to_string(path) {
+        Ok(hex_key) => hex::decode(hex_key.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(ProverKey::from_bytes)
+            .ok_or_else(|| ProverError::InvalidKey(path.display().to_string())),
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            let key = ProverKey::random(&mut OsRng);
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.mode(0o600);
+            }
+            options.open(path)?.write_all(hex::encode(key.to_bytes()).as_bytes())?;
+            tracing::info!(path = %path.display(), "generated prover key");
+            Ok(key)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::pubkey::Pubkey;
+    use veil_circuits::{setup, UnshieldCircuit};
+    use veil_sdk::{CommitmentTree, Note, ProofResponse, UnshieldParams};
+
+    #[test]
+    fn test_proves_sealed_requests() {
+        let (pk, _) = setup(UnshieldCircuit::blank()).unwrap();
+        let prover = Prover::new(ProverKey::random(&mut OsRng), [("unshield", pk)].into());
+        assert_eq!(prover.circuits(), ["unshield"]);
+
+        let note = Note::random(1_000, &Pubkey::default(), &mut OsRng);
+        let tree = CommitmentTree::from_leaves(&[note.commitment_bytes()]).unwrap();
+        let params = UnshieldParams {
+            recipient: Pubkey::new_unique(),
+            relayer: Pubkey::new_unique(),
+            fee: 10,
+            refund: 0,
+            expiry_slot: 100,
+        };
+        let request = ProofRequest::Unshield {
+            spend: tree.spend(note.clone()).unwrap(),
+            pool: Pubkey::new_unique(),
+            mint: Pubkey::default(),
+            params,
+        };
+        let (body, response_key) = request.seal(&prover.public_key(), &mut OsRng).unwrap();
+        let ProofResponse::Unshield(unshield) =
+            response_key.open(&prover.prove(&body).unwrap()).unwrap()
+        else {
+            panic!("expected a withdrawal proof");
+        };
+        assert_eq!(unshield.proof.len(), 257);
+        assert_eq!((unshield.amount, unshield.fee), (1_000, 10));
+
+        let spend = tree.spend(note).unwrap();
+        let transfer = ProofRequest::Transfer {
+            spend,
+            pool: Pubkey::new_unique(),
+            mint: Pubkey::default(),
+            expiry_slot: 100,
+        };
+        let (body, _) = transfer.seal(&prover.public_key(), &mut OsRng).unwrap();
+        assert!(matches!(prover.prove(&body), Err(ProverError::UnsupportedCircuit("transfer"))));
+        assert!(matches!(prover.prove(&body[1..]), Err(ProverError::Sealed(_))));
+    }
+}
@@ -0,0 +1,250 @@
+//! HTTP API
+//!
+//! - `GET /health`: the relayer's pubkey, balance and the cluster's slot;
+//!   503 when the RPC is unreachable or the balance is too low to pay for
+//!   withdrawals
+//! - `GET /fee`: the relayer's fee terms
+//! - `POST /withdraw`: queue a withdrawal, answering with its job; 202 when
+//!   queued, 200 when its nullifier was already queued or relayed. Setting
+//!   `max_delay_secs` delays its broadcast by a random time up to that,
+//!   see `delay`
+//! - `GET /jobs/{nullifier}`: a job's status, and its signature once it
+//!   landed
+//!
+//! Pubkeys are base58, and hashes and proofs hex, as wallets display them.
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::delay;
+use crate::error::{RelayerError, Result};
+use crate::fees::FeeQuote;
+use crate::queue::{now_ms, Job, JobQueue, JobStatus};
+use crate::relay::{Relayer, Withdrawal};
+
+/// State shared by the handlers
+#[derive(Clone)]
+pub struct AppState {
+    pub relayer: Arc<Relayer>,
+    pub queue: Arc<JobQueue>,
+}
+
+/// Routes of the relayer service
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/fee", get(fee))
+        .route("/withdraw", post(withdraw))
+        .route("/jobs/:id", get(job))
+        .with_state(state)
+}
+
+/// Body of `POST /withdraw`
+#[derive(Debug, Deserialize)]
+pub struct WithdrawalRequest {
+    pub pool: String,
+    /// Mint of an SPL pool; omitted for a SOL pool
+    #[serde(default)]
+    pub mint: Option<String>,
+    pub vk_version: u32,
+    pub treasury: String,
+    pub recipient: String,
+    #[serde(default)]
+    pub tree_epoch: Option<String>,
+    pub nullifier: String,
+    pub root: String,
+    pub amount: u64,
+    pub fee: u64,
+    #[serde(default)]
+    pub refund: u64,
+    pub expiry_slot: u64,
+    pub proof: String,
+    /// Longest random delay before broadcasting, in seconds (0 = none)
+    #[serde(default)]
+    pub max_delay_secs: u64,
+}
+
+impl TryFrom<WithdrawalRequest> for Withdrawal {
+    type Error = RelayerError;
+
+    fn try_from(request: WithdrawalRequest) -> Result<Self> {
+        Ok(Self {
+            pool: pubkey("pool", &request.pool)?,
+            mint: request.mint.as_deref().map(|mint| pubkey("mint", mint)).transpose()?,
+            vk_version: request.vk_version,
+            treasury: pubkey("treasury", &request.treasury)?,
+            recipient: pubkey("recipient", &request.recipient)?,
+            tree_epoch: request
+                .tree_epoch
+                .as_deref()
+                .map(|tree_epoch| pubkey("tree_epoch", tree_epoch))
+                .transpose()?,
+            nullifier: bytes32("nullifier", &request.nullifier)?,
+            root: bytes32("root", &request.root)?,
+            amount: request.amount,
+            fee: request.fee,
+            refund: request.refund,
+            expiry_slot: request.expiry_slot,
+            proof: hex::decode(&request.proof)
+                .map_err(|err| RelayerError::InvalidRequest(format!("proof: {err}")))?,
+        })
+    }
+}
+
+/// Body answering `POST /withdraw` and `GET /jobs/{nullifier}`
+#[derive(Debug, Serialize)]
+pub struct JobResponse {
+    pub id: String,
+    pub status: JobStatus,
+    pub attempts: u32,
+    /// Milliseconds since the Unix epoch the next attempt is due at
+    pub next_attempt_at: u64,
+    pub signature: Option<String>,
+    pub error: Option<String>,
+}
+
+impl From<&Job> for JobResponse {
+    fn from(job: &Job) -> Self {
+        Self {
+            id: job.id(),
+            status: job.status,
+            attempts: job.attempts,
+            next_attempt_at: job.next_attempt_at,
+            signature: job.signature.map(|signature| signature.to_string()),
+            error: job.error.clone(),
+        }
+    }
+}
+
+/// Body answering `GET /health`
+#[derive(Debug, Serialize)]
+pub struct Health {
+    pub healthy: bool,
+    pub relayer: String,
+    pub balance: Option<u64>,
+    pub slot: Option<u64>,
+}
+
+async fn health(State(state): State<AppState>) -> (StatusCode, Json<Health>) {
+    let relayer = &state.relayer;
+    let balance = relayer.balance().await.ok();
+    let slot = relayer.slot().await.ok();
+    let healthy = slot.is_some()
+        && balance.is_some_and(|balance| balance >= relayer.config().min_balance);
+    let status = if healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+
+    let health = Health { healthy, relayer: relayer.pubkey().to_string(), balance, slot };
+    (status, Json(health))
+}
+
+async fn fee(State(state): State<AppState>) -> Json<FeeQuote> {
+    let relayer = &state.relayer;
+    let config = relayer.config();
+    Json(FeeQuote {
+        relayer: relayer.pubkey().to_string(),
+        fee_bps: config.fee_bps,
+        min_fee: config.min_fee,
+        min_withdrawal: config.min_withdrawal,
+    })
+}
+
+async fn withdraw(
+    State(state): State<AppState>,
+    Json(request): Json<WithdrawalRequest>,
+) -> Result<(StatusCode, Json<JobResponse>)> {
+    let requested_delay_secs = request.max_delay_secs;
+    let withdrawal = Withdrawal::try_from(request)?;
+    // Answer for a nullifier already queued or relayed even once its proof
+    // has expired
+    if let Some(job) = state.queue.get(&withdrawal.nullifier)? {
+        if job.status != JobStatus::Failed {
+            return Ok((StatusCode::OK, Json(JobResponse::from(&job))));
+        }
+    }
+    state.relayer.check(&withdrawal).await?;
+
+    let delay_ms = if requested_delay_secs > 0 {
+        let slots_left = withdrawal.expiry_slot.saturating_sub(state.relayer.slot().await?);
+        let max_ms = delay::max_delay_ms(
+            requested_delay_secs,
+            state.relayer.config().max_delay_secs,
+            slots_left,
+        );
+        delay::draw(max_ms, &mut rand::thread_rng())
+    } else {
+        0
+    };
+
+    let (job, queued) = state.queue.push(withdrawal, now_ms(), delay_ms)?;
+    let status = if queued { StatusCode::ACCEPTED } else { StatusCode::OK };
+    if queued {
+        tracing::info!(id = %job.id(), pool = %job.withdrawal.pool, "withdrawal queued");
+    }
+    Ok((status, Json(JobResponse::from(&job))))
+}
+
+async fn job(State(state): State<AppState>, Path(id): Path<String>) -> Result<Json<JobResponse>> {
+    let nullifier = bytes32("id", &id)?;
+    let job = state.queue.get(&nullifier)?.ok_or(RelayerError::JobNotFound(id))?;
+    Ok(Json(JobResponse::from(&job)))
+}
+
+fn pubkey(field: &str, value: &str) -> Result<Pubkey> {
+    Pubkey::from_str(value)
+        .map_err(|err| RelayerError::InvalidRequest(format!("{field}: {err}")))
+}
+
+fn bytes32(field: &str, value: &str) -> Result<[u8; 32]> {
+    hex::decode(value)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| RelayerError::InvalidRequest(format!("{field}: expected 32 hex bytes")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(nullifier: &str) -> serde_json::Value {
+        json!({
+            "pool": Pubkey::new_unique().to_string(),
+            "vk_version": 1,
+            "treasury": Pubkey::new_unique().to_string(),
+            "recipient": Pubkey::new_unique().to_string(),
+            "nullifier": nullifier,
+            "root": hex::encode([2u8; 32]),
+            "amount": 1_000_000_000u64,
+            "fee": 3_000_000u64,
+            "expiry_slot": 100,
+            "proof": hex::encode([7u8; 256]),
+        })
+    }
+
+    #[test]
+    fn test_withdrawal_from_request() {
+        let request: WithdrawalRequest =
+            serde_json::from_value(request(&hex::encode([1u8; 32]))).unwrap();
+        let withdrawal = Withdrawal::try_from(request).unwrap();
+        assert!(withdrawal.is_sol());
+        assert_eq!(withdrawal.nullifier, [1u8; 32]);
+        assert_eq!(withdrawal.refund, 0);
+        assert_eq!(withdrawal.proof.len(), 256);
+    }
+
+    #[test]
+    fn test_malformed_request_is_rejected() {
+        let request: WithdrawalRequest = serde_json::from_value(request("abcd")).unwrap();
+        assert!(matches!(
+            Withdrawal::try_from(request),
+            Err(RelayerError::InvalidRequest(_))
+        ));
+    }
+}
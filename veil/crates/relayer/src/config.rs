@@ -0,0 +1,89 @@
+//! Relayer configuration
+//!
+//! Read from `VEIL_RELAYER_*` environment variables, every one but the
+//! keypair path having a default fit for a local validator.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::error::{RelayerError, Result};
+
+/// Settings of the relayer service
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Address the HTTP server listens on (`VEIL_RELAYER_BIND`)
+    pub bind: SocketAddr,
+    /// Solana JSON-RPC endpoint (`VEIL_RELAYER_RPC_URL`)
+    pub rpc_url: String,
+    /// Keypair signing and paying for withdrawals (`VEIL_RELAYER_KEYPAIR`)
+    pub keypair_path: PathBuf,
+    /// Fee charged, in basis points of the withdrawal (`VEIL_RELAYER_FEE_BPS`)
+    pub fee_bps: u16,
+    /// Smallest fee in lamports for SOL withdrawals, covering the
+    /// transaction's cost (`VEIL_RELAYER_MIN_FEE`)
+    pub min_fee: u64,
+    /// Smallest withdrawal accepted, in the pool's units
+    /// (`VEIL_RELAYER_MIN_WITHDRAWAL`)
+    pub min_withdrawal: u64,
+    /// Compute units requested per withdrawal (`VEIL_RELAYER_COMPUTE_UNITS`)
+    pub compute_unit_limit: u32,
+    /// Lowest priority fee paid, in micro-lamports per compute unit
+    /// (`VEIL_RELAYER_MIN_PRIORITY_FEE`)
+    pub min_priority_fee: u64,
+    /// Highest priority fee paid, in micro-lamports per compute unit
+    /// (`VEIL_RELAYER_MAX_PRIORITY_FEE`)
+    pub max_priority_fee: u64,
+    /// Resubmissions of a withdrawal that failed to land
+    /// (`VEIL_RELAYER_MAX_RETRIES`)
+    pub max_retries: u32,
+    /// Directory of the job queue's database (`VEIL_RELAYER_DB`)
+    pub db_path: PathBuf,
+    /// Withdrawals submitted at once for one pool
+    /// (`VEIL_RELAYER_MAX_JOBS_PER_POOL`)
+    pub max_jobs_per_pool: usize,
+    /// Withdrawals submitted at once across all pools
+    /// (`VEIL_RELAYER_MAX_JOBS`)
+    pub max_jobs: usize,
+    /// Longest broadcast delay a wallet may ask for, in seconds
+    /// (`VEIL_RELAYER_MAX_DELAY_SECS`, 0 = no delays)
+    pub max_delay_secs: u64,
+    /// Balance in lamports below which the relayer reports itself unhealthy
+    /// (`VEIL_RELAYER_MIN_BALANCE`)
+    pub min_balance: u64,
+}
+
+impl Config {
+    /// Read the configuration from the environment
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            bind: var("VEIL_RELAYER_BIND", SocketAddr::from(([0, 0, 0, 0], 8080)))?,
+            rpc_url: var("VEIL_RELAYER_RPC_URL", "http://127.0.0.1:8899".to_string())?,
+            keypair_path: std::env::var("VEIL_RELAYER_KEYPAIR")
+                .map(PathBuf::from)
+                .map_err(|_| RelayerError::Config("VEIL_RELAYER_KEYPAIR is not set".into()))?,
+            fee_bps: var("VEIL_RELAYER_FEE_BPS", 30)?,
+            min_fee: var("VEIL_RELAYER_MIN_FEE", 10_000)?,
+            min_withdrawal: var("VEIL_RELAYER_MIN_WITHDRAWAL", 0)?,
+            compute_unit_limit: var("VEIL_RELAYER_COMPUTE_UNITS", 400_000)?,
+            min_priority_fee: var("VEIL_RELAYER_MIN_PRIORITY_FEE", 0)?,
+            max_priority_fee: var("VEIL_RELAYER_MAX_PRIORITY_FEE", 100_000)?,
+            max_retries: var("VEIL_RELAYER_MAX_RETRIES", 5)?,
+            db_path: var("VEIL_RELAYER_DB", PathBuf::from("relayer-db"))?,
+            max_jobs_per_pool: var("VEIL_RELAYER_MAX_JOBS_PER_POOL", 2)?,
+            max_jobs: var("VEIL_RELAYER_MAX_JOBS", 16)?,
+            max_delay_secs: var("VEIL_RELAYER_MAX_DELAY_SECS", 600)?,
+            min_balance: var("VEIL_RELAYER_MIN_BALANCE", 100_000_000)?,
+        })
+    }
+}
+
+/// Parse an environment variable, or fall back to `default` when unset
+fn var<T: FromStr>(name: &str, default: T) -> Result<T> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| RelayerError::Config(format!("{name}: cannot parse {value:?}"))),
+        Err(_) => Ok(default),
+    }
+}
@@ -0,0 +1,47 @@
+//! Broadcast delay
+//!
+//! A withdrawal sent as soon as it is asked for lands seconds after the
+//! wallet contacted the relayer, linking the two for anyone who sees both.
+//! Wallets may opt into a random delay of up to `max_delay_secs`, capped
+//! by the relayer's `max_delay_secs` and by half the time left before the
+//! proof expires, so delayed withdrawals still land in time.
+
+use rand::Rng;
+
+/// Slot time assumed when converting slots left to time, in milliseconds
+const SLOT_MS: u64 = 400;
+
+/// Longest delay a withdrawal may be given, in milliseconds
+pub fn max_delay_ms(requested_secs: u64, config_max_secs: u64, slots_left: u64) -> u64 {
+    requested_secs
+        .min(config_max_secs)
+        .saturating_mul(1_000)
+        .min(slots_left.saturating_mul(SLOT_MS) / 2)
+}
+
+/// A delay drawn uniformly from `0..=max_ms`
+pub fn draw(max_ms: u64, rng: &mut impl Rng) -> u64 {
+    rng.gen_range(0..=max_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_delay() {
+        // Capped by the relayer
+        assert_eq!(max_delay_ms(3_600, 600, u64::MAX), 600_000);
+        // And by half the time left before the proof expires
+        assert_eq!(max_delay_ms(600, 600, 1_000), 200_000);
+        // Wallets that don't ask aren't delayed
+        assert_eq!(max_delay_ms(0, 600, 1_000), 0);
+    }
+
+    #[test]
+    fn test_draw_stays_in_window() {
+        let mut rng = rand::thread_rng();
+        assert_eq!(draw(0, &mut rng), 0);
+        assert!((0..100).all(|_| draw(5_000, &mut rng) <= 5_000));
+    }
+}
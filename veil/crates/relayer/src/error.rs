@@ -0,0 +1,102 @@
+//! Relayer errors
+//!
+//! Errors a request caused are answered with a 4xx status, so wallets can
+//! tell a withdrawal to fix from a relayer to retry later or replace.
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use solana_client::client_error::ClientError;
+use thiserror::Error;
+
+/// Errors of the relayer service
+#[derive(Error, Debug)]
+pub enum RelayerError {
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Fee {offered} is below the relayer's fee of {required}")]
+    FeeTooLow { offered: u64, required: u64 },
+
+    #[error("Fee {offered} is above the relayer's quote of {quoted}")]
+    FeeAboveQuote { offered: u64, quoted: u64 },
+
+    #[error("Amount {0} is below the relayer's minimum withdrawal")]
+    AmountTooLow(u64),
+
+    #[error("Proof expired at slot {0}")]
+    Expired(u64),
+
+    #[error("Simulation failed: {0}")]
+    SimulationFailed(String),
+
+    #[error("Transaction failed: {0}")]
+    TransactionFailed(String),
+
+    /// Boxed: the client error is large enough that every `Result` would
+    /// carry its size
+    #[error("RPC error: {0}")]
+    Rpc(Box<ClientError>),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Database error: {0}")]
+    Database(String),
+
+    #[error("No job for nullifier {0}")]
+    JobNotFound(String),
+}
+
+impl RelayerError {
+    /// HTTP status the error is answered with
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::InvalidRequest(_)
+            | Self::FeeTooLow { .. }
+            | Self::FeeAboveQuote { .. }
+            | Self::AmountTooLow(_)
+            | Self::Expired(_)
+            | Self::SimulationFailed(_) => StatusCode::BAD_REQUEST,
+            Self::TransactionFailed(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::JobNotFound(_) => StatusCode::NOT_FOUND,
+            Self::Rpc(_) => StatusCode::BAD_GATEWAY,
+            Self::Config(_) | Self::Io(_) | Self::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Whether retrying may succeed: the RPC or network failed, not the
+    /// withdrawal
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Rpc(_) | Self::Io(_))
+    }
+}
+
+impl From<ClientError> for RelayerError {
+    fn from(err: ClientError) -> Self {
+        Self::Rpc(Box::new(err))
+    }
+}
+
+impl From<sled::Error> for RelayerError {
+    fn from(err: sled::Error) -> Self {
+        Self::Database(err.to_string())
+    }
+}
+
+impl IntoResponse for RelayerError {
+    fn into_response(self) -> Response {
+        (self.status(), Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+// Every queue and handler `Result` carries the error's size: keep it
+// under clippy's `result_large_err` limit by boxing large sources
+const _: () = assert!(std::mem::size_of::<RelayerError>() <= 128);
+
+/// Result type for relayer operations
+pub type Result<T> = std::result::Result<T, RelayerError>;
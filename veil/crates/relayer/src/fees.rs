@@ -0,0 +1,127 @@
+//! Fee policy
+//!
+//! A withdrawal pays the relayer the fee committed to in its proof, so the
+//! relayer only accepts withdrawals whose fee is exactly its quote: the
+//! rate, plus `min_fee` for SOL withdrawals to cover the transaction. SPL
+//! fees are in the mint's units and are only held to the rate. Relaying a
+//! fee above the quote would leave the relayer's bond open to a
+//! `dispute_relayer_fee`.
+
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::error::{RelayerError, Result};
+
+/// Fee a withdrawal of `amount` owes the relayer
+pub fn required_fee(config: &Config, amount: u64, is_sol: bool) -> u64 {
+    let fee = (amount as u128 * config.fee_bps as u128 / 10_000) as u64;
+    if is_sol {
+        fee.max(config.min_fee)
+    } else {
+        fee
+    }
+}
+
+/// Fail unless a withdrawal of `amount` paying `fee` is worth relaying
+pub fn check_withdrawal(config: &Config, amount: u64, fee: u64, is_sol: bool) -> Result<()> {
+    if amount < config.min_withdrawal.max(1) {
+        return Err(RelayerError::AmountTooLow(amount));
+    }
+    let required = required_fee(config, amount, is_sol);
+    if fee < required {
+        return Err(RelayerError::FeeTooLow { offered: fee, required });
+    }
+    if fee > required {
+        return Err(RelayerError::FeeAboveQuote { offered: fee, quoted: required });
+    }
+    if fee > amount {
+        return Err(RelayerError::InvalidRequest("fee exceeds the amount".into()));
+    }
+    Ok(())
+}
+
+/// Priority fee to pay, in micro-lamports per compute unit
+///
+/// The 75th percentile of the recent fees paid for the pool's account,
+/// clamped to the configured range, so withdrawals land during congestion
+/// without overpaying when the pool is quiet.
+pub fn priority_fee(recent: &[u64], min: u64, max: u64) -> u64 {
+    let mut recent = recent.to_vec();
+    recent.sort_unstable();
+    let percentile = recent.get(recent.len() * 3 / 4).copied().unwrap_or(0);
+    percentile.clamp(min, max.max(min))
+}
+
+/// Terms the relayer currently offers, served by `GET /fee`
+#[derive(Debug, Serialize)]
+pub struct FeeQuote {
+    /// The relayer's pubkey, which withdrawal proofs must name
+    pub relayer: String,
+    /// Fee rate, in basis points of the withdrawal
+    pub fee_bps: u16,
+    /// Smallest fee in lamports for SOL withdrawals
+    pub min_fee: u64,
+    /// Smallest withdrawal accepted
+    pub min_withdrawal: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            bind: ([127, 0, 0, 1], 0).into(),
+            rpc_url: String::new(),
+            keypair_path: Default::default(),
+            fee_bps: 30,
+            min_fee: 10_000,
+            min_withdrawal: 1_000,
+            compute_unit_limit: 400_000,
+            min_priority_fee: 0,
+            max_priority_fee: 100_000,
+            max_retries: 3,
+            db_path: Default::default(),
+            max_jobs_per_pool: 2,
+            max_jobs: 16,
+            max_delay_secs: 600,
+            min_balance: 0,
+        }
+    }
+
+    #[test]
+    fn test_required_fee() {
+        let config = config();
+        // 0.3% of 1 SOL
+        assert_eq!(required_fee(&config, 1_000_000_000, true), 3_000_000);
+        // Small SOL withdrawals still cover the transaction
+        assert_eq!(required_fee(&config, 1_000_000, true), 10_000);
+        assert_eq!(required_fee(&config, 1_000_000, false), 3_000);
+    }
+
+    #[test]
+    fn test_check_withdrawal() {
+        let config = config();
+        assert!(check_withdrawal(&config, 1_000_000_000, 3_000_000, true).is_ok());
+        assert!(matches!(
+            check_withdrawal(&config, 1_000_000_000, 2_999_999, true),
+            Err(RelayerError::FeeTooLow { required: 3_000_000, .. })
+        ));
+        assert!(matches!(
+            check_withdrawal(&config, 1_000_000_000, 3_000_001, true),
+            Err(RelayerError::FeeAboveQuote { quoted: 3_000_000, .. })
+        ));
+        assert!(matches!(
+            check_withdrawal(&config, 999, 999, false),
+            Err(RelayerError::AmountTooLow(999))
+        ));
+        assert!(check_withdrawal(&config, 5_000, 10_000, true).is_err());
+    }
+
+    #[test]
+    fn test_priority_fee() {
+        assert_eq!(priority_fee(&[], 100, 1_000), 100);
+        assert_eq!(priority_fee(&[40, 10, 30, 20], 0, 1_000), 40);
+        assert_eq!(priority_fee(&[5_000; 8], 0, 1_000), 1_000);
+    }
+}
@@ -0,0 +1,64 @@
+//! Veil Relayer
+//!
+//! Reference relayer for Veil privacy pools. Wallets send it a withdrawal's
+//! proof and public inputs; it signs and pays for the transaction, so the
+//! recipient never needs SOL and the withdrawal isn't linked to the note
+//! owner's wallet. The withdrawal's fee pays the relayer back.
+//!
+//! Usage: VEIL_RELAYER_KEYPAIR=<path> veil-relayer
+//!
+//! See `config` for the other settings and `api` for the endpoints. Proofs
+//! must name the relayer's pubkey, served by `GET /fee`, and verify with
+//! the pool's Groth16 withdraw key. Accepted withdrawals wait in the
+//! persistent `queue` until a `worker` submits them.
+
+use std::process::ExitCode;
+use std::sync::Arc;
+
+mod api;
+mod config;
+mod delay;
+mod error;
+mod fees;
+mod queue;
+mod relay;
+mod worker;
+
+use api::AppState;
+use config::Config;
+use error::Result;
+use queue::JobQueue;
+use relay::Relayer;
+
+async fn run(config: Config) -> Result<()> {
+    let bind = config.bind;
+    let queue = Arc::new(JobQueue::open(&config.db_path)?);
+    let relayer = Arc::new(Relayer::new(config).await?);
+    tokio::spawn(worker::run(queue.clone(), relayer.clone()));
+    tracing::info!(relayer = %relayer.pubkey(), %bind, "relayer listening");
+
+    let listener = tokio::net::TcpListener::bind(bind).await?;
+    axum::serve(listener, api::router(AppState { relayer, queue }))
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt::init();
+
+    let result = match Config::from_env() {
+        Ok(config) => run(config).await,
+        Err(err) => Err(err),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
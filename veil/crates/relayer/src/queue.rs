@@ -0,0 +1,290 @@
+//! Persistent job queue
+//!
+//! Withdrawals are stored in a sled database keyed by their nullifier
+//! before they are acknowledged, so a crash or an RPC outage can't drop
+//! them, and a withdrawal whose nullifier is already queued or relayed is
+//! answered with the existing job instead of being submitted twice. A job
+//! records every signature it is sent under before sending it, and is only
+//! resubmitted once none of them landed.
+//!
+//! Ready jobs are taken by priority, the fee rate the withdrawal pays, then
+//! age. Jobs that fail to land are retried with exponential backoff until
+//! `max_retries` resubmissions are used up.
+
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::signature::Signature;
+
+use crate::error::{RelayerError, Result};
+use crate::relay::Withdrawal;
+
+/// Delay before the first retry, in milliseconds
+const RETRY_BASE_DELAY_MS: u64 = 500;
+
+/// Longest delay between retries, in milliseconds
+const MAX_RETRY_DELAY_MS: u64 = 60_000;
+
+/// Milliseconds since the Unix epoch
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Delay before the retry following `attempts` failed attempts
+fn backoff_ms(attempts: u32) -> u64 {
+    RETRY_BASE_DELAY_MS
+        .saturating_mul(1 << attempts.saturating_sub(1).min(16))
+        .min(MAX_RETRY_DELAY_MS)
+}
+
+/// Fee rate a withdrawal pays, in basis points of its amount
+pub fn priority(withdrawal: &Withdrawal) -> u64 {
+    (withdrawal.fee as u128 * 10_000 / withdrawal.amount.max(1) as u128) as u64
+}
+
+/// Where a job stands
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Waiting for its next attempt
+    Pending,
+    /// Landed on chain
+    Confirmed,
+    /// Rejected, expired or out of retries
+    Failed,
+}
+
+/// A queued withdrawal
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Job {
+    pub withdrawal: Withdrawal,
+    pub status: JobStatus,
+    /// Taken first when higher; see `priority`
+    pub priority: u64,
+    /// Attempts that failed to land
+    pub attempts: u32,
+    /// Milliseconds since the Unix epoch the job was queued at
+    pub created_at: u64,
+    /// Milliseconds since the Unix epoch the next attempt is due at
+    pub next_attempt_at: u64,
+    /// Every signature the withdrawal was sent under
+    pub signatures: Vec<Signature>,
+    /// Signature that landed
+    pub signature: Option<Signature>,
+    /// Why the last attempt failed
+    pub error: Option<String>,
+}
+
+impl Job {
+    /// A job due right away
+    pub fn new(withdrawal: Withdrawal, now: u64) -> Self {
+        Self {
+            priority: priority(&withdrawal),
+            withdrawal,
+            status: JobStatus::Pending,
+            attempts: 0,
+            created_at: now,
+            next_attempt_at: now,
+            signatures: Vec::new(),
+            signature: None,
+            error: None,
+        }
+    }
+
+    /// The job's ID, its nullifier in hex
+    pub fn id(&self) -> String {
+        hex::encode(self.withdrawal.nullifier)
+    }
+
+    /// Whether the job is due for an attempt at `now`
+    pub fn is_ready(&self, now: u64) -> bool {
+        self.status == JobStatus::Pending && self.next_attempt_at <= now
+    }
+
+    /// Record that the withdrawal landed under `signature`
+    pub fn confirm(&mut self, signature: Signature) {
+        self.status = JobStatus::Confirmed;
+        self.signature = Some(signature);
+        self.error = None;
+    }
+
+    /// Record that the withdrawal can't succeed
+    pub fn fail(&mut self, error: String) {
+        self.status = JobStatus::Failed;
+        self.error = Some(error);
+    }
+
+    /// Schedule a retry after a failure to land, or fail the job once
+    /// `max_retries` retries are used up
+    pub fn retry(&mut self, error: String, now: u64, max_retries: u32) {
+        self.attempts += 1;
+        if self.attempts > max_retries {
+            self.fail(error);
+        } else {
+            self.next_attempt_at = now.saturating_add(backoff_ms(self.attempts));
+            self.error = Some(error);
+        }
+    }
+}
+
+/// Jobs stored by nullifier
+pub struct JobQueue {
+    jobs: sled::Tree,
+    /// Serializes pushes, so two requests for a nullifier can't both queue it
+    push_lock: Mutex<()>,
+}
+
+impl JobQueue {
+    /// Open the queue's database at `path`, creating it if needed
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::from_db(sled::open(path)?)
+    }
+
+    fn from_db(db: sled::Db) -> Result<Self> {
+        Ok(Self { jobs: db.open_tree("jobs")?, push_lock: Mutex::new(()) })
+    }
+
+    /// Queue a withdrawal due `delay_ms` from `now`, unless its nullifier
+    /// is pending or confirmed
+    ///
+    /// Returns the job holding the nullifier and whether it is new. A
+    /// failed job is replaced, keeping its signatures in case one lands.
+    pub fn push(&self, withdrawal: Withdrawal, now: u64, delay_ms: u64) -> Result<(Job, bool)> {
+        let _guard = self.push_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut job = Job::new(withdrawal, now);
+        job.next_attempt_at = now.saturating_add(delay_ms);
+        if let Some(existing) = self.get(&job.withdrawal.nullifier)? {
+            if existing.status != JobStatus::Failed {
+                return Ok((existing, false));
+            }
+            job.signatures = existing.signatures;
+        }
+        self.put(&job)?;
+        Ok((job, true))
+    }
+
+    /// The job holding `nullifier`
+    pub fn get(&self, nullifier: &[u8; 32]) -> Result<Option<Job>> {
+        self.jobs.get(nullifier)?.map(|value| decode(&value)).transpose()
+    }
+
+    /// Store a job, durably before returning
+    pub fn put(&self, job: &Job) -> Result<()> {
+        let value = serde_json::to_vec(job).map_err(|err| RelayerError::Database(err.to_string()))?;
+        self.jobs.insert(job.withdrawal.nullifier, value)?;
+        self.jobs.flush()?;
+        Ok(())
+    }
+
+    /// Jobs due at `now`, highest priority then oldest first
+    pub fn ready(&self, now: u64) -> Result<Vec<Job>> {
+        let mut jobs = Vec::new();
+        for entry in self.jobs.iter() {
+            let (_, value) = entry?;
+            let job = decode(&value)?;
+            if job.is_ready(now) {
+                jobs.push(job);
+            }
+        }
+        jobs.sort_by_key(|job| (std::cmp::Reverse(job.priority), job.created_at));
+        Ok(jobs)
+    }
+}
+
+fn decode(value: &[u8]) -> Result<Job> {
+    serde_json::from_slice(value).map_err(|err| RelayerError::Database(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn queue() -> JobQueue {
+        JobQueue::from_db(sled::Config::new().temporary(true).open().unwrap()).unwrap()
+    }
+
+    fn withdrawal(nullifier: u8, fee: u64) -> Withdrawal {
+        Withdrawal {
+            pool: Pubkey::new_unique(),
+            mint: None,
+            vk_version: 1,
+            treasury: Pubkey::new_unique(),
+            recipient: Pubkey::new_unique(),
+            tree_epoch: None,
+            nullifier: [nullifier; 32],
+            root: [2u8; 32],
+            amount: 1_000_000_000,
+            fee,
+            refund: 0,
+            expiry_slot: 100,
+            proof: vec![7; 256],
+        }
+    }
+
+    #[test]
+    fn test_nullifier_is_queued_once() {
+        let queue = queue();
+        let (job, queued) = queue.push(withdrawal(1, 3_000_000), 0, 0).unwrap();
+        assert!(queued);
+
+        // A second request for the nullifier gets the existing job
+        let (existing, queued) = queue.push(withdrawal(1, 9_000_000), 5, 0).unwrap();
+        assert!(!queued);
+        assert_eq!(existing.withdrawal, job.withdrawal);
+
+        // Until it fails, when it may be queued again with its signatures
+        let mut failed = existing;
+        failed.signatures.push(Signature::new_unique());
+        failed.fail("expired".into());
+        queue.put(&failed).unwrap();
+        let (retried, queued) = queue.push(withdrawal(1, 9_000_000), 10, 0).unwrap();
+        assert!(queued);
+        assert_eq!(retried.status, JobStatus::Pending);
+        assert_eq!(retried.signatures, failed.signatures);
+    }
+
+    #[test]
+    fn test_ready_jobs_by_priority_then_age() {
+        let queue = queue();
+        queue.push(withdrawal(1, 3_000_000), 0, 0).unwrap();
+        queue.push(withdrawal(2, 5_000_000), 1, 0).unwrap();
+        queue.push(withdrawal(3, 3_000_000), 2, 0).unwrap();
+        let (mut later, _) = queue.push(withdrawal(4, 9_000_000), 3, 0).unwrap();
+        later.retry("timeout".into(), 3, 5);
+        queue.put(&later).unwrap();
+
+        let ready: Vec<u8> =
+            queue.ready(10).unwrap().iter().map(|job| job.withdrawal.nullifier[0]).collect();
+        assert_eq!(ready, vec![2, 1, 3]);
+        assert_eq!(queue.ready(10_000).unwrap()[0].withdrawal.nullifier[0], 4);
+    }
+
+    #[test]
+    fn test_delayed_job_waits() {
+        let queue = queue();
+        let (job, _) = queue.push(withdrawal(1, 3_000_000), 100, 5_000).unwrap();
+        assert_eq!(job.next_attempt_at, 5_100);
+        assert!(queue.ready(5_099).unwrap().is_empty());
+        assert_eq!(queue.ready(5_100).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_retries_back_off_then_fail() {
+        let mut job = Job::new(withdrawal(1, 3_000_000), 0);
+        job.retry("timeout".into(), 0, 2);
+        assert_eq!(job.next_attempt_at, 500);
+        job.retry("timeout".into(), 500, 2);
+        assert_eq!(job.next_attempt_at, 1_500);
+        assert_eq!(job.status, JobStatus::Pending);
+
+        job.retry("timeout".into(), 1_500, 2);
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(backoff_ms(30), MAX_RETRY_DELAY_MS);
+    }
+}
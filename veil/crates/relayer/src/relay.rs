@@ -0,0 +1,294 @@
+//! Withdrawal submission
+//!
+//! A withdrawal is checked against the fee policy and its expiry slot,
+//! then simulated, so a proof the program would reject costs the relayer
+//! nothing. Accepted withdrawals are sent with a compute budget and a
+//! priority fee following the pool's recent fees. Retrying withdrawals
+//! that fail to land is left to the job queue, which records each
+//! signature first.
+
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use veil_cpi::{accounts, instruction, pda};
+
+use crate::config::Config;
+use crate::error::{RelayerError, Result};
+use crate::fees;
+
+/// A withdrawal to submit, with the public inputs its proof commits to
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Withdrawal {
+    pub pool: Pubkey,
+    /// Mint of an SPL pool, `None` for a SOL pool
+    pub mint: Option<Pubkey>,
+    /// Version of the pool's Groth16 withdraw key the proof verifies with
+    pub vk_version: u32,
+    pub treasury: Pubkey,
+    pub recipient: Pubkey,
+    /// Archived tree the note belongs to, if not the current one
+    pub tree_epoch: Option<Pubkey>,
+    pub nullifier: [u8; 32],
+    pub root: [u8; 32],
+    pub amount: u64,
+    pub fee: u64,
+    pub refund: u64,
+    pub expiry_slot: u64,
+    pub proof: Vec<u8>,
+}
+
+impl Withdrawal {
+    /// Whether the withdrawal is from a SOL pool
+    pub fn is_sol(&self) -> bool {
+        self.mint.is_none()
+    }
+
+    /// The withdrawal's instruction, submitted by `relayer`
+    pub fn instruction(&self, relayer: Pubkey, relayer_bond: Option<Pubkey>) -> Instruction {
+        match self.mint {
+            None => {
+                let mut accounts = accounts::UnshieldSol::new(
+                    self.pool,
+                    &self.nullifier,
+                    self.vk_version,
+                    self.recipient,
+                    self.treasury,
+                    relayer,
+                );
+                accounts.tree_epoch = self.tree_epoch;
+                accounts.relayer_bond = relayer_bond;
+                veil_cpi::unshield_sol(
+                    &accounts,
+                    &instruction::UnshieldSol {
+                        nullifier: self.nullifier,
+                        root: self.root,
+                        amount: self.amount,
+                        fee: self.fee,
+                        refund: self.refund,
+                        expiry_slot: self.expiry_slot,
+                        proof: self.proof.clone(),
+                    },
+                )
+            }
+            Some(mint) => {
+                let mut accounts = accounts::Unshield::new(
+                    self.pool,
+                    mint,
+                    &self.nullifier,
+                    self.vk_version,
+                    self.recipient,
+                    self.treasury,
+                    relayer,
+                );
+                accounts.tree_epoch = self.tree_epoch;
+                accounts.relayer_bond = relayer_bond;
+                veil_cpi::unshield(
+                    &accounts,
+                    &instruction::Unshield {
+                        nullifier: self.nullifier,
+                        root: self.root,
+                        amount: self.amount,
+                        fee: self.fee,
+                        refund: self.refund,
+                        expiry_slot: self.expiry_slot,
+                        proof: self.proof.clone(),
+                    },
+                )
+            }
+        }
+    }
+}
+
+/// Signs, simulates and submits withdrawals
+pub struct Relayer {
+    config: Config,
+    rpc: RpcClient,
+    keypair: Keypair,
+    /// The relayer's bond, passed to pools that only accept bonded relayers
+    relayer_bond: Option<Pubkey>,
+}
+
+impl Relayer {
+    /// Load the relayer's keypair and look up its bond
+    pub async fn new(config: Config) -> Result<Self> {
+        let keypair = read_keypair_file(&config.keypair_path).map_err(|err| {
+            RelayerError::Config(format!("{}: {err}", config.keypair_path.display()))
+        })?;
+        let rpc = RpcClient::new_with_commitment(config.rpc_url.clone(), CommitmentConfig::confirmed());
+
+        let bond = pda::relayer_bond(&keypair.pubkey());
+        let relayer_bond = rpc
+            .get_account_with_commitment(&bond, rpc.commitment())
+            .await?
+            .value
+            .map(|_| bond);
+
+        Ok(Self { config, rpc, keypair, relayer_bond })
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The relayer's pubkey, signing and paying for withdrawals
+    pub fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    /// The relayer's balance in lamports
+    pub async fn balance(&self) -> Result<u64> {
+        Ok(self.rpc.get_balance(&self.pubkey()).await?)
+    }
+
+    /// The cluster's current slot
+    pub async fn slot(&self) -> Result<u64> {
+        Ok(self.rpc.get_slot().await?)
+    }
+
+    /// Fail unless the withdrawal pays exactly the relayer's fee and hasn't expired
+    pub async fn check(&self, withdrawal: &Withdrawal) -> Result<()> {
+        fees::check_withdrawal(&self.config, withdrawal.amount, withdrawal.fee, withdrawal.is_sol())?;
+        if self.slot().await? > withdrawal.expiry_slot {
+            return Err(RelayerError::Expired(withdrawal.expiry_slot));
+        }
+        Ok(())
+    }
+
+    /// Sign the withdrawal's transaction, failing if its simulation fails
+    pub async fn prepare(&self, withdrawal: &Withdrawal) -> Result<Transaction> {
+        let instructions = self.instructions(withdrawal).await?;
+        let transaction = self.sign(&instructions).await?;
+        self.simulate(&transaction).await?;
+        Ok(transaction)
+    }
+
+    /// Send a prepared transaction and wait for its confirmation
+    pub async fn send(&self, transaction: &Transaction) -> Result<Signature> {
+        match self.rpc.send_and_confirm_transaction(transaction).await {
+            Ok(signature) => Ok(signature),
+            // The program rejected it; resubmitting won't help
+            Err(err) if err.get_transaction_error().is_some() => {
+                Err(RelayerError::TransactionFailed(err.to_string()))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Whether a transaction sent earlier landed
+    pub async fn landed(&self, signature: &Signature) -> Result<bool> {
+        Ok(matches!(self.rpc.get_signature_status(signature).await?, Some(Ok(()))))
+    }
+
+    /// The withdrawal's instruction behind its compute budget
+    async fn instructions(&self, withdrawal: &Withdrawal) -> Result<Vec<Instruction>> {
+        let recent: Vec<u64> = self
+            .rpc
+            .get_recent_prioritization_fees(&[withdrawal.pool])
+            .await?
+            .iter()
+            .map(|fee| fee.prioritization_fee)
+            .collect();
+        let price = fees::priority_fee(
+            &recent,
+            self.config.min_priority_fee,
+            self.config.max_priority_fee,
+        );
+
+        Ok(vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(self.config.compute_unit_limit),
+            ComputeBudgetInstruction::set_compute_unit_price(price),
+            withdrawal.instruction(self.pubkey(), self.relayer_bond),
+        ])
+    }
+
+    /// Sign `instructions` with the latest blockhash
+    async fn sign(&self, instructions: &[Instruction]) -> Result<Transaction> {
+        let blockhash = self.rpc.get_latest_blockhash().await?;
+        Ok(Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.pubkey()),
+            &[&self.keypair],
+            blockhash,
+        ))
+    }
+
+    /// Fail with the program's error if the transaction would fail
+    async fn simulate(&self, transaction: &Transaction) -> Result<()> {
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: true,
+            commitment: Some(self.rpc.commitment()),
+            ..Default::default()
+        };
+        let result = self
+            .rpc
+            .simulate_transaction_with_config(transaction, config)
+            .await?
+            .value;
+
+        match result.err {
+            None => Ok(()),
+            Some(err) => {
+                let log = result
+                    .logs
+                    .unwrap_or_default()
+                    .into_iter()
+                    .rev()
+                    .find(|log| log.contains("Error"));
+                Err(RelayerError::SimulationFailed(match log {
+                    Some(log) => format!("{err} ({log})"),
+                    None => err.to_string(),
+                }))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn withdrawal(mint: Option<Pubkey>) -> Withdrawal {
+        Withdrawal {
+            pool: Pubkey::new_unique(),
+            mint,
+            vk_version: 1,
+            treasury: Pubkey::new_unique(),
+            recipient: Pubkey::new_unique(),
+            tree_epoch: None,
+            nullifier: [1u8; 32],
+            root: [2u8; 32],
+            amount: 1_000_000_000,
+            fee: 3_000_000,
+            refund: 0,
+            expiry_slot: 100,
+            proof: vec![7; 256],
+        }
+    }
+
+    #[test]
+    fn test_instruction_for_the_pool_kind() {
+        let relayer = Pubkey::new_unique();
+        let sol = withdrawal(None).instruction(relayer, None);
+        assert_eq!(sol.program_id, veil_cpi::ID);
+        assert_eq!(sol.accounts.len(), 20);
+
+        let spl = withdrawal(Some(Pubkey::new_unique())).instruction(relayer, None);
+        assert_eq!(spl.accounts.len(), 24);
+        // The relayer signs
+        assert!(spl.accounts.iter().any(|meta| meta.pubkey == relayer && meta.is_signer));
+    }
+
+    #[test]
+    fn test_instruction_passes_the_bond() {
+        let relayer = Pubkey::new_unique();
+        let bond = pda::relayer_bond(&relayer);
+        let instruction = withdrawal(None).instruction(relayer, Some(bond));
+        assert!(instruction.accounts.iter().any(|meta| meta.pubkey == bond));
+    }
+}
@@ -0,0 +1,180 @@
+//! Queue worker
+//!
+//! Polls the queue for ready jobs and submits each in its own task, at
+//! most `max_jobs` at once and `max_jobs_per_pool` per pool: withdrawals
+//! from one pool write the same accounts and would only contend with each
+//! other.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+
+use crate::error::{RelayerError, Result};
+use crate::queue::{now_ms, Job, JobQueue};
+use crate::relay::Relayer;
+
+/// How often the queue is polled for ready jobs
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Jobs being submitted
+#[derive(Default)]
+struct InFlight {
+    jobs: HashSet<[u8; 32]>,
+    pools: HashMap<Pubkey, usize>,
+}
+
+impl InFlight {
+    /// Take a slot for `job`, unless it is running or its pool or the
+    /// relayer is at its limit
+    fn reserve(&mut self, job: &Job, max_jobs: usize, max_jobs_per_pool: usize) -> bool {
+        let pool = self.pools.get(&job.withdrawal.pool).copied().unwrap_or(0);
+        if self.jobs.len() >= max_jobs
+            || pool >= max_jobs_per_pool
+            || !self.jobs.insert(job.withdrawal.nullifier)
+        {
+            return false;
+        }
+        self.pools.insert(job.withdrawal.pool, pool + 1);
+        true
+    }
+
+    fn release(&mut self, job: &Job) {
+        self.jobs.remove(&job.withdrawal.nullifier);
+        if let Some(count) = self.pools.get_mut(&job.withdrawal.pool) {
+            *count -= 1;
+            if *count == 0 {
+                self.pools.remove(&job.withdrawal.pool);
+            }
+        }
+    }
+}
+
+/// Submit queued jobs until the process exits
+pub async fn run(queue: Arc<JobQueue>, relayer: Arc<Relayer>) {
+    let in_flight = Arc::new(Mutex::new(InFlight::default()));
+    loop {
+        match queue.ready(now_ms()) {
+            Ok(jobs) => {
+                for job in jobs {
+                    let reserved = in_flight.lock().unwrap().reserve(
+                        &job,
+                        relayer.config().max_jobs,
+                        relayer.config().max_jobs_per_pool,
+                    );
+                    if reserved {
+                        let (queue, relayer, in_flight) =
+                            (queue.clone(), relayer.clone(), in_flight.clone());
+                        tokio::spawn(async move {
+                            process(&queue, &relayer, job.clone()).await;
+                            in_flight.lock().unwrap().release(&job);
+                        });
+                    }
+                }
+            }
+            Err(err) => tracing::error!("cannot read the job queue: {err}"),
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Make one attempt at a job and store the outcome
+async fn process(queue: &JobQueue, relayer: &Relayer, mut job: Job) {
+    let id = job.id();
+    match attempt(queue, relayer, &mut job).await {
+        Ok(signature) => {
+            tracing::info!(%id, %signature, "withdrawal relayed");
+            job.confirm(signature);
+        }
+        Err(err) if err.is_transient() => {
+            tracing::warn!(%id, attempts = job.attempts + 1, "withdrawal did not land: {err}");
+            job.retry(err.to_string(), now_ms(), relayer.config().max_retries);
+        }
+        Err(err) => {
+            tracing::warn!(%id, "withdrawal failed: {err}");
+            job.fail(err.to_string());
+        }
+    }
+    if let Err(err) = queue.put(&job) {
+        tracing::error!(%id, "cannot store the job: {err}");
+    }
+}
+
+/// Submit a job, returning the signature it landed under
+async fn attempt(queue: &JobQueue, relayer: &Relayer, job: &mut Job) -> Result<Signature> {
+    // An earlier attempt may have landed after all, and its nullifier
+    // would fail this one
+    if let Some(signature) = landed(relayer, &job.signatures).await? {
+        return Ok(signature);
+    }
+    relayer.check(&job.withdrawal).await?;
+    let transaction = relayer.prepare(&job.withdrawal).await?;
+
+    job.signatures.push(transaction.signatures[0]);
+    queue.put(job)?;
+    match relayer.send(&transaction).await {
+        Err(RelayerError::TransactionFailed(err)) => {
+            match landed(relayer, &job.signatures).await? {
+                Some(signature) => Ok(signature),
+                None => Err(RelayerError::TransactionFailed(err)),
+            }
+        }
+        result => result,
+    }
+}
+
+/// The first of `signatures` that landed
+async fn landed(relayer: &Relayer, signatures: &[Signature]) -> Result<Option<Signature>> {
+    for signature in signatures {
+        if relayer.landed(signature).await? {
+            return Ok(Some(*signature));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::relay::Withdrawal;
+
+    fn job(pool: Pubkey, nullifier: u8) -> Job {
+        let withdrawal = Withdrawal {
+            pool,
+            mint: None,
+            vk_version: 1,
+            treasury: Pubkey::new_unique(),
+            recipient: Pubkey::new_unique(),
+            tree_epoch: None,
+            nullifier: [nullifier; 32],
+            root: [2u8; 32],
+            amount: 1_000_000_000,
+            fee: 3_000_000,
+            refund: 0,
+            expiry_slot: 100,
+            proof: vec![7; 256],
+        };
+        Job::new(withdrawal, 0)
+    }
+
+    #[test]
+    fn test_in_flight_limits() {
+        let (pool, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut in_flight = InFlight::default();
+
+        assert!(in_flight.reserve(&job(pool, 1), 3, 2));
+        // A running job isn't taken twice
+        assert!(!in_flight.reserve(&job(pool, 1), 3, 2));
+        assert!(in_flight.reserve(&job(pool, 2), 3, 2));
+        // The pool is full, the relayer isn't
+        assert!(!in_flight.reserve(&job(pool, 3), 3, 2));
+        assert!(in_flight.reserve(&job(other, 4), 3, 2));
+        // Now the relayer is
+        assert!(!in_flight.reserve(&job(Pubkey::new_unique(), 5), 3, 2));
+
+        in_flight.release(&job(pool, 1));
+        assert!(in_flight.reserve(&job(pool, 3), 3, 2));
+    }
+}
@@ -0,0 +1,311 @@
+//! Note backups
+//!
+//! A password-encrypted file holding a wallet's notes, so they can be moved
+//! to another device or restored after losing one. A note's opening is all
+//! it takes to spend it, so the file is encrypted under a key stretched
+//! from the password with Argon2id, and sealed with ChaCha20-Poly1305.
+//!
+//! ```text
+//! magic "VEILBKUP" (8) || version (1) || m_cost KiB (4) || t_cost (4)
+//!     || p_cost (4) || salt (16) || nonce (12) || ciphertext || tag (16)
+//! ```
+//!
+//! Everything before the ciphertext is the AEAD's associated data, so the
+//! stored KDF parameters can't be weakened without failing decryption.
+//! Integers are little-endian.
+//!
+//! Version 1 plaintext is the note count (4) followed by each note:
+//!
+//! ```text
+//! flags (1) || pool (32) || mint (32) || secret (32) || blinding (32)
+//!     || amount (8) || asset_id (8) || tree_epoch (8) || leaf_index (8)
+//!     [|| stealth_tweak (32), when flags has FLAG_STEALTH]
+//! ```
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::{CryptoRng, RngCore};
+use solana_program::pubkey::Pubkey;
+
+use crate::codec::Reader;
+use crate::error::{Result, SdkError};
+use crate::note::{fr_to_bytes, Note};
+use crate::scanner::OwnedNote;
+
+/// Leading bytes of a backup
+const MAGIC: &[u8; 8] = b"VEILBKUP";
+
+/// Version of the backup format written by `export_notes`
+pub const BACKUP_VERSION: u8 = 1;
+
+/// Length of the header: magic, version, KDF parameters, salt and nonce
+const HEADER_LEN: usize = 8 + 1 + 4 + 4 + 4 + SALT_LEN + 12;
+
+const SALT_LEN: usize = 16;
+
+/// Largest Argon2 memory cost accepted on import (1 GiB), so a crafted
+/// backup can't exhaust memory before its password is checked
+const MAX_M_COST_KIB: u32 = 1 << 20;
+
+/// Largest Argon2 time cost accepted on import
+const MAX_T_COST: u32 = 64;
+
+/// Largest Argon2 parallelism accepted on import
+const MAX_P_COST: u32 = 16;
+
+const FLAG_SPENT: u8 = 1;
+const FLAG_LEAF_INDEX: u8 = 2;
+const FLAG_TREE_EPOCH: u8 = 4;
+const FLAG_STEALTH: u8 = 8;
+
+/// Length of a note record without a stealth tweak
+const RECORD_LEN: usize = 1 + 32 * 4 + 8 * 4;
+
+/// Argon2id cost of stretching a backup's password
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    /// Memory, in KiB
+    pub m_cost: u32,
+    /// Passes over the memory
+    pub t_cost: u32,
+    /// Lanes
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    /// 64 MiB, 3 passes, 1 lane
+    fn default() -> Self {
+        Self { m_cost: 64 * 1024, t_cost: 3, p_cost: 1 }
+    }
+}
+
+impl KdfParams {
+    /// The 32-byte key for `password` and `salt`
+    fn derive_key(&self, password: &[u8], salt: &[u8]) -> Result<[u8; 32]> {
+        if self.m_cost > MAX_M_COST_KIB || self.t_cost > MAX_T_COST || self.p_cost > MAX_P_COST {
+            return Err(SdkError::InvalidKdfParams);
+        }
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(|_| SdkError::InvalidKdfParams)?;
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password, salt, &mut key)
+            .map_err(|_| SdkError::InvalidKdfParams)?;
+        Ok(key)
+    }
+}
+
+/// A note as backed up, with what a wallet knows about it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackupNote {
+    pub pool: Pubkey,
+    /// Mint of the pool (default pubkey for SOL)
+    pub mint: Pubkey,
+    pub note: Note,
+    /// Tree epoch of the note's leaf, once inserted
+    pub tree_epoch: Option<u64>,
+    /// Leaf index of the note, once inserted
+    pub leaf_index: Option<u64>,
+    pub spent: bool,
+}
+
+impl From<OwnedNote> for BackupNote {
+    fn from(owned: OwnedNote) -> Self {
+        Self {
+            pool: owned.pool,
+            mint: owned.mint,
+            note: owned.note,
+            tree_epoch: Some(owned.tree_epoch),
+            leaf_index: Some(owned.leaf_index),
+            spent: owned.spent,
+        }
+    }
+}
+
+/// Encrypt `notes` into a backup under `password`
+pub fn export_notes<R: RngCore + CryptoRng>(
+    notes: &[BackupNote],
+    password: &str,
+    params: &KdfParams,
+    rng: &mut R,
+) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    rng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; 12];
+    rng.fill_bytes(&mut nonce);
+    let key = params.derive_key(password.as_bytes(), &salt)?;
+
+    let mut backup = Vec::with_capacity(HEADER_LEN + 4 + notes.len() * RECORD_LEN);
+    backup.extend_from_slice(MAGIC);
+    backup.push(BACKUP_VERSION);
+    backup.extend_from_slice(&params.m_cost.to_le_bytes());
+    backup.extend_from_slice(&params.t_cost.to_le_bytes());
+    backup.extend_from_slice(&params.p_cost.to_le_bytes());
+    backup.extend_from_slice(&salt);
+    backup.extend_from_slice(&nonce);
+
+    let ciphertext = ChaCha20Poly1305::new(&Key::from(key))
+        .encrypt(&Nonce::from(nonce), Payload { msg: &encode_notes(notes), aad: &backup })
+        .map_err(|_| SdkError::MalformedBackup)?;
+    backup.extend_from_slice(&ciphertext);
+    Ok(backup)
+}
+
+/// Decrypt the notes of a backup written by `export_notes`
+pub fn import_notes(backup: &[u8], password: &str) -> Result<Vec<BackupNote>> {
+    if backup.len() < HEADER_LEN || &backup[..8] != MAGIC {
+        return Err(SdkError::MalformedBackup);
+    }
+    if backup[8] != BACKUP_VERSION {
+        return Err(SdkError::UnsupportedBackupVersion(backup[8]));
+    }
+    let u32_at = |offset: usize| u32::from_le_bytes(backup[offset..offset + 4].try_into().unwrap());
+    let params = KdfParams { m_cost: u32_at(9), t_cost: u32_at(13), p_cost: u32_at(17) };
+    let (header, ciphertext) = backup.split_at(HEADER_LEN);
+    let salt = &header[21..21 + SALT_LEN];
+    let nonce: [u8; 12] = header[21 + SALT_LEN..].try_into().unwrap();
+    let key = params.derive_key(password.as_bytes(), salt)?;
+
+    let plaintext = ChaCha20Poly1305::new(&Key::from(key))
+        .decrypt(&Nonce::from(nonce), Payload { msg: ciphertext, aad: header })
+        .map_err(|_| SdkError::WrongPassword)?;
+    decode_notes(&plaintext)
+}
+
+fn encode_notes(notes: &[BackupNote]) -> Vec<u8> {
+    let mut plaintext = Vec::with_capacity(4 + notes.len() * RECORD_LEN);
+    plaintext.extend_from_slice(&(notes.len() as u32).to_le_bytes());
+    for backup in notes {
+        let note = &backup.note;
+        let mut flags = 0;
+        if backup.spent {
+            flags |= FLAG_SPENT;
+        }
+        if backup.leaf_index.is_some() {
+            flags |= FLAG_LEAF_INDEX;
+        }
+        if backup.tree_epoch.is_some() {
+            flags |= FLAG_TREE_EPOCH;
+        }
+        if note.stealth_tweak.is_some() {
+            flags |= FLAG_STEALTH;
+        }
+        plaintext.push(flags);
+        plaintext.extend_from_slice(backup.pool.as_ref());
+        plaintext.extend_from_slice(backup.mint.as_ref());
+        plaintext.extend_from_slice(&fr_to_bytes(&note.secret));
+        plaintext.extend_from_slice(&fr_to_bytes(&note.blinding));
+        plaintext.extend_from_slice(&note.amount.to_le_bytes());
+        plaintext.extend_from_slice(&note.asset_id.to_le_bytes());
+        plaintext.extend_from_slice(&backup.tree_epoch.unwrap_or_default().to_le_bytes());
+        plaintext.extend_from_slice(&backup.leaf_index.unwrap_or_default().to_le_bytes());
+        if let Some(tweak) = &note.stealth_tweak {
+            plaintext.extend_from_slice(&fr_to_bytes(tweak));
+        }
+    }
+    plaintext
+}
+
+fn decode_notes(plaintext: &[u8]) -> Result<Vec<BackupNote>> {
+    let mut reader = Reader::new(plaintext, || SdkError::MalformedBackup);
+    let count = u32::from_le_bytes(reader.take()?);
+    let mut notes = Vec::new();
+    for _ in 0..count {
+        let flags = reader.u8()?;
+        let pool = reader.pubkey()?;
+        let mint = reader.pubkey()?;
+        let secret = reader.field()?;
+        let blinding = reader.field()?;
+        let amount = reader.u64()?;
+        let asset_id = reader.u64()?;
+        let tree_epoch = reader.u64()?;
+        let leaf_index = reader.u64()?;
+        let mut note = Note::new(secret, amount, blinding, asset_id);
+        if flags & FLAG_STEALTH != 0 {
+            note = note.with_stealth_tweak(reader.field()?);
+        }
+        notes.push(BackupNote {
+            pool,
+            mint,
+            note,
+            tree_epoch: (flags & FLAG_TREE_EPOCH != 0).then_some(tree_epoch),
+            leaf_index: (flags & FLAG_LEAF_INDEX != 0).then_some(leaf_index),
+            spent: flags & FLAG_SPENT != 0,
+        });
+    }
+    reader.finish()?;
+    Ok(notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use rand::rngs::OsRng;
+
+    /// Cheap parameters, so tests don't spend seconds stretching
+    const TEST_PARAMS: KdfParams = KdfParams { m_cost: 64, t_cost: 1, p_cost: 1 };
+
+    fn notes() -> Vec<BackupNote> {
+        let mint = Pubkey::new_unique();
+        let note = Note::random(5, &mint, &mut OsRng);
+        vec![
+            BackupNote {
+                pool: Pubkey::new_unique(),
+                mint,
+                note: note.clone(),
+                tree_epoch: Some(1),
+                leaf_index: Some(42),
+                spent: true,
+            },
+            BackupNote {
+                pool: Pubkey::new_unique(),
+                mint: Pubkey::default(),
+                note: note.with_stealth_tweak(Fr::from(3u64)),
+                tree_epoch: None,
+                leaf_index: None,
+                spent: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let notes = notes();
+        let backup = export_notes(&notes, "hunter2", &TEST_PARAMS, &mut OsRng).unwrap();
+        assert_eq!(import_notes(&backup, "hunter2").unwrap(), notes);
+
+        let empty = export_notes(&[], "hunter2", &TEST_PARAMS, &mut OsRng).unwrap();
+        assert!(import_notes(&empty, "hunter2").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rejects_wrong_password_and_tampering() {
+        let backup = export_notes(&notes(), "hunter2", &TEST_PARAMS, &mut OsRng).unwrap();
+        assert!(matches!(import_notes(&backup, "hunter3"), Err(SdkError::WrongPassword)));
+
+        // Weakening the stored KDF cost changes the key and the header
+        let mut weakened = backup.clone();
+        weakened[9..13].copy_from_slice(&8u32.to_le_bytes());
+        assert!(matches!(import_notes(&weakened, "hunter2"), Err(SdkError::WrongPassword)));
+
+        let mut versioned = backup.clone();
+        versioned[8] = 2;
+        assert!(matches!(
+            import_notes(&versioned, "hunter2"),
+            Err(SdkError::UnsupportedBackupVersion(2))
+        ));
+        assert!(matches!(
+            import_notes(&backup[..HEADER_LEN - 1], "hunter2"),
+            Err(SdkError::MalformedBackup)
+        ));
+    }
+
+    #[test]
+    fn test_rejects_excessive_kdf_cost() {
+        let mut backup = export_notes(&notes(), "hunter2", &TEST_PARAMS, &mut OsRng).unwrap();
+        backup[9..13].copy_from_slice(&(MAX_M_COST_KIB + 1).to_le_bytes());
+        assert!(matches!(import_notes(&backup, "hunter2"), Err(SdkError::InvalidKdfParams)));
+    }
+}
@@ -0,0 +1,59 @@
+//! Binary decoding shared by the SDK's file and wire formats
+
+use ark_bn254::Fr;
+use solana_program::pubkey::Pubkey;
+
+use crate::error::{Result, SdkError};
+use crate::note::fr_from_bytes;
+
+/// Reads fixed-size fields off the front of a buffer, failing with the
+/// format's own error when it runs short
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    malformed: fn() -> SdkError,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8], malformed: fn() -> SdkError) -> Self {
+        Self { bytes, malformed }
+    }
+
+    pub fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.slice(N)?.try_into().unwrap())
+    }
+
+    pub fn slice(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err((self.malformed)());
+        }
+        let (field, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(field)
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    pub fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    pub fn pubkey(&mut self) -> Result<Pubkey> {
+        Ok(Pubkey::new_from_array(self.take()?))
+    }
+
+    /// A canonical field element
+    pub fn field(&mut self) -> Result<Fr> {
+        fr_from_bytes(&self.take()?)
+    }
+
+    /// Check nothing is left over
+    pub fn finish(self) -> Result<()> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err((self.malformed)())
+        }
+    }
+}
@@ -0,0 +1,379 @@
+//! Note encryption
+//!
+//! The format of the notes the program logs in `EncryptedNote` events, so
+//! a recipient finds incoming notes by trial-decrypting each event with
+//! their viewing key.
+//!
+//! An encrypted note is
+//!
+//! ```text
+//! ephemeral_key (32) || version (1) || ChaCha20-Poly1305(note) || tag (16)
+//! ```
+//!
+//! The sender draws an x25519 ephemeral key per note and derives the AEAD
+//! key from its Diffie-Hellman secret with the recipient's viewing key.
+//! The key is never reused, so the nonce is fixed at zero. The header, the
+//! ephemeral key and version, is the AEAD's associated data. The ephemeral
+//! key leads so the program can log it on its own.
+//!
+//! Version 1 plaintext:
+//!
+//! ```text
+//! secret (32) || blinding (32) || amount (8) || asset_id (8)
+//! ```
+//!
+//! Field elements are big-endian, integers little-endian. A decrypted note
+//! proves nothing on its own; `ViewingKey::try_open` also checks it opens
+//! the commitment it was logged with.
+//!
+//! Every format shares the header, so a reader tells them apart before
+//! decrypting:
+//!
+//! - 1: the x25519 notes above
+//! - 2: the BN254 notes of `veil_core::crypto::encryption`, which stealth
+//!   notes and the Python client use. A stealth note's secret is the
+//!   recipient's, which the sender never holds, so it opens with the
+//!   recipient's `StealthKeys` (`open_stealth_note`), not a viewing key.
+
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use solana_program::pubkey::Pubkey;
+use veil_core::crypto::encryption::{self as bn254, EncryptedNote, NoteData};
+use veil_core::crypto::stealth::{self, StealthKeys, StealthMetaAddress};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::error::{Result, SdkError};
+use crate::note::{asset_id, fr_from_bytes, fr_to_bytes, Note};
+
+/// Version of the encryption format written by `encrypt_note`
+pub const ENCRYPTION_VERSION: u8 = 1;
+
+/// Version of the BN254 format stealth notes are written in
+pub const STEALTH_ENCRYPTION_VERSION: u8 = bn254::ENCRYPTION_VERSION;
+
+/// Length of the ephemeral x25519 public key leading every note
+pub const EPHEMERAL_KEY_LEN: usize = 32;
+
+/// Length of the header: ephemeral key and version
+pub const HEADER_LEN: usize = EPHEMERAL_KEY_LEN + 1;
+
+/// Length of the Poly1305 tag
+pub const TAG_LEN: usize = 16;
+
+/// Length of a plaintext note
+const NOTE_LEN: usize = 32 + 32 + 8 + 8;
+
+/// Length of an encrypted note
+pub const ENCRYPTED_NOTE_LEN: usize = HEADER_LEN + NOTE_LEN + TAG_LEN;
+
+/// Domain separator for key derivation
+const KEY_DOMAIN: &[u8] = b"VEIL_NOTE_ENCRYPTION_V1";
+
+/// The secret notes are encrypted to
+///
+/// Finds and opens incoming notes. A note's opening includes its secret,
+/// so whoever holds the viewing key can spend the notes it opens.
+#[derive(Clone)]
+pub struct ViewingKey(StaticSecret);
+
+impl ViewingKey {
+    /// A viewing key from its 32 secret bytes
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(StaticSecret::from(bytes))
+    }
+
+    /// A fresh viewing key
+    pub fn random<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let mut bytes = [0u8; 32];
+        rng.fill_bytes(&mut bytes);
+        Self::from_bytes(bytes)
+    }
+
+    /// The secret bytes, as `from_bytes` takes them
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    /// The public key senders encrypt notes to
+    pub fn public_key(&self) -> [u8; 32] {
+        *PublicKey::from(&self.0).as_bytes()
+    }
+
+    /// Decrypt a note encrypted to this key
+    pub fn decrypt(&self, encrypted_note: &[u8]) -> Result<Note> {
+        let version = *encrypted_note
+            .get(EPHEMERAL_KEY_LEN)
+            .ok_or(SdkError::MalformedEncryptedNote)?;
+        if version != ENCRYPTION_VERSION {
+            return Err(SdkError::UnsupportedEncryptionVersion(version));
+        }
+        if encrypted_note.len() != ENCRYPTED_NOTE_LEN {
+            return Err(SdkError::MalformedEncryptedNote);
+        }
+        let (header, ciphertext) = encrypted_note.split_at(HEADER_LEN);
+
+        let mut ephemeral_key = [0u8; EPHEMERAL_KEY_LEN];
+        ephemeral_key.copy_from_slice(&header[..EPHEMERAL_KEY_LEN]);
+        let ephemeral_key = PublicKey::from(ephemeral_key);
+        let public_key = PublicKey::from(&self.0);
+        let key = note_key(&self.0, &ephemeral_key, &ephemeral_key, &public_key)
+            .ok_or(SdkError::DecryptionFailed)?;
+
+        let plaintext = cipher(&key)
+            .decrypt(&Nonce::from([0u8; 12]), Payload { msg: ciphertext, aad: header })
+            .map_err(|_| SdkError::DecryptionFailed)?;
+        decode_note(&plaintext)
+    }
+
+    /// The note behind `commitment`, if `encrypted_note` is addressed to
+    /// this key and opens it
+    ///
+    /// The scanning check: `None` for the notes of other recipients, and
+    /// for notes whose sender logged a ciphertext not matching the leaf.
+    pub fn try_open(&self, commitment: &[u8; 32], encrypted_note: &[u8]) -> Option<Note> {
+        let note = self.decrypt(encrypted_note).ok()?;
+        (note.commitment_bytes() == *commitment).then_some(note)
+    }
+}
+
+/// Encrypt `note` to the viewing key `recipient`
+pub fn encrypt_note<R: RngCore + CryptoRng>(
+    note: &Note,
+    recipient: &[u8; 32],
+    rng: &mut R,
+) -> Result<Vec<u8>> {
+    if note.stealth_tweak.is_some() {
+        return Err(SdkError::UnsupportedNote("Stealth"));
+    }
+    let recipient = PublicKey::from(*recipient);
+    let ephemeral_secret = ViewingKey::random(rng).0;
+    let ephemeral_key = PublicKey::from(&ephemeral_secret);
+    // Low-order recipient keys give an all-zero secret anyone can compute
+    let key = note_key(&ephemeral_secret, &recipient, &ephemeral_key, &recipient)
+        .ok_or(SdkError::InvalidViewingKey)?;
+
+    let mut encrypted_note = Vec::with_capacity(ENCRYPTED_NOTE_LEN);
+    encrypted_note.extend_from_slice(ephemeral_key.as_bytes());
+    encrypted_note.push(ENCRYPTION_VERSION);
+    let ciphertext = cipher(&key)
+        .encrypt(
+            &Nonce::from([0u8; 12]),
+            Payload { msg: &encode_note(note), aad: &encrypted_note },
+        )
+        .map_err(|_| SdkError::MalformedEncryptedNote)?;
+    encrypted_note.extend_from_slice(&ciphertext);
+    Ok(encrypted_note)
+}
+
+/// Commitment and encrypted note of a note of `amount` of `mint` sent to
+/// the stealth address `meta_address`, as `shield` and `shield_sol` take
+/// them
+pub fn send_to_stealth_address<R: RngCore + CryptoRng>(
+    amount: u64,
+    mint: &Pubkey,
+    meta_address: &StealthMetaAddress,
+    rng: &mut R,
+) -> Result<([u8; 32], Vec<u8>)> {
+    let mut blinding = [0u8; 32];
+    blinding.copy_from_slice(&Fr::rand(rng).into_bigint().to_bytes_le());
+    let note_data = NoteData::new(amount, blinding, asset_id(mint));
+    let note = stealth::send_to_stealth_address(&note_data, meta_address)
+        .map_err(|_| SdkError::InvalidViewingKey)?;
+    let commitment = stealth::note_commitment(&note.spending_key, &note_data);
+    Ok((fr_to_bytes(&commitment), note.encrypted_note.to_bytes().to_vec()))
+}
+
+/// The stealth note behind `commitment`, if `encrypted_note` was sent to
+/// the stealth address of `keys` and opens it
+///
+/// `None` for notes in other formats, as `ViewingKey::try_open` is for
+/// stealth notes.
+pub fn open_stealth_note(
+    keys: &StealthKeys,
+    commitment: &[u8; 32],
+    encrypted_note: &[u8],
+) -> Option<Note> {
+    let received = keys.scan(&EncryptedNote::from_bytes(encrypted_note).ok()?)?;
+    let note_data = &received.note_data;
+    let note = Note::new(
+        Fr::from_le_bytes_mod_order(keys.spend_secret()),
+        note_data.amount,
+        Fr::from_le_bytes_mod_order(&note_data.blinding),
+        note_data.asset_id,
+    )
+    .with_stealth_tweak(received.tweak);
+    (note.commitment_bytes() == *commitment).then_some(note)
+}
+
+/// The ephemeral key an encrypted note leads with, as the program logs it
+pub fn ephemeral_key(encrypted_note: &[u8]) -> Option<[u8; 32]> {
+    encrypted_note.get(..EPHEMERAL_KEY_LEN)?.try_into().ok()
+}
+
+/// AEAD key of a note from the Diffie-Hellman secret of `secret` and the
+/// other party's `public`, `None` if the secret is all zeros
+fn note_key(
+    secret: &StaticSecret,
+    public: &PublicKey,
+    ephemeral_key: &PublicKey,
+    recipient: &PublicKey,
+) -> Option<[u8; 32]> {
+    let shared = secret.diffie_hellman(public);
+    if shared.as_bytes() == &[0u8; 32] {
+        return None;
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(KEY_DOMAIN);
+    hasher.update(shared.as_bytes());
+    hasher.update(ephemeral_key.as_bytes());
+    hasher.update(recipient.as_bytes());
+    Some(hasher.finalize().into())
+}
+
+fn cipher(key: &[u8; 32]) -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(&Key::from(*key))
+}
+
+fn encode_note(note: &Note) -> [u8; NOTE_LEN] {
+    let mut plaintext = [0u8; NOTE_LEN];
+    plaintext[..32].copy_from_slice(&fr_to_bytes(&note.secret));
+    plaintext[32..64].copy_from_slice(&fr_to_bytes(&note.blinding));
+    plaintext[64..72].copy_from_slice(&note.amount.to_le_bytes());
+    plaintext[72..].copy_from_slice(&note.asset_id.to_le_bytes());
+    plaintext
+}
+
+fn decode_note(plaintext: &[u8]) -> Result<Note> {
+    if plaintext.len() != NOTE_LEN {
+        return Err(SdkError::MalformedEncryptedNote);
+    }
+    let field = |offset: usize| {
+        let bytes: &[u8; 32] = plaintext[offset..offset + 32].try_into().unwrap();
+        fr_from_bytes(bytes)
+    };
+    let integer = |offset: usize| {
+        u64::from_le_bytes(plaintext[offset..offset + 8].try_into().unwrap())
+    };
+    Ok(Note::new(field(0)?, integer(64), field(32)?, integer(72)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+    use solana_program::pubkey::Pubkey;
+
+    fn note() -> Note {
+        Note::random(1_000_000, &Pubkey::default(), &mut OsRng)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let viewing_key = ViewingKey::random(&mut OsRng);
+        let note = note();
+        let encrypted = encrypt_note(&note, &viewing_key.public_key(), &mut OsRng).unwrap();
+        assert_eq!(encrypted.len(), ENCRYPTED_NOTE_LEN);
+        assert_eq!(encrypted[EPHEMERAL_KEY_LEN], ENCRYPTION_VERSION);
+        assert_eq!(viewing_key.decrypt(&encrypted).unwrap(), note);
+
+        let stealth = note.with_stealth_tweak(Fr::from(7u64));
+        assert!(matches!(
+            encrypt_note(&stealth, &viewing_key.public_key(), &mut OsRng),
+            Err(SdkError::UnsupportedNote("Stealth"))
+        ));
+    }
+
+    #[cfg(feature = "instructions")]
+    #[test]
+    fn test_fits_program_log() {
+        use veil_program::state;
+        const _: () = assert!(ENCRYPTED_NOTE_LEN <= state::MAX_ENCRYPTED_NOTE_LEN);
+        assert_eq!(EPHEMERAL_KEY_LEN, state::EPHEMERAL_KEY_LEN);
+    }
+
+    #[test]
+    fn test_other_recipient_fails() {
+        let viewing_key = ViewingKey::random(&mut OsRng);
+        let other = ViewingKey::random(&mut OsRng);
+        let note = note();
+        let encrypted = encrypt_note(&note, &viewing_key.public_key(), &mut OsRng).unwrap();
+        assert!(matches!(other.decrypt(&encrypted), Err(SdkError::DecryptionFailed)));
+        assert!(other.try_open(&note.commitment_bytes(), &encrypted).is_none());
+        assert_eq!(viewing_key.try_open(&note.commitment_bytes(), &encrypted), Some(note));
+    }
+
+    #[test]
+    fn test_rejects_tampering() {
+        let viewing_key = ViewingKey::random(&mut OsRng);
+        let encrypted = encrypt_note(&note(), &viewing_key.public_key(), &mut OsRng).unwrap();
+
+        let mut tampered = encrypted.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(viewing_key.decrypt(&tampered), Err(SdkError::DecryptionFailed)));
+
+        let mut tampered = encrypted.clone();
+        tampered[0] ^= 1;
+        assert!(viewing_key.decrypt(&tampered).is_err());
+
+        let mut versioned = encrypted.clone();
+        versioned[EPHEMERAL_KEY_LEN] = 2;
+        assert!(matches!(
+            viewing_key.decrypt(&versioned),
+            Err(SdkError::UnsupportedEncryptionVersion(2))
+        ));
+
+        assert!(matches!(
+            viewing_key.decrypt(&encrypted[..HEADER_LEN]),
+            Err(SdkError::MalformedEncryptedNote)
+        ));
+    }
+
+    #[test]
+    fn test_try_open_checks_commitment() {
+        let viewing_key = ViewingKey::random(&mut OsRng);
+        let note = note();
+        let encrypted = encrypt_note(&note, &viewing_key.public_key(), &mut OsRng).unwrap();
+        assert!(viewing_key.try_open(&[0u8; 32], &encrypted).is_none());
+        assert_eq!(ephemeral_key(&encrypted).unwrap(), encrypted[..32]);
+    }
+
+    #[test]
+    fn test_rejects_low_order_key() {
+        let note = note();
+        assert!(matches!(
+            encrypt_note(&note, &[0u8; 32], &mut OsRng),
+            Err(SdkError::InvalidViewingKey)
+        ));
+    }
+
+    #[test]
+    fn test_stealth_notes_interoperate() {
+        let keys = StealthKeys::from_secrets(&[1u8; 32], &[2u8; 32]);
+        let mint = Pubkey::new_unique();
+        let (commitment, encrypted) =
+            send_to_stealth_address(500, &mint, &keys.meta_address(), &mut OsRng).unwrap();
+        assert_eq!(encrypted.len(), bn254::ENCRYPTED_NOTE_SIZE);
+        assert_eq!(encrypted[EPHEMERAL_KEY_LEN], STEALTH_ENCRYPTION_VERSION);
+
+        // Opens into a note the SDK can spend
+        let opened = open_stealth_note(&keys, &commitment, &encrypted).unwrap();
+        assert_eq!((opened.amount, opened.asset_id), (500, asset_id(&mint)));
+        assert!(opened.stealth_tweak.is_some());
+        assert!(open_stealth_note(&keys, &[0u8; 32], &encrypted).is_none());
+        let other = StealthKeys::from_secrets(&[3u8; 32], &[2u8; 32]);
+        assert!(open_stealth_note(&other, &commitment, &encrypted).is_none());
+
+        // Each format's reader turns the other's notes away by version
+        let viewing_key = ViewingKey::random(&mut OsRng);
+        assert!(matches!(
+            viewing_key.decrypt(&encrypted),
+            Err(SdkError::UnsupportedEncryptionVersion(STEALTH_ENCRYPTION_VERSION))
+        ));
+        let x25519 = encrypt_note(&note(), &viewing_key.public_key(), &mut OsRng).unwrap();
+        assert!(open_stealth_note(&keys, &commitment, &x25519).is_none());
+    }
+}
@@ -0,0 +1,86 @@
+//! SDK errors
+
+use thiserror::Error;
+use veil_core::crypto::merkle::MerkleError;
+
+/// Errors of the Veil SDK
+#[derive(Error, Debug)]
+pub enum SdkError {
+    #[error("Merkle error: {0}")]
+    Merkle(#[from] MerkleError),
+
+    #[error("Commitment not found in the tree")]
+    CommitmentNotFound,
+
+    #[error("Not a canonical field element")]
+    NonCanonicalField,
+
+    #[error("Proof generation failed: {0}")]
+    Proving(String),
+
+    #[error("{0} notes can't be spent with this circuit")]
+    UnsupportedNote(&'static str),
+
+    #[error("Fee {fee} exceeds the withdrawn amount {amount}")]
+    FeeExceedsAmount { fee: u64, amount: u64 },
+
+    #[error("Viewing key has low order")]
+    InvalidViewingKey,
+
+    #[error("Unsupported encrypted note version {0}")]
+    UnsupportedEncryptionVersion(u8),
+
+    #[error("Malformed encrypted note")]
+    MalformedEncryptedNote,
+
+    #[error("Encrypted note failed to decrypt")]
+    DecryptionFailed,
+
+    #[error("Leaf {got} is past the {expected} leaves synced")]
+    LeafGap { expected: u64, got: u64 },
+
+    #[error("Leaf {leaf_index} differs from the one synced")]
+    LeafMismatch { leaf_index: u64 },
+
+    #[error("Leaves belong to another pool or tree epoch")]
+    WrongTree,
+
+    #[error("Synced root is not in the pool's root history; sync again")]
+    UnknownRoot,
+
+    #[error("Invalid account: {0}")]
+    InvalidAccount(&'static str),
+
+    #[error("Malformed tree file")]
+    MalformedTreeFile,
+
+    #[error("Unsupported tree file version {0}")]
+    UnsupportedTreeFileVersion(u8),
+
+    #[error("Wrong password or corrupted backup")]
+    WrongPassword,
+
+    #[error("Malformed backup")]
+    MalformedBackup,
+
+    #[error("Unsupported backup version {0}")]
+    UnsupportedBackupVersion(u8),
+
+    #[error("Backup key derivation parameters are out of range")]
+    InvalidKdfParams,
+
+    #[error("Prover key has low order")]
+    InvalidProverKey,
+
+    #[error("Unsupported remote proving message version {0}")]
+    UnsupportedRemoteVersion(u8),
+
+    #[error("Malformed remote proving message")]
+    MalformedRemoteMessage,
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Result type of the SDK
+pub type Result<T> = std::result::Result<T, SdkError>;
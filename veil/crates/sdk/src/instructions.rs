@@ -0,0 +1,73 @@
+//! Instruction builders
+//!
+//! Every program instruction has its accounts struct in `accounts` and its
+//! arguments in `instruction`, generated by Anchor from the program itself,
+//! so they can't drift from its account orders or discriminators. `build`
+//! turns a pair into an `Instruction`:
+//!
+//! ```ignore
+//! let accounts = accounts::SetRelayerPolicy { pool, authority, .. };
+//! let data = instruction::SetRelayerPolicy { policy: 2, allowed_relayers };
+//! let ix = instructions::build(&accounts, &data);
+//! ```
+//!
+//! The accounts of `veil_cpi` derive the PDAs of the common spends and
+//! build with `build` as well. Optional accounts left as `None` are passed
+//! as the program ID, as Anchor expects.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::InstructionData;
+
+pub use veil_program::{accounts, instruction};
+
+/// Build an instruction calling the Veil program
+pub fn build(accounts: &impl ToAccountMetas, data: &impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: veil_program::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::Discriminator;
+
+    #[test]
+    fn test_program_data_matches_cpi_builders() {
+        let pool = Pubkey::new_unique();
+        let accounts = veil_cpi::accounts::UnshieldSol::new(
+            pool,
+            &[1u8; 32],
+            1,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let data = instruction::UnshieldSol {
+            nullifier: [1u8; 32],
+            root: [2u8; 32],
+            amount: 1_000_000_000,
+            fee: 3_000_000,
+            refund: 0,
+            expiry_slot: 100,
+            proof: vec![7; 257],
+        };
+        let cpi_data = veil_cpi::instruction::UnshieldSol {
+            nullifier: data.nullifier,
+            root: data.root,
+            amount: data.amount,
+            fee: data.fee,
+            refund: data.refund,
+            expiry_slot: data.expiry_slot,
+            proof: data.proof.clone(),
+        };
+
+        let ix = build(&accounts, &data);
+        assert_eq!(ix.program_id, veil_cpi::ID);
+        assert_eq!(ix.data[..8], instruction::UnshieldSol::DISCRIMINATOR);
+        assert_eq!(ix, veil_cpi::unshield_sol(&accounts, &cpi_data));
+    }
+}
@@ -0,0 +1,63 @@
+//! Veil SDK
+//!
+//! Everything a Rust client needs to use the Veil program: notes and the
+//! commitments and nullifiers the circuits derive from them, Merkle paths
+//! over a pool's commitments, Groth16 proofs in the program's encoding and
+//! instructions for every program entrypoint.
+//!
+//! # Modules
+//! - `note`: Notes, their commitments and nullifiers, and field encodings
+//! - `tree`: A pool's commitment tree, rebuilt from its leaves, and the
+//!   spend witnesses taken from it
+//! - `sync`: A local copy of a pool's tree, synced from its leaf archive,
+//!   persisted and checked against the pool's root history
+//! - `encryption`: The encrypted notes logged with commitments, and the
+//!   viewing and stealth keys that open them
+//! - `backup`: Password-encrypted note backups, to move a wallet's notes
+//!   between devices
+//! - `prover`: Transfer and withdrawal proofs, with the public values the
+//!   instructions take
+//! - `remote`: Proof requests sealed to a remote proving server, for
+//!   devices too weak to prove
+//! - `scanner`: Finds a wallet's notes and their spends in the program's
+//!   events with its viewing key
+//! - `instructions`: Builders for every program instruction (default
+//!   `instructions` feature; without it the crate builds for wasm32)
+//!
+//! A withdrawal, given the pool's leaves and the withdraw proving key:
+//!
+//! ```ignore
+//! let tree = CommitmentTree::from_leaves(&leaves)?;
+//! let spend = tree.spend(note)?;
+//! let params = UnshieldParams { recipient, relayer, fee, refund: 0, expiry_slot };
+//! let proof = prover::prove_unshield(&pk, &spend, &pool, &Pubkey::default(), &params, &mut OsRng)?;
+//! let accounts = veil_cpi::accounts::UnshieldSol::new(pool, &proof.nullifier, 1, recipient, treasury, relayer);
+//! let instruction = instructions::build(&accounts, &proof.unshield_sol());
+//! ```
+
+pub mod backup;
+mod codec;
+pub mod encryption;
+pub mod error;
+#[cfg(feature = "instructions")]
+pub mod instructions;
+pub mod note;
+pub mod prover;
+pub mod remote;
+pub mod scanner;
+pub mod sync;
+pub mod tree;
+
+pub use backup::{BackupNote, KdfParams};
+pub use encryption::ViewingKey;
+pub use error::{Result, SdkError};
+pub use note::Note;
+pub use prover::{TransferProof, UnshieldParams, UnshieldProof};
+pub use remote::{ProofRequest, ProofResponse, ProverKey};
+pub use scanner::{OwnedNote, ScanEvent, Scanner};
+pub use sync::TreeSync;
+pub use tree::{CommitmentTree, Spend};
+#[cfg(feature = "instructions")]
+pub use veil_cpi::pda;
+#[cfg(feature = "instructions")]
+pub use veil_program::ID;
@@ -0,0 +1,146 @@
+//! Notes
+//!
+//! A note is the opening of a commitment in a pool's tree. Its derivations
+//! are the circuits' own (`veil_circuits::note`), so commitments built here
+//! are the ones the circuits prove membership of and nullifiers are the ones
+//! the program records.
+//!
+//! Field elements cross into instructions as 32 big-endian bytes, the
+//! encoding the program hands the alt_bn128 syscalls.
+
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use rand::{CryptoRng, RngCore};
+use solana_program::pubkey::Pubkey;
+use veil_circuits::note;
+
+use crate::error::{Result, SdkError};
+
+/// Encode a field element as the program passes it
+pub fn fr_to_bytes(value: &Fr) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&value.into_bigint().to_bytes_be());
+    bytes
+}
+
+/// Decode a field element from program bytes, rejecting values >= r
+pub fn fr_from_bytes(bytes: &[u8; 32]) -> Result<Fr> {
+    let value = Fr::from_be_bytes_mod_order(bytes);
+    if fr_to_bytes(&value) != *bytes {
+        return Err(SdkError::NonCanonicalField);
+    }
+    Ok(value)
+}
+
+/// A pubkey as the field element the program passes for it
+pub fn pubkey_to_field(pubkey: &Pubkey) -> Fr {
+    note::bytes_to_field(&pubkey.to_bytes())
+}
+
+/// Tag binding nullifiers to `pool` and its mint (default pubkey for SOL)
+pub fn nullifier_domain(pool: &Pubkey, mint: &Pubkey) -> Fr {
+    note::nullifier_domain(&pool.to_bytes(), &mint.to_bytes())
+}
+
+/// Asset ID notes of `mint` commit to (0 for SOL and wSOL)
+pub fn asset_id(mint: &Pubkey) -> u64 {
+    note::asset_id(&mint.to_bytes())
+}
+
+/// A shielded note
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Note {
+    /// Owner's secret, from which the spending key derives
+    pub secret: Fr,
+    pub amount: u64,
+    pub blinding: Fr,
+    pub asset_id: u64,
+    /// Tweak of a note sent to the owner's stealth address, `None` for a
+    /// note of the owner's own spending key
+    pub stealth_tweak: Option<Fr>,
+}
+
+impl Note {
+    /// A note of `amount` owned by `secret`
+    pub fn new(secret: Fr, amount: u64, blinding: Fr, asset_id: u64) -> Self {
+        Self { secret, amount, blinding, asset_id, stealth_tweak: None }
+    }
+
+    /// A note of `amount` of `mint` with a fresh secret and blinding
+    pub fn random<R: RngCore + CryptoRng>(amount: u64, mint: &Pubkey, rng: &mut R) -> Self {
+        Self::new(Fr::rand(rng), amount, Fr::rand(rng), asset_id(mint))
+    }
+
+    /// The note sent to the owner's stealth address under `tweak`
+    pub fn with_stealth_tweak(mut self, tweak: Fr) -> Self {
+        self.stealth_tweak = Some(tweak);
+        self
+    }
+
+    /// The key owning the note: the one-time key for a stealth note
+    pub fn spending_key(&self) -> Fr {
+        let spending_key = note::spending_key(&self.secret);
+        match &self.stealth_tweak {
+            Some(tweak) => note::stealth_spending_key(&spending_key, tweak),
+            None => spending_key,
+        }
+    }
+
+    /// The commitment shielded for the note
+    pub fn commitment(&self) -> Fr {
+        note::note_commitment(
+            &self.spending_key(),
+            &Fr::from(self.amount),
+            &self.blinding,
+            &Fr::from(self.asset_id),
+        )
+    }
+
+    /// The commitment as `shield` and `shield_sol` take it
+    pub fn commitment_bytes(&self) -> [u8; 32] {
+        fr_to_bytes(&self.commitment())
+    }
+
+    /// The nullifier spending the note at `leaf_index` of `pool`
+    pub fn nullifier(&self, leaf_index: u64, pool: &Pubkey, mint: &Pubkey) -> Fr {
+        note::nullifier(&self.spending_key(), leaf_index, &nullifier_domain(pool, mint))
+    }
+
+    /// The same note under a new blinding, as a transfer outputs it
+    pub fn refreshed(&self, blinding: Fr) -> Self {
+        Self { blinding, ..self.clone() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_field_encoding_round_trips() {
+        let value = Fr::rand(&mut OsRng);
+        assert_eq!(fr_from_bytes(&fr_to_bytes(&value)).unwrap(), value);
+        assert_eq!(fr_to_bytes(&Fr::from(1u64))[31], 1);
+        assert!(matches!(fr_from_bytes(&[0xff; 32]), Err(SdkError::NonCanonicalField)));
+    }
+
+    #[test]
+    fn test_nullifiers_are_bound_to_pool_and_index() {
+        let note = Note::random(1_000_000_000, &Pubkey::default(), &mut OsRng);
+        let (pool, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mint = Pubkey::default();
+
+        assert_eq!(note.nullifier(3, &pool, &mint), note.nullifier(3, &pool, &mint));
+        assert_ne!(note.nullifier(3, &pool, &mint), note.nullifier(4, &pool, &mint));
+        assert_ne!(note.nullifier(3, &pool, &mint), note.nullifier(3, &other, &mint));
+    }
+
+    #[test]
+    fn test_stealth_note_has_its_own_commitment() {
+        let note = Note::random(5, &Pubkey::default(), &mut OsRng);
+        let stealth = note.clone().with_stealth_tweak(Fr::from(7u64));
+        assert_ne!(note.commitment(), stealth.commitment());
+        assert_eq!(note.refreshed(note.blinding), note);
+    }
+}
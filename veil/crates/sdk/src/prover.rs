@@ -0,0 +1,284 @@
+//! Proof generation
+//!
+//! Groth16 proofs over BN254 with arkworks, encoded as the program reads
+//! them: the arkworks format byte followed by the uncompressed proof points
+//! (see `groth16` in the program). Each prover returns the public values
+//! the proof binds, ready for the spend instruction's arguments.
+//!
+//! Proving keys come from the trusted setup of the pool's registered
+//! verifying keys; `veil_circuits::setup` keys only verify against keys
+//! registered from the same setup.
+
+use ark_bn254::{Bn254, Fr};
+use ark_ff::UniformRand;
+use ark_groth16::{Groth16, Proof, ProvingKey};
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_serialize::CanonicalSerialize;
+use ark_snark::SNARK;
+use rand::{CryptoRng, RngCore};
+use solana_program::pubkey::Pubkey;
+use veil_circuits::{TransferCircuit, UnshieldCircuit};
+#[cfg(feature = "instructions")]
+use veil_program::instruction;
+
+use crate::error::{Result, SdkError};
+use crate::note::{fr_to_bytes, nullifier_domain, pubkey_to_field, Note};
+use crate::tree::Spend;
+
+/// Format byte of arkworks-encoded proofs
+const ARKWORKS_PROOF_FORMAT: u8 = 1;
+
+/// Encode a proof as spend instructions take it
+pub fn encode_proof(proof: &Proof<Bn254>) -> Result<Vec<u8>> {
+    let mut bytes = vec![ARKWORKS_PROOF_FORMAT];
+    proof
+        .serialize_uncompressed(&mut bytes)
+        .map_err(|err| SdkError::Proving(err.to_string()))?;
+    Ok(bytes)
+}
+
+/// Prove `circuit` and encode the proof
+pub fn prove<C, R>(pk: &ProvingKey<Bn254>, circuit: C, rng: &mut R) -> Result<Vec<u8>>
+where
+    C: ConstraintSynthesizer<Fr>,
+    R: RngCore + CryptoRng,
+{
+    let proof = Groth16::<Bn254>::prove(pk, circuit, rng)
+        .map_err(|err| SdkError::Proving(err.to_string()))?;
+    encode_proof(&proof)
+}
+
+/// A transfer proof and the values it binds
+#[derive(Clone, Debug)]
+pub struct TransferProof {
+    pub proof: Vec<u8>,
+    pub nullifier: [u8; 32],
+    pub root: [u8; 32],
+    pub new_commitment: [u8; 32],
+    pub expiry_slot: u64,
+    /// The note the transfer creates, to keep for spending it later
+    pub output: Note,
+}
+
+#[cfg(feature = "instructions")]
+impl TransferProof {
+    /// Arguments of `transfer`
+    pub fn transfer(&self, encrypted_note: Option<Vec<u8>>) -> instruction::Transfer {
+        instruction::Transfer {
+            nullifier: self.nullifier,
+            root: self.root,
+            new_commitment: self.new_commitment,
+            expiry_slot: self.expiry_slot,
+            proof: self.proof.clone(),
+            encrypted_note,
+        }
+    }
+}
+
+/// Prove a transfer of `spend`'s note in `pool` into a fresh note of the
+/// same owner and amount
+pub fn prove_transfer<R: RngCore + CryptoRng>(
+    pk: &ProvingKey<Bn254>,
+    spend: &Spend,
+    pool: &Pubkey,
+    mint: &Pubkey,
+    expiry_slot: u64,
+    rng: &mut R,
+) -> Result<TransferProof> {
+    let note = &spend.note;
+    if note.stealth_tweak.is_some() {
+        return Err(SdkError::UnsupportedNote("Stealth"));
+    }
+    let output = note.refreshed(Fr::rand(rng));
+    let nullifier = note.nullifier(spend.leaf_index, pool, mint);
+    let circuit = TransferCircuit::new(
+        spend.root,
+        nullifier,
+        output.commitment(),
+        expiry_slot,
+        nullifier_domain(pool, mint),
+        note.secret,
+        Fr::from(note.amount),
+        note.blinding,
+        Fr::from(note.asset_id),
+        spend.leaf_index,
+        spend.path.siblings.clone(),
+        spend.path.indices.clone(),
+        output.blinding,
+    );
+
+    Ok(TransferProof {
+        proof: prove(pk, circuit, rng)?,
+        nullifier: fr_to_bytes(&nullifier),
+        root: fr_to_bytes(&spend.root),
+        new_commitment: output.commitment_bytes(),
+        expiry_slot,
+        output,
+    })
+}
+
+/// Terms of a withdrawal
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnshieldParams {
+    pub recipient: Pubkey,
+    /// Submits the withdrawal and is paid `fee`
+    pub relayer: Pubkey,
+    pub fee: u64,
+    /// Lamports the relayer forwards to the recipient
+    pub refund: u64,
+    /// Last slot at which the proof may be submitted
+    pub expiry_slot: u64,
+}
+
+/// A withdrawal proof and the values it binds
+#[derive(Clone, Debug)]
+pub struct UnshieldProof {
+    pub proof: Vec<u8>,
+    pub nullifier: [u8; 32],
+    pub root: [u8; 32],
+    pub amount: u64,
+    pub fee: u64,
+    pub refund: u64,
+    pub expiry_slot: u64,
+}
+
+#[cfg(feature = "instructions")]
+impl UnshieldProof {
+    /// Arguments of `unshield_sol`
+    pub fn unshield_sol(&self) -> instruction::UnshieldSol {
+        instruction::UnshieldSol {
+            nullifier: self.nullifier,
+            root: self.root,
+            amount: self.amount,
+            fee: self.fee,
+            refund: self.refund,
+            expiry_slot: self.expiry_slot,
+            proof: self.proof.clone(),
+        }
+    }
+
+    /// Arguments of `unshield`
+    pub fn unshield(&self) -> instruction::Unshield {
+        instruction::Unshield {
+            nullifier: self.nullifier,
+            root: self.root,
+            amount: self.amount,
+            fee: self.fee,
+            refund: self.refund,
+            expiry_slot: self.expiry_slot,
+            proof: self.proof.clone(),
+        }
+    }
+}
+
+/// Prove a withdrawal of all of `spend`'s note from `pool`
+pub fn prove_unshield<R: RngCore + CryptoRng>(
+    pk: &ProvingKey<Bn254>,
+    spend: &Spend,
+    pool: &Pubkey,
+    mint: &Pubkey,
+    params: &UnshieldParams,
+    rng: &mut R,
+) -> Result<UnshieldProof> {
+    let note = &spend.note;
+    if params.fee > note.amount {
+        return Err(SdkError::FeeExceedsAmount { fee: params.fee, amount: note.amount });
+    }
+    let nullifier = note.nullifier(spend.leaf_index, pool, mint);
+    let mut circuit = UnshieldCircuit::new(
+        spend.root,
+        nullifier,
+        pubkey_to_field(&params.recipient),
+        note.amount,
+        pubkey_to_field(&params.relayer),
+        params.fee,
+        params.refund,
+        params.expiry_slot,
+        nullifier_domain(pool, mint),
+        note.secret,
+        note.blinding,
+        Fr::from(note.asset_id),
+        spend.leaf_index,
+        spend.path.siblings.clone(),
+        spend.path.indices.clone(),
+    );
+    if let Some(tweak) = note.stealth_tweak {
+        circuit = circuit.with_stealth_tweak(tweak);
+    }
+
+    Ok(UnshieldProof {
+        proof: prove(pk, circuit, rng)?,
+        nullifier: fr_to_bytes(&nullifier),
+        root: fr_to_bytes(&spend.root),
+        amount: note.amount,
+        fee: params.fee,
+        refund: params.refund,
+        expiry_slot: params.expiry_slot,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_serialize::CanonicalDeserialize;
+    use rand::rngs::OsRng;
+    use veil_circuits::setup;
+
+    use crate::note::fr_from_bytes;
+    use crate::tree::CommitmentTree;
+
+    #[test]
+    fn test_unshield_proof_verifies() {
+        let (pool, mint) = (Pubkey::new_unique(), Pubkey::default());
+        let note = Note::random(1_000_000_000, &mint, &mut OsRng);
+        let tree = CommitmentTree::from_leaves(&[[1u8; 32], note.commitment_bytes()]).unwrap();
+        let spend = tree.spend(note).unwrap();
+        let (pk, vk) = setup(UnshieldCircuit::blank()).unwrap();
+
+        let params = UnshieldParams {
+            recipient: Pubkey::new_unique(),
+            relayer: Pubkey::new_unique(),
+            fee: 3_000_000,
+            refund: 0,
+            expiry_slot: 100,
+        };
+        let unshield = prove_unshield(&pk, &spend, &pool, &mint, &params, &mut OsRng).unwrap();
+        assert_eq!(unshield.proof.len(), 257);
+        assert_eq!(unshield.proof[0], ARKWORKS_PROOF_FORMAT);
+
+        // Public inputs in the program's withdraw order
+        let inputs = [
+            fr_from_bytes(&unshield.root).unwrap(),
+            fr_from_bytes(&unshield.nullifier).unwrap(),
+            pubkey_to_field(&params.recipient),
+            Fr::from(unshield.amount),
+            pubkey_to_field(&params.relayer),
+            Fr::from(unshield.fee),
+            Fr::from(unshield.refund),
+            Fr::from(unshield.expiry_slot),
+            nullifier_domain(&pool, &mint),
+        ];
+        let proof = Proof::deserialize_uncompressed(&unshield.proof[1..]).unwrap();
+        assert!(Groth16::<Bn254>::verify(&vk, &inputs, &proof).unwrap());
+    }
+
+    #[test]
+    fn test_fee_above_amount_is_rejected() {
+        let note = Note::random(10, &Pubkey::default(), &mut OsRng);
+        let tree = CommitmentTree::from_leaves(&[note.commitment_bytes()]).unwrap();
+        let spend = tree.spend(note).unwrap();
+        let (pk, _) = setup(UnshieldCircuit::blank()).unwrap();
+        let params = UnshieldParams {
+            recipient: Pubkey::new_unique(),
+            relayer: Pubkey::new_unique(),
+            fee: 11,
+            refund: 0,
+            expiry_slot: 100,
+        };
+        let pool = Pubkey::new_unique();
+        assert!(matches!(
+            prove_unshield(&pk, &spend, &pool, &Pubkey::default(), &params, &mut OsRng),
+            Err(SdkError::FeeExceedsAmount { fee: 11, amount: 10 })
+        ));
+    }
+}
@@ -0,0 +1,487 @@
+//! Remote proving
+//!
+//! Lets a device too weak to prove hand its witness to a proving server
+//! (`veil-prover`) and get the proof back. The witness is the note's full
+//! opening, so the server can spend the note: use a server you run or
+//! trust with the funds. Requests are sealed to the server's x25519 key and
+//! responses to a key only the requester derives, so the witness and the
+//! proof's output note are unreadable to anyone between the two, TLS
+//! terminators included.
+//!
+//! A request is
+//!
+//! ```text
+//! ephemeral_key (32) || version (1) || ChaCha20-Poly1305(request) || tag (16)
+//! ```
+//!
+//! and its response `version (1) || ChaCha20-Poly1305(response) || tag (16)`.
+//! Both keys hash the Diffie-Hellman secret of the ephemeral key and the
+//! server's key, each under its own domain, and are used once, so nonces
+//! are zero. Headers are associated data.
+//!
+//! Version 1 request plaintext:
+//!
+//! ```text
+//! kind (1) || pool (32) || mint (32) || note || leaf_index (8) || root (32)
+//!     || siblings (32 each, leaf to root) || indices (1 each)
+//!     || expiry_slot (8)                                  (transfer)
+//!     || recipient (32) || relayer (32) || fee (8) || refund (8)
+//!        || expiry_slot (8)                               (unshield)
+//! note = flags (1) || secret (32) || blinding (32) || amount (8)
+//!     || asset_id (8) [|| stealth_tweak (32)]
+//! ```
+//!
+//! and response plaintext:
+//!
+//! ```text
+//! kind (1) || proof_len (2) || proof || nullifier (32) || root (32)
+//!     || new_commitment (32) || expiry_slot (8) || note    (transfer)
+//!     || amount (8) || fee (8) || refund (8) || expiry_slot (8)
+//!                                                         (unshield)
+//! ```
+//!
+//! Integers are little-endian, field elements big-endian.
+
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::ProvingKey;
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use solana_program::pubkey::Pubkey;
+use veil_core::crypto::merkle::{MerklePath, TREE_DEPTH};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::codec::Reader;
+use crate::error::{Result, SdkError};
+use crate::note::{fr_to_bytes, Note};
+use crate::prover::{self, TransferProof, UnshieldParams, UnshieldProof};
+use crate::tree::Spend;
+
+/// Version of the request and response formats
+pub const REMOTE_VERSION: u8 = 1;
+
+/// Domain separator of request keys
+const REQUEST_DOMAIN: &[u8] = b"VEIL_PROVER_REQUEST_V1";
+
+/// Domain separator of response keys
+const RESPONSE_DOMAIN: &[u8] = b"VEIL_PROVER_RESPONSE_V1";
+
+const KIND_TRANSFER: u8 = 1;
+const KIND_UNSHIELD: u8 = 2;
+
+/// Note flag: a stealth tweak follows the note
+const FLAG_STEALTH: u8 = 1;
+
+/// What a proving server is asked to prove
+#[derive(Clone, Debug)]
+pub enum ProofRequest {
+    /// `prover::prove_transfer` of `spend`
+    Transfer { spend: Spend, pool: Pubkey, mint: Pubkey, expiry_slot: u64 },
+    /// `prover::prove_unshield` of `spend`
+    Unshield { spend: Spend, pool: Pubkey, mint: Pubkey, params: UnshieldParams },
+}
+
+/// A proving server's answer
+#[derive(Clone, Debug)]
+pub enum ProofResponse {
+    Transfer(TransferProof),
+    Unshield(UnshieldProof),
+}
+
+impl ProofRequest {
+    /// Name of the circuit the request is proven with
+    pub fn circuit(&self) -> &'static str {
+        match self {
+            Self::Transfer { .. } => "transfer",
+            Self::Unshield { .. } => "unshield",
+        }
+    }
+
+    /// Prove the request with the circuit's proving key
+    pub fn prove<R: RngCore + CryptoRng>(
+        &self,
+        pk: &ProvingKey<Bn254>,
+        rng: &mut R,
+    ) -> Result<ProofResponse> {
+        match self {
+            Self::Transfer { spend, pool, mint, expiry_slot } => Ok(ProofResponse::Transfer(
+                prover::prove_transfer(pk, spend, pool, mint, *expiry_slot, rng)?,
+            )),
+            Self::Unshield { spend, pool, mint, params } => Ok(ProofResponse::Unshield(
+                prover::prove_unshield(pk, spend, pool, mint, params, rng)?,
+            )),
+        }
+    }
+
+    /// Seal the request to the server key `server`, returning the body to
+    /// send and the key opening its response
+    pub fn seal<R: RngCore + CryptoRng>(
+        &self,
+        server: &[u8; 32],
+        rng: &mut R,
+    ) -> Result<(Vec<u8>, ResponseKey)> {
+        let server = PublicKey::from(*server);
+        let mut secret = [0u8; 32];
+        rng.fill_bytes(&mut secret);
+        let ephemeral_secret = StaticSecret::from(secret);
+        let ephemeral_key = PublicKey::from(&ephemeral_secret);
+        let keys = SessionKeys::derive(&ephemeral_secret, &server, &ephemeral_key, &server)
+            .ok_or(SdkError::InvalidProverKey)?;
+
+        let mut body = ephemeral_key.as_bytes().to_vec();
+        body.push(REMOTE_VERSION);
+        let ciphertext = seal(&keys.request, &self.encode(), &body)?;
+        body.extend_from_slice(&ciphertext);
+        Ok((body, ResponseKey(keys.response)))
+    }
+
+    /// Open a sealed request with the server's key, returning it and the
+    /// key to seal its response with
+    pub fn open(server: &ProverKey, body: &[u8]) -> Result<(Self, ResponseKey)> {
+        let mut reader = Reader::new(body, || SdkError::MalformedRemoteMessage);
+        let ephemeral_key = PublicKey::from(reader.take::<32>()?);
+        let version = reader.u8()?;
+        if version != REMOTE_VERSION {
+            return Err(SdkError::UnsupportedRemoteVersion(version));
+        }
+        let public_key = PublicKey::from(&server.0);
+        let keys = SessionKeys::derive(&server.0, &ephemeral_key, &ephemeral_key, &public_key)
+            .ok_or(SdkError::DecryptionFailed)?;
+        let (header, ciphertext) = body.split_at(33);
+        let request = Self::decode(&open(&keys.request, ciphertext, header)?)?;
+        Ok((request, ResponseKey(keys.response)))
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let (kind, spend, pool, mint) = match self {
+            Self::Transfer { spend, pool, mint, .. } => (KIND_TRANSFER, spend, pool, mint),
+            Self::Unshield { spend, pool, mint, .. } => (KIND_UNSHIELD, spend, pool, mint),
+        };
+        bytes.push(kind);
+        bytes.extend_from_slice(pool.as_ref());
+        bytes.extend_from_slice(mint.as_ref());
+        encode_note(&mut bytes, &spend.note);
+        bytes.extend_from_slice(&spend.leaf_index.to_le_bytes());
+        bytes.extend_from_slice(&fr_to_bytes(&spend.root));
+        for sibling in &spend.path.siblings {
+            bytes.extend_from_slice(&fr_to_bytes(sibling));
+        }
+        bytes.extend(spend.path.indices.iter().map(|&right| right as u8));
+        match self {
+            Self::Transfer { expiry_slot, .. } => {
+                bytes.extend_from_slice(&expiry_slot.to_le_bytes());
+            }
+            Self::Unshield { params, .. } => {
+                bytes.extend_from_slice(params.recipient.as_ref());
+                bytes.extend_from_slice(params.relayer.as_ref());
+                bytes.extend_from_slice(&params.fee.to_le_bytes());
+                bytes.extend_from_slice(&params.refund.to_le_bytes());
+                bytes.extend_from_slice(&params.expiry_slot.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes, || SdkError::MalformedRemoteMessage);
+        let kind = reader.u8()?;
+        let pool = reader.pubkey()?;
+        let mint = reader.pubkey()?;
+        let note = decode_note(&mut reader)?;
+        let leaf_index = reader.u64()?;
+        let root = reader.field()?;
+        let siblings = (0..TREE_DEPTH).map(|_| reader.field()).collect::<Result<Vec<Fr>>>()?;
+        let indices = reader
+            .slice(TREE_DEPTH)?
+            .iter()
+            .map(|&index| match index {
+                0 => Ok(false),
+                1 => Ok(true),
+                _ => Err(SdkError::MalformedRemoteMessage),
+            })
+            .collect::<Result<Vec<bool>>>()?;
+        let path = MerklePath { siblings, indices, leaf_index };
+        let spend = Spend { note, leaf_index, root, path };
+
+        let request = match kind {
+            KIND_TRANSFER => Self::Transfer { spend, pool, mint, expiry_slot: reader.u64()? },
+            KIND_UNSHIELD => {
+                let params = UnshieldParams {
+                    recipient: reader.pubkey()?,
+                    relayer: reader.pubkey()?,
+                    fee: reader.u64()?,
+                    refund: reader.u64()?,
+                    expiry_slot: reader.u64()?,
+                };
+                Self::Unshield { spend, pool, mint, params }
+            }
+            _ => return Err(SdkError::MalformedRemoteMessage),
+        };
+        reader.finish()?;
+        Ok(request)
+    }
+}
+
+impl ProofResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let (kind, proof) = match self {
+            Self::Transfer(transfer) => (KIND_TRANSFER, &transfer.proof),
+            Self::Unshield(unshield) => (KIND_UNSHIELD, &unshield.proof),
+        };
+        bytes.push(kind);
+        bytes.extend_from_slice(&(proof.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(proof);
+        match self {
+            Self::Transfer(transfer) => {
+                bytes.extend_from_slice(&transfer.nullifier);
+                bytes.extend_from_slice(&transfer.root);
+                bytes.extend_from_slice(&transfer.new_commitment);
+                bytes.extend_from_slice(&transfer.expiry_slot.to_le_bytes());
+                encode_note(&mut bytes, &transfer.output);
+            }
+            Self::Unshield(unshield) => {
+                bytes.extend_from_slice(&unshield.nullifier);
+                bytes.extend_from_slice(&unshield.root);
+                bytes.extend_from_slice(&unshield.amount.to_le_bytes());
+                bytes.extend_from_slice(&unshield.fee.to_le_bytes());
+                bytes.extend_from_slice(&unshield.refund.to_le_bytes());
+                bytes.extend_from_slice(&unshield.expiry_slot.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes, || SdkError::MalformedRemoteMessage);
+        let kind = reader.u8()?;
+        let proof_len = u16::from_le_bytes(reader.take()?) as usize;
+        let proof = reader.slice(proof_len)?.to_vec();
+        let nullifier = reader.take()?;
+        let root = reader.take()?;
+        let response = match kind {
+            KIND_TRANSFER => Self::Transfer(TransferProof {
+                proof,
+                nullifier,
+                root,
+                new_commitment: reader.take()?,
+                expiry_slot: reader.u64()?,
+                output: decode_note(&mut reader)?,
+            }),
+            KIND_UNSHIELD => Self::Unshield(UnshieldProof {
+                proof,
+                nullifier,
+                root,
+                amount: reader.u64()?,
+                fee: reader.u64()?,
+                refund: reader.u64()?,
+                expiry_slot: reader.u64()?,
+            }),
+            _ => return Err(SdkError::MalformedRemoteMessage),
+        };
+        reader.finish()?;
+        Ok(response)
+    }
+}
+
+/// The key a request's response is sealed under
+pub struct ResponseKey([u8; 32]);
+
+impl ResponseKey {
+    /// Seal `response` for the requester
+    pub fn seal(&self, response: &ProofResponse) -> Result<Vec<u8>> {
+        let mut body = vec![REMOTE_VERSION];
+        let ciphertext = seal(&self.0, &response.encode(), &body)?;
+        body.extend_from_slice(&ciphertext);
+        Ok(body)
+    }
+
+    /// Open the response to the request this key was returned with
+    pub fn open(&self, body: &[u8]) -> Result<ProofResponse> {
+        let version = *body.first().ok_or(SdkError::MalformedRemoteMessage)?;
+        if version != REMOTE_VERSION {
+            return Err(SdkError::UnsupportedRemoteVersion(version));
+        }
+        let (header, ciphertext) = body.split_at(1);
+        ProofResponse::decode(&open(&self.0, ciphertext, header)?)
+    }
+}
+
+/// A proving server's x25519 key, which requests are sealed to
+#[derive(Clone)]
+pub struct ProverKey(StaticSecret);
+
+impl ProverKey {
+    /// A server key from its 32 secret bytes
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(StaticSecret::from(bytes))
+    }
+
+    /// A fresh server key
+    pub fn random<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let mut bytes = [0u8; 32];
+        rng.fill_bytes(&mut bytes);
+        Self::from_bytes(bytes)
+    }
+
+    /// The secret bytes, as `from_bytes` takes them
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    /// The public key clients seal requests to
+    pub fn public_key(&self) -> [u8; 32] {
+        *PublicKey::from(&self.0).as_bytes()
+    }
+}
+
+/// Keys of one request and its response
+struct SessionKeys {
+    request: [u8; 32],
+    response: [u8; 32],
+}
+
+impl SessionKeys {
+    /// Keys from the Diffie-Hellman secret of `secret` and the other
+    /// party's `public`, `None` if the secret is all zeros
+    fn derive(
+        secret: &StaticSecret,
+        public: &PublicKey,
+        ephemeral_key: &PublicKey,
+        server: &PublicKey,
+    ) -> Option<Self> {
+        let shared = secret.diffie_hellman(public);
+        if shared.as_bytes() == &[0u8; 32] {
+            return None;
+        }
+        let key = |domain: &[u8]| -> [u8; 32] {
+            Sha256::new()
+                .chain_update(domain)
+                .chain_update(shared.as_bytes())
+                .chain_update(ephemeral_key.as_bytes())
+                .chain_update(server.as_bytes())
+                .finalize()
+                .into()
+        };
+        Some(Self { request: key(REQUEST_DOMAIN), response: key(RESPONSE_DOMAIN) })
+    }
+}
+
+fn seal(key: &[u8; 32], plaintext: &[u8], header: &[u8]) -> Result<Vec<u8>> {
+    ChaCha20Poly1305::new(&Key::from(*key))
+        .encrypt(&Nonce::from([0u8; 12]), Payload { msg: plaintext, aad: header })
+        .map_err(|_| SdkError::MalformedRemoteMessage)
+}
+
+fn open(key: &[u8; 32], ciphertext: &[u8], header: &[u8]) -> Result<Vec<u8>> {
+    ChaCha20Poly1305::new(&Key::from(*key))
+        .decrypt(&Nonce::from([0u8; 12]), Payload { msg: ciphertext, aad: header })
+        .map_err(|_| SdkError::DecryptionFailed)
+}
+
+fn encode_note(bytes: &mut Vec<u8>, note: &Note) {
+    bytes.push(if note.stealth_tweak.is_some() { FLAG_STEALTH } else { 0 });
+    bytes.extend_from_slice(&fr_to_bytes(&note.secret));
+    bytes.extend_from_slice(&fr_to_bytes(&note.blinding));
+    bytes.extend_from_slice(&note.amount.to_le_bytes());
+    bytes.extend_from_slice(&note.asset_id.to_le_bytes());
+    if let Some(tweak) = &note.stealth_tweak {
+        bytes.extend_from_slice(&fr_to_bytes(tweak));
+    }
+}
+
+fn decode_note(reader: &mut Reader) -> Result<Note> {
+    let flags = reader.u8()?;
+    let secret = reader.field()?;
+    let blinding = reader.field()?;
+    let note = Note::new(secret, reader.u64()?, blinding, reader.u64()?);
+    match flags {
+        0 => Ok(note),
+        FLAG_STEALTH => Ok(note.with_stealth_tweak(reader.field()?)),
+        _ => Err(SdkError::MalformedRemoteMessage),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::CommitmentTree;
+    use rand::rngs::OsRng;
+
+    fn request() -> ProofRequest {
+        let note = Note::random(1_000, &Pubkey::default(), &mut OsRng)
+            .with_stealth_tweak(Fr::from(9u64));
+        let tree = CommitmentTree::from_leaves(&[[1u8; 32], note.commitment_bytes()]).unwrap();
+        let params = UnshieldParams {
+            recipient: Pubkey::new_unique(),
+            relayer: Pubkey::new_unique(),
+            fee: 10,
+            refund: 0,
+            expiry_slot: 50,
+        };
+        ProofRequest::Unshield {
+            spend: tree.spend(note).unwrap(),
+            pool: Pubkey::new_unique(),
+            mint: Pubkey::default(),
+            params,
+        }
+    }
+
+    #[test]
+    fn test_request_round_trip() {
+        let server = ProverKey::random(&mut OsRng);
+        let request = request();
+        let (body, client_key) = request.seal(&server.public_key(), &mut OsRng).unwrap();
+        let (opened, server_key) = ProofRequest::open(&server, &body).unwrap();
+        assert_eq!(opened.encode(), request.encode());
+        assert_eq!(opened.circuit(), "unshield");
+        let ProofRequest::Unshield { spend, .. } = &opened else { unreachable!() };
+        assert!(spend.path.verify(&spend.note.commitment(), &spend.root));
+
+        let output = Note::random(5, &Pubkey::default(), &mut OsRng);
+        let response = ProofResponse::Transfer(TransferProof {
+            proof: vec![1u8; 257],
+            nullifier: [2u8; 32],
+            root: [3u8; 32],
+            new_commitment: output.commitment_bytes(),
+            expiry_slot: 7,
+            output: output.clone(),
+        });
+        let sealed = server_key.seal(&response).unwrap();
+        let ProofResponse::Transfer(transfer) = client_key.open(&sealed).unwrap() else {
+            panic!("expected a transfer");
+        };
+        assert_eq!(transfer.proof, vec![1u8; 257]);
+        assert_eq!(transfer.output, output);
+    }
+
+    #[test]
+    fn test_rejects_other_keys_and_tampering() {
+        let server = ProverKey::random(&mut OsRng);
+        let (mut body, client_key) = request().seal(&server.public_key(), &mut OsRng).unwrap();
+        let other = ProverKey::random(&mut OsRng);
+        assert!(matches!(ProofRequest::open(&other, &body), Err(SdkError::DecryptionFailed)));
+
+        let (_, server_key) = ProofRequest::open(&server, &body).unwrap();
+        let response = ProofResponse::Unshield(UnshieldProof {
+            proof: vec![1u8; 257],
+            nullifier: [2u8; 32],
+            root: [3u8; 32],
+            amount: 1_000,
+            fee: 10,
+            refund: 0,
+            expiry_slot: 50,
+        });
+        let mut sealed = server_key.seal(&response).unwrap();
+        sealed[5] ^= 1;
+        assert!(matches!(client_key.open(&sealed), Err(SdkError::DecryptionFailed)));
+
+        body[32] = 2;
+        assert!(matches!(
+            ProofRequest::open(&server, &body),
+            Err(SdkError::UnsupportedRemoteVersion(2))
+        ));
+    }
+}
@@ -0,0 +1,350 @@
+//! Wallet scanning
+//!
+//! A `Scanner` keeps a wallet's note set current from the program's
+//! events: it trial-decrypts every `EncryptedNote` with the wallet's
+//! viewing key, and its stealth keys if it has a stealth address, and
+//! marks notes spent as their `NullifierSpent` events arrive. Nothing
+//! about the wallet's notes is queried from anyone.
+//!
+//! The program emits events as self-CPIs, so they are read from the inner
+//! instructions of its transactions, as returned by RPC `getTransaction`
+//! or an indexer. `ScanEvent::from_instruction` decodes one; other
+//! instructions and events decode to `None`, so a source can feed every
+//! inner instruction through it.
+//!
+//! Events may arrive in any order and more than once: a nullifier spent
+//! before its note is found is remembered, and a note found twice is kept
+//! once. Only watched pools are scanned, since a note's nullifier depends
+//! on the pool's mint.
+
+use std::collections::{HashMap, HashSet};
+
+use solana_program::pubkey::Pubkey;
+use veil_core::crypto::stealth::StealthKeys;
+
+use crate::encryption::{open_stealth_note, ViewingKey};
+use crate::note::{asset_id, fr_to_bytes, Note};
+
+/// An event the scanner consumes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScanEvent {
+    /// A commitment was inserted with an encrypted note
+    EncryptedNote {
+        pool: Pubkey,
+        tree_epoch: u64,
+        commitment: [u8; 32],
+        leaf_index: u64,
+        encrypted_note: Vec<u8>,
+    },
+    /// A nullifier was spent
+    NullifierSpent { pool: Pubkey, nullifier: [u8; 32] },
+}
+
+#[cfg(feature = "instructions")]
+impl ScanEvent {
+    /// The event an instruction of `program_id` carries, if it is an event
+    /// self-CPI of the Veil program with an event the scanner consumes
+    pub fn from_instruction(program_id: &Pubkey, data: &[u8]) -> Option<Self> {
+        use anchor_lang::event::EVENT_IX_TAG_LE;
+        use anchor_lang::{AnchorDeserialize, Discriminator};
+        use veil_program::events::{EncryptedNote, NullifierSpent};
+
+        if *program_id != veil_program::ID {
+            return None;
+        }
+        let event = data.strip_prefix(&EVENT_IX_TAG_LE[..])?;
+        if event.len() < 8 {
+            return None;
+        }
+        let (discriminator, mut body) = event.split_at(8);
+        if discriminator == EncryptedNote::DISCRIMINATOR {
+            let event = EncryptedNote::deserialize(&mut body).ok()?;
+            Some(Self::EncryptedNote {
+                pool: event.pool,
+                tree_epoch: event.tree_epoch,
+                commitment: event.commitment,
+                leaf_index: event.leaf_index,
+                encrypted_note: event.encrypted_note,
+            })
+        } else if discriminator == NullifierSpent::DISCRIMINATOR {
+            let event = NullifierSpent::deserialize(&mut body).ok()?;
+            Some(Self::NullifierSpent { pool: event.pool, nullifier: event.nullifier })
+        } else {
+            None
+        }
+    }
+}
+
+/// A note the scanner found, at its leaf
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OwnedNote {
+    /// Pool the note is in
+    pub pool: Pubkey,
+    /// Mint of the pool (default pubkey for SOL)
+    pub mint: Pubkey,
+    /// Tree epoch of the leaf
+    pub tree_epoch: u64,
+    /// Leaf index of the commitment
+    pub leaf_index: u64,
+    /// The note
+    pub note: Note,
+    /// Nullifier spending the note, as the program records it
+    pub nullifier: [u8; 32],
+    /// Whether the nullifier has been spent
+    pub spent: bool,
+}
+
+/// Finds a wallet's notes in the program's events
+pub struct Scanner {
+    viewing_key: ViewingKey,
+    /// Keys of the wallet's stealth address, if it has one
+    stealth_keys: Option<StealthKeys>,
+    /// Mint of each watched pool
+    pools: HashMap<Pubkey, Pubkey>,
+    notes: Vec<OwnedNote>,
+    /// Index into `notes` by nullifier
+    by_nullifier: HashMap<[u8; 32], usize>,
+    /// Nullifiers spent in watched pools
+    spent: HashSet<[u8; 32]>,
+}
+
+impl Scanner {
+    /// A scanner watching no pools yet
+    pub fn new(viewing_key: ViewingKey) -> Self {
+        Self {
+            viewing_key,
+            stealth_keys: None,
+            pools: HashMap::new(),
+            notes: Vec::new(),
+            by_nullifier: HashMap::new(),
+            spent: HashSet::new(),
+        }
+    }
+
+    /// Also find notes sent to the stealth address of `keys`
+    pub fn scan_stealth(&mut self, keys: StealthKeys) {
+        self.stealth_keys = Some(keys);
+    }
+
+    /// Scan `pool`, whose notes are of `mint`
+    pub fn watch(&mut self, pool: Pubkey, mint: Pubkey) {
+        self.pools.insert(pool, mint);
+    }
+
+    /// Resume from notes found by an earlier scan
+    pub fn restore(&mut self, notes: impl IntoIterator<Item = OwnedNote>) {
+        for note in notes {
+            if note.spent {
+                self.spent.insert(note.nullifier);
+            }
+            self.add(note);
+        }
+    }
+
+    /// Apply one event, returning the note it revealed, if any
+    pub fn ingest(&mut self, event: ScanEvent) -> Option<&OwnedNote> {
+        match event {
+            ScanEvent::EncryptedNote {
+                pool,
+                tree_epoch,
+                commitment,
+                leaf_index,
+                encrypted_note,
+            } => {
+                let mint = *self.pools.get(&pool)?;
+                let note = self.viewing_key.try_open(&commitment, &encrypted_note).or_else(|| {
+                    let keys = self.stealth_keys.as_ref()?;
+                    open_stealth_note(keys, &commitment, &encrypted_note)
+                })?;
+                if note.asset_id != asset_id(&mint) {
+                    return None;
+                }
+                let nullifier = fr_to_bytes(&note.nullifier(leaf_index, &pool, &mint));
+                let spent = self.spent.contains(&nullifier);
+                let found =
+                    OwnedNote { pool, mint, tree_epoch, leaf_index, note, nullifier, spent };
+                self.add(found).map(|index| &self.notes[index])
+            }
+            ScanEvent::NullifierSpent { pool, nullifier } => {
+                if self.pools.contains_key(&pool) {
+                    self.spent.insert(nullifier);
+                    if let Some(&index) = self.by_nullifier.get(&nullifier) {
+                        self.notes[index].spent = true;
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    /// Apply events in order
+    pub fn ingest_all(&mut self, events: impl IntoIterator<Item = ScanEvent>) {
+        for event in events {
+            self.ingest(event);
+        }
+    }
+
+    /// Every note found, spent or not, in the order found
+    pub fn notes(&self) -> &[OwnedNote] {
+        &self.notes
+    }
+
+    /// Notes found and not yet spent
+    pub fn spendable(&self) -> impl Iterator<Item = &OwnedNote> {
+        self.notes.iter().filter(|note| !note.spent)
+    }
+
+    /// Total amount of the spendable notes in `pool`
+    pub fn balance(&self, pool: &Pubkey) -> u64 {
+        self.spendable()
+            .filter(|note| note.pool == *pool)
+            .map(|note| note.note.amount)
+            .sum()
+    }
+
+    /// Add `note` unless already found, returning its index
+    fn add(&mut self, note: OwnedNote) -> Option<usize> {
+        if self.by_nullifier.contains_key(&note.nullifier) {
+            return None;
+        }
+        let index = self.notes.len();
+        self.by_nullifier.insert(note.nullifier, index);
+        self.notes.push(note);
+        Some(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::encrypt_note;
+    use rand::rngs::OsRng;
+
+    fn shielded(viewing_key: &ViewingKey, pool: Pubkey, leaf_index: u64) -> (Note, ScanEvent) {
+        let note = Note::random(1_000, &Pubkey::default(), &mut OsRng);
+        let encrypted_note = encrypt_note(&note, &viewing_key.public_key(), &mut OsRng).unwrap();
+        let event = ScanEvent::EncryptedNote {
+            pool,
+            tree_epoch: 0,
+            commitment: note.commitment_bytes(),
+            leaf_index,
+            encrypted_note,
+        };
+        (note, event)
+    }
+
+    #[test]
+    fn test_finds_and_spends_notes() {
+        let viewing_key = ViewingKey::random(&mut OsRng);
+        let pool = Pubkey::new_unique();
+        let mut scanner = Scanner::new(viewing_key.clone());
+        scanner.watch(pool, Pubkey::default());
+
+        let (note, event) = shielded(&viewing_key, pool, 0);
+        let (_, other) = shielded(&ViewingKey::random(&mut OsRng), pool, 1);
+        let (change, change_event) = shielded(&viewing_key, pool, 2);
+        assert_eq!(scanner.ingest(event.clone()).unwrap().note, note);
+        assert!(scanner.ingest(other).is_none());
+        assert!(scanner.ingest(event).is_none(), "replayed events are found once");
+        scanner.ingest(change_event);
+        assert_eq!(scanner.balance(&pool), 2_000);
+
+        let nullifier = fr_to_bytes(&note.nullifier(0, &pool, &Pubkey::default()));
+        scanner.ingest(ScanEvent::NullifierSpent { pool, nullifier });
+        assert_eq!(scanner.notes().len(), 2);
+        assert_eq!(scanner.spendable().map(|owned| &owned.note).collect::<Vec<_>>(), [&change]);
+        assert_eq!(scanner.balance(&pool), 1_000);
+    }
+
+    #[test]
+    fn test_spent_before_found() {
+        let viewing_key = ViewingKey::random(&mut OsRng);
+        let pool = Pubkey::new_unique();
+        let mut scanner = Scanner::new(viewing_key.clone());
+        scanner.watch(pool, Pubkey::default());
+
+        let (note, event) = shielded(&viewing_key, pool, 5);
+        let nullifier = fr_to_bytes(&note.nullifier(5, &pool, &Pubkey::default()));
+        scanner.ingest_all([ScanEvent::NullifierSpent { pool, nullifier }, event]);
+        assert!(scanner.notes()[0].spent);
+        assert_eq!(scanner.balance(&pool), 0);
+
+        let mut restored = Scanner::new(viewing_key);
+        restored.watch(pool, Pubkey::default());
+        restored.restore(scanner.notes().to_vec());
+        assert_eq!(restored.notes(), scanner.notes());
+    }
+
+    #[test]
+    fn test_finds_stealth_notes() {
+        use crate::encryption::send_to_stealth_address;
+
+        let viewing_key = ViewingKey::random(&mut OsRng);
+        let keys = StealthKeys::from_secrets(&[1u8; 32], &[2u8; 32]);
+        let meta_address = keys.meta_address();
+        let pool = Pubkey::new_unique();
+        let mut scanner = Scanner::new(viewing_key.clone());
+        scanner.watch(pool, Pubkey::default());
+
+        let (commitment, encrypted_note) =
+            send_to_stealth_address(700, &Pubkey::default(), &meta_address, &mut OsRng).unwrap();
+        let event = ScanEvent::EncryptedNote {
+            pool,
+            tree_epoch: 0,
+            commitment,
+            leaf_index: 0,
+            encrypted_note,
+        };
+        assert!(scanner.ingest(event.clone()).is_none());
+        scanner.scan_stealth(keys);
+        let found = scanner.ingest(event).unwrap();
+        assert_eq!(found.note.commitment_bytes(), commitment);
+        assert!(found.note.stealth_tweak.is_some());
+
+        let (_, event) = shielded(&viewing_key, pool, 1);
+        scanner.ingest(event);
+        assert_eq!(scanner.balance(&pool), 1_700);
+    }
+
+    #[test]
+    fn test_ignores_unwatched_pools() {
+        let viewing_key = ViewingKey::random(&mut OsRng);
+        let mut scanner = Scanner::new(viewing_key.clone());
+        let (_, event) = shielded(&viewing_key, Pubkey::new_unique(), 0);
+        assert!(scanner.ingest(event).is_none());
+
+        let pool = Pubkey::new_unique();
+        scanner.watch(pool, Pubkey::new_unique());
+        let (_, event) = shielded(&viewing_key, pool, 0);
+        assert!(scanner.ingest(event).is_none(), "notes of another asset are rejected");
+    }
+
+    #[cfg(feature = "instructions")]
+    #[test]
+    fn test_decodes_event_cpis() {
+        use anchor_lang::event::EVENT_IX_TAG_LE;
+        use anchor_lang::Event;
+        use veil_program::events::{NullifierSpent, RootUpdated};
+
+        let pool = Pubkey::new_unique();
+        let event = NullifierSpent { pool, nullifier: [7u8; 32], slot: 9 };
+        let data = [&EVENT_IX_TAG_LE[..], &event.data()].concat();
+        assert_eq!(
+            ScanEvent::from_instruction(&veil_program::ID, &data),
+            Some(ScanEvent::NullifierSpent { pool, nullifier: [7u8; 32] })
+        );
+        assert_eq!(ScanEvent::from_instruction(&Pubkey::new_unique(), &data), None);
+        assert_eq!(ScanEvent::from_instruction(&veil_program::ID, &event.data()), None);
+
+        let event = RootUpdated {
+            pool,
+            tree_epoch: 0,
+            old_root: [0u8; 32],
+            new_root: [1u8; 32],
+            leaf_count: 1,
+            slot: 9,
+        };
+        let data = [&EVENT_IX_TAG_LE[..], &event.data()].concat();
+        assert_eq!(ScanEvent::from_instruction(&veil_program::ID, &data), None);
+    }
+}
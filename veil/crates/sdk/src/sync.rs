@@ -0,0 +1,373 @@
+//! Local Merkle tree sync
+//!
+//! A `TreeSync` keeps a local copy of one tree of a pool, fed from the
+//! program's leaf archive: its commitment chunk accounts, or the
+//! `MerkleLeafInserted` events of its transactions. Leaves may be applied
+//! more than once and chunks re-read while they fill; a leaf that differs
+//! from the one already held, or that leaves a gap, is rejected.
+//!
+//! Before proving, `check_roots` (or `check_pool`, from the pool account)
+//! confirms the local root is one the program still accepts: the pool's
+//! current root or one in its root history. A local tree the pool has
+//! moved on from by more than its history needs syncing first.
+//!
+//! The tree persists as its leaves; loading rebuilds the hashes:
+//!
+//! ```text
+//! magic "VEILTREE" (8) || version (1) || pool (32) || tree_epoch (8)
+//!     || leaf_count (8) || leaves (32 each)
+//! ```
+//!
+//! Integers are little-endian.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[cfg(feature = "instructions")]
+use anchor_lang::{event::EVENT_IX_TAG_LE, AnchorDeserialize, Discriminator};
+use solana_program::pubkey::Pubkey;
+#[cfg(feature = "instructions")]
+use veil_program::events::MerkleLeafInserted;
+#[cfg(feature = "instructions")]
+use veil_program::state::{
+    CommitmentChunk, PrivacyPool, RootEntry, TreeBackend, COMMITMENT_CHUNK_LEAVES,
+};
+
+use crate::error::{Result, SdkError};
+use crate::note::Note;
+use crate::tree::{CommitmentTree, Spend};
+
+/// Leading bytes of a persisted tree
+const MAGIC: &[u8; 8] = b"VEILTREE";
+
+/// Version of the persisted format
+pub const TREE_FILE_VERSION: u8 = 1;
+
+/// Length of the persisted header, before the leaves
+const HEADER_LEN: usize = 8 + 1 + 32 + 8 + 8;
+
+/// A local copy of one tree of a pool
+#[derive(Clone, Debug)]
+pub struct TreeSync {
+    pool: Pubkey,
+    tree_epoch: u64,
+    leaves: Vec<[u8; 32]>,
+    /// Index of the first leaf of each commitment
+    positions: HashMap<[u8; 32], u64>,
+    tree: CommitmentTree,
+}
+
+impl TreeSync {
+    /// An empty copy of tree `tree_epoch` of `pool`
+    pub fn new(pool: Pubkey, tree_epoch: u64) -> Self {
+        Self {
+            pool,
+            tree_epoch,
+            leaves: Vec::new(),
+            positions: HashMap::new(),
+            tree: CommitmentTree::new(),
+        }
+    }
+
+    pub fn pool(&self) -> &Pubkey {
+        &self.pool
+    }
+
+    pub fn tree_epoch(&self) -> u64 {
+        self.tree_epoch
+    }
+
+    /// Leaves synced so far, in insertion order
+    pub fn leaves(&self) -> &[[u8; 32]] {
+        &self.leaves
+    }
+
+    /// Number of leaves synced
+    pub fn len(&self) -> u64 {
+        self.leaves.len() as u64
+    }
+
+    /// Whether no leaves are synced
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Root of the synced leaves, as spend instructions take it
+    pub fn root_bytes(&self) -> [u8; 32] {
+        self.tree.root_bytes()
+    }
+
+    /// Apply `leaves`, the first at `start_index`, returning how many
+    /// were new
+    pub fn apply_leaves(&mut self, start_index: u64, leaves: &[[u8; 32]]) -> Result<usize> {
+        if start_index > self.len() {
+            return Err(SdkError::LeafGap { expected: self.len(), got: start_index });
+        }
+        let mut added = 0;
+        for (leaf_index, leaf) in (start_index..).zip(leaves) {
+            match self.leaves.get(leaf_index as usize) {
+                Some(known) if known == leaf => {}
+                Some(_) => return Err(SdkError::LeafMismatch { leaf_index }),
+                None => {
+                    self.tree.insert(leaf)?;
+                    self.leaves.push(*leaf);
+                    self.positions.entry(*leaf).or_insert(leaf_index);
+                    added += 1;
+                }
+            }
+        }
+        Ok(added)
+    }
+
+    /// Check the synced root is among `valid_roots`, the pool's current
+    /// root and root history
+    pub fn check_roots(&self, valid_roots: impl IntoIterator<Item = [u8; 32]>) -> Result<()> {
+        let root = self.root_bytes();
+        if valid_roots.into_iter().any(|valid| valid == root) {
+            Ok(())
+        } else {
+            Err(SdkError::UnknownRoot)
+        }
+    }
+
+    /// Index of the first leaf holding `commitment`
+    pub fn position(&self, commitment: &[u8; 32]) -> Option<u64> {
+        self.positions.get(commitment).copied()
+    }
+
+    /// The witness spending `note`, against the synced root
+    pub fn witness(&self, note: Note) -> Result<Spend> {
+        let leaf_index =
+            self.position(&note.commitment_bytes()).ok_or(SdkError::CommitmentNotFound)?;
+        Ok(Spend { path: self.tree.path(leaf_index)?, root: self.tree.root(), leaf_index, note })
+    }
+
+    /// The persisted form of the tree
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + 32 * self.leaves.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(TREE_FILE_VERSION);
+        bytes.extend_from_slice(self.pool.as_ref());
+        bytes.extend_from_slice(&self.tree_epoch.to_le_bytes());
+        bytes.extend_from_slice(&self.len().to_le_bytes());
+        for leaf in &self.leaves {
+            bytes.extend_from_slice(leaf);
+        }
+        bytes
+    }
+
+    /// A tree from its persisted form
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < HEADER_LEN || &bytes[..8] != MAGIC {
+            return Err(SdkError::MalformedTreeFile);
+        }
+        if bytes[8] != TREE_FILE_VERSION {
+            return Err(SdkError::UnsupportedTreeFileVersion(bytes[8]));
+        }
+        let pool = Pubkey::new_from_array(bytes[9..41].try_into().unwrap());
+        let tree_epoch = u64::from_le_bytes(bytes[41..49].try_into().unwrap());
+        let count = u64::from_le_bytes(bytes[49..57].try_into().unwrap());
+        let leaves = &bytes[HEADER_LEN..];
+        if count.checked_mul(32) != Some(leaves.len() as u64) {
+            return Err(SdkError::MalformedTreeFile);
+        }
+
+        let leaves: Vec<[u8; 32]> =
+            leaves.chunks_exact(32).map(|leaf| leaf.try_into().unwrap()).collect();
+        let mut sync = Self::new(pool, tree_epoch);
+        sync.apply_leaves(0, &leaves)?;
+        Ok(sync)
+    }
+
+    /// Save the tree to `path`, replacing it atomically
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, self.to_bytes())?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Load a tree saved with `save`
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+}
+
+#[cfg(feature = "instructions")]
+impl TreeSync {
+    /// Index of the commitment chunk holding the next unsynced leaf
+    pub fn next_chunk_index(&self) -> u32 {
+        CommitmentChunk::index_of(self.len())
+    }
+
+    /// Apply the leaves of a commitment chunk account, from its data,
+    /// returning how many were new
+    pub fn apply_chunk(&mut self, data: &[u8]) -> Result<usize> {
+        if data.get(..8) != Some(&CommitmentChunk::DISCRIMINATOR[..]) {
+            return Err(SdkError::InvalidAccount("not a commitment chunk"));
+        }
+        let chunk = data
+            .get(8..8 + CommitmentChunk::SIZE)
+            .map(bytemuck::pod_read_unaligned::<CommitmentChunk>)
+            .ok_or(SdkError::InvalidAccount("not a commitment chunk"))?;
+        if chunk.pool != self.pool || chunk.tree_epoch != self.tree_epoch {
+            return Err(SdkError::WrongTree);
+        }
+        let count = (chunk.count as usize).min(COMMITMENT_CHUNK_LEAVES);
+        self.apply_leaves(chunk.start_index, &chunk.leaves[..count])
+    }
+
+    /// Apply the leaf of a `MerkleLeafInserted` event self-CPI, returning
+    /// whether it was new
+    ///
+    /// Other instructions and events, and leaves of other trees, are
+    /// ignored.
+    pub fn apply_event(&mut self, program_id: &Pubkey, data: &[u8]) -> Result<bool> {
+        if *program_id != veil_program::ID {
+            return Ok(false);
+        }
+        let Some(event) = data.strip_prefix(&EVENT_IX_TAG_LE[..]) else {
+            return Ok(false);
+        };
+        if event.get(..8) != Some(&MerkleLeafInserted::DISCRIMINATOR[..]) {
+            return Ok(false);
+        }
+        let event = MerkleLeafInserted::deserialize(&mut &event[8..])
+            .map_err(|_| SdkError::InvalidAccount("malformed MerkleLeafInserted"))?;
+        if event.pool != self.pool || event.tree_epoch != self.tree_epoch {
+            return Ok(false);
+        }
+        Ok(self.apply_leaves(event.leaf_index, &[event.leaf])? == 1)
+    }
+
+    /// Check the synced root is one the pool at `data` accepts, from the
+    /// pool account's data
+    pub fn check_pool(&self, data: &[u8]) -> Result<()> {
+        if data.get(..8) != Some(&PrivacyPool::DISCRIMINATOR[..]) {
+            return Err(SdkError::InvalidAccount("not a pool"));
+        }
+        let pool = data
+            .get(8..8 + PrivacyPool::SIZE)
+            .map(bytemuck::pod_read_unaligned::<PrivacyPool>)
+            .ok_or(SdkError::InvalidAccount("not a pool"))?;
+        if pool.tree_backend() != TreeBackend::Incremental {
+            return Err(SdkError::InvalidAccount("compressed trees have no chunks"));
+        }
+        if pool.tree_epoch != self.tree_epoch {
+            return Err(SdkError::WrongTree);
+        }
+        let history = data[8 + PrivacyPool::SIZE..]
+            .chunks_exact(RootEntry::SIZE)
+            .take(pool.root_history_size as usize)
+            .map(|entry| bytemuck::pod_read_unaligned::<RootEntry>(entry).root)
+            .filter(|root| *root != [0u8; 32]);
+        self.check_roots(std::iter::once(pool.current_root()).chain(history))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn notes(count: u64) -> Vec<Note> {
+        (1..=count).map(|amount| Note::random(amount, &Pubkey::default(), &mut OsRng)).collect()
+    }
+
+    #[test]
+    fn test_applies_overlapping_leaves() {
+        let notes = notes(4);
+        let leaves: Vec<[u8; 32]> = notes.iter().map(Note::commitment_bytes).collect();
+        let mut sync = TreeSync::new(Pubkey::new_unique(), 0);
+        assert_eq!(sync.apply_leaves(0, &leaves[..2]).unwrap(), 2);
+        assert_eq!(sync.apply_leaves(0, &leaves[..3]).unwrap(), 1);
+        assert_eq!(sync.apply_leaves(3, &leaves[3..]).unwrap(), 1);
+        assert_eq!(sync.root_bytes(), CommitmentTree::from_leaves(&leaves).unwrap().root_bytes());
+
+        assert!(matches!(
+            sync.apply_leaves(6, &leaves[..1]),
+            Err(SdkError::LeafGap { expected: 4, got: 6 })
+        ));
+        assert!(matches!(
+            sync.apply_leaves(1, &leaves[..1]),
+            Err(SdkError::LeafMismatch { leaf_index: 1 })
+        ));
+
+        let spend = sync.witness(notes[2].clone()).unwrap();
+        assert_eq!(spend.leaf_index, 2);
+        assert!(spend.path.verify(&notes[2].commitment(), &spend.root));
+    }
+
+    #[test]
+    fn test_checks_roots() {
+        let leaves: Vec<[u8; 32]> = notes(2).iter().map(Note::commitment_bytes).collect();
+        let mut sync = TreeSync::new(Pubkey::new_unique(), 0);
+        sync.apply_leaves(0, &leaves[..1]).unwrap();
+        let old_root = sync.root_bytes();
+        sync.apply_leaves(1, &leaves[1..]).unwrap();
+
+        assert!(sync.check_roots([old_root, sync.root_bytes()]).is_ok());
+        assert!(matches!(sync.check_roots([old_root]), Err(SdkError::UnknownRoot)));
+    }
+
+    #[test]
+    fn test_persists() {
+        let leaves: Vec<[u8; 32]> = notes(3).iter().map(Note::commitment_bytes).collect();
+        let mut sync = TreeSync::new(Pubkey::new_unique(), 2);
+        sync.apply_leaves(0, &leaves).unwrap();
+
+        let loaded = TreeSync::from_bytes(&sync.to_bytes()).unwrap();
+        assert_eq!(loaded.pool(), sync.pool());
+        assert_eq!(loaded.tree_epoch(), 2);
+        assert_eq!(loaded.leaves(), sync.leaves());
+        assert_eq!(loaded.root_bytes(), sync.root_bytes());
+
+        let path = std::env::temp_dir().join(format!("veil-tree-{}.bin", Pubkey::new_unique()));
+        sync.save(&path).unwrap();
+        assert_eq!(TreeSync::load(&path).unwrap().leaves(), sync.leaves());
+        fs::remove_file(&path).unwrap();
+
+        let bytes = sync.to_bytes();
+        assert!(matches!(
+            TreeSync::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(SdkError::MalformedTreeFile)
+        ));
+        let mut versioned = bytes;
+        versioned[8] = 2;
+        assert!(matches!(
+            TreeSync::from_bytes(&versioned),
+            Err(SdkError::UnsupportedTreeFileVersion(2))
+        ));
+    }
+
+    #[cfg(feature = "instructions")]
+    #[test]
+    fn test_applies_leaf_events() {
+        use anchor_lang::event::EVENT_IX_TAG_LE;
+        use anchor_lang::Event;
+        use veil_program::events::MerkleLeafInserted;
+
+        let pool = Pubkey::new_unique();
+        let leaf = notes(1)[0].commitment_bytes();
+        let event = |tree_epoch| MerkleLeafInserted {
+            pool,
+            tree_epoch,
+            leaf,
+            leaf_index: 0,
+            new_root: [0u8; 32],
+            slot: 1,
+        };
+        let mut sync = TreeSync::new(pool, 1);
+        let other_epoch = [&EVENT_IX_TAG_LE[..], &event(0).data()].concat();
+        assert!(!sync.apply_event(&veil_program::ID, &other_epoch).unwrap());
+        let data = [&EVENT_IX_TAG_LE[..], &event(1).data()].concat();
+        assert!(!sync.apply_event(&Pubkey::new_unique(), &data).unwrap());
+        assert!(sync.apply_event(&veil_program::ID, &data).unwrap());
+        assert!(!sync.apply_event(&veil_program::ID, &data).unwrap());
+        assert_eq!(sync.leaves(), [leaf]);
+    }
+}
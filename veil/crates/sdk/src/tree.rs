@@ -0,0 +1,112 @@
+//! Commitment tree
+//!
+//! A pool's tree, rebuilt from its leaves in insertion order (from the
+//! program's commitment chunks or its shield events). Spend proofs take
+//! their Merkle path and root from it; the root must still be in the pool's
+//! root history when the proof is submitted.
+
+use ark_bn254::Fr;
+use veil_core::crypto::merkle::{MerklePath, PoseidonMerkleTree};
+
+use crate::error::{Result, SdkError};
+use crate::note::{fr_from_bytes, fr_to_bytes, Note};
+
+/// A pool's commitment tree
+#[derive(Clone, Debug, Default)]
+pub struct CommitmentTree {
+    tree: PoseidonMerkleTree,
+}
+
+impl CommitmentTree {
+    /// An empty tree
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The tree holding `leaves`, in insertion order
+    pub fn from_leaves(leaves: &[[u8; 32]]) -> Result<Self> {
+        let mut tree = Self::new();
+        for leaf in leaves {
+            tree.insert(leaf)?;
+        }
+        Ok(tree)
+    }
+
+    /// Append a leaf, returning its index
+    pub fn insert(&mut self, leaf: &[u8; 32]) -> Result<u64> {
+        Ok(self.tree.insert(fr_from_bytes(leaf)?)?)
+    }
+
+    /// Number of leaves
+    pub fn len(&self) -> u64 {
+        self.tree.len()
+    }
+
+    /// Whether the tree has no leaves
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn root(&self) -> Fr {
+        self.tree.root()
+    }
+
+    /// The root as spend instructions take it
+    pub fn root_bytes(&self) -> [u8; 32] {
+        fr_to_bytes(&self.root())
+    }
+
+    /// Index of the first leaf equal to `leaf`
+    pub fn position(&self, leaf: &Fr) -> Option<u64> {
+        (0..self.len()).find(|&index| self.tree.get_leaf(index).as_ref() == Some(leaf))
+    }
+
+    /// Merkle path of the leaf at `leaf_index`
+    pub fn path(&self, leaf_index: u64) -> Result<MerklePath> {
+        Ok(self.tree.generate_proof(leaf_index)?)
+    }
+
+    /// The witness spending `note`, located by its commitment
+    pub fn spend(&self, note: Note) -> Result<Spend> {
+        let leaf_index = self.position(&note.commitment()).ok_or(SdkError::CommitmentNotFound)?;
+        Ok(Spend { path: self.path(leaf_index)?, root: self.root(), leaf_index, note })
+    }
+}
+
+/// A note with its place in the tree, ready to prove a spend of
+#[derive(Clone, Debug)]
+pub struct Spend {
+    pub note: Note,
+    pub leaf_index: u64,
+    /// Root the path leads to
+    pub root: Fr,
+    pub path: MerklePath,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+    use solana_program::pubkey::Pubkey;
+
+    #[test]
+    fn test_spend_path_leads_to_root() {
+        let notes: Vec<Note> =
+            (1..=3).map(|amount| Note::random(amount, &Pubkey::default(), &mut OsRng)).collect();
+        let leaves: Vec<[u8; 32]> = notes.iter().map(Note::commitment_bytes).collect();
+        let tree = CommitmentTree::from_leaves(&leaves).unwrap();
+        assert_eq!(tree.len(), 3);
+
+        let spend = tree.spend(notes[1].clone()).unwrap();
+        assert_eq!(spend.leaf_index, 1);
+        assert!(spend.path.verify(&notes[1].commitment(), &spend.root));
+        assert_eq!(fr_to_bytes(&spend.root), tree.root_bytes());
+    }
+
+    #[test]
+    fn test_unknown_note_is_rejected() {
+        let tree = CommitmentTree::from_leaves(&[[1u8; 32]]).unwrap();
+        let note = Note::random(1, &Pubkey::default(), &mut OsRng);
+        assert!(matches!(tree.spend(note), Err(SdkError::CommitmentNotFound)));
+    }
+}
@@ -0,0 +1,59 @@
+// Proving worker
+//
+// Serves proofs from the `veil-wasm` package off the page's main thread.
+// Post `{ id, type: "load", transferKey, unshieldKey }` once with the
+// proving key bytes, then `{ id, type: "transfer" | "unshield", ...args }`
+// per proof, the arguments named as in `Prover.proveTransfer` and
+// `Prover.proveUnshield` with the note as `{ secret, amount, blinding, mint }`.
+// Replies are `{ id, proof, nullifier, root, newCommitment?, output? }` or
+// `{ id, error }`, with their buffers transferred.
+
+import init, { Note, Prover } from "../pkg/veil_wasm.js";
+
+const ready = init();
+const prover = new Prover();
+
+function note({ secret, amount, blinding, mint }) {
+  return new Note(secret, BigInt(amount), blinding, mint);
+}
+
+function reply(id, proof) {
+  const message = { id, proof: proof.proof, nullifier: proof.nullifier, root: proof.root };
+  const buffers = [message.proof.buffer, message.nullifier.buffer, message.root.buffer];
+  const output = proof.output;
+  if (output) {
+    message.newCommitment = proof.newCommitment;
+    message.output = { secret: output.secret, amount: output.amount, blinding: output.blinding };
+    buffers.push(message.newCommitment.buffer);
+  }
+  self.postMessage(message, buffers);
+}
+
+self.onmessage = async ({ data }) => {
+  const { id, type } = data;
+  try {
+    await ready;
+    switch (type) {
+      case "load":
+        if (data.transferKey) prover.loadTransferKey(data.transferKey);
+        if (data.unshieldKey) prover.loadUnshieldKey(data.unshieldKey);
+        self.postMessage({ id });
+        break;
+      case "transfer":
+        reply(id, prover.proveTransfer(
+          note(data.note), data.leaves, data.pool, data.mint, BigInt(data.expirySlot),
+        ));
+        break;
+      case "unshield":
+        reply(id, prover.proveUnshield(
+          note(data.note), data.leaves, data.pool, data.mint, data.recipient, data.relayer,
+          BigInt(data.fee), BigInt(data.refund ?? 0), BigInt(data.expirySlot),
+        ));
+        break;
+      default:
+        throw new Error(`unknown request ${type}`);
+    }
+  } catch (error) {
+    self.postMessage({ id, error: String(error) });
+  }
+};
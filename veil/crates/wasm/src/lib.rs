@@ -0,0 +1,272 @@
+//! Veil WASM
+//!
+//! `wasm-bindgen` bindings of the SDK's note derivation and proof
+//! generation, so browser wallets prove their own spends and their secrets
+//! never leave the page. Build with:
+//!
+//! ```text
+//! wasm-pack build crates/wasm --target web
+//! ```
+//!
+//! Every input and output is a `Uint8Array` or `BigInt` (32-byte field
+//! elements are big-endian, pubkeys raw bytes), so they can be posted to
+//! and from a web worker. Proving takes seconds of CPU and should run in
+//! one, off the page's main thread; `js/worker.js` is a worker serving
+//! proofs over `postMessage`.
+//!
+//! Proving keys are the `<name>.pk` files `export-vks` writes, matching
+//! the verifying keys registered for the pool.
+
+use ark_bn254::Bn254;
+use ark_groth16::ProvingKey;
+use ark_serialize::CanonicalDeserialize;
+use rand::rngs::OsRng;
+use solana_program::pubkey::Pubkey;
+use veil_sdk::note::{fr_from_bytes, fr_to_bytes};
+use veil_sdk::prover::{self, UnshieldParams};
+use veil_sdk::{CommitmentTree, Spend};
+use wasm_bindgen::prelude::*;
+
+/// 32 bytes named `name`
+fn bytes32(name: &str, bytes: &[u8]) -> Result<[u8; 32], JsError> {
+    bytes.try_into().map_err(|_| JsError::new(&format!("{name} must be 32 bytes")))
+}
+
+fn pubkey(name: &str, bytes: &[u8]) -> Result<Pubkey, JsError> {
+    Ok(Pubkey::new_from_array(bytes32(name, bytes)?))
+}
+
+/// The witness spending `note`, from the pool's leaves concatenated in
+/// insertion order
+fn spend(note: &Note, leaves: &[u8]) -> Result<Spend, JsError> {
+    if !leaves.len().is_multiple_of(32) {
+        return Err(JsError::new("leaves must be a multiple of 32 bytes"));
+    }
+    let leaves: Vec<[u8; 32]> =
+        leaves.chunks_exact(32).map(|leaf| leaf.try_into().expect("32-byte chunk")).collect();
+    Ok(CommitmentTree::from_leaves(&leaves)?.spend(note.0.clone())?)
+}
+
+fn proving_key(bytes: &[u8]) -> Result<ProvingKey<Bn254>, JsError> {
+    Ok(ProvingKey::deserialize_compressed(bytes)?)
+}
+
+/// A shielded note
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Note(veil_sdk::Note);
+
+#[wasm_bindgen]
+impl Note {
+    /// The note of `amount` of `mint` (32 zero bytes for SOL) opened by
+    /// `secret` and `blinding`
+    #[wasm_bindgen(constructor)]
+    pub fn new(secret: &[u8], amount: u64, blinding: &[u8], mint: &[u8]) -> Result<Note, JsError> {
+        Ok(Self(veil_sdk::Note::new(
+            fr_from_bytes(&bytes32("secret", secret)?)?,
+            amount,
+            fr_from_bytes(&bytes32("blinding", blinding)?)?,
+            veil_sdk::note::asset_id(&pubkey("mint", mint)?),
+        )))
+    }
+
+    /// A note of `amount` of `mint` with a fresh secret and blinding
+    pub fn random(amount: u64, mint: &[u8]) -> Result<Note, JsError> {
+        Ok(Self(veil_sdk::Note::random(amount, &pubkey("mint", mint)?, &mut OsRng)))
+    }
+
+    /// The note sent to the owner's stealth address under `tweak`
+    #[wasm_bindgen(js_name = withStealthTweak)]
+    pub fn with_stealth_tweak(&self, tweak: &[u8]) -> Result<Note, JsError> {
+        let tweak = fr_from_bytes(&bytes32("tweak", tweak)?)?;
+        Ok(Self(self.0.clone().with_stealth_tweak(tweak)))
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn secret(&self) -> Vec<u8> {
+        fr_to_bytes(&self.0.secret).to_vec()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn blinding(&self) -> Vec<u8> {
+        fr_to_bytes(&self.0.blinding).to_vec()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn amount(&self) -> u64 {
+        self.0.amount
+    }
+
+    /// The commitment shielded for the note
+    pub fn commitment(&self) -> Vec<u8> {
+        self.0.commitment_bytes().to_vec()
+    }
+
+    /// The nullifier spending the note at `leaf_index` of `pool`
+    pub fn nullifier(&self, leaf_index: u64, pool: &[u8], mint: &[u8]) -> Result<Vec<u8>, JsError> {
+        let (pool, mint) = (pubkey("pool", pool)?, pubkey("mint", mint)?);
+        Ok(fr_to_bytes(&self.0.nullifier(leaf_index, &pool, &mint)).to_vec())
+    }
+}
+
+/// A spend proof and the values the spend instruction takes with it
+#[wasm_bindgen]
+pub struct SpendProof {
+    proof: Vec<u8>,
+    nullifier: [u8; 32],
+    root: [u8; 32],
+    new_commitment: Option<[u8; 32]>,
+    output: Option<Note>,
+}
+
+#[wasm_bindgen]
+impl SpendProof {
+    /// The proof in the program's encoding
+    #[wasm_bindgen(getter)]
+    pub fn proof(&self) -> Vec<u8> {
+        self.proof.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn nullifier(&self) -> Vec<u8> {
+        self.nullifier.to_vec()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn root(&self) -> Vec<u8> {
+        self.root.to_vec()
+    }
+
+    /// Commitment of a transfer's output note
+    #[wasm_bindgen(getter, js_name = newCommitment)]
+    pub fn new_commitment(&self) -> Option<Vec<u8>> {
+        self.new_commitment.map(|commitment| commitment.to_vec())
+    }
+
+    /// A transfer's output note, to keep for spending it later
+    #[wasm_bindgen(getter)]
+    pub fn output(&self) -> Option<Note> {
+        self.output.clone()
+    }
+}
+
+/// Proving keys, loaded once per worker
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Prover {
+    transfer: Option<ProvingKey<Bn254>>,
+    unshield: Option<ProvingKey<Bn254>>,
+}
+
+#[wasm_bindgen]
+impl Prover {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Prover {
+        Self::default()
+    }
+
+    /// Load the transfer proving key (`transfer.pk`)
+    #[wasm_bindgen(js_name = loadTransferKey)]
+    pub fn load_transfer_key(&mut self, bytes: &[u8]) -> Result<(), JsError> {
+        self.transfer = Some(proving_key(bytes)?);
+        Ok(())
+    }
+
+    /// Load the withdraw proving key (`unshield.pk`)
+    #[wasm_bindgen(js_name = loadUnshieldKey)]
+    pub fn load_unshield_key(&mut self, bytes: &[u8]) -> Result<(), JsError> {
+        self.unshield = Some(proving_key(bytes)?);
+        Ok(())
+    }
+
+    /// Prove a transfer of `note` in `pool` into a fresh note of the same
+    /// owner and amount
+    #[wasm_bindgen(js_name = proveTransfer)]
+    pub fn prove_transfer(
+        &self,
+        note: &Note,
+        leaves: &[u8],
+        pool: &[u8],
+        mint: &[u8],
+        expiry_slot: u64,
+    ) -> Result<SpendProof, JsError> {
+        let pk = self.transfer.as_ref().ok_or_else(|| JsError::new("transfer key not loaded"))?;
+        let transfer = prover::prove_transfer(
+            pk,
+            &spend(note, leaves)?,
+            &pubkey("pool", pool)?,
+            &pubkey("mint", mint)?,
+            expiry_slot,
+            &mut OsRng,
+        )?;
+        Ok(SpendProof {
+            proof: transfer.proof,
+            nullifier: transfer.nullifier,
+            root: transfer.root,
+            new_commitment: Some(transfer.new_commitment),
+            output: Some(Note(transfer.output)),
+        })
+    }
+
+    /// Prove a withdrawal of all of `note` from `pool` to `recipient`,
+    /// submitted by `relayer` for `fee`
+    #[wasm_bindgen(js_name = proveUnshield)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn prove_unshield(
+        &self,
+        note: &Note,
+        leaves: &[u8],
+        pool: &[u8],
+        mint: &[u8],
+        recipient: &[u8],
+        relayer: &[u8],
+        fee: u64,
+        refund: u64,
+        expiry_slot: u64,
+    ) -> Result<SpendProof, JsError> {
+        let pk = self.unshield.as_ref().ok_or_else(|| JsError::new("unshield key not loaded"))?;
+        let params = UnshieldParams {
+            recipient: pubkey("recipient", recipient)?,
+            relayer: pubkey("relayer", relayer)?,
+            fee,
+            refund,
+            expiry_slot,
+        };
+        let unshield = prover::prove_unshield(
+            pk,
+            &spend(note, leaves)?,
+            &pubkey("pool", pool)?,
+            &pubkey("mint", mint)?,
+            &params,
+            &mut OsRng,
+        )?;
+        Ok(SpendProof {
+            proof: unshield.proof,
+            nullifier: unshield.nullifier,
+            root: unshield.root,
+            new_commitment: None,
+            output: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_note_round_trips_through_bytes() {
+        let mint = [0u8; 32];
+        let note = Note::random(1_000, &mint).unwrap();
+        let restored = Note::new(&note.secret(), note.amount(), &note.blinding(), &mint).unwrap();
+        assert_eq!(restored.commitment(), note.commitment());
+    }
+
+    #[test]
+    fn test_spend_from_concatenated_leaves() {
+        let note = Note::random(1_000, &[0u8; 32]).unwrap();
+        let mut leaves = vec![1u8; 32];
+        leaves.extend(note.commitment());
+        assert_eq!(spend(&note, &leaves).unwrap().leaf_index, 1);
+    }
+}
@@ -73,7 +73,8 @@ run_integration_tests() {
     fi
 
     cd "$PROJECT_ROOT"
-    cargo test -p nyx-privacy-program --test '*' -- --nocapture
+    # Integration tests submit mock MVP proofs
+    cargo test -p nyx-privacy-program --features mock-prover --test '*' -- --nocapture
 }
 
 # Run all tests
@@ -6,6 +6,7 @@ This module provides utilities for managing different asset types
 """
 
 import hashlib
+from decimal import Decimal
 from typing import Union
 from solders.pubkey import Pubkey
 
@@ -13,6 +14,12 @@ from solders.pubkey import Pubkey
 # Special asset ID for native SOL
 SOL_ASSET_ID = 0
 
+# Domain separator for asset IDs (matches the program)
+ASSET_ID_DOMAIN = b"NYX_ASSET"
+
+# Wrapped SOL mint, whose notes count as native SOL
+WRAPPED_SOL_MINT = Pubkey.from_string("So11111111111111111111111111111111111111112")
+
 
 class AssetRegistry:
     """
@@ -27,6 +34,11 @@ class AssetRegistry:
         """
         Convert a token identifier to an 8-byte asset ID.
 
+        Matches `verification::asset_id` in the program, which multi-asset
+        pools bind into shield and unshield proofs: the first 8 bytes of
+        sha256(ASSET_ID_DOMAIN || mint), read little-endian. Wrapped SOL
+        shares native SOL's asset ID.
+
         Args:
             token: Either "SOL" string or a token mint address (Pubkey or string)
 
@@ -38,14 +50,17 @@ class AssetRegistry:
                 return SOL_ASSET_ID
 
             # It's a mint address string
-            mint_str = token
+            mint = Pubkey.from_string(token)
         elif isinstance(token, Pubkey):
-            mint_str = str(token)
+            mint = token
         else:
             raise ValueError(f"Invalid token type: {type(token)}")
 
-        # Hash the mint address to get a deterministic 8-byte ID
-        hash_bytes = hashlib.sha256(mint_str.encode()).digest()
+        if mint == WRAPPED_SOL_MINT:
+            return SOL_ASSET_ID
+
+        # Hash the mint to get a deterministic 8-byte ID
+        hash_bytes = hashlib.sha256(ASSET_ID_DOMAIN + bytes(mint)).digest()
         # Take first 8 bytes and convert to little-endian integer
         return int.from_bytes(hash_bytes[:8], 'little')
 
@@ -76,6 +91,47 @@ class AssetRegistry:
         return asset_id != SOL_ASSET_ID
 
 
+# Decimals of native SOL (lamports per SOL)
+SOL_DECIMALS = 9
+
+
+def to_base_units(ui_amount: Union[int, str, Decimal], decimals: int) -> int:
+    """
+    Convert a UI amount (e.g. 100 USDC) to the mint's base units.
+
+    Pool denominations and note amounts are in base units, so a "100 USDC
+    pool" has denomination ``to_base_units(100, 6)``, not 100.
+
+    Args:
+        ui_amount: Amount in whole tokens, possibly fractional
+        decimals: Decimals of the mint
+
+    Returns:
+        Amount in base units
+
+    Raises:
+        ValueError: If the amount has more precision than the mint
+    """
+    scaled = Decimal(ui_amount).scaleb(decimals)
+    if scaled != scaled.to_integral_value():
+        raise ValueError(f"{ui_amount} has more than {decimals} decimals")
+    return int(scaled)
+
+
+def to_ui_amount(amount: int, decimals: int) -> Decimal:
+    """
+    Convert a base-unit amount to whole tokens.
+
+    Args:
+        amount: Amount in base units
+        decimals: Decimals of the mint
+
+    Returns:
+        Amount in whole tokens
+    """
+    return Decimal(amount).scaleb(-decimals)
+
+
 # Common token mint addresses for convenience
 COMMON_TOKENS = {
     "USDC": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
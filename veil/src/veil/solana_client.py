@@ -30,17 +30,60 @@ DEFAULT_PROGRAM_ID = "Nyx1111111111111111111111111111111111111111"
 POOL_SEED = b"pool"
 VAULT_SEED = b"vault"
 NULLIFIER_SEED = b"nullifier"
+PROTOCOL_CONFIG_SEED = b"protocol_config"
+COMMITMENT_CHUNK_SEED = b"commitment_chunk"
+COMMITMENT_RECORD_SEED = b"commitment"
+TREE_EPOCH_SEED = b"tree_epoch"
+POOL_STATS_SEED = b"pool_stats"
+# Seed of the PDA signing the program's event self-CPIs (Anchor event CPI)
+EVENT_AUTHORITY_SEED = b"__event_authority"
 
+# Mint recorded by the multi-asset pool (must match token::MULTI_ASSET_MINT)
+MULTI_ASSET_MINT = Pubkey(bytes([0xFF] * 32))
 
-def find_pool_pda(program_id: Pubkey, denomination: int = 0) -> Tuple[Pubkey, int]:
-    """Derive the pool PDA address for a specific denomination.
+# Mint recorded by the NFT pool (must match token::NFT_POOL_MINT)
+NFT_POOL_MINT = Pubkey(bytes([0xFE] * 32))
+
+# Longest encrypted note the program emits (must match state::MAX_ENCRYPTED_NOTE_LEN)
+MAX_ENCRYPTED_NOTE_LEN = 256
+
+# Every encrypted note starts with its ephemeral key (must match state::EPHEMERAL_KEY_LEN)
+EPHEMERAL_KEY_LEN = 32
+
+# Leaves archived per commitment chunk (must match state::COMMITMENT_CHUNK_LEAVES)
+COMMITMENT_CHUNK_LEAVES = 256
+
+# Leaves of a pool's tree (must match IncrementalMerkleTree::MAX_LEAVES)
+MAX_TREE_LEAVES = 1 << 20
+
+INSTRUCTIONS_SYSVAR_ID = Pubkey.from_string("Sysvar1nstructions1111111111111111111111111")
+
+
+def find_pool_pda(
+    program_id: Pubkey, denomination: int = 0, mint: Optional[Pubkey] = None
+) -> Tuple[Pubkey, int]:
+    """Derive the pool PDA address for a specific denomination and mint.
 
     Args:
         program_id: The program pubkey
         denomination: Pool denomination in lamports (0 = custom)
+        mint: Mint of an SPL pool (None for native SOL)
     """
     denomination_bytes = denomination.to_bytes(8, byteorder="little")
-    return Pubkey.find_program_address([POOL_SEED, denomination_bytes], program_id)
+    seeds = [POOL_SEED, denomination_bytes]
+    if mint is not None and mint != Pubkey.default():
+        seeds.append(bytes(mint))
+    return Pubkey.find_program_address(seeds, program_id)
+
+
+def find_multi_asset_pool_pda(program_id: Pubkey) -> Tuple[Pubkey, int]:
+    """Derive the multi-asset pool PDA address"""
+    return find_pool_pda(program_id, 0, MULTI_ASSET_MINT)
+
+
+def find_nft_pool_pda(program_id: Pubkey) -> Tuple[Pubkey, int]:
+    """Derive the NFT pool PDA address"""
+    return find_pool_pda(program_id, 0, NFT_POOL_MINT)
 
 
 def find_vault_pda(program_id: Pubkey, pool: Pubkey) -> Tuple[Pubkey, int]:
@@ -48,6 +91,60 @@ def find_vault_pda(program_id: Pubkey, pool: Pubkey) -> Tuple[Pubkey, int]:
     return Pubkey.find_program_address([VAULT_SEED, bytes(pool)], program_id)
 
 
+def find_event_authority_pda(program_id: Pubkey) -> Tuple[Pubkey, int]:
+    """Derive the event authority PDA
+
+    Events are emitted as inner instructions signed by this PDA, and
+    instructions that emit events take it and the program as accounts.
+    """
+    return Pubkey.find_program_address([EVENT_AUTHORITY_SEED], program_id)
+
+
+def find_protocol_config_pda(program_id: Pubkey) -> Tuple[Pubkey, int]:
+    """Derive the protocol config PDA address"""
+    return Pubkey.find_program_address([PROTOCOL_CONFIG_SEED], program_id)
+
+
+def find_commitment_chunk_pda(
+    program_id: Pubkey, pool: Pubkey, tree_epoch: int, leaf_index: int
+) -> Tuple[Pubkey, int]:
+    """Derive the PDA of the commitment chunk archiving a leaf"""
+    chunk_index = leaf_index // COMMITMENT_CHUNK_LEAVES
+    return Pubkey.find_program_address(
+        [
+            COMMITMENT_CHUNK_SEED,
+            bytes(pool),
+            tree_epoch.to_bytes(8, byteorder="little"),
+            chunk_index.to_bytes(4, byteorder="little"),
+        ],
+        program_id,
+    )
+
+
+def find_tree_epoch_pda(
+    program_id: Pubkey, pool: Pubkey, tree_epoch: int
+) -> Tuple[Pubkey, int]:
+    """Derive the PDA archiving a pool's tree once it rolls over"""
+    return Pubkey.find_program_address(
+        [TREE_EPOCH_SEED, bytes(pool), tree_epoch.to_bytes(8, byteorder="little")],
+        program_id,
+    )
+
+
+def find_commitment_record_pda(
+    program_id: Pubkey, pool: Pubkey, commitment: bytes
+) -> Tuple[Pubkey, int]:
+    """Derive the PDA recording where a commitment landed"""
+    return Pubkey.find_program_address(
+        [COMMITMENT_RECORD_SEED, bytes(pool), commitment], program_id
+    )
+
+
+def find_pool_stats_pda(program_id: Pubkey, pool: Pubkey) -> Tuple[Pubkey, int]:
+    """Derive the pool stats PDA address"""
+    return Pubkey.find_program_address([POOL_STATS_SEED, bytes(pool)], program_id)
+
+
 def find_nullifier_pda(
     program_id: Pubkey, pool: Pubkey, nullifier: bytes
 ) -> Tuple[Pubkey, int]:
@@ -57,6 +154,21 @@ def find_nullifier_pda(
     )
 
 
+def encode_encrypted_note(encrypted_note: Optional[bytes]) -> bytes:
+    """Borsh-encode the optional encrypted note appended to shield/transfer data"""
+    if encrypted_note is None:
+        return b"\x00"
+    if len(encrypted_note) > MAX_ENCRYPTED_NOTE_LEN:
+        raise ValueError(
+            f"Encrypted note must be at most {MAX_ENCRYPTED_NOTE_LEN} bytes"
+        )
+    if len(encrypted_note) < EPHEMERAL_KEY_LEN:
+        raise ValueError(
+            f"Encrypted note must be at least {EPHEMERAL_KEY_LEN} bytes"
+        )
+    return b"\x01" + struct.pack("<I", len(encrypted_note)) + encrypted_note
+
+
 class InstructionBuilder:
     """Builds Veil privacy pool instructions"""
 
@@ -65,6 +177,7 @@ class InstructionBuilder:
     INITIALIZE_DISC = bytes([175, 175, 109, 31, 13, 152, 155, 237])
     SHIELD_SOL_DISC = bytes([236, 230, 72, 63, 15, 240, 212, 155])
     SHIELD_DISC = bytes([220, 198, 253, 246, 231, 84, 147, 98])
+    SHIELD_SOL_FOR_DISC = bytes([155, 235, 112, 137, 49, 228, 70, 137])
     TRANSFER_DISC = bytes([163, 52, 200, 231, 140, 3, 69, 186])
     UNSHIELD_SOL_DISC = bytes([211, 8, 170, 159, 48, 29, 154, 202])
     UNSHIELD_DISC = bytes([21, 228, 55, 24, 194, 10, 21, 22])
@@ -89,29 +202,130 @@ class InstructionBuilder:
 
         return Instruction(self.program_id, self.INITIALIZE_DISC, accounts)
 
+    def _shield_sol_accounts(
+        self,
+        depositor: Pubkey,
+        commitment: bytes,
+        denomination: int,
+        tree_epoch: int,
+        next_index: int,
+    ) -> list:
+        """Accounts of `shield_sol` and `shield_sol_for`
+
+        `tree_epoch` and `next_index` are the pool's, and pick the chunk the
+        new leaf is archived in. A full tree is rolled over by the shield, so
+        its leaf goes to the next tree and the full one's archive is passed.
+        Pool stats and wSOL unwrapping are left out, passing the program ID
+        as Anchor does for absent optional accounts.
+        """
+        pool, _pool_bump = find_pool_pda(self.program_id, denomination)
+        vault, _vault_bump = find_vault_pda(self.program_id, pool)
+        protocol_config, _ = find_protocol_config_pda(self.program_id)
+        tree_archive = self.program_id
+        if next_index >= MAX_TREE_LEAVES:
+            tree_archive, _ = find_tree_epoch_pda(self.program_id, pool, tree_epoch)
+            tree_epoch, next_index = tree_epoch + 1, 0
+        commitment_chunk, _ = find_commitment_chunk_pda(
+            self.program_id, pool, tree_epoch, next_index
+        )
+        commitment_record, _ = find_commitment_record_pda(
+            self.program_id, pool, commitment
+        )
+        event_authority, _ = find_event_authority_pda(self.program_id)
+
+        return [
+            AccountMeta(pool, is_signer=False, is_writable=True),
+            AccountMeta(protocol_config, is_signer=False, is_writable=False),
+            AccountMeta(vault, is_signer=False, is_writable=True),
+            AccountMeta(depositor, is_signer=True, is_writable=True),
+            AccountMeta(SYSTEM_PROGRAM_ID, is_signer=False, is_writable=False),
+            AccountMeta(commitment_chunk, is_signer=False, is_writable=True),
+            AccountMeta(
+                tree_archive, is_signer=False, is_writable=tree_archive != self.program_id
+            ),
+            AccountMeta(commitment_record, is_signer=False, is_writable=True),
+            # pool_stats, depositor_wsol_account, token_program,
+            # wsol_unwrap_account, wsol_mint
+            AccountMeta(self.program_id, is_signer=False, is_writable=False),
+            AccountMeta(self.program_id, is_signer=False, is_writable=False),
+            AccountMeta(self.program_id, is_signer=False, is_writable=False),
+            AccountMeta(self.program_id, is_signer=False, is_writable=False),
+            AccountMeta(self.program_id, is_signer=False, is_writable=False),
+            AccountMeta(INSTRUCTIONS_SYSVAR_ID, is_signer=False, is_writable=False),
+            AccountMeta(event_authority, is_signer=False, is_writable=False),
+            AccountMeta(self.program_id, is_signer=False, is_writable=False),
+        ]
+
     def shield_sol(
         self,
         depositor: Pubkey,
         commitment: bytes,
         amount: int,
         denomination: int = 0,
+        encrypted_note: Optional[bytes] = None,
+        tree_epoch: int = 0,
+        next_index: int = 0,
     ) -> Instruction:
         """Build shield SOL instruction"""
         if len(commitment) != 32:
             raise ValueError("Commitment must be 32 bytes")
 
-        pool, _pool_bump = find_pool_pda(self.program_id, denomination)
-        vault, _vault_bump = find_vault_pda(self.program_id, pool)
+        accounts = self._shield_sol_accounts(
+            depositor, commitment, denomination, tree_epoch, next_index
+        )
 
-        accounts = [
-            AccountMeta(pool, is_signer=False, is_writable=True),
-            AccountMeta(vault, is_signer=False, is_writable=True),
-            AccountMeta(depositor, is_signer=True, is_writable=True),
-            AccountMeta(SYSTEM_PROGRAM_ID, is_signer=False, is_writable=False),
-        ]
+        # Instruction data: discriminator + commitment (32 bytes) + amount (u64)
+        # + optional encrypted note
+        data = (
+            self.SHIELD_SOL_DISC
+            + commitment
+            + struct.pack("<Q", amount)
+            + encode_encrypted_note(encrypted_note)
+        )
+
+        return Instruction(self.program_id, data, accounts)
+
+    def shield_sol_for(
+        self,
+        depositor: Pubkey,
+        commitment: bytes,
+        amount: int,
+        encrypted_note: bytes,
+        denomination: int = 0,
+        tree_epoch: int = 0,
+        next_index: int = 0,
+    ) -> Instruction:
+        """Build shield SOL instruction for a note owned by someone else
+
+        `commitment` is built from the recipient's spending key, and
+        `encrypted_note` carries the note's opening to the recipient.
+        """
+        if len(commitment) != 32:
+            raise ValueError("Commitment must be 32 bytes")
+        if not encrypted_note:
+            raise ValueError("Encrypted note is required")
+        if len(encrypted_note) > MAX_ENCRYPTED_NOTE_LEN:
+            raise ValueError(
+                f"Encrypted note must be at most {MAX_ENCRYPTED_NOTE_LEN} bytes"
+            )
+        if len(encrypted_note) < EPHEMERAL_KEY_LEN:
+            raise ValueError(
+                f"Encrypted note must be at least {EPHEMERAL_KEY_LEN} bytes"
+            )
+
+        accounts = self._shield_sol_accounts(
+            depositor, commitment, denomination, tree_epoch, next_index
+        )
 
         # Instruction data: discriminator + commitment (32 bytes) + amount (u64)
-        data = self.SHIELD_SOL_DISC + commitment + struct.pack("<Q", amount)
+        # + encrypted note (Vec<u8>)
+        data = (
+            self.SHIELD_SOL_FOR_DISC
+            + commitment
+            + struct.pack("<Q", amount)
+            + struct.pack("<I", len(encrypted_note))
+            + encrypted_note
+        )
 
         return Instruction(self.program_id, data, accounts)
 
@@ -122,6 +336,7 @@ class InstructionBuilder:
         vault_token_account: Pubkey,
         commitment: bytes,
         amount: int,
+        encrypted_note: Optional[bytes] = None,
     ) -> Instruction:
         """Build shield SPL token instruction"""
         if len(commitment) != 32:
@@ -140,7 +355,13 @@ class InstructionBuilder:
         ]
 
         # Instruction data: discriminator + commitment (32 bytes) + amount (u64)
-        data = self.SHIELD_DISC + commitment + struct.pack("<Q", amount)
+        # + optional encrypted note
+        data = (
+            self.SHIELD_DISC
+            + commitment
+            + struct.pack("<Q", amount)
+            + encode_encrypted_note(encrypted_note)
+        )
 
         return Instruction(self.program_id, data, accounts)
 
@@ -150,6 +371,7 @@ class InstructionBuilder:
         nullifier: bytes,
         new_commitment: bytes,
         proof: bytes,
+        encrypted_note: Optional[bytes] = None,
     ) -> Instruction:
         """Build private transfer instruction"""
         if len(nullifier) != 32:
@@ -170,13 +392,15 @@ class InstructionBuilder:
         ]
 
         # Instruction data: discriminator + nullifier + new_commitment + proof
-        # Proof is variable length, preceded by 4-byte length
+        # + optional encrypted note. Proof is variable length, preceded by
+        # 4-byte length
         data = (
             self.TRANSFER_DISC
             + nullifier
             + new_commitment
             + struct.pack("<I", len(proof))
             + proof
+            + encode_encrypted_note(encrypted_note)
         )
 
         return Instruction(self.program_id, data, accounts)
@@ -1,6 +1,8 @@
 //! Integration tests for Nyx Privacy Pool
 //!
 //! These tests use solana-program-test to simulate the Solana runtime.
+//! They submit mock MVP proofs, so the program must be built with the
+//! `mock-prover` feature.
 
 use anchor_lang::prelude::*;
 use anchor_lang::InstructionData;
@@ -28,6 +30,30 @@ fn find_vault_pda(pool: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[b"vault", pool.as_ref()], &program_id())
 }
 
+fn find_protocol_config_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"protocol_config"], &program_id())
+}
+
+fn find_commitment_chunk_pda(pool: &Pubkey, tree_epoch: u64, chunk_index: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"commitment_chunk",
+            pool.as_ref(),
+            &tree_epoch.to_le_bytes(),
+            &chunk_index.to_le_bytes(),
+        ],
+        &program_id(),
+    )
+}
+
+fn find_commitment_record_pda(pool: &Pubkey, commitment: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"commitment", pool.as_ref(), commitment], &program_id())
+}
+
+fn find_event_authority_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"__event_authority"], &program_id())
+}
+
 fn find_nullifier_pda(pool: &Pubkey, nullifier: &[u8; 32]) -> (Pubkey, u8) {
     Pubkey::find_program_address(
         &[b"nullifier", pool.as_ref(), nullifier],
@@ -54,6 +80,10 @@ fn create_initialize_ix(authority: &Pubkey) -> Instruction {
 }
 
 /// Create shield_sol instruction
+///
+/// The leaf goes to the first chunk of a fresh pool's tree. Pool stats and
+/// wSOL unwrapping are left out, passing the program ID as Anchor does for
+/// absent optional accounts.
 fn create_shield_sol_ix(
     depositor: &Pubkey,
     commitment: [u8; 32],
@@ -61,6 +91,10 @@ fn create_shield_sol_ix(
 ) -> Instruction {
     let (pool, _) = find_pool_pda();
     let (vault, _) = find_vault_pda(&pool);
+    let (protocol_config, _) = find_protocol_config_pda();
+    let (commitment_chunk, _) = find_commitment_chunk_pda(&pool, 0, 0);
+    let (commitment_record, _) = find_commitment_record_pda(&pool, &commitment);
+    let (event_authority, _) = find_event_authority_pda();
 
     // Anchor instruction discriminator for "shield_sol"
     let discriminator: [u8; 8] = [183, 4, 24, 123, 20, 45, 203, 91];
@@ -68,14 +102,31 @@ fn create_shield_sol_ix(
     let mut data = discriminator.to_vec();
     data.extend_from_slice(&commitment);
     data.extend_from_slice(&amount.to_le_bytes());
+    // No encrypted note
+    data.push(0);
 
     Instruction {
         program_id: program_id(),
         accounts: vec![
             AccountMeta::new(pool, false),
+            AccountMeta::new_readonly(protocol_config, false),
             AccountMeta::new(vault, false),
             AccountMeta::new(*depositor, true),
             AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(commitment_chunk, false),
+            // tree_archive, only needed once the tree is full
+            AccountMeta::new_readonly(program_id(), false),
+            AccountMeta::new(commitment_record, false),
+            // pool_stats, depositor_wsol_account, token_program,
+            // wsol_unwrap_account, wsol_mint
+            AccountMeta::new_readonly(program_id(), false),
+            AccountMeta::new_readonly(program_id(), false),
+            AccountMeta::new_readonly(program_id(), false),
+            AccountMeta::new_readonly(program_id(), false),
+            AccountMeta::new_readonly(program_id(), false),
+            AccountMeta::new_readonly(solana_sdk::sysvar::instructions::ID, false),
+            AccountMeta::new_readonly(event_authority, false),
+            AccountMeta::new_readonly(program_id(), false),
         ],
         data,
     }
@@ -100,6 +151,8 @@ fn create_transfer_ix(
     // Vec<u8> is serialized as: 4-byte length + data
     data.extend_from_slice(&(proof.len() as u32).to_le_bytes());
     data.extend_from_slice(&proof);
+    // No encrypted note
+    data.push(0);
 
     Instruction {
         program_id: program_id(),
@@ -187,9 +240,9 @@ mod tests {
         let ix = create_shield_sol_ix(&depositor.pubkey(), commitment, amount);
 
         assert_eq!(ix.program_id, program_id());
-        assert_eq!(ix.accounts.len(), 4);
-        // Data: 8 (discriminator) + 32 (commitment) + 8 (amount) = 48
-        assert_eq!(ix.data.len(), 48);
+        assert_eq!(ix.accounts.len(), 13);
+        // Data: 8 (discriminator) + 32 (commitment) + 8 (amount) + 1 (no note) = 49
+        assert_eq!(ix.data.len(), 49);
     }
 
     /// Test transfer instruction creation
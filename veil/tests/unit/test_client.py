@@ -106,9 +106,126 @@ class TestInstructionBuilder:
         ix = builder.shield_sol(depositor, commitment, amount)
 
         # Check instruction data format
-        assert len(ix.data) == 8 + 32 + 8  # discriminator + commitment + amount
+        # discriminator + commitment + amount + no encrypted note
+        assert len(ix.data) == 8 + 32 + 8 + 1
         assert ix.data[8:40] == commitment
         assert int.from_bytes(ix.data[40:48], 'little') == amount
+        assert ix.data[48] == 0
+
+    def test_shield_sol_encrypted_note(self):
+        """Test shield SOL appends the encrypted note as a Borsh option"""
+        from nyx_protocol.solana_client import InstructionBuilder
+        from solders.pubkey import Pubkey
+
+        program_id = Pubkey.from_string("Nyx1111111111111111111111111111111111111111")
+        builder = InstructionBuilder(program_id)
+
+        note = bytes([7] * 80)
+        ix = builder.shield_sol(
+            Pubkey.new_unique(), bytes([42] * 32), 1_000, encrypted_note=note
+        )
+
+        assert ix.data[48] == 1
+        assert int.from_bytes(ix.data[49:53], 'little') == len(note)
+        assert ix.data[53:] == note
+
+    def test_shield_sol_accounts(self):
+        """Test shield SOL passes the accounts of the program's ShieldSol"""
+        from nyx_protocol.solana_client import (
+            InstructionBuilder,
+            INSTRUCTIONS_SYSVAR_ID,
+            find_commitment_chunk_pda,
+            find_commitment_record_pda,
+            find_event_authority_pda,
+            find_pool_pda,
+            find_protocol_config_pda,
+        )
+        from solders.pubkey import Pubkey
+
+        program_id = Pubkey.from_string("Nyx1111111111111111111111111111111111111111")
+        builder = InstructionBuilder(program_id)
+
+        depositor = Pubkey.new_unique()
+        commitment = bytes([42] * 32)
+        pool, _ = find_pool_pda(program_id, 10**9)
+        ix = builder.shield_sol(
+            depositor, commitment, 10**9, 10**9, tree_epoch=1, next_index=300
+        )
+        keys = [meta.pubkey for meta in ix.accounts]
+
+        assert len(keys) == 16
+        assert keys[0] == pool
+        assert keys[1] == find_protocol_config_pda(program_id)[0]
+        assert keys[3] == depositor and ix.accounts[3].is_signer
+        # Leaf 300 of epoch 1 lands in that epoch's second chunk
+        assert keys[5] == find_commitment_chunk_pda(program_id, pool, 1, 256)[0]
+        assert keys[6] == program_id
+        assert keys[7] == find_commitment_record_pda(program_id, pool, commitment)[0]
+        assert keys[8:13] == [program_id] * 5
+        assert keys[13] == INSTRUCTIONS_SYSVAR_ID
+        assert keys[14:] == [find_event_authority_pda(program_id)[0], program_id]
+
+        # Shield-for takes the same accounts
+        ix = builder.shield_sol_for(
+            depositor, commitment, 10**9, bytes([7] * 80), 10**9, 1, 300
+        )
+        assert [meta.pubkey for meta in ix.accounts] == keys
+
+    def test_shield_sol_rolls_full_tree_over(self):
+        """Test shielding into a full tree passes its archive and the next tree's chunk"""
+        from nyx_protocol.solana_client import (
+            InstructionBuilder,
+            MAX_TREE_LEAVES,
+            find_commitment_chunk_pda,
+            find_pool_pda,
+            find_tree_epoch_pda,
+        )
+        from solders.pubkey import Pubkey
+
+        program_id = Pubkey.from_string("Nyx1111111111111111111111111111111111111111")
+        builder = InstructionBuilder(program_id)
+        pool, _ = find_pool_pda(program_id, 10**9)
+
+        ix = builder.shield_sol(
+            Pubkey.new_unique(), bytes([42] * 32), 10**9, 10**9,
+            tree_epoch=1, next_index=MAX_TREE_LEAVES,
+        )
+        assert ix.accounts[5].pubkey == find_commitment_chunk_pda(program_id, pool, 2, 0)[0]
+        assert ix.accounts[6].pubkey == find_tree_epoch_pda(program_id, pool, 1)[0]
+        assert ix.accounts[6].is_writable
+
+    def test_shield_sol_for_requires_encrypted_note(self):
+        """Test shield-for always carries the recipient's encrypted note"""
+        from nyx_protocol.solana_client import InstructionBuilder
+        from solders.pubkey import Pubkey
+
+        program_id = Pubkey.from_string("Nyx1111111111111111111111111111111111111111")
+        builder = InstructionBuilder(program_id)
+
+        depositor = Pubkey.new_unique()
+        commitment = bytes([42] * 32)
+        note = bytes([7] * 80)
+
+        ix = builder.shield_sol_for(depositor, commitment, 1_000, note)
+        assert int.from_bytes(ix.data[48:52], 'little') == len(note)
+        assert ix.data[52:] == note
+
+        with pytest.raises(ValueError):
+            builder.shield_sol_for(depositor, commitment, 1_000, b"")
+
+    def test_encrypted_note_carries_ephemeral_key(self):
+        """Test notes too short to start with an ephemeral key are rejected"""
+        from nyx_protocol.solana_client import InstructionBuilder, encode_encrypted_note
+        from solders.pubkey import Pubkey
+
+        program_id = Pubkey.from_string("Nyx1111111111111111111111111111111111111111")
+        builder = InstructionBuilder(program_id)
+
+        assert encode_encrypted_note(bytes(32))[5:] == bytes(32)
+        with pytest.raises(ValueError):
+            encode_encrypted_note(bytes(31))
+        with pytest.raises(ValueError):
+            builder.shield_sol_for(Pubkey.new_unique(), bytes([42] * 32), 1_000, bytes(31))
 
     def test_transfer_instruction_data(self):
         """Test transfer instruction data format"""
@@ -127,7 +244,8 @@ class TestInstructionBuilder:
 
         # Check instruction data format
         # 8 (disc) + 32 (nullifier) + 32 (commitment) + 4 (len) + 96 (proof)
-        assert len(ix.data) == 8 + 32 + 32 + 4 + 96
+        # + 1 (no encrypted note)
+        assert len(ix.data) == 8 + 32 + 32 + 4 + 96 + 1
         assert ix.data[8:40] == nullifier
         assert ix.data[40:72] == new_commitment
 